@@ -9,7 +9,12 @@ use crate::{
 };
 use inflector::Inflector;
 use serde_json::Value;
-use solana_sdk::{instruction::InstructionError, pubkey::Pubkey, stake, system_program, sysvar};
+use solana_sdk::{
+    instruction::InstructionError,
+    pubkey::Pubkey,
+    spl_token_ids::{spl_token_ids, SplTokenIds},
+    stake, system_program, sysvar,
+};
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -86,9 +91,27 @@ pub fn parse_account_data(
     data: &[u8],
     additional_data: Option<AccountAdditionalData>,
 ) -> Result<ParsedAccount, ParseAccountError> {
-    let program_name = PARSABLE_PROGRAM_IDS
-        .get(program_id)
-        .ok_or(ParseAccountError::ProgramNotParsable)?;
+    parse_account_data_with_spl_token_ids(
+        pubkey,
+        program_id,
+        data,
+        additional_data,
+        spl_token_ids(),
+    )
+}
+
+fn parse_account_data_with_spl_token_ids(
+    pubkey: &Pubkey,
+    program_id: &Pubkey,
+    data: &[u8],
+    additional_data: Option<AccountAdditionalData>,
+    spl_token_ids: &SplTokenIds,
+) -> Result<ParsedAccount, ParseAccountError> {
+    let program_name = match PARSABLE_PROGRAM_IDS.get(program_id) {
+        Some(program_name) => program_name,
+        None if spl_token_ids.contains(program_id) => &ParsableAccount::SplToken,
+        None => return Err(ParseAccountError::ProgramNotParsable),
+    };
     let additional_data = additional_data.unwrap_or_default();
     let parsed_json = match program_name {
         ParsableAccount::BpfUpgradeableLoader => {
@@ -118,6 +141,7 @@ mod test {
         State,
     };
     use solana_vote_program::vote_state::{VoteState, VoteStateVersions};
+    use spl_token_v2_0::{solana_program::program_pack::Pack, state::Mint};
 
     #[test]
     fn test_parse_account_data() {
@@ -151,5 +175,29 @@ mod test {
         .unwrap();
         assert_eq!(parsed.program, "nonce".to_string());
         assert_eq!(parsed.space, State::size() as u64);
+
+        let token_program = solana_sdk::pubkey::new_rand();
+        let mut mint_account_data = vec![0; Mint::get_packed_len()];
+        Mint::pack(
+            Mint {
+                is_initialized: true,
+                ..Mint::default()
+            },
+            &mut mint_account_data,
+        )
+        .unwrap();
+        assert!(
+            parse_account_data(&account_pubkey, &token_program, &mint_account_data, None).is_err()
+        );
+        let parsed = parse_account_data_with_spl_token_ids(
+            &account_pubkey,
+            &token_program,
+            &mint_account_data,
+            None,
+            &SplTokenIds::new(vec![token_program]),
+        )
+        .unwrap();
+        assert_eq!(parsed.program, "spl-token".to_string());
+        assert_eq!(parsed.space, Mint::get_packed_len() as u64);
     }
 }
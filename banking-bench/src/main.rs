@@ -4,7 +4,7 @@ use crossbeam_channel::unbounded;
 use log::*;
 use rand::{thread_rng, Rng};
 use rayon::prelude::*;
-use solana_core::{banking_stage::BankingStage, cost_model::CostModel, cost_tracker::CostTracker};
+use solana_core::{banking_stage::BankingStage, packet_forwarder::ForwardingConfig};
 use solana_gossip::{cluster_info::ClusterInfo, cluster_info::Node};
 use solana_ledger::{
     blockstore::Blockstore,
@@ -13,12 +13,17 @@ use solana_ledger::{
 };
 use solana_measure::measure::Measure;
 use solana_perf::packet::to_packets_chunked;
-use solana_poh::poh_recorder::{create_test_recorder, PohRecorder, WorkingBankEntry};
+use solana_poh::poh_recorder::{
+    create_test_recorder, EntryBatchConfig, PohRecorder, WorkingBankEntry,
+};
 use solana_runtime::{
     accounts_background_service::AbsRequestSender, bank::Bank, bank_forks::BankForks,
+    cost_model::CostModel, cost_tracker::CostTracker,
 };
 use solana_sdk::{
+    clock::Slot,
     hash::Hash,
+    pubkey::Pubkey,
     signature::Keypair,
     signature::Signature,
     system_transaction,
@@ -26,23 +31,38 @@ use solana_sdk::{
     transaction::Transaction,
 };
 use std::{
+    collections::HashSet,
+    str::FromStr,
     sync::{atomic::Ordering, mpsc::Receiver, Arc, Mutex, RwLock},
     thread::sleep,
     time::{Duration, Instant},
 };
 
+#[derive(Default)]
+struct EntryStats {
+    slots: HashSet<Slot>,
+    entries: usize,
+    transactions: usize,
+}
+
 fn check_txs(
     receiver: &Arc<Receiver<WorkingBankEntry>>,
     ref_tx_count: usize,
     poh_recorder: &Arc<Mutex<PohRecorder>>,
+    entry_stats: &mut EntryStats,
 ) -> bool {
     let mut total = 0;
     let now = Instant::now();
     let mut no_bank = false;
     loop {
-        if let Ok((_bank, (entry, _tick_height))) = receiver.recv_timeout(Duration::from_millis(10))
+        if let Ok((bank, (entry, _tick_height))) = receiver.recv_timeout(Duration::from_millis(10))
         {
             total += entry.transactions.len();
+            if !entry.is_tick() {
+                entry_stats.slots.insert(bank.slot());
+                entry_stats.entries += 1;
+                entry_stats.transactions += entry.transactions.len();
+            }
         }
         if total >= ref_tx_count {
             break;
@@ -62,23 +82,55 @@ fn check_txs(
     no_bank
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum WriteLockContention {
+    /// No transactions write the same account
+    None,
+    /// Transactions in the same packet batch write the same account
+    SameBatchOnly,
+    /// All transactions write the same account
+    Full,
+}
+
+impl FromStr for WriteLockContention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "same-batch-only" => Ok(Self::SameBatchOnly),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("unknown write lock contention: {}", s)),
+        }
+    }
+}
+
 fn make_accounts_txs(
     total_num_transactions: usize,
+    packets_per_batch: usize,
     hash: Hash,
     same_payer: bool,
+    contention: WriteLockContention,
 ) -> Vec<Transaction> {
     let to_pubkey = solana_sdk::pubkey::new_rand();
     let payer_key = Keypair::new();
     let dummy = system_transaction::transfer(&payer_key, &to_pubkey, 1, hash);
+    let batch_to_pubkeys: Vec<Pubkey> = (0..total_num_transactions / packets_per_batch + 1)
+        .map(|_| solana_sdk::pubkey::new_rand())
+        .collect();
     (0..total_num_transactions)
         .into_par_iter()
-        .map(|_| {
+        .map(|i| {
             let mut new = dummy.clone();
             let sig: Vec<u8> = (0..64).map(|_| thread_rng().gen::<u8>()).collect();
             if !same_payer {
                 new.message.account_keys[0] = solana_sdk::pubkey::new_rand();
             }
-            new.message.account_keys[1] = solana_sdk::pubkey::new_rand();
+            new.message.account_keys[1] = match contention {
+                WriteLockContention::None => solana_sdk::pubkey::new_rand(),
+                WriteLockContention::SameBatchOnly => batch_to_pubkeys[i / packets_per_batch],
+                WriteLockContention::Full => to_pubkey,
+            };
             new.signatures = vec![Signature::new(&sig[0..64])];
             new
         })
@@ -146,6 +198,31 @@ fn main() {
                 .takes_value(true)
                 .help("Number of iterations"),
         )
+        .arg(
+            Arg::with_name("write_lock_contention")
+                .long("write-lock-contention")
+                .takes_value(true)
+                .possible_values(&["none", "same-batch-only", "full"])
+                .help("Accounts that transactions write to more than once"),
+        )
+        .arg(
+            Arg::with_name("central_scheduler")
+                .long("central-scheduler")
+                .takes_value(false)
+                .help("Hand banking threads non-conflicting batches from a central scheduler"),
+        )
+        .arg(
+            Arg::with_name("entry_target_transactions")
+                .long("entry-target-transactions")
+                .takes_value(true)
+                .help("Accumulate transaction batches into PoH entries of this many transactions"),
+        )
+        .arg(
+            Arg::with_name("entry_max_delay_us")
+                .long("entry-max-delay-us")
+                .takes_value(true)
+                .help("Longest a transaction batch waits for its PoH entry to fill up"),
+        )
         .get_matches();
 
     let num_threads =
@@ -154,6 +231,8 @@ fn main() {
     let num_chunks = value_t!(matches, "num_chunks", usize).unwrap_or(16);
     let packets_per_chunk = value_t!(matches, "packets_per_chunk", usize).unwrap_or(192);
     let iterations = value_t!(matches, "iterations", usize).unwrap_or(1000);
+    let write_lock_contention = value_t!(matches, "write_lock_contention", WriteLockContention)
+        .unwrap_or(WriteLockContention::None);
 
     let total_num_transactions = num_chunks * num_threads * packets_per_chunk;
     let mint_total = 1_000_000_000_000;
@@ -170,11 +249,19 @@ fn main() {
     let mut bank_forks = BankForks::new(bank0);
     let mut bank = bank_forks.working_bank();
 
-    info!("threads: {} txs: {}", num_threads, total_num_transactions);
+    info!(
+        "threads: {} txs: {} contention: {:?}",
+        num_threads, total_num_transactions, write_lock_contention
+    );
 
     let same_payer = matches.is_present("same_payer");
-    let mut transactions =
-        make_accounts_txs(total_num_transactions, genesis_config.hash(), same_payer);
+    let mut transactions = make_accounts_txs(
+        total_num_transactions,
+        packets_per_chunk,
+        genesis_config.hash(),
+        same_payer,
+        write_lock_contention,
+    );
 
     // fund all the accounts
     transactions.iter().for_each(|tx| {
@@ -200,12 +287,13 @@ fn main() {
         });
         bank.clear_signatures();
         //sanity check, make sure all the transactions can execute in parallel
-
-        let res = bank.process_transactions(transactions.iter());
-        for r in res {
-            assert!(r.is_ok(), "sanity parallel execution error: {:?}", r);
+        if write_lock_contention == WriteLockContention::None {
+            let res = bank.process_transactions(transactions.iter());
+            for r in res {
+                assert!(r.is_ok(), "sanity parallel execution error: {:?}", r);
+            }
+            bank.clear_signatures();
         }
-        bank.clear_signatures();
     }
 
     let mut verified: Vec<_> = to_packets_chunked(&transactions, packets_per_chunk);
@@ -216,19 +304,52 @@ fn main() {
         );
         let (exit, poh_recorder, poh_service, signal_receiver) =
             create_test_recorder(&bank, &blockstore, None);
+        let default_entry_batch_config = EntryBatchConfig::default();
+        poh_recorder
+            .lock()
+            .unwrap()
+            .set_entry_batch_config(EntryBatchConfig {
+                target_transactions: value_t!(matches, "entry_target_transactions", usize)
+                    .unwrap_or(default_entry_batch_config.target_transactions),
+                max_delay: value_t!(matches, "entry_max_delay_us", u64)
+                    .map(Duration::from_micros)
+                    .unwrap_or(default_entry_batch_config.max_delay),
+            });
         let cluster_info = ClusterInfo::new_with_invalid_keypair(Node::new_localhost().info);
         let cluster_info = Arc::new(cluster_info);
-        let banking_stage = BankingStage::new(
-            &cluster_info,
-            &poh_recorder,
-            verified_receiver,
-            vote_receiver,
-            None,
-            replay_vote_sender,
-            Arc::new(RwLock::new(CostTracker::new(Arc::new(RwLock::new(
-                CostModel::default(),
-            ))))),
-        );
+        let cost_tracker = Arc::new(RwLock::new(CostTracker::new(Arc::new(RwLock::new(
+            CostModel::default(),
+        )))));
+        let forwarding_bank_forks = Arc::new(RwLock::new(BankForks::new_from_banks(
+            &[bank.clone()],
+            bank.slot(),
+        )));
+        let banking_stage = if matches.is_present("central_scheduler") {
+            BankingStage::new_with_scheduler(
+                &cluster_info,
+                &poh_recorder,
+                verified_receiver,
+                vote_receiver,
+                BankingStage::num_threads(),
+                None,
+                replay_vote_sender,
+                cost_tracker,
+                &forwarding_bank_forks,
+                ForwardingConfig::default(),
+            )
+        } else {
+            BankingStage::new(
+                &cluster_info,
+                &poh_recorder,
+                verified_receiver,
+                vote_receiver,
+                None,
+                replay_vote_sender,
+                cost_tracker,
+                &forwarding_bank_forks,
+                ForwardingConfig::default(),
+            )
+        };
         poh_recorder.lock().unwrap().set_bank(&bank);
 
         let chunk_len = verified.len() / num_chunks;
@@ -250,6 +371,7 @@ fn main() {
             num_threads,
         };
         let mut total_sent = 0;
+        let mut entry_stats = EntryStats::default();
         for _ in 0..iterations {
             let now = Instant::now();
             let mut sent = 0;
@@ -295,6 +417,7 @@ fn main() {
                 &signal_receiver,
                 total_num_transactions / num_chunks,
                 &poh_recorder,
+                &mut entry_stats,
             ) {
                 debug!(
                     "resetting bank {} tx count: {} txs_proc: {}",
@@ -378,6 +501,14 @@ fn main() {
             "{{'name': 'banking_bench_success_tx_total', 'median': '{:.2}'}}",
             (1000.0 * 1000.0 * (txs_processed - base_tx_count) as f64) / (total_us as f64),
         );
+        eprintln!(
+            "{{'name': 'banking_bench_entries_per_slot', 'median': '{:.2}'}}",
+            entry_stats.entries as f64 / entry_stats.slots.len().max(1) as f64,
+        );
+        eprintln!(
+            "{{'name': 'banking_bench_transactions_per_entry', 'median': '{:.2}'}}",
+            entry_stats.transactions as f64 / entry_stats.entries.max(1) as f64,
+        );
 
         drop(verified_sender);
         drop(vote_sender);
@@ -1,4 +1,3 @@
-use crate::send_transaction_service::{SendTransactionService, TransactionInfo};
 use bincode::{deserialize, serialize};
 use futures::{future, prelude::stream::StreamExt};
 use solana_banks_interface::{
@@ -15,6 +14,10 @@ use solana_sdk::{
     signature::Signature,
     transaction::{self, Transaction},
 };
+use solana_send_transaction_service::{
+    send_transaction_service::{self, SendTransactionService, TransactionInfo},
+    tpu_info::NullTpuInfo,
+};
 use std::{
     io,
     net::{Ipv4Addr, SocketAddr},
@@ -153,8 +156,13 @@ impl Banks for BanksServer {
             .get_blockhash_last_valid_slot(blockhash)
             .unwrap();
         let signature = transaction.signatures.get(0).cloned().unwrap_or_default();
-        let info =
-            TransactionInfo::new(signature, serialize(&transaction).unwrap(), last_valid_slot);
+        let info = TransactionInfo::new(
+            signature,
+            serialize(&transaction).unwrap(),
+            last_valid_slot,
+            None,
+            None,
+        );
         self.transaction_sender.send(info).unwrap();
     }
 
@@ -231,8 +239,13 @@ impl Banks for BanksServer {
             .get_blockhash_last_valid_slot(blockhash)
             .unwrap();
         let signature = transaction.signatures.get(0).cloned().unwrap_or_default();
-        let info =
-            TransactionInfo::new(signature, serialize(&transaction).unwrap(), last_valid_slot);
+        let info = TransactionInfo::new(
+            signature,
+            serialize(&transaction).unwrap(),
+            last_valid_slot,
+            None,
+            None,
+        );
         self.transaction_sender.send(info).unwrap();
         self.poll_signature_status(&signature, blockhash, last_valid_slot, commitment)
             .await
@@ -284,7 +297,16 @@ pub async fn start_tcp_server(
         .map(move |chan| {
             let (sender, receiver) = channel();
 
-            SendTransactionService::new(tpu_addr, &bank_forks, receiver);
+            SendTransactionService::new_with_config(
+                tpu_addr,
+                &bank_forks,
+                None::<NullTpuInfo>,
+                receiver,
+                send_transaction_service::Config {
+                    retry_rate_ms: 5_000,
+                    ..send_transaction_service::Config::default()
+                },
+            );
 
             let server =
                 BanksServer::new(bank_forks.clone(), block_commitment_cache.clone(), sender);
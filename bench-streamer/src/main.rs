@@ -1,7 +1,7 @@
 #![allow(clippy::integer_arithmetic)]
 use clap::{crate_description, crate_name, App, Arg};
 use solana_streamer::packet::{Packet, Packets, PacketsRecycler, PACKET_DATA_SIZE};
-use solana_streamer::streamer::{receiver, PacketReceiver};
+use solana_streamer::streamer::{receiver, PacketReceiver, PacketReceiverConfig};
 use std::cmp::max;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
@@ -61,11 +61,22 @@ fn main() -> Result<()> {
                 .takes_value(true)
                 .help("Use NUM receive sockets"),
         )
+        .arg(
+            Arg::with_name("receiver-options")
+                .long("receiver-options")
+                .value_name("OPTIONS")
+                .takes_value(true)
+                .help("Receive thread tuning, e.g. cores=2-3,busy_poll_us=50,batch_size=256,timestamps"),
+        )
         .get_matches();
 
     if let Some(n) = matches.value_of("num-recv-sockets") {
         num_sockets = max(num_sockets, n.to_string().parse().expect("integer"));
     }
+    let receiver_config: PacketReceiverConfig = matches
+        .value_of("receiver-options")
+        .map(|options| options.parse().expect("receiver options"))
+        .unwrap_or_default();
 
     let mut port = 0;
     let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
@@ -93,6 +104,7 @@ fn main() -> Result<()> {
             "bench-streamer-test",
             1,
             true,
+            receiver_config.clone(),
         ));
     }
 
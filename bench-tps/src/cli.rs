@@ -29,6 +29,7 @@ pub struct Config {
     pub num_lamports_per_account: u64,
     pub target_slots_per_epoch: u64,
     pub target_node: Option<Pubkey>,
+    pub use_quic: bool,
 }
 
 impl Default for Config {
@@ -52,6 +53,7 @@ impl Default for Config {
             num_lamports_per_account: NUM_LAMPORTS_PER_ACCOUNT_DEFAULT,
             target_slots_per_epoch: 0,
             target_node: None,
+            use_quic: false,
         }
     }
 }
@@ -125,6 +127,12 @@ pub fn build_args<'a, 'b>(version: &'b str) -> App<'a, 'b> {
                 .value_name("PUBKEY")
                 .help("Specify an exact node to send transactions to."),
         )
+        .arg(
+            Arg::with_name("tpu_use_quic")
+                .long("tpu-use-quic")
+                .takes_value(false)
+                .help("Send transactions to the QUIC TPU port, authenticated with the identity keypair"),
+        )
         .arg(
             Arg::with_name("tx_count")
                 .long("tx_count")
@@ -273,6 +281,7 @@ pub fn extract_args(matches: &ArgMatches) -> Config {
     args.target_node = matches
         .value_of("target_node")
         .map(|target_str| target_str.parse().unwrap());
+    args.use_quic = matches.is_present("tpu_use_quic");
 
     if let Some(v) = matches.value_of("num_lamports_per_account") {
         args.num_lamports_per_account = v.to_string().parse().expect("can't parse lamports");
@@ -2,6 +2,7 @@
 use log::*;
 use solana_bench_tps::bench::{do_bench_tps, generate_and_fund_keypairs, generate_keypairs};
 use solana_bench_tps::cli;
+use solana_client::connection_cache;
 use solana_genesis::Base64Account;
 use solana_gossip::gossip_service::{discover_cluster, get_client, get_multi_client};
 use solana_sdk::fee_calculator::FeeRateGovernor;
@@ -33,6 +34,7 @@ fn main() {
         multi_client,
         num_lamports_per_account,
         target_node,
+        use_quic,
         ..
     } = &cli_config;
 
@@ -67,6 +69,11 @@ fn main() {
         return;
     }
 
+    if *use_quic {
+        connection_cache::set_use_quic(true);
+        connection_cache::set_identity(id);
+    }
+
     info!("Connecting to the cluster");
     let nodes = discover_cluster(entrypoint_addr, *num_nodes).unwrap_or_else(|err| {
         eprintln!("Failed to discover {} nodes: {:?}", num_nodes, err);
@@ -1982,6 +1982,7 @@ pub fn process_transaction_history(
             until,
             limit: Some(limit),
             commitment: Some(CommitmentConfig::confirmed()),
+            ..GetConfirmedSignaturesForAddress2Config::default()
         },
     )?;
 
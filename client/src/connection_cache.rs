@@ -0,0 +1,137 @@
+//! The `connection_cache` module keeps QUIC connections to recently used TPUs open, so that
+//! clients sending many transactions to the same leaders don't pay for a handshake per send.
+
+use {
+    crate::quic_client::QuicTpuConnection,
+    lazy_static::lazy_static,
+    solana_sdk::{
+        quic::QUIC_PORT_OFFSET, signature::Keypair, transport::Result as TransportResult,
+    },
+    std::{
+        collections::{HashMap, VecDeque},
+        net::{SocketAddr, UdpSocket},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+    },
+};
+
+// Should be large enough to hold the connections to every leader in the fanout
+const MAX_CONNECTIONS: usize = 64;
+
+static USE_QUIC: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref CONNECTION_MAP: Mutex<ConnectionMap> = Mutex::new(ConnectionMap::default());
+}
+
+#[derive(Default)]
+struct ConnectionMap {
+    map: HashMap<SocketAddr, Arc<QuicTpuConnection>>,
+    // Insertion order, oldest first
+    order: VecDeque<SocketAddr>,
+    identity: Option<Arc<Keypair>>,
+}
+
+impl ConnectionMap {
+    fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+}
+
+/// Makes `TpuClient` and `ThinClient` send transactions over QUIC instead of UDP
+pub fn set_use_quic(use_quic: bool) {
+    USE_QUIC.store(use_quic, Ordering::Relaxed);
+}
+
+pub fn use_quic() -> bool {
+    USE_QUIC.load(Ordering::Relaxed)
+}
+
+/// Sets the keypair QUIC connections authenticate with. A new keypair is generated if none is
+/// set. Connections opened with a previous identity are dropped
+pub fn set_identity(keypair: &Keypair) {
+    let keypair = Keypair::from_bytes(&keypair.to_bytes()).expect("valid keypair");
+    let mut map = CONNECTION_MAP.lock().unwrap();
+    map.identity = Some(Arc::new(keypair));
+    map.clear();
+}
+
+fn quic_addr(tpu_addr: &SocketAddr) -> SocketAddr {
+    SocketAddr::new(
+        tpu_addr.ip(),
+        tpu_addr.port().saturating_add(QUIC_PORT_OFFSET),
+    )
+}
+
+/// Returns the cached connection to the QUIC port next to the UDP `tpu_addr`, opening a new
+/// one if needed. The map is not locked while the connection is created, so that senders to
+/// other TPUs don't wait on it
+pub fn get_connection(tpu_addr: &SocketAddr) -> TransportResult<Arc<QuicTpuConnection>> {
+    let addr = quic_addr(tpu_addr);
+    let identity = {
+        let mut map = CONNECTION_MAP.lock().unwrap();
+        if let Some(connection) = map.map.get(&addr) {
+            return Ok(connection.clone());
+        }
+        map.identity
+            .get_or_insert_with(|| Arc::new(Keypair::new()))
+            .clone()
+    };
+
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let connection = Arc::new(QuicTpuConnection::new(socket, addr, &identity)?);
+
+    let mut map = CONNECTION_MAP.lock().unwrap();
+    // Another sender may have connected in the meantime, or the identity may have changed
+    if let Some(connection) = map.map.get(&addr) {
+        return Ok(connection.clone());
+    }
+    if !matches!(&map.identity, Some(current) if Arc::ptr_eq(current, &identity)) {
+        return Ok(connection);
+    }
+    while map.map.len() >= MAX_CONNECTIONS {
+        match map.order.pop_front() {
+            Some(oldest) => {
+                map.map.remove(&oldest);
+            }
+            None => break,
+        }
+    }
+    map.map.insert(addr, connection.clone());
+    map.order.push_back(addr);
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::net::Ipv4Addr};
+
+    #[test]
+    fn test_connection_cache() {
+        let tpu_addr = |port| SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+        let first = get_connection(&tpu_addr(1000)).unwrap();
+        assert_eq!(first.tpu_addr().port(), 1000 + QUIC_PORT_OFFSET);
+        assert!(Arc::ptr_eq(
+            &first,
+            &get_connection(&tpu_addr(1000)).unwrap()
+        ));
+
+        // The oldest connection is evicted once the cache is full
+        for port in 1..MAX_CONNECTIONS as u16 {
+            get_connection(&tpu_addr(1000 + port * 10)).unwrap();
+        }
+        assert!(Arc::ptr_eq(
+            &first,
+            &get_connection(&tpu_addr(1000)).unwrap()
+        ));
+        get_connection(&tpu_addr(2000)).unwrap();
+        assert!(!Arc::ptr_eq(
+            &first,
+            &get_connection(&tpu_addr(1000)).unwrap()
+        ));
+        assert!(CONNECTION_MAP.lock().unwrap().map.len() <= MAX_CONNECTIONS);
+    }
+}
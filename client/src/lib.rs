@@ -4,11 +4,13 @@ extern crate serde_derive;
 
 pub mod blockhash_query;
 pub mod client_error;
+pub mod connection_cache;
 pub mod http_sender;
 pub mod mock_sender;
 pub mod nonce_utils;
 pub mod perf_utils;
 pub mod pubsub_client;
+pub mod quic_client;
 pub mod rpc_cache;
 pub mod rpc_client;
 pub mod rpc_config;
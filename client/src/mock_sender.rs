@@ -5,7 +5,8 @@ use {
         client_error::Result,
         rpc_request::RpcRequest,
         rpc_response::{
-            Response, RpcResponseContext, RpcSimulateTransactionResult, RpcVersionInfo,
+            Response, RpcFees, RpcResponseContext, RpcSimulateBundleResult,
+            RpcSimulateTransactionResult, RpcVersionInfo,
         },
         rpc_sender::RpcSender,
     },
@@ -113,6 +114,24 @@ impl RpcSender for MockSender {
                     value,
                 })?
             }
+            RpcRequest::GetFees => serde_json::to_value(Response {
+                context: RpcResponseContext { slot: 1 },
+                value: RpcFees {
+                    blockhash: PUBKEY.to_string(),
+                    fee_calculator: FeeCalculator::default(),
+                    last_valid_slot: 42,
+                    last_valid_block_height: 42,
+                },
+            })?,
+            RpcRequest::GetBlockHeight => {
+                // Past the last valid block height of the blockhash returned by GetFees
+                let block_height = if self.url == "blockhash_expired" {
+                    43
+                } else {
+                    1
+                };
+                Value::Number(Number::from(block_height))
+            }
             RpcRequest::GetFeeRateGovernor => serde_json::to_value(Response {
                 context: RpcResponseContext { slot: 1 },
                 value: serde_json::to_value(FeeRateGovernor::default()).unwrap(),
@@ -128,7 +147,7 @@ impl RpcSender for MockSender {
                 } else {
                     Ok(())
                 };
-                let status = if self.url == "sig_not_found" {
+                let status = if self.url == "sig_not_found" || self.url == "blockhash_expired" {
                     None
                 } else {
                     let err = status.clone().err();
@@ -175,6 +194,17 @@ impl RpcSender for MockSender {
                     units_consumed: None,
                 },
             })?,
+            RpcRequest::SimulateBundle => serde_json::to_value(Response {
+                context: RpcResponseContext { slot: 1 },
+                value: RpcSimulateBundleResult {
+                    transaction_results: vec![RpcSimulateTransactionResult {
+                        err: None,
+                        logs: None,
+                        accounts: None,
+                        units_consumed: None,
+                    }],
+                },
+            })?,
             RpcRequest::GetMinimumBalanceForRentExemption => Value::Number(Number::from(20)),
             RpcRequest::GetVersion => {
                 let version = Version::default();
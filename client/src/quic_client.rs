@@ -0,0 +1,251 @@
+//! The `quic_client` module sends wire transactions to a TPU over QUIC, one transaction per
+//! unidirectional stream, authenticating with a certificate derived from the client identity.
+
+use {
+    futures_util::future::join_all,
+    lazy_static::lazy_static,
+    log::*,
+    quinn::{ClientConfig, Connection, Endpoint, EndpointConfig, NewConnection},
+    solana_sdk::{
+        quic::{QUIC_KEEP_ALIVE_MS, QUIC_MAX_TIMEOUT_MS},
+        signature::Keypair,
+        transport::{Result as TransportResult, TransportError},
+    },
+    solana_streamer::tls_certificates::new_self_signed_tls_certificate,
+    std::{
+        net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+        sync::Arc,
+        time::{Duration, SystemTime},
+    },
+    tokio::{runtime::Runtime, sync::Mutex},
+};
+
+lazy_static! {
+    static ref RUNTIME: Runtime = tokio::runtime::Builder::new_multi_thread()
+        .thread_name("quic-client")
+        .enable_all()
+        .build()
+        .unwrap();
+}
+
+/// The server presents a self-signed certificate; its identity is not checked
+struct SkipServerVerification;
+
+impl rustls::client::ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::ServerCertVerified::assertion())
+    }
+}
+
+fn quic_error<E: std::fmt::Debug>(err: E) -> TransportError {
+    TransportError::Custom(format!("quic error: {:?}", err))
+}
+
+pub struct QuicTpuConnection {
+    client: Arc<QuicClient>,
+}
+
+impl QuicTpuConnection {
+    /// Creates a connection to the QUIC TPU port at `tpu_addr`. Nothing is sent until the
+    /// first transaction, and the connection is reestablished when it times out
+    pub fn new(
+        client_socket: UdpSocket,
+        tpu_addr: SocketAddr,
+        identity: &Keypair,
+    ) -> TransportResult<Self> {
+        let client = QuicClient::new(client_socket, tpu_addr, identity)?;
+        Ok(Self {
+            client: Arc::new(client),
+        })
+    }
+
+    pub fn tpu_addr(&self) -> &SocketAddr {
+        &self.client.addr
+    }
+
+    pub fn send_wire_transaction(&self, wire_transaction: &[u8]) -> TransportResult<()> {
+        RUNTIME.block_on(self.client.send_buffer(wire_transaction))
+    }
+
+    /// Queues `wire_transaction` to be sent without waiting for the stream to finish, so that a
+    /// slow or unreachable TPU doesn't hold up the caller
+    pub fn send_wire_transaction_async(&self, wire_transaction: Vec<u8>) {
+        let client = self.client.clone();
+        RUNTIME.spawn(async move {
+            if let Err(err) = client.send_buffer(&wire_transaction).await {
+                debug!("quic send to {} failed: {:?}", client.addr, err);
+            }
+        });
+    }
+
+    pub fn send_wire_transaction_batch(
+        &self,
+        wire_transactions: &[Vec<u8>],
+    ) -> TransportResult<()> {
+        RUNTIME.block_on(self.client.send_batch(wire_transactions))
+    }
+}
+
+struct QuicClient {
+    endpoint: Endpoint,
+    connection: Mutex<Option<Connection>>,
+    addr: SocketAddr,
+}
+
+impl QuicClient {
+    fn new(
+        client_socket: UdpSocket,
+        addr: SocketAddr,
+        identity: &Keypair,
+    ) -> TransportResult<Self> {
+        let (cert, priv_key) =
+            new_self_signed_tls_certificate(identity, IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+                .map_err(quic_error)?;
+        let crypto = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(SkipServerVerification))
+            .with_single_cert(vec![cert], priv_key)
+            .map_err(quic_error)?;
+
+        let mut config = ClientConfig::new(Arc::new(crypto));
+        let transport_config = Arc::get_mut(&mut config.transport).unwrap();
+        transport_config.max_idle_timeout(Some(quinn::VarInt::from(QUIC_MAX_TIMEOUT_MS).into()));
+        transport_config.keep_alive_interval(Some(Duration::from_millis(QUIC_KEEP_ALIVE_MS)));
+
+        let (mut endpoint, _) = {
+            let _guard = RUNTIME.enter();
+            Endpoint::new(EndpointConfig::default(), None, client_socket)?
+        };
+        endpoint.set_default_client_config(config);
+
+        Ok(Self {
+            endpoint,
+            connection: Mutex::new(None),
+            addr,
+        })
+    }
+
+    async fn get_connection(&self) -> TransportResult<Connection> {
+        let mut cached = self.connection.lock().await;
+        if let Some(connection) = cached.as_ref() {
+            return Ok(connection.clone());
+        }
+        let NewConnection { connection, .. } = self
+            .endpoint
+            .connect(self.addr, "connect")
+            .map_err(quic_error)?
+            .await
+            .map_err(quic_error)?;
+        *cached = Some(connection.clone());
+        Ok(connection)
+    }
+
+    async fn reconnect(&self) -> TransportResult<Connection> {
+        self.connection.lock().await.take();
+        self.get_connection().await
+    }
+
+    async fn send_on_connection(connection: &Connection, data: &[u8]) -> TransportResult<()> {
+        let mut send_stream = connection.open_uni().await.map_err(quic_error)?;
+        send_stream.write_all(data).await.map_err(quic_error)?;
+        send_stream.finish().await.map_err(quic_error)?;
+        Ok(())
+    }
+
+    async fn send_buffer(&self, data: &[u8]) -> TransportResult<()> {
+        let connection = self.get_connection().await?;
+        if Self::send_on_connection(&connection, data).await.is_ok() {
+            return Ok(());
+        }
+        // The cached connection may have been closed by the server; retry once on a new one
+        let connection = self.reconnect().await?;
+        Self::send_on_connection(&connection, data).await
+    }
+
+    async fn send_batch(&self, buffers: &[Vec<u8>]) -> TransportResult<()> {
+        let (first, rest) = match buffers.split_first() {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+        // The first transaction (re)establishes the connection the rest are sent on
+        self.send_buffer(first).await?;
+        let connection = self.get_connection().await?;
+        join_all(
+            rest.iter()
+                .map(|buffer| Self::send_on_connection(&connection, buffer)),
+        )
+        .await
+        .into_iter()
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::packet::PACKET_DATA_SIZE,
+        solana_streamer::{quic::spawn_server, streamer::StakedNodes},
+        std::sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc::channel,
+            RwLock,
+        },
+    };
+
+    #[test]
+    fn test_quic_tpu_connection() {
+        let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let exit = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = channel();
+        let server = spawn_server(
+            server_socket,
+            &Keypair::new(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            sender,
+            exit.clone(),
+            Arc::new(RwLock::new(StakedNodes::default())),
+            1,
+        )
+        .unwrap();
+
+        let connection = QuicTpuConnection::new(
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+            server_addr,
+            &Keypair::new(),
+        )
+        .unwrap();
+        connection.send_wire_transaction(&[1u8; 10]).unwrap();
+        let batch: Vec<_> = (2..6u8).map(|i| vec![i; PACKET_DATA_SIZE]).collect();
+        connection.send_wire_transaction_batch(&batch).unwrap();
+
+        let mut received = vec![];
+        while received.len() < 5 {
+            let packets = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+            received.extend(
+                packets
+                    .packets
+                    .iter()
+                    .map(|packet| (packet.data[0], packet.meta.size)),
+            );
+        }
+        received.sort_unstable();
+        assert_eq!(received[0], (1, 10));
+        for (i, (data, size)) in received[1..].iter().enumerate() {
+            assert_eq!(*data, i as u8 + 2);
+            assert_eq!(*size, PACKET_DATA_SIZE);
+        }
+
+        exit.store(true, Ordering::Relaxed);
+        server.join().unwrap();
+    }
+}
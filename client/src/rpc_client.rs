@@ -43,8 +43,8 @@ use {
         transaction::{self, uses_durable_nonce, Transaction},
     },
     solana_transaction_status::{
-        EncodedConfirmedBlock, EncodedConfirmedTransaction, TransactionStatus, UiConfirmedBlock,
-        UiTransactionEncoding,
+        EncodedConfirmedBlock, EncodedConfirmedTransaction, TransactionStatus,
+        TransactionStatusFilter, UiConfirmedBlock, UiTransactionEncoding,
     },
     solana_vote_program::vote_state::MAX_LOCKOUT_HISTORY,
     std::{
@@ -775,6 +775,74 @@ impl RpcClient {
         )
     }
 
+    /// Simulates an ordered bundle of transactions, where each transaction observes the
+    /// account changes made by the ones before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use solana_client::{
+    /// #     client_error::ClientError,
+    /// #     rpc_client::RpcClient,
+    /// # };
+    /// # use solana_sdk::{
+    /// #     signature::Signer,
+    /// #     signer::keypair::Keypair,
+    /// #     hash::Hash,
+    /// #     system_transaction,
+    /// # };
+    /// # let rpc_client = RpcClient::new_mock("succeeds".to_string());
+    /// // Fund a new account, then spend from it
+    /// let key = Keypair::new();
+    /// let new_key = Keypair::new();
+    /// let to = solana_sdk::pubkey::new_rand();
+    /// # let recent_blockhash = Hash::default();
+    /// let tx1 = system_transaction::transfer(&key, &new_key.pubkey(), 50, recent_blockhash);
+    /// let tx2 = system_transaction::transfer(&new_key, &to, 20, recent_blockhash);
+    /// let result = rpc_client.simulate_bundle(&[tx1, tx2])?;
+    /// assert!(result.value.transaction_results.iter().all(|r| r.err.is_none()));
+    /// # Ok::<(), ClientError>(())
+    /// ```
+    pub fn simulate_bundle(
+        &self,
+        transactions: &[Transaction],
+    ) -> RpcResult<RpcSimulateBundleResult> {
+        self.simulate_bundle_with_config(
+            transactions,
+            RpcSimulateBundleConfig {
+                commitment: Some(self.commitment()),
+                ..RpcSimulateBundleConfig::default()
+            },
+        )
+    }
+
+    pub fn simulate_bundle_with_config(
+        &self,
+        transactions: &[Transaction],
+        config: RpcSimulateBundleConfig,
+    ) -> RpcResult<RpcSimulateBundleResult> {
+        let encoding = if let Some(encoding) = config.encoding {
+            encoding
+        } else {
+            self.default_cluster_transaction_encoding()?
+        };
+        let commitment = config.commitment.unwrap_or_default();
+        let commitment = self.maybe_map_commitment(commitment)?;
+        let config = RpcSimulateBundleConfig {
+            encoding: Some(encoding),
+            commitment: Some(commitment),
+            ..config
+        };
+        let serialized_encoded = transactions
+            .iter()
+            .map(|transaction| serialize_encode_transaction(transaction, encoding))
+            .collect::<ClientResult<Vec<_>>>()?;
+        self.send(
+            RpcRequest::SimulateBundle,
+            json!([serialized_encoded, config]),
+        )
+    }
+
     pub fn get_snapshot_slot(&self) -> ClientResult<Slot> {
         self.send(RpcRequest::GetSnapshotSlot, Value::Null)
     }
@@ -1193,12 +1261,7 @@ impl RpcClient {
         address: &Pubkey,
         config: GetConfirmedSignaturesForAddress2Config,
     ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
-        let config = RpcSignaturesForAddressConfig {
-            before: config.before.map(|signature| signature.to_string()),
-            until: config.until.map(|signature| signature.to_string()),
-            limit: config.limit,
-            commitment: config.commitment,
-        };
+        let config = RpcSignaturesForAddressConfig::from(config);
 
         let result: Vec<RpcConfirmedTransactionStatusWithSignature> = self.send(
             self.maybe_map_request(RpcRequest::GetSignaturesForAddress)?,
@@ -1208,6 +1271,21 @@ impl RpcClient {
         Ok(result)
     }
 
+    /// Like `get_signatures_for_address_with_config()`, but also returns where to resume a
+    /// filtered search that stopped before finding `limit` matching signatures
+    pub fn get_filtered_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<RpcFilteredSignaturesForAddress> {
+        let config = RpcSignaturesForAddressConfig::from(config);
+
+        self.send(
+            RpcRequest::GetFilteredSignaturesForAddress,
+            json!([address.to_string(), config]),
+        )
+    }
+
     #[deprecated(
         since = "1.7.0",
         note = "Please use RpcClient::get_signatures_for_address() instead"
@@ -1373,6 +1451,17 @@ impl RpcClient {
         self.send(RpcRequest::GetRecentPerformanceSamples, json!([limit]))
     }
 
+    pub fn get_duplicate_slot_proofs(
+        &self,
+        start_slot: Option<Slot>,
+        limit: Option<usize>,
+    ) -> ClientResult<Vec<RpcDuplicateSlotProof>> {
+        self.send(
+            RpcRequest::GetDuplicateSlotProofs,
+            json!([start_slot, limit]),
+        )
+    }
+
     pub fn get_identity(&self) -> ClientResult<Pubkey> {
         let rpc_identity: RpcIdentity = self.send(RpcRequest::GetIdentity, Value::Null)?;
 
@@ -2427,6 +2516,30 @@ pub struct GetConfirmedSignaturesForAddress2Config {
     pub until: Option<Signature>,
     pub limit: Option<usize>,
     pub commitment: Option<CommitmentConfig>,
+    pub status: Option<TransactionStatusFilter>,
+    pub min_block_time: Option<UnixTimestamp>,
+    pub max_block_time: Option<UnixTimestamp>,
+    pub program_ids: Option<Vec<Pubkey>>,
+}
+
+impl From<GetConfirmedSignaturesForAddress2Config> for RpcSignaturesForAddressConfig {
+    fn from(config: GetConfirmedSignaturesForAddress2Config) -> Self {
+        Self {
+            before: config.before.map(|signature| signature.to_string()),
+            until: config.until.map(|signature| signature.to_string()),
+            limit: config.limit,
+            commitment: config.commitment,
+            status: config.status,
+            min_block_time: config.min_block_time,
+            max_block_time: config.max_block_time,
+            program_ids: config.program_ids.map(|program_ids| {
+                program_ids
+                    .iter()
+                    .map(|program_id| program_id.to_string())
+                    .collect()
+            }),
+        }
+    }
 }
 
 fn new_spinner_progress_bar() -> ProgressBar {
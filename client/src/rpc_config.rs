@@ -1,11 +1,14 @@
 use {
     crate::rpc_filter::RpcFilterType,
-    solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig},
+    solana_account_decoder::{UiAccount, UiAccountEncoding, UiDataSliceConfig},
     solana_sdk::{
-        clock::{Epoch, Slot},
+        clock::{Epoch, Slot, UnixTimestamp},
         commitment_config::{CommitmentConfig, CommitmentLevel},
     },
-    solana_transaction_status::{TransactionDetails, UiTransactionEncoding},
+    solana_transaction_status::{
+        TransactionDetails, TransactionStatusFilter, UiTransactionEncoding,
+    },
+    std::collections::HashMap,
 };
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -21,6 +24,7 @@ pub struct RpcSendTransactionConfig {
     pub skip_preflight: bool,
     pub preflight_commitment: Option<CommitmentLevel>,
     pub encoding: Option<UiTransactionEncoding>,
+    pub max_retries: Option<usize>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -43,6 +47,23 @@ pub struct RpcSimulateTransactionConfig {
     pub accounts: Option<RpcSimulateTransactionAccountsConfig>,
 }
 
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSimulateBundleConfig {
+    #[serde(default)]
+    pub sig_verify: bool,
+    #[serde(default)]
+    pub replace_recent_blockhash: bool,
+    #[serde(flatten)]
+    pub commitment: Option<CommitmentConfig>,
+    pub encoding: Option<UiTransactionEncoding>,
+    /// Accounts whose state is returned after each transaction of the bundle
+    pub accounts: Option<RpcSimulateTransactionAccountsConfig>,
+    /// Account states to assume before the bundle runs, keyed by base-58 encoded address. A
+    /// `null` value simulates the account not existing.
+    pub account_overrides: Option<HashMap<String, Option<UiAccount>>>,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcRequestAirdropConfig {
@@ -180,6 +201,14 @@ pub struct RpcSignaturesForAddressConfig {
     pub limit: Option<usize>,
     #[serde(flatten)]
     pub commitment: Option<CommitmentConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<TransactionStatusFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_block_time: Option<UnixTimestamp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_block_time: Option<UnixTimestamp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program_ids: Option<Vec<String>>, // Pubkeys as base-58 strings
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
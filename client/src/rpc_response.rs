@@ -331,6 +331,14 @@ pub struct RpcSimulateTransactionResult {
     pub units_consumed: Option<u64>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSimulateBundleResult {
+    /// Results of the executed transactions, in bundle order. Transactions following the first
+    /// failure are not executed and have no entry.
+    pub transaction_results: Vec<RpcSimulateTransactionResult>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcStorageTurn {
@@ -390,6 +398,15 @@ pub struct RpcConfirmedTransactionStatusWithSignature {
     pub confirmation_status: Option<TransactionConfirmationStatus>,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcFilteredSignaturesForAddress {
+    pub signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+    /// Set when the search stopped after examining as many transactions as a query may, before
+    /// finding `limit` matches: the signature to pass as `before` to continue it
+    pub resume_before: Option<String>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcPerfSample {
@@ -399,6 +416,15 @@ pub struct RpcPerfSample {
     pub sample_period_secs: u16,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcDuplicateSlotProof {
+    pub slot: Slot,
+    /// Base-64 encoded payloads of the two conflicting shreds signed by the slot leader
+    pub shred1: String,
+    pub shred2: String,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcInflationReward {
@@ -4,7 +4,10 @@
 //! unstable and may change in future releases.
 
 use {
-    crate::{rpc_client::RpcClient, rpc_config::RpcProgramAccountsConfig, rpc_response::Response},
+    crate::{
+        connection_cache, rpc_client::RpcClient, rpc_config::RpcProgramAccountsConfig,
+        rpc_response::Response,
+    },
     bincode::{serialize_into, serialized_size},
     log::*,
     solana_sdk::{
@@ -180,6 +183,18 @@ impl ThinClient {
         &self.tpu_addrs[self.optimizer.best()]
     }
 
+    /// Sends a serialized transaction to the TPU, over QUIC if the connection cache is enabled
+    fn send_wire_transaction(&self, wire_transaction: &[u8]) -> TransportResult<()> {
+        if connection_cache::use_quic() {
+            connection_cache::get_connection(self.tpu_addr())?
+                .send_wire_transaction(wire_transaction)
+        } else {
+            self.transactions_socket
+                .send_to(wire_transaction, self.tpu_addr())?;
+            Ok(())
+        }
+    }
+
     fn rpc_client(&self) -> &RpcClient {
         &self.rpc_clients[self.optimizer.best()]
     }
@@ -225,8 +240,7 @@ impl ThinClient {
             while now.elapsed().as_secs() < wait_time as u64 {
                 if num_confirmed == 0 {
                     // Send the transaction if there has been no confirmation (e.g. the first time)
-                    self.transactions_socket
-                        .send_to(&buf[..], &self.tpu_addr())?;
+                    self.send_wire_transaction(&buf)?;
                 }
 
                 if let Ok(confirmed_blocks) = self.poll_for_signature_confirmation(
@@ -569,8 +583,11 @@ impl AsyncClient for ThinClient {
         serialize_into(&mut wr, &transaction)
             .expect("serialize Transaction in pub fn transfer_signed");
         assert!(buf.len() < PACKET_DATA_SIZE);
-        self.transactions_socket
-            .send_to(&buf[..], &self.tpu_addr())?;
+        if connection_cache::use_quic() {
+            connection_cache::get_connection(self.tpu_addr())?.send_wire_transaction_async(buf);
+        } else {
+            self.transactions_socket.send_to(&buf, self.tpu_addr())?;
+        }
         Ok(transaction.signatures[0])
     }
     fn async_send_message<T: Signers>(
@@ -1,11 +1,21 @@
 use crate::{
+    connection_cache,
     pubsub_client::{PubsubClient, PubsubClientError, PubsubClientSubscription},
     rpc_client::RpcClient,
-    rpc_response::SlotUpdate,
+    rpc_request::MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS,
+    rpc_response::{Fees, SlotUpdate},
 };
 use bincode::serialize;
 use log::*;
-use solana_sdk::{clock::Slot, pubkey::Pubkey, transaction::Transaction};
+use solana_sdk::{
+    clock::Slot,
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Signature, SignerError},
+    signers::Signers,
+    transaction::{Transaction, TransactionError},
+    transport::TransportError,
+};
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     net::{SocketAddr, UdpSocket},
@@ -14,7 +24,7 @@ use std::{
         atomic::{AtomicBool, Ordering},
         Arc, RwLock,
     },
-    thread::JoinHandle,
+    thread::{sleep, JoinHandle},
     time::{Duration, Instant},
 };
 use thiserror::Error;
@@ -27,6 +37,10 @@ pub enum TpuSenderError {
     RpcError(#[from] crate::client_error::ClientError),
     #[error("IO error: {0:?}")]
     IoError(#[from] std::io::Error),
+    #[error("Signer error: {0:?}")]
+    SignerError(#[from] SignerError),
+    #[error("{0} transactions were not confirmed after {1} blockhash refreshes")]
+    UnconfirmedTransactions(usize, usize),
 }
 
 type Result<T> = std::result::Result<T, TpuSenderError>;
@@ -37,6 +51,20 @@ pub const DEFAULT_FANOUT_SLOTS: u64 = 12;
 /// Maximum number of slots used to build TPU socket fanout set
 pub const MAX_FANOUT_SLOTS: u64 = 100;
 
+/// Maximum number of times the blockhash of the pending transactions may expire and be refreshed
+/// before `send_and_confirm_messages_with_progress` gives up
+pub const MAX_BLOCKHASH_REFRESHES: usize = 5;
+
+// Pending transactions are sent to the leaders again at this interval
+const TRANSACTION_RESEND_INTERVAL: Duration = Duration::from_secs(2);
+
+// Throttle sends to about 100 TPS, unless sending every pending transaction at that rate would
+// take longer than `TRANSACTION_RESEND_INTERVAL`
+const SEND_TRANSACTION_INTERVAL: Duration = Duration::from_millis(10);
+
+// Interval between signature status checks
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 /// Config params for `TpuClient`
 #[derive(Clone, Debug)]
 pub struct TpuClientConfig {
@@ -53,6 +81,22 @@ impl Default for TpuClientConfig {
     }
 }
 
+/// Progress of [`TpuClient::send_and_confirm_messages_with_progress`], reported to its
+/// callback after every round of signature status checks
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SendAndConfirmProgress {
+    /// Number of messages in the batch
+    pub num_messages: usize,
+    /// Number of messages whose transaction reached the RPC client's commitment
+    pub num_confirmed: usize,
+    /// Number of times the blockhash of the pending transactions expired and was refreshed
+    pub num_blockhash_refreshes: usize,
+    /// Block height observed in the last status check
+    pub block_height: u64,
+    /// Last block height at which the current blockhash is valid
+    pub last_valid_block_height: u64,
+}
+
 /// Client which sends transactions directly to the current leader's TPU port over UDP.
 /// The client uses RPC to determine the current leader and fetch node contact info
 pub struct TpuClient {
@@ -60,6 +104,7 @@ pub struct TpuClient {
     fanout_slots: u64,
     leader_tpu_service: LeaderTpuService,
     exit: Arc<AtomicBool>,
+    rpc_client: Arc<RpcClient>,
 }
 
 impl TpuClient {
@@ -69,24 +114,199 @@ impl TpuClient {
         self.send_wire_transaction(&wire_transaction)
     }
 
-    /// Sends a transaction to the current leader's TPU port
+    /// Sends a transaction to the current leader's TPU port, over QUIC if the connection cache is
+    /// enabled. QUIC sends are queued so that every leader in the fanout is sent to concurrently
     pub fn send_wire_transaction(&self, wire_transaction: &[u8]) -> bool {
         let mut sent = false;
         for tpu_address in self
             .leader_tpu_service
             .leader_tpu_sockets(self.fanout_slots)
         {
-            if self
-                .send_socket
-                .send_to(wire_transaction, tpu_address)
-                .is_ok()
-            {
+            let result = if connection_cache::use_quic() {
+                connection_cache::get_connection(&tpu_address).map(|connection| {
+                    connection.send_wire_transaction_async(wire_transaction.to_vec())
+                })
+            } else {
+                self.send_socket
+                    .send_to(wire_transaction, tpu_address)
+                    .map(|_| ())
+                    .map_err(TransportError::from)
+            };
+            if result.is_ok() {
                 sent = true;
             }
         }
         sent
     }
 
+    /// Signs `messages` with `signers`, sends them to the upcoming leaders and waits until each
+    /// transaction reaches the RPC client's commitment. Pending transactions are resent until
+    /// their blockhash expires, then re-signed with a fresh one; after
+    /// `MAX_BLOCKHASH_REFRESHES` expiries, the remaining ones are given up on. Transactions that
+    /// were processed by the cluster but have not reached the commitment yet are not re-signed,
+    /// so they cannot land twice.
+    ///
+    /// `progress_callback` is called after every round of status checks. On success, the result
+    /// holds the error of each message's transaction, in the order of `messages`.
+    pub fn send_and_confirm_messages_with_progress<
+        T: Signers,
+        F: FnMut(&SendAndConfirmProgress),
+    >(
+        &self,
+        messages: &[Message],
+        signers: &T,
+        mut progress_callback: F,
+    ) -> Result<Vec<Option<TransactionError>>> {
+        let mut transaction_errors = vec![None; messages.len()];
+        let mut progress = SendAndConfirmProgress {
+            num_messages: messages.len(),
+            ..SendAndConfirmProgress::default()
+        };
+        let mut transactions: Vec<_> = messages
+            .iter()
+            .map(|message| Transaction::new_unsigned(message.clone()))
+            .collect();
+        let mut unconfirmed: Vec<usize> = (0..messages.len()).collect();
+        // Maps the signature of each pending transaction to its message index
+        let mut pending_transactions = HashMap::new();
+
+        loop {
+            let Fees {
+                blockhash,
+                last_valid_block_height,
+                ..
+            } = self.rpc_client.get_fees()?;
+            progress.last_valid_block_height = last_valid_block_height;
+
+            for index in unconfirmed {
+                let transaction = &mut transactions[index];
+                transaction.try_sign(signers, blockhash)?;
+                pending_transactions.insert(transaction.signatures[0], index);
+            }
+
+            let mut last_send: Option<Instant> = None;
+            loop {
+                let send_due = match last_send {
+                    Some(last_send) => last_send.elapsed() >= TRANSACTION_RESEND_INTERVAL,
+                    None => true,
+                };
+                if send_due {
+                    let send_interval = SEND_TRANSACTION_INTERVAL.min(
+                        TRANSACTION_RESEND_INTERVAL / (pending_transactions.len() as u32).max(1),
+                    );
+                    for index in pending_transactions.values() {
+                        let transaction = &transactions[*index];
+                        if !self.send_transaction(transaction) {
+                            debug!(
+                                "failed to send transaction {} to any leader",
+                                transaction.signatures[0]
+                            );
+                        }
+                        sleep(send_interval);
+                    }
+                    last_send = Some(Instant::now());
+                }
+
+                self.confirm_pending_transactions(
+                    &mut pending_transactions,
+                    &mut transaction_errors,
+                    &mut progress,
+                );
+                progress.block_height = self.rpc_client.get_block_height()?;
+                progress_callback(&progress);
+
+                if pending_transactions.is_empty() {
+                    return Ok(transaction_errors);
+                }
+                if progress.block_height > last_valid_block_height {
+                    break;
+                }
+                sleep(STATUS_POLL_INTERVAL);
+            }
+
+            // Every expiry counts, even if no transaction needs to be re-signed, so that waiting
+            // on transactions that never reach the commitment ends too
+            if progress.num_blockhash_refreshes == MAX_BLOCKHASH_REFRESHES {
+                return Err(TpuSenderError::UnconfirmedTransactions(
+                    pending_transactions.len(),
+                    MAX_BLOCKHASH_REFRESHES,
+                ));
+            }
+            progress.num_blockhash_refreshes += 1;
+
+            // Transactions that were processed may still reach the commitment, so keep waiting
+            // on them and only re-sign the ones the cluster has not seen
+            let processed_signatures = self.get_processed_signatures(&pending_transactions);
+            unconfirmed = pending_transactions
+                .iter()
+                .filter(|(signature, _)| !processed_signatures.contains(*signature))
+                .map(|(_, index)| *index)
+                .collect();
+            unconfirmed.sort_unstable();
+            for index in &unconfirmed {
+                pending_transactions.remove(&transactions[*index].signatures[0]);
+            }
+        }
+    }
+
+    // Drop the transactions that reached the RPC client's commitment from `pending_transactions`,
+    // recording their errors
+    fn confirm_pending_transactions(
+        &self,
+        pending_transactions: &mut HashMap<Signature, usize>,
+        transaction_errors: &mut [Option<TransactionError>],
+        progress: &mut SendAndConfirmProgress,
+    ) {
+        let commitment = self.rpc_client.commitment();
+        let pending_signatures: Vec<_> = pending_transactions.keys().cloned().collect();
+        for signatures in pending_signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+            let statuses = match self.rpc_client.get_signature_statuses(signatures) {
+                Ok(response) => response.value,
+                Err(err) => {
+                    debug!("failed to get signature statuses: {}", err);
+                    continue;
+                }
+            };
+            for (signature, status) in signatures.iter().zip(statuses) {
+                if let Some(status) = status {
+                    if status.satisfies_commitment(commitment) {
+                        if let Some(index) = pending_transactions.remove(signature) {
+                            transaction_errors[index] = status.err;
+                            progress.num_confirmed += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Signatures of the pending transactions that have a status at the processed commitment.
+    // Transactions whose status cannot be fetched count as unprocessed
+    fn get_processed_signatures(
+        &self,
+        pending_transactions: &HashMap<Signature, usize>,
+    ) -> HashSet<Signature> {
+        let pending_signatures: Vec<_> = pending_transactions.keys().cloned().collect();
+        let mut processed_signatures = HashSet::new();
+        for signatures in pending_signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+            match self.rpc_client.get_signature_statuses(signatures) {
+                Ok(response) => processed_signatures.extend(
+                    signatures
+                        .iter()
+                        .zip(response.value)
+                        .filter(|(_, status)| status.is_some())
+                        .map(|(signature, _)| *signature),
+                ),
+                Err(err) => debug!("failed to get signature statuses: {}", err),
+            }
+        }
+        processed_signatures
+    }
+
+    pub fn rpc_client(&self) -> &RpcClient {
+        &self.rpc_client
+    }
+
     /// Create a new client that disconnects when dropped
     pub fn new(
         rpc_client: Arc<RpcClient>,
@@ -94,13 +314,15 @@ impl TpuClient {
         config: TpuClientConfig,
     ) -> Result<Self> {
         let exit = Arc::new(AtomicBool::new(false));
-        let leader_tpu_service = LeaderTpuService::new(rpc_client, websocket_url, exit.clone())?;
+        let leader_tpu_service =
+            LeaderTpuService::new(rpc_client.clone(), websocket_url, exit.clone())?;
 
         Ok(Self {
             send_socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
             fanout_slots: config.fanout_slots.min(MAX_FANOUT_SLOTS).max(1),
             leader_tpu_service,
             exit,
+            rpc_client,
         })
     }
 }
@@ -360,6 +582,11 @@ impl LeaderTpuService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        system_instruction,
+    };
 
     fn assert_slot(recent_slots: RecentLeaderSlots, expected_slot: Slot) {
         assert_eq!(recent_slots.estimated_current_slot(), expected_slot);
@@ -390,4 +617,77 @@ mod tests {
         assert_slot(RecentLeaderSlots::from(vec![1, 2, 3, 100]), 3);
         assert_slot(RecentLeaderSlots::from(vec![1, 2, 3, 99, 100]), 3);
     }
+
+    fn new_mock_tpu_client(url: &str) -> TpuClient {
+        let rpc_client = Arc::new(RpcClient::new_mock(url.to_string()));
+        TpuClient::new(rpc_client, "", TpuClientConfig::default()).unwrap()
+    }
+
+    fn transfer_messages(payer: &Keypair, num_messages: usize) -> Vec<Message> {
+        (0..num_messages)
+            .map(|_| {
+                let instruction =
+                    system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+                Message::new(&[instruction], Some(&payer.pubkey()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_send_and_confirm_messages_with_progress() {
+        let payer = Keypair::new();
+        let messages = transfer_messages(&payer, 3);
+
+        let tpu_client = new_mock_tpu_client("succeeds");
+        let mut last_progress = None;
+        let results = tpu_client
+            .send_and_confirm_messages_with_progress(&messages, &[&payer], |progress| {
+                last_progress = Some(progress.clone())
+            })
+            .unwrap();
+        assert_eq!(results, vec![None; 3]);
+        let last_progress = last_progress.unwrap();
+        assert_eq!(last_progress.num_messages, 3);
+        assert_eq!(last_progress.num_confirmed, 3);
+        assert_eq!(last_progress.num_blockhash_refreshes, 0);
+        assert_eq!(last_progress.block_height, 1);
+        assert_eq!(last_progress.last_valid_block_height, 42);
+
+        let tpu_client = new_mock_tpu_client("instruction_error");
+        let results = tpu_client
+            .send_and_confirm_messages_with_progress(&messages, &[&payer], |_| {})
+            .unwrap();
+        let expected_error = Some(TransactionError::InstructionError(
+            0,
+            InstructionError::UninitializedAccount,
+        ));
+        assert_eq!(results, vec![expected_error; 3]);
+    }
+
+    #[test]
+    fn test_send_and_confirm_messages_blockhash_expired() {
+        let payer = Keypair::new();
+        let messages = transfer_messages(&payer, 3);
+
+        // The transactions never land, and the blockhash expires on every pass
+        let tpu_client = new_mock_tpu_client("blockhash_expired");
+        let mut num_blockhash_refreshes = vec![];
+        let result =
+            tpu_client.send_and_confirm_messages_with_progress(&messages, &[&payer], |progress| {
+                assert_eq!(progress.num_confirmed, 0);
+                num_blockhash_refreshes.push(progress.num_blockhash_refreshes);
+            });
+        // They are re-signed after each expiry, until the refreshes run out
+        assert_eq!(
+            num_blockhash_refreshes,
+            (0..=MAX_BLOCKHASH_REFRESHES).collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            result,
+            Err(TpuSenderError::UnconfirmedTransactions(
+                3,
+                MAX_BLOCKHASH_REFRESHES
+            ))
+        ));
+    }
 }
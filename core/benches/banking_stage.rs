@@ -8,8 +8,7 @@ use log::*;
 use rand::{thread_rng, Rng};
 use rayon::prelude::*;
 use solana_core::banking_stage::{BankingStage, BankingStageStats};
-use solana_core::cost_model::CostModel;
-use solana_core::cost_tracker::CostTracker;
+use solana_core::packet_forwarder::ForwardingConfig;
 use solana_entry::entry::{next_hash, Entry};
 use solana_gossip::cluster_info::ClusterInfo;
 use solana_gossip::cluster_info::Node;
@@ -20,6 +19,9 @@ use solana_perf::packet::to_packets_chunked;
 use solana_perf::test_tx::test_tx;
 use solana_poh::poh_recorder::{create_test_recorder, WorkingBankEntry};
 use solana_runtime::bank::Bank;
+use solana_runtime::bank_forks::BankForks;
+use solana_runtime::cost_model::CostModel;
+use solana_runtime::cost_tracker::CostTracker;
 use solana_sdk::genesis_config::GenesisConfig;
 use solana_sdk::hash::Hash;
 use solana_sdk::message::Message;
@@ -219,6 +221,11 @@ fn bench_banking(bencher: &mut Bencher, tx_type: TransactionType) {
             Arc::new(RwLock::new(CostTracker::new(Arc::new(RwLock::new(
                 CostModel::new(std::u64::MAX, std::u64::MAX),
             ))))),
+            &Arc::new(RwLock::new(BankForks::new_from_banks(
+                std::slice::from_ref(&bank),
+                bank.slot(),
+            ))),
+            ForwardingConfig::default(),
         );
         poh_recorder.lock().unwrap().set_bank(&bank);
 
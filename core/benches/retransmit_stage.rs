@@ -87,7 +87,9 @@ fn bench_retransmitter(bencher: &mut Bencher) {
     let slot = 0;
     let parent = 0;
     let shredder = Shredder::new(slot, parent, 0, 0).unwrap();
-    let mut data_shreds = shredder.entries_to_shreds(&keypair, &entries, true, 0).0;
+    let mut data_shreds = shredder
+        .entries_to_shreds(&keypair, &entries, true, 0, Some(0))
+        .0;
 
     let num_packets = data_shreds.len();
 
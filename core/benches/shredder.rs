@@ -75,7 +75,7 @@ fn bench_shredder_ticks(bencher: &mut Bencher) {
     let entries = create_ticks(num_ticks, 0, Hash::default());
     bencher.iter(|| {
         let shredder = Shredder::new(1, 0, 0, 0).unwrap();
-        shredder.entries_to_shreds(&kp, &entries, true, 0);
+        shredder.entries_to_shreds(&kp, &entries, true, 0, Some(0));
     })
 }
 
@@ -94,7 +94,7 @@ fn bench_shredder_large_entries(bencher: &mut Bencher) {
     // 1Mb
     bencher.iter(|| {
         let shredder = Shredder::new(1, 0, 0, 0).unwrap();
-        shredder.entries_to_shreds(&kp, &entries, true, 0);
+        shredder.entries_to_shreds(&kp, &entries, true, 0, Some(0));
     })
 }
 
@@ -107,7 +107,9 @@ fn bench_deshredder(bencher: &mut Bencher) {
     let num_ticks = max_ticks_per_n_shreds(1, Some(shred_size)) * num_shreds as u64;
     let entries = create_ticks(num_ticks, 0, Hash::default());
     let shredder = Shredder::new(1, 0, 0, 0).unwrap();
-    let data_shreds = shredder.entries_to_shreds(&kp, &entries, true, 0).0;
+    let data_shreds = shredder
+        .entries_to_shreds(&kp, &entries, true, 0, Some(0))
+        .0;
     bencher.iter(|| {
         let raw = &mut Shredder::deshred(&data_shreds).unwrap();
         assert_ne!(raw.len(), 0);
@@ -133,7 +135,8 @@ fn bench_shredder_coding(bencher: &mut Bencher) {
     bencher.iter(|| {
         Shredder::generate_coding_shreds(
             &data_shreds[..symbol_count],
-            true, // is_last_in_slot
+            true,    // is_last_in_slot
+            Some(0), // next_code_index
         )
         .len();
     })
@@ -145,7 +148,8 @@ fn bench_shredder_decoding(bencher: &mut Bencher) {
     let data_shreds = make_shreds(symbol_count);
     let coding_shreds = Shredder::generate_coding_shreds(
         &data_shreds[..symbol_count],
-        true, // is_last_in_slot
+        true,    // is_last_in_slot
+        Some(0), // next_code_index
     );
     bencher.iter(|| {
         Shredder::try_recovery(
@@ -153,6 +157,7 @@ fn bench_shredder_decoding(bencher: &mut Bencher) {
             symbol_count,
             symbol_count,
             0, // first index
+            0, // first code index
             1, // slot
         )
         .unwrap();
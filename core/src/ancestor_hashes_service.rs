@@ -0,0 +1,916 @@
+//! The `ancestor_hashes_service` module samples the cluster for the ancestors
+//! of slots we failed to replay, in order to find the earliest ancestor where
+//! our version of the fork diverges from the version the cluster duplicate
+//! confirmed. Those ancestors are then dumped and repaired by ReplayStage and
+//! the RepairService.
+use crate::{
+    cluster_slots::ClusterSlots,
+    duplicate_repair_status::{DeadSlotAncestorRequestStatus, DuplicateAncestorDecision},
+    outstanding_requests::OutstandingRequests,
+    repair_response,
+    repair_service::{DuplicateSlotsResetSender, RepairInfo, RepairStatsGroup},
+    replay_stage::DUPLICATE_THRESHOLD,
+    result::{Error, Result},
+    serve_repair::{AncestorHashesRepair, AncestorHashesResponseVersion, ServeRepair},
+};
+use crossbeam_channel::{unbounded, Receiver as CrossbeamReceiver, Sender as CrossbeamSender};
+use solana_gossip::cluster_info::ClusterInfo;
+use solana_ledger::{blockstore::Blockstore, shred::SIZE_OF_NONCE};
+use solana_perf::{
+    packet::{limited_deserialize, Packet},
+    recycler::Recycler,
+};
+use solana_runtime::bank::Bank;
+use solana_sdk::{
+    clock::{Slot, DEFAULT_MS_PER_SLOT},
+    feature_set,
+    pubkey::Pubkey,
+    signature::Keypair,
+    timing::timestamp,
+};
+use solana_streamer::streamer::{self, PacketReceiver, PacketReceiverConfig};
+use std::{
+    collections::{HashMap, HashSet},
+    net::UdpSocket,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc, RwLock,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AncestorHashesReplayUpdate {
+    // A slot we failed to replay
+    Dead(Slot),
+    // A slot we failed to replay, but which the cluster has duplicate confirmed
+    DeadDuplicateConfirmed(Slot),
+}
+
+// Limit on the number of slots we sample the cluster for every second
+pub const MAX_ANCESTOR_HASHES_SLOT_REQUESTS_PER_SECOND: usize = 2;
+
+pub type AncestorHashesReplayUpdateSender = CrossbeamSender<AncestorHashesReplayUpdate>;
+pub type AncestorHashesReplayUpdateReceiver = CrossbeamReceiver<AncestorHashesReplayUpdate>;
+
+type RetryableSlotsSender = CrossbeamSender<Slot>;
+type RetryableSlotsReceiver = CrossbeamReceiver<Slot>;
+type OutstandingAncestorHashesRepairs = OutstandingRequests<AncestorHashesRepair>;
+type AncestorHashesRequestStatuses = RwLock<HashMap<Slot, DeadSlotAncestorRequestStatus>>;
+
+#[derive(Default)]
+pub struct AncestorHashesResponsesStats {
+    pub total_packets: usize,
+    pub processed: usize,
+    pub invalid_packets: usize,
+    pub unknown_requests: usize,
+}
+
+impl AncestorHashesResponsesStats {
+    fn report(&mut self) {
+        datapoint_info!(
+            "ancestor_hashes_responses",
+            ("total_packets", self.total_packets, i64),
+            ("processed", self.processed, i64),
+            ("invalid_packets", self.invalid_packets, i64),
+            ("unknown_requests", self.unknown_requests, i64),
+        );
+        *self = AncestorHashesResponsesStats::default();
+    }
+}
+
+pub struct AncestorRepairRequestsStats {
+    pub ancestor_requests: RepairStatsGroup,
+    last_report: Instant,
+}
+
+impl Default for AncestorRepairRequestsStats {
+    fn default() -> Self {
+        AncestorRepairRequestsStats {
+            ancestor_requests: RepairStatsGroup::default(),
+            last_report: Instant::now(),
+        }
+    }
+}
+
+impl AncestorRepairRequestsStats {
+    fn report(&mut self) {
+        if self.last_report.elapsed().as_secs() <= 2 {
+            return;
+        }
+        let slot_to_count: Vec<_> = self
+            .ancestor_requests
+            .slot_pubkeys
+            .iter()
+            .map(|(slot, slot_repairs)| (slot, slot_repairs.pubkey_repairs().values().sum::<u64>()))
+            .collect();
+        if self.ancestor_requests.count > 0 {
+            info!("ancestor_repair_requests_stats: {:?}", slot_to_count);
+            datapoint_info!(
+                "ancestor-repair",
+                ("ancestor-repair-count", self.ancestor_requests.count, i64)
+            );
+        }
+        *self = AncestorRepairRequestsStats::default();
+    }
+}
+
+pub struct AncestorHashesService {
+    thread_hdls: Vec<JoinHandle<()>>,
+}
+
+impl AncestorHashesService {
+    pub fn new(
+        exit: Arc<AtomicBool>,
+        blockstore: Arc<Blockstore>,
+        ancestor_hashes_request_socket: Arc<UdpSocket>,
+        repair_info: Arc<RepairInfo>,
+        cluster_info: Arc<ClusterInfo>,
+        cluster_slots: Arc<ClusterSlots>,
+        ancestor_hashes_replay_update_receiver: AncestorHashesReplayUpdateReceiver,
+    ) -> Self {
+        let outstanding_requests: Arc<RwLock<OutstandingAncestorHashesRepairs>> =
+            Arc::new(RwLock::new(OutstandingAncestorHashesRepairs::default()));
+        let (response_sender, response_receiver) = channel();
+        let t_receiver = streamer::receiver(
+            ancestor_hashes_request_socket.clone(),
+            &exit,
+            response_sender,
+            Recycler::default(),
+            "ancestor_hashes_response_receiver",
+            1,
+            false,
+            PacketReceiverConfig::default(),
+        );
+
+        let ancestor_hashes_request_statuses: Arc<AncestorHashesRequestStatuses> = Arc::default();
+        let (retryable_slots_sender, retryable_slots_receiver) = unbounded();
+
+        // Listen for responses to our ancestor requests
+        let t_ancestor_hashes_responses = Self::run_responses_listener(
+            ancestor_hashes_request_statuses.clone(),
+            response_receiver,
+            blockstore,
+            outstanding_requests.clone(),
+            exit.clone(),
+            repair_info.duplicate_slots_reset_sender.clone(),
+            retryable_slots_sender,
+        );
+
+        // Generate ancestor requests for dead slots that are repairable
+        let t_ancestor_requests = Self::run_manage_ancestor_requests(
+            ancestor_hashes_request_statuses,
+            ancestor_hashes_request_socket,
+            repair_info,
+            cluster_info,
+            cluster_slots,
+            outstanding_requests,
+            exit,
+            ancestor_hashes_replay_update_receiver,
+            retryable_slots_receiver,
+        );
+        Self {
+            thread_hdls: vec![t_receiver, t_ancestor_hashes_responses, t_ancestor_requests],
+        }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        for thread_hdl in self.thread_hdls {
+            thread_hdl.join()?;
+        }
+        Ok(())
+    }
+
+    /// Listen for responses to our ancestors hashes repair requests
+    fn run_responses_listener(
+        ancestor_hashes_request_statuses: Arc<AncestorHashesRequestStatuses>,
+        response_receiver: PacketReceiver,
+        blockstore: Arc<Blockstore>,
+        outstanding_requests: Arc<RwLock<OutstandingAncestorHashesRepairs>>,
+        exit: Arc<AtomicBool>,
+        duplicate_slots_reset_sender: DuplicateSlotsResetSender,
+        retryable_slots_sender: RetryableSlotsSender,
+    ) -> JoinHandle<()> {
+        Builder::new()
+            .name("solana-ancestor-hashes-responses-service".to_string())
+            .spawn(move || {
+                let mut stats = AncestorHashesResponsesStats::default();
+                let mut last_stats_report = Instant::now();
+                loop {
+                    let result = Self::process_new_packets_from_channel(
+                        &ancestor_hashes_request_statuses,
+                        &response_receiver,
+                        &blockstore,
+                        &outstanding_requests,
+                        &mut stats,
+                        &duplicate_slots_reset_sender,
+                        &retryable_slots_sender,
+                    );
+                    match result {
+                        Err(Error::RecvTimeout(_)) | Ok(_) => {}
+                        Err(err) => info!("ancestors hashes responses listener error: {:?}", err),
+                    };
+                    if exit.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    if last_stats_report.elapsed().as_secs() > 2 {
+                        stats.report();
+                        last_stats_report = Instant::now();
+                    }
+                }
+            })
+            .unwrap()
+    }
+
+    /// Process messages from the network
+    fn process_new_packets_from_channel(
+        ancestor_hashes_request_statuses: &AncestorHashesRequestStatuses,
+        response_receiver: &PacketReceiver,
+        blockstore: &Blockstore,
+        outstanding_requests: &RwLock<OutstandingAncestorHashesRepairs>,
+        stats: &mut AncestorHashesResponsesStats,
+        duplicate_slots_reset_sender: &DuplicateSlotsResetSender,
+        retryable_slots_sender: &RetryableSlotsSender,
+    ) -> Result<()> {
+        let timeout = Duration::new(1, 0);
+        let mut packets = vec![response_receiver.recv_timeout(timeout)?];
+        packets.extend(response_receiver.try_iter());
+        for packet in packets.iter().flat_map(|packets| &packets.packets) {
+            stats.total_packets += 1;
+            let decision = Self::verify_and_process_ancestor_response(
+                packet,
+                ancestor_hashes_request_statuses,
+                stats,
+                outstanding_requests,
+                blockstore,
+            );
+            if let Some((slot, decision)) = decision {
+                Self::handle_ancestor_request_decision(
+                    slot,
+                    decision,
+                    duplicate_slots_reset_sender,
+                    retryable_slots_sender,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `Some((request_slot, decision))`, where `decision` is an actionable
+    /// result after processing sufficient responses for the subject of the query,
+    /// `request_slot`
+    fn verify_and_process_ancestor_response(
+        packet: &Packet,
+        ancestor_hashes_request_statuses: &AncestorHashesRequestStatuses,
+        stats: &mut AncestorHashesResponsesStats,
+        outstanding_requests: &RwLock<OutstandingAncestorHashesRepairs>,
+        blockstore: &Blockstore,
+    ) -> Option<(Slot, DuplicateAncestorDecision)> {
+        let from_addr = packet.meta.addr();
+        let size = packet.meta.size;
+        let response = size
+            .checked_sub(SIZE_OF_NONCE)
+            .and_then(|size| {
+                limited_deserialize::<AncestorHashesResponseVersion>(&packet.data[..size]).ok()
+            })
+            .zip(repair_response::nonce(&packet.data[..size]));
+        let (response, nonce) = match response {
+            Some(response) => response,
+            None => {
+                stats.invalid_packets += 1;
+                return None;
+            }
+        };
+
+        // Verify the response
+        let request_slot = outstanding_requests.write().unwrap().register_response(
+            nonce,
+            &response,
+            timestamp(),
+            // If the response is valid, return the slot the request
+            // was for
+            |ancestor_hashes_request| ancestor_hashes_request.0,
+        );
+        let request_slot = match request_slot {
+            Some(request_slot) => request_slot,
+            None => {
+                stats.unknown_requests += 1;
+                return None;
+            }
+        };
+        stats.processed += 1;
+
+        // Add the response to the status of the request. If enough of the
+        // sampled validators responded, a decision is made and the request
+        // is complete.
+        let mut ancestor_hashes_request_statuses =
+            ancestor_hashes_request_statuses.write().unwrap();
+        let decision = ancestor_hashes_request_statuses
+            .get_mut(&request_slot)?
+            .add_response(&from_addr, response.into_slot_hashes(), blockstore)?;
+        ancestor_hashes_request_statuses.remove(&request_slot);
+        Some((request_slot, decision))
+    }
+
+    fn handle_ancestor_request_decision(
+        slot: Slot,
+        decision: DuplicateAncestorDecision,
+        duplicate_slots_reset_sender: &DuplicateSlotsResetSender,
+        retryable_slots_sender: &RetryableSlotsSender,
+    ) {
+        if decision.is_retryable() {
+            let _ = retryable_slots_sender.send(slot);
+        }
+        // In the case of DuplicateAncestorDecision::ContinueSearch, all the
+        // ancestors in the response were mismatched, so the earliest mismatched
+        // ancestor has yet to be found. ReplayStage dumps the earliest known
+        // ancestor and repairs it; if that version still chains to a wrong
+        // ancestor it is marked dead again and another round of the search starts.
+        let potential_slots_to_dump = decision
+            .repair_status()
+            .map(|status| status.correct_ancestors_to_repair.clone());
+        if let Some(potential_slots_to_dump) = potential_slots_to_dump {
+            if !potential_slots_to_dump.is_empty() {
+                let _ = duplicate_slots_reset_sender.send(potential_slots_to_dump);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run_manage_ancestor_requests(
+        ancestor_hashes_request_statuses: Arc<AncestorHashesRequestStatuses>,
+        ancestor_hashes_request_socket: Arc<UdpSocket>,
+        repair_info: Arc<RepairInfo>,
+        cluster_info: Arc<ClusterInfo>,
+        cluster_slots: Arc<ClusterSlots>,
+        outstanding_requests: Arc<RwLock<OutstandingAncestorHashesRepairs>>,
+        exit: Arc<AtomicBool>,
+        ancestor_hashes_replay_update_receiver: AncestorHashesReplayUpdateReceiver,
+        retryable_slots_receiver: RetryableSlotsReceiver,
+    ) -> JoinHandle<()> {
+        let serve_repair = ServeRepair::new(cluster_info.clone());
+        let mut repair_stats = AncestorRepairRequestsStats::default();
+        // Dead slots which are not known to be duplicate confirmed yet
+        let mut dead_slot_pool = HashSet::new();
+        // Dead slots which are worth sampling the cluster for
+        let mut repairable_dead_slot_pool = HashSet::new();
+        // Timestamps of the requests sent in the last second
+        let mut request_throttle = vec![];
+        Builder::new()
+            .name("solana-manage-ancestor-requests".to_string())
+            .spawn(move || loop {
+                if exit.load(Ordering::Relaxed) {
+                    return;
+                }
+                Self::manage_ancestor_requests(
+                    &ancestor_hashes_request_statuses,
+                    &ancestor_hashes_request_socket,
+                    &repair_info,
+                    &cluster_info,
+                    &cluster_slots,
+                    &outstanding_requests,
+                    &ancestor_hashes_replay_update_receiver,
+                    &retryable_slots_receiver,
+                    &serve_repair,
+                    &mut repair_stats,
+                    &mut dead_slot_pool,
+                    &mut repairable_dead_slot_pool,
+                    &mut request_throttle,
+                );
+                sleep(Duration::from_millis(DEFAULT_MS_PER_SLOT));
+            })
+            .unwrap()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn manage_ancestor_requests(
+        ancestor_hashes_request_statuses: &AncestorHashesRequestStatuses,
+        ancestor_hashes_request_socket: &UdpSocket,
+        repair_info: &RepairInfo,
+        cluster_info: &ClusterInfo,
+        cluster_slots: &ClusterSlots,
+        outstanding_requests: &RwLock<OutstandingAncestorHashesRepairs>,
+        ancestor_hashes_replay_update_receiver: &AncestorHashesReplayUpdateReceiver,
+        retryable_slots_receiver: &RetryableSlotsReceiver,
+        serve_repair: &ServeRepair,
+        repair_stats: &mut AncestorRepairRequestsStats,
+        dead_slot_pool: &mut HashSet<Slot>,
+        repairable_dead_slot_pool: &mut HashSet<Slot>,
+        request_throttle: &mut Vec<u64>,
+    ) {
+        let root_bank = repair_info.bank_forks.read().unwrap().root_bank();
+        let root_slot = root_bank.slot();
+        for slot in retryable_slots_receiver.try_iter() {
+            datapoint_info!("ancestor-repair-retry", ("slot", slot, i64));
+            repairable_dead_slot_pool.insert(slot);
+        }
+
+        Self::process_replay_updates(
+            ancestor_hashes_replay_update_receiver,
+            ancestor_hashes_request_statuses,
+            dead_slot_pool,
+            repairable_dead_slot_pool,
+            root_slot,
+        );
+
+        Self::find_epoch_slots_frozen_dead_slots(
+            cluster_slots,
+            dead_slot_pool,
+            repairable_dead_slot_pool,
+            &root_bank,
+        );
+
+        dead_slot_pool.retain(|slot| *slot > root_slot);
+        repairable_dead_slot_pool.retain(|slot| *slot > root_slot);
+
+        ancestor_hashes_request_statuses
+            .write()
+            .unwrap()
+            .retain(|slot, status| {
+                if *slot <= root_slot {
+                    false
+                } else if status.is_expired() {
+                    // Add the slot back to the repairable pool to retry
+                    repairable_dead_slot_pool.insert(*slot);
+                    false
+                } else {
+                    true
+                }
+            });
+
+        // Keep around the last second of requests in the throttler.
+        let now = timestamp();
+        request_throttle.retain(|request_ts| now.saturating_sub(*request_ts) < 1000);
+
+        let keypair = cluster_info.keypair().clone();
+        let identity_keypair = root_bank
+            .feature_set
+            .is_active(&feature_set::sign_repair_requests::id())
+            .then(|| keypair.as_ref());
+
+        let number_of_allowed_requests =
+            MAX_ANCESTOR_HASHES_SLOT_REQUESTS_PER_SECOND.saturating_sub(request_throttle.len());
+        let slots: Vec<Slot> = repairable_dead_slot_pool
+            .iter()
+            .take(number_of_allowed_requests)
+            .copied()
+            .collect();
+        for slot in slots {
+            warn!(
+                "Cluster froze slot: {}, but we marked it as dead.
+                Initiating protocol to sample cluster for dead slot ancestors.",
+                slot
+            );
+
+            if Self::initiate_ancestor_hashes_requests_for_duplicate_slot(
+                ancestor_hashes_request_statuses,
+                ancestor_hashes_request_socket,
+                cluster_slots,
+                serve_repair,
+                &repair_info.repair_validators,
+                slot,
+                repair_stats,
+                outstanding_requests,
+                identity_keypair,
+            ) {
+                request_throttle.push(timestamp());
+                repairable_dead_slot_pool.remove(&slot);
+            }
+        }
+        repair_stats.report();
+    }
+
+    fn process_replay_updates(
+        ancestor_hashes_replay_update_receiver: &AncestorHashesReplayUpdateReceiver,
+        ancestor_hashes_request_statuses: &AncestorHashesRequestStatuses,
+        dead_slot_pool: &mut HashSet<Slot>,
+        repairable_dead_slot_pool: &mut HashSet<Slot>,
+        root_slot: Slot,
+    ) {
+        for ancestor_hashes_replay_update in ancestor_hashes_replay_update_receiver.try_iter() {
+            let dead_slot = match ancestor_hashes_replay_update {
+                AncestorHashesReplayUpdate::Dead(dead_slot)
+                | AncestorHashesReplayUpdate::DeadDuplicateConfirmed(dead_slot) => dead_slot,
+            };
+            // Ignore slots that are rooted or already being sampled
+            if dead_slot <= root_slot
+                || ancestor_hashes_request_statuses
+                    .read()
+                    .unwrap()
+                    .contains_key(&dead_slot)
+            {
+                continue;
+            }
+            match ancestor_hashes_replay_update {
+                AncestorHashesReplayUpdate::Dead(dead_slot) => {
+                    if !repairable_dead_slot_pool.contains(&dead_slot) {
+                        dead_slot_pool.insert(dead_slot);
+                    }
+                }
+                AncestorHashesReplayUpdate::DeadDuplicateConfirmed(dead_slot) => {
+                    // The cluster confirmed some other version of this slot, so
+                    // there's no need to wait on epoch slots
+                    dead_slot_pool.remove(&dead_slot);
+                    repairable_dead_slot_pool.insert(dead_slot);
+                }
+            }
+        }
+    }
+
+    /// Find dead slots which more than `DUPLICATE_THRESHOLD` of the cluster
+    /// has completed according to epoch slots, and move them to the pool of
+    /// slots worth sampling the cluster for.
+    fn find_epoch_slots_frozen_dead_slots(
+        cluster_slots: &ClusterSlots,
+        dead_slot_pool: &mut HashSet<Slot>,
+        repairable_dead_slot_pool: &mut HashSet<Slot>,
+        root_bank: &Bank,
+    ) {
+        dead_slot_pool.retain(|dead_slot| {
+            let epoch = root_bank.get_epoch_and_slot_index(*dead_slot).0;
+            if let Some(epoch_stakes) = root_bank.epoch_stakes(epoch) {
+                let total_stake = epoch_stakes.total_stake();
+                let frozen_stake: u64 = cluster_slots
+                    .lookup(*dead_slot)
+                    .map(|slot_pubkeys| slot_pubkeys.read().unwrap().values().sum())
+                    .unwrap_or(0);
+                if total_stake > 0 && frozen_stake as f64 / total_stake as f64 > DUPLICATE_THRESHOLD
+                {
+                    repairable_dead_slot_pool.insert(*dead_slot);
+                    false
+                } else {
+                    true
+                }
+            } else {
+                warn!(
+                    "Dead slot {} is too far ahead of root bank {}",
+                    dead_slot,
+                    root_bank.slot()
+                );
+                false
+            }
+        })
+    }
+
+    /// Returns true if a request was successfully made and the status
+    /// added to `ancestor_hashes_request_statuses`
+    #[allow(clippy::too_many_arguments)]
+    fn initiate_ancestor_hashes_requests_for_duplicate_slot(
+        ancestor_hashes_request_statuses: &AncestorHashesRequestStatuses,
+        ancestor_hashes_request_socket: &UdpSocket,
+        cluster_slots: &ClusterSlots,
+        serve_repair: &ServeRepair,
+        repair_validators: &Option<HashSet<Pubkey>>,
+        duplicate_slot: Slot,
+        repair_stats: &mut AncestorRepairRequestsStats,
+        outstanding_requests: &RwLock<OutstandingAncestorHashesRepairs>,
+        identity_keypair: Option<&Keypair>,
+    ) -> bool {
+        let sampled_validators = serve_repair.repair_request_ancestor_hashes_sample_peers(
+            duplicate_slot,
+            cluster_slots,
+            repair_validators,
+        );
+        let sampled_validators = match sampled_validators {
+            Ok(sampled_validators) if !sampled_validators.is_empty() => sampled_validators,
+            _ => return false,
+        };
+
+        for (pubkey, socket_addr) in &sampled_validators {
+            repair_stats
+                .ancestor_requests
+                .update(pubkey, duplicate_slot, 0);
+            let nonce = outstanding_requests
+                .write()
+                .unwrap()
+                .add_request(AncestorHashesRepair(duplicate_slot), timestamp());
+            match serve_repair.ancestor_repair_request_bytes(
+                duplicate_slot,
+                pubkey,
+                nonce,
+                identity_keypair,
+            ) {
+                Ok(request_bytes) => {
+                    if let Err(err) =
+                        ancestor_hashes_request_socket.send_to(&request_bytes, socket_addr)
+                    {
+                        info!(
+                            "ancestor hashes req send_to {} ({}) error {:?}",
+                            pubkey, socket_addr, err
+                        );
+                    }
+                }
+                Err(err) => error!("failed to serialize ancestor hashes request: {:?}", err),
+            }
+        }
+
+        let ancestor_request_status = DeadSlotAncestorRequestStatus::new(
+            sampled_validators
+                .into_iter()
+                .map(|(_pubkey, socket_addr)| socket_addr),
+            duplicate_slot,
+        );
+        ancestor_hashes_request_statuses
+            .write()
+            .unwrap()
+            .insert(duplicate_slot, ancestor_request_status);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        duplicate_repair_status::DuplicateSlotRepairStatus,
+        serve_repair_service::ServeRepairService,
+    };
+    use solana_gossip::cluster_info::Node;
+    use solana_ledger::{blockstore::make_many_slot_entries, get_tmp_ledger_path_auto_delete};
+    use solana_runtime::{
+        bank_forks::BankForks,
+        genesis_utils::{create_genesis_config_with_vote_accounts, ValidatorVoteKeypairs},
+    };
+    use solana_sdk::{hash::Hash, signature::Signer};
+
+    #[test]
+    fn test_process_replay_updates() {
+        let (sender, receiver) = unbounded();
+        let ancestor_hashes_request_statuses = AncestorHashesRequestStatuses::default();
+        let mut dead_slot_pool = HashSet::new();
+        let mut repairable_dead_slot_pool = HashSet::new();
+        let root_slot = 5;
+
+        // Rooted slots are ignored, dead slots wait in `dead_slot_pool`
+        sender.send(AncestorHashesReplayUpdate::Dead(4)).unwrap();
+        sender.send(AncestorHashesReplayUpdate::Dead(6)).unwrap();
+        sender.send(AncestorHashesReplayUpdate::Dead(7)).unwrap();
+        AncestorHashesService::process_replay_updates(
+            &receiver,
+            &ancestor_hashes_request_statuses,
+            &mut dead_slot_pool,
+            &mut repairable_dead_slot_pool,
+            root_slot,
+        );
+        assert_eq!(dead_slot_pool, vec![6, 7].into_iter().collect());
+        assert!(repairable_dead_slot_pool.is_empty());
+
+        // Duplicate confirmed dead slots are immediately repairable
+        sender
+            .send(AncestorHashesReplayUpdate::DeadDuplicateConfirmed(6))
+            .unwrap();
+        sender
+            .send(AncestorHashesReplayUpdate::DeadDuplicateConfirmed(8))
+            .unwrap();
+        AncestorHashesService::process_replay_updates(
+            &receiver,
+            &ancestor_hashes_request_statuses,
+            &mut dead_slot_pool,
+            &mut repairable_dead_slot_pool,
+            root_slot,
+        );
+        assert_eq!(dead_slot_pool, vec![7].into_iter().collect());
+        assert_eq!(repairable_dead_slot_pool, vec![6, 8].into_iter().collect());
+
+        // Slots which are already being sampled are ignored
+        ancestor_hashes_request_statuses
+            .write()
+            .unwrap()
+            .insert(9, DeadSlotAncestorRequestStatus::new(std::iter::empty(), 9));
+        sender.send(AncestorHashesReplayUpdate::Dead(9)).unwrap();
+        sender
+            .send(AncestorHashesReplayUpdate::DeadDuplicateConfirmed(9))
+            .unwrap();
+        AncestorHashesService::process_replay_updates(
+            &receiver,
+            &ancestor_hashes_request_statuses,
+            &mut dead_slot_pool,
+            &mut repairable_dead_slot_pool,
+            root_slot,
+        );
+        assert!(!dead_slot_pool.contains(&9));
+        assert!(!repairable_dead_slot_pool.contains(&9));
+    }
+
+    #[test]
+    fn test_find_epoch_slots_frozen_dead_slots() {
+        let validator_keypairs: Vec<_> =
+            (0..3).map(|_| ValidatorVoteKeypairs::new_rand()).collect();
+        let genesis_config = create_genesis_config_with_vote_accounts(
+            1_000_000_000,
+            &validator_keypairs,
+            vec![100; validator_keypairs.len()],
+        )
+        .genesis_config;
+        let bank = Bank::new(&genesis_config);
+        let bank_forks = RwLock::new(BankForks::new(bank));
+        let root_bank = bank_forks.read().unwrap().root_bank();
+        let cluster_info = ClusterInfo::new_with_invalid_keypair(Node::new_localhost().info);
+        let cluster_slots = ClusterSlots::default();
+        cluster_slots.update(0, &cluster_info, &bank_forks);
+
+        let dead_slot = 10;
+        let mut dead_slot_pool = vec![dead_slot].into_iter().collect();
+        let mut repairable_dead_slot_pool = HashSet::new();
+
+        // A third of the stake is not more than `DUPLICATE_THRESHOLD`
+        cluster_slots.insert_node_id(dead_slot, validator_keypairs[0].node_keypair.pubkey());
+        AncestorHashesService::find_epoch_slots_frozen_dead_slots(
+            &cluster_slots,
+            &mut dead_slot_pool,
+            &mut repairable_dead_slot_pool,
+            &root_bank,
+        );
+        assert!(dead_slot_pool.contains(&dead_slot));
+        assert!(repairable_dead_slot_pool.is_empty());
+
+        // Once two thirds of the stake completed the slot it becomes repairable
+        cluster_slots.insert_node_id(dead_slot, validator_keypairs[1].node_keypair.pubkey());
+        AncestorHashesService::find_epoch_slots_frozen_dead_slots(
+            &cluster_slots,
+            &mut dead_slot_pool,
+            &mut repairable_dead_slot_pool,
+            &root_bank,
+        );
+        assert!(dead_slot_pool.is_empty());
+        assert!(repairable_dead_slot_pool.contains(&dead_slot));
+    }
+
+    #[test]
+    fn test_handle_ancestor_request_decision() {
+        let (duplicate_slots_reset_sender, duplicate_slots_reset_receiver) = unbounded();
+        let (retryable_slots_sender, retryable_slots_receiver) = unbounded();
+        let slot = 10;
+
+        // Retryable decisions go back to the pool
+        for decision in [
+            DuplicateAncestorDecision::InvalidSample,
+            DuplicateAncestorDecision::SampleNotDuplicateConfirmed,
+        ] {
+            AncestorHashesService::handle_ancestor_request_decision(
+                slot,
+                decision,
+                &duplicate_slots_reset_sender,
+                &retryable_slots_sender,
+            );
+            assert_eq!(retryable_slots_receiver.try_recv().unwrap(), slot);
+            assert!(duplicate_slots_reset_receiver.try_recv().is_err());
+        }
+
+        // Nothing to do if all the ancestors match
+        AncestorHashesService::handle_ancestor_request_decision(
+            slot,
+            DuplicateAncestorDecision::AncestorsAllMatch,
+            &duplicate_slots_reset_sender,
+            &retryable_slots_sender,
+        );
+        assert!(retryable_slots_receiver.try_recv().is_err());
+        assert!(duplicate_slots_reset_receiver.try_recv().is_err());
+
+        // Mismatched ancestors are sent to ReplayStage to dump
+        let correct_ancestors_to_repair = vec![(slot - 1, Hash::new_unique())];
+        AncestorHashesService::handle_ancestor_request_decision(
+            slot,
+            DuplicateAncestorDecision::EarliestMismatchFound(DuplicateSlotRepairStatus {
+                correct_ancestors_to_repair: correct_ancestors_to_repair.clone(),
+                ..DuplicateSlotRepairStatus::default()
+            }),
+            &duplicate_slots_reset_sender,
+            &retryable_slots_sender,
+        );
+        assert!(retryable_slots_receiver.try_recv().is_err());
+        assert_eq!(
+            duplicate_slots_reset_receiver.try_recv().unwrap(),
+            correct_ancestors_to_repair
+        );
+    }
+
+    #[test]
+    fn test_ancestor_hashes_request_and_response() {
+        solana_logger::setup();
+        let exit = Arc::new(AtomicBool::new(false));
+        let dead_slot = 5;
+
+        // The responder has duplicate confirmed the whole fork up to `dead_slot`
+        let responder_ledger_path = get_tmp_ledger_path_auto_delete!();
+        let responder_blockstore =
+            Arc::new(Blockstore::open(responder_ledger_path.path()).unwrap());
+        let (shreds, _) = make_many_slot_entries(0, dead_slot + 1, 5);
+        responder_blockstore
+            .insert_shreds(shreds, None, false)
+            .unwrap();
+        let correct_hashes: Vec<_> = (0..=dead_slot).map(|_| Hash::new_unique()).collect();
+        for (slot, hash) in correct_hashes.iter().enumerate() {
+            responder_blockstore.insert_bank_hash(slot as Slot, *hash, true);
+        }
+        let responder_node = Node::new_localhost();
+        let responder_info = responder_node.info.clone();
+        let responder_serve_repair = Arc::new(RwLock::new(ServeRepair::new(Arc::new(
+            ClusterInfo::new_with_invalid_keypair(responder_info.clone()),
+        ))));
+        let responder_service = ServeRepairService::new(
+            &responder_serve_repair,
+            Some(responder_blockstore),
+            None,
+            responder_node.sockets.serve_repair,
+            PacketReceiverConfig::default(),
+            &exit,
+        );
+
+        // The requester replayed a different version of `dead_slot`
+        let requester_ledger_path = get_tmp_ledger_path_auto_delete!();
+        let requester_blockstore = Blockstore::open(requester_ledger_path.path()).unwrap();
+        for (slot, hash) in correct_hashes.iter().enumerate().take(dead_slot as usize) {
+            requester_blockstore.insert_bank_hash(slot as Slot, *hash, false);
+        }
+        requester_blockstore.insert_bank_hash(dead_slot, Hash::new_unique(), false);
+        let requester_node = Node::new_localhost();
+        let requester_cluster_info = Arc::new(ClusterInfo::new_with_invalid_keypair(
+            requester_node.info.clone(),
+        ));
+        requester_cluster_info.insert_info(responder_info.clone());
+        let serve_repair = ServeRepair::new(requester_cluster_info);
+        let cluster_slots = ClusterSlots::default();
+        cluster_slots.insert_node_id(dead_slot, responder_info.id);
+
+        let ancestor_hashes_request_socket =
+            Arc::new(requester_node.sockets.ancestor_hashes_requests);
+        let (response_sender, response_receiver) = channel();
+        let t_receiver = streamer::receiver(
+            ancestor_hashes_request_socket.clone(),
+            &exit,
+            response_sender,
+            Recycler::default(),
+            "ancestor_hashes_response_receiver",
+            1,
+            false,
+            PacketReceiverConfig::default(),
+        );
+
+        let ancestor_hashes_request_statuses = AncestorHashesRequestStatuses::default();
+        let outstanding_requests = RwLock::new(OutstandingAncestorHashesRepairs::default());
+        assert!(
+            AncestorHashesService::initiate_ancestor_hashes_requests_for_duplicate_slot(
+                &ancestor_hashes_request_statuses,
+                &ancestor_hashes_request_socket,
+                &cluster_slots,
+                &serve_repair,
+                &None,
+                dead_slot,
+                &mut AncestorRepairRequestsStats::default(),
+                &outstanding_requests,
+                None,
+            )
+        );
+        assert!(ancestor_hashes_request_statuses
+            .read()
+            .unwrap()
+            .contains_key(&dead_slot));
+
+        // The only sampled validator agrees on every ancestor but `dead_slot`
+        let (duplicate_slots_reset_sender, duplicate_slots_reset_receiver) = unbounded();
+        let (retryable_slots_sender, retryable_slots_receiver) = unbounded();
+        let mut stats = AncestorHashesResponsesStats::default();
+        AncestorHashesService::process_new_packets_from_channel(
+            &ancestor_hashes_request_statuses,
+            &response_receiver,
+            &requester_blockstore,
+            &outstanding_requests,
+            &mut stats,
+            &duplicate_slots_reset_sender,
+            &retryable_slots_sender,
+        )
+        .unwrap();
+        assert_eq!(stats.processed, 1);
+        assert!(ancestor_hashes_request_statuses.read().unwrap().is_empty());
+        assert!(retryable_slots_receiver.try_recv().is_err());
+        assert_eq!(
+            duplicate_slots_reset_receiver.try_recv().unwrap(),
+            vec![(dead_slot, correct_hashes[dead_slot as usize])]
+        );
+
+        // Responses to requests which already completed are dropped
+        let requester_socket_addr = ancestor_hashes_request_socket.local_addr().unwrap();
+        let packet = repair_response::repair_response_packet_from_bytes(
+            bincode::serialize(&AncestorHashesResponseVersion::Current(vec![])).unwrap(),
+            &requester_socket_addr,
+            0,
+        )
+        .unwrap();
+        assert!(AncestorHashesService::verify_and_process_ancestor_response(
+            &packet,
+            &ancestor_hashes_request_statuses,
+            &mut stats,
+            &outstanding_requests,
+            &requester_blockstore,
+        )
+        .is_none());
+        assert_eq!(stats.unknown_requests, 1);
+
+        exit.store(true, Ordering::Relaxed);
+        responder_service.join().unwrap();
+        t_receiver.join().unwrap();
+    }
+}
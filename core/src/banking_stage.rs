@@ -1,8 +1,15 @@
 //! The `banking_stage` processes Transaction messages. It is intended to be used
 //! to contruct a software pipeline. The stage uses all available CPU cores and
 //! can do its processing in parallel with signature verification on the GPU.
-use crate::{cost_tracker::CostTracker, packet_hasher::PacketHasher};
-use crossbeam_channel::{Receiver as CrossbeamReceiver, RecvTimeoutError};
+use crate::{
+    packet_forwarder::{max_forwarding_age, ForwardingConfig, PacketForwarder},
+    packet_hasher::PacketHasher,
+    transaction_scheduler::{ScheduledBatch, TransactionScheduler},
+};
+use crossbeam_channel::{
+    bounded, select, unbounded, Receiver as CrossbeamReceiver, RecvTimeoutError,
+    Sender as CrossbeamSender,
+};
 use itertools::Itertools;
 use lru::LruCache;
 use retain_mut::RetainMut;
@@ -14,24 +21,23 @@ use solana_metrics::{inc_new_counter_debug, inc_new_counter_info};
 use solana_perf::{
     cuda_runtime::PinnedVec,
     packet::{limited_deserialize, Packet, Packets, PACKETS_PER_BATCH},
-    perf_libs,
 };
-use solana_poh::poh_recorder::{PohRecorder, PohRecorderError, TransactionRecorder};
+use solana_poh::poh_recorder::{BankStart, PohRecorder, PohRecorderError, TransactionRecorder};
 use solana_runtime::{
     accounts_db::ErrorCounters,
     bank::{
         Bank, ExecuteTimings, TransactionBalancesSet, TransactionCheckResult,
         TransactionExecutionResult,
     },
+    bank_forks::BankForks,
     bank_utils,
+    cost_tracker::CostTracker,
     transaction_batch::TransactionBatch,
     vote_sender_types::ReplayVoteSender,
 };
 use solana_sdk::{
-    clock::{
-        Slot, DEFAULT_TICKS_PER_SLOT, MAX_PROCESSING_AGE, MAX_TRANSACTION_FORWARDING_DELAY,
-        MAX_TRANSACTION_FORWARDING_DELAY_GPU,
-    },
+    clock::{Slot, DEFAULT_TICKS_PER_SLOT, MAX_PROCESSING_AGE},
+    fee_calculator::{FeeCalculator, DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE},
     message::Message,
     pubkey::Pubkey,
     sanitized_transaction::SanitizedTransaction,
@@ -46,10 +52,9 @@ use solana_transaction_status::token_balances::{
 use std::{
     borrow::Cow,
     cmp,
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     env,
     mem::size_of,
-    net::UdpSocket,
     ops::DerefMut,
     sync::atomic::{AtomicU64, AtomicUsize, Ordering},
     sync::{Arc, Mutex, RwLock},
@@ -268,6 +273,8 @@ impl BankingStage {
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: ReplayVoteSender,
         cost_tracker: Arc<RwLock<CostTracker>>,
+        bank_forks: &Arc<RwLock<BankForks>>,
+        forwarding_config: ForwardingConfig,
     ) -> Self {
         Self::new_num_threads(
             cluster_info,
@@ -278,9 +285,12 @@ impl BankingStage {
             transaction_status_sender,
             gossip_vote_sender,
             cost_tracker,
+            bank_forks,
+            forwarding_config,
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn new_num_threads(
         cluster_info: &Arc<ClusterInfo>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
@@ -290,6 +300,8 @@ impl BankingStage {
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: ReplayVoteSender,
         cost_tracker: Arc<RwLock<CostTracker>>,
+        bank_forks: &Arc<RwLock<BankForks>>,
+        forwarding_config: ForwardingConfig,
     ) -> Self {
         let batch_limit = TOTAL_BUFFERED_PACKETS / ((num_threads - 1) as usize * PACKETS_PER_BATCH);
         // Single thread to generate entries from many banks.
@@ -299,6 +311,8 @@ impl BankingStage {
             LruCache::new(DEFAULT_LRU_SIZE),
             PacketHasher::default(),
         )));
+        // Forwarding bandwidth is shared by all banking threads
+        let forwarder = Arc::new(PacketForwarder::new(forwarding_config, bank_forks.clone()));
         // Many banks that process transactions in parallel.
         let bank_thread_hdls: Vec<JoinHandle<()>> = (0..num_threads)
             .map(|i| {
@@ -316,6 +330,7 @@ impl BankingStage {
                 let gossip_vote_sender = gossip_vote_sender.clone();
                 let duplicates = duplicates.clone();
                 let cost_tracker = cost_tracker.clone();
+                let forwarder = forwarder.clone();
                 Builder::new()
                     .name("solana-banking-stage-tx".to_string())
                     .spawn(move || {
@@ -331,6 +346,7 @@ impl BankingStage {
                             gossip_vote_sender,
                             &duplicates,
                             &cost_tracker,
+                            &forwarder,
                         );
                     })
                     .unwrap()
@@ -339,29 +355,104 @@ impl BankingStage {
         Self { bank_thread_hdls }
     }
 
-    fn filter_valid_packets_for_forwarding<'a>(
-        all_packets: impl Iterator<Item = &'a PacketsAndOffsets>,
-    ) -> Vec<&'a Packet> {
-        all_packets
-            .filter(|(_p, _indexes, forwarded)| !forwarded)
-            .flat_map(|(p, valid_indexes, _forwarded)| {
-                valid_indexes.iter().map(move |x| &p.packets[*x])
+    /// Create the stage with a central scheduler thread. The scheduler queues transactions by
+    /// fee per compute unit and hands `num_threads - 2` banking threads only batches that do not
+    /// contend for account locks with any other batch in flight. Vote transactions keep their
+    /// own thread.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_scheduler(
+        cluster_info: &Arc<ClusterInfo>,
+        poh_recorder: &Arc<Mutex<PohRecorder>>,
+        verified_receiver: CrossbeamReceiver<Vec<Packets>>,
+        verified_vote_receiver: CrossbeamReceiver<Vec<Packets>>,
+        num_threads: u32,
+        transaction_status_sender: Option<TransactionStatusSender>,
+        gossip_vote_sender: ReplayVoteSender,
+        cost_tracker: Arc<RwLock<CostTracker>>,
+        bank_forks: &Arc<RwLock<BankForks>>,
+        forwarding_config: ForwardingConfig,
+    ) -> Self {
+        let forwarder = Arc::new(PacketForwarder::new(forwarding_config, bank_forks.clone()));
+        let num_workers = cmp::max(num_threads.saturating_sub(2), 1);
+        let (scheduled_sender, scheduled_receiver) = bounded(num_workers as usize);
+        let (completed_sender, completed_receiver) = unbounded();
+        let mut bank_thread_hdls: Vec<JoinHandle<()>> = (0..num_workers)
+            .map(|i| {
+                let scheduled_receiver = scheduled_receiver.clone();
+                let completed_sender = completed_sender.clone();
+                let poh_recorder = poh_recorder.clone();
+                let transaction_status_sender = transaction_status_sender.clone();
+                let gossip_vote_sender = gossip_vote_sender.clone();
+                Builder::new()
+                    .name("solana-banking-stage-tx".to_string())
+                    .spawn(move || {
+                        Self::scheduled_batch_loop(
+                            &scheduled_receiver,
+                            &completed_sender,
+                            &poh_recorder,
+                            i,
+                            transaction_status_sender,
+                            gossip_vote_sender,
+                        );
+                    })
+                    .unwrap()
             })
-            .collect()
-    }
+            .collect();
 
-    fn forward_buffered_packets(
-        socket: &std::net::UdpSocket,
-        tpu_forwards: &std::net::SocketAddr,
-        unprocessed_packets: &UnprocessedPackets,
-    ) -> std::io::Result<()> {
-        let packets = Self::filter_valid_packets_for_forwarding(unprocessed_packets.iter());
-        inc_new_counter_info!("banking_stage-forwarded_packets", packets.len());
-        for p in packets {
-            socket.send_to(&p.data[..p.meta.size], &tpu_forwards)?;
-        }
+        let scheduler_id = num_workers;
+        let scheduler_poh_recorder = poh_recorder.clone();
+        let scheduler_cluster_info = cluster_info.clone();
+        let scheduler_cost_tracker = cost_tracker.clone();
+        let scheduler_forwarder = forwarder.clone();
+        bank_thread_hdls.push(
+            Builder::new()
+                .name("solana-banking-stage-scheduler".to_string())
+                .spawn(move || {
+                    Self::scheduler_loop(
+                        &verified_receiver,
+                        &scheduled_sender,
+                        &completed_receiver,
+                        &scheduler_poh_recorder,
+                        &scheduler_cluster_info,
+                        scheduler_id,
+                        &scheduler_cost_tracker,
+                        &scheduler_forwarder,
+                    );
+                })
+                .unwrap(),
+        );
 
-        Ok(())
+        let vote_id = num_workers + 1;
+        let batch_limit = TOTAL_BUFFERED_PACKETS / (vote_id as usize * PACKETS_PER_BATCH);
+        let duplicates = Arc::new(Mutex::new((
+            LruCache::new(DEFAULT_LRU_SIZE),
+            PacketHasher::default(),
+        )));
+        let poh_recorder = poh_recorder.clone();
+        let cluster_info = cluster_info.clone();
+        bank_thread_hdls.push(
+            Builder::new()
+                .name("solana-banking-stage-tx".to_string())
+                .spawn(move || {
+                    Self::process_loop(
+                        &verified_vote_receiver,
+                        &poh_recorder,
+                        &cluster_info,
+                        &mut Instant::now(),
+                        // Disable forwarding of vote transactions, as votes are gossiped
+                        false,
+                        vote_id,
+                        batch_limit,
+                        transaction_status_sender,
+                        gossip_vote_sender,
+                        &duplicates,
+                        &cost_tracker,
+                        &forwarder,
+                    );
+                })
+                .unwrap(),
+        );
+        Self { bank_thread_hdls }
     }
 
     // Returns whether the given `Packets` has any more remaining unprocessed
@@ -534,20 +625,14 @@ impl BankingStage {
         )
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn process_buffered_packets(
+    /// Decides what to do with buffered transactions given where this node is in the leader
+    /// schedule. Also returns the working bank, if any, after resetting the cost tracker for it.
+    fn buffered_packets_decision(
         my_pubkey: &Pubkey,
-        socket: &std::net::UdpSocket,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
-        cluster_info: &ClusterInfo,
-        buffered_packets: &mut UnprocessedPackets,
-        enable_forwarding: bool,
-        transaction_status_sender: Option<TransactionStatusSender>,
-        gossip_vote_sender: &ReplayVoteSender,
-        banking_stage_stats: &BankingStageStats,
-        recorder: &TransactionRecorder,
         cost_tracker: &Arc<RwLock<CostTracker>>,
-    ) -> BufferedPacketsDecision {
+        banking_stage_stats: &BankingStageStats,
+    ) -> (BufferedPacketsDecision, Option<BankStart>) {
         let bank_start;
         let (
             leader_at_slot_offset,
@@ -581,6 +666,29 @@ impl BankingStage {
             would_be_leader,
             would_be_leader_shortly,
         );
+        (decision, bank_start)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn process_buffered_packets(
+        my_pubkey: &Pubkey,
+        forwarder: &PacketForwarder,
+        poh_recorder: &Arc<Mutex<PohRecorder>>,
+        cluster_info: &ClusterInfo,
+        buffered_packets: &mut UnprocessedPackets,
+        enable_forwarding: bool,
+        transaction_status_sender: Option<TransactionStatusSender>,
+        gossip_vote_sender: &ReplayVoteSender,
+        banking_stage_stats: &BankingStageStats,
+        recorder: &TransactionRecorder,
+        cost_tracker: &Arc<RwLock<CostTracker>>,
+    ) -> BufferedPacketsDecision {
+        let (decision, _bank_start) = Self::buffered_packets_decision(
+            my_pubkey,
+            poh_recorder,
+            cost_tracker,
+            banking_stage_stats,
+        );
 
         match decision {
             BufferedPacketsDecision::Consume(max_tx_ingestion_ns) => {
@@ -603,7 +711,7 @@ impl BankingStage {
                     cluster_info,
                     buffered_packets,
                     poh_recorder,
-                    socket,
+                    forwarder,
                     false,
                 );
             }
@@ -613,7 +721,7 @@ impl BankingStage {
                     cluster_info,
                     buffered_packets,
                     poh_recorder,
-                    socket,
+                    forwarder,
                     true,
                 );
             }
@@ -627,7 +735,7 @@ impl BankingStage {
         cluster_info: &ClusterInfo,
         buffered_packets: &mut UnprocessedPackets,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
-        socket: &UdpSocket,
+        forwarder: &PacketForwarder,
         hold: bool,
     ) {
         if !enable_forwarding {
@@ -637,19 +745,45 @@ impl BankingStage {
             return;
         }
 
-        let addr = match next_leader_tpu_forwards(cluster_info, poh_recorder) {
-            Some(addr) => addr,
-            None => return,
-        };
-        let _ = Self::forward_buffered_packets(socket, &addr, buffered_packets);
-        if hold {
-            buffered_packets.retain(|(_, index, _)| !index.is_empty());
-            for (_, _, forwarded) in buffered_packets.iter_mut() {
-                *forwarded = true;
+        let addrs = forwarder.leader_addrs(cluster_info, poh_recorder);
+        let bank = forwarder.working_bank();
+        let mut has_data_budget = !addrs.is_empty();
+        for (msgs, valid_indexes, forwarded) in buffered_packets.iter_mut() {
+            if *forwarded {
+                continue;
+            }
+            if hold && !has_data_budget {
+                // Held packets are still processed once this node leads, nothing to drop
+                break;
+            }
+            let mut unforwarded_indexes = vec![];
+            for index in valid_indexes.iter().copied() {
+                let packet = &mut msgs.packets[index];
+                let is_forwardable = !packet.meta.forward
+                    && Self::sanitized_transaction_from_packet(packet)
+                        .map(|tx| forwarder.is_forwardable(&bank, &tx))
+                        .unwrap_or(false);
+                if !is_forwardable {
+                    continue;
+                }
+                if has_data_budget {
+                    has_data_budget = forwarder.forward_packet(&addrs, packet);
+                }
+                if !packet.meta.forward {
+                    // Out of data budget, forwarded on a later pass
+                    unforwarded_indexes.push(index);
+                }
+            }
+            *forwarded = unforwarded_indexes.is_empty();
+            if !hold {
+                // Only filter what is sent; held packets are still processed once this node leads
+                *valid_indexes = unforwarded_indexes;
             }
-        } else {
-            buffered_packets.clear();
         }
+        // Unless held, packets are kept only until they are forwarded or no longer forwardable
+        buffered_packets.retain(|(_, valid_indexes, forwarded)| {
+            !valid_indexes.is_empty() && (hold || !forwarded)
+        });
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -665,9 +799,9 @@ impl BankingStage {
         gossip_vote_sender: ReplayVoteSender,
         duplicates: &Arc<Mutex<(LruCache<u64, ()>, PacketHasher)>>,
         cost_tracker: &Arc<RwLock<CostTracker>>,
+        forwarder: &PacketForwarder,
     ) {
         let recorder = poh_recorder.lock().unwrap().recorder();
-        let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let mut buffered_packets = VecDeque::with_capacity(batch_limit);
         let banking_stage_stats = BankingStageStats::new(id);
         loop {
@@ -675,7 +809,7 @@ impl BankingStage {
             while !buffered_packets.is_empty() {
                 let decision = Self::process_buffered_packets(
                     &my_pubkey,
-                    &socket,
+                    forwarder,
                     poh_recorder,
                     cluster_info,
                     &mut buffered_packets,
@@ -729,6 +863,258 @@ impl BankingStage {
         }
     }
 
+    fn scheduler_loop(
+        verified_receiver: &CrossbeamReceiver<Vec<Packets>>,
+        scheduled_sender: &CrossbeamSender<(ScheduledBatch, BankStart)>,
+        completed_receiver: &CrossbeamReceiver<(u64, Vec<usize>, Vec<usize>)>,
+        poh_recorder: &Arc<Mutex<PohRecorder>>,
+        cluster_info: &ClusterInfo,
+        id: u32,
+        cost_tracker: &Arc<RwLock<CostTracker>>,
+        forwarder: &PacketForwarder,
+    ) {
+        let mut scheduler = TransactionScheduler::new(TOTAL_BUFFERED_PACKETS);
+        let mut fee_calculator = FeeCalculator::new(DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE);
+        let banking_stage_stats = BankingStageStats::new(id);
+        loop {
+            let recv_timeout = if scheduler.is_empty() {
+                Duration::from_millis(100)
+            } else {
+                Duration::from_millis(10)
+            };
+            select! {
+                recv(verified_receiver) -> mms => match mms {
+                    Ok(mms) => Self::push_scheduled_packets(
+                        &mut scheduler,
+                        mms,
+                        &fee_calculator,
+                        cost_tracker,
+                        &banking_stage_stats,
+                    ),
+                    Err(_) => break,
+                },
+                recv(completed_receiver) -> completed => {
+                    if let Ok((batch_id, committed_indexes, retryable_indexes)) = completed {
+                        scheduler.complete_batch(
+                            batch_id,
+                            &committed_indexes,
+                            &retryable_indexes,
+                            &mut cost_tracker.write().unwrap(),
+                        );
+                    }
+                },
+                default(recv_timeout) => (),
+            }
+            for (batch_id, committed_indexes, retryable_indexes) in completed_receiver.try_iter() {
+                scheduler.complete_batch(
+                    batch_id,
+                    &committed_indexes,
+                    &retryable_indexes,
+                    &mut cost_tracker.write().unwrap(),
+                );
+            }
+
+            if !scheduler.is_empty() {
+                let my_pubkey = cluster_info.id();
+                let (decision, bank_start) = Self::buffered_packets_decision(
+                    &my_pubkey,
+                    poh_recorder,
+                    cost_tracker,
+                    &banking_stage_stats,
+                );
+                match (decision, bank_start) {
+                    (BufferedPacketsDecision::Consume(_), Some(bank_start)) => {
+                        fee_calculator = bank_start.0.last_blockhash_with_fee_calculator().1;
+                        while !scheduled_sender.is_full() {
+                            let batch = scheduler.schedule_batch(
+                                MAX_NUM_TRANSACTIONS_PER_BATCH,
+                                bank_start.0.slot(),
+                                &mut cost_tracker.write().unwrap(),
+                            );
+                            match batch {
+                                Some(batch) => {
+                                    if scheduled_sender.send((batch, bank_start.clone())).is_err() {
+                                        return;
+                                    }
+                                }
+                                None => break,
+                            }
+                        }
+                    }
+                    (BufferedPacketsDecision::Forward, _) => {
+                        Self::forward_scheduled_packets(
+                            &mut scheduler,
+                            cluster_info,
+                            poh_recorder,
+                            forwarder,
+                            false,
+                        );
+                    }
+                    (BufferedPacketsDecision::ForwardAndHold, _) => {
+                        Self::forward_scheduled_packets(
+                            &mut scheduler,
+                            cluster_info,
+                            poh_recorder,
+                            forwarder,
+                            true,
+                        );
+                    }
+                    _ => (),
+                }
+            }
+
+            banking_stage_stats
+                .current_buffered_packets_count
+                .swap(scheduler.len(), Ordering::Relaxed);
+            banking_stage_stats.report(1000);
+        }
+    }
+
+    /// Forwards the scheduler's pending transactions, highest priority first, dropping those no
+    /// longer worth forwarding. Unless `hold`, forwarded transactions are dropped as well, and
+    /// the ones left for lack of data budget are forwarded on a later pass.
+    fn forward_scheduled_packets(
+        scheduler: &mut TransactionScheduler,
+        cluster_info: &ClusterInfo,
+        poh_recorder: &Arc<Mutex<PohRecorder>>,
+        forwarder: &PacketForwarder,
+        hold: bool,
+    ) {
+        let addrs = forwarder.leader_addrs(cluster_info, poh_recorder);
+        let bank = forwarder.working_bank();
+        let mut has_data_budget = !addrs.is_empty();
+        scheduler.retain_by_priority(|pending| {
+            if !forwarder.is_forwardable(&bank, &pending.transaction) {
+                return false;
+            }
+            if has_data_budget {
+                has_data_budget = forwarder.forward_packet(&addrs, &mut pending.packet);
+            }
+            hold || !pending.packet.meta.forward
+        });
+    }
+
+    fn push_scheduled_packets(
+        scheduler: &mut TransactionScheduler,
+        mms: Vec<Packets>,
+        fee_calculator: &FeeCalculator,
+        cost_tracker: &Arc<RwLock<CostTracker>>,
+        banking_stage_stats: &BankingStageStats,
+    ) {
+        let cost_tracker = cost_tracker.read().unwrap();
+        let mut count = 0;
+        let mut newly_buffered_packets_count = 0;
+        let mut dropped_packets_count = 0;
+        for msgs in mms {
+            count += msgs.packets.len();
+            for packet in msgs.packets.iter().filter(|p| !p.meta.discard) {
+                if let Some(tx) = Self::sanitized_transaction_from_packet(packet) {
+                    newly_buffered_packets_count += 1;
+                    dropped_packets_count +=
+                        scheduler.push(tx, packet.clone(), fee_calculator, &cost_tracker);
+                }
+            }
+        }
+        inc_new_counter_debug!("banking_stage-transactions_received", count);
+        inc_new_counter_info!(
+            "banking_stage-scheduler_dropped_packets",
+            dropped_packets_count
+        );
+        banking_stage_stats
+            .process_packets_count
+            .fetch_add(count, Ordering::Relaxed);
+        banking_stage_stats
+            .newly_buffered_packets_count
+            .fetch_add(newly_buffered_packets_count, Ordering::Relaxed);
+    }
+
+    fn scheduled_batch_loop(
+        scheduled_receiver: &CrossbeamReceiver<(ScheduledBatch, BankStart)>,
+        completed_sender: &CrossbeamSender<(u64, Vec<usize>, Vec<usize>)>,
+        poh_recorder: &Arc<Mutex<PohRecorder>>,
+        id: u32,
+        transaction_status_sender: Option<TransactionStatusSender>,
+        gossip_vote_sender: ReplayVoteSender,
+    ) {
+        let recorder = poh_recorder.lock().unwrap().recorder();
+        let banking_stage_stats = BankingStageStats::new(id);
+        while let Ok((batch, (bank, bank_creation_time))) = scheduled_receiver.recv() {
+            let mut process_tx_time = Measure::start("process_tx_time");
+            let (committed_indexes, retryable_indexes) = Self::process_scheduled_batch(
+                &bank,
+                &bank_creation_time,
+                batch.transactions,
+                &recorder,
+                transaction_status_sender.clone(),
+                &gossip_vote_sender,
+            );
+            process_tx_time.stop();
+            let processed = committed_indexes.len();
+            if completed_sender
+                .send((batch.id, committed_indexes, retryable_indexes))
+                .is_err()
+            {
+                break;
+            }
+            banking_stage_stats
+                .new_tx_count
+                .fetch_add(processed, Ordering::Relaxed);
+            banking_stage_stats
+                .transaction_processing_elapsed
+                .fetch_add(process_tx_time.as_us(), Ordering::Relaxed);
+            banking_stage_stats.report(1000);
+        }
+    }
+
+    /// Processes a batch handed out by the scheduler, whose cost the scheduler already reserved.
+    /// Returns the indexes of the transactions committed and of the transactions to retry.
+    fn process_scheduled_batch(
+        bank: &Arc<Bank>,
+        bank_creation_time: &Instant,
+        transactions: Vec<SanitizedTransaction<'static>>,
+        recorder: &TransactionRecorder,
+        transaction_status_sender: Option<TransactionStatusSender>,
+        gossip_vote_sender: &ReplayVoteSender,
+    ) -> (Vec<usize>, Vec<usize>) {
+        if !Bank::should_bank_still_be_processing_txs(bank_creation_time, bank.ns_per_slot) {
+            return (vec![], (0..transactions.len()).collect());
+        }
+        let libsecp256k1_0_5_upgrade_enabled = bank.libsecp256k1_0_5_upgrade_enabled();
+        let (transactions, transaction_indexes): (Vec<_>, Vec<_>) = transactions
+            .into_iter()
+            .enumerate()
+            .filter(|(_, tx)| {
+                tx.verify_precompiles(libsecp256k1_0_5_upgrade_enabled)
+                    .is_ok()
+            })
+            .map(|(index, tx)| (tx, index))
+            .unzip();
+        let (result, retryable_txs) = Self::process_and_record_transactions(
+            bank,
+            &transactions,
+            recorder,
+            0,
+            transaction_status_sender,
+            gossip_vote_sender,
+        );
+        let to_batch_indexes = |indexes: Vec<usize>| -> Vec<usize> {
+            indexes
+                .into_iter()
+                .map(|index| transaction_indexes[index])
+                .collect()
+        };
+        (
+            to_batch_indexes(result.unwrap_or_default()),
+            to_batch_indexes(retryable_txs),
+        )
+    }
+
+    fn sanitized_transaction_from_packet(packet: &Packet) -> Option<SanitizedTransaction<'static>> {
+        let tx: Transaction = limited_deserialize(&packet.data[0..packet.meta.size]).ok()?;
+        let message_hash = Message::hash_raw_message(Self::packet_message(packet)?);
+        SanitizedTransaction::try_create(Cow::Owned(tx), message_hash).ok()
+    }
+
     pub fn num_threads() -> u32 {
         const MIN_THREADS_VOTES: u32 = 1;
         const MIN_THREADS_BANKING: u32 = 1;
@@ -746,7 +1132,7 @@ impl BankingStage {
         txs: impl Iterator<Item = &'a Transaction>,
         results: &[TransactionExecutionResult],
         recorder: &TransactionRecorder,
-    ) -> (Result<usize, PohRecorderError>, Vec<usize>) {
+    ) -> (Result<Vec<usize>, PohRecorderError>, Vec<usize>) {
         let mut processed_generation = Measure::start("record::process_generation");
         let (processed_transactions, processed_transactions_indexes): (Vec<_>, Vec<_>) = results
             .iter()
@@ -795,7 +1181,7 @@ impl BankingStage {
             }
             poh_record.stop();
         }
-        (Ok(num_to_commit), vec![])
+        (Ok(processed_transactions_indexes), vec![])
     }
 
     fn process_and_record_transactions_locked(
@@ -804,7 +1190,7 @@ impl BankingStage {
         batch: &TransactionBatch,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
-    ) -> (Result<usize, PohRecorderError>, Vec<usize>) {
+    ) -> (Result<Vec<usize>, PohRecorderError>, Vec<usize>) {
         let mut load_execute_time = Measure::start("load_execute_time");
         // Use a shorter maximum age when adding transactions into the pipeline.  This will reduce
         // the likelihood of any single thread getting starved and processing old ids.
@@ -840,32 +1226,33 @@ impl BankingStage {
             transaction_status_sender.is_some(),
             transaction_status_sender.is_some(),
             &mut execute_timings,
+            None,
         );
         load_execute_time.stop();
 
         let freeze_lock = bank.freeze_lock();
 
         let mut record_time = Measure::start("record_time");
-        let (num_to_commit, retryable_record_txs) =
+        let (committed_txs, retryable_record_txs) =
             Self::record_transactions(bank.slot(), batch.transactions_iter(), &results, poh);
         inc_new_counter_info!(
             "banking_stage-record_transactions_num_to_commit",
-            *num_to_commit.as_ref().unwrap_or(&0)
+            committed_txs.as_ref().map(Vec::len).unwrap_or(0)
         );
         inc_new_counter_info!(
             "banking_stage-record_transactions_retryable_record_txs",
             retryable_record_txs.len()
         );
         retryable_txs.extend(retryable_record_txs);
-        if num_to_commit.is_err() {
-            return (num_to_commit, retryable_txs);
-        }
+        let committed_txs = match committed_txs {
+            Ok(committed_txs) => committed_txs,
+            Err(err) => return (Err(err), retryable_txs),
+        };
         record_time.stop();
 
         let mut commit_time = Measure::start("commit_time");
         let sanitized_txs = batch.sanitized_transactions();
-        let num_to_commit = num_to_commit.unwrap();
-        if num_to_commit != 0 {
+        if !committed_txs.is_empty() {
             let tx_results = bank.commit_transactions(
                 sanitized_txs,
                 &mut loaded_accounts,
@@ -910,9 +1297,11 @@ impl BankingStage {
             execute_timings
         );
 
-        (Ok(num_to_commit), retryable_txs)
+        (Ok(committed_txs), retryable_txs)
     }
 
+    /// Returns the indexes of the committed transactions, or the error that stopped recording,
+    /// and the indexes of the transactions to retry.
     pub fn process_and_record_transactions(
         bank: &Arc<Bank>,
         txs: &[SanitizedTransaction],
@@ -920,14 +1309,14 @@ impl BankingStage {
         chunk_offset: usize,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
-    ) -> (Result<usize, PohRecorderError>, Vec<usize>) {
+    ) -> (Result<Vec<usize>, PohRecorderError>, Vec<usize>) {
         let mut lock_time = Measure::start("lock_time");
         // Once accounts are locked, other threads cannot encode transactions that will modify the
         // same account state
         let batch = bank.prepare_sanitized_batch(txs);
         lock_time.stop();
 
-        let (result, mut retryable_txs) = Self::process_and_record_transactions_locked(
+        let (mut result, mut retryable_txs) = Self::process_and_record_transactions_locked(
             bank,
             poh,
             &batch,
@@ -935,6 +1324,9 @@ impl BankingStage {
             gossip_vote_sender,
         );
         retryable_txs.iter_mut().for_each(|x| *x += chunk_offset);
+        if let Ok(committed_txs) = &mut result {
+            committed_txs.iter_mut().for_each(|x| *x += chunk_offset);
+        }
 
         let mut unlock_time = Measure::start("unlock_time");
         // Once the accounts are new transactions can enter the pipeline to process them
@@ -955,7 +1347,8 @@ impl BankingStage {
     /// Sends transactions to the bank.
     ///
     /// Returns the number of transactions successfully processed by the bank, which may be less
-    /// than the total number if max PoH height was reached and the bank halted
+    /// than the total number if max PoH height was reached and the bank halted, the indexes of
+    /// the transactions to retry and the indexes of the committed transactions
     fn process_transactions(
         bank: &Arc<Bank>,
         bank_creation_time: &Instant,
@@ -963,9 +1356,10 @@ impl BankingStage {
         poh: &TransactionRecorder,
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
-    ) -> (usize, Vec<usize>) {
+    ) -> (usize, Vec<usize>, Vec<usize>) {
         let mut chunk_start = 0;
         let mut unprocessed_txs = vec![];
+        let mut committed_txs = vec![];
 
         while chunk_start != transactions.len() {
             let chunk_end = std::cmp::min(
@@ -985,6 +1379,9 @@ impl BankingStage {
             // Add the retryable txs (transactions that errored in a way that warrants a retry)
             // to the list of unprocessed txs.
             unprocessed_txs.extend_from_slice(&retryable_txs_in_chunk);
+            if let Ok(committed_txs_in_chunk) = &result {
+                committed_txs.extend_from_slice(committed_txs_in_chunk);
+            }
 
             // If `bank_creation_time` is None, it's a test so ignore the option so
             // allow processing
@@ -1009,7 +1406,7 @@ impl BankingStage {
             chunk_start = chunk_end;
         }
 
-        (chunk_start, unprocessed_txs)
+        (chunk_start, unprocessed_txs, committed_txs)
     }
 
     // This function creates a filter of transaction results with Ok() for every pending
@@ -1054,7 +1451,8 @@ impl BankingStage {
     // This function deserializes packets into transactions, computes the blake3 hash of transaction messages,
     // and verifies secp256k1 instructions. A list of valid transactions are returned with their message hashes
     // and packet indexes.
-    // Also returned is packet indexes for transaction should be retried due to cost limits.
+    // The cost of the returned transactions is reserved in `cost_tracker`, packet indexes for
+    // transaction should be retried due to cost limits are also returned.
     #[allow(clippy::needless_collect)]
     fn transactions_from_packets(
         msgs: &Packets,
@@ -1085,11 +1483,11 @@ impl BankingStage {
 
         let mut cost_tracker_check_time = Measure::start("cost_tracker_check_time");
         let (filtered_transactions, filter_transaction_packet_indexes) = {
-            let cost_tracker_readonly = cost_tracker.read().unwrap();
+            let mut cost_tracker = cost_tracker.write().unwrap();
             verified_transactions_with_packet_indexes
                 .into_iter()
                 .filter_map(|(tx, tx_index)| {
-                    let result = cost_tracker_readonly.would_transaction_fit(&tx);
+                    let result = cost_tracker.try_add_transaction(&tx);
                     if result.is_err() {
                         debug!("transaction {:?} would exceed limit: {:?}", tx, result);
                         retryable_transaction_packet_indexes.push(tx_index);
@@ -1127,24 +1525,12 @@ impl BankingStage {
             Self::prepare_filter_for_pending_transactions(transactions.len(), pending_indexes);
 
         let mut error_counters = ErrorCounters::default();
-        // The following code also checks if the blockhash for a transaction is too old
-        // The check accounts for
-        //  1. Transaction forwarding delay
-        //  2. The slot at which the next leader will actually process the transaction
+        // The following code also checks if the blockhash for a transaction is too old.
         // Drop the transaction if it will expire by the time the next node receives and processes it
-        let api = perf_libs::api();
-        let max_tx_fwd_delay = if api.is_none() {
-            MAX_TRANSACTION_FORWARDING_DELAY
-        } else {
-            MAX_TRANSACTION_FORWARDING_DELAY_GPU
-        };
-
         let results = bank.check_transactions(
             transactions,
             &filter,
-            (MAX_PROCESSING_AGE)
-                .saturating_sub(max_tx_fwd_delay)
-                .saturating_sub(FORWARD_TRANSACTIONS_TO_LEADER_AT_SLOT_OFFSET as usize),
+            max_forwarding_age(),
             &mut error_counters,
         );
 
@@ -1188,7 +1574,7 @@ impl BankingStage {
         let tx_len = transactions.len();
 
         let mut process_tx_time = Measure::start("process_tx_time");
-        let (processed, unprocessed_tx_indexes) = Self::process_transactions(
+        let (processed, unprocessed_tx_indexes, committed_tx_indexes) = Self::process_transactions(
             bank,
             bank_creation_time,
             &transactions,
@@ -1203,13 +1589,14 @@ impl BankingStage {
             unprocessed_tx_count
         );
 
-        // applying cost of processed transactions to shared cost_tracker
+        // releasing the reserved cost of transactions that were not committed
         let mut cost_tracking_time = Measure::start("cost_tracking_time");
-        transactions.iter().enumerate().for_each(|(index, tx)| {
-            if unprocessed_tx_indexes.iter().all(|&i| i != index) {
-                cost_tracker.write().unwrap().add_transaction_cost(tx);
-            }
-        });
+        Self::remove_uncommitted_transaction_costs(
+            bank,
+            &transactions,
+            &committed_tx_indexes,
+            cost_tracker,
+        );
         cost_tracking_time.stop();
 
         let mut filter_pending_packets_time = Measure::start("filter_pending_packets_time");
@@ -1246,6 +1633,21 @@ impl BankingStage {
         (processed, tx_len, filtered_unprocessed_packet_indexes)
     }
 
+    fn remove_uncommitted_transaction_costs(
+        bank: &Bank,
+        transactions: &[SanitizedTransaction],
+        committed_tx_indexes: &[usize],
+        cost_tracker: &RwLock<CostTracker>,
+    ) {
+        let committed_tx_indexes: HashSet<_> = committed_tx_indexes.iter().copied().collect();
+        let mut cost_tracker = cost_tracker.write().unwrap();
+        for (index, tx) in transactions.iter().enumerate() {
+            if !committed_tx_indexes.contains(&index) {
+                cost_tracker.remove_transaction_cost(bank.slot(), tx);
+            }
+        }
+    }
+
     fn filter_unprocessed_packets(
         bank: &Arc<Bank>,
         msgs: &Packets,
@@ -1535,32 +1937,18 @@ pub(crate) fn next_leader_tpu(
     }
 }
 
-fn next_leader_tpu_forwards(
-    cluster_info: &ClusterInfo,
-    poh_recorder: &Arc<Mutex<PohRecorder>>,
-) -> Option<std::net::SocketAddr> {
-    if let Some(leader_pubkey) = poh_recorder
-        .lock()
-        .unwrap()
-        .leader_after_n_slots(FORWARD_TRANSACTIONS_TO_LEADER_AT_SLOT_OFFSET)
-    {
-        cluster_info.lookup_contact_info(&leader_pubkey, |leader| leader.tpu_forwards)
-    } else {
-        None
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cost_model::CostModel;
     use crossbeam_channel::unbounded;
-    use itertools::Itertools;
     use solana_entry::entry::{next_entry, Entry, EntrySlice};
-    use solana_gossip::cluster_info::Node;
+    use solana_gossip::{cluster_info::Node, contact_info::ContactInfo};
     use solana_ledger::{
         blockstore::{entries_to_test_shreds, Blockstore},
-        genesis_utils::{create_genesis_config, GenesisConfigInfo},
+        genesis_utils::{
+            bootstrap_validator_stake_lamports, create_genesis_config,
+            create_genesis_config_with_leader, GenesisConfigInfo,
+        },
         get_tmp_ledger_path,
         leader_schedule_cache::LeaderScheduleCache,
     };
@@ -1570,9 +1958,11 @@ mod tests {
         poh_service::PohService,
     };
     use solana_rpc::transaction_status_service::TransactionStatusService;
+    use solana_runtime::cost_model::CostModel;
     use solana_sdk::{
         hash::Hash,
         instruction::InstructionError,
+        packet::PACKET_DATA_SIZE,
         poh_config::PohConfig,
         signature::{Keypair, Signer},
         system_instruction::SystemError,
@@ -1582,7 +1972,7 @@ mod tests {
     use solana_transaction_status::TransactionWithStatusMeta;
     use std::{
         convert::TryInto,
-        net::SocketAddr,
+        net::{SocketAddr, UdpSocket},
         path::Path,
         sync::{
             atomic::{AtomicBool, Ordering},
@@ -1591,6 +1981,13 @@ mod tests {
         thread::sleep,
     };
 
+    fn new_test_bank_forks(bank: &Arc<Bank>) -> Arc<RwLock<BankForks>> {
+        Arc::new(RwLock::new(BankForks::new_from_banks(
+            std::slice::from_ref(bank),
+            bank.slot(),
+        )))
+    }
+
     #[test]
     fn test_banking_stage_shutdown1() {
         let genesis_config = create_genesis_config(2).genesis_config;
@@ -1618,6 +2015,8 @@ mod tests {
                 Arc::new(RwLock::new(CostTracker::new(Arc::new(RwLock::new(
                     CostModel::default(),
                 ))))),
+                &new_test_bank_forks(&bank),
+                ForwardingConfig::default(),
             );
             drop(verified_sender);
             drop(vote_sender);
@@ -1666,6 +2065,8 @@ mod tests {
                 Arc::new(RwLock::new(CostTracker::new(Arc::new(RwLock::new(
                     CostModel::default(),
                 ))))),
+                &new_test_bank_forks(&bank),
+                ForwardingConfig::default(),
             );
             trace!("sending bank");
             drop(verified_sender);
@@ -1738,6 +2139,8 @@ mod tests {
                 Arc::new(RwLock::new(CostTracker::new(Arc::new(RwLock::new(
                     CostModel::default(),
                 ))))),
+                &new_test_bank_forks(&bank),
+                ForwardingConfig::default(),
             );
 
             // fund another account so we can send 2 good transactions in a single batch.
@@ -1818,6 +2221,84 @@ mod tests {
         Blockstore::destroy(&ledger_path).unwrap();
     }
 
+    #[test]
+    fn test_banking_stage_with_scheduler() {
+        solana_logger::setup();
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_slow_genesis_config(10);
+        let bank = Arc::new(Bank::new_no_wallclock_throttle(&genesis_config));
+        let start_hash = bank.last_blockhash();
+        let (verified_sender, verified_receiver) = unbounded();
+        let (vote_sender, vote_receiver) = unbounded();
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Arc::new(
+                Blockstore::open(&ledger_path)
+                    .expect("Expected to be able to open database ledger"),
+            );
+            let poh_config = PohConfig {
+                // limit tick count to avoid clearing working_bank at PohRecord then
+                // PohRecorderError(MaxHeightReached) at BankingStage
+                target_tick_count: Some(bank.max_tick_height() - 1),
+                ..PohConfig::default()
+            };
+            let (exit, poh_recorder, poh_service, entry_receiver) =
+                create_test_recorder(&bank, &blockstore, Some(poh_config));
+            let cluster_info = ClusterInfo::new_with_invalid_keypair(Node::new_localhost().info);
+            let cluster_info = Arc::new(cluster_info);
+            let (gossip_vote_sender, _gossip_vote_receiver) = unbounded();
+
+            let banking_stage = BankingStage::new_with_scheduler(
+                &cluster_info,
+                &poh_recorder,
+                verified_receiver,
+                vote_receiver,
+                4,
+                None,
+                gossip_vote_sender,
+                Arc::new(RwLock::new(CostTracker::new(Arc::new(RwLock::new(
+                    CostModel::default(),
+                ))))),
+                &new_test_bank_forks(&bank),
+                ForwardingConfig::default(),
+            );
+
+            // All transfers write the mint account, so they are scheduled one at a time
+            let recipients: Vec<_> = (0..3).map(|_| solana_sdk::pubkey::new_rand()).collect();
+            let transactions: Vec<_> = recipients
+                .iter()
+                .map(|to| system_transaction::transfer(&mint_keypair, to, 1, start_hash))
+                .collect();
+            let packets = to_packets_chunked(&transactions, 3);
+            assert_eq!(packets.len(), 1);
+            verified_sender.send(packets).unwrap();
+
+            let mut blockhash = start_hash;
+            let bank = Arc::new(Bank::new_no_wallclock_throttle(&genesis_config));
+            while recipients.iter().any(|to| bank.get_balance(to) == 0) {
+                let (_bank, (entry, _tick_height)) = entry_receiver.recv().unwrap();
+                assert!(entry.verify(&blockhash));
+                blockhash = entry.hash;
+                bank.process_transactions(entry.transactions.iter())
+                    .iter()
+                    .for_each(|x| assert_eq!(*x, Ok(())));
+            }
+            assert!(recipients.iter().all(|to| bank.get_balance(to) == 1));
+
+            drop(verified_sender);
+            drop(vote_sender);
+            banking_stage.join().unwrap();
+            exit.store(true, Ordering::Relaxed);
+            poh_service.join().unwrap();
+            drop(poh_recorder);
+            drop(entry_receiver);
+        }
+        Blockstore::destroy(&ledger_path).unwrap();
+    }
+
     #[test]
     fn test_banking_stage_entryfication() {
         solana_logger::setup();
@@ -1889,6 +2370,8 @@ mod tests {
                     Arc::new(RwLock::new(CostTracker::new(Arc::new(RwLock::new(
                         CostModel::default(),
                     ))))),
+                    &new_test_bank_forks(&bank),
+                    ForwardingConfig::default(),
                 );
 
                 // wait for banking_stage to eat the packets
@@ -2010,7 +2493,7 @@ mod tests {
                 &results,
                 &recorder,
             );
-            res.unwrap();
+            assert_eq!(res.unwrap(), vec![1]);
             assert!(retryable.is_empty());
             let (_bank, (entry, _tick_height)) = entry_receiver.recv().unwrap();
             assert_eq!(entry.transactions.len(), transactions.len() - 1);
@@ -2398,44 +2881,178 @@ mod tests {
     }
 
     #[test]
-    fn test_filter_valid_packets() {
+    fn test_handle_forwarding() {
         solana_logger::setup();
+        let leader = solana_sdk::pubkey::new_rand();
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config_with_leader(
+            10_000,
+            &leader,
+            bootstrap_validator_stake_lamports(),
+        );
+        let bank = Arc::new(Bank::new_no_wallclock_throttle(&genesis_config));
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Arc::new(
+                Blockstore::open(&ledger_path)
+                    .expect("Expected to be able to open database ledger"),
+            );
+            let (exit, poh_recorder, poh_service, _entry_receiver) =
+                create_test_recorder(&bank, &blockstore, None);
+            let cluster_info = ClusterInfo::new_with_invalid_keypair(Node::new_localhost().info);
+            let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+            receiver
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .unwrap();
+            let mut leader_info = ContactInfo::new_localhost(&leader, timestamp());
+            leader_info.tpu_forwards = receiver.local_addr().unwrap();
+            cluster_info.insert_info(leader_info);
+            let forwarder =
+                PacketForwarder::new(ForwardingConfig::default(), new_test_bank_forks(&bank));
+
+            let to = solana_sdk::pubkey::new_rand();
+            let fresh = system_transaction::transfer(&mint_keypair, &to, 1, bank.last_blockhash());
+            let stale = system_transaction::transfer(&mint_keypair, &to, 2, Hash::new_unique());
+            let relayed =
+                system_transaction::transfer(&mint_keypair, &to, 3, bank.last_blockhash());
+            let mut packets = to_packets_chunked(&[fresh, stale, relayed], 3)
+                .pop()
+                .unwrap();
+            // Arrived through the TPU forwards port, so it was forwarded already
+            packets.packets[2].meta.forward = true;
+            let mut buffered_packets: UnprocessedPackets =
+                vec![(packets, vec![0, 1, 2], false)].into_iter().collect();
 
-        let mut all_packets = (0..16)
-            .map(|packets_id| {
-                let packets = Packets::new(
-                    (0..32)
-                        .map(|packet_id| {
-                            let mut p = Packet::default();
-                            p.meta.port = packets_id << 8 | packet_id;
-                            p
-                        })
-                        .collect_vec(),
+            for _ in 0..2 {
+                BankingStage::handle_forwarding(
+                    true,
+                    &cluster_info,
+                    &mut buffered_packets,
+                    &poh_recorder,
+                    &forwarder,
+                    true,
                 );
-                let valid_indexes = (0..32)
-                    .filter_map(|x| if x % 2 != 0 { Some(x as usize) } else { None })
-                    .collect_vec();
-                (packets, valid_indexes, false)
-            })
-            .collect_vec();
+                // The stale transaction is not forwarded, but all of them are held
+                assert_eq!(buffered_packets.len(), 1);
+                assert_eq!(buffered_packets[0].1, vec![0, 1, 2]);
+                assert!(buffered_packets[0].2);
+            }
 
-        let result = BankingStage::filter_valid_packets_for_forwarding(all_packets.iter());
+            // Only the fresh transaction was sent, and only once
+            let mut buf = [0u8; PACKET_DATA_SIZE];
+            let (size, _) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..size], &buffered_packets[0].0.packets[0].data[..size]);
+            assert!(receiver.recv_from(&mut buf).is_err());
 
-        assert_eq!(result.len(), 256);
+            BankingStage::handle_forwarding(
+                true,
+                &cluster_info,
+                &mut buffered_packets,
+                &poh_recorder,
+                &forwarder,
+                false,
+            );
+            assert!(buffered_packets.is_empty());
 
-        let _ = result
-            .into_iter()
-            .enumerate()
-            .map(|(index, p)| {
-                let packets_id = index / 16;
-                let packet_id = (index % 16) * 2 + 1;
-                assert_eq!(p.meta.port, (packets_id << 8 | packet_id) as u16);
-            })
-            .collect_vec();
+            exit.store(true, Ordering::Relaxed);
+            poh_service.join().unwrap();
+        }
+        Blockstore::destroy(&ledger_path).unwrap();
+    }
+
+    #[test]
+    fn test_handle_forwarding_budget_exhausted() {
+        solana_logger::setup();
+        let leader = solana_sdk::pubkey::new_rand();
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config_with_leader(
+            10_000,
+            &leader,
+            bootstrap_validator_stake_lamports(),
+        );
+        let bank = Arc::new(Bank::new_no_wallclock_throttle(&genesis_config));
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Arc::new(
+                Blockstore::open(&ledger_path)
+                    .expect("Expected to be able to open database ledger"),
+            );
+            let (exit, poh_recorder, poh_service, _entry_receiver) =
+                create_test_recorder(&bank, &blockstore, None);
+            let cluster_info = ClusterInfo::new_with_invalid_keypair(Node::new_localhost().info);
+            let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+            receiver
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .unwrap();
+            let mut leader_info = ContactInfo::new_localhost(&leader, timestamp());
+            leader_info.tpu_forwards = receiver.local_addr().unwrap();
+            cluster_info.insert_info(leader_info);
 
-        all_packets[0].2 = true;
-        let result = BankingStage::filter_valid_packets_for_forwarding(all_packets.iter());
-        assert_eq!(result.len(), 240);
+            let to = solana_sdk::pubkey::new_rand();
+            let transactions: Vec<_> = (1..=2)
+                .map(|lamports| {
+                    system_transaction::transfer(
+                        &mint_keypair,
+                        &to,
+                        lamports,
+                        bank.last_blockhash(),
+                    )
+                })
+                .collect();
+            let packets = to_packets_chunked(&transactions, 2).pop().unwrap();
+            let second_packet = packets.packets[1].data[..packets.packets[1].meta.size].to_vec();
+            // Each 100ms interval's worth of budget fits a single packet
+            let forwarder = PacketForwarder::new(
+                ForwardingConfig {
+                    max_bytes_per_second: packets.packets[0].meta.size * 10,
+                    ..ForwardingConfig::default()
+                },
+                new_test_bank_forks(&bank),
+            );
+            let mut buffered_packets: UnprocessedPackets =
+                vec![(packets, vec![0, 1], false)].into_iter().collect();
+
+            BankingStage::handle_forwarding(
+                true,
+                &cluster_info,
+                &mut buffered_packets,
+                &poh_recorder,
+                &forwarder,
+                false,
+            );
+            // The packet left over once the budget ran out is kept for the next pass
+            assert_eq!(buffered_packets.len(), 1);
+            assert_eq!(buffered_packets[0].1, vec![1]);
+            assert!(!buffered_packets[0].2);
+            let mut buf = [0u8; PACKET_DATA_SIZE];
+            let (size, _) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..size], &buffered_packets[0].0.packets[0].data[..size]);
+            assert!(receiver.recv_from(&mut buf).is_err());
+
+            sleep(Duration::from_millis(150));
+            BankingStage::handle_forwarding(
+                true,
+                &cluster_info,
+                &mut buffered_packets,
+                &poh_recorder,
+                &forwarder,
+                false,
+            );
+            assert!(buffered_packets.is_empty());
+            let (size, _) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..size], &second_packet[..]);
+            assert!(receiver.recv_from(&mut buf).is_err());
+
+            exit.store(true, Ordering::Relaxed);
+            poh_service.join().unwrap();
+        }
+        Blockstore::destroy(&ledger_path).unwrap();
     }
 
     #[test]
@@ -2482,7 +3099,7 @@ mod tests {
 
             let (gossip_vote_sender, _gossip_vote_receiver) = unbounded();
 
-            let (processed_transactions_count, mut retryable_txs) =
+            let (processed_transactions_count, mut retryable_txs, committed_txs) =
                 BankingStage::process_transactions(
                     &bank,
                     &Instant::now(),
@@ -2493,6 +3110,7 @@ mod tests {
                 );
 
             assert_eq!(processed_transactions_count, 0,);
+            assert!(committed_txs.is_empty());
 
             retryable_txs.sort_unstable();
             let expected: Vec<usize> = (0..transactions.len()).collect();
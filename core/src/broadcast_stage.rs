@@ -485,7 +485,8 @@ pub mod test {
         let coding_shreds = Shredder::data_shreds_to_coding_shreds(
             &keypair,
             &data_shreds[0..],
-            true, // is_last_in_slot
+            true,    // is_last_in_slot
+            Some(0), // next_code_index
             &mut ProcessShredsStats::default(),
         )
         .unwrap();
@@ -3,6 +3,7 @@ use solana_entry::entry::Entry;
 use solana_ledger::shred::Shredder;
 use solana_runtime::blockhash_queue::BlockhashQueue;
 use solana_sdk::{
+    feature_set,
     hash::Hash,
     signature::{Keypair, Signer},
     system_transaction,
@@ -203,6 +204,9 @@ impl BroadcastRun for BroadcastDuplicatesRun {
             .map(|(original_last_entry, _)| original_last_entry.hash)
             .or_else(|| Some(receive_results.entries.last().unwrap().hash));
 
+        let variable_erasure_batch_size = bank
+            .feature_set
+            .is_active(&feature_set::variable_erasure_batch_size::id());
         let shredder = Shredder::new(
             bank.slot(),
             bank.parent().unwrap().slot(),
@@ -216,28 +220,44 @@ impl BroadcastRun for BroadcastDuplicatesRun {
             &receive_results.entries,
             last_tick_height == bank.max_tick_height() && last_entries.is_none(),
             self.next_shred_index,
+            variable_erasure_batch_size.then(|| self.next_shred_index),
         );
 
         self.next_shred_index += data_shreds.len() as u32;
-        let last_shreds = last_entries.map(|(original_last_entry, duplicate_extra_last_entries)| {
-            let (original_last_data_shred, _, _) =
-                shredder.entries_to_shreds(keypair, &[original_last_entry], true, self.next_shred_index);
+        let last_shreds =
+            last_entries.map(|(original_last_entry, duplicate_extra_last_entries)| {
+                let (original_last_data_shred, _, _) = shredder.entries_to_shreds(
+                    keypair,
+                    &[original_last_entry],
+                    true,
+                    self.next_shred_index,
+                    variable_erasure_batch_size.then(|| self.next_shred_index),
+                );
 
-            let (partition_last_data_shred, _, _) =
+                let (partition_last_data_shred, _, _) =
                 // Don't mark the last shred as last so that validators won't know that
                 // they've gotten all the shreds, and will continue trying to repair
-                shredder.entries_to_shreds(keypair, &duplicate_extra_last_entries, true, self.next_shred_index);
+                shredder.entries_to_shreds(
+                    keypair,
+                    &duplicate_extra_last_entries,
+                    true,
+                    self.next_shred_index,
+                    variable_erasure_batch_size.then(|| self.next_shred_index),
+                );
 
-                let sigs: Vec<_> = partition_last_data_shred.iter().map(|s| (s.signature(), s.index())).collect();
+                let sigs: Vec<_> = partition_last_data_shred
+                    .iter()
+                    .map(|s| (s.signature(), s.index()))
+                    .collect();
                 info!(
                     "duplicate signatures for slot {}, sigs: {:?}",
                     bank.slot(),
                     sigs,
                 );
 
-            self.next_shred_index += 1;
-            (original_last_data_shred, partition_last_data_shred)
-        });
+                self.next_shred_index += 1;
+                (original_last_data_shred, partition_last_data_shred)
+            });
 
         let data_shreds = Arc::new(data_shreds);
         blockstore_sender.send((data_shreds.clone(), None))?;
@@ -1,8 +1,8 @@
 use super::*;
 use solana_entry::entry::Entry;
 use solana_ledger::shred::Shredder;
-use solana_sdk::hash::Hash;
 use solana_sdk::signature::Keypair;
+use solana_sdk::{feature_set, hash::Hash};
 
 #[derive(Clone)]
 pub(super) struct BroadcastFakeShredsRun {
@@ -43,6 +43,9 @@ impl BroadcastRun for BroadcastFakeShredsRun {
 
         let num_entries = receive_results.entries.len();
 
+        let variable_erasure_batch_size = bank
+            .feature_set
+            .is_active(&feature_set::variable_erasure_batch_size::id());
         let shredder = Shredder::new(
             bank.slot(),
             bank.parent().unwrap().slot(),
@@ -56,6 +59,7 @@ impl BroadcastRun for BroadcastFakeShredsRun {
             &receive_results.entries,
             last_tick_height == bank.max_tick_height(),
             next_shred_index,
+            variable_erasure_batch_size.then(|| next_shred_index),
         );
 
         // If the last blockhash is default, a new block is being created
@@ -73,6 +77,7 @@ impl BroadcastRun for BroadcastFakeShredsRun {
             &fake_entries,
             last_tick_height == bank.max_tick_height(),
             next_shred_index,
+            variable_erasure_batch_size.then(|| next_shred_index),
         );
 
         // If it's the last tick, reset the last block hash to default
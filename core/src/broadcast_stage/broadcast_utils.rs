@@ -20,12 +20,18 @@ pub(super) struct ReceiveResults {
 #[derive(Clone)]
 pub struct UnfinishedSlotInfo {
     pub next_shred_index: u32,
+    // None if the slot's coding shreds follow the legacy erasure batch layout.
+    // See Shredder::data_shreds_to_coding_shreds.
+    pub(crate) next_code_index: Option<u32>,
     pub slot: Slot,
     pub parent: Slot,
     // Data shreds buffered to make a batch of size
     // MAX_DATA_SHREDS_PER_FEC_BLOCK.
     pub(crate) data_shreds_buffer: Vec<Shred>,
     pub(crate) fec_set_offset: u32, // See Shredder::fec_set_index.
+    // Whether the slot is broadcast as Merkle shreds, in which case the
+    // buffered data shreds are not signed yet.
+    pub(crate) merkle_variant: bool,
 }
 
 /// This parameter tunes how many entries are received in one iteration of recv loop
@@ -1,8 +1,8 @@
 use super::*;
 use crate::cluster_nodes::ClusterNodes;
 use solana_ledger::shred::Shredder;
-use solana_sdk::hash::Hash;
 use solana_sdk::signature::Keypair;
+use solana_sdk::{feature_set, hash::Hash};
 use std::{thread::sleep, time::Duration};
 
 pub const NUM_BAD_SLOTS: u64 = 10;
@@ -68,6 +68,9 @@ impl BroadcastRun for FailEntryVerificationBroadcastRun {
             }
         };
 
+        let variable_erasure_batch_size = bank
+            .feature_set
+            .is_active(&feature_set::variable_erasure_batch_size::id());
         let shredder = Shredder::new(
             bank.slot(),
             bank.parent().unwrap().slot(),
@@ -81,17 +84,29 @@ impl BroadcastRun for FailEntryVerificationBroadcastRun {
             &receive_results.entries,
             last_tick_height == bank.max_tick_height() && last_entries.is_none(),
             self.next_shred_index,
+            variable_erasure_batch_size.then(|| self.next_shred_index),
         );
 
         self.next_shred_index += data_shreds.len() as u32;
         let last_shreds = last_entries.map(|(good_last_entry, bad_last_entry)| {
-            let (good_last_data_shred, _, _) =
-                shredder.entries_to_shreds(keypair, &[good_last_entry], true, self.next_shred_index);
+            let (good_last_data_shred, _, _) = shredder.entries_to_shreds(
+                keypair,
+                &[good_last_entry],
+                true,
+                self.next_shred_index,
+                variable_erasure_batch_size.then(|| self.next_shred_index),
+            );
 
             let (bad_last_data_shred, _, _) =
                 // Don't mark the last shred as last so that validators won't know that
                 // they've gotten all the shreds, and will continue trying to repair
-                shredder.entries_to_shreds(keypair, &[bad_last_entry], false, self.next_shred_index);
+                shredder.entries_to_shreds(
+                    keypair,
+                    &[bad_last_entry],
+                    false,
+                    self.next_shred_index,
+                    variable_erasure_batch_size.then(|| self.next_shred_index),
+                );
 
             self.next_shred_index += 1;
             (good_last_data_shred, bad_last_data_shred)
@@ -7,9 +7,10 @@ use super::{
 use crate::{broadcast_stage::broadcast_utils::UnfinishedSlotInfo, cluster_nodes::ClusterNodes};
 use solana_entry::entry::Entry;
 use solana_ledger::shred::{
-    ProcessShredsStats, Shred, Shredder, MAX_DATA_SHREDS_PER_FEC_BLOCK, SHRED_TICK_REFERENCE_MASK,
+    enable_merkle_shreds, ProcessShredsStats, Shred, Shredder, MAX_DATA_SHREDS_PER_FEC_BLOCK,
+    SHRED_TICK_REFERENCE_MASK,
 };
-use solana_sdk::{pubkey::Pubkey, signature::Keypair, timing::duration_as_us};
+use solana_sdk::{feature_set, pubkey::Pubkey, signature::Keypair, timing::duration_as_us};
 use std::{collections::HashMap, sync::RwLock, time::Duration};
 
 #[derive(Clone)]
@@ -73,15 +74,28 @@ impl StandardBroadcastRun {
                     self.shred_version,
                     fec_set_index.unwrap(),
                 );
-                Shredder::sign_shred(keypair, &mut shred);
-                state.data_shreds_buffer.push(shred.clone());
-                let mut shreds = make_coding_shreds(
-                    keypair,
-                    &mut self.unfinished_slot,
-                    true, // is_last_in_slot
-                    stats,
-                );
-                shreds.insert(0, shred);
+                let shreds = if state.merkle_variant {
+                    state.data_shreds_buffer.push(shred);
+                    let (mut shreds, coding_shreds) = make_merkle_shreds(
+                        keypair,
+                        &mut self.unfinished_slot,
+                        true, // is_last_in_slot
+                        stats,
+                    );
+                    shreds.extend(coding_shreds);
+                    shreds
+                } else {
+                    Shredder::sign_shred(keypair, &mut shred);
+                    state.data_shreds_buffer.push(shred.clone());
+                    let mut shreds = make_coding_shreds(
+                        keypair,
+                        &mut self.unfinished_slot,
+                        true, // is_last_in_slot
+                        stats,
+                    );
+                    shreds.insert(0, shred);
+                    shreds
+                };
                 self.report_and_reset_stats();
                 self.unfinished_slot = None;
                 shreds
@@ -96,30 +110,46 @@ impl StandardBroadcastRun {
         blockstore: &Blockstore,
         reference_tick: u8,
         is_slot_end: bool,
+        merkle_variant: bool,
+        variable_erasure_batch_size: bool,
         process_stats: &mut ProcessShredsStats,
     ) -> Vec<Shred> {
         let (slot, parent_slot) = self.current_slot_and_parent.unwrap();
-        let (next_shred_index, fec_set_offset) = match &self.unfinished_slot {
-            Some(state) => (state.next_shred_index, state.fec_set_offset),
+        let (next_shred_index, next_code_index, fec_set_offset) = match &self.unfinished_slot {
+            Some(state) => (
+                state.next_shred_index,
+                state.next_code_index,
+                state.fec_set_offset,
+            ),
             None => match blockstore.meta(slot).unwrap() {
                 Some(slot_meta) => {
                     let shreds_consumed = slot_meta.consumed as u32;
-                    (shreds_consumed, shreds_consumed)
+                    let next_code_index = variable_erasure_batch_size.then(|| shreds_consumed);
+                    (shreds_consumed, next_code_index, shreds_consumed)
                 }
-                None => (0, 0),
+                None => (0, variable_erasure_batch_size.then(|| 0), 0),
             },
         };
-        let (data_shreds, next_shred_index) =
-            Shredder::new(slot, parent_slot, reference_tick, self.shred_version)
-                .unwrap()
-                .entries_to_data_shreds(
-                    keypair,
-                    entries,
-                    is_slot_end,
-                    next_shred_index,
-                    fec_set_offset,
-                    process_stats,
-                );
+        let shredder =
+            Shredder::new(slot, parent_slot, reference_tick, self.shred_version).unwrap();
+        let (data_shreds, next_shred_index) = if merkle_variant {
+            shredder.entries_to_merkle_data_shreds(
+                entries,
+                is_slot_end,
+                next_shred_index,
+                fec_set_offset,
+                process_stats,
+            )
+        } else {
+            shredder.entries_to_data_shreds(
+                keypair,
+                entries,
+                is_slot_end,
+                next_shred_index,
+                fec_set_offset,
+                process_stats,
+            )
+        };
         let mut data_shreds_buffer = match &mut self.unfinished_slot {
             Some(state) => {
                 assert_eq!(state.slot, slot);
@@ -130,10 +160,12 @@ impl StandardBroadcastRun {
         data_shreds_buffer.extend(data_shreds.clone());
         self.unfinished_slot = Some(UnfinishedSlotInfo {
             next_shred_index,
+            next_code_index,
             slot,
             parent: parent_slot,
             data_shreds_buffer,
             fec_set_offset,
+            merkle_variant,
         });
         data_shreds
     }
@@ -201,14 +233,33 @@ impl StandardBroadcastRun {
         // 2) Convert entries to shreds and coding shreds
         let is_last_in_slot = last_tick_height == bank.max_tick_height();
         let reference_tick = bank.tick_height() % bank.ticks_per_slot();
+        let merkle_variant = enable_merkle_shreds(bank.slot(), &bank);
+        let variable_erasure_batch_size = bank
+            .feature_set
+            .is_active(&feature_set::variable_erasure_batch_size::id());
         let data_shreds = self.entries_to_data_shreds(
             keypair,
             &receive_results.entries,
             blockstore,
             reference_tick as u8,
             is_last_in_slot,
+            merkle_variant,
+            variable_erasure_batch_size,
             &mut process_stats,
         );
+        // Merkle data shreds are signed along with the coding shreds of their
+        // erasure batch, so they are only sent once the batch is complete.
+        let (data_shreds, merkle_coding_shreds) = if merkle_variant {
+            let (data_shreds, coding_shreds) = make_merkle_shreds(
+                keypair,
+                &mut self.unfinished_slot,
+                is_last_in_slot,
+                &mut process_stats,
+            );
+            (data_shreds, Some(coding_shreds))
+        } else {
+            (data_shreds, None)
+        };
         // Insert the first shred so blockstore stores that the leader started this block
         // This must be done before the blocks are sent out over the wire.
         if !data_shreds.is_empty() && data_shreds[0].index() == 0 {
@@ -264,12 +315,15 @@ impl StandardBroadcastRun {
         blockstore_sender.send((data_shreds, batch_info.clone()))?;
 
         // Create and send coding shreds
-        let coding_shreds = make_coding_shreds(
-            keypair,
-            &mut self.unfinished_slot,
-            is_last_in_slot,
-            &mut process_stats,
-        );
+        let coding_shreds = match merkle_coding_shreds {
+            Some(coding_shreds) => coding_shreds,
+            None => make_coding_shreds(
+                keypair,
+                &mut self.unfinished_slot,
+                is_last_in_slot,
+                &mut process_stats,
+            ),
+        };
         let coding_shreds = Arc::new(coding_shreds);
         socket_sender.send(((stakes, coding_shreds.clone()), batch_info.clone()))?;
         blockstore_sender.send((coding_shreds, batch_info))?;
@@ -429,8 +483,42 @@ fn make_coding_shreds(
     is_slot_end: bool,
     stats: &mut ProcessShredsStats,
 ) -> Vec<Shred> {
-    let data_shreds = match unfinished_slot {
-        None => Vec::default(),
+    let (data_shreds, next_code_index) = take_data_shreds_buffer(unfinished_slot, is_slot_end);
+    let coding_shreds = Shredder::data_shreds_to_coding_shreds(
+        keypair,
+        &data_shreds,
+        is_slot_end,
+        next_code_index,
+        stats,
+    )
+    .unwrap();
+    advance_next_code_index(unfinished_slot, &coding_shreds);
+    coding_shreds
+}
+
+// Consumes data_shreds_buffer returning the signed Merkle data and coding
+// shreds of the completed erasure batches.
+fn make_merkle_shreds(
+    keypair: &Keypair,
+    unfinished_slot: &mut Option<UnfinishedSlotInfo>,
+    is_slot_end: bool,
+    stats: &mut ProcessShredsStats,
+) -> (Vec<Shred>, Vec<Shred>) {
+    let (data_shreds, next_code_index) = take_data_shreds_buffer(unfinished_slot, is_slot_end);
+    let (data_shreds, coding_shreds) =
+        Shredder::make_merkle_shreds(keypair, &data_shreds, is_slot_end, next_code_index, stats);
+    advance_next_code_index(unfinished_slot, &coding_shreds);
+    (data_shreds, coding_shreds)
+}
+
+// Returns the buffered data shreds to generate coding shreds for, along with
+// the index of the first coding shred to generate.
+fn take_data_shreds_buffer(
+    unfinished_slot: &mut Option<UnfinishedSlotInfo>,
+    is_slot_end: bool,
+) -> (Vec<Shred>, Option<u32>) {
+    match unfinished_slot {
+        None => (Vec::default(), None),
         Some(unfinished_slot) => {
             let size = unfinished_slot.data_shreds_buffer.len();
             // Consume a multiple of 32, unless this is the slot end.
@@ -439,13 +527,24 @@ fn make_coding_shreds(
             } else {
                 size % MAX_DATA_SHREDS_PER_FEC_BLOCK as usize
             };
-            unfinished_slot
+            let data_shreds = unfinished_slot
                 .data_shreds_buffer
                 .drain(0..size - offset)
-                .collect()
+                .collect();
+            (data_shreds, unfinished_slot.next_code_index)
         }
-    };
-    Shredder::data_shreds_to_coding_shreds(keypair, &data_shreds, is_slot_end, stats).unwrap()
+    }
+}
+
+fn advance_next_code_index(
+    unfinished_slot: &mut Option<UnfinishedSlotInfo>,
+    coding_shreds: &[Shred],
+) {
+    if let (Some(unfinished_slot), Some(shred)) = (unfinished_slot, coding_shreds.last()) {
+        if let Some(next_code_index) = &mut unfinished_slot.next_code_index {
+            *next_code_index = shred.index() + 1;
+        }
+    }
 }
 
 impl BroadcastRun for StandardBroadcastRun {
@@ -505,7 +604,7 @@ mod test {
     use solana_ledger::{
         blockstore::Blockstore, get_tmp_ledger_path, shred::max_ticks_per_n_shreds,
     };
-    use solana_runtime::bank::Bank;
+    use solana_runtime::{bank::Bank, genesis_utils::activate_all_features};
     use solana_sdk::{
         genesis_config::GenesisConfig,
         signature::{Keypair, Signer},
@@ -538,6 +637,10 @@ mod test {
         let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let mut genesis_config = create_genesis_config(10_000).genesis_config;
         genesis_config.ticks_per_slot = max_ticks_per_n_shreds(num_shreds_per_slot, None) + 1;
+        // Legacy shreds unless a test explicitly activates Merkle shreds.
+        genesis_config
+            .accounts
+            .remove(&feature_set::merkle_shreds::id());
 
         let bank = Bank::new(&genesis_config);
         let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
@@ -564,10 +667,12 @@ mod test {
         let parent = 0;
         run.unfinished_slot = Some(UnfinishedSlotInfo {
             next_shred_index,
+            next_code_index: Some(next_shred_index),
             slot,
             parent,
             data_shreds_buffer: Vec::default(),
             fec_set_offset: next_shred_index,
+            merkle_variant: false,
         });
         run.slot_broadcast_start = Some(Instant::now());
 
@@ -588,6 +693,38 @@ mod test {
         assert!(shred.verify(&keypair.pubkey()));
     }
 
+    #[test]
+    fn test_interrupted_slot_last_merkle_shred() {
+        let keypair = Arc::new(Keypair::new());
+        let mut run = StandardBroadcastRun::new(0);
+        let next_shred_index = 10;
+        let slot = 1;
+        let parent = 0;
+        run.unfinished_slot = Some(UnfinishedSlotInfo {
+            next_shred_index,
+            next_code_index: Some(next_shred_index),
+            slot,
+            parent,
+            data_shreds_buffer: Vec::default(),
+            fec_set_offset: next_shred_index,
+            merkle_variant: true,
+        });
+        run.slot_broadcast_start = Some(Instant::now());
+        run.current_slot_and_parent = Some((4, 2));
+
+        // The last shred is sent along with the coding shreds of its batch.
+        let shreds = run.finish_prev_slot(&keypair, 0, &mut ProcessShredsStats::default());
+        let shred = &shreds[0];
+        assert_eq!(shred.parent(), parent);
+        assert_eq!(shred.slot(), slot);
+        assert_eq!(shred.index(), next_shred_index);
+        assert!(shred.is_data() && shred.last_in_slot());
+        assert!(shreds[1..].iter().all(Shred::is_code));
+        assert!(shreds
+            .iter()
+            .all(|shred| shred.is_merkle() && shred.verify(&keypair.pubkey())));
+    }
+
     #[test]
     fn test_slot_interrupt() {
         // Setup
@@ -767,6 +904,73 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_buffer_merkle_shreds() {
+        let num_shreds_per_slot = 2;
+        let (
+            blockstore,
+            mut genesis_config,
+            _cluster_info,
+            _bank,
+            leader_keypair,
+            _socket,
+            _bank_forks,
+        ) = setup(num_shreds_per_slot);
+        // Merkle shreds are used from the epoch after the feature's activation
+        activate_all_features(&mut genesis_config);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let slot = genesis_config.epoch_schedule.get_first_slot_in_epoch(1);
+        assert!(!enable_merkle_shreds(slot - 1, &bank0));
+        assert!(enable_merkle_shreds(slot, &bank0));
+        let bank = Arc::new(Bank::new_from_parent(
+            &bank0,
+            &leader_keypair.pubkey(),
+            slot,
+        ));
+        let (bsend, brecv) = channel();
+        let (ssend, _srecv) = channel();
+        let mut last_tick_height = 0;
+        let mut standard_broadcast_run = StandardBroadcastRun::new(0);
+        let mut process_ticks = |num_ticks| {
+            let ticks = create_ticks(num_ticks, 0, genesis_config.hash());
+            last_tick_height += (ticks.len() - 1) as u64;
+            let receive_results = ReceiveResults {
+                entries: ticks,
+                time_elapsed: Duration::new(1, 0),
+                bank: bank.clone(),
+                last_tick_height,
+            };
+            standard_broadcast_run
+                .process_receive_results(
+                    &leader_keypair,
+                    &blockstore,
+                    &ssend,
+                    &bsend,
+                    receive_results,
+                )
+                .unwrap();
+        };
+        // Nothing is sent until an erasure batch is complete.
+        process_ticks(100);
+        let mut shreds = Vec::<Shred>::new();
+        while let Ok((recv_shreds, _)) = brecv.recv_timeout(Duration::from_secs(1)) {
+            shreds.extend(recv_shreds.deref().clone());
+        }
+        assert!(shreds.is_empty(), "shreds.len(): {}", shreds.len());
+        process_ticks(1000);
+        while let Ok((recv_shreds, _)) = brecv.recv_timeout(Duration::from_secs(1)) {
+            shreds.extend(recv_shreds.deref().clone());
+        }
+        assert!(shreds.len() >= 64, "shreds.len(): {}", shreds.len());
+        let num_data_shreds = shreds.iter().filter(|shred| shred.is_data()).count();
+        let num_coding_shreds = shreds.iter().filter(|shred| shred.is_code()).count();
+        assert_eq!(num_data_shreds % MAX_DATA_SHREDS_PER_FEC_BLOCK as usize, 0);
+        assert_eq!(num_data_shreds, num_coding_shreds);
+        assert!(shreds
+            .iter()
+            .all(|shred| shred.is_merkle() && shred.verify(&leader_keypair.pubkey())));
+    }
+
     #[test]
     fn test_slot_finish() {
         // Setup
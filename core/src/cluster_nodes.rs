@@ -1,8 +1,12 @@
 use {
-    crate::{broadcast_stage::BroadcastStage, retransmit_stage::RetransmitStage},
+    crate::{
+        broadcast_stage::BroadcastStage,
+        retransmit_stage::RetransmitStage,
+        turbine_tree::{TreePeers, TurbineTree},
+    },
     itertools::Itertools,
     solana_gossip::{
-        cluster_info::{compute_retransmit_peers, ClusterInfo},
+        cluster_info::ClusterInfo,
         contact_info::ContactInfo,
         crds_gossip_pull::CRDS_GOSSIP_PULL_CRDS_TIMEOUT_MS,
         weighted_shuffle::{weighted_best, weighted_shuffle},
@@ -35,6 +39,13 @@ pub struct ClusterNodes<T> {
     _phantom: PhantomData<T>,
 }
 
+pub struct RetransmitPeers<'a> {
+    // True if this node expects the shred on its tvu-forwards socket.
+    pub forwarded: bool,
+    pub tvu: Vec<&'a ContactInfo>,
+    pub tvu_forwards: Vec<&'a ContactInfo>,
+}
+
 impl Node {
     #[inline]
     fn pubkey(&self) -> Pubkey {
@@ -102,13 +113,10 @@ impl ClusterNodes<RetransmitStage> {
 
     pub fn get_retransmit_peers(
         &self,
+        tree: &dyn TurbineTree,
         shred_seed: [u8; 32],
-        fanout: usize,
         slot_leader: Option<Pubkey>,
-    ) -> (
-        Vec<&ContactInfo>, // neighbors
-        Vec<&ContactInfo>, // children
-    ) {
+    ) -> RetransmitPeers {
         // Exclude leader from list of nodes.
         let index = self.index.iter().copied();
         let (weights, index): (Vec<u64>, Vec<usize>) = match slot_leader {
@@ -132,20 +140,18 @@ impl ClusterNodes<RetransmitStage> {
             .iter()
             .position(|i| self.nodes[*i].pubkey() == self.pubkey)
             .unwrap();
-        let (neighbors, children) = compute_retransmit_peers(fanout, self_index, &index);
-        // Assert that the node itself is included in the set of neighbors, at
-        // the right offset.
-        debug_assert_eq!(
-            self.nodes[neighbors[self_index % fanout]].pubkey(),
-            self.pubkey
-        );
-        let get_contact_infos = |index: Vec<usize>| -> Vec<&ContactInfo> {
-            index
+        let TreePeers { tvu, tvu_forwards } = tree.get_peers(self_index, index.len());
+        let get_contact_infos = |peers: Vec<usize>| -> Vec<&ContactInfo> {
+            peers
                 .into_iter()
-                .map(|i| self.nodes[i].contact_info().unwrap())
+                .map(|k| self.nodes[index[k]].contact_info().unwrap())
                 .collect()
         };
-        (get_contact_infos(neighbors), get_contact_infos(children))
+        RetransmitPeers {
+            forwarded: tree.receives_on_forwards(self_index),
+            tvu: get_contact_infos(tvu),
+            tvu_forwards: get_contact_infos(tvu_forwards),
+        }
     }
 }
 
@@ -238,8 +244,10 @@ impl<T> Default for ClusterNodes<T> {
 mod tests {
     use {
         super::*,
+        crate::turbine_tree::{NeighborhoodTree, StakeWeightedTree},
         rand::{seq::SliceRandom, Rng},
         solana_gossip::{
+            cluster_info::compute_retransmit_peers,
             crds_value::{CrdsData, CrdsValue},
             deprecated::{
                 shuffle_peers_and_index, sorted_retransmit_peers_and_stakes,
@@ -374,16 +382,53 @@ mod tests {
         for fanout in 1..200 {
             let (neighbors_indices, children_indices) =
                 compute_retransmit_peers(fanout, self_index, &shuffled_index);
-            let (neighbors, children) =
-                cluster_nodes.get_retransmit_peers(shred_seed, fanout, Some(slot_leader));
+            let RetransmitPeers {
+                forwarded,
+                tvu,
+                tvu_forwards,
+            } = cluster_nodes.get_retransmit_peers(
+                &NeighborhoodTree::new(fanout),
+                shred_seed,
+                Some(slot_leader),
+            );
+            let anchor_node = peers[neighbors_indices[0]].id == this_node.id;
+            assert_eq!(forwarded, !anchor_node);
+            let (neighbors, children) = if anchor_node {
+                (tvu_forwards, tvu)
+            } else {
+                assert!(tvu.is_empty());
+                (Vec::default(), tvu_forwards)
+            };
             assert_eq!(children.len(), children_indices.len());
             for (node, index) in children.into_iter().zip(children_indices) {
                 assert_eq!(*node, peers[index]);
             }
-            assert_eq!(neighbors.len(), neighbors_indices.len());
-            assert_eq!(neighbors[0].id, peers[neighbors_indices[0]].id);
-            for (node, index) in neighbors.into_iter().zip(neighbors_indices).skip(1) {
-                assert_eq!(*node, peers[index]);
+            if anchor_node {
+                // First neighbor is this node itself.
+                assert_eq!(neighbors.len() + 1, neighbors_indices.len());
+                for (node, index) in neighbors
+                    .into_iter()
+                    .zip(neighbors_indices.into_iter().skip(1))
+                {
+                    assert_eq!(*node, peers[index]);
+                }
+            }
+            // The stake-weighted tree only sends to children, on their tvu socket.
+            let RetransmitPeers {
+                forwarded,
+                tvu,
+                tvu_forwards,
+            } = cluster_nodes.get_retransmit_peers(
+                &StakeWeightedTree::new(fanout),
+                shred_seed,
+                Some(slot_leader),
+            );
+            assert!(!forwarded);
+            assert!(tvu_forwards.is_empty());
+            let children_indices = (self_index * fanout + 1..shuffled_index.len()).take(fanout);
+            assert_eq!(tvu.len(), children_indices.len());
+            for (node, index) in tvu.into_iter().zip(children_indices) {
+                assert_eq!(*node, peers[shuffled_index[index]]);
             }
         }
     }
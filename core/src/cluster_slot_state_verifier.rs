@@ -1,4 +1,8 @@
-use crate::{fork_choice::ForkChoice, heaviest_subtree_fork_choice::HeaviestSubtreeForkChoice};
+use crate::{
+    ancestor_hashes_service::{AncestorHashesReplayUpdate, AncestorHashesReplayUpdateSender},
+    fork_choice::ForkChoice,
+    heaviest_subtree_fork_choice::HeaviestSubtreeForkChoice,
+};
 use solana_ledger::blockstore::Blockstore;
 use solana_sdk::{clock::Slot, hash::Hash};
 use std::collections::{BTreeMap, BTreeSet, HashSet};
@@ -188,12 +192,55 @@ impl DuplicateState {
     }
 }
 
+#[derive(PartialEq, Debug)]
+pub struct AncestorHashesAgreedState {
+    // Keep fields private, forces construction
+    // via constructor
+    agreed_hash: Hash,
+    duplicate_confirmed_hash: Option<Hash>,
+    bank_status: BankStatus,
+}
+impl AncestorHashesAgreedState {
+    pub fn new_from_state(
+        slot: Slot,
+        agreed_hash: Hash,
+        gossip_duplicate_confirmed_slots: &GossipDuplicateConfirmedSlots,
+        fork_choice: &mut HeaviestSubtreeForkChoice,
+        is_dead: impl Fn() -> bool,
+        get_hash: impl Fn() -> Option<Hash>,
+    ) -> Self {
+        let bank_status = BankStatus::new(is_dead, get_hash);
+        let duplicate_confirmed_hash = get_duplicate_confirmed_hash_from_state(
+            slot,
+            gossip_duplicate_confirmed_slots,
+            fork_choice,
+            bank_status.bank_hash(),
+        );
+        Self::new(agreed_hash, duplicate_confirmed_hash, bank_status)
+    }
+
+    fn new(
+        agreed_hash: Hash,
+        duplicate_confirmed_hash: Option<Hash>,
+        bank_status: BankStatus,
+    ) -> Self {
+        Self {
+            agreed_hash,
+            duplicate_confirmed_hash,
+            bank_status,
+        }
+    }
+}
+
 #[derive(PartialEq, Debug)]
 pub enum SlotStateUpdate {
     BankFrozen(BankFrozenState),
     DuplicateConfirmed(DuplicateConfirmedState),
     Dead(DeadState),
     Duplicate(DuplicateState),
+    // A sample of the cluster agreed on the hash of this slot in response
+    // to an ancestor hashes repair request
+    AncestorHashesAgreed(AncestorHashesAgreedState),
 }
 
 impl SlotStateUpdate {
@@ -205,6 +252,9 @@ impl SlotStateUpdate {
             }
             SlotStateUpdate::Dead(_) => Some(Hash::default()),
             SlotStateUpdate::Duplicate(duplicate_state) => duplicate_state.bank_status.bank_hash(),
+            SlotStateUpdate::AncestorHashesAgreed(ancestor_hashes_agreed_state) => {
+                ancestor_hashes_agreed_state.bank_status.bank_hash()
+            }
         }
     }
 }
@@ -220,6 +270,8 @@ pub enum ResultingStateChange {
     RepairDuplicateConfirmedVersion(Hash),
     // Hash of our current frozen version of the slot
     DuplicateConfirmedSlotMatchesCluster(Hash),
+    // Ask the cluster for the ancestors of a dead slot
+    SendAncestorHashesReplayUpdate(AncestorHashesReplayUpdate),
 }
 
 impl SlotStateUpdate {
@@ -240,6 +292,9 @@ impl SlotStateUpdate {
                 on_duplicate_confirmed(slot, duplicate_confirmed_state)
             }
             SlotStateUpdate::Duplicate(duplicate_state) => on_duplicate(duplicate_state),
+            SlotStateUpdate::AncestorHashesAgreed(ancestor_hashes_agreed_state) => {
+                on_ancestor_hashes_agreed(slot, ancestor_hashes_agreed_state)
+            }
         }
     }
 }
@@ -301,8 +356,18 @@ fn on_dead_slot(slot: Slot, dead_state: DeadState) -> Vec<ResultingStateChange>
             bank_hash,
             is_dead,
         );
-    } else if is_slot_duplicate {
-        state_changes.push(ResultingStateChange::MarkSlotDuplicate(Hash::default()));
+        state_changes.push(ResultingStateChange::SendAncestorHashesReplayUpdate(
+            AncestorHashesReplayUpdate::DeadDuplicateConfirmed(slot),
+        ));
+    } else {
+        if is_slot_duplicate {
+            state_changes.push(ResultingStateChange::MarkSlotDuplicate(Hash::default()));
+        }
+        // Our version of the slot may chain to the wrong version of some ancestor,
+        // in which case repairing this slot alone would not help
+        state_changes.push(ResultingStateChange::SendAncestorHashesReplayUpdate(
+            AncestorHashesReplayUpdate::Dead(slot),
+        ));
     }
 
     state_changes
@@ -361,6 +426,11 @@ fn on_duplicate_confirmed(
         bank_hash,
         is_dead,
     );
+    if is_dead {
+        state_changes.push(ResultingStateChange::SendAncestorHashesReplayUpdate(
+            AncestorHashesReplayUpdate::DeadDuplicateConfirmed(slot),
+        ));
+    }
 
     state_changes
 }
@@ -394,6 +464,50 @@ fn on_duplicate(duplicate_state: DuplicateState) -> Vec<ResultingStateChange> {
     vec![]
 }
 
+fn on_ancestor_hashes_agreed(
+    slot: Slot,
+    ancestor_hashes_agreed_state: AncestorHashesAgreedState,
+) -> Vec<ResultingStateChange> {
+    let AncestorHashesAgreedState {
+        agreed_hash,
+        duplicate_confirmed_hash,
+        bank_status,
+    } = ancestor_hashes_agreed_state;
+
+    match bank_status {
+        BankStatus::Dead | BankStatus::Frozen(_) => (),
+        // No action to be taken yet
+        BankStatus::Unprocessed => {
+            return vec![];
+        }
+    }
+
+    // If we have seen some version of the slot duplicate confirmed, then the
+    // `SlotStateUpdate::DuplicateConfirmed`, `SlotStateUpdate::BankFrozen`, or
+    // `SlotStateUpdate::Dead` state transitions take precedence over a sample of
+    // the cluster.
+    if duplicate_confirmed_hash.is_some() {
+        return vec![];
+    }
+
+    let bank_hash = bank_status.bank_hash().expect("bank hash must exist");
+    if bank_hash == agreed_hash {
+        return vec![];
+    }
+
+    // Peers only respond to ancestor hashes requests with the ancestors they
+    // saw duplicate confirmed, so treat the agreed upon version as such and
+    // dump our version in order to repair it
+    warn!(
+        "Sampled cluster agreed on slot {} with hash {}, but our version has hash {}",
+        slot, agreed_hash, bank_hash
+    );
+    vec![
+        ResultingStateChange::MarkSlotDuplicate(bank_hash),
+        ResultingStateChange::RepairDuplicateConfirmedVersion(agreed_hash),
+    ]
+}
+
 fn get_duplicate_confirmed_hash_from_state(
     slot: Slot,
     gossip_duplicate_confirmed_slots: &GossipDuplicateConfirmedSlots,
@@ -460,6 +574,7 @@ fn apply_state_changes(
     fork_choice: &mut HeaviestSubtreeForkChoice,
     duplicate_slots_to_repair: &mut DuplicateSlotsToRepair,
     blockstore: &Blockstore,
+    ancestor_hashes_replay_update_sender: &AncestorHashesReplayUpdateSender,
     state_changes: Vec<ResultingStateChange>,
 ) {
     // Handle cases where the bank is frozen, but not duplicate confirmed
@@ -494,6 +609,9 @@ fn apply_state_changes(
                     )
                     .unwrap();
             }
+            ResultingStateChange::SendAncestorHashesReplayUpdate(ancestor_hashes_replay_update) => {
+                let _ = ancestor_hashes_replay_update_sender.send(ancestor_hashes_replay_update);
+            }
         }
     }
 
@@ -510,6 +628,7 @@ pub(crate) fn check_slot_agrees_with_cluster(
     duplicate_slots_tracker: &mut DuplicateSlotsTracker,
     fork_choice: &mut HeaviestSubtreeForkChoice,
     duplicate_slots_to_repair: &mut HashSet<(Slot, Hash)>,
+    ancestor_hashes_replay_update_sender: &AncestorHashesReplayUpdateSender,
     slot_state_update: SlotStateUpdate,
 ) {
     info!(
@@ -539,6 +658,7 @@ pub(crate) fn check_slot_agrees_with_cluster(
         fork_choice,
         duplicate_slots_to_repair,
         blockstore,
+        ancestor_hashes_replay_update_sender,
         state_changes,
     );
 }
@@ -547,6 +667,7 @@ pub(crate) fn check_slot_agrees_with_cluster(
 mod test {
     use super::*;
     use crate::{progress_map::ProgressMap, replay_stage::tests::setup_forks_from_tree};
+    use crossbeam_channel::unbounded;
     use solana_runtime::bank_forks::BankForks;
     use std::{
         collections::{HashMap, HashSet},
@@ -687,7 +808,8 @@ mod test {
                 SlotStateUpdate::DuplicateConfirmed(duplicate_confirmed_state),
                 vec![
                 ResultingStateChange::MarkSlotDuplicate(Hash::default()),
-                ResultingStateChange::RepairDuplicateConfirmedVersion(duplicate_confirmed_hash)],
+                ResultingStateChange::RepairDuplicateConfirmedVersion(duplicate_confirmed_hash),
+                ResultingStateChange::SendAncestorHashesReplayUpdate(AncestorHashesReplayUpdate::DeadDuplicateConfirmed(10))],
             )
         },
         duplicate_confirmed_state_update_2: {
@@ -727,7 +849,8 @@ mod test {
             );
             (
                 SlotStateUpdate::Dead(dead_state),
-                Vec::<ResultingStateChange>::new()
+                vec![
+                ResultingStateChange::SendAncestorHashesReplayUpdate(AncestorHashesReplayUpdate::Dead(10))],
             )
         },
         dead_state_update_1: {
@@ -739,7 +862,9 @@ mod test {
             );
             (
                 SlotStateUpdate::Dead(dead_state),
-                vec![ResultingStateChange::MarkSlotDuplicate(Hash::default())],
+                vec![
+                ResultingStateChange::MarkSlotDuplicate(Hash::default()),
+                ResultingStateChange::SendAncestorHashesReplayUpdate(AncestorHashesReplayUpdate::Dead(10))],
             )
         },
         dead_state_update_2: {
@@ -753,7 +878,8 @@ mod test {
                 SlotStateUpdate::Dead(dead_state),
                 vec![
                 ResultingStateChange::MarkSlotDuplicate(Hash::default()),
-                ResultingStateChange::RepairDuplicateConfirmedVersion(duplicate_confirmed_hash.unwrap())],
+                ResultingStateChange::RepairDuplicateConfirmedVersion(duplicate_confirmed_hash.unwrap()),
+                ResultingStateChange::SendAncestorHashesReplayUpdate(AncestorHashesReplayUpdate::DeadDuplicateConfirmed(10))],
             )
         },
         dead_state_update_3: {
@@ -767,7 +893,8 @@ mod test {
                 SlotStateUpdate::Dead(dead_state),
                 vec![
                 ResultingStateChange::MarkSlotDuplicate(Hash::default()),
-                ResultingStateChange::RepairDuplicateConfirmedVersion(duplicate_confirmed_hash.unwrap())],
+                ResultingStateChange::RepairDuplicateConfirmedVersion(duplicate_confirmed_hash.unwrap()),
+                ResultingStateChange::SendAncestorHashesReplayUpdate(AncestorHashesReplayUpdate::DeadDuplicateConfirmed(10))],
             )
         },
         duplicate_state_update_0: {
@@ -835,6 +962,82 @@ mod test {
                 Vec::<ResultingStateChange>::new(),
             )
         },
+        ancestor_hashes_agreed_state_update_0: {
+            let agreed_hash = Hash::new_unique();
+            let duplicate_confirmed_hash = None;
+            let bank_status = BankStatus::Unprocessed;
+            let ancestor_hashes_agreed_state = AncestorHashesAgreedState::new(
+                agreed_hash,
+                duplicate_confirmed_hash,
+                bank_status,
+            );
+            (
+                SlotStateUpdate::AncestorHashesAgreed(ancestor_hashes_agreed_state),
+                Vec::<ResultingStateChange>::new()
+            )
+        },
+        ancestor_hashes_agreed_state_update_1: {
+            let agreed_hash = Hash::new_unique();
+            let duplicate_confirmed_hash = None;
+            let bank_status = BankStatus::Dead;
+            let ancestor_hashes_agreed_state = AncestorHashesAgreedState::new(
+                agreed_hash,
+                duplicate_confirmed_hash,
+                bank_status,
+            );
+            (
+                SlotStateUpdate::AncestorHashesAgreed(ancestor_hashes_agreed_state),
+                vec![
+                ResultingStateChange::MarkSlotDuplicate(Hash::default()),
+                ResultingStateChange::RepairDuplicateConfirmedVersion(agreed_hash)],
+            )
+        },
+        ancestor_hashes_agreed_state_update_2: {
+            let agreed_hash = Hash::new_unique();
+            let duplicate_confirmed_hash = None;
+            let bank_status = BankStatus::Frozen(agreed_hash);
+            let ancestor_hashes_agreed_state = AncestorHashesAgreedState::new(
+                agreed_hash,
+                duplicate_confirmed_hash,
+                bank_status,
+            );
+            (
+                SlotStateUpdate::AncestorHashesAgreed(ancestor_hashes_agreed_state),
+                Vec::<ResultingStateChange>::new()
+            )
+        },
+        ancestor_hashes_agreed_state_update_3: {
+            let agreed_hash = Hash::new_unique();
+            let frozen_hash = Hash::new_unique();
+            let duplicate_confirmed_hash = None;
+            let bank_status = BankStatus::Frozen(frozen_hash);
+            let ancestor_hashes_agreed_state = AncestorHashesAgreedState::new(
+                agreed_hash,
+                duplicate_confirmed_hash,
+                bank_status,
+            );
+            (
+                SlotStateUpdate::AncestorHashesAgreed(ancestor_hashes_agreed_state),
+                vec![
+                ResultingStateChange::MarkSlotDuplicate(frozen_hash),
+                ResultingStateChange::RepairDuplicateConfirmedVersion(agreed_hash)],
+            )
+        },
+        ancestor_hashes_agreed_state_update_4: {
+            let agreed_hash = Hash::new_unique();
+            let frozen_hash = Hash::new_unique();
+            let duplicate_confirmed_hash = Some(frozen_hash);
+            let bank_status = BankStatus::Frozen(frozen_hash);
+            let ancestor_hashes_agreed_state = AncestorHashesAgreedState::new(
+                agreed_hash,
+                duplicate_confirmed_hash,
+                bank_status,
+            );
+            (
+                SlotStateUpdate::AncestorHashesAgreed(ancestor_hashes_agreed_state),
+                Vec::<ResultingStateChange>::new()
+            )
+        },
     }
 
     struct InitialState {
@@ -876,6 +1079,8 @@ mod test {
             blockstore,
             ..
         } = setup();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
 
         let mut duplicate_slots_to_repair = DuplicateSlotsToRepair::default();
 
@@ -893,6 +1098,7 @@ mod test {
             &mut heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
             &blockstore,
+            &ancestor_hashes_replay_update_sender,
             vec![ResultingStateChange::MarkSlotDuplicate(duplicate_slot_hash)],
         );
         assert!(!heaviest_subtree_fork_choice
@@ -926,6 +1132,7 @@ mod test {
             &mut heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
             &blockstore,
+            &ancestor_hashes_replay_update_sender,
             vec![ResultingStateChange::RepairDuplicateConfirmedVersion(
                 correct_hash,
             )],
@@ -943,6 +1150,8 @@ mod test {
             blockstore,
             ..
         } = setup();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
 
         let duplicate_slot = bank_forks.read().unwrap().root() + 1;
         let duplicate_slot_hash = bank_forks
@@ -961,6 +1170,7 @@ mod test {
             &mut heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
             &blockstore,
+            &ancestor_hashes_replay_update_sender,
             vec![ResultingStateChange::BankFrozen(duplicate_slot_hash)],
         );
         assert_eq!(
@@ -983,6 +1193,7 @@ mod test {
             &mut heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
             &blockstore,
+            &ancestor_hashes_replay_update_sender,
             vec![ResultingStateChange::BankFrozen(new_bank_hash)],
         );
         assert_eq!(
@@ -1003,6 +1214,8 @@ mod test {
             blockstore,
             ..
         } = setup();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
 
         let duplicate_slot = bank_forks.read().unwrap().root() + 1;
         let our_duplicate_slot_hash = bank_forks
@@ -1029,6 +1242,7 @@ mod test {
             &mut heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
             &blockstore,
+            &ancestor_hashes_replay_update_sender,
             state_changes,
         );
         for child_slot in descendants
@@ -1079,6 +1293,8 @@ mod test {
             blockstore,
             ..
         } = setup();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
 
         // Setup a duplicate slot state transition with the initial bank state of the duplicate slot
         // determined by `initial_bank_hash`, which can be:
@@ -1103,6 +1319,7 @@ mod test {
             &mut duplicate_slots_tracker,
             &mut heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::Duplicate(duplicate_state),
         );
         assert!(duplicate_slots_tracker.contains(&duplicate_slot));
@@ -1135,6 +1352,7 @@ mod test {
             &mut duplicate_slots_tracker,
             &mut heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::BankFrozen(bank_frozen_state),
         );
 
@@ -1176,6 +1394,8 @@ mod test {
             blockstore,
             ..
         } = setup();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
 
         let slot3_hash = bank_forks.read().unwrap().get(3).unwrap().hash();
         assert_eq!(
@@ -1201,6 +1421,7 @@ mod test {
             &mut duplicate_slots_tracker,
             &mut heaviest_subtree_fork_choice,
             &mut DuplicateSlotsToRepair::default(),
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::DuplicateConfirmed(duplicate_confirmed_state),
         );
         assert!(heaviest_subtree_fork_choice
@@ -1236,6 +1457,7 @@ mod test {
             &mut duplicate_slots_tracker,
             &mut heaviest_subtree_fork_choice,
             &mut DuplicateSlotsToRepair::default(),
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::Duplicate(duplicate_state),
         );
         assert!(duplicate_slots_tracker.contains(&3));
@@ -1276,6 +1498,8 @@ mod test {
             blockstore,
             ..
         } = setup();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
 
         let slot3_hash = bank_forks.read().unwrap().get(3).unwrap().hash();
         assert_eq!(
@@ -1302,6 +1526,7 @@ mod test {
             &mut duplicate_slots_tracker,
             &mut heaviest_subtree_fork_choice,
             &mut DuplicateSlotsToRepair::default(),
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::Duplicate(duplicate_state),
         );
         assert!(duplicate_slots_tracker.contains(&2));
@@ -1335,6 +1560,7 @@ mod test {
             &mut duplicate_slots_tracker,
             &mut heaviest_subtree_fork_choice,
             &mut DuplicateSlotsToRepair::default(),
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::DuplicateConfirmed(duplicate_confirmed_state),
         );
         for slot in 0..=3 {
@@ -1362,6 +1588,8 @@ mod test {
             blockstore,
             ..
         } = setup();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
 
         let slot3_hash = bank_forks.read().unwrap().get(3).unwrap().hash();
         assert_eq!(
@@ -1387,6 +1615,7 @@ mod test {
             &mut duplicate_slots_tracker,
             &mut heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::DuplicateConfirmed(duplicate_confirmed_state),
         );
         let verify_all_slots_duplicate_confirmed =
@@ -1426,6 +1655,7 @@ mod test {
             &mut duplicate_slots_tracker,
             &mut heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::Duplicate(duplicate_state),
         );
         assert!(duplicate_slots_tracker.contains(&1));
@@ -1,12 +1,12 @@
 //! this service receives instruction ExecuteTimings from replay_stage,
-//! update cost_model which is shared with banking_stage to optimize
-//! packing transactions into block; it also triggers persisting cost
-//! table to blockstore.
+//! update the instruction cost table of cost_model with the observed
+//! execution times; it also triggers persisting cost table to blockstore.
+//! The table is only informational, transactions are charged fixed
+//! per-program compute units.
 
-use crate::cost_model::CostModel;
 use solana_ledger::blockstore::Blockstore;
 use solana_measure::measure::Measure;
-use solana_runtime::bank::ExecuteTimings;
+use solana_runtime::{bank::ExecuteTimings, cost_model::CostModel};
 use solana_sdk::timing::timestamp;
 use std::{
     sync::{
@@ -26,6 +26,23 @@ pub enum DuplicateAncestorDecision {
 }
 
 impl DuplicateAncestorDecision {
+    /// Returns true if the decision was inconclusive, and another sample of
+    /// the cluster should be taken.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            // If we get a bad sample from malicious validators, then retry
+            DuplicateAncestorDecision::InvalidSample
+            // It may be possible the validators have not yet detected duplicate confirmation
+            // so retry
+            | DuplicateAncestorDecision::SampleNotDuplicateConfirmed => true,
+
+            DuplicateAncestorDecision::AncestorsAllMatch
+            | DuplicateAncestorDecision::ContinueSearch(_)
+            | DuplicateAncestorDecision::EarliestAncestorNotFrozen(_)
+            | DuplicateAncestorDecision::EarliestMismatchFound(_) => false,
+        }
+    }
+
     pub fn repair_status(&self) -> Option<&DuplicateSlotRepairStatus> {
         match self {
             DuplicateAncestorDecision::InvalidSample
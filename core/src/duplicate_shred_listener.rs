@@ -0,0 +1,359 @@
+//! The `duplicate_shred_listener` module reassembles the duplicate-slot proofs
+//! other nodes push to gossip, verifies them against the leader schedule, and
+//! stores them in the blockstore. ReplayStage is then signaled so that every
+//! node marks the slot duplicate, not just the nodes which happened to receive
+//! both versions of the shred.
+use crate::window_service::DuplicateSlotSender;
+use solana_gossip::{
+    cluster_info::{ClusterInfo, GOSSIP_SLEEP_MILLIS},
+    crds::Cursor,
+    duplicate_shred::{self, DuplicateShred},
+};
+use solana_ledger::{blockstore::Blockstore, leader_schedule_cache::LeaderScheduleCache};
+use solana_runtime::bank_forks::BankForks;
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+// A proof holds two shreds, which fit within this many gossip chunks.
+const MAX_NUM_CHUNKS: u8 = 4;
+// Limit on the number of partially received proofs kept around.
+const MAX_BUFFERED_PROOFS: usize = 512;
+// Proofs for slots too far past the root can not be verified against the
+// leader schedule and are ignored.
+const MAX_SLOT_DISTANCE_FROM_ROOT: Slot = 10_000;
+
+#[derive(Default)]
+struct DuplicateShredListenerStats {
+    num_chunks: usize,
+    num_invalid_chunks: usize,
+    num_outdated_chunks: usize,
+    num_invalid_proofs: usize,
+    num_new_duplicate_slots: usize,
+    num_pruned_proofs: usize,
+}
+
+impl DuplicateShredListenerStats {
+    fn report(&mut self) {
+        datapoint_info!(
+            "duplicate_shred_listener",
+            ("num_chunks", self.num_chunks, i64),
+            ("num_invalid_chunks", self.num_invalid_chunks, i64),
+            ("num_outdated_chunks", self.num_outdated_chunks, i64),
+            ("num_invalid_proofs", self.num_invalid_proofs, i64),
+            ("num_new_duplicate_slots", self.num_new_duplicate_slots, i64),
+            ("num_pruned_proofs", self.num_pruned_proofs, i64),
+        );
+        *self = DuplicateShredListenerStats::default();
+    }
+}
+
+pub struct DuplicateShredHandler {
+    // Chunks received so far for the proof each node pushed for a slot.
+    buffer: HashMap<(Slot, Pubkey), Vec<Option<DuplicateShred>>>,
+    blockstore: Arc<Blockstore>,
+    leader_schedule_cache: Arc<LeaderScheduleCache>,
+    bank_forks: Arc<RwLock<BankForks>>,
+    duplicate_slots_sender: DuplicateSlotSender,
+    stats: DuplicateShredListenerStats,
+}
+
+impl DuplicateShredHandler {
+    pub fn new(
+        blockstore: Arc<Blockstore>,
+        leader_schedule_cache: Arc<LeaderScheduleCache>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        duplicate_slots_sender: DuplicateSlotSender,
+    ) -> Self {
+        Self {
+            buffer: HashMap::new(),
+            blockstore,
+            leader_schedule_cache,
+            bank_forks,
+            duplicate_slots_sender,
+            stats: DuplicateShredListenerStats::default(),
+        }
+    }
+
+    pub fn handle(&mut self, chunk: DuplicateShred) {
+        self.stats.num_chunks += 1;
+        let slot = chunk.slot();
+        let root = self.bank_forks.read().unwrap().root();
+        if slot <= root
+            || slot > root.saturating_add(MAX_SLOT_DISTANCE_FROM_ROOT)
+            || self.blockstore.has_duplicate_shreds_in_slot(slot)
+        {
+            self.stats.num_outdated_chunks += 1;
+            return;
+        }
+        let num_chunks = chunk.num_chunks();
+        if num_chunks == 0 || num_chunks > MAX_NUM_CHUNKS || chunk.chunk_index() >= num_chunks {
+            self.stats.num_invalid_chunks += 1;
+            return;
+        }
+        let key = (slot, chunk.sender());
+        let chunks = self.buffer.entry(key).or_default();
+        // The node may have pushed a new proof for the same slot, in which
+        // case the chunks of the older proof are discarded.
+        if chunks.len() != num_chunks as usize {
+            *chunks = vec![None; num_chunks as usize];
+        }
+        let chunk_index = chunk.chunk_index() as usize;
+        chunks[chunk_index] = Some(chunk);
+        if chunks.iter().all(Option::is_some) {
+            let chunks = self.buffer.remove(&key).unwrap();
+            self.verify_and_store_proof(slot, chunks.into_iter().flatten());
+        } else if self.buffer.len() > MAX_BUFFERED_PROOFS {
+            self.prune_buffer(root);
+        }
+    }
+
+    fn verify_and_store_proof(&mut self, slot: Slot, chunks: impl Iterator<Item = DuplicateShred>) {
+        let root_bank = self.bank_forks.read().unwrap().root_bank();
+        let leader_schedule_cache = &self.leader_schedule_cache;
+        let leader = |slot| leader_schedule_cache.slot_leader_at(slot, Some(&root_bank));
+        let (shred1, shred2) = match duplicate_shred::into_shreds(chunks, leader) {
+            Ok(shreds) => shreds,
+            Err(err) => {
+                debug!("invalid duplicate slot proof for slot {}: {:?}", slot, err);
+                self.stats.num_invalid_proofs += 1;
+                return;
+            }
+        };
+        if let Err(err) =
+            self.blockstore
+                .store_duplicate_if_not_existing(slot, shred1.payload, shred2.payload)
+        {
+            error!(
+                "failed to store duplicate slot proof for slot {}: {:?}",
+                slot, err
+            );
+            return;
+        }
+        self.stats.num_new_duplicate_slots += 1;
+        // Proofs other nodes pushed for this slot are no longer needed.
+        self.buffer
+            .retain(|(buffered_slot, _), _| *buffered_slot != slot);
+        let _ = self.duplicate_slots_sender.send(slot);
+    }
+
+    // Drops proofs for rooted slots, then the proofs pushed by the nodes with
+    // the least stake until the buffer is back within capacity.
+    fn prune_buffer(&mut self, root: Slot) {
+        let num_proofs = self.buffer.len();
+        self.buffer.retain(|(slot, _), _| *slot > root);
+        if self.buffer.len() > MAX_BUFFERED_PROOFS {
+            let root_bank = self.bank_forks.read().unwrap().root_bank();
+            let stakes = root_bank
+                .epoch_staked_nodes(root_bank.epoch())
+                .unwrap_or_default();
+            let mut keys: Vec<_> = self.buffer.keys().copied().collect();
+            keys.sort_unstable_by_key(|(slot, pubkey)| {
+                let stake = stakes.get(pubkey).copied().unwrap_or_default();
+                (stake, std::cmp::Reverse(*slot))
+            });
+            let num_excess = self.buffer.len() - MAX_BUFFERED_PROOFS / 2;
+            for key in keys.into_iter().take(num_excess) {
+                self.buffer.remove(&key);
+            }
+        }
+        self.stats.num_pruned_proofs += num_proofs - self.buffer.len();
+    }
+}
+
+pub struct DuplicateShredListener {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl DuplicateShredListener {
+    pub fn new(
+        exit: Arc<AtomicBool>,
+        cluster_info: Arc<ClusterInfo>,
+        handler: DuplicateShredHandler,
+    ) -> Self {
+        let thread_hdl = Builder::new()
+            .name("solana-duplicate-shred-listener".to_string())
+            .spawn(move || Self::recv_loop(exit, &cluster_info, handler))
+            .unwrap();
+        Self { thread_hdl }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+
+    fn recv_loop(
+        exit: Arc<AtomicBool>,
+        cluster_info: &ClusterInfo,
+        mut handler: DuplicateShredHandler,
+    ) {
+        let mut cursor = Cursor::default();
+        let mut last_stats_report = Instant::now();
+        while !exit.load(Ordering::Relaxed) {
+            for chunk in cluster_info.get_duplicate_shreds(&mut cursor) {
+                handler.handle(chunk);
+            }
+            if last_stats_report.elapsed().as_secs() > 2 {
+                handler.stats.report();
+                last_stats_report = Instant::now();
+            }
+            sleep(Duration::from_millis(GOSSIP_SLEEP_MILLIS));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::unbounded;
+    use solana_entry::entry::Entry;
+    use solana_gossip::cluster_info::DUPLICATE_SHRED_MAX_PAYLOAD_SIZE;
+    use solana_ledger::{
+        blockstore_meta::DuplicateSlotProof,
+        genesis_utils::{create_genesis_config_with_leader, GenesisConfigInfo},
+        get_tmp_ledger_path_auto_delete,
+        shred::{Shred, Shredder},
+    };
+    use solana_runtime::bank::Bank;
+    use solana_sdk::{
+        hash::Hash,
+        signature::{Keypair, Signer},
+        system_transaction,
+        timing::timestamp,
+    };
+
+    fn new_shred(slot: Slot, keypair: &Keypair) -> Shred {
+        let shredder = Shredder::new(slot, slot.saturating_sub(1), 0, 0).unwrap();
+        let tx = system_transaction::transfer(
+            &Keypair::new(),
+            &Pubkey::new_unique(),
+            1,
+            Hash::new_unique(),
+        );
+        let entries = vec![Entry::new(&Hash::new_unique(), 1, vec![tx])];
+        let (mut data_shreds, _, _) =
+            shredder.entries_to_shreds(keypair, &entries, true, 0, Some(0));
+        data_shreds.swap_remove(0)
+    }
+
+    fn new_proof_chunks(
+        slot: Slot,
+        keypair: &Keypair,
+        sender: Pubkey,
+    ) -> (DuplicateSlotProof, Vec<DuplicateShred>) {
+        let proof = DuplicateSlotProof {
+            shred1: new_shred(slot, keypair).payload,
+            shred2: new_shred(slot, keypair).payload,
+        };
+        let chunks = duplicate_shred::from_duplicate_slot_proof(
+            &proof,
+            sender,
+            None::<fn(Slot) -> Option<Pubkey>>,
+            timestamp(),
+            DUPLICATE_SHRED_MAX_PAYLOAD_SIZE,
+        )
+        .unwrap()
+        .collect();
+        (proof, chunks)
+    }
+
+    #[test]
+    fn test_handle_duplicate_shred_chunks() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Arc::new(Blockstore::open(ledger_path.path()).unwrap());
+        let leader_keypair = Keypair::new();
+        let GenesisConfigInfo { genesis_config, .. } =
+            create_genesis_config_with_leader(10_000, &leader_keypair.pubkey(), 100);
+        let bank = Bank::new(&genesis_config);
+        let leader_schedule_cache = Arc::new(LeaderScheduleCache::new_from_bank(&bank));
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let (duplicate_slots_sender, duplicate_slots_receiver) = unbounded();
+        let mut handler = DuplicateShredHandler::new(
+            blockstore.clone(),
+            leader_schedule_cache,
+            bank_forks,
+            duplicate_slots_sender,
+        );
+
+        // The slot is only marked duplicate once every chunk of the proof
+        // has been received
+        let slot = 5;
+        let (proof, chunks) = new_proof_chunks(slot, &leader_keypair, Pubkey::new_unique());
+        assert!(chunks.len() > 1 && chunks.len() <= MAX_NUM_CHUNKS as usize);
+        let num_chunks = chunks.len();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            assert!(!blockstore.has_duplicate_shreds_in_slot(slot));
+            assert!(duplicate_slots_receiver.try_recv().is_err());
+            handler.handle(chunk);
+            assert_eq!(handler.buffer.is_empty(), i + 1 == num_chunks);
+        }
+        let stored_proof = blockstore.get_duplicate_slot(slot).unwrap();
+        assert_eq!(stored_proof.shred1, proof.shred1);
+        assert_eq!(stored_proof.shred2, proof.shred2);
+        assert_eq!(duplicate_slots_receiver.try_recv(), Ok(slot));
+
+        // Proofs for slots already known to be duplicate are ignored
+        let (_, chunks) = new_proof_chunks(slot, &leader_keypair, Pubkey::new_unique());
+        for chunk in chunks {
+            handler.handle(chunk);
+        }
+        assert!(handler.buffer.is_empty());
+        assert!(duplicate_slots_receiver.try_recv().is_err());
+
+        // Proofs for rooted slots are ignored
+        let (_, chunks) = new_proof_chunks(0, &leader_keypair, Pubkey::new_unique());
+        for chunk in chunks {
+            handler.handle(chunk);
+        }
+        assert!(handler.buffer.is_empty());
+        assert!(!blockstore.has_duplicate_shreds_in_slot(0));
+
+        // Proofs with shreds not signed by the slot leader are rejected
+        let slot = 6;
+        let (_, chunks) = new_proof_chunks(slot, &Keypair::new(), Pubkey::new_unique());
+        for chunk in chunks {
+            handler.handle(chunk);
+        }
+        assert!(handler.buffer.is_empty());
+        assert!(!blockstore.has_duplicate_shreds_in_slot(slot));
+        assert!(duplicate_slots_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_prune_duplicate_shred_buffer() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Arc::new(Blockstore::open(ledger_path.path()).unwrap());
+        let leader_keypair = Keypair::new();
+        let GenesisConfigInfo { genesis_config, .. } =
+            create_genesis_config_with_leader(10_000, &leader_keypair.pubkey(), 100);
+        let bank = Bank::new(&genesis_config);
+        let leader_schedule_cache = Arc::new(LeaderScheduleCache::new_from_bank(&bank));
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let (duplicate_slots_sender, _duplicate_slots_receiver) = unbounded();
+        let mut handler = DuplicateShredHandler::new(
+            blockstore,
+            leader_schedule_cache,
+            bank_forks,
+            duplicate_slots_sender,
+        );
+
+        // Only the first chunk of each proof is received, so none complete
+        let staked_sender = leader_keypair.pubkey();
+        let (_, chunks) = new_proof_chunks(1, &leader_keypair, staked_sender);
+        handler.handle(chunks.into_iter().next().unwrap());
+        for slot in 1..=MAX_BUFFERED_PROOFS as Slot {
+            let (_, chunks) = new_proof_chunks(slot, &leader_keypair, Pubkey::new_unique());
+            handler.handle(chunks.into_iter().next().unwrap());
+        }
+        assert!(handler.buffer.len() <= MAX_BUFFERED_PROOFS);
+        // The proof from the staked node is kept
+        assert!(handler.buffer.contains_key(&(1, staked_sender)));
+    }
+}
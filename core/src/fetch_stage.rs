@@ -2,16 +2,19 @@
 
 use crate::banking_stage::HOLD_TRANSACTIONS_SLOT_OFFSET;
 use crate::result::{Error, Result};
+use crate::stake_weighted_qos::StakeWeightedQos;
 use solana_metrics::{inc_new_counter_debug, inc_new_counter_info};
 use solana_perf::packet::PacketsRecycler;
 use solana_perf::recycler::Recycler;
 use solana_poh::poh_recorder::PohRecorder;
 use solana_sdk::clock::DEFAULT_TICKS_PER_SLOT;
-use solana_streamer::streamer::{self, PacketReceiver, PacketSender};
+use solana_streamer::streamer::{
+    self, PacketReceiver, PacketReceiverConfig, PacketSender, StakedNodes,
+};
 use std::net::UdpSocket;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::{channel, RecvTimeoutError};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread::{self, Builder, JoinHandle};
 
 pub struct FetchStage {
@@ -26,6 +29,8 @@ impl FetchStage {
         exit: &Arc<AtomicBool>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
         coalesce_ms: u64,
+        staked_nodes: &Arc<RwLock<StakedNodes>>,
+        receiver_config: PacketReceiverConfig,
     ) -> (Self, PacketReceiver) {
         let (sender, receiver) = channel();
         (
@@ -36,6 +41,8 @@ impl FetchStage {
                 &sender,
                 poh_recorder,
                 coalesce_ms,
+                staked_nodes,
+                receiver_config,
             ),
             receiver,
         )
@@ -47,6 +54,8 @@ impl FetchStage {
         sender: &PacketSender,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
         coalesce_ms: u64,
+        staked_nodes: &Arc<RwLock<StakedNodes>>,
+        receiver_config: PacketReceiverConfig,
     ) -> Self {
         let tx_sockets = sockets.into_iter().map(Arc::new).collect();
         let tpu_forwards_sockets = tpu_forwards_sockets.into_iter().map(Arc::new).collect();
@@ -57,6 +66,8 @@ impl FetchStage {
             sender,
             poh_recorder,
             coalesce_ms,
+            staked_nodes,
+            receiver_config,
         )
     }
 
@@ -83,7 +94,11 @@ impl FetchStage {
             .would_be_leader(HOLD_TRANSACTIONS_SLOT_OFFSET.saturating_mul(DEFAULT_TICKS_PER_SLOT))
         {
             inc_new_counter_debug!("fetch_stage-honor_forwards", len);
-            for packets in batch {
+            for mut packets in batch {
+                // Already forwarded once, so banking stage will not forward these again
+                for packet in packets.packets.iter_mut() {
+                    packet.meta.forward = true;
+                }
                 if sendr.send(packets).is_err() {
                     return Err(Error::Send);
                 }
@@ -102,11 +117,17 @@ impl FetchStage {
         sender: &PacketSender,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
         coalesce_ms: u64,
+        staked_nodes: &Arc<RwLock<StakedNodes>>,
+        receiver_config: PacketReceiverConfig,
     ) -> Self {
         let recycler: PacketsRecycler = Recycler::warmed(1000, 1024);
 
+        // Every receiver thread applies the stake weighted QoS to its own packets before they
+        // are verified, with its own shard of the budgets
+        let num_shards = sockets.len() + tpu_forwards_sockets.len();
         let tpu_threads = sockets.into_iter().map(|socket| {
-            streamer::receiver(
+            let mut qos = StakeWeightedQos::new(staked_nodes.clone(), num_shards);
+            streamer::receiver_with_filter(
                 socket,
                 exit,
                 sender.clone(),
@@ -114,12 +135,15 @@ impl FetchStage {
                 "fetch_stage",
                 coalesce_ms,
                 true,
+                receiver_config.clone(),
+                move |packets| qos.apply(packets),
             )
         });
 
         let (forward_sender, forward_receiver) = channel();
         let tpu_forwards_threads = tpu_forwards_sockets.into_iter().map(|socket| {
-            streamer::receiver(
+            let mut qos = StakeWeightedQos::new(staked_nodes.clone(), num_shards);
+            streamer::receiver_with_filter(
                 socket,
                 exit,
                 forward_sender.clone(),
@@ -127,6 +151,8 @@ impl FetchStage {
                 "fetch_forward_stage",
                 coalesce_ms,
                 true,
+                receiver_config.clone(),
+                move |packets| qos.apply(packets),
             )
         });
 
@@ -1,8 +1,10 @@
 //! The `ledger_cleanup_service` drops older ledger data to limit disk space usage
 
 use rand::{thread_rng, Rng};
-use solana_ledger::blockstore::{Blockstore, PurgeType};
-use solana_ledger::blockstore_db::Result as BlockstoreResult;
+use solana_ledger::blockstore::{
+    blockstore_archive::TransactionArchiveFilter, Blockstore, PurgeType,
+};
+use solana_ledger::blockstore_db::{Result as BlockstoreResult, ShredStorageType};
 use solana_measure::measure::Measure;
 use solana_sdk::clock::{Slot, DEFAULT_TICKS_PER_SLOT, TICKS_PER_DAY};
 use std::string::ToString;
@@ -47,6 +49,7 @@ impl LedgerCleanupService {
         exit: &Arc<AtomicBool>,
         compaction_interval: Option<u64>,
         max_compaction_jitter: Option<u64>,
+        transaction_archive_filter: Option<Arc<TransactionArchiveFilter>>,
     ) -> Self {
         let exit = exit.clone();
         let mut last_purge_slot = 0;
@@ -60,6 +63,16 @@ impl LedgerCleanupService {
             "LedgerCleanupService active. max ledger shreds={}, compaction interval={}",
             max_ledger_shreds, compaction_interval,
         );
+        if let ShredStorageType::RocksFifo(_) = blockstore.shred_storage_type() {
+            info!("Shred columns use FIFO compaction; only ledger metadata is purged here");
+        }
+        if let Some(transaction_archive_filter) = &transaction_archive_filter {
+            info!(
+                "Archiving purged transactions of {} program ids and {} accounts",
+                transaction_archive_filter.program_ids.len(),
+                transaction_archive_filter.accounts.len(),
+            );
+        }
 
         let exit_compact = exit.clone();
         let blockstore_compact = blockstore.clone();
@@ -77,6 +90,7 @@ impl LedgerCleanupService {
                     &mut last_purge_slot,
                     DEFAULT_PURGE_SLOT_INTERVAL,
                     &last_compact_slot,
+                    &transaction_archive_filter,
                 ) {
                     match e {
                         RecvTimeoutError::Disconnected => break,
@@ -172,6 +186,7 @@ impl LedgerCleanupService {
         last_purge_slot: &mut u64,
         purge_interval: u64,
         last_compact_slot: &Arc<AtomicU64>,
+        transaction_archive_filter: &Option<Arc<TransactionArchiveFilter>>,
     ) -> Result<(), RecvTimeoutError> {
         let root = Self::receive_new_roots(new_root_receiver)?;
         if root - *last_purge_slot <= purge_interval {
@@ -194,9 +209,41 @@ impl LedgerCleanupService {
             let blockstore = blockstore.clone();
             let purge_complete1 = purge_complete.clone();
             let last_compact_slot1 = last_compact_slot.clone();
+            let transaction_archive_filter = transaction_archive_filter.clone();
             let _t_purge = Builder::new()
                 .name("solana-ledger-purge".to_string())
                 .spawn(move || {
+                    // Archive before raising lowest_cleanup_slot, while the slots are still
+                    // readable. If that fails, the slots are kept and the next pass retries
+                    if let Some(transaction_archive_filter) = transaction_archive_filter {
+                        let mut archive_time = Measure::start("archive_transactions");
+                        match blockstore.archive_filtered_transactions(
+                            purge_first_slot,
+                            lowest_cleanup_slot,
+                            &transaction_archive_filter,
+                        ) {
+                            Ok(num_archived) => {
+                                archive_time.stop();
+                                info!(
+                                    "archived {} transactions from slots {} to {}, {}",
+                                    num_archived,
+                                    purge_first_slot,
+                                    lowest_cleanup_slot,
+                                    archive_time
+                                );
+                            }
+                            Err(err) => {
+                                error!(
+                                    "Error: {:?}; Archiving failed in range {:?} to {:?}, \
+                                     deferring purge",
+                                    err, purge_first_slot, lowest_cleanup_slot
+                                );
+                                purge_complete1.store(true, Ordering::Relaxed);
+                                return;
+                            }
+                        }
+                    }
+
                     let mut slot_update_time = Measure::start("slot_update");
                     *blockstore.lowest_cleanup_slot.write().unwrap() = lowest_cleanup_slot;
                     slot_update_time.stop();
@@ -332,6 +379,7 @@ mod tests {
             &mut last_purge_slot,
             10,
             &highest_compaction_slot,
+            &None,
         )
         .unwrap();
         assert_eq!(last_purge_slot, 50);
@@ -402,6 +450,7 @@ mod tests {
                 &mut last_purge_slot,
                 10,
                 &last_compaction_slot,
+                &None,
             )
             .unwrap();
             time.stop();
@@ -8,6 +8,7 @@
 //!
 
 pub mod accounts_hash_verifier;
+pub mod ancestor_hashes_service;
 pub mod banking_stage;
 pub mod broadcast_stage;
 pub mod cache_block_meta_service;
@@ -19,11 +20,9 @@ pub mod cluster_slots_service;
 pub mod commitment_service;
 pub mod completed_data_sets_service;
 pub mod consensus;
-pub mod cost_model;
-pub mod cost_tracker;
 pub mod cost_update_service;
 pub mod duplicate_repair_status;
-pub mod execute_cost_table;
+pub mod duplicate_shred_listener;
 pub mod fetch_stage;
 pub mod fork_choice;
 pub mod gen_keys;
@@ -32,6 +31,7 @@ pub mod latest_validator_votes_for_frozen_banks;
 pub mod ledger_cleanup_service;
 pub mod optimistic_confirmation_verifier;
 pub mod outstanding_requests;
+pub mod packet_forwarder;
 pub mod packet_hasher;
 pub mod progress_map;
 pub mod repair_response;
@@ -51,9 +51,13 @@ pub mod sigverify;
 pub mod sigverify_shreds;
 pub mod sigverify_stage;
 pub mod snapshot_packager_service;
+pub mod stake_weighted_qos;
+pub mod staked_nodes_updater_service;
 pub mod test_validator;
 pub mod tpu;
+pub mod transaction_scheduler;
 pub mod tree_diff;
+pub mod turbine_tree;
 pub mod tvu;
 pub mod unfrozen_gossip_verified_vote_hashes;
 pub mod validator;
@@ -0,0 +1,238 @@
+//! The `packet_forwarder` sends buffered transaction packets to the TPU forwards ports of the
+//! upcoming leaders. Each packet is forwarded at most once, transactions that would expire or
+//! that pay too little are dropped instead, and the bytes sent by all banking threads together
+//! are capped by a shared `DataBudget`.
+use crate::banking_stage::FORWARD_TRANSACTIONS_TO_LEADER_AT_SLOT_OFFSET;
+use solana_gossip::{cluster_info::ClusterInfo, data_budget::DataBudget};
+use solana_metrics::inc_new_counter_info;
+use solana_perf::{packet::Packet, perf_libs};
+use solana_poh::poh_recorder::PohRecorder;
+use solana_runtime::{accounts_db::ErrorCounters, bank::Bank, bank_forks::BankForks};
+use solana_sdk::{
+    clock::{
+        MAX_PROCESSING_AGE, MAX_TRANSACTION_FORWARDING_DELAY, MAX_TRANSACTION_FORWARDING_DELAY_GPU,
+        NUM_CONSECUTIVE_LEADER_SLOTS,
+    },
+    sanitized_transaction::SanitizedTransaction,
+};
+use std::{
+    cmp,
+    net::{SocketAddr, UdpSocket},
+    sync::{Arc, Mutex, RwLock},
+};
+
+pub const DEFAULT_FORWARD_NUM_LEADERS: usize = 2;
+// 100 Mbps
+pub const DEFAULT_FORWARD_MAX_BYTES_PER_SECOND: usize = 12_500_000;
+
+const DATA_BUDGET_UPDATE_INTERVAL_MS: u64 = 100;
+
+#[derive(Clone, Debug)]
+pub struct ForwardingConfig {
+    /// Number of distinct upcoming leaders each packet is forwarded to
+    pub num_leaders: usize,
+    /// Transactions paying less than this are dropped instead of forwarded
+    pub min_fee_lamports: u64,
+    /// Bytes all banking threads together may forward per second
+    pub max_bytes_per_second: usize,
+}
+
+impl Default for ForwardingConfig {
+    fn default() -> Self {
+        Self {
+            num_leaders: DEFAULT_FORWARD_NUM_LEADERS,
+            min_fee_lamports: 0,
+            max_bytes_per_second: DEFAULT_FORWARD_MAX_BYTES_PER_SECOND,
+        }
+    }
+}
+
+pub struct PacketForwarder {
+    config: ForwardingConfig,
+    bank_forks: Arc<RwLock<BankForks>>,
+    data_budget: DataBudget,
+    socket: UdpSocket,
+}
+
+/// Maximum blockhash age of a transaction that is still worth forwarding. It accounts for the
+/// forwarding delay and for the slot at which the next leader actually processes it.
+pub fn max_forwarding_age() -> usize {
+    let max_tx_fwd_delay = if perf_libs::api().is_none() {
+        MAX_TRANSACTION_FORWARDING_DELAY
+    } else {
+        MAX_TRANSACTION_FORWARDING_DELAY_GPU
+    };
+    MAX_PROCESSING_AGE
+        .saturating_sub(max_tx_fwd_delay)
+        .saturating_sub(FORWARD_TRANSACTIONS_TO_LEADER_AT_SLOT_OFFSET as usize)
+}
+
+impl PacketForwarder {
+    pub fn new(config: ForwardingConfig, bank_forks: Arc<RwLock<BankForks>>) -> Self {
+        Self {
+            config,
+            bank_forks,
+            data_budget: DataBudget::default(),
+            socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
+        }
+    }
+
+    pub fn working_bank(&self) -> Arc<Bank> {
+        self.bank_forks.read().unwrap().working_bank()
+    }
+
+    /// TPU forwards addresses of the next `num_leaders` distinct leaders, excluding this node.
+    pub fn leader_addrs(
+        &self,
+        cluster_info: &ClusterInfo,
+        poh_recorder: &Mutex<PohRecorder>,
+    ) -> Vec<SocketAddr> {
+        let my_pubkey = cluster_info.id();
+        let mut leaders = Vec::with_capacity(self.config.num_leaders);
+        {
+            let poh_recorder = poh_recorder.lock().unwrap();
+            // Leaders hold NUM_CONSECUTIVE_LEADER_SLOTS slots in a row
+            for i in 0..self.config.num_leaders as u64 {
+                let slot_offset = FORWARD_TRANSACTIONS_TO_LEADER_AT_SLOT_OFFSET
+                    + i * NUM_CONSECUTIVE_LEADER_SLOTS;
+                if let Some(leader) = poh_recorder.leader_after_n_slots(slot_offset) {
+                    if leader != my_pubkey && !leaders.contains(&leader) {
+                        leaders.push(leader);
+                    }
+                }
+            }
+        }
+        leaders
+            .iter()
+            .filter_map(|leader| cluster_info.lookup_contact_info(leader, |ci| ci.tpu_forwards))
+            .collect()
+    }
+
+    /// Whether `transaction` is still worth forwarding: its blockhash will not have expired by
+    /// the time the next leader processes it, it has not been processed yet, and it pays at
+    /// least `min_fee_lamports`.
+    pub fn is_forwardable(&self, bank: &Bank, transaction: &SanitizedTransaction) -> bool {
+        let fee_calculator = match bank.get_fee_calculator(&transaction.message.recent_blockhash) {
+            Some(fee_calculator) => fee_calculator,
+            None => return false,
+        };
+        if fee_calculator.calculate_fee(&transaction.message) < self.config.min_fee_lamports {
+            return false;
+        }
+        let results = bank.check_transactions(
+            std::slice::from_ref(transaction),
+            &[Ok(())],
+            max_forwarding_age(),
+            &mut ErrorCounters::default(),
+        );
+        results[0].0.is_ok()
+    }
+
+    /// Sends `packet` to every address in `addrs` and marks it forwarded, unless it was
+    /// forwarded before. Returns false without sending if the data budget is exhausted.
+    pub fn forward_packet(&self, addrs: &[SocketAddr], packet: &mut Packet) -> bool {
+        if packet.meta.forward {
+            return true;
+        }
+        self.refill_data_budget();
+        if !self.data_budget.take(packet.meta.size * addrs.len()) {
+            inc_new_counter_info!("banking_stage-forward_budget_exhausted", 1);
+            return false;
+        }
+        for addr in addrs {
+            let _ = self.socket.send_to(&packet.data[..packet.meta.size], addr);
+        }
+        packet.meta.forward = true;
+        inc_new_counter_info!("banking_stage-forwarded_packets", 1);
+        true
+    }
+
+    fn refill_data_budget(&self) {
+        let max_bytes = self.config.max_bytes_per_second;
+        let bytes_per_interval = max_bytes * DATA_BUDGET_UPDATE_INTERVAL_MS as usize / 1000;
+        self.data_budget
+            .update(DATA_BUDGET_UPDATE_INTERVAL_MS, |bytes| {
+                cmp::min(bytes + bytes_per_interval, max_bytes)
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_ledger::genesis_utils::create_genesis_config;
+    use solana_sdk::{
+        hash::Hash,
+        signature::{Keypair, Signer},
+        system_transaction,
+    };
+    use std::convert::TryFrom;
+
+    fn new_forwarder(config: ForwardingConfig) -> (PacketForwarder, Arc<Bank>) {
+        let genesis_config = create_genesis_config(10_000).genesis_config;
+        let bank = Bank::new_no_wallclock_throttle(&genesis_config);
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let bank = bank_forks.read().unwrap().working_bank();
+        (PacketForwarder::new(config, bank_forks), bank)
+    }
+
+    #[test]
+    fn test_is_forwardable() {
+        let (forwarder, bank) = new_forwarder(ForwardingConfig::default());
+        let keypair = Keypair::new();
+        let fresh = system_transaction::transfer(
+            &keypair,
+            &Keypair::new().pubkey(),
+            1,
+            bank.last_blockhash(),
+        );
+        let stale =
+            system_transaction::transfer(&keypair, &keypair.pubkey(), 1, Hash::new_unique());
+        assert!(forwarder.is_forwardable(
+            &bank,
+            &SanitizedTransaction::try_from(fresh.clone()).unwrap()
+        ));
+        assert!(!forwarder.is_forwardable(&bank, &SanitizedTransaction::try_from(stale).unwrap()));
+
+        let fee = bank
+            .get_fee_calculator(&bank.last_blockhash())
+            .unwrap()
+            .calculate_fee(&fresh.message);
+        let (forwarder, bank) = new_forwarder(ForwardingConfig {
+            min_fee_lamports: fee + 1,
+            ..ForwardingConfig::default()
+        });
+        let fresh = system_transaction::transfer(
+            &keypair,
+            &Keypair::new().pubkey(),
+            1,
+            bank.last_blockhash(),
+        );
+        assert!(!forwarder.is_forwardable(&bank, &SanitizedTransaction::try_from(fresh).unwrap()));
+    }
+
+    #[test]
+    fn test_forward_packet_once_within_budget() {
+        let (forwarder, _bank) = new_forwarder(ForwardingConfig {
+            max_bytes_per_second: 20_000,
+            ..ForwardingConfig::default()
+        });
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addrs = vec![receiver.local_addr().unwrap()];
+        let mut packet = Packet::default();
+        packet.meta.size = 1_000;
+
+        // The first update fills one interval's worth of budget: 2_000 bytes
+        assert!(forwarder.forward_packet(&addrs, &mut packet));
+        assert!(packet.meta.forward);
+        // Forwarded packets are not sent, nor charged, again
+        assert!(forwarder.forward_packet(&addrs, &mut packet));
+        let mut packet = Packet::default();
+        packet.meta.size = 1_000;
+        assert!(forwarder.forward_packet(&addrs, &mut packet));
+        let mut packet = Packet::default();
+        packet.meta.size = 1_000;
+        assert!(!forwarder.forward_packet(&addrs, &mut packet));
+        assert!(!packet.meta.forward);
+    }
+}
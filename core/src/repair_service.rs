@@ -1,6 +1,7 @@
 //! The `repair_service` module implements the tools necessary to generate a thread which
 //! regularly finds missing shreds in the ledger and sends repair requests for those shreds
 use crate::{
+    ancestor_hashes_service::{AncestorHashesReplayUpdateReceiver, AncestorHashesService},
     cluster_info_vote_listener::VerifiedVoteReceiver,
     cluster_slots::ClusterSlots,
     duplicate_repair_status::DuplicateSlotRepairStatus,
@@ -19,7 +20,8 @@ use solana_ledger::{
 use solana_measure::measure::Measure;
 use solana_runtime::{bank_forks::BankForks, contains::Contains};
 use solana_sdk::{
-    clock::Slot, epoch_schedule::EpochSchedule, hash::Hash, pubkey::Pubkey, timing::timestamp,
+    clock::Slot, epoch_schedule::EpochSchedule, feature_set, hash::Hash, pubkey::Pubkey,
+    signature::Keypair, timing::timestamp,
 };
 use std::{
     collections::{HashMap, HashSet},
@@ -33,8 +35,10 @@ use std::{
     time::{Duration, Instant},
 };
 
-pub type DuplicateSlotsResetSender = CrossbeamSender<Slot>;
-pub type DuplicateSlotsResetReceiver = CrossbeamReceiver<Slot>;
+pub type DuplicateSlotsResetSender = CrossbeamSender<Vec<(Slot, Hash)>>;
+pub type DuplicateSlotsResetReceiver = CrossbeamReceiver<Vec<(Slot, Hash)>>;
+pub type DumpedSlotsSender = CrossbeamSender<(Slot, Hash)>;
+pub type DumpedSlotsReceiver = CrossbeamReceiver<(Slot, Hash)>;
 pub type ConfirmedSlotsSender = CrossbeamSender<Vec<Slot>>;
 pub type ConfirmedSlotsReceiver = CrossbeamReceiver<Vec<Slot>>;
 pub type OutstandingShredRepairs = OutstandingRequests<ShredRepairType>;
@@ -46,6 +50,12 @@ pub struct SlotRepairs {
     pubkey_repairs: HashMap<Pubkey, u64>,
 }
 
+impl SlotRepairs {
+    pub fn pubkey_repairs(&self) -> &HashMap<Pubkey, u64> {
+        &self.pubkey_repairs
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct RepairStatsGroup {
     pub count: u64,
@@ -134,47 +144,76 @@ impl Default for RepairSlotRange {
 
 pub struct RepairService {
     t_repair: JoinHandle<()>,
+    ancestor_hashes_service: AncestorHashesService,
 }
 
 impl RepairService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         blockstore: Arc<Blockstore>,
         exit: Arc<AtomicBool>,
         repair_socket: Arc<UdpSocket>,
+        ancestor_hashes_socket: Arc<UdpSocket>,
         cluster_info: Arc<ClusterInfo>,
         repair_info: RepairInfo,
         cluster_slots: Arc<ClusterSlots>,
         verified_vote_receiver: VerifiedVoteReceiver,
         outstanding_requests: Arc<RwLock<OutstandingShredRepairs>>,
+        ancestor_hashes_replay_update_receiver: AncestorHashesReplayUpdateReceiver,
+        dumped_slots_receiver: DumpedSlotsReceiver,
     ) -> Self {
-        let t_repair = Builder::new()
-            .name("solana-repair-service".to_string())
-            .spawn(move || {
-                Self::run(
-                    &blockstore,
-                    &exit,
-                    &repair_socket,
-                    cluster_info,
-                    repair_info,
-                    &cluster_slots,
-                    verified_vote_receiver,
-                    &outstanding_requests,
-                )
-            })
-            .unwrap();
+        let repair_info = Arc::new(repair_info);
+        let t_repair = {
+            let blockstore = blockstore.clone();
+            let exit = exit.clone();
+            let cluster_info = cluster_info.clone();
+            let repair_info = repair_info.clone();
+            let cluster_slots = cluster_slots.clone();
+            Builder::new()
+                .name("solana-repair-service".to_string())
+                .spawn(move || {
+                    Self::run(
+                        &blockstore,
+                        &exit,
+                        &repair_socket,
+                        cluster_info,
+                        &repair_info,
+                        &cluster_slots,
+                        verified_vote_receiver,
+                        &outstanding_requests,
+                        dumped_slots_receiver,
+                    )
+                })
+                .unwrap()
+        };
+
+        let ancestor_hashes_service = AncestorHashesService::new(
+            exit,
+            blockstore,
+            ancestor_hashes_socket,
+            repair_info,
+            cluster_info,
+            cluster_slots,
+            ancestor_hashes_replay_update_receiver,
+        );
 
-        RepairService { t_repair }
+        RepairService {
+            t_repair,
+            ancestor_hashes_service,
+        }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn run(
         blockstore: &Blockstore,
         exit: &AtomicBool,
         repair_socket: &UdpSocket,
         cluster_info: Arc<ClusterInfo>,
-        repair_info: RepairInfo,
+        repair_info: &RepairInfo,
         cluster_slots: &ClusterSlots,
         verified_vote_receiver: VerifiedVoteReceiver,
         outstanding_requests: &RwLock<OutstandingShredRepairs>,
+        dumped_slots_receiver: DumpedSlotsReceiver,
     ) {
         let mut repair_weight = RepairWeight::new(repair_info.bank_forks.read().unwrap().root());
         let serve_repair = ServeRepair::new(cluster_info.clone());
@@ -182,7 +221,7 @@ impl RepairService {
         let mut repair_stats = RepairStats::default();
         let mut repair_timing = RepairTiming::default();
         let mut last_stats = Instant::now();
-        let duplicate_slot_repair_statuses: HashMap<Slot, DuplicateSlotRepairStatus> =
+        let mut duplicate_slot_repair_statuses: HashMap<Slot, DuplicateSlotRepairStatus> =
             HashMap::new();
         let mut peers_cache = LruCache::new(REPAIR_PEERS_CACHE_CAPACITY);
 
@@ -195,7 +234,7 @@ impl RepairService {
             let mut get_votes_elapsed;
             let mut add_votes_elapsed;
 
-            let repairs = {
+            let (repairs, sign_repair_requests) = {
                 let root_bank = repair_info.bank_forks.read().unwrap().root_bank().clone();
                 let new_root = root_bank.slot();
 
@@ -228,7 +267,21 @@ impl RepairService {
                 );
                 add_votes_elapsed.stop();
 
-                repair_weight.get_best_weighted_repairs(
+                // Slots ReplayStage dumped in order to repair the version the
+                // cluster agreed upon
+                for (dumped_slot, correct_hash) in dumped_slots_receiver.try_iter() {
+                    Self::initiate_repair_for_duplicate_slot(
+                        dumped_slot,
+                        correct_hash,
+                        &mut duplicate_slot_repair_statuses,
+                        cluster_slots,
+                        &serve_repair,
+                        &repair_info.repair_validators,
+                    );
+                }
+                duplicate_slot_repair_statuses.retain(|slot, _| *slot > new_root);
+
+                let repairs = repair_weight.get_best_weighted_repairs(
                     blockstore,
                     root_bank.epoch_stakes_map(),
                     root_bank.epoch_schedule(),
@@ -236,26 +289,46 @@ impl RepairService {
                     MAX_REPAIR_LENGTH,
                     &duplicate_slot_repair_statuses,
                     Some(&mut repair_timing),
-                )
+                );
+                let sign_repair_requests = root_bank
+                    .feature_set
+                    .is_active(&feature_set::sign_repair_requests::id());
+                (repairs, sign_repair_requests)
             };
 
             let mut send_repairs_elapsed = Measure::start("send_repairs_elapsed");
-            let mut outstanding_requests = outstanding_requests.write().unwrap();
-            repairs.into_iter().for_each(|repair_request| {
-                if let Ok((to, req)) = serve_repair.repair_request(
-                    cluster_slots,
-                    repair_request,
-                    &mut peers_cache,
-                    &mut repair_stats,
-                    &repair_info.repair_validators,
-                    &mut outstanding_requests,
-                ) {
-                    repair_socket.send_to(&req, to).unwrap_or_else(|e| {
-                        info!("{} repair req send_to({}) error {:?}", id, to, e);
-                        0
-                    });
-                }
-            });
+            let keypair = cluster_info.keypair().clone();
+            let identity_keypair = sign_repair_requests.then(|| keypair.as_ref());
+            {
+                let mut outstanding_requests = outstanding_requests.write().unwrap();
+                repairs.into_iter().for_each(|repair_request| {
+                    if let Ok((to, req)) = serve_repair.repair_request(
+                        cluster_slots,
+                        repair_request,
+                        &mut peers_cache,
+                        &mut repair_stats,
+                        &repair_info.repair_validators,
+                        &mut outstanding_requests,
+                        identity_keypair,
+                    ) {
+                        repair_socket.send_to(&req, to).unwrap_or_else(|e| {
+                            info!("{} repair req send_to({}) error {:?}", id, to, e);
+                            0
+                        });
+                    }
+                });
+            }
+            Self::generate_and_send_duplicate_repairs(
+                &mut duplicate_slot_repair_statuses,
+                cluster_slots,
+                blockstore,
+                &serve_repair,
+                &mut repair_stats,
+                repair_socket,
+                &repair_info.repair_validators,
+                outstanding_requests,
+                identity_keypair,
+            );
             send_repairs_elapsed.stop();
             repair_timing.update(
                 set_root_elapsed.as_us(),
@@ -414,7 +487,6 @@ impl RepairService {
         }
     }
 
-    #[allow(dead_code)]
     fn generate_duplicate_repairs_for_slot(
         blockstore: &Blockstore,
         slot: Slot,
@@ -439,7 +511,7 @@ impl RepairService {
         }
     }
 
-    #[allow(dead_code)]
+    #[allow(clippy::too_many_arguments)]
     fn generate_and_send_duplicate_repairs(
         duplicate_slot_repair_statuses: &mut HashMap<Slot, DuplicateSlotRepairStatus>,
         cluster_slots: &ClusterSlots,
@@ -449,6 +521,7 @@ impl RepairService {
         repair_socket: &UdpSocket,
         repair_validators: &Option<HashSet<Pubkey>>,
         outstanding_requests: &RwLock<OutstandingShredRepairs>,
+        identity_keypair: Option<&Keypair>,
     ) {
         duplicate_slot_repair_statuses.retain(|slot, status| {
             Self::update_duplicate_slot_repair_addr(
@@ -473,6 +546,7 @@ impl RepairService {
                             serve_repair,
                             repair_stats,
                             nonce,
+                            identity_keypair,
                         ) {
                             info!(
                                 "repair req send_to {} ({}) error {:?}",
@@ -490,7 +564,7 @@ impl RepairService {
         })
     }
 
-    #[allow(dead_code)]
+    #[allow(clippy::too_many_arguments)]
     fn serialize_and_send_request(
         repair_type: &ShredRepairType,
         repair_socket: &UdpSocket,
@@ -499,14 +573,19 @@ impl RepairService {
         serve_repair: &ServeRepair,
         repair_stats: &mut RepairStats,
         nonce: Nonce,
+        identity_keypair: Option<&Keypair>,
     ) -> Result<()> {
-        let req =
-            serve_repair.map_repair_request(repair_type, repair_pubkey, repair_stats, nonce)?;
+        let req = serve_repair.map_repair_request(
+            repair_type,
+            repair_pubkey,
+            repair_stats,
+            nonce,
+            identity_keypair,
+        )?;
         repair_socket.send_to(&req, to)?;
         Ok(())
     }
 
-    #[allow(dead_code)]
     fn update_duplicate_slot_repair_addr(
         slot: Slot,
         status: &mut DuplicateSlotRepairStatus,
@@ -528,9 +607,9 @@ impl RepairService {
         }
     }
 
-    #[allow(dead_code)]
     fn initiate_repair_for_duplicate_slot(
         slot: Slot,
+        correct_hash: Hash,
         duplicate_slot_repair_statuses: &mut HashMap<Slot, DuplicateSlotRepairStatus>,
         cluster_slots: &ClusterSlots,
         serve_repair: &ServeRepair,
@@ -546,7 +625,7 @@ impl RepairService {
             .repair_request_duplicate_compute_best_peer(slot, cluster_slots, repair_validators)
             .ok();
         let new_duplicate_slot_repair_status = DuplicateSlotRepairStatus {
-            correct_ancestors_to_repair: vec![(slot, Hash::default())],
+            correct_ancestors_to_repair: vec![(slot, correct_hash)],
             repair_pubkey_and_addr,
             start_ts: timestamp(),
         };
@@ -554,7 +633,8 @@ impl RepairService {
     }
 
     pub fn join(self) -> thread::Result<()> {
-        self.t_repair.join()
+        self.t_repair.join()?;
+        self.ancestor_hashes_service.join()
     }
 }
 
@@ -893,6 +973,7 @@ mod test {
             &UdpSocket::bind("0.0.0.0:0").unwrap(),
             &None,
             &RwLock::new(OutstandingRequests::default()),
+            None,
         );
         assert!(ancestor_hashes_request_statuses
             .get(&dead_slot)
@@ -918,6 +999,7 @@ mod test {
             &UdpSocket::bind("0.0.0.0:0").unwrap(),
             &None,
             &RwLock::new(OutstandingRequests::default()),
+            None,
         );
         assert_eq!(ancestor_hashes_request_statuses.len(), 1);
         assert!(ancestor_hashes_request_statuses.get(&dead_slot).is_some());
@@ -936,6 +1018,7 @@ mod test {
             &UdpSocket::bind("0.0.0.0:0").unwrap(),
             &None,
             &RwLock::new(OutstandingRequests::default()),
+            None,
         );
         assert!(ancestor_hashes_request_statuses.is_empty());
     }
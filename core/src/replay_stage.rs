@@ -1,6 +1,7 @@
 //! The `replay_stage` replays transactions broadcast by the leader.
 
 use crate::{
+    ancestor_hashes_service::AncestorHashesReplayUpdateSender,
     broadcast_stage::RetransmitSlotsSender,
     cache_block_meta_service::CacheBlockMetaSender,
     cluster_info_vote_listener::{
@@ -17,7 +18,7 @@ use crate::{
     heaviest_subtree_fork_choice::HeaviestSubtreeForkChoice,
     latest_validator_votes_for_frozen_banks::LatestValidatorVotesForFrozenBanks,
     progress_map::{ForkProgress, ProgressMap, PropagatedStats},
-    repair_service::DuplicateSlotsResetReceiver,
+    repair_service::{DumpedSlotsSender, DuplicateSlotsResetReceiver},
     rewards_recorder_service::RewardsRecorderSender,
     unfrozen_gossip_verified_vote_hashes::UnfrozenGossipVerifiedVoteHashes,
     voting_service::VoteOp,
@@ -150,6 +151,7 @@ pub struct ReplayTiming {
     bank_count: u64,
     process_gossip_duplicate_confirmed_slots_elapsed: u64,
     process_duplicate_slots_elapsed: u64,
+    process_ancestor_hashes_duplicate_slots_elapsed: u64,
     process_unfrozen_gossip_verified_vote_hashes_elapsed: u64,
     repair_correct_slots_elapsed: u64,
 }
@@ -173,6 +175,7 @@ impl ReplayTiming {
         process_gossip_duplicate_confirmed_slots_elapsed: u64,
         process_unfrozen_gossip_verified_vote_hashes_elapsed: u64,
         process_duplicate_slots_elapsed: u64,
+        process_ancestor_hashes_duplicate_slots_elapsed: u64,
         repair_correct_slots_elapsed: u64,
     ) {
         self.collect_frozen_banks_elapsed += collect_frozen_banks_elapsed;
@@ -193,6 +196,8 @@ impl ReplayTiming {
         self.process_unfrozen_gossip_verified_vote_hashes_elapsed +=
             process_unfrozen_gossip_verified_vote_hashes_elapsed;
         self.process_duplicate_slots_elapsed += process_duplicate_slots_elapsed;
+        self.process_ancestor_hashes_duplicate_slots_elapsed +=
+            process_ancestor_hashes_duplicate_slots_elapsed;
         self.repair_correct_slots_elapsed += repair_correct_slots_elapsed;
         let now = timestamp();
         let elapsed_ms = now - self.last_print;
@@ -279,6 +284,11 @@ impl ReplayTiming {
                     self.process_duplicate_slots_elapsed as i64,
                     i64
                 ),
+                (
+                    "process_ancestor_hashes_duplicate_slots_elapsed",
+                    self.process_ancestor_hashes_duplicate_slots_elapsed as i64,
+                    i64
+                ),
                 (
                     "repair_correct_slots_elapsed",
                     self.repair_correct_slots_elapsed as i64,
@@ -311,13 +321,15 @@ impl ReplayStage {
         vote_tracker: Arc<VoteTracker>,
         cluster_slots: Arc<ClusterSlots>,
         retransmit_slots_sender: RetransmitSlotsSender,
-        _duplicate_slots_reset_receiver: DuplicateSlotsResetReceiver,
+        duplicate_slots_reset_receiver: DuplicateSlotsResetReceiver,
         replay_vote_sender: ReplayVoteSender,
         gossip_duplicate_confirmed_slots_receiver: GossipDuplicateConfirmedSlotsReceiver,
         gossip_verified_vote_hash_receiver: GossipVerifiedVoteHashReceiver,
         cluster_slots_update_sender: ClusterSlotsUpdateSender,
         cost_update_sender: Sender<ExecuteTimings>,
         voting_sender: Sender<VoteOp>,
+        ancestor_hashes_replay_update_sender: AncestorHashesReplayUpdateSender,
+        dumped_slots_sender: DumpedSlotsSender,
     ) -> Self {
         let ReplayStageConfig {
             vote_account,
@@ -417,7 +429,8 @@ impl ReplayStage {
                         &mut latest_validator_votes_for_frozen_banks,
                         &cluster_slots_update_sender,
                         &cost_update_sender,
-                        &mut duplicate_slots_to_repair
+                        &mut duplicate_slots_to_repair,
+                        &ancestor_hashes_replay_update_sender,
                     );
                     replay_active_banks_time.stop();
 
@@ -433,7 +446,8 @@ impl ReplayStage {
                         &bank_forks,
                         &mut progress,
                         &mut heaviest_subtree_fork_choice,
-                        &mut duplicate_slots_to_repair
+                        &mut duplicate_slots_to_repair,
+                        &ancestor_hashes_replay_update_sender,
                     );
                     process_gossip_duplicate_confirmed_slots_time.stop();
 
@@ -463,11 +477,28 @@ impl ReplayStage {
                             &bank_forks,
                             &mut progress,
                             &mut heaviest_subtree_fork_choice,
-                            &mut duplicate_slots_to_repair
+                            &mut duplicate_slots_to_repair,
+                            &ancestor_hashes_replay_update_sender,
                         );
                     }
                     process_duplicate_slots_time.stop();
 
+                    // Check for slots the ancestor hashes service found we
+                    // have the wrong version of
+                    let mut process_ancestor_hashes_duplicate_slots_time = Measure::start("process_ancestor_hashes_duplicate_slots");
+                    Self::process_ancestor_hashes_duplicate_slots(
+                        &blockstore,
+                        &duplicate_slots_reset_receiver,
+                        &mut duplicate_slots_tracker,
+                        &gossip_duplicate_confirmed_slots,
+                        &bank_forks,
+                        &progress,
+                        &mut heaviest_subtree_fork_choice,
+                        &mut duplicate_slots_to_repair,
+                        &ancestor_hashes_replay_update_sender,
+                    );
+                    process_ancestor_hashes_duplicate_slots_time.stop();
+
                     let mut collect_frozen_banks_time = Measure::start("frozen_banks");
                     let mut frozen_banks: Vec<_> = bank_forks
                         .read()
@@ -505,7 +536,7 @@ impl ReplayStage {
                             &bank_forks,
                         );
 
-                        Self::mark_slots_confirmed(&confirmed_forks, &blockstore, &bank_forks, &mut progress, &mut duplicate_slots_tracker, &mut heaviest_subtree_fork_choice,  &mut duplicate_slots_to_repair);
+                        Self::mark_slots_confirmed(&confirmed_forks, &blockstore, &bank_forks, &mut progress, &mut duplicate_slots_tracker, &mut heaviest_subtree_fork_choice,  &mut duplicate_slots_to_repair, &ancestor_hashes_replay_update_sender);
                     }
                     compute_slot_stats_time.stop();
 
@@ -717,7 +748,7 @@ impl ReplayStage {
                     //
                     // Has to be before `maybe_start_leader()`. Otherwise, `ancestors` and `descendants`
                     // will be outdated, and we cannot assume `poh_bank` will be in either of these maps.
-                    Self::dump_then_repair_correct_slots(&mut duplicate_slots_to_repair, &mut ancestors, &mut descendants, &mut progress, &bank_forks, &blockstore, poh_bank.map(|bank| bank.slot()));
+                    Self::dump_then_repair_correct_slots(&mut duplicate_slots_to_repair, &mut ancestors, &mut descendants, &mut progress, &bank_forks, &blockstore, poh_bank.map(|bank| bank.slot()), &dumped_slots_sender);
                     dump_then_repair_correct_slots_time.stop();
 
                     // From this point on, its not safe to use ancestors/descendants since maybe_start_leader
@@ -780,6 +811,7 @@ impl ReplayStage {
                         process_gossip_duplicate_confirmed_slots_time.as_us(),
                         process_unfrozen_gossip_verified_vote_hashes_time.as_us(),
                         process_duplicate_slots_time.as_us(),
+                        process_ancestor_hashes_duplicate_slots_time.as_us(),
                         dump_then_repair_correct_slots_time.as_us(),
                     );
                 }
@@ -855,6 +887,7 @@ impl ReplayStage {
         bank_forks: &RwLock<BankForks>,
         blockstore: &Blockstore,
         poh_bank_slot: Option<Slot>,
+        dumped_slots_sender: &DumpedSlotsSender,
     ) {
         if duplicate_slots_to_repair.is_empty() {
             return;
@@ -923,9 +956,10 @@ impl ReplayStage {
                         bank_forks,
                         blockstore,
                     );
+                    // Signal repair to repair the correct version of
+                    // `duplicate_slot` with hash == `correct_hash`
+                    let _ = dumped_slots_sender.send((*duplicate_slot, *correct_hash));
                     true
-                // TODO: Send signal to repair to repair the correct version of
-                // `duplicate_slot` with hash == `correct_hash`
                 } else {
                     warn!(
                         "PoH bank for slot {} is building on duplicate slot {}",
@@ -1075,6 +1109,7 @@ impl ReplayStage {
         progress: &mut ProgressMap,
         fork_choice: &mut HeaviestSubtreeForkChoice,
         duplicate_slots_to_repair: &mut DuplicateSlotsToRepair,
+        ancestor_hashes_replay_update_sender: &AncestorHashesReplayUpdateSender,
     ) {
         let root = bank_forks.read().unwrap().root();
         for new_confirmed_slots in gossip_duplicate_confirmed_slots_receiver.try_iter() {
@@ -1101,12 +1136,55 @@ impl ReplayStage {
                     duplicate_slots_tracker,
                     fork_choice,
                     duplicate_slots_to_repair,
+                    ancestor_hashes_replay_update_sender,
                     SlotStateUpdate::DuplicateConfirmed(duplicate_confirmed_state),
                 );
             }
         }
     }
 
+    // Check for slots the ancestor hashes service sampled the cluster for,
+    // and found that the version we have does not match the version the
+    // cluster duplicate confirmed.
+    #[allow(clippy::too_many_arguments)]
+    fn process_ancestor_hashes_duplicate_slots(
+        blockstore: &Blockstore,
+        duplicate_slots_reset_receiver: &DuplicateSlotsResetReceiver,
+        duplicate_slots_tracker: &mut DuplicateSlotsTracker,
+        gossip_duplicate_confirmed_slots: &GossipDuplicateConfirmedSlots,
+        bank_forks: &RwLock<BankForks>,
+        progress: &ProgressMap,
+        fork_choice: &mut HeaviestSubtreeForkChoice,
+        duplicate_slots_to_repair: &mut DuplicateSlotsToRepair,
+        ancestor_hashes_replay_update_sender: &AncestorHashesReplayUpdateSender,
+    ) {
+        let root = bank_forks.read().unwrap().root();
+        for mut agreed_slot_hashes in duplicate_slots_reset_receiver.try_iter() {
+            // Process ancestors before their descendants
+            agreed_slot_hashes.sort_unstable();
+            for (agreed_slot, agreed_hash) in agreed_slot_hashes {
+                let ancestor_hashes_agreed_state = AncestorHashesAgreedState::new_from_state(
+                    agreed_slot,
+                    agreed_hash,
+                    gossip_duplicate_confirmed_slots,
+                    fork_choice,
+                    || progress.is_dead(agreed_slot).unwrap_or(false),
+                    || bank_forks.read().unwrap().bank_hash(agreed_slot),
+                );
+                check_slot_agrees_with_cluster(
+                    agreed_slot,
+                    root,
+                    blockstore,
+                    duplicate_slots_tracker,
+                    fork_choice,
+                    duplicate_slots_to_repair,
+                    ancestor_hashes_replay_update_sender,
+                    SlotStateUpdate::AncestorHashesAgreed(ancestor_hashes_agreed_state),
+                );
+            }
+        }
+    }
+
     fn process_gossip_verified_vote_hashes(
         gossip_verified_vote_hash_receiver: &GossipVerifiedVoteHashReceiver,
         unfrozen_gossip_verified_vote_hashes: &mut UnfrozenGossipVerifiedVoteHashes,
@@ -1136,6 +1214,7 @@ impl ReplayStage {
         progress: &mut ProgressMap,
         fork_choice: &mut HeaviestSubtreeForkChoice,
         duplicate_slots_to_repair: &mut DuplicateSlotsToRepair,
+        ancestor_hashes_replay_update_sender: &AncestorHashesReplayUpdateSender,
     ) {
         let new_duplicate_slots: Vec<Slot> = duplicate_slots_receiver.try_iter().collect();
         let (root_slot, bank_hashes) = {
@@ -1165,6 +1244,7 @@ impl ReplayStage {
                 duplicate_slots_tracker,
                 fork_choice,
                 duplicate_slots_to_repair,
+                ancestor_hashes_replay_update_sender,
                 SlotStateUpdate::Duplicate(duplicate_state),
             );
         }
@@ -1413,6 +1493,7 @@ impl ReplayStage {
         progress: &mut ProgressMap,
         heaviest_subtree_fork_choice: &mut HeaviestSubtreeForkChoice,
         duplicate_slots_to_repair: &mut DuplicateSlotsToRepair,
+        ancestor_hashes_replay_update_sender: &AncestorHashesReplayUpdateSender,
     ) {
         // Do not remove from progress map when marking dead! Needed by
         // `process_gossip_duplicate_confirmed_slots()`
@@ -1460,6 +1541,7 @@ impl ReplayStage {
             duplicate_slots_tracker,
             heaviest_subtree_fork_choice,
             duplicate_slots_to_repair,
+            ancestor_hashes_replay_update_sender,
             SlotStateUpdate::Dead(dead_state),
         );
     }
@@ -1859,6 +1941,7 @@ impl ReplayStage {
         cluster_slots_update_sender: &ClusterSlotsUpdateSender,
         cost_update_sender: &Sender<ExecuteTimings>,
         duplicate_slots_to_repair: &mut DuplicateSlotsToRepair,
+        ancestor_hashes_replay_update_sender: &AncestorHashesReplayUpdateSender,
     ) -> bool {
         let mut did_complete_bank = false;
         let mut tx_count = 0;
@@ -1926,6 +2009,7 @@ impl ReplayStage {
                             progress,
                             heaviest_subtree_fork_choice,
                             duplicate_slots_to_repair,
+                            ancestor_hashes_replay_update_sender,
                         );
                         // If the bank was corrupted, don't try to run the below logic to check if the
                         // bank is completed
@@ -1974,6 +2058,7 @@ impl ReplayStage {
                     duplicate_slots_tracker,
                     heaviest_subtree_fork_choice,
                     duplicate_slots_to_repair,
+                    ancestor_hashes_replay_update_sender,
                     SlotStateUpdate::BankFrozen(bank_frozen_state),
                 );
                 if let Some(sender) = bank_notification_sender {
@@ -2490,6 +2575,7 @@ impl ReplayStage {
         duplicate_slots_tracker: &mut DuplicateSlotsTracker,
         fork_choice: &mut HeaviestSubtreeForkChoice,
         duplicate_slots_to_repair: &mut DuplicateSlotsToRepair,
+        ancestor_hashes_replay_update_sender: &AncestorHashesReplayUpdateSender,
     ) {
         let root_slot = bank_forks.read().unwrap().root();
         for (slot, frozen_hash) in confirmed_forks.iter() {
@@ -2515,6 +2601,7 @@ impl ReplayStage {
                     duplicate_slots_tracker,
                     fork_choice,
                     duplicate_slots_to_repair,
+                    ancestor_hashes_replay_update_sender,
                     SlotStateUpdate::DuplicateConfirmed(duplicate_confirmed_state),
                 );
             }
@@ -3391,6 +3478,8 @@ pub mod tests {
                 block_commitment_cache,
                 OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
             ));
+            let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+                unbounded();
             if let Err(err) = &res {
                 ReplayStage::mark_dead_slot(
                     &blockstore,
@@ -3403,6 +3492,7 @@ pub mod tests {
                     &mut progress,
                     &mut heaviest_subtree_fork_choice,
                     &mut DuplicateSlotsToRepair::default(),
+                    &ancestor_hashes_replay_update_sender,
                 );
             }
 
@@ -4831,6 +4921,8 @@ pub mod tests {
         blockstore.store_duplicate_slot(4, vec![], vec![]).unwrap();
         let mut duplicate_slots_tracker = DuplicateSlotsTracker::default();
         let mut gossip_duplicate_confirmed_slots = GossipDuplicateConfirmedSlots::default();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
         let bank4_hash = bank_forks.read().unwrap().bank_hash(4).unwrap();
         assert_ne!(bank4_hash, Hash::default());
         let duplicate_state = DuplicateState::new_from_state(
@@ -4847,6 +4939,7 @@ pub mod tests {
             &mut duplicate_slots_tracker,
             &mut vote_simulator.heaviest_subtree_fork_choice,
             &mut DuplicateSlotsToRepair::default(),
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::Duplicate(duplicate_state),
         );
 
@@ -4878,6 +4971,7 @@ pub mod tests {
             &mut duplicate_slots_tracker,
             &mut vote_simulator.heaviest_subtree_fork_choice,
             &mut DuplicateSlotsToRepair::default(),
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::Duplicate(duplicate_state),
         );
 
@@ -4910,6 +5004,7 @@ pub mod tests {
             &mut duplicate_slots_tracker,
             &mut vote_simulator.heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::DuplicateConfirmed(duplicate_confirmed_state),
         );
         // The confirmed hash is detected in `progress`, which means
@@ -4955,8 +5050,9 @@ pub mod tests {
         // Insert different versions of both 1 and 2. Both slots 1 and 2 should
         // then be purged
         let mut duplicate_slots_to_repair = DuplicateSlotsToRepair::default();
-        duplicate_slots_to_repair.insert((1, Hash::new_unique()));
-        duplicate_slots_to_repair.insert((2, Hash::new_unique()));
+        let correct_hashes = [(1, Hash::new_unique()), (2, Hash::new_unique())];
+        duplicate_slots_to_repair.extend(correct_hashes.iter().copied());
+        let (dumped_slots_sender, dumped_slots_receiver) = unbounded();
         ReplayStage::dump_then_repair_correct_slots(
             &mut duplicate_slots_to_repair,
             &mut ancestors,
@@ -4965,8 +5061,14 @@ pub mod tests {
             bank_forks,
             blockstore,
             None,
+            &dumped_slots_sender,
         );
 
+        // Repair should be signaled to fetch the correct versions
+        let mut dumped_slots: Vec<_> = dumped_slots_receiver.try_iter().collect();
+        dumped_slots.sort_unstable();
+        assert_eq!(dumped_slots, correct_hashes);
+
         let r_bank_forks = bank_forks.read().unwrap();
         for slot in 0..=2 {
             let bank = r_bank_forks.get(slot);
@@ -5038,6 +5140,9 @@ pub mod tests {
         gossip_duplicate_confirmed_slots.insert(2, duplicate_confirmed_bank2_hash);
         let mut duplicate_slots_tracker = DuplicateSlotsTracker::default();
         let mut duplicate_slots_to_repair = DuplicateSlotsToRepair::default();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
+        let (dumped_slots_sender, _dumped_slots_receiver) = unbounded();
 
         // Mark fork choice branch as invalid so select forks below doesn't panic
         // on a nonexistent `heaviest_bank_on_same_fork` after we dump the duplciate fork.
@@ -5053,6 +5158,7 @@ pub mod tests {
             &mut duplicate_slots_tracker,
             heaviest_subtree_fork_choice,
             &mut duplicate_slots_to_repair,
+            &ancestor_hashes_replay_update_sender,
             SlotStateUpdate::DuplicateConfirmed(duplicate_confirmed_state),
         );
         assert!(duplicate_slots_to_repair.contains(&(2, duplicate_confirmed_bank2_hash)));
@@ -5068,6 +5174,7 @@ pub mod tests {
             bank_forks,
             blockstore,
             None,
+            &dumped_slots_sender,
         );
 
         // Check everything was purged properly
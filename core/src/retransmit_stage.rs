@@ -2,19 +2,21 @@
 #![allow(clippy::rc_buffer)]
 
 use crate::{
+    ancestor_hashes_service::AncestorHashesReplayUpdateReceiver,
     cluster_info_vote_listener::VerifiedVoteReceiver,
-    cluster_nodes::ClusterNodes,
+    cluster_nodes::{ClusterNodes, RetransmitPeers},
     cluster_slots::ClusterSlots,
     cluster_slots_service::{ClusterSlotsService, ClusterSlotsUpdateReceiver},
     completed_data_sets_service::CompletedDataSetsSender,
-    repair_service::{DuplicateSlotsResetSender, RepairInfo},
+    repair_service::{DumpedSlotsReceiver, DuplicateSlotsResetSender, RepairInfo},
     result::{Error, Result},
+    turbine_tree::get_turbine_tree,
     window_service::{should_retransmit_and_persist, WindowService},
 };
 use crossbeam_channel::{Receiver, Sender};
 use lru::LruCache;
 use solana_client::rpc_response::SlotUpdate;
-use solana_gossip::cluster_info::{ClusterInfo, DATA_PLANE_FANOUT};
+use solana_gossip::cluster_info::ClusterInfo;
 use solana_ledger::shred::{get_shred_slot_index_type, ShredFetchStats};
 use solana_ledger::{blockstore::Blockstore, leader_schedule_cache::LeaderScheduleCache};
 use solana_measure::measure::Measure;
@@ -329,7 +331,6 @@ fn retransmit(
     let mut peers_len = 0;
     epoch_cache_update.stop();
 
-    let my_id = cluster_info.id();
     let mut discard_total = 0;
     let mut repair_total = 0;
     let mut retransmit_total = 0;
@@ -367,14 +368,16 @@ fn retransmit(
 
         let mut compute_turbine_peers = Measure::start("turbine_start");
         let slot_leader = leader_schedule_cache.slot_leader_at(shred_slot, Some(r_bank.deref()));
-        let (neighbors, children) =
-            cluster_nodes.get_retransmit_peers(packet.meta.seed, DATA_PLANE_FANOUT, slot_leader);
-        // If the node is on the critical path (i.e. the first node in each
-        // neighborhood), then we expect that the packet arrives at tvu socket
-        // as opposed to tvu-forwards. If this is not the case, then the
-        // turbine broadcast/retransmit tree is mismatched across nodes.
-        let anchor_node = neighbors[0].id == my_id;
-        if packet.meta.forward == anchor_node {
+        let turbine_tree = get_turbine_tree(shred_slot, &root_bank);
+        let RetransmitPeers {
+            forwarded,
+            tvu,
+            tvu_forwards,
+        } = cluster_nodes.get_retransmit_peers(turbine_tree, packet.meta.seed, slot_leader);
+        // If the packet does not arrive at the socket this node's position in
+        // the tree expects, then the turbine broadcast/retransmit tree is
+        // mismatched across nodes.
+        if packet.meta.forward != forwarded {
             // TODO: Consider forwarding the packet to the root node here.
             retransmit_tree_mismatch += 1;
         }
@@ -388,25 +391,12 @@ fn retransmit(
             .or_default() += 1;
 
         let mut retransmit_time = Measure::start("retransmit_to");
-        // If the node is on the critical path (i.e. the first node in each
-        // neighborhood), it should send the packet to tvu socket of its
-        // children and also tvu_forward socket of its neighbors. Otherwise it
-        // should only forward to tvu_forward socket of its children.
-        if anchor_node {
-            // First neighbor is this node itself, so skip it.
-            ClusterInfo::retransmit_to(
-                &neighbors[1..],
-                packet,
-                sock,
-                /*forward socket=*/ true,
-            );
+        if !tvu_forwards.is_empty() {
+            ClusterInfo::retransmit_to(&tvu_forwards, packet, sock, /*forward socket=*/ true);
+        }
+        if !tvu.is_empty() {
+            ClusterInfo::retransmit_to(&tvu, packet, sock, /*forward socket=*/ false);
         }
-        ClusterInfo::retransmit_to(
-            &children,
-            packet,
-            sock,
-            !anchor_node, // send to forward socket!
-        );
         retransmit_time.stop();
         retransmit_total += retransmit_time.as_us();
     }
@@ -528,6 +518,7 @@ impl RetransmitStage {
         cluster_info: &Arc<ClusterInfo>,
         retransmit_sockets: Arc<Vec<UdpSocket>>,
         repair_socket: Arc<UdpSocket>,
+        ancestor_hashes_socket: Arc<UdpSocket>,
         verified_receiver: Receiver<Vec<Packets>>,
         exit: &Arc<AtomicBool>,
         cluster_slots_update_receiver: ClusterSlotsUpdateReceiver,
@@ -542,6 +533,8 @@ impl RetransmitStage {
         max_slots: &Arc<MaxSlots>,
         rpc_subscriptions: Option<Arc<RpcSubscriptions>>,
         duplicate_slots_sender: Sender<Slot>,
+        ancestor_hashes_replay_update_receiver: AncestorHashesReplayUpdateReceiver,
+        dumped_slots_receiver: DumpedSlotsReceiver,
     ) -> Self {
         let (retransmit_sender, retransmit_receiver) = channel();
 
@@ -578,6 +571,7 @@ impl RetransmitStage {
             verified_receiver,
             retransmit_sender,
             repair_socket,
+            ancestor_hashes_socket,
             exit,
             repair_info,
             leader_schedule_cache,
@@ -600,6 +594,8 @@ impl RetransmitStage {
             verified_vote_receiver,
             completed_data_sets_sender,
             duplicate_slots_sender,
+            ancestor_hashes_replay_update_receiver,
+            dumped_slots_receiver,
         );
 
         Self {
@@ -741,7 +737,7 @@ mod tests {
         assert_eq!(check_if_already_received(&packet, &shreds_received), None);
         assert_eq!(check_if_already_received(&packet, &shreds_received), None);
 
-        let shred = Shred::new_empty_coding(slot, index, 0, 1, 1, version);
+        let shred = Shred::new_empty_coding(slot, index, 0, 1, 1, 0, version);
         shred.copy_to_packet(&mut packet);
         // Coding at (1, 5) passes
         assert_eq!(
@@ -751,7 +747,7 @@ mod tests {
         // then blocked
         assert_eq!(check_if_already_received(&packet, &shreds_received), None);
 
-        let shred = Shred::new_empty_coding(slot, index, 2, 1, 1, version);
+        let shred = Shred::new_empty_coding(slot, index, 2, 1, 1, 0, version);
         shred.copy_to_packet(&mut packet);
         // 2nd unique coding at (1, 5) passes
         assert_eq!(
@@ -761,7 +757,7 @@ mod tests {
         // same again is blocked
         assert_eq!(check_if_already_received(&packet, &shreds_received), None);
 
-        let shred = Shred::new_empty_coding(slot, index, 3, 1, 1, version);
+        let shred = Shred::new_empty_coding(slot, index, 3, 1, 1, 0, version);
         shred.copy_to_packet(&mut packet);
         // Another unique coding at (1, 5) always blocked
         assert_eq!(check_if_already_received(&packet, &shreds_received), None);
@@ -24,13 +24,20 @@ use solana_ledger::{
 };
 use solana_measure::measure::Measure;
 use solana_metrics::inc_new_counter_debug;
-use solana_perf::packet::{limited_deserialize, Packets, PacketsRecycler};
+use solana_perf::packet::{limited_deserialize, Packet, Packets, PacketsRecycler};
+use solana_runtime::bank_forks::BankForks;
 use solana_sdk::{
-    clock::Slot, hash::Hash, packet::PACKET_DATA_SIZE, pubkey::Pubkey, timing::duration_as_ms,
+    clock::{Epoch, Slot},
+    feature_set,
+    hash::Hash,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer, SIGNATURE_BYTES},
+    timing::{duration_as_ms, timestamp},
 };
 use solana_streamer::streamer::{PacketReceiver, PacketSender};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     net::SocketAddr,
     sync::atomic::{AtomicBool, Ordering},
     sync::{Arc, RwLock},
@@ -55,6 +62,21 @@ pub const MAX_ANCESTOR_RESPONSES: usize =
     MAX_ANCESTOR_BYTES_IN_PACKET / std::mem::size_of::<SlotHash>();
 #[cfg(test)]
 static_assertions::const_assert_eq!(MAX_ANCESTOR_RESPONSES, 30);
+// Signed requests whose timestamp is further than this from the local clock
+// are dropped.
+const SIGNED_REPAIR_TIME_WINDOW: Duration = Duration::from_secs(60 * 10);
+// Offset of the signature in a serialized signed request, after the enum
+// discriminator.
+const SIGNED_REPAIR_SIGNATURE_OFFSET: usize = 4;
+// Interval over which the per-requester budgets of signed requests are
+// replenished.
+const REPAIR_BUDGET_INTERVAL: Duration = Duration::from_secs(1);
+// Signed requests served per interval to any staked requester.
+const BASE_REPAIR_BUDGET: usize = 128;
+// Signed requests served per interval, split among requesters by stake.
+const STAKED_REPAIR_BUDGET: usize = 50_000;
+// Signed requests served per interval to all unstaked requesters combined.
+const UNSTAKED_REPAIR_BUDGET: usize = 1_024;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum ShredRepairType {
@@ -95,14 +117,14 @@ impl RequestResponse for ShredRepairType {
     }
 }
 
-pub struct AncestorHashesRepair(Slot);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AncestorHashesRepair(pub Slot);
 #[derive(Serialize, Deserialize)]
 pub enum AncestorHashesResponseVersion {
     Current(Vec<SlotHash>),
 }
 impl AncestorHashesResponseVersion {
-    #[cfg(test)]
-    fn into_slot_hashes(self) -> Vec<SlotHash> {
+    pub fn into_slot_hashes(self) -> Vec<SlotHash> {
         match self {
             AncestorHashesResponseVersion::Current(slot_hashes) => slot_hashes,
         }
@@ -141,6 +163,33 @@ pub struct ServeRepairStats {
     pub highest_window_index: usize,
     pub orphan: usize,
     pub ancestor_hashes: usize,
+    pub signed_requests: usize,
+    pub err_sig_verify: usize,
+    pub err_id_mismatch: usize,
+    pub err_time_skew: usize,
+    pub err_unsigned: usize,
+    pub over_budget: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RepairRequestHeader {
+    signature: Signature,
+    sender: Pubkey,
+    recipient: Pubkey,
+    timestamp: u64,
+    nonce: Nonce,
+}
+
+impl RepairRequestHeader {
+    pub fn new(sender: Pubkey, recipient: Pubkey, timestamp: u64, nonce: Nonce) -> Self {
+        Self {
+            signature: Signature::default(),
+            sender,
+            recipient,
+            timestamp,
+            nonce,
+        }
+    }
 }
 
 /// Window protocol messages
@@ -153,6 +202,144 @@ pub enum RepairProtocol {
     HighestWindowIndexWithNonce(ContactInfo, Slot, u64, Nonce),
     OrphanWithNonce(ContactInfo, Slot, Nonce),
     AncestorHashes(ContactInfo, Slot, Nonce),
+    SignedWindowIndex {
+        header: RepairRequestHeader,
+        slot: Slot,
+        shred_index: u64,
+    },
+    SignedHighestWindowIndex {
+        header: RepairRequestHeader,
+        slot: Slot,
+        shred_index: u64,
+    },
+    SignedOrphan {
+        header: RepairRequestHeader,
+        slot: Slot,
+    },
+    SignedAncestorHashes {
+        header: RepairRequestHeader,
+        slot: Slot,
+    },
+}
+
+impl RepairProtocol {
+    fn sender(&self) -> &Pubkey {
+        match self {
+            RepairProtocol::WindowIndex(from, _, _)
+            | RepairProtocol::HighestWindowIndex(from, _, _)
+            | RepairProtocol::Orphan(from, _)
+            | RepairProtocol::WindowIndexWithNonce(from, _, _, _)
+            | RepairProtocol::HighestWindowIndexWithNonce(from, _, _, _)
+            | RepairProtocol::OrphanWithNonce(from, _, _)
+            | RepairProtocol::AncestorHashes(from, _, _) => &from.id,
+            RepairProtocol::SignedWindowIndex { header, .. }
+            | RepairProtocol::SignedHighestWindowIndex { header, .. }
+            | RepairProtocol::SignedOrphan { header, .. }
+            | RepairProtocol::SignedAncestorHashes { header, .. } => &header.sender,
+        }
+    }
+
+    fn header(&self) -> Option<&RepairRequestHeader> {
+        match self {
+            RepairProtocol::WindowIndex(..)
+            | RepairProtocol::HighestWindowIndex(..)
+            | RepairProtocol::Orphan(..)
+            | RepairProtocol::WindowIndexWithNonce(..)
+            | RepairProtocol::HighestWindowIndexWithNonce(..)
+            | RepairProtocol::OrphanWithNonce(..)
+            | RepairProtocol::AncestorHashes(..) => None,
+            RepairProtocol::SignedWindowIndex { header, .. }
+            | RepairProtocol::SignedHighestWindowIndex { header, .. }
+            | RepairProtocol::SignedOrphan { header, .. }
+            | RepairProtocol::SignedAncestorHashes { header, .. } => Some(header),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RepairVerifyError {
+    IdMismatch,
+    TimeSkew,
+}
+
+// Number of signed requests served to each staked requester within the
+// current interval, capped in proportion to the requester's stake. Unstaked
+// requesters share a single budget so that new identities gain nothing.
+struct RepairBudgets {
+    epoch: Option<Epoch>,
+    stakes: HashMap<Pubkey, u64>,
+    total_stake: u64,
+    interval_start: Instant,
+    used: HashMap<Pubkey, usize>,
+    unstaked_used: usize,
+}
+
+impl RepairBudgets {
+    fn new(now: Instant) -> Self {
+        Self {
+            epoch: None,
+            stakes: HashMap::default(),
+            total_stake: 0,
+            interval_start: now,
+            used: HashMap::default(),
+            unstaked_used: 0,
+        }
+    }
+
+    fn maybe_update_stakes(&mut self, bank_forks: &RwLock<BankForks>) {
+        let root_bank = bank_forks.read().unwrap().root_bank();
+        let epoch = root_bank.epoch();
+        if self.epoch != Some(epoch) {
+            self.stakes = root_bank.epoch_staked_nodes(epoch).unwrap_or_default();
+            self.total_stake = self.stakes.values().sum();
+            self.epoch = Some(epoch);
+        }
+    }
+
+    fn is_staked(&self, pubkey: &Pubkey) -> bool {
+        self.stakes.get(pubkey).copied().unwrap_or_default() > 0
+    }
+
+    fn budget(&self, pubkey: &Pubkey) -> usize {
+        if !self.is_staked(pubkey) {
+            return UNSTAKED_REPAIR_BUDGET;
+        }
+        let stake = self.stakes[pubkey];
+        let staked_budget = (STAKED_REPAIR_BUDGET as u128 * stake as u128)
+            .checked_div(self.total_stake as u128)
+            .unwrap_or_default();
+        BASE_REPAIR_BUDGET + staked_budget as usize
+    }
+
+    fn used(&self, pubkey: &Pubkey) -> usize {
+        if self.is_staked(pubkey) {
+            self.used.get(pubkey).copied().unwrap_or_default()
+        } else {
+            self.unstaked_used
+        }
+    }
+
+    // Returns false if the requester's budget for the current interval is
+    // already spent.
+    fn has_budget(&mut self, pubkey: &Pubkey, now: Instant) -> bool {
+        if now.duration_since(self.interval_start) >= REPAIR_BUDGET_INTERVAL {
+            self.used.clear();
+            self.unstaked_used = 0;
+            self.interval_start = now;
+        }
+        self.used(pubkey) < self.budget(pubkey)
+    }
+
+    // Charges one request to the requester. Only requests whose signature
+    // verified are charged, so that forged requests cannot spend the budget
+    // of the node they claim to come from.
+    fn charge(&mut self, pubkey: &Pubkey) {
+        if self.is_staked(pubkey) {
+            *self.used.entry(*pubkey).or_default() += 1;
+        } else {
+            self.unstaked_used += 1;
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -213,18 +400,6 @@ impl ServeRepair {
         self.cluster_info.id()
     }
 
-    fn get_repair_sender(request: &RepairProtocol) -> &ContactInfo {
-        match request {
-            RepairProtocol::WindowIndex(ref from, _, _) => from,
-            RepairProtocol::HighestWindowIndex(ref from, _, _) => from,
-            RepairProtocol::Orphan(ref from, _) => from,
-            RepairProtocol::WindowIndexWithNonce(ref from, _, _, _) => from,
-            RepairProtocol::HighestWindowIndexWithNonce(ref from, _, _, _) => from,
-            RepairProtocol::OrphanWithNonce(ref from, _, _) => from,
-            RepairProtocol::AncestorHashes(ref from, _, _) => from,
-        }
-    }
-
     fn handle_repair(
         me: &Arc<RwLock<Self>>,
         recycler: &PacketsRecycler,
@@ -236,16 +411,20 @@ impl ServeRepair {
         let now = Instant::now();
 
         let my_id = me.read().unwrap().my_id();
-        //TODO: verify `from` is signed
-        let from = Self::get_repair_sender(&request);
-        if from.id == my_id {
+        let from = request.sender();
+        if *from == my_id {
             stats.self_repair += 1;
             return None;
         }
 
         let (res, label) = {
             match &request {
-                RepairProtocol::WindowIndexWithNonce(_, slot, shred_index, nonce) => {
+                RepairProtocol::WindowIndexWithNonce(_, slot, shred_index, nonce)
+                | RepairProtocol::SignedWindowIndex {
+                    header: RepairRequestHeader { nonce, .. },
+                    slot,
+                    shred_index,
+                } => {
                     stats.window_index += 1;
                     (
                         Self::run_window_request(
@@ -261,7 +440,12 @@ impl ServeRepair {
                         "WindowIndexWithNonce",
                     )
                 }
-                RepairProtocol::HighestWindowIndexWithNonce(_, slot, highest_index, nonce) => {
+                RepairProtocol::HighestWindowIndexWithNonce(_, slot, highest_index, nonce)
+                | RepairProtocol::SignedHighestWindowIndex {
+                    header: RepairRequestHeader { nonce, .. },
+                    slot,
+                    shred_index: highest_index,
+                } => {
                     stats.highest_window_index += 1;
                     (
                         Self::run_highest_window_request(
@@ -275,7 +459,11 @@ impl ServeRepair {
                         "HighestWindowIndexWithNonce",
                     )
                 }
-                RepairProtocol::OrphanWithNonce(_, slot, nonce) => {
+                RepairProtocol::OrphanWithNonce(_, slot, nonce)
+                | RepairProtocol::SignedOrphan {
+                    header: RepairRequestHeader { nonce, .. },
+                    slot,
+                } => {
                     stats.orphan += 1;
                     (
                         Self::run_orphan(
@@ -289,7 +477,11 @@ impl ServeRepair {
                         "OrphanWithNonce",
                     )
                 }
-                RepairProtocol::AncestorHashes(_, slot, nonce) => {
+                RepairProtocol::AncestorHashes(_, slot, nonce)
+                | RepairProtocol::SignedAncestorHashes {
+                    header: RepairRequestHeader { nonce, .. },
+                    slot,
+                } => {
                     stats.ancestor_hashes += 1;
                     (
                         Self::run_ancestor_hashes(recycler, from_addr, blockstore, *slot, *nonce),
@@ -305,6 +497,52 @@ impl ServeRepair {
         res
     }
 
+    // Returns true if the request is either unsigned while unsigned requests
+    // are still accepted, or properly signed, addressed to this node and within
+    // the sender's budget.
+    fn check_signed_request(
+        my_id: &Pubkey,
+        packet: &Packet,
+        request: &RepairProtocol,
+        budgets: &mut RepairBudgets,
+        reject_unsigned_requests: bool,
+        now: Instant,
+        stats: &mut ServeRepairStats,
+    ) -> bool {
+        let header = match request.header() {
+            None if reject_unsigned_requests => {
+                stats.err_unsigned += 1;
+                return false;
+            }
+            None => return true,
+            Some(header) => header,
+        };
+        stats.signed_requests += 1;
+        // The signature is verified last, as it is the most expensive check.
+        match verify_signed_request_header(my_id, header, timestamp()) {
+            Ok(()) => (),
+            Err(RepairVerifyError::IdMismatch) => {
+                stats.err_id_mismatch += 1;
+                return false;
+            }
+            Err(RepairVerifyError::TimeSkew) => {
+                stats.err_time_skew += 1;
+                return false;
+            }
+        }
+        if !budgets.has_budget(&header.sender, now) {
+            stats.over_budget += 1;
+            return false;
+        }
+        let data = &packet.data[..packet.meta.size];
+        if !verify_signed_request_signature(header, data) {
+            stats.err_sig_verify += 1;
+            return false;
+        }
+        budgets.charge(&header.sender);
+        true
+    }
+
     fn report_time_spent(label: &str, time: &Duration, extra: &str) {
         let count = duration_as_ms(time);
         if count > 5 {
@@ -313,6 +551,7 @@ impl ServeRepair {
     }
 
     /// Process messages from the network
+    #[allow(clippy::too_many_arguments)]
     fn run_listen(
         obj: &Arc<RwLock<Self>>,
         recycler: &PacketsRecycler,
@@ -321,6 +560,8 @@ impl ServeRepair {
         response_sender: &PacketSender,
         stats: &mut ServeRepairStats,
         max_packets: &mut usize,
+        budgets: &mut RepairBudgets,
+        reject_unsigned_requests: bool,
     ) -> Result<()> {
         //TODO cache connections
         let timeout = Duration::new(1, 0);
@@ -343,7 +584,16 @@ impl ServeRepair {
 
         let mut time = Measure::start("repair::handle_packets");
         for reqs in reqs_v {
-            Self::handle_packets(obj, recycler, blockstore, reqs, response_sender, stats);
+            Self::handle_packets(
+                obj,
+                recycler,
+                blockstore,
+                reqs,
+                response_sender,
+                stats,
+                budgets,
+                reject_unsigned_requests,
+            );
         }
         time.stop();
         if total_packets >= *max_packets {
@@ -384,12 +634,22 @@ impl ServeRepair {
             "serve_repair-request-ancestor-hashes",
             stats.ancestor_hashes
         );
+        datapoint_info!(
+            "serve_repair-signed-requests",
+            ("signed_requests", stats.signed_requests, i64),
+            ("err_sig_verify", stats.err_sig_verify, i64),
+            ("err_id_mismatch", stats.err_id_mismatch, i64),
+            ("err_time_skew", stats.err_time_skew, i64),
+            ("err_unsigned", stats.err_unsigned, i64),
+            ("over_budget", stats.over_budget, i64),
+        );
         *stats = ServeRepairStats::default();
     }
 
     pub fn listen(
         me: Arc<RwLock<Self>>,
         blockstore: Option<Arc<Blockstore>>,
+        bank_forks: Option<Arc<RwLock<BankForks>>>,
         requests_receiver: PacketReceiver,
         response_sender: PacketSender,
         exit: &Arc<AtomicBool>,
@@ -402,7 +662,21 @@ impl ServeRepair {
                 let mut last_print = Instant::now();
                 let mut stats = ServeRepairStats::default();
                 let mut max_packets = 1024;
+                let mut budgets = RepairBudgets::new(Instant::now());
                 loop {
+                    // Once the feature is active every node signs its requests.
+                    let reject_unsigned_requests = match &bank_forks {
+                        Some(bank_forks) => {
+                            budgets.maybe_update_stakes(bank_forks);
+                            bank_forks
+                                .read()
+                                .unwrap()
+                                .root_bank()
+                                .feature_set
+                                .is_active(&feature_set::sign_repair_requests::id())
+                        }
+                        None => false,
+                    };
                     let result = Self::run_listen(
                         &me,
                         &recycler,
@@ -411,6 +685,8 @@ impl ServeRepair {
                         &response_sender,
                         &mut stats,
                         &mut max_packets,
+                        &mut budgets,
+                        reject_unsigned_requests,
                     );
                     match result {
                         Err(Error::RecvTimeout(_)) | Ok(_) => {}
@@ -428,6 +704,7 @@ impl ServeRepair {
             .unwrap()
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn handle_packets(
         me: &Arc<RwLock<Self>>,
         recycler: &PacketsRecycler,
@@ -435,13 +712,28 @@ impl ServeRepair {
         packets: Packets,
         response_sender: &PacketSender,
         stats: &mut ServeRepairStats,
+        budgets: &mut RepairBudgets,
+        reject_unsigned_requests: bool,
     ) {
+        let my_id = me.read().unwrap().my_id();
+        let now = Instant::now();
         // iter over the packets
         packets.packets.iter().for_each(|packet| {
             let from_addr = packet.meta.addr();
             limited_deserialize(&packet.data[..packet.meta.size])
                 .into_iter()
                 .for_each(|request| {
+                    if !Self::check_signed_request(
+                        &my_id,
+                        packet,
+                        &request,
+                        budgets,
+                        reject_unsigned_requests,
+                        now,
+                        stats,
+                    ) {
+                        return;
+                    }
                     stats.processed += 1;
                     let rsp =
                         Self::handle_repair(me, recycler, &from_addr, blockstore, request, stats);
@@ -452,35 +744,6 @@ impl ServeRepair {
         });
     }
 
-    fn window_index_request_bytes(
-        &self,
-        slot: Slot,
-        shred_index: u64,
-        nonce: Nonce,
-    ) -> Result<Vec<u8>> {
-        let req = RepairProtocol::WindowIndexWithNonce(self.my_info(), slot, shred_index, nonce);
-        let out = serialize(&req)?;
-        Ok(out)
-    }
-
-    fn window_highest_index_request_bytes(
-        &self,
-        slot: Slot,
-        shred_index: u64,
-        nonce: Nonce,
-    ) -> Result<Vec<u8>> {
-        let req =
-            RepairProtocol::HighestWindowIndexWithNonce(self.my_info(), slot, shred_index, nonce);
-        let out = serialize(&req)?;
-        Ok(out)
-    }
-
-    fn orphan_bytes(&self, slot: Slot, nonce: Nonce) -> Result<Vec<u8>> {
-        let req = RepairProtocol::OrphanWithNonce(self.my_info(), slot, nonce);
-        let out = serialize(&req)?;
-        Ok(out)
-    }
-
     pub(crate) fn repair_request(
         &self,
         cluster_slots: &ClusterSlots,
@@ -489,6 +752,7 @@ impl ServeRepair {
         repair_stats: &mut RepairStats,
         repair_validators: &Option<HashSet<Pubkey>>,
         outstanding_requests: &mut OutstandingShredRepairs,
+        identity_keypair: Option<&Keypair>,
     ) -> Result<(SocketAddr, Vec<u8>)> {
         // find a peer that appears to be accepting replication and has the desired slot, as indicated
         // by a valid tvu port location
@@ -507,7 +771,13 @@ impl ServeRepair {
         let (peer, addr) = repair_peers.sample(&mut rand::thread_rng());
         let nonce =
             outstanding_requests.add_request(repair_request, solana_sdk::timing::timestamp());
-        let out = self.map_repair_request(&repair_request, &peer, repair_stats, nonce)?;
+        let out = self.map_repair_request(
+            &repair_request,
+            &peer,
+            repair_stats,
+            nonce,
+            identity_keypair,
+        )?;
         Ok((addr, out))
     }
 
@@ -523,13 +793,18 @@ impl ServeRepair {
         }
         let weights = cluster_slots.compute_weights_exclude_nonfrozen(slot, &repair_peers);
         let mut sampled_validators = weighted_shuffle(
-            weights.into_iter().map(|(stake, _i)| stake),
+            weights.iter().map(|(stake, _i)| *stake),
             solana_sdk::pubkey::new_rand().to_bytes(),
         );
         sampled_validators.truncate(ANCESTOR_HASH_REPAIR_SAMPLE_SIZE);
+        // Shuffled indices are into `weights`, which only has the peers that
+        // have the slot.
         Ok(sampled_validators
             .into_iter()
-            .map(|i| (repair_peers[i].id, repair_peers[i].serve_repair))
+            .map(|i| {
+                let (_stake, i) = weights[i];
+                (repair_peers[i].id, repair_peers[i].serve_repair)
+            })
             .collect())
     }
 
@@ -554,23 +829,88 @@ impl ServeRepair {
         repair_peer_id: &Pubkey,
         repair_stats: &mut RepairStats,
         nonce: Nonce,
+        identity_keypair: Option<&Keypair>,
     ) -> Result<Vec<u8>> {
-        match repair_request {
+        let header = identity_keypair.map(|keypair| {
+            RepairRequestHeader::new(keypair.pubkey(), *repair_peer_id, timestamp(), nonce)
+        });
+        let request = match repair_request {
             ShredRepairType::Shred(slot, shred_index) => {
                 repair_stats
                     .shred
                     .update(repair_peer_id, *slot, *shred_index);
-                Ok(self.window_index_request_bytes(*slot, *shred_index, nonce)?)
+                match header {
+                    Some(header) => RepairProtocol::SignedWindowIndex {
+                        header,
+                        slot: *slot,
+                        shred_index: *shred_index,
+                    },
+                    None => RepairProtocol::WindowIndexWithNonce(
+                        self.my_info(),
+                        *slot,
+                        *shred_index,
+                        nonce,
+                    ),
+                }
             }
             ShredRepairType::HighestShred(slot, shred_index) => {
                 repair_stats
                     .highest_shred
                     .update(repair_peer_id, *slot, *shred_index);
-                Ok(self.window_highest_index_request_bytes(*slot, *shred_index, nonce)?)
+                match header {
+                    Some(header) => RepairProtocol::SignedHighestWindowIndex {
+                        header,
+                        slot: *slot,
+                        shred_index: *shred_index,
+                    },
+                    None => RepairProtocol::HighestWindowIndexWithNonce(
+                        self.my_info(),
+                        *slot,
+                        *shred_index,
+                        nonce,
+                    ),
+                }
             }
             ShredRepairType::Orphan(slot) => {
                 repair_stats.orphan.update(repair_peer_id, *slot, 0);
-                Ok(self.orphan_bytes(*slot, nonce)?)
+                match header {
+                    Some(header) => RepairProtocol::SignedOrphan {
+                        header,
+                        slot: *slot,
+                    },
+                    None => RepairProtocol::OrphanWithNonce(self.my_info(), *slot, nonce),
+                }
+            }
+        };
+        match identity_keypair {
+            Some(keypair) => repair_proto_to_bytes(&request, keypair),
+            None => Ok(serialize(&request)?),
+        }
+    }
+
+    pub fn ancestor_repair_request_bytes(
+        &self,
+        request_slot: Slot,
+        repair_peer_id: &Pubkey,
+        nonce: Nonce,
+        identity_keypair: Option<&Keypair>,
+    ) -> Result<Vec<u8>> {
+        match identity_keypair {
+            Some(keypair) => {
+                let request = RepairProtocol::SignedAncestorHashes {
+                    header: RepairRequestHeader::new(
+                        keypair.pubkey(),
+                        *repair_peer_id,
+                        timestamp(),
+                        nonce,
+                    ),
+                    slot: request_slot,
+                };
+                repair_proto_to_bytes(&request, keypair)
+            }
+            None => {
+                let request = RepairProtocol::AncestorHashes(self.my_info(), request_slot, nonce);
+                Ok(serialize(&request)?)
             }
         }
     }
@@ -598,7 +938,7 @@ impl ServeRepair {
 
     fn run_window_request(
         recycler: &PacketsRecycler,
-        from: &ContactInfo,
+        from: &Pubkey,
         from_addr: &SocketAddr,
         blockstore: Option<&Arc<Blockstore>>,
         my_id: &Pubkey,
@@ -630,7 +970,7 @@ impl ServeRepair {
         trace!(
             "{}: failed WindowIndex {} {} {}",
             my_id,
-            from.id,
+            from,
             slot,
             shred_index,
         );
@@ -742,6 +1082,49 @@ impl ServeRepair {
     }
 }
 
+// Signs everything in the serialized request but the signature itself.
+fn repair_proto_to_bytes(request: &RepairProtocol, keypair: &Keypair) -> Result<Vec<u8>> {
+    debug_assert!(request.header().is_some());
+    let mut payload = serialize(request)?;
+    let signature = keypair.sign_message(&signed_repair_data(&payload));
+    payload[SIGNED_REPAIR_SIGNATURE_OFFSET..SIGNED_REPAIR_SIGNATURE_OFFSET + SIGNATURE_BYTES]
+        .copy_from_slice(signature.as_ref());
+    Ok(payload)
+}
+
+fn signed_repair_data(payload: &[u8]) -> Vec<u8> {
+    [
+        &payload[..SIGNED_REPAIR_SIGNATURE_OFFSET],
+        &payload[SIGNED_REPAIR_SIGNATURE_OFFSET + SIGNATURE_BYTES..],
+    ]
+    .concat()
+}
+
+fn verify_signed_request_header(
+    my_id: &Pubkey,
+    header: &RepairRequestHeader,
+    now: u64,
+) -> std::result::Result<(), RepairVerifyError> {
+    if header.recipient != *my_id {
+        return Err(RepairVerifyError::IdMismatch);
+    }
+    let time_diff_ms = if now > header.timestamp {
+        now - header.timestamp
+    } else {
+        header.timestamp - now
+    };
+    if u128::from(time_diff_ms) > SIGNED_REPAIR_TIME_WINDOW.as_millis() {
+        return Err(RepairVerifyError::TimeSkew);
+    }
+    Ok(())
+}
+
+fn verify_signed_request_signature(header: &RepairRequestHeader, data: &[u8]) -> bool {
+    header
+        .signature
+        .verify(header.sender.as_ref(), &signed_repair_data(data))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -855,7 +1238,7 @@ mod tests {
             };
             let rv = ServeRepair::run_window_request(
                 &recycler,
-                &me,
+                &me.id,
                 &socketaddr_any!(),
                 Some(&blockstore),
                 &me.id,
@@ -873,7 +1256,7 @@ mod tests {
             let index = 1;
             let rv = ServeRepair::run_window_request(
                 &recycler,
-                &me,
+                &me.id,
                 &socketaddr_any!(),
                 Some(&blockstore),
                 &me.id,
@@ -913,6 +1296,7 @@ mod tests {
             &mut RepairStats::default(),
             &None,
             &mut outstanding_requests,
+            None,
         );
         assert_matches!(rv, Err(Error::ClusterInfo(ClusterInfoError::NoPeers)));
 
@@ -941,6 +1325,7 @@ mod tests {
                 &mut RepairStats::default(),
                 &None,
                 &mut outstanding_requests,
+                None,
             )
             .unwrap();
         assert_eq!(nxt.serve_repair, serve_repair_addr);
@@ -975,6 +1360,7 @@ mod tests {
                     &mut RepairStats::default(),
                     &None,
                     &mut outstanding_requests,
+                    None,
                 )
                 .unwrap();
             if rv.0 == serve_repair_addr {
@@ -1239,6 +1625,7 @@ mod tests {
                     &mut RepairStats::default(),
                     &trusted_validators,
                     &mut OutstandingShredRepairs::default(),
+                    None,
                 )
                 .is_err());
         }
@@ -1256,6 +1643,7 @@ mod tests {
                 &mut RepairStats::default(),
                 &trusted_validators,
                 &mut OutstandingShredRepairs::default(),
+                None,
             )
             .is_ok());
 
@@ -1277,10 +1665,256 @@ mod tests {
                 &mut RepairStats::default(),
                 &None,
                 &mut OutstandingShredRepairs::default(),
+                None,
             )
             .is_ok());
     }
 
+    #[test]
+    fn test_signed_repair_request() {
+        let keypair = Keypair::new();
+        let me = ContactInfo::new_localhost(&keypair.pubkey(), timestamp());
+        let serve_repair = ServeRepair::new_with_invalid_keypair(me);
+        let recipient = solana_sdk::pubkey::new_rand();
+        let nonce = 42;
+        for repair_type in [
+            ShredRepairType::Shred(5, 7),
+            ShredRepairType::HighestShred(5, 7),
+            ShredRepairType::Orphan(5),
+        ] {
+            let mut payload = serve_repair
+                .map_repair_request(
+                    &repair_type,
+                    &recipient,
+                    &mut RepairStats::default(),
+                    nonce,
+                    Some(&keypair),
+                )
+                .unwrap();
+            let request: RepairProtocol = limited_deserialize(&payload).unwrap();
+            let header = request.header().unwrap().clone();
+            assert_eq!(request.sender(), &keypair.pubkey());
+            assert_eq!(header.recipient, recipient);
+            assert_eq!(header.nonce, nonce);
+            let now = header.timestamp;
+            assert_eq!(
+                verify_signed_request_header(&recipient, &header, now),
+                Ok(())
+            );
+            assert!(verify_signed_request_signature(&header, &payload));
+            // Addressed to another node.
+            assert_eq!(
+                verify_signed_request_header(&keypair.pubkey(), &header, now),
+                Err(RepairVerifyError::IdMismatch)
+            );
+            // Replayed long after it was signed.
+            let later = now + SIGNED_REPAIR_TIME_WINDOW.as_millis() as u64 + 1;
+            assert_eq!(
+                verify_signed_request_header(&recipient, &header, later),
+                Err(RepairVerifyError::TimeSkew)
+            );
+            // Tampered with after signing.
+            *payload.last_mut().unwrap() ^= 1;
+            assert!(!verify_signed_request_signature(&header, &payload));
+        }
+
+        let payload = serve_repair
+            .ancestor_repair_request_bytes(5, &recipient, nonce, Some(&keypair))
+            .unwrap();
+        let request: RepairProtocol = limited_deserialize(&payload).unwrap();
+        let header = request.header().unwrap().clone();
+        assert_matches!(
+            request,
+            RepairProtocol::SignedAncestorHashes { slot: 5, .. }
+        );
+        assert_eq!(
+            verify_signed_request_header(&recipient, &header, header.timestamp),
+            Ok(())
+        );
+        assert!(verify_signed_request_signature(&header, &payload));
+
+        // Without a keypair the legacy unsigned request is sent.
+        let payload = serve_repair
+            .map_repair_request(
+                &ShredRepairType::Shred(5, 7),
+                &recipient,
+                &mut RepairStats::default(),
+                nonce,
+                None,
+            )
+            .unwrap();
+        let request: RepairProtocol = limited_deserialize(&payload).unwrap();
+        assert_matches!(request, RepairProtocol::WindowIndexWithNonce(_, 5, 7, 42));
+        assert!(request.header().is_none());
+    }
+
+    #[test]
+    fn test_check_signed_request() {
+        let my_id = solana_sdk::pubkey::new_rand();
+        let me = ContactInfo::new_localhost(&my_id, timestamp());
+        let serve_repair = ServeRepair::new_with_invalid_keypair(me);
+        let keypair = Keypair::new();
+        let payload = serve_repair
+            .map_repair_request(
+                &ShredRepairType::Orphan(5),
+                &my_id,
+                &mut RepairStats::default(),
+                0,
+                Some(&keypair),
+            )
+            .unwrap();
+        let mut packet = Packet::default();
+        packet.data[..payload.len()].copy_from_slice(&payload);
+        packet.meta.size = payload.len();
+        let request: RepairProtocol = limited_deserialize(&payload).unwrap();
+
+        let now = Instant::now();
+        let mut budgets = RepairBudgets::new(now);
+        let mut stats = ServeRepairStats::default();
+        for _ in 0..UNSTAKED_REPAIR_BUDGET {
+            assert!(ServeRepair::check_signed_request(
+                &my_id,
+                &packet,
+                &request,
+                &mut budgets,
+                false,
+                now,
+                &mut stats
+            ));
+        }
+        assert!(!ServeRepair::check_signed_request(
+            &my_id,
+            &packet,
+            &request,
+            &mut budgets,
+            false,
+            now,
+            &mut stats
+        ));
+        assert_eq!(stats.signed_requests, UNSTAKED_REPAIR_BUDGET + 1);
+        assert_eq!(stats.over_budget, 1);
+        // All unstaked requesters share the same budget.
+        let other_payload = serve_repair
+            .map_repair_request(
+                &ShredRepairType::Orphan(5),
+                &my_id,
+                &mut RepairStats::default(),
+                0,
+                Some(&Keypair::new()),
+            )
+            .unwrap();
+        let mut other_packet = Packet::default();
+        other_packet.data[..other_payload.len()].copy_from_slice(&other_payload);
+        other_packet.meta.size = other_payload.len();
+        let other_request: RepairProtocol = limited_deserialize(&other_payload).unwrap();
+        assert!(!ServeRepair::check_signed_request(
+            &my_id,
+            &other_packet,
+            &other_request,
+            &mut budgets,
+            false,
+            now,
+            &mut stats
+        ));
+        assert_eq!(stats.over_budget, 2);
+        // The budget is replenished in the next interval.
+        assert!(ServeRepair::check_signed_request(
+            &my_id,
+            &packet,
+            &request,
+            &mut budgets,
+            false,
+            now + REPAIR_BUDGET_INTERVAL,
+            &mut stats
+        ));
+        // Requests addressed to another node are dropped.
+        assert!(!ServeRepair::check_signed_request(
+            &keypair.pubkey(),
+            &packet,
+            &request,
+            &mut budgets,
+            false,
+            now,
+            &mut stats
+        ));
+        assert_eq!(stats.err_id_mismatch, 1);
+        // Requests that fail signature verification are dropped.
+        packet.data[packet.meta.size - 1] ^= 1;
+        assert!(!ServeRepair::check_signed_request(
+            &my_id,
+            &packet,
+            &request,
+            &mut budgets,
+            false,
+            now + REPAIR_BUDGET_INTERVAL,
+            &mut stats
+        ));
+        assert_eq!(stats.err_sig_verify, 1);
+
+        // Unsigned requests are dropped once every node signs its requests.
+        let payload = serve_repair
+            .map_repair_request(
+                &ShredRepairType::Orphan(5),
+                &my_id,
+                &mut RepairStats::default(),
+                0,
+                None,
+            )
+            .unwrap();
+        let mut packet = Packet::default();
+        packet.data[..payload.len()].copy_from_slice(&payload);
+        packet.meta.size = payload.len();
+        let request: RepairProtocol = limited_deserialize(&payload).unwrap();
+        assert!(ServeRepair::check_signed_request(
+            &my_id,
+            &packet,
+            &request,
+            &mut budgets,
+            false,
+            now,
+            &mut stats
+        ));
+        assert!(!ServeRepair::check_signed_request(
+            &my_id,
+            &packet,
+            &request,
+            &mut budgets,
+            true,
+            now,
+            &mut stats
+        ));
+        assert_eq!(stats.err_unsigned, 1);
+    }
+
+    #[test]
+    fn test_repair_budgets() {
+        let staked = solana_sdk::pubkey::new_rand();
+        let unstaked = solana_sdk::pubkey::new_rand();
+        let mut budgets = RepairBudgets::new(Instant::now());
+        assert_eq!(budgets.budget(&staked), UNSTAKED_REPAIR_BUDGET);
+        budgets.stakes = vec![(staked, 30), (solana_sdk::pubkey::new_rand(), 70)]
+            .into_iter()
+            .collect();
+        budgets.total_stake = 100;
+        assert_eq!(
+            budgets.budget(&staked),
+            BASE_REPAIR_BUDGET + STAKED_REPAIR_BUDGET * 3 / 10
+        );
+        assert_eq!(budgets.budget(&unstaked), UNSTAKED_REPAIR_BUDGET);
+
+        // Staked requesters are charged individually, unstaked ones together.
+        let now = Instant::now();
+        assert!(budgets.has_budget(&staked, now));
+        budgets.charge(&staked);
+        budgets.charge(&unstaked);
+        budgets.charge(&solana_sdk::pubkey::new_rand());
+        assert_eq!(budgets.used(&staked), 1);
+        assert_eq!(budgets.used(&unstaked), 2);
+        assert!(budgets.has_budget(&unstaked, now + REPAIR_BUDGET_INTERVAL));
+        assert_eq!(budgets.used(&staked), 0);
+        assert_eq!(budgets.used(&unstaked), 0);
+    }
+
     #[test]
     fn test_verify_shred_response() {
         let repair = ShredRepairType::Orphan(9);
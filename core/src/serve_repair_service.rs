@@ -1,7 +1,8 @@
 use crate::serve_repair::ServeRepair;
 use solana_ledger::blockstore::Blockstore;
 use solana_perf::recycler::Recycler;
-use solana_streamer::streamer;
+use solana_runtime::bank_forks::BankForks;
+use solana_streamer::streamer::{self, PacketReceiverConfig};
 use std::net::UdpSocket;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::channel;
@@ -16,7 +17,9 @@ impl ServeRepairService {
     pub fn new(
         serve_repair: &Arc<RwLock<ServeRepair>>,
         blockstore: Option<Arc<Blockstore>>,
+        bank_forks: Option<Arc<RwLock<BankForks>>>,
         serve_repair_socket: UdpSocket,
+        receiver_config: PacketReceiverConfig,
         exit: &Arc<AtomicBool>,
     ) -> Self {
         let (request_sender, request_receiver) = channel();
@@ -34,6 +37,7 @@ impl ServeRepairService {
             "serve_repair_receiver",
             1,
             false,
+            receiver_config,
         );
         let (response_sender, response_receiver) = channel();
         let t_responder =
@@ -41,6 +45,7 @@ impl ServeRepairService {
         let t_listen = ServeRepair::listen(
             serve_repair.clone(),
             blockstore,
+            bank_forks,
             request_receiver,
             response_sender,
             exit,
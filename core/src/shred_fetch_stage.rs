@@ -2,13 +2,16 @@
 
 use crate::packet_hasher::PacketHasher;
 use lru::LruCache;
-use solana_ledger::shred::{get_shred_slot_index_type, ShredFetchStats};
+use solana_ledger::shred::{
+    enable_merkle_shreds, get_shred_slot_index_type, ShredFetchStats, ShredType,
+    OFFSET_OF_SHRED_TYPE,
+};
 use solana_perf::cuda_runtime::PinnedVec;
 use solana_perf::packet::{Packet, PacketsRecycler};
 use solana_perf::recycler::Recycler;
-use solana_runtime::bank_forks::BankForks;
+use solana_runtime::{bank::Bank, bank_forks::BankForks};
 use solana_sdk::clock::{Slot, DEFAULT_MS_PER_SLOT};
-use solana_streamer::streamer::{self, PacketReceiver, PacketSender};
+use solana_streamer::streamer::{self, PacketReceiver, PacketReceiverConfig, PacketSender};
 use std::net::UdpSocket;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::channel;
@@ -32,6 +35,7 @@ impl ShredFetchStage {
         last_root: Slot,
         last_slot: Slot,
         slots_per_epoch: u64,
+        root_bank: Option<&Bank>,
         modify: &F,
         packet_hasher: &PacketHasher,
     ) where
@@ -39,6 +43,13 @@ impl ShredFetchStage {
     {
         p.meta.discard = true;
         if let Some((slot, _index, _shred_type)) = get_shred_slot_index_type(p, stats) {
+            // Merkle shreds are dropped until they are enabled for the slot
+            if ShredType(p.data[OFFSET_OF_SHRED_TYPE]).is_merkle()
+                && !root_bank.map_or(false, |root_bank| enable_merkle_shreds(slot, root_bank))
+            {
+                stats.merkle_shred_not_enabled += 1;
+                return;
+            }
             // Seems reasonable to limit shreds to 2 epochs away
             if slot > last_root && slot < (last_slot + 2 * slots_per_epoch) {
                 // Shred filter
@@ -75,6 +86,7 @@ impl ShredFetchStage {
         let mut last_root = 0;
         let mut last_slot = std::u64::MAX;
         let mut slots_per_epoch = 0;
+        let mut root_bank = None;
 
         let mut last_stats = Instant::now();
         let mut stats = ShredFetchStats::default();
@@ -90,8 +102,9 @@ impl ShredFetchStage {
                     last_root = bank_forks_r.root();
                     let working_bank = bank_forks_r.working_bank();
                     last_slot = working_bank.slot();
-                    let root_bank = bank_forks_r.root_bank();
-                    slots_per_epoch = root_bank.get_slots_in_epoch(root_bank.epoch());
+                    let bank = bank_forks_r.root_bank();
+                    slots_per_epoch = bank.get_slots_in_epoch(bank.epoch());
+                    root_bank = Some(bank);
                 }
             }
             stats.shred_count += p.packets.len();
@@ -103,6 +116,7 @@ impl ShredFetchStage {
                     last_root,
                     last_slot,
                     slots_per_epoch,
+                    root_bank.as_deref(),
                     &modify,
                     &packet_hasher,
                 );
@@ -117,6 +131,11 @@ impl ShredFetchStage {
                     ("index_out_of_bounds", stats.index_out_of_bounds, i64),
                     ("slot_out_of_range", stats.slot_out_of_range, i64),
                     ("duplicate_shred", stats.duplicate_shred, i64),
+                    (
+                        "merkle_shred_not_enabled",
+                        stats.merkle_shred_not_enabled,
+                        i64
+                    ),
                 );
                 stats = ShredFetchStats::default();
                 last_stats = Instant::now();
@@ -134,6 +153,7 @@ impl ShredFetchStage {
         recycler: Recycler<PinnedVec<Packet>>,
         bank_forks: Option<Arc<RwLock<BankForks>>>,
         name: &'static str,
+        receiver_config: &PacketReceiverConfig,
         modify: F,
     ) -> (Vec<JoinHandle<()>>, JoinHandle<()>)
     where
@@ -151,6 +171,7 @@ impl ShredFetchStage {
                     "packet_modifier",
                     1,
                     true,
+                    receiver_config.clone(),
                 )
             })
             .collect();
@@ -168,6 +189,8 @@ impl ShredFetchStage {
         repair_socket: Arc<UdpSocket>,
         sender: &PacketSender,
         bank_forks: Option<Arc<RwLock<BankForks>>>,
+        tvu_receiver_config: &PacketReceiverConfig,
+        repair_receiver_config: &PacketReceiverConfig,
         exit: &Arc<AtomicBool>,
     ) -> Self {
         let recycler: PacketsRecycler = Recycler::warmed(100, 1024);
@@ -179,6 +202,7 @@ impl ShredFetchStage {
             recycler.clone(),
             bank_forks.clone(),
             "shred_fetch",
+            tvu_receiver_config,
             |_| {},
         );
 
@@ -189,6 +213,7 @@ impl ShredFetchStage {
             recycler.clone(),
             bank_forks.clone(),
             "shred_fetch_tvu_forwards",
+            tvu_receiver_config,
             |p| p.meta.forward = true,
         );
 
@@ -199,6 +224,7 @@ impl ShredFetchStage {
             recycler,
             bank_forks,
             "shred_fetch_repair",
+            repair_receiver_config,
             |p| p.meta.repair = true,
         );
 
@@ -224,8 +250,11 @@ impl ShredFetchStage {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use solana_entry::entry::Entry;
     use solana_ledger::blockstore::MAX_DATA_SHREDS_PER_SLOT;
-    use solana_ledger::shred::Shred;
+    use solana_ledger::genesis_utils::{create_genesis_config, GenesisConfigInfo};
+    use solana_ledger::shred::{Shred, Shredder};
+    use solana_sdk::{feature_set, hash::Hash, signature::Keypair};
 
     #[test]
     fn test_data_code_same_index() {
@@ -259,13 +288,15 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            None,
             &|_p| {},
             &hasher,
         );
         assert!(!packet.meta.discard);
         let coding = solana_ledger::shred::Shredder::generate_coding_shreds(
             &[shred],
-            false, // is_last_in_slot
+            false,   // is_last_in_slot
+            Some(3), // next_code_index
         );
         coding[0].copy_to_packet(&mut packet);
         ShredFetchStage::process_packet(
@@ -275,6 +306,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            None,
             &|_p| {},
             &hasher,
         );
@@ -301,6 +333,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            None,
             &|_p| {},
             &hasher,
         );
@@ -317,6 +350,7 @@ mod tests {
             3,
             last_slot,
             slots_per_epoch,
+            None,
             &|_p| {},
             &hasher,
         );
@@ -330,6 +364,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            None,
             &|_p| {},
             &hasher,
         );
@@ -343,6 +378,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            None,
             &|_p| {},
             &hasher,
         );
@@ -359,6 +395,7 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            None,
             &|_p| {},
             &hasher,
         );
@@ -374,9 +411,68 @@ mod tests {
             last_root,
             last_slot,
             slots_per_epoch,
+            None,
             &|_p| {},
             &hasher,
         );
         assert!(packet.meta.discard);
     }
+
+    #[test]
+    fn test_merkle_shred_filter() {
+        solana_logger::setup();
+        let GenesisConfigInfo {
+            mut genesis_config, ..
+        } = create_genesis_config(10_000);
+        let enabled_bank = Bank::new(&genesis_config);
+        genesis_config
+            .accounts
+            .remove(&feature_set::merkle_shreds::id());
+        let disabled_bank = Bank::new(&genesis_config);
+
+        let slot = genesis_config.epoch_schedule.get_first_slot_in_epoch(1);
+        let entries = vec![Entry::new(&Hash::default(), 1, vec![])];
+        let (data_shreds, _coding_shreds, _) = Shredder::new(slot, slot - 1, 0, 0)
+            .unwrap()
+            .entries_to_merkle_shreds(&Keypair::new(), &entries, true, 0, Some(0));
+        let mut packet = Packet::default();
+        data_shreds[0].copy_to_packet(&mut packet);
+
+        let mut shreds_received = LruCache::new(DEFAULT_LRU_SIZE);
+        let mut stats = ShredFetchStats::default();
+        let hasher = PacketHasher::default();
+        let last_root = 0;
+        let slots_per_epoch = genesis_config.epoch_schedule.slots_per_epoch;
+
+        // Dropped before the feature is activated, or without a root bank
+        for root_bank in [None, Some(&disabled_bank)] {
+            ShredFetchStage::process_packet(
+                &mut packet,
+                &mut shreds_received,
+                &mut stats,
+                last_root,
+                slot,
+                slots_per_epoch,
+                root_bank,
+                &|_p| {},
+                &hasher,
+            );
+            assert!(packet.meta.discard);
+        }
+        assert_eq!(stats.merkle_shred_not_enabled, 2);
+
+        // Accepted once the feature was activated in an earlier epoch
+        ShredFetchStage::process_packet(
+            &mut packet,
+            &mut shreds_received,
+            &mut stats,
+            last_root,
+            slot,
+            slots_per_epoch,
+            Some(&enabled_bank),
+            &|_p| {},
+            &hasher,
+        );
+        assert!(!packet.meta.discard);
+    }
 }
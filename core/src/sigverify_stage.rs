@@ -4,22 +4,43 @@
 //! signature in that packet is valid. It assumes each packet contains one
 //! transaction. All processing is done on the CPU by default and on a GPU
 //! if perf-libs are available
+//!
+//! Before verification, packets already seen within the last couple of
+//! dedup filter rotations are discarded, and if the backlog of queued packets
+//! grows past several passes' worth of verification, the excess is discarded
+//! at random while keeping an even share of packets per sender IP.
 
+use crate::packet_hasher::PacketHasher;
 use crate::sigverify;
 use crossbeam_channel::{SendError, Sender as CrossbeamSender};
+use rand::{seq::SliceRandom, thread_rng, Rng};
 use solana_measure::measure::Measure;
 use solana_metrics::datapoint_debug;
 use solana_perf::packet::Packets;
 use solana_perf::perf_libs;
+use solana_runtime::bloom::{AtomicBloom, Bloom};
 use solana_sdk::timing;
 use solana_streamer::streamer::{self, PacketReceiver, StreamerError};
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread::{self, Builder, JoinHandle};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
-const RECV_BATCH_MAX_CPU: usize = 1_000;
-const RECV_BATCH_MAX_GPU: usize = 5_000;
+// Maximum backlog of packets, about ten passes' worth of verification;
+// anything queued beyond this is load shed before verification.
+const MAX_SIGVERIFY_BACKLOG_CPU: usize = 10_000;
+const MAX_SIGVERIFY_BACKLOG_GPU: usize = 50_000;
+
+// Upper bound on the packets drained from the channel in one pass.
+const MAX_RECV_BACKLOG: usize = 100_000;
+
+const DEDUP_FILTER_MAX_ITEMS: usize = 1_000_000;
+const DEDUP_FILTER_FALSE_RATE: f64 = 0.001;
+const DEDUP_FILTER_MAX_BITS: usize = 8 << 22;
+const DEDUP_FILTER_ROTATE_INTERVAL: Duration = Duration::from_secs(2);
 
 #[derive(Error, Debug)]
 pub enum SigVerifyServiceError {
@@ -32,6 +53,140 @@ pub enum SigVerifyServiceError {
 
 type Result<T> = std::result::Result<T, SigVerifyServiceError>;
 
+struct DedupFilters {
+    current: AtomicBloom<[u8; 8]>,
+    previous: AtomicBloom<[u8; 8]>,
+    rotated: Instant,
+}
+
+impl DedupFilters {
+    fn new_filter() -> AtomicBloom<[u8; 8]> {
+        Bloom::random(
+            DEDUP_FILTER_MAX_ITEMS,
+            DEDUP_FILTER_FALSE_RATE,
+            DEDUP_FILTER_MAX_BITS,
+        )
+        .into()
+    }
+}
+
+/// Filters out packets seen within the last one to two rotation intervals.
+/// Lookups check both the current and the previous bloom filter, so a
+/// packet is never forgotten right after a rotation.
+pub struct PacketDeduper {
+    filters: RwLock<DedupFilters>,
+    packet_hasher: PacketHasher,
+}
+
+impl Default for PacketDeduper {
+    fn default() -> Self {
+        Self {
+            filters: RwLock::new(DedupFilters {
+                current: DedupFilters::new_filter(),
+                previous: DedupFilters::new_filter(),
+                rotated: Instant::now(),
+            }),
+            packet_hasher: PacketHasher::default(),
+        }
+    }
+}
+
+impl PacketDeduper {
+    /// Replaces the previous filter with the current one and starts a fresh
+    /// current filter once the rotation interval has passed.
+    pub fn maybe_rotate(&self, interval: Duration) {
+        if self.filters.read().unwrap().rotated.elapsed() < interval {
+            return;
+        }
+        let mut filters = self.filters.write().unwrap();
+        if filters.rotated.elapsed() >= interval {
+            filters.previous = std::mem::replace(&mut filters.current, DedupFilters::new_filter());
+            filters.rotated = Instant::now();
+        }
+    }
+
+    /// Marks packets already seen as discarded and returns how many were.
+    pub fn dedup_packets(&self, batches: &mut [Packets]) -> usize {
+        let filters = self.filters.read().unwrap();
+        let mut num_deduped = 0;
+        for packet in batches
+            .iter_mut()
+            .flat_map(|batch| batch.packets.iter_mut())
+        {
+            if packet.meta.discard {
+                continue;
+            }
+            let key = self.packet_hasher.hash_packet(packet).to_le_bytes();
+            if filters.current.contains(&key) || filters.previous.contains(&key) {
+                packet.meta.discard = true;
+                num_deduped += 1;
+            } else {
+                filters.current.add(&key);
+            }
+        }
+        num_deduped
+    }
+}
+
+fn max_sigverify_backlog() -> usize {
+    if perf_libs::api().is_some() {
+        MAX_SIGVERIFY_BACKLOG_GPU
+    } else {
+        MAX_SIGVERIFY_BACKLOG_CPU
+    }
+}
+
+/// Discards packets at random until at most `max_packets` remain, keeping an
+/// even share per sender IP: every sender keeps its `n`-th packet before any
+/// sender keeps its `n+1`-th. Returns the number of packets discarded.
+pub fn discard_excess_packets<R: Rng>(
+    batches: &mut [Packets],
+    max_packets: usize,
+    rng: &mut R,
+) -> usize {
+    let num_packets = batches
+        .iter()
+        .flat_map(|batch| batch.packets.iter())
+        .filter(|packet| !packet.meta.discard)
+        .count();
+    if num_packets <= max_packets {
+        return 0;
+    }
+    let mut sender_indexes: HashMap<IpAddr, usize> = HashMap::new();
+    let mut senders: Vec<Vec<(usize, usize)>> = vec![];
+    for (batch_index, batch) in batches.iter().enumerate() {
+        for (packet_index, packet) in batch.packets.iter().enumerate() {
+            if !packet.meta.discard {
+                let sender = *sender_indexes
+                    .entry(packet.meta.addr().ip())
+                    .or_insert_with(|| {
+                        senders.push(vec![]);
+                        senders.len() - 1
+                    });
+                senders[sender].push((batch_index, packet_index));
+            }
+        }
+    }
+    senders.shuffle(rng);
+    let mut packets: Vec<_> = senders
+        .iter_mut()
+        .enumerate()
+        .flat_map(|(sender, packets)| {
+            packets.shuffle(rng);
+            packets
+                .iter()
+                .enumerate()
+                .map(move |(rank, indexes)| ((rank, sender), *indexes))
+        })
+        .collect();
+    packets.sort_unstable_by_key(|(order, _)| *order);
+    let excess = &packets[max_packets..];
+    for (_, (batch_index, packet_index)) in excess {
+        batches[*batch_index].packets[*packet_index].meta.discard = true;
+    }
+    excess.len()
+}
+
 pub struct SigVerifyStage {
     thread_hdls: Vec<JoinHandle<()>>,
 }
@@ -66,16 +221,23 @@ impl SigVerifyStage {
         sendr: &CrossbeamSender<Vec<Packets>>,
         id: usize,
         verifier: &T,
+        deduper: &PacketDeduper,
     ) -> Result<()> {
-        let (batch, len, recv_time) = streamer::recv_batch(
+        let (mut batch, len, recv_time) = streamer::recv_batch(
             &recvr.lock().expect("'recvr' lock in fn verifier"),
-            if perf_libs::api().is_some() {
-                RECV_BATCH_MAX_GPU
-            } else {
-                RECV_BATCH_MAX_CPU
-            },
+            MAX_RECV_BACKLOG,
         )?;
 
+        let mut dedup_time = Measure::start("sigverify_dedup_time");
+        deduper.maybe_rotate(DEDUP_FILTER_ROTATE_INTERVAL);
+        let num_deduped = deduper.dedup_packets(&mut batch);
+        let num_discarded =
+            discard_excess_packets(&mut batch, max_sigverify_backlog(), &mut thread_rng());
+        // Batches left without packets to verify are not sent to the verifier, so that
+        // they are not copied to the GPU.
+        batch.retain(|packets| packets.packets.iter().any(|packet| !packet.meta.discard));
+        dedup_time.stop();
+
         let mut verify_batch_time = Measure::start("sigverify_batch_time");
         let batch_len = batch.len();
         debug!(
@@ -101,6 +263,9 @@ impl SigVerifyStage {
             "sigverify_stage-total_verify_time",
             ("num_batches", batch_len, i64),
             ("num_packets", len, i64),
+            ("num_deduped", num_deduped, i64),
+            ("num_discarded", num_discarded, i64),
+            ("dedup_time_us", dedup_time.as_us(), i64),
             ("verify_time_ms", verify_batch_time.as_ms(), i64),
             ("recv_time", recv_time, i64),
         );
@@ -113,12 +278,15 @@ impl SigVerifyStage {
         verified_sender: CrossbeamSender<Vec<Packets>>,
         id: usize,
         verifier: &T,
+        deduper: Arc<PacketDeduper>,
     ) -> JoinHandle<()> {
         let verifier = verifier.clone();
         Builder::new()
             .name(format!("solana-verifier-{}", id))
             .spawn(move || loop {
-                if let Err(e) = Self::verifier(&packet_receiver, &verified_sender, id, &verifier) {
+                if let Err(e) =
+                    Self::verifier(&packet_receiver, &verified_sender, id, &verifier, &deduper)
+                {
                     match e {
                         SigVerifyServiceError::Streamer(StreamerError::RecvTimeout(
                             RecvTimeoutError::Disconnected,
@@ -142,9 +310,16 @@ impl SigVerifyStage {
         verifier: T,
     ) -> Vec<JoinHandle<()>> {
         let receiver = Arc::new(Mutex::new(packet_receiver));
+        let deduper = Arc::new(PacketDeduper::default());
         (0..4)
             .map(|id| {
-                Self::verifier_service(receiver.clone(), verified_sender.clone(), id, &verifier)
+                Self::verifier_service(
+                    receiver.clone(),
+                    verified_sender.clone(),
+                    id,
+                    &verifier,
+                    deduper.clone(),
+                )
             })
             .collect()
     }
@@ -156,3 +331,99 @@ impl SigVerifyStage {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_perf::packet::Packet;
+    use std::net::SocketAddr;
+
+    fn new_packet(data: u8, sender: u8) -> Packet {
+        let mut packet = Packet::default();
+        packet.data[0] = data;
+        packet.meta.size = 1;
+        packet
+            .meta
+            .set_addr(&SocketAddr::from(([10, 0, 0, sender], 8000)));
+        packet
+    }
+
+    fn count_discarded(batches: &[Packets]) -> usize {
+        batches
+            .iter()
+            .flat_map(|batch| batch.packets.iter())
+            .filter(|packet| packet.meta.discard)
+            .count()
+    }
+
+    #[test]
+    fn test_dedup_packets() {
+        let deduper = PacketDeduper::default();
+        let packets = vec![new_packet(1, 1), new_packet(2, 1), new_packet(1, 2)];
+        let mut batches = vec![Packets::new(packets.clone())];
+        assert_eq!(deduper.dedup_packets(&mut batches), 1);
+        assert!(batches[0].packets[2].meta.discard);
+
+        // Packets seen before the last rotation are still filtered.
+        deduper.maybe_rotate(Duration::default());
+        let mut batches = vec![Packets::new(packets.clone())];
+        assert_eq!(deduper.dedup_packets(&mut batches), 3);
+
+        // Two rotations later they are forgotten.
+        deduper.maybe_rotate(Duration::default());
+        deduper.maybe_rotate(Duration::default());
+        let mut batches = vec![Packets::new(packets)];
+        assert_eq!(deduper.dedup_packets(&mut batches), 1);
+    }
+
+    #[test]
+    fn test_discard_excess_packets() {
+        let mut rng = thread_rng();
+        // Sender 1 floods, sender 2 sends a couple of packets.
+        let mut packets: Vec<_> = (0..100).map(|i| new_packet(i, 1)).collect();
+        packets.push(new_packet(0, 2));
+        packets.push(new_packet(1, 2));
+        let mut batches = vec![Packets::new(packets.clone()), Packets::new(packets)];
+        batches[1].packets[0].meta.discard = true;
+
+        assert_eq!(discard_excess_packets(&mut batches, 300, &mut rng), 0);
+        assert_eq!(count_discarded(&batches), 1);
+
+        assert_eq!(discard_excess_packets(&mut batches, 10, &mut rng), 193);
+        assert_eq!(count_discarded(&batches), 194);
+        // All of sender 2's packets fit in its fair share.
+        let kept_from = |sender: u8| {
+            batches
+                .iter()
+                .flat_map(|batch| batch.packets.iter())
+                .filter(|packet| !packet.meta.discard)
+                .filter(|packet| packet.meta.addr().ip() == IpAddr::from([10, 0, 0, sender]))
+                .count()
+        };
+        assert_eq!(kept_from(2), 4);
+        assert_eq!(kept_from(1), 6);
+    }
+
+    #[test]
+    fn test_backlog_below_threshold_is_kept() {
+        let mut rng = thread_rng();
+        // Several passes' worth of packets from a single sender
+        let max_backlog = max_sigverify_backlog();
+        let packets: Vec<_> = (0..=u8::MAX).map(|i| new_packet(i, 1)).collect();
+        let mut batches = vec![Packets::new(packets); max_backlog / 256];
+
+        assert_eq!(
+            discard_excess_packets(&mut batches, max_backlog, &mut rng),
+            0
+        );
+        assert_eq!(count_discarded(&batches), 0);
+
+        // Only the packets beyond the backlog threshold are shed
+        batches.push(batches[0].clone());
+        let num_packets = batches.len() * 256;
+        assert_eq!(
+            discard_excess_packets(&mut batches, max_backlog, &mut rng),
+            num_packets - max_backlog
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! The `stake_weighted_qos` module gives the senders of UDP TPU packets a budget of packets per
+//! interval. Staked senders share most of the budget in proportion to their stake, and unstaked
+//! senders share the remainder, so that an unstaked flood can't crowd out staked nodes.
+//! Packets over their sender's budget are discarded before signature verification.
+//!
+//! Senders are identified by the source IP of their packets, which UDP doesn't authenticate, so
+//! this is a best-effort defense: a spammer spoofing a staked node's IP spends that node's budget.
+//! Connections to the QUIC TPU are authenticated, and are weighted by stake in
+//! `solana_streamer::quic` instead.
+
+use solana_perf::packet::Packets;
+use solana_streamer::streamer::StakedNodes;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    net::IpAddr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Budgets are reset at this interval
+pub const QOS_INTERVAL_MS: u64 = 100;
+
+/// Packets admitted per interval, across all senders
+pub const MAX_PACKETS_PER_INTERVAL: usize = 10_000;
+
+/// Share of `MAX_PACKETS_PER_INTERVAL` reserved for staked senders. The rest is shared by the
+/// unstaked senders
+pub const STAKED_PACKETS_PERCENT: usize = 80;
+
+/// Budget of a staked sender, however small its stake
+pub const MIN_STAKED_PACKETS_PER_INTERVAL: usize = 10;
+
+/// Budget of a single unstaked sender, so that one of them can't exhaust the unstaked share
+pub const MAX_UNSTAKED_PACKETS_PER_IP_PER_INTERVAL: usize = 200;
+
+#[derive(Default)]
+struct StakeWeightedQosStats {
+    staked_packets: usize,
+    staked_packets_discarded: usize,
+    unstaked_packets: usize,
+    unstaked_packets_discarded: usize,
+    num_staked_senders: usize,
+    num_unstaked_senders: usize,
+}
+
+impl StakeWeightedQosStats {
+    fn report(&self, name: &'static str) {
+        datapoint_info!(
+            name,
+            ("staked_packets", self.staked_packets, i64),
+            (
+                "staked_packets_discarded",
+                self.staked_packets_discarded,
+                i64
+            ),
+            ("unstaked_packets", self.unstaked_packets, i64),
+            (
+                "unstaked_packets_discarded",
+                self.unstaked_packets_discarded,
+                i64
+            ),
+            ("num_staked_senders", self.num_staked_senders, i64),
+            ("num_unstaked_senders", self.num_unstaked_senders, i64),
+        );
+    }
+}
+
+/// Each receiver thread of the fetch stage owns one `StakeWeightedQos`. A sender's packets are
+/// expected to arrive on one socket, so every shard grants staked senders their whole budget,
+/// while the unstaked budget is split between the shards
+pub struct StakeWeightedQos {
+    staked_nodes: Arc<RwLock<StakedNodes>>,
+    num_shards: usize,
+    interval_start: Instant,
+    // Packets admitted from each sender in the current interval
+    sender_packets: HashMap<IpAddr, usize>,
+    unstaked_packets: usize,
+    stats: StakeWeightedQosStats,
+    last_report: Instant,
+}
+
+impl StakeWeightedQos {
+    pub fn new(staked_nodes: Arc<RwLock<StakedNodes>>, num_shards: usize) -> Self {
+        Self {
+            staked_nodes,
+            num_shards: num_shards.max(1),
+            interval_start: Instant::now(),
+            sender_packets: HashMap::new(),
+            unstaked_packets: 0,
+            stats: StakeWeightedQosStats::default(),
+            last_report: Instant::now(),
+        }
+    }
+
+    /// Packets a sender with `stake` out of `total_stake` may send per interval
+    pub fn staked_packets_budget(stake: u64, total_stake: u64) -> usize {
+        let staked_packets = MAX_PACKETS_PER_INTERVAL * STAKED_PACKETS_PERCENT / 100;
+        let share = (staked_packets as u128)
+            .saturating_mul(stake as u128)
+            .checked_div(total_stake as u128)
+            .unwrap_or_default() as usize;
+        share.max(MIN_STAKED_PACKETS_PER_INTERVAL)
+    }
+
+    fn unstaked_packets_budget(&self) -> usize {
+        MAX_PACKETS_PER_INTERVAL * (100 - STAKED_PACKETS_PERCENT) / 100 / self.num_shards
+    }
+
+    fn maybe_reset_interval(&mut self) {
+        if self.interval_start.elapsed() >= Duration::from_millis(QOS_INTERVAL_MS) {
+            self.interval_start = Instant::now();
+            self.sender_packets.clear();
+            self.unstaked_packets = 0;
+        }
+    }
+
+    fn maybe_report(&mut self) {
+        if self.last_report.elapsed() >= Duration::from_secs(1) {
+            self.stats.report("stake_weighted_qos");
+            self.stats = StakeWeightedQosStats::default();
+            self.last_report = Instant::now();
+        }
+    }
+
+    /// Marks the packets over their sender's budget as discarded
+    pub fn apply(&mut self, packets: &mut Packets) {
+        self.maybe_reset_interval();
+        let unstaked_packets_budget = self.unstaked_packets_budget();
+        let staked_nodes = self.staked_nodes.read().unwrap();
+        for packet in packets
+            .packets
+            .iter_mut()
+            .filter(|packet| !packet.meta.discard)
+        {
+            let ip = packet.meta.addr().ip();
+            let stake = staked_nodes
+                .ip_stake_map
+                .get(&ip)
+                .copied()
+                .unwrap_or_default();
+            let sender_packets = match self.sender_packets.entry(ip) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    if stake > 0 {
+                        self.stats.num_staked_senders += 1;
+                    } else {
+                        self.stats.num_unstaked_senders += 1;
+                    }
+                    entry.insert(0)
+                }
+            };
+            if stake > 0 {
+                self.stats.staked_packets += 1;
+                if *sender_packets >= Self::staked_packets_budget(stake, staked_nodes.total_stake) {
+                    packet.meta.discard = true;
+                    self.stats.staked_packets_discarded += 1;
+                    continue;
+                }
+            } else {
+                self.stats.unstaked_packets += 1;
+                if *sender_packets >= MAX_UNSTAKED_PACKETS_PER_IP_PER_INTERVAL
+                    || self.unstaked_packets >= unstaked_packets_budget
+                {
+                    packet.meta.discard = true;
+                    self.stats.unstaked_packets_discarded += 1;
+                    continue;
+                }
+                self.unstaked_packets += 1;
+            }
+            *sender_packets += 1;
+        }
+        drop(staked_nodes);
+        self.maybe_report();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_perf::packet::Packet,
+        std::net::{Ipv4Addr, SocketAddr},
+    };
+
+    fn packets_from(ip: IpAddr, num_packets: usize) -> Packets {
+        let mut packet = Packet::default();
+        packet.meta.set_addr(&SocketAddr::new(ip, 8000));
+        Packets::new(vec![packet; num_packets])
+    }
+
+    fn num_discarded(packets: &Packets) -> usize {
+        packets
+            .packets
+            .iter()
+            .filter(|packet| packet.meta.discard)
+            .count()
+    }
+
+    #[test]
+    fn test_staked_packets_budget() {
+        let staked_packets = MAX_PACKETS_PER_INTERVAL * STAKED_PACKETS_PERCENT / 100;
+        assert_eq!(
+            StakeWeightedQos::staked_packets_budget(1, 1),
+            staked_packets
+        );
+        assert_eq!(
+            StakeWeightedQos::staked_packets_budget(1, 4),
+            staked_packets / 4
+        );
+        assert_eq!(
+            StakeWeightedQos::staked_packets_budget(1, u64::MAX),
+            MIN_STAKED_PACKETS_PER_INTERVAL
+        );
+    }
+
+    #[test]
+    fn test_stake_weighted_qos() {
+        let staked_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let small_staked_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let staked_nodes = StakedNodes {
+            total_stake: 1_000,
+            ip_stake_map: [(staked_ip, 990), (small_staked_ip, 10)]
+                .iter()
+                .cloned()
+                .collect(),
+            ..StakedNodes::default()
+        };
+        let mut qos = StakeWeightedQos::new(Arc::new(RwLock::new(staked_nodes)), 1);
+
+        let staked_budget = StakeWeightedQos::staked_packets_budget(990, 1_000);
+        let small_staked_budget = StakeWeightedQos::staked_packets_budget(10, 1_000);
+        let mut batch = vec![
+            packets_from(staked_ip, staked_budget + 5),
+            packets_from(small_staked_ip, small_staked_budget + 5),
+        ];
+        // An unstaked flood from many senders is capped per sender and in total
+        let num_unstaked_senders = 20;
+        for i in 0..num_unstaked_senders {
+            batch.push(packets_from(
+                IpAddr::V4(Ipv4Addr::new(192, 168, 0, i)),
+                MAX_UNSTAKED_PACKETS_PER_IP_PER_INTERVAL + 1,
+            ));
+        }
+        batch.iter_mut().for_each(|packets| qos.apply(packets));
+
+        assert_eq!(num_discarded(&batch[0]), 5);
+        assert_eq!(num_discarded(&batch[1]), 5);
+        let unstaked_admitted: usize = batch[2..]
+            .iter()
+            .map(|packets| packets.packets.len() - num_discarded(packets))
+            .sum();
+        assert_eq!(unstaked_admitted, qos.unstaked_packets_budget());
+        assert_eq!(num_discarded(&batch[2]), 1);
+        assert_eq!(
+            num_discarded(batch.last().unwrap()),
+            MAX_UNSTAKED_PACKETS_PER_IP_PER_INTERVAL + 1
+        );
+
+        // Budgets are replenished in the next interval
+        qos.interval_start -= Duration::from_millis(QOS_INTERVAL_MS);
+        let mut packets = packets_from(staked_ip, staked_budget);
+        qos.apply(&mut packets);
+        assert_eq!(num_discarded(&packets), 0);
+    }
+
+    #[test]
+    fn test_stake_weighted_qos_shards() {
+        let staked_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let staked_nodes = StakedNodes {
+            total_stake: 1_000,
+            ip_stake_map: [(staked_ip, 1_000)].iter().cloned().collect(),
+            ..StakedNodes::default()
+        };
+        let num_shards = 4;
+        let mut qos = StakeWeightedQos::new(Arc::new(RwLock::new(staked_nodes)), num_shards);
+
+        // Staked senders get their whole budget on every shard
+        let staked_budget = StakeWeightedQos::staked_packets_budget(1_000, 1_000);
+        let mut packets = packets_from(staked_ip, staked_budget);
+        qos.apply(&mut packets);
+        assert_eq!(num_discarded(&packets), 0);
+
+        // The unstaked budget is split between the shards
+        let unstaked_budget = MAX_PACKETS_PER_INTERVAL * (100 - STAKED_PACKETS_PERCENT) / 100;
+        assert_eq!(qos.unstaked_packets_budget(), unstaked_budget / num_shards);
+        let mut admitted = 0;
+        for i in 0..20 {
+            let mut packets = packets_from(
+                IpAddr::V4(Ipv4Addr::new(192, 168, 0, i)),
+                MAX_UNSTAKED_PACKETS_PER_IP_PER_INTERVAL,
+            );
+            qos.apply(&mut packets);
+            admitted += packets.packets.len() - num_discarded(&packets);
+        }
+        assert_eq!(admitted, unstaked_budget / num_shards);
+    }
+}
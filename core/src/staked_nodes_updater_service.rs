@@ -0,0 +1,87 @@
+//! The `staked_nodes_updater_service` periodically refreshes the stakes of the nodes in the
+//! current epoch, and the TPU addresses they advertise in gossip, for the TPU quality of service.
+
+use solana_gossip::cluster_info::ClusterInfo;
+use solana_runtime::bank_forks::BankForks;
+use solana_streamer::streamer::StakedNodes;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const STAKED_NODES_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct StakedNodesUpdaterService {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl StakedNodesUpdaterService {
+    pub fn new(
+        exit: Arc<AtomicBool>,
+        cluster_info: Arc<ClusterInfo>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        staked_nodes: Arc<RwLock<StakedNodes>>,
+    ) -> Self {
+        let thread_hdl = Builder::new()
+            .name("sol-sn-updater".to_string())
+            .spawn(move || {
+                let mut last_refresh: Option<Instant> = None;
+                while !exit.load(Ordering::Relaxed) {
+                    let should_refresh = match last_refresh {
+                        Some(last_refresh) => {
+                            last_refresh.elapsed() >= STAKED_NODES_REFRESH_INTERVAL
+                        }
+                        None => true,
+                    };
+                    if should_refresh {
+                        let new_staked_nodes =
+                            Self::compute_staked_nodes(&cluster_info, &bank_forks);
+                        *staked_nodes.write().unwrap() = new_staked_nodes;
+                        last_refresh = Some(Instant::now());
+                    }
+                    sleep(Duration::from_millis(100));
+                }
+            })
+            .unwrap();
+
+        Self { thread_hdl }
+    }
+
+    fn compute_staked_nodes(
+        cluster_info: &ClusterInfo,
+        bank_forks: &RwLock<BankForks>,
+    ) -> StakedNodes {
+        let bank = bank_forks.read().unwrap().root_bank();
+        let pubkey_stake_map = bank.epoch_staked_nodes(bank.epoch()).unwrap_or_default();
+        let total_stake = pubkey_stake_map.values().sum();
+
+        // All the nodes in gossip, this one included. Transactions and forwarded packets are
+        // sent from the sockets bound next to a node's TPU, not from its gossip address
+        let mut ip_stake_map = HashMap::new();
+        for (contact_info, _) in cluster_info.all_peers() {
+            if let Some(stake) = pubkey_stake_map.get(&contact_info.id) {
+                let tpu_ip = contact_info.tpu.ip();
+                let tpu_forwards_ip = contact_info.tpu_forwards.ip();
+                *ip_stake_map.entry(tpu_ip).or_default() += stake;
+                if tpu_forwards_ip != tpu_ip {
+                    *ip_stake_map.entry(tpu_forwards_ip).or_default() += stake;
+                }
+            }
+        }
+
+        StakedNodes {
+            total_stake,
+            pubkey_stake_map,
+            ip_stake_map,
+        }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
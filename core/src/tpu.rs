@@ -8,11 +8,11 @@ use crate::{
         ClusterInfoVoteListener, GossipDuplicateConfirmedSlotsSender, GossipVerifiedVoteHashSender,
         VerifiedVoteSender, VoteTracker,
     },
-    cost_model::CostModel,
-    cost_tracker::CostTracker,
     fetch_stage::FetchStage,
+    packet_forwarder::ForwardingConfig,
     sigverify::TransactionSigVerifier,
     sigverify_stage::SigVerifyStage,
+    staked_nodes_updater_service::StakedNodesUpdaterService,
 };
 use crossbeam_channel::unbounded;
 use solana_gossip::cluster_info::ClusterInfo;
@@ -24,8 +24,14 @@ use solana_rpc::{
 };
 use solana_runtime::{
     bank_forks::BankForks,
+    cost_model::CostModel,
+    cost_tracker::CostTracker,
     vote_sender_types::{ReplayVoteReceiver, ReplayVoteSender},
 };
+use solana_streamer::{
+    quic::spawn_server,
+    streamer::{PacketReceiverConfig, StakedNodes},
+};
 use std::{
     net::UdpSocket,
     sync::{
@@ -40,10 +46,12 @@ pub const DEFAULT_TPU_COALESCE_MS: u64 = 5;
 
 pub struct Tpu {
     fetch_stage: FetchStage,
+    tpu_quic_t: Option<thread::JoinHandle<()>>,
     sigverify_stage: SigVerifyStage,
     banking_stage: BankingStage,
     cluster_info_vote_listener: ClusterInfoVoteListener,
     broadcast_stage: BroadcastStage,
+    staked_nodes_updater_service: StakedNodesUpdaterService,
 }
 
 impl Tpu {
@@ -55,6 +63,7 @@ impl Tpu {
         retransmit_slots_receiver: RetransmitSlotsReceiver,
         transactions_sockets: Vec<UdpSocket>,
         tpu_forwards_sockets: Vec<UdpSocket>,
+        tpu_quic_socket: Option<UdpSocket>,
         broadcast_sockets: Vec<UdpSocket>,
         subscriptions: &Arc<RpcSubscriptions>,
         transaction_status_sender: Option<TransactionStatusSender>,
@@ -72,8 +81,18 @@ impl Tpu {
         tpu_coalesce_ms: u64,
         cluster_confirmed_slot_sender: GossipDuplicateConfirmedSlotsSender,
         cost_model: &Arc<RwLock<CostModel>>,
+        forwarding_config: ForwardingConfig,
+        receiver_config: PacketReceiverConfig,
+        use_banking_stage_scheduler: bool,
     ) -> Self {
         let (packet_sender, packet_receiver) = channel();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let staked_nodes_updater_service = StakedNodesUpdaterService::new(
+            exit.clone(),
+            cluster_info.clone(),
+            bank_forks.clone(),
+            staked_nodes.clone(),
+        );
         let fetch_stage = FetchStage::new_with_sender(
             transactions_sockets,
             tpu_forwards_sockets,
@@ -81,7 +100,21 @@ impl Tpu {
             &packet_sender,
             poh_recorder,
             tpu_coalesce_ms,
+            &staked_nodes,
+            receiver_config,
         );
+        let tpu_quic_t = tpu_quic_socket.map(|tpu_quic_socket| {
+            spawn_server(
+                tpu_quic_socket,
+                &cluster_info.keypair(),
+                cluster_info.my_contact_info().tpu.ip(),
+                packet_sender,
+                exit.clone(),
+                staked_nodes,
+                tpu_coalesce_ms,
+            )
+            .expect("start quic server")
+        });
         let (verified_sender, verified_receiver) = unbounded();
 
         let sigverify_stage = {
@@ -107,15 +140,32 @@ impl Tpu {
         );
 
         let cost_tracker = Arc::new(RwLock::new(CostTracker::new(cost_model.clone())));
-        let banking_stage = BankingStage::new(
-            cluster_info,
-            poh_recorder,
-            verified_receiver,
-            verified_vote_packets_receiver,
-            transaction_status_sender,
-            replay_vote_sender,
-            cost_tracker,
-        );
+        let banking_stage = if use_banking_stage_scheduler {
+            BankingStage::new_with_scheduler(
+                cluster_info,
+                poh_recorder,
+                verified_receiver,
+                verified_vote_packets_receiver,
+                BankingStage::num_threads(),
+                transaction_status_sender,
+                replay_vote_sender,
+                cost_tracker,
+                &bank_forks,
+                forwarding_config,
+            )
+        } else {
+            BankingStage::new(
+                cluster_info,
+                poh_recorder,
+                verified_receiver,
+                verified_vote_packets_receiver,
+                transaction_status_sender,
+                replay_vote_sender,
+                cost_tracker,
+                &bank_forks,
+                forwarding_config,
+            )
+        };
 
         let broadcast_stage = broadcast_type.new_broadcast_stage(
             broadcast_sockets,
@@ -130,10 +180,12 @@ impl Tpu {
 
         Self {
             fetch_stage,
+            tpu_quic_t,
             sigverify_stage,
             banking_stage,
             cluster_info_vote_listener,
             broadcast_stage,
+            staked_nodes_updater_service,
         }
     }
 
@@ -143,8 +195,12 @@ impl Tpu {
             self.sigverify_stage.join(),
             self.cluster_info_vote_listener.join(),
             self.banking_stage.join(),
+            self.staked_nodes_updater_service.join(),
         ];
         let broadcast_result = self.broadcast_stage.join();
+        if let Some(tpu_quic_t) = self.tpu_quic_t {
+            tpu_quic_t.join()?;
+        }
         for result in results {
             result?;
         }
@@ -0,0 +1,478 @@
+//! The `transaction_scheduler` orders pending transactions by fee per compute unit and hands
+//! out batches whose account locks conflict neither with each other nor with any batch still
+//! being processed by a banking thread, so banking threads no longer race each other for hot
+//! accounts. The cost of a batch is reserved in the block when it is handed out, and released
+//! for the transactions that were not committed once the batch completes.
+use retain_mut::RetainMut;
+use solana_perf::packet::Packet;
+use solana_runtime::cost_tracker::CostTracker;
+use solana_sdk::{
+    clock::Slot, fee_calculator::FeeCalculator, pubkey::Pubkey,
+    sanitized_transaction::SanitizedTransaction,
+};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+// Fees are scaled up before dividing by the compute cost so that small fee differences still
+// order transactions.
+const PRIORITY_SCALE: u64 = 1_000_000;
+
+// Maximum number of pending transactions looked at when building one batch.
+const MAX_SCHEDULE_LOOKAHEAD: usize = 1_024;
+
+pub struct PendingTransaction {
+    priority: u64,
+    sequence: u64,
+    pub transaction: SanitizedTransaction<'static>,
+    pub packet: Packet,
+}
+
+impl PendingTransaction {
+    fn order(&self) -> (u64, Reverse<u64>) {
+        // Highest fee per compute unit first, then first come first served
+        (self.priority, Reverse(self.sequence))
+    }
+}
+
+impl PartialEq for PendingTransaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.order() == other.order()
+    }
+}
+
+impl Eq for PendingTransaction {}
+
+impl PartialOrd for PendingTransaction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PendingTransaction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order().cmp(&other.order())
+    }
+}
+
+#[derive(Debug, Default)]
+struct AccountLocks {
+    write_locks: HashSet<Pubkey>,
+    read_locks: HashMap<Pubkey, usize>,
+}
+
+impl AccountLocks {
+    fn is_locked(&self, transaction: &SanitizedTransaction) -> bool {
+        let (writable_keys, readonly_keys) = transaction.message.get_account_keys_by_lock_type();
+        writable_keys
+            .into_iter()
+            .any(|key| self.write_locks.contains(key) || self.read_locks.contains_key(key))
+            || readonly_keys
+                .into_iter()
+                .any(|key| self.write_locks.contains(key))
+    }
+
+    fn lock(&mut self, transaction: &SanitizedTransaction) {
+        let (writable_keys, readonly_keys) = transaction.message.get_account_keys_by_lock_type();
+        self.write_locks.extend(writable_keys);
+        for key in readonly_keys {
+            *self.read_locks.entry(*key).or_default() += 1;
+        }
+    }
+
+    fn unlock(&mut self, transaction: &SanitizedTransaction) {
+        let (writable_keys, readonly_keys) = transaction.message.get_account_keys_by_lock_type();
+        for key in writable_keys {
+            self.write_locks.remove(key);
+        }
+        for key in readonly_keys {
+            if let Some(count) = self.read_locks.get_mut(key) {
+                *count -= 1;
+                if *count == 0 {
+                    self.read_locks.remove(key);
+                }
+            }
+        }
+    }
+}
+
+/// Transactions handed to a banking thread. None of them conflict with each other or with any
+/// other batch in flight.
+pub struct ScheduledBatch {
+    pub id: u64,
+    pub transactions: Vec<SanitizedTransaction<'static>>,
+}
+
+struct InFlightBatch {
+    // Slot of the bank the batch's cost is reserved for
+    slot: Slot,
+    scheduled: Vec<PendingTransaction>,
+}
+
+pub struct TransactionScheduler {
+    pending: BinaryHeap<PendingTransaction>,
+    in_flight: HashMap<u64, InFlightBatch>,
+    account_locks: AccountLocks,
+    max_pending: usize,
+    next_sequence: u64,
+    next_batch_id: u64,
+}
+
+impl TransactionScheduler {
+    pub fn new(max_pending: usize) -> Self {
+        Self {
+            pending: BinaryHeap::new(),
+            in_flight: HashMap::new(),
+            account_locks: AccountLocks::default(),
+            max_pending,
+            next_sequence: 0,
+            next_batch_id: 0,
+        }
+    }
+
+    /// Number of transactions waiting to be scheduled.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Number of batches handed out and not yet completed.
+    pub fn num_in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Queues a transaction, prioritized by its fee per compute unit. Returns the number of
+    /// lowest priority transactions dropped to stay within `max_pending`.
+    pub fn push(
+        &mut self,
+        transaction: SanitizedTransaction<'static>,
+        packet: Packet,
+        fee_calculator: &FeeCalculator,
+        cost_tracker: &CostTracker,
+    ) -> usize {
+        let fee = fee_calculator.calculate_fee(&transaction.message);
+        let cost = cost_tracker.transaction_cost(&transaction).max(1);
+        self.pending.push(PendingTransaction {
+            priority: fee.saturating_mul(PRIORITY_SCALE) / cost,
+            sequence: self.next_sequence,
+            transaction,
+            packet,
+        });
+        self.next_sequence += 1;
+        if self.pending.len() <= self.max_pending {
+            return 0;
+        }
+        // Trim a quarter of the queue at once so the sort is amortized over many pushes
+        let mut pending = std::mem::take(&mut self.pending).into_sorted_vec();
+        let num_dropped = pending.len() - self.max_pending * 3 / 4;
+        pending.drain(..num_dropped);
+        self.pending = pending.into();
+        num_dropped
+    }
+
+    /// Builds the highest priority batch of at most `max_batch_size` transactions that do not
+    /// conflict with each other or with any batch in flight, and that fit in the block of `slot`
+    /// according to `cost_tracker`. The batch's cost is reserved in `cost_tracker` and its
+    /// account locks are held until `complete_batch`.
+    pub fn schedule_batch(
+        &mut self,
+        max_batch_size: usize,
+        slot: Slot,
+        cost_tracker: &mut CostTracker,
+    ) -> Option<ScheduledBatch> {
+        let mut batch_locks = AccountLocks::default();
+        let mut scheduled = vec![];
+        let mut skipped = vec![];
+        while scheduled.len() < max_batch_size
+            && scheduled.len() + skipped.len() < MAX_SCHEDULE_LOOKAHEAD
+        {
+            let pending = match self.pending.pop() {
+                Some(pending) => pending,
+                None => break,
+            };
+            if self.account_locks.is_locked(&pending.transaction)
+                || batch_locks.is_locked(&pending.transaction)
+                || cost_tracker
+                    .try_add_transaction(&pending.transaction)
+                    .is_err()
+            {
+                skipped.push(pending);
+            } else {
+                batch_locks.lock(&pending.transaction);
+                scheduled.push(pending);
+            }
+        }
+        self.pending.extend(skipped);
+        if scheduled.is_empty() {
+            return None;
+        }
+
+        let id = self.next_batch_id;
+        self.next_batch_id += 1;
+        for pending in &scheduled {
+            self.account_locks.lock(&pending.transaction);
+        }
+        let transactions = scheduled
+            .iter()
+            .map(|pending| pending.transaction.clone())
+            .collect();
+        self.in_flight.insert(id, InFlightBatch { slot, scheduled });
+        Some(ScheduledBatch { id, transactions })
+    }
+
+    /// Releases the locks of batch `id`, and the reserved cost of its transactions not at
+    /// `committed_indexes`, and requeues its transactions at `retryable_indexes`.
+    pub fn complete_batch(
+        &mut self,
+        id: u64,
+        committed_indexes: &[usize],
+        retryable_indexes: &[usize],
+        cost_tracker: &mut CostTracker,
+    ) {
+        let batch = match self.in_flight.remove(&id) {
+            Some(batch) => batch,
+            None => return,
+        };
+        for (index, pending) in batch.scheduled.into_iter().enumerate() {
+            self.account_locks.unlock(&pending.transaction);
+            if !committed_indexes.contains(&index) {
+                cost_tracker.remove_transaction_cost(batch.slot, &pending.transaction);
+            }
+            if retryable_indexes.contains(&index) {
+                self.pending.push(pending);
+            }
+        }
+    }
+
+    /// Visits the pending transactions from highest to lowest priority, dropping those for which
+    /// `keep` returns false.
+    pub fn retain_by_priority<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&mut PendingTransaction) -> bool,
+    {
+        let mut pending = std::mem::take(&mut self.pending).into_sorted_vec();
+        pending.reverse();
+        pending.retain_mut(|pending| keep(pending));
+        self.pending = pending.into();
+    }
+
+    /// Drops all pending transactions. Batches in flight are unaffected.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_runtime::cost_model::CostModel;
+    use solana_sdk::{
+        hash::Hash,
+        signature::{Keypair, Signer},
+        system_transaction,
+        transaction::Transaction,
+    };
+    use std::{
+        convert::TryFrom,
+        sync::{Arc, RwLock},
+    };
+
+    fn new_cost_tracker() -> CostTracker {
+        CostTracker::new(Arc::new(RwLock::new(CostModel::default())))
+    }
+
+    fn sanitize(transaction: Transaction) -> SanitizedTransaction<'static> {
+        SanitizedTransaction::try_from(transaction).unwrap()
+    }
+
+    fn push(
+        scheduler: &mut TransactionScheduler,
+        transaction: Transaction,
+        lamports_per_signature: u64,
+        cost_tracker: &CostTracker,
+    ) {
+        scheduler.push(
+            sanitize(transaction),
+            Packet::default(),
+            &FeeCalculator::new(lamports_per_signature),
+            cost_tracker,
+        );
+    }
+
+    #[test]
+    fn test_schedule_batch_priority() {
+        let mut cost_tracker = new_cost_tracker();
+        let mut scheduler = TransactionScheduler::new(100);
+        let from = Keypair::new();
+        let to = Keypair::new();
+        let low = system_transaction::transfer(&from, &to.pubkey(), 1, Hash::default());
+        let high = system_transaction::transfer(&to, &from.pubkey(), 1, Hash::default());
+        push(&mut scheduler, low.clone(), 5_000, &cost_tracker);
+        push(&mut scheduler, high.clone(), 10_000, &cost_tracker);
+
+        // Both write `from` and `to`, so only the higher fee one is scheduled
+        let batch = scheduler.schedule_batch(10, 0, &mut cost_tracker).unwrap();
+        assert_eq!(batch.transactions.len(), 1);
+        assert_eq!(batch.transactions[0].signatures, high.signatures);
+
+        // The other stays blocked until the first batch completes
+        assert!(scheduler.schedule_batch(10, 0, &mut cost_tracker).is_none());
+        assert_eq!(scheduler.len(), 1);
+        scheduler.complete_batch(batch.id, &[0], &[], &mut cost_tracker);
+        let batch = scheduler.schedule_batch(10, 0, &mut cost_tracker).unwrap();
+        assert_eq!(batch.transactions[0].signatures, low.signatures);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_schedule_batch_non_conflicting() {
+        let mut cost_tracker = new_cost_tracker();
+        let mut scheduler = TransactionScheduler::new(100);
+        let hot = Keypair::new();
+        for _ in 0..3 {
+            let tx =
+                system_transaction::transfer(&Keypair::new(), &hot.pubkey(), 1, Hash::default());
+            push(&mut scheduler, tx, 5_000, &cost_tracker);
+        }
+        let cold = system_transaction::transfer(
+            &Keypair::new(),
+            &solana_sdk::pubkey::new_rand(),
+            1,
+            Hash::default(),
+        );
+        push(&mut scheduler, cold, 5_000, &cost_tracker);
+
+        // One write to the hot account per batch, plus the unrelated transfer
+        let first = scheduler.schedule_batch(10, 0, &mut cost_tracker).unwrap();
+        assert_eq!(first.transactions.len(), 2);
+        let second = scheduler.schedule_batch(10, 0, &mut cost_tracker);
+        assert!(second.is_none());
+        assert_eq!(scheduler.num_in_flight(), 1);
+
+        // A retried transaction is queued again along with the remaining one
+        scheduler.complete_batch(first.id, &[1], &[0], &mut cost_tracker);
+        assert_eq!(scheduler.len(), 3);
+        let second = scheduler.schedule_batch(10, 0, &mut cost_tracker).unwrap();
+        assert_eq!(second.transactions.len(), 1);
+    }
+
+    #[test]
+    fn test_push_drops_lowest_priority() {
+        let mut cost_tracker = new_cost_tracker();
+        let mut scheduler = TransactionScheduler::new(4);
+        for lamports_per_signature in 1..=4 {
+            let tx = system_transaction::transfer(
+                &Keypair::new(),
+                &solana_sdk::pubkey::new_rand(),
+                1,
+                Hash::default(),
+            );
+            push(&mut scheduler, tx, lamports_per_signature, &cost_tracker);
+        }
+        assert_eq!(scheduler.len(), 4);
+        let tx = system_transaction::transfer(
+            &Keypair::new(),
+            &solana_sdk::pubkey::new_rand(),
+            1,
+            Hash::default(),
+        );
+        assert_eq!(
+            scheduler.push(
+                sanitize(tx),
+                Packet::default(),
+                &FeeCalculator::new(5),
+                &cost_tracker
+            ),
+            2
+        );
+        assert_eq!(scheduler.len(), 3);
+
+        // The remaining transactions are scheduled highest fee first
+        let batch = scheduler.schedule_batch(10, 0, &mut cost_tracker).unwrap();
+        let cost = cost_tracker.transaction_cost(&batch.transactions[0]);
+        let priorities: Vec<_> = scheduler.in_flight[&batch.id]
+            .scheduled
+            .iter()
+            .map(|pending| pending.priority)
+            .collect();
+        assert_eq!(
+            priorities,
+            vec![
+                5 * PRIORITY_SCALE / cost,
+                4 * PRIORITY_SCALE / cost,
+                3 * PRIORITY_SCALE / cost
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retain_by_priority() {
+        let mut cost_tracker = new_cost_tracker();
+        let mut scheduler = TransactionScheduler::new(100);
+        for lamports_per_signature in 1..=4 {
+            let tx = system_transaction::transfer(
+                &Keypair::new(),
+                &solana_sdk::pubkey::new_rand(),
+                1,
+                Hash::default(),
+            );
+            push(&mut scheduler, tx, lamports_per_signature, &cost_tracker);
+        }
+
+        // Visited highest priority first; odd ones are dropped
+        let mut visited = vec![];
+        scheduler.retain_by_priority(|pending| {
+            visited.push(pending.priority);
+            visited.len() % 2 == 0
+        });
+        assert!(visited.windows(2).all(|w| w[0] > w[1]));
+        assert_eq!(scheduler.len(), 2);
+        let batch = scheduler.schedule_batch(10, 0, &mut cost_tracker).unwrap();
+        let priorities: Vec<_> = scheduler.in_flight[&batch.id]
+            .scheduled
+            .iter()
+            .map(|pending| pending.priority)
+            .collect();
+        assert_eq!(priorities, vec![visited[1], visited[3]]);
+    }
+
+    #[test]
+    fn test_schedule_batch_reserves_cost() {
+        let transactions: Vec<_> = (0..2)
+            .map(|_| {
+                system_transaction::transfer(
+                    &Keypair::new(),
+                    &solana_sdk::pubkey::new_rand(),
+                    1,
+                    Hash::default(),
+                )
+            })
+            .collect();
+        let cost = new_cost_tracker().transaction_cost(&sanitize(transactions[0].clone()));
+        // Room for a single transfer in the block
+        let mut cost_tracker = CostTracker::new(Arc::new(RwLock::new(CostModel::new(cost, cost))));
+        let mut scheduler = TransactionScheduler::new(100);
+        for tx in transactions {
+            push(&mut scheduler, tx, 5_000, &cost_tracker);
+        }
+
+        // The transfers don't conflict, but the second doesn't fit next to the first one
+        let batch = scheduler.schedule_batch(10, 0, &mut cost_tracker).unwrap();
+        assert_eq!(batch.transactions.len(), 1);
+        assert!(scheduler.schedule_batch(10, 0, &mut cost_tracker).is_none());
+
+        // The cost of a retried transaction is released
+        scheduler.complete_batch(batch.id, &[], &[0], &mut cost_tracker);
+        let batch = scheduler.schedule_batch(10, 0, &mut cost_tracker).unwrap();
+        assert_eq!(batch.transactions.len(), 1);
+
+        // but not that of a committed one
+        scheduler.complete_batch(batch.id, &[0], &[], &mut cost_tracker);
+        assert!(scheduler.schedule_batch(10, 0, &mut cost_tracker).is_none());
+        assert_eq!(scheduler.len(), 1);
+    }
+}
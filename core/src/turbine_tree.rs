@@ -0,0 +1,400 @@
+//! The `turbine_tree` module defines the shape of the turbine retransmit
+//! tree. For each shred, nodes are ordered by a stake-weighted shuffle seeded
+//! by the shred; the first node in that order receives the shred from the
+//! leader, and a `TurbineTree` determines whom every other position relays it
+//! to.
+
+use {
+    rand::Rng,
+    solana_gossip::{cluster_info::DATA_PLANE_FANOUT, weighted_shuffle::weighted_shuffle},
+    solana_runtime::bank::Bank,
+    solana_sdk::{clock::Slot, feature_set, pubkey::Pubkey},
+    std::collections::VecDeque,
+};
+
+static NEIGHBORHOOD_TREE: NeighborhoodTree = NeighborhoodTree {
+    fanout: DATA_PLANE_FANOUT,
+};
+static STAKE_WEIGHTED_TREE: StakeWeightedTree = StakeWeightedTree {
+    fanout: DATA_PLANE_FANOUT,
+};
+// Fanouts of the stake-weighted tree, each used from the epoch after its
+// feature is activated. Later entries take precedence over earlier ones.
+static STAKE_WEIGHTED_TREE_FANOUTS: [(&Pubkey, StakeWeightedTree); 1] = [(
+    &feature_set::stake_weighted_turbine_fanout_64::ID,
+    StakeWeightedTree { fanout: 64 },
+)];
+
+/// Positions in the shuffled nodes which a node relays a shred to.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TreePeers {
+    /// Nodes sent the shred on their tvu socket.
+    pub tvu: Vec<usize>,
+    /// Nodes sent the shred on their tvu-forwards socket.
+    pub tvu_forwards: Vec<usize>,
+}
+
+pub trait TurbineTree: Sync {
+    /// Returns the number of nodes each node relays the shred to.
+    fn fanout(&self) -> usize;
+
+    /// Returns the peers of the node at `index` among `num_nodes` shuffled
+    /// nodes. Index 0 is the root of the tree.
+    fn get_peers(&self, index: usize, num_nodes: usize) -> TreePeers;
+
+    /// Returns true if the node at `index` should receive the shred on its
+    /// tvu-forwards socket rather than its tvu socket.
+    fn receives_on_forwards(&self, index: usize) -> bool;
+}
+
+/// Tree made of neighborhoods of `fanout` nodes. The first node of each
+/// neighborhood relays the shred to the rest of its neighborhood, and every
+/// node relays it to one node in each of `fanout` neighborhoods of the next
+/// layer. Same layout as `cluster_info::compute_retransmit_peers`.
+#[derive(Debug)]
+pub struct NeighborhoodTree {
+    fanout: usize,
+}
+
+/// Tree where each node relays the shred to its `fanout` children only, so
+/// the staked nodes early in the shuffle sit close to the root.
+#[derive(Debug)]
+pub struct StakeWeightedTree {
+    fanout: usize,
+}
+
+impl NeighborhoodTree {
+    pub fn new(fanout: usize) -> Self {
+        assert!(fanout > 0, "fanout must be positive");
+        Self { fanout }
+    }
+}
+
+impl TurbineTree for NeighborhoodTree {
+    fn fanout(&self) -> usize {
+        self.fanout
+    }
+
+    fn get_peers(&self, index: usize, num_nodes: usize) -> TreePeers {
+        let offset = index % self.fanout; // Node's index within its neighborhood.
+        let anchor = index - offset; // First node in the neighborhood.
+        let children: Vec<_> = ((anchor + 1) * self.fanout + offset..num_nodes)
+            .step_by(self.fanout)
+            .take(self.fanout)
+            .collect();
+        if offset == 0 {
+            let neighbors = (anchor + 1..num_nodes.min(anchor + self.fanout)).collect();
+            TreePeers {
+                tvu: children,
+                tvu_forwards: neighbors,
+            }
+        } else {
+            TreePeers {
+                tvu: Vec::default(),
+                tvu_forwards: children,
+            }
+        }
+    }
+
+    fn receives_on_forwards(&self, index: usize) -> bool {
+        index % self.fanout > 0
+    }
+}
+
+impl StakeWeightedTree {
+    pub fn new(fanout: usize) -> Self {
+        assert!(fanout > 0, "fanout must be positive");
+        Self { fanout }
+    }
+}
+
+impl TurbineTree for StakeWeightedTree {
+    fn fanout(&self) -> usize {
+        self.fanout
+    }
+
+    fn get_peers(&self, index: usize, num_nodes: usize) -> TreePeers {
+        let first_child = index.saturating_mul(self.fanout).saturating_add(1);
+        let end = num_nodes.min(first_child.saturating_add(self.fanout));
+        TreePeers {
+            tvu: (first_child..end).collect(),
+            tvu_forwards: Vec::default(),
+        }
+    }
+
+    fn receives_on_forwards(&self, _index: usize) -> bool {
+        false
+    }
+}
+
+/// Returns the tree used to retransmit shreds of `shred_slot`. The tree and
+/// its fanout only change at an epoch boundary so that all nodes agree on
+/// them.
+pub fn get_turbine_tree(shred_slot: Slot, root_bank: &Bank) -> &'static dyn TurbineTree {
+    if !check_feature_activation(
+        &feature_set::stake_weighted_turbine_tree::id(),
+        shred_slot,
+        root_bank,
+    ) {
+        return &NEIGHBORHOOD_TREE;
+    }
+    STAKE_WEIGHTED_TREE_FANOUTS
+        .iter()
+        .rev()
+        .find(|(feature, _)| check_feature_activation(feature, shred_slot, root_bank))
+        .map(|(_, tree)| tree)
+        .unwrap_or(&STAKE_WEIGHTED_TREE)
+}
+
+fn check_feature_activation(feature: &Pubkey, shred_slot: Slot, root_bank: &Bank) -> bool {
+    match root_bank.feature_set.activated_slot(feature) {
+        None => false,
+        Some(feature_slot) => {
+            let epoch_schedule = root_bank.epoch_schedule();
+            let feature_epoch = epoch_schedule.get_epoch(feature_slot);
+            let shred_epoch = epoch_schedule.get_epoch(shred_slot);
+            feature_epoch < shred_epoch
+        }
+    }
+}
+
+/// Outcome of propagating a single shred through a turbine tree.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PropagationReport {
+    /// Number of nodes in the tree, excluding the leader.
+    pub num_nodes: usize,
+    /// Number of nodes which neither received nor relayed the shred.
+    pub num_failed: usize,
+    /// Number of live nodes which received the shred.
+    pub num_reached: usize,
+    /// Stake of live nodes, and of those which received the shred.
+    pub live_stake: u64,
+    pub reached_stake: u64,
+    /// Number of hops from the leader to the farthest node reached.
+    pub depth: usize,
+}
+
+impl PropagationReport {
+    /// Fraction of live nodes which received the shred.
+    pub fn coverage(&self) -> f64 {
+        let num_live = self.num_nodes - self.num_failed;
+        if num_live == 0 {
+            1.0
+        } else {
+            self.num_reached as f64 / num_live as f64
+        }
+    }
+
+    /// Fraction of live stake which received the shred.
+    pub fn stake_coverage(&self) -> f64 {
+        if self.live_stake == 0 {
+            1.0
+        } else {
+            self.reached_stake as f64 / self.live_stake as f64
+        }
+    }
+}
+
+/// Propagation reports aggregated over many shreds.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PropagationStats {
+    pub num_trials: usize,
+    pub mean_coverage: f64,
+    pub min_coverage: f64,
+    pub mean_stake_coverage: f64,
+    pub min_stake_coverage: f64,
+    pub mean_depth: f64,
+    pub max_depth: usize,
+}
+
+/// Runs `num_trials` independent rounds of `simulate_propagation`, each with
+/// a new shred seed and a new set of failed nodes, and aggregates them.
+pub fn simulate_propagation_trials<R: Rng>(
+    rng: &mut R,
+    tree: &dyn TurbineTree,
+    stakes: &[u64],
+    failure_rate: f64,
+    num_trials: usize,
+) -> PropagationStats {
+    let mut stats = PropagationStats {
+        num_trials,
+        min_coverage: 1.0,
+        min_stake_coverage: 1.0,
+        ..PropagationStats::default()
+    };
+    if num_trials == 0 {
+        return stats;
+    }
+    for _ in 0..num_trials {
+        let report = simulate_propagation(rng, tree, stakes, failure_rate);
+        stats.mean_coverage += report.coverage();
+        stats.min_coverage = stats.min_coverage.min(report.coverage());
+        stats.mean_stake_coverage += report.stake_coverage();
+        stats.min_stake_coverage = stats.min_stake_coverage.min(report.stake_coverage());
+        stats.mean_depth += report.depth as f64;
+        stats.max_depth = stats.max_depth.max(report.depth);
+    }
+    stats.mean_coverage /= num_trials as f64;
+    stats.mean_stake_coverage /= num_trials as f64;
+    stats.mean_depth /= num_trials as f64;
+    stats
+}
+
+/// Simulates the leader sending one shred through `tree` to nodes with the
+/// given `stakes`, where every node independently fails with probability
+/// `failure_rate`.
+pub fn simulate_propagation<R: Rng>(
+    rng: &mut R,
+    tree: &dyn TurbineTree,
+    stakes: &[u64],
+    failure_rate: f64,
+) -> PropagationReport {
+    let mut shred_seed = [0u8; 32];
+    rng.fill(&mut shred_seed[..]);
+    // Same weights as ClusterNodes uses for sampling peers.
+    let shuffle = weighted_shuffle(stakes.iter().map(|stake| (*stake).max(1)), shred_seed);
+    let failed: Vec<bool> = shuffle.iter().map(|_| rng.gen_bool(failure_rate)).collect();
+    let mut report = PropagationReport {
+        num_nodes: stakes.len(),
+        num_failed: failed.iter().filter(|failed| **failed).count(),
+        live_stake: shuffle
+            .iter()
+            .zip(&failed)
+            .filter(|(_, failed)| !**failed)
+            .map(|(node, _)| stakes[*node])
+            .sum(),
+        ..PropagationReport::default()
+    };
+    let mut reached = failed.clone();
+    let mut queue = VecDeque::new();
+    if let Some(false) = failed.first() {
+        reached[0] = true;
+        queue.push_back((0, 1));
+    }
+    while let Some((index, depth)) = queue.pop_front() {
+        report.num_reached += 1;
+        report.reached_stake += stakes[shuffle[index]];
+        report.depth = report.depth.max(depth);
+        let TreePeers { tvu, tvu_forwards } = tree.get_peers(index, shuffle.len());
+        for peer in tvu.into_iter().chain(tvu_forwards) {
+            if !reached[peer] {
+                reached[peer] = true;
+                queue.push_back((peer, depth + 1));
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, rand::SeedableRng, rand_chacha::ChaChaRng,
+        solana_gossip::cluster_info::compute_retransmit_peers,
+        solana_ledger::genesis_utils::create_genesis_config, std::iter::repeat_with,
+    };
+
+    #[test]
+    fn test_get_turbine_tree() {
+        let genesis_config = create_genesis_config(10_000).genesis_config;
+        let mut bank = Bank::new(&genesis_config);
+        bank.deactivate_feature(&feature_set::stake_weighted_turbine_tree::id());
+        bank.deactivate_feature(&feature_set::stake_weighted_turbine_fanout_64::id());
+        // Features are activated at slot 0 and take effect from epoch 1.
+        let shred_slot = bank.epoch_schedule().get_first_slot_in_epoch(1);
+        let tree = get_turbine_tree(shred_slot, &bank);
+        assert!(tree.receives_on_forwards(1));
+        assert_eq!(tree.fanout(), DATA_PLANE_FANOUT);
+        bank.activate_feature(&feature_set::stake_weighted_turbine_fanout_64::id());
+        let tree = get_turbine_tree(shred_slot, &bank);
+        assert!(tree.receives_on_forwards(1));
+        assert_eq!(tree.fanout(), DATA_PLANE_FANOUT);
+        bank.activate_feature(&feature_set::stake_weighted_turbine_tree::id());
+        let tree = get_turbine_tree(shred_slot, &bank);
+        assert!(!tree.receives_on_forwards(1));
+        assert_eq!(tree.fanout(), 64);
+        assert_eq!(get_turbine_tree(0, &bank).fanout(), DATA_PLANE_FANOUT);
+        assert!(get_turbine_tree(0, &bank).receives_on_forwards(1));
+        bank.deactivate_feature(&feature_set::stake_weighted_turbine_fanout_64::id());
+        let tree = get_turbine_tree(shred_slot, &bank);
+        assert!(!tree.receives_on_forwards(1));
+        assert_eq!(tree.fanout(), DATA_PLANE_FANOUT);
+    }
+
+    #[test]
+    fn test_neighborhood_tree_matches_compute_retransmit_peers() {
+        let num_nodes = 1000;
+        let index: Vec<_> = (0..num_nodes).collect();
+        for fanout in 1..50 {
+            let tree = NeighborhoodTree::new(fanout);
+            for node in 0..num_nodes {
+                let (neighbors, children) = compute_retransmit_peers(fanout, node, &index);
+                let peers = tree.get_peers(node, num_nodes);
+                let anchor = neighbors[0] == node;
+                assert_eq!(tree.receives_on_forwards(node), !anchor);
+                if anchor {
+                    assert_eq!(peers.tvu, children);
+                    assert_eq!(peers.tvu_forwards, neighbors[1..]);
+                } else {
+                    assert!(peers.tvu.is_empty());
+                    assert_eq!(peers.tvu_forwards, children);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stake_weighted_tree_has_single_parent() {
+        let num_nodes = 1000;
+        for fanout in 1..50 {
+            let tree = StakeWeightedTree::new(fanout);
+            let mut parents = vec![0; num_nodes];
+            for node in 0..num_nodes {
+                let peers = tree.get_peers(node, num_nodes);
+                assert!(peers.tvu.len() <= fanout);
+                assert!(peers.tvu_forwards.is_empty());
+                assert!(!tree.receives_on_forwards(node));
+                for child in peers.tvu {
+                    assert!(child > node);
+                    parents[child] += 1;
+                }
+            }
+            assert_eq!(parents[0], 0);
+            assert!(parents[1..].iter().all(|count| *count == 1));
+        }
+    }
+
+    #[test]
+    fn test_simulate_propagation() {
+        let mut rng = ChaChaRng::from_seed([7u8; 32]);
+        let stakes: Vec<u64> = repeat_with(|| rng.gen_range(0, 1_000)).take(2000).collect();
+        let total_stake: u64 = stakes.iter().sum();
+        for tree in [
+            &NeighborhoodTree::new(32) as &dyn TurbineTree,
+            &StakeWeightedTree::new(32),
+        ] {
+            // Without failures the shred reaches everyone.
+            let report = simulate_propagation(&mut rng, tree, &stakes, 0.0);
+            assert_eq!(report.num_failed, 0);
+            assert_eq!(report.num_reached, stakes.len());
+            assert_eq!(report.reached_stake, total_stake);
+            assert_eq!(report.coverage(), 1.0);
+            assert!(report.depth <= 5, "{:?}", report);
+            // With failures, whole subtrees are cut off.
+            for _ in 0..20 {
+                let report = simulate_propagation(&mut rng, tree, &stakes, 0.1);
+                assert!(report.num_failed > 0);
+                assert!(report.num_reached + report.num_failed <= stakes.len());
+                assert!(report.reached_stake <= report.live_stake);
+                assert!(report.coverage() <= 1.0);
+                assert!(report.stake_coverage() <= 1.0);
+            }
+        }
+        // Everyone fails.
+        let report = simulate_propagation(&mut rng, &StakeWeightedTree::new(32), &stakes, 1.0);
+        assert_eq!(report.num_failed, stakes.len());
+        assert_eq!(report.num_reached, 0);
+        assert_eq!(report.depth, 0);
+        assert_eq!(report.coverage(), 1.0);
+    }
+}
@@ -12,8 +12,8 @@ use crate::{
     cluster_slots::ClusterSlots,
     completed_data_sets_service::CompletedDataSetsSender,
     consensus::Tower,
-    cost_model::CostModel,
     cost_update_service::CostUpdateService,
+    duplicate_shred_listener::{DuplicateShredHandler, DuplicateShredListener},
     ledger_cleanup_service::LedgerCleanupService,
     replay_stage::{ReplayStage, ReplayStageConfig},
     retransmit_stage::RetransmitStage,
@@ -27,7 +27,8 @@ use crate::{
 use crossbeam_channel::unbounded;
 use solana_gossip::cluster_info::ClusterInfo;
 use solana_ledger::{
-    blockstore::Blockstore, blockstore_processor::TransactionStatusSender,
+    blockstore::{blockstore_archive::TransactionArchiveFilter, Blockstore},
+    blockstore_processor::TransactionStatusSender,
     leader_schedule_cache::LeaderScheduleCache,
 };
 use solana_poh::poh_recorder::PohRecorder;
@@ -43,10 +44,12 @@ use solana_runtime::{
     bank::ExecuteTimings,
     bank_forks::BankForks,
     commitment::BlockCommitmentCache,
+    cost_model::CostModel,
     snapshot_config::SnapshotConfig,
     vote_sender_types::ReplayVoteSender,
 };
 use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use solana_streamer::streamer::PacketReceiverConfig;
 use std::{
     boxed::Box,
     collections::HashSet,
@@ -69,6 +72,7 @@ pub struct Tvu {
     accounts_hash_verifier: AccountsHashVerifier,
     cost_update_service: CostUpdateService,
     voting_service: VotingService,
+    duplicate_shred_listener: DuplicateShredListener,
 }
 
 pub struct Sockets {
@@ -76,11 +80,13 @@ pub struct Sockets {
     pub repair: UdpSocket,
     pub retransmit: Vec<UdpSocket>,
     pub forwards: Vec<UdpSocket>,
+    pub ancestor_hashes_requests: UdpSocket,
 }
 
 #[derive(Default)]
 pub struct TvuConfig {
     pub max_ledger_shreds: Option<u64>,
+    pub transaction_archive_filter: Option<Arc<TransactionArchiveFilter>>,
     pub shred_version: u16,
     pub halt_on_trusted_validators_accounts_hash_mismatch: bool,
     pub trusted_validators: Option<HashSet<Pubkey>>,
@@ -93,6 +99,8 @@ pub struct TvuConfig {
     pub rocksdb_max_compaction_jitter: Option<u64>,
     pub wait_for_vote_to_start_leader: bool,
     pub accounts_shrink_ratio: AccountShrinkThreshold,
+    pub tvu_receiver_config: PacketReceiverConfig,
+    pub repair_receiver_config: PacketReceiverConfig,
 }
 
 impl Tvu {
@@ -139,6 +147,7 @@ impl Tvu {
             fetch: fetch_sockets,
             retransmit: retransmit_sockets,
             forwards: tvu_forward_sockets,
+            ancestor_hashes_requests: ancestor_hashes_socket,
         } = sockets;
 
         let (fetch_sender, fetch_receiver) = channel();
@@ -153,6 +162,8 @@ impl Tvu {
             repair_socket.clone(),
             &fetch_sender,
             Some(bank_forks.clone()),
+            &tvu_config.tvu_receiver_config,
+            &tvu_config.repair_receiver_config,
             exit,
         );
 
@@ -169,6 +180,19 @@ impl Tvu {
         let max_compaction_jitter = tvu_config.rocksdb_max_compaction_jitter;
         let (duplicate_slots_sender, duplicate_slots_receiver) = unbounded();
         let (cluster_slots_update_sender, cluster_slots_update_receiver) = unbounded();
+        let (ancestor_hashes_replay_update_sender, ancestor_hashes_replay_update_receiver) =
+            unbounded();
+        let (dumped_slots_sender, dumped_slots_receiver) = unbounded();
+        let duplicate_shred_listener = DuplicateShredListener::new(
+            exit.clone(),
+            cluster_info.clone(),
+            DuplicateShredHandler::new(
+                blockstore.clone(),
+                leader_schedule_cache.clone(),
+                bank_forks.clone(),
+                duplicate_slots_sender.clone(),
+            ),
+        );
         let retransmit_stage = RetransmitStage::new(
             bank_forks.clone(),
             leader_schedule_cache,
@@ -176,6 +200,7 @@ impl Tvu {
             cluster_info,
             Arc::new(retransmit_sockets),
             repair_socket,
+            Arc::new(ancestor_hashes_socket),
             verified_receiver,
             exit,
             cluster_slots_update_receiver,
@@ -190,6 +215,8 @@ impl Tvu {
             max_slots,
             Some(rpc_subscriptions.clone()),
             duplicate_slots_sender,
+            ancestor_hashes_replay_update_receiver,
+            dumped_slots_receiver,
         );
 
         let (ledger_cleanup_slot_sender, ledger_cleanup_slot_receiver) = channel();
@@ -309,6 +336,8 @@ impl Tvu {
             cluster_slots_update_sender,
             cost_update_sender,
             voting_sender,
+            ancestor_hashes_replay_update_sender,
+            dumped_slots_sender,
         );
 
         let ledger_cleanup_service = tvu_config.max_ledger_shreds.map(|max_ledger_shreds| {
@@ -319,6 +348,7 @@ impl Tvu {
                 exit,
                 compaction_interval,
                 max_compaction_jitter,
+                tvu_config.transaction_archive_filter.clone(),
             )
         });
 
@@ -341,6 +371,7 @@ impl Tvu {
             accounts_hash_verifier,
             cost_update_service,
             voting_service,
+            duplicate_shred_listener,
         }
     }
 
@@ -356,6 +387,7 @@ impl Tvu {
         self.accounts_hash_verifier.join()?;
         self.cost_update_service.join()?;
         self.voting_service.join()?;
+        self.duplicate_shred_listener.join()?;
         Ok(())
     }
 }
@@ -367,6 +399,7 @@ pub mod tests {
     use solana_gossip::cluster_info::{ClusterInfo, Node};
     use solana_ledger::{
         blockstore::BlockstoreSignals,
+        blockstore_db::BlockstoreOptions,
         create_new_tmp_ledger,
         genesis_utils::{create_genesis_config, GenesisConfigInfo},
     };
@@ -400,7 +433,7 @@ pub mod tests {
             blockstore,
             ledger_signal_receiver,
             ..
-        } = Blockstore::open_with_signal(&blockstore_path, None, true)
+        } = Blockstore::open_with_signal(&blockstore_path, BlockstoreOptions::default())
             .expect("Expected to successfully open ledger");
         let blockstore = Arc::new(blockstore);
         let bank = bank_forks.working_bank();
@@ -428,6 +461,7 @@ pub mod tests {
                     retransmit: target1.sockets.retransmit_sockets,
                     fetch: target1.sockets.tvu,
                     forwards: target1.sockets.tvu_forwards,
+                    ancestor_hashes_requests: target1.sockets.ancestor_hashes_requests,
                 }
             },
             blockstore,
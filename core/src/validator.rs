@@ -6,7 +6,7 @@ use crate::{
     cluster_info_vote_listener::VoteTracker,
     completed_data_sets_service::CompletedDataSetsService,
     consensus::{reconcile_blockstore_roots_with_tower, Tower},
-    cost_model::CostModel,
+    packet_forwarder::ForwardingConfig,
     rewards_recorder_service::{RewardsRecorderSender, RewardsRecorderService},
     sample_performance_service::SamplePerformanceService,
     serve_repair::ServeRepair,
@@ -29,8 +29,11 @@ use solana_gossip::{
 };
 use solana_ledger::{
     bank_forks_utils,
-    blockstore::{Blockstore, BlockstoreSignals, CompletedSlotsReceiver, PurgeType},
-    blockstore_db::BlockstoreRecoveryMode,
+    blockstore::{
+        blockstore_archive::TransactionArchiveFilter, Blockstore, BlockstoreSignals,
+        CompletedSlotsReceiver, PurgeType,
+    },
+    blockstore_db::{BlockstoreOptions, BlockstoreRecoveryMode, ShredStorageType},
     blockstore_processor::{self, TransactionStatusSender},
     leader_schedule::FixedSchedule,
     leader_schedule_cache::LeaderScheduleCache,
@@ -38,7 +41,7 @@ use solana_ledger::{
 use solana_measure::measure::Measure;
 use solana_metrics::datapoint_info;
 use solana_poh::{
-    poh_recorder::{PohRecorder, GRACE_TICKS_FACTOR, MAX_GRACE_SLOTS},
+    poh_recorder::{EntryBatchConfig, PohRecorder, GRACE_TICKS_FACTOR, MAX_GRACE_SLOTS},
     poh_service::{self, PohService},
 };
 use solana_rpc::{
@@ -59,6 +62,7 @@ use solana_runtime::{
     bank::Bank,
     bank_forks::BankForks,
     commitment::BlockCommitmentCache,
+    cost_model::CostModel,
     hardened_unpack::{open_genesis_config, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     snapshot_config::SnapshotConfig,
 };
@@ -73,6 +77,8 @@ use solana_sdk::{
     signature::{Keypair, Signer},
     timing::timestamp,
 };
+use solana_send_transaction_service::send_transaction_service;
+use solana_streamer::streamer::PacketReceiverConfig;
 use solana_vote_program::vote_state::VoteState;
 use std::{
     collections::HashSet,
@@ -103,6 +109,7 @@ pub struct ValidatorConfig {
     pub pubsub_config: PubSubConfig,
     pub snapshot_config: Option<SnapshotConfig>,
     pub max_ledger_shreds: Option<u64>,
+    pub transaction_archive_filter: Option<TransactionArchiveFilter>,
     pub broadcast_stage_type: BroadcastStageType,
     pub enable_partition: Option<Arc<AtomicBool>>,
     pub enforce_ulimit_nofile: bool,
@@ -121,6 +128,7 @@ pub struct ValidatorConfig {
     pub accounts_hash_interval_slots: u64,
     pub max_genesis_archive_unpacked_size: u64,
     pub wal_recovery_mode: Option<BlockstoreRecoveryMode>,
+    pub shred_storage_type: ShredStorageType,
     pub poh_verify: bool, // Perform PoH verification during blockstore processing at boo
     pub cuda: bool,
     pub require_tower: bool,
@@ -129,17 +137,24 @@ pub struct ValidatorConfig {
     pub contact_debug_interval: u64,
     pub contact_save_interval: u64,
     pub bpf_jit: bool,
-    pub send_transaction_retry_ms: u64,
-    pub send_transaction_leader_forward_count: u64,
+    pub send_transaction_service_config: send_transaction_service::Config,
     pub no_poh_speed_test: bool,
     pub poh_pinned_cpu_core: usize,
     pub poh_hashes_per_batch: u64,
+    pub poh_entry_batch_config: EntryBatchConfig,
     pub account_indexes: AccountSecondaryIndexes,
     pub accounts_db_caching_enabled: bool,
     pub warp_slot: Option<Slot>,
     pub accounts_db_test_hash_calculation: bool,
     pub accounts_db_use_index_hash_calculation: bool,
     pub tpu_coalesce_ms: u64,
+    pub tpu_use_quic: bool,
+    pub forwarding_config: ForwardingConfig,
+    pub banking_stage_scheduler: bool,
+    pub gossip_receiver_config: PacketReceiverConfig,
+    pub tvu_receiver_config: PacketReceiverConfig,
+    pub repair_receiver_config: PacketReceiverConfig,
+    pub tpu_receiver_config: PacketReceiverConfig,
     pub validator_exit: Arc<RwLock<Exit>>,
     pub no_wait_for_vote_to_start_leader: bool,
     pub accounts_shrink_ratio: AccountShrinkThreshold,
@@ -154,6 +169,7 @@ impl Default for ValidatorConfig {
             expected_shred_version: None,
             voting_disabled: false,
             max_ledger_shreds: None,
+            transaction_archive_filter: None,
             account_paths: Vec::new(),
             account_shrink_paths: None,
             rpc_config: JsonRpcConfig::default(),
@@ -178,6 +194,7 @@ impl Default for ValidatorConfig {
             accounts_hash_interval_slots: std::u64::MAX,
             max_genesis_archive_unpacked_size: MAX_GENESIS_ARCHIVE_UNPACKED_SIZE,
             wal_recovery_mode: None,
+            shred_storage_type: ShredStorageType::RocksLevel,
             poh_verify: true,
             cuda: false,
             require_tower: false,
@@ -186,17 +203,24 @@ impl Default for ValidatorConfig {
             contact_debug_interval: DEFAULT_CONTACT_DEBUG_INTERVAL_MILLIS,
             contact_save_interval: DEFAULT_CONTACT_SAVE_INTERVAL_MILLIS,
             bpf_jit: false,
-            send_transaction_retry_ms: 2000,
-            send_transaction_leader_forward_count: 2,
+            send_transaction_service_config: send_transaction_service::Config::default(),
             no_poh_speed_test: true,
             poh_pinned_cpu_core: poh_service::DEFAULT_PINNED_CPU_CORE,
             poh_hashes_per_batch: poh_service::DEFAULT_HASHES_PER_BATCH,
+            poh_entry_batch_config: EntryBatchConfig::default(),
             account_indexes: AccountSecondaryIndexes::default(),
             accounts_db_caching_enabled: false,
             warp_slot: None,
             accounts_db_test_hash_calculation: false,
             accounts_db_use_index_hash_calculation: true,
             tpu_coalesce_ms: DEFAULT_TPU_COALESCE_MS,
+            tpu_use_quic: false,
+            forwarding_config: ForwardingConfig::default(),
+            banking_stage_scheduler: false,
+            gossip_receiver_config: PacketReceiverConfig::default(),
+            tvu_receiver_config: PacketReceiverConfig::default(),
+            repair_receiver_config: PacketReceiverConfig::default(),
+            tpu_receiver_config: PacketReceiverConfig::default(),
             validator_exit: Arc::new(RwLock::new(Exit::default())),
             no_wait_for_vote_to_start_leader: true,
             accounts_shrink_ratio: AccountShrinkThreshold::default(),
@@ -333,6 +357,7 @@ impl Validator {
                     ledger_path,
                     wait_for_supermajority_slot + 1,
                     shred_version,
+                    config.shred_storage_type,
                 );
             }
         }
@@ -502,6 +527,7 @@ impl Validator {
         if config.snapshot_config.is_some() {
             poh_recorder.set_bank(&bank);
         }
+        poh_recorder.set_entry_batch_config(config.poh_entry_batch_config.clone());
         let poh_recorder = Arc::new(Mutex::new(poh_recorder));
 
         let rpc_override_health_check = Arc::new(AtomicBool::new(false));
@@ -533,8 +559,7 @@ impl Validator {
                     config.trusted_validators.clone(),
                     rpc_override_health_check.clone(),
                     optimistically_confirmed_bank.clone(),
-                    config.send_transaction_retry_ms,
-                    config.send_transaction_leader_forward_count,
+                    config.send_transaction_service_config.clone(),
                     max_slots.clone(),
                     leader_schedule_cache.clone(),
                     max_complete_transaction_status_slot,
@@ -588,13 +613,16 @@ impl Validator {
             node.sockets.gossip,
             config.gossip_validators.clone(),
             should_check_duplicate_instance,
+            config.gossip_receiver_config.clone(),
             &exit,
         );
         let serve_repair = Arc::new(RwLock::new(ServeRepair::new(cluster_info.clone())));
         let serve_repair_service = ServeRepairService::new(
             &serve_repair,
             Some(blockstore.clone()),
+            Some(bank_forks.clone()),
             node.sockets.serve_repair,
+            config.repair_receiver_config.clone(),
             &exit,
         );
 
@@ -700,6 +728,11 @@ impl Validator {
                     .iter()
                     .map(|s| s.try_clone().expect("Failed to clone TVU forwards Sockets"))
                     .collect(),
+                ancestor_hashes_requests: node
+                    .sockets
+                    .ancestor_hashes_requests
+                    .try_clone()
+                    .expect("Failed to clone ancestor_hashes_requests socket"),
             },
             blockstore.clone(),
             ledger_signal_receiver,
@@ -724,6 +757,7 @@ impl Validator {
             cluster_confirmed_slot_receiver,
             TvuConfig {
                 max_ledger_shreds: config.max_ledger_shreds,
+                transaction_archive_filter: config.transaction_archive_filter.clone().map(Arc::new),
                 halt_on_trusted_validators_accounts_hash_mismatch: config
                     .halt_on_trusted_validators_accounts_hash_mismatch,
                 shred_version: node.info.shred_version,
@@ -737,11 +771,21 @@ impl Validator {
                 rocksdb_max_compaction_jitter: config.rocksdb_compaction_interval,
                 wait_for_vote_to_start_leader,
                 accounts_shrink_ratio: config.accounts_shrink_ratio,
+                tvu_receiver_config: config.tvu_receiver_config.clone(),
+                repair_receiver_config: config.repair_receiver_config.clone(),
             },
             &max_slots,
             &cost_model,
         );
 
+        let tpu_quic_socket = if config.tpu_use_quic {
+            if node.sockets.tpu_quic.is_none() {
+                warn!("QUIC is enabled for the TPU but no QUIC socket is bound, ignoring");
+            }
+            node.sockets.tpu_quic
+        } else {
+            None
+        };
         let tpu = Tpu::new(
             &cluster_info,
             &poh_recorder,
@@ -749,6 +793,7 @@ impl Validator {
             retransmit_slots_receiver,
             node.sockets.tpu,
             node.sockets.tpu_forwards,
+            tpu_quic_socket,
             node.sockets.broadcast,
             &rpc_subscriptions,
             transaction_status_sender,
@@ -766,6 +811,9 @@ impl Validator {
             config.tpu_coalesce_ms,
             cluster_confirmed_slot_sender,
             &cost_model,
+            config.forwarding_config.clone(),
+            config.tpu_receiver_config.clone(),
+            config.banking_stage_scheduler,
         );
 
         datapoint_info!("validator-new", ("id", id.to_string(), String));
@@ -1081,8 +1129,12 @@ fn new_banks_from_ledger(
         ..
     } = Blockstore::open_with_signal(
         ledger_path,
-        config.wal_recovery_mode.clone(),
-        enforce_ulimit_nofile,
+        BlockstoreOptions {
+            recovery_mode: config.wal_recovery_mode.clone(),
+            enforce_ulimit_nofile,
+            shred_storage_type: config.shred_storage_type,
+            ..BlockstoreOptions::default()
+        },
     )
     .expect("Failed to open ledger database");
     blockstore.set_no_compaction(config.no_rocksdb_compaction);
@@ -1264,8 +1316,20 @@ fn blockstore_contains_bad_shred_version(
     false
 }
 
-fn backup_and_clear_blockstore(ledger_path: &Path, start_slot: Slot, shred_version: u16) {
-    let blockstore = Blockstore::open(ledger_path).unwrap();
+fn backup_and_clear_blockstore(
+    ledger_path: &Path,
+    start_slot: Slot,
+    shred_version: u16,
+    shred_storage_type: ShredStorageType,
+) {
+    let blockstore = Blockstore::open_with_options(
+        ledger_path,
+        BlockstoreOptions {
+            shred_storage_type,
+            ..BlockstoreOptions::default()
+        },
+    )
+    .unwrap();
     let do_copy_and_clear =
         blockstore_contains_bad_shred_version(&blockstore, start_slot, shred_version);
 
@@ -1647,7 +1711,7 @@ mod tests {
             drop(blockstore);
 
             // this purges and compacts all slots greater than or equal to 5
-            backup_and_clear_blockstore(&blockstore_path, 5, 2);
+            backup_and_clear_blockstore(&blockstore_path, 5, 2, ShredStorageType::RocksLevel);
 
             let blockstore = Blockstore::open(&blockstore_path).unwrap();
             // assert that slots less than 5 aren't affected
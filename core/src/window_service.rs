@@ -2,12 +2,13 @@
 //!   blockstore and retransmitting where required
 //!
 use crate::{
+    ancestor_hashes_service::AncestorHashesReplayUpdateReceiver,
     cluster_info_vote_listener::VerifiedVoteReceiver,
     cluster_slots::ClusterSlots,
     completed_data_sets_service::CompletedDataSetsSender,
     outstanding_requests::OutstandingRequests,
     repair_response,
-    repair_service::{OutstandingShredRepairs, RepairInfo, RepairService},
+    repair_service::{DumpedSlotsReceiver, OutstandingShredRepairs, RepairInfo, RepairService},
     result::{Error, Result},
 };
 use crossbeam_channel::{
@@ -131,11 +132,14 @@ fn verify_repair(
     repair_meta
         .as_ref()
         .map(|repair_meta| {
-            outstanding_requests.register_response(
-                repair_meta.nonce,
-                shred,
-                solana_sdk::timing::timestamp(),
-            )
+            outstanding_requests
+                .register_response(
+                    repair_meta.nonce,
+                    shred,
+                    solana_sdk::timing::timestamp(),
+                    |_| (),
+                )
+                .is_some()
         })
         .unwrap_or(true)
 }
@@ -333,6 +337,7 @@ impl WindowService {
         verified_receiver: CrossbeamReceiver<Vec<Packets>>,
         retransmit: PacketSender,
         repair_socket: Arc<UdpSocket>,
+        ancestor_hashes_socket: Arc<UdpSocket>,
         exit: &Arc<AtomicBool>,
         repair_info: RepairInfo,
         leader_schedule_cache: &Arc<LeaderScheduleCache>,
@@ -341,6 +346,8 @@ impl WindowService {
         verified_vote_receiver: VerifiedVoteReceiver,
         completed_data_sets_sender: CompletedDataSetsSender,
         duplicate_slots_sender: DuplicateSlotSender,
+        ancestor_hashes_replay_update_receiver: AncestorHashesReplayUpdateReceiver,
+        dumped_slots_receiver: DumpedSlotsReceiver,
     ) -> WindowService
     where
         F: 'static
@@ -357,11 +364,14 @@ impl WindowService {
             blockstore.clone(),
             exit.clone(),
             repair_socket,
+            ancestor_hashes_socket,
             cluster_info.clone(),
             repair_info,
             cluster_slots,
             verified_vote_receiver,
             outstanding_requests.clone(),
+            ancestor_hashes_replay_update_receiver,
+            dumped_slots_receiver,
         );
 
         let (insert_sender, insert_receiver) = unbounded();
@@ -624,7 +634,9 @@ mod test {
         keypair: &Keypair,
     ) -> Vec<Shred> {
         let shredder = Shredder::new(slot, parent, 0, 0).unwrap();
-        shredder.entries_to_shreds(keypair, entries, true, 0).0
+        shredder
+            .entries_to_shreds(keypair, entries, true, 0, Some(0))
+            .0
     }
 
     #[test]
@@ -677,7 +689,7 @@ mod test {
         ));
 
         // If it's a coding shred, test that slot >= root
-        let (common, coding) = Shredder::new_coding_shred_header(5, 5, 5, 6, 6, 0);
+        let (common, coding) = Shredder::new_coding_shred_header(5, 5, 5, 6, 6, 0, 0);
         let mut coding_shred =
             Shred::new_empty_from_header(common, DataShredHeader::default(), coding);
         Shredder::sign_shred(&leader_keypair, &mut coding_shred);
@@ -785,7 +797,7 @@ mod test {
         use crate::serve_repair::ShredRepairType;
         use std::net::{IpAddr, Ipv4Addr};
         solana_logger::setup();
-        let (common, coding) = Shredder::new_coding_shred_header(5, 5, 5, 6, 6, 0);
+        let (common, coding) = Shredder::new_coding_shred_header(5, 5, 5, 6, 6, 0, 0);
         let shred = Shred::new_empty_from_header(common, DataShredHeader::default(), coding);
         let mut shreds = vec![shred.clone(), shred.clone(), shred];
         let _from_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
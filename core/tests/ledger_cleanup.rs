@@ -250,6 +250,7 @@ mod tests {
             &exit,
             compaction_interval,
             None,
+            None,
         );
 
         let exit_cpu = Arc::new(AtomicBool::new(false));
@@ -528,6 +529,7 @@ mod tests {
             &mut last_purge_slot,
             10,
             &highest_compact_slot,
+            &None,
         )
         .unwrap();
 
@@ -0,0 +1,115 @@
+//! Turbine Propagation Simulation
+//!
+//! Compares how far a shred travels through the neighborhood and the
+//! stake-weighted turbine trees when some nodes fail to relay it.
+//!
+//! A test library function exists for configuring clusters.
+//! ```
+//!     /// * num_nodes - number of nodes in the cluster, excluding the leader
+//!     /// * fanout - number of nodes each node relays the shred to
+//!     /// * failure_rate - 0 to 1.0 rate of nodes which neither receive nor relay the shred
+//!     /// * num_trials - number of shreds propagated
+//!     fn test_with_cluster(num_nodes: usize, fanout: usize, failure_rate: f64, num_trials: usize);
+//! ```
+//! Modify the test function
+//! ```
+//! #[test]
+//! #[ignore]
+//! fn test_propagation_sweep() {
+//!     test_with_cluster(5_000, 200, 0.1, 1_000);
+//! }
+//! ```
+//! Run with cargo
+//!
+//! ```
+//! cargo test propagation_sweep --release -- --nocapture --ignored
+//! ```
+//!
+//! The output will look like this
+//! ```
+//! nodes: 5000, fanout: 200, failure rate: 0.10, tree: stake-weighted, coverage: mean 0.9990 min 0.9975, stake coverage: mean 0.9991 min 0.9969, depth: mean 3.00 max 3
+//! ```
+use {
+    rand::{Rng, SeedableRng},
+    rand_chacha::ChaChaRng,
+    solana_core::turbine_tree::{
+        simulate_propagation_trials, NeighborhoodTree, PropagationStats, StakeWeightedTree,
+        TurbineTree,
+    },
+    std::iter::repeat_with,
+};
+
+fn test_with_cluster(
+    num_nodes: usize,
+    fanout: usize,
+    failure_rate: f64,
+    num_trials: usize,
+) -> Vec<PropagationStats> {
+    let mut rng = ChaChaRng::from_seed([42u8; 32]);
+    let stakes: Vec<u64> = repeat_with(|| rng.gen_range(0, 1_000_000))
+        .take(num_nodes)
+        .collect();
+    let trees: [(&str, &dyn TurbineTree); 2] = [
+        ("neighborhood", &NeighborhoodTree::new(fanout)),
+        ("stake-weighted", &StakeWeightedTree::new(fanout)),
+    ];
+    trees
+        .iter()
+        .map(|(name, tree)| {
+            let stats =
+                simulate_propagation_trials(&mut rng, *tree, &stakes, failure_rate, num_trials);
+            println!(
+                "nodes: {}, fanout: {}, failure rate: {:.2}, tree: {}, \
+                 coverage: mean {:.4} min {:.4}, \
+                 stake coverage: mean {:.4} min {:.4}, \
+                 depth: mean {:.2} max {}",
+                num_nodes,
+                fanout,
+                failure_rate,
+                name,
+                stats.mean_coverage,
+                stats.min_coverage,
+                stats.mean_stake_coverage,
+                stats.min_stake_coverage,
+                stats.mean_depth,
+                stats.max_depth,
+            );
+            stats
+        })
+        .collect()
+}
+
+#[test]
+fn test_propagation_without_failures() {
+    for fanout in [64, 200] {
+        for stats in test_with_cluster(2_000, fanout, 0.0, 10) {
+            assert_eq!(stats.num_trials, 10);
+            assert_eq!(stats.min_coverage, 1.0);
+            assert_eq!(stats.min_stake_coverage, 1.0);
+            assert!(stats.max_depth <= 3, "{:?}", stats);
+        }
+    }
+}
+
+#[test]
+fn test_propagation_with_failures() {
+    for stats in test_with_cluster(2_000, 64, 0.2, 10) {
+        assert!(stats.min_coverage <= stats.mean_coverage);
+        assert!(stats.mean_coverage <= 1.0);
+        assert!(stats.min_stake_coverage <= stats.mean_stake_coverage);
+        assert!(stats.mean_stake_coverage <= 1.0);
+        assert!(stats.mean_depth <= stats.max_depth as f64);
+    }
+}
+
+#[test]
+#[ignore]
+fn test_propagation_sweep() {
+    for num_nodes in [1_000, 5_000, 10_000] {
+        for fanout in [32, 64, 128, 200] {
+            for failure_rate in [0.0, 0.01, 0.05, 0.1, 0.2, 0.33] {
+                test_with_cluster(num_nodes, fanout, failure_rate, 1_000);
+            }
+        }
+    }
+}
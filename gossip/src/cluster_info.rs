@@ -27,6 +27,7 @@ use {
             SnapshotHash, Version, Vote, MAX_WALLCLOCK,
         },
         data_budget::DataBudget,
+        duplicate_shred::DuplicateShred,
         epoch_slots::EpochSlots,
         gossip_error::GossipError,
         ping_pong::{self, PingCache, Pong},
@@ -42,7 +43,7 @@ use {
     solana_measure::measure::Measure,
     solana_metrics::{inc_new_counter_debug, inc_new_counter_error},
     solana_net_utils::{
-        bind_common, bind_common_in_range, bind_in_range, find_available_port_in_range,
+        bind_common, bind_common_in_range, bind_in_range, bind_to, find_available_port_in_range,
         multi_bind_in_range, PortRange,
     },
     solana_perf::packet::{
@@ -56,6 +57,7 @@ use {
         feature_set::{self, FeatureSet},
         hash::Hash,
         pubkey::Pubkey,
+        quic::QUIC_PORT_OFFSET,
         sanitize::{Sanitize, SanitizeError},
         signature::{Keypair, Signable, Signature, Signer},
         timing::timestamp,
@@ -73,7 +75,7 @@ use {
         collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
         fmt::Debug,
         fs::{self, File},
-        io::BufReader,
+        io::{self, BufReader},
         iter::repeat,
         net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, UdpSocket},
         ops::{Deref, Div},
@@ -107,7 +109,7 @@ const MAX_GOSSIP_TRAFFIC: usize = 128_000_000 / PACKET_DATA_SIZE;
 /// is equal to PACKET_DATA_SIZE minus serialized size of an empty push
 /// message: Protocol::PushMessage(Pubkey::default(), Vec::default())
 const PUSH_MESSAGE_MAX_PAYLOAD_SIZE: usize = PACKET_DATA_SIZE - 44;
-const DUPLICATE_SHRED_MAX_PAYLOAD_SIZE: usize = PACKET_DATA_SIZE - 115;
+pub const DUPLICATE_SHRED_MAX_PAYLOAD_SIZE: usize = PACKET_DATA_SIZE - 115;
 /// Maximum number of hashes in SnapshotHashes/AccountsHashes a node publishes
 /// such that the serialized size of the push/pull message stays below
 /// PACKET_DATA_SIZE.
@@ -1116,6 +1118,23 @@ impl ClusterInfo {
             .collect()
     }
 
+    /// Returns duplicate-shreds inserted since the given cursor.
+    pub fn get_duplicate_shreds(&self, cursor: &mut Cursor) -> Vec<DuplicateShred> {
+        let self_shred_version = Some(self.my_shred_version());
+        let gossip_crds = self.gossip.crds.read().unwrap();
+        gossip_crds
+            .get_duplicate_shreds(cursor)
+            .filter(|entry| {
+                let origin = entry.value.pubkey();
+                gossip_crds.get_shred_version(&origin) == self_shred_version
+            })
+            .map(|entry| match &entry.value.data {
+                CrdsData::DuplicateShred(_, dup) => dup.clone(),
+                _ => panic!("this should not happen!"),
+            })
+            .collect()
+    }
+
     pub fn get_node_version(&self, pubkey: &Pubkey) -> Option<solana_version::Version> {
         let gossip_crds = self.gossip.crds.read().unwrap();
         if let Some(version) = gossip_crds.get::<&Version>(*pubkey) {
@@ -2657,10 +2676,16 @@ pub struct Sockets {
     pub tvu_forwards: Vec<UdpSocket>,
     pub tpu: Vec<UdpSocket>,
     pub tpu_forwards: Vec<UdpSocket>,
+    /// Receives transactions over QUIC, at the `tpu` port plus `QUIC_PORT_OFFSET`. Only bound
+    /// by `Node::bind_tpu_quic`
+    pub tpu_quic: Option<UdpSocket>,
     pub broadcast: Vec<UdpSocket>,
     pub repair: UdpSocket,
     pub retransmit_sockets: Vec<UdpSocket>,
     pub serve_repair: UdpSocket,
+    /// Sends ancestor hashes requests and receives their responses. Not advertised in
+    /// `ContactInfo` since peers respond to the address the request came from
+    pub ancestor_hashes_requests: UdpSocket,
 }
 
 #[derive(Debug)]
@@ -2693,6 +2718,7 @@ impl Node {
         let broadcast = vec![UdpSocket::bind("0.0.0.0:0").unwrap()];
         let retransmit_socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let serve_repair = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let ancestor_hashes_requests = UdpSocket::bind("0.0.0.0:0").unwrap();
         let unused = UdpSocket::bind("0.0.0.0:0").unwrap();
         let info = ContactInfo {
             id: *pubkey,
@@ -2718,14 +2744,46 @@ impl Node {
                 tvu_forwards: vec![tvu_forwards],
                 tpu: vec![tpu],
                 tpu_forwards: vec![tpu_forwards],
+                tpu_quic: None,
                 broadcast,
                 repair,
                 retransmit_sockets: vec![retransmit_socket],
                 serve_repair,
+                ancestor_hashes_requests,
             },
         }
     }
 
+    /// Re-binds the tpu sockets within `port_range` so that the port `QUIC_PORT_OFFSET` above
+    /// them, also within `port_range`, is free for the QUIC socket
+    pub fn bind_tpu_quic(&mut self, bind_ip_addr: IpAddr, port_range: PortRange) -> io::Result<()> {
+        const NUM_TRIES: usize = 100;
+        let tpu_port_range = match port_range.1.checked_sub(QUIC_PORT_OFFSET) {
+            Some(end) if end > port_range.0 => (port_range.0, end),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("port range {:?} is too small for tpu_quic", port_range),
+                ))
+            }
+        };
+        let mut error = None;
+        for _ in 0..NUM_TRIES {
+            let (tpu_port, tpu_sockets) =
+                multi_bind_in_range(bind_ip_addr, tpu_port_range, self.sockets.tpu.len())?;
+            match bind_to(bind_ip_addr, tpu_port + QUIC_PORT_OFFSET, false) {
+                Ok(tpu_quic) => {
+                    self.sockets.tpu = tpu_sockets;
+                    self.sockets.tpu_quic = Some(tpu_quic);
+                    self.info.tpu.set_port(tpu_port);
+                    return Ok(());
+                }
+                Err(err) => error = Some(err),
+            }
+        }
+        Err(error.unwrap())
+    }
+
     fn get_gossip_port(
         gossip_addr: &SocketAddr,
         port_range: PortRange,
@@ -2762,6 +2820,7 @@ impl Node {
         let (repair_port, repair) = Self::bind(bind_ip_addr, port_range);
         let (serve_repair_port, serve_repair) = Self::bind(bind_ip_addr, port_range);
         let (_, broadcast) = Self::bind(bind_ip_addr, port_range);
+        let (_, ancestor_hashes_requests) = Self::bind(bind_ip_addr, port_range);
 
         let rpc_port = find_available_port_in_range(bind_ip_addr, port_range).unwrap();
         let rpc_pubsub_port = find_available_port_in_range(bind_ip_addr, port_range).unwrap();
@@ -2792,10 +2851,12 @@ impl Node {
                 tvu_forwards: vec![tvu_forwards],
                 tpu: vec![tpu],
                 tpu_forwards: vec![tpu_forwards],
+                tpu_quic: None,
                 broadcast: vec![broadcast],
                 repair,
                 retransmit_sockets: vec![retransmit_socket],
                 serve_repair,
+                ancestor_hashes_requests,
             },
         }
     }
@@ -2830,6 +2891,8 @@ impl Node {
         let (_, broadcast) =
             multi_bind_in_range(bind_ip_addr, port_range, 4).expect("broadcast multi_bind");
 
+        let (_, ancestor_hashes_requests) = Self::bind(bind_ip_addr, port_range);
+
         let info = ContactInfo {
             id: *pubkey,
             gossip: SocketAddr::new(gossip_addr.ip(), gossip_port),
@@ -2855,11 +2918,13 @@ impl Node {
                 tvu_forwards: tvu_forwards_sockets,
                 tpu: tpu_sockets,
                 tpu_forwards: tpu_forwards_sockets,
+                tpu_quic: None,
                 broadcast,
                 repair,
                 retransmit_sockets,
                 serve_repair,
                 ip_echo: Some(ip_echo),
+                ancestor_hashes_requests,
             },
         }
     }
@@ -3316,9 +3381,11 @@ mod tests {
     fn check_node_sockets(node: &Node, ip: IpAddr, range: (u16, u16)) {
         check_socket(&node.sockets.gossip, ip, range);
         check_socket(&node.sockets.repair, ip, range);
+        check_socket(&node.sockets.ancestor_hashes_requests, ip, range);
 
         check_sockets(&node.sockets.tvu, ip, range);
         check_sockets(&node.sockets.tpu, ip, range);
+        assert!(node.sockets.tpu_quic.is_none());
     }
 
     #[test]
@@ -3354,6 +3421,34 @@ mod tests {
         assert_eq!(node.sockets.gossip.local_addr().unwrap().port(), port);
     }
 
+    #[test]
+    fn test_bind_tpu_quic() {
+        let ip = IpAddr::V4(Ipv4Addr::from(0));
+        let mut node = Node::new_with_external_ip(
+            &solana_sdk::pubkey::new_rand(),
+            &socketaddr!(0, 0),
+            VALIDATOR_PORT_RANGE,
+            ip,
+        );
+        node.bind_tpu_quic(ip, VALIDATOR_PORT_RANGE).unwrap();
+
+        check_sockets(&node.sockets.tpu, ip, VALIDATOR_PORT_RANGE);
+        let tpu_port = node.sockets.tpu[0].local_addr().unwrap().port();
+        assert_eq!(node.info.tpu.port(), tpu_port);
+        let tpu_quic = node.sockets.tpu_quic.as_ref().unwrap();
+        check_socket(tpu_quic, ip, VALIDATOR_PORT_RANGE);
+        assert_eq!(
+            tpu_quic.local_addr().unwrap().port(),
+            tpu_port + QUIC_PORT_OFFSET
+        );
+
+        let port_range = (
+            VALIDATOR_PORT_RANGE.0,
+            VALIDATOR_PORT_RANGE.0 + QUIC_PORT_OFFSET,
+        );
+        assert!(node.bind_tpu_quic(ip, port_range).is_err());
+    }
+
     //test that all cluster_info objects only generate signed messages
     //when constructed with keypairs
     #[test]
@@ -41,6 +41,25 @@ pub struct DuplicateShred {
     chunk: Vec<u8>,
 }
 
+impl DuplicateShred {
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// Pubkey of the node which pushed this chunk to gossip.
+    pub fn sender(&self) -> Pubkey {
+        self.from
+    }
+
+    pub fn num_chunks(&self) -> u8 {
+        self.num_chunks
+    }
+
+    pub fn chunk_index(&self) -> u8 {
+        self.chunk_index
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("data chunk mismatch")]
@@ -340,6 +359,7 @@ pub(crate) mod tests {
             &entries,
             true, // is_last_in_slot
             next_shred_index,
+            Some(next_shred_index), // next_code_index
         );
         data_shreds.swap_remove(0)
     }
@@ -13,7 +13,7 @@ use {
         pubkey::Pubkey,
         signature::{Keypair, Signer},
     },
-    solana_streamer::streamer,
+    solana_streamer::streamer::{self, PacketReceiverConfig},
     std::{
         collections::HashSet,
         net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, UdpSocket},
@@ -38,6 +38,7 @@ impl GossipService {
         gossip_socket: UdpSocket,
         gossip_validators: Option<HashSet<Pubkey>>,
         should_check_duplicate_instance: bool,
+        receiver_config: PacketReceiverConfig,
         exit: &Arc<AtomicBool>,
     ) -> Self {
         let (request_sender, request_receiver) = channel();
@@ -55,6 +56,7 @@ impl GossipService {
             "gossip_receiver",
             1,
             false,
+            receiver_config,
         );
         let (response_sender, response_receiver) = channel();
         let (consume_sender, listen_receiver) = channel();
@@ -320,6 +322,7 @@ pub fn make_gossip_node(
         gossip_socket,
         None,
         should_check_duplicate_instance,
+        PacketReceiverConfig::default(),
         exit,
     );
     (gossip_service, ip_echo, cluster_info)
@@ -347,6 +350,7 @@ mod tests {
             tn.sockets.gossip,
             None,
             true, // should_check_duplicate_instance
+            PacketReceiverConfig::default(),
             &exit,
         );
         exit.store(true, Ordering::Relaxed);
@@ -18,6 +18,7 @@ use {
         timing::timestamp,
         transaction::Transaction,
     },
+    solana_streamer::streamer::PacketReceiverConfig,
     solana_vote_program::{vote_instruction, vote_state::Vote},
     std::{
         net::UdpSocket,
@@ -40,6 +41,7 @@ fn test_node(exit: &Arc<AtomicBool>) -> (Arc<ClusterInfo>, GossipService, UdpSoc
         test_node.sockets.gossip,
         None,
         true, // should_check_duplicate_instance
+        PacketReceiverConfig::default(),
         exit,
     );
     let _ = cluster_info.my_contact_info();
@@ -63,6 +65,7 @@ fn test_node_with_bank(
         test_node.sockets.gossip,
         None,
         true, // should_check_duplicate_instance
+        PacketReceiverConfig::default(),
         exit,
     );
     let _ = cluster_info.my_contact_info();
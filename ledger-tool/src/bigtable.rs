@@ -10,7 +10,10 @@ use solana_cli_output::{
 };
 use solana_ledger::{blockstore::Blockstore, blockstore_db::AccessType};
 use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
-use solana_transaction_status::{ConfirmedBlock, EncodedTransaction, UiTransactionEncoding};
+use solana_storage_bigtable::{LedgerStorageAdapter, LocalLedgerStorage};
+use solana_transaction_status::{
+    AddressSignatureFilter, ConfirmedBlock, EncodedTransaction, UiTransactionEncoding,
+};
 use std::{
     path::Path,
     process::exit,
@@ -18,20 +21,37 @@ use std::{
     sync::{atomic::AtomicBool, Arc},
 };
 
+// The local storage in `local_storage_path` if given, BigTable otherwise
+async fn ledger_storage(
+    read_only: bool,
+    local_storage_path: Option<&Path>,
+) -> Result<Arc<dyn LedgerStorageAdapter>, Box<dyn std::error::Error>> {
+    Ok(match local_storage_path {
+        Some(path) => Arc::new(
+            LocalLedgerStorage::open(path, read_only)
+                .map_err(|err| format!("Failed to open local storage: {:?}", err))?,
+        ),
+        None => Arc::new(
+            solana_storage_bigtable::LedgerStorage::new(read_only, None)
+                .await
+                .map_err(|err| format!("Failed to connect to storage: {:?}", err))?,
+        ),
+    })
+}
+
 async fn upload(
     blockstore: Blockstore,
     starting_slot: Slot,
     ending_slot: Option<Slot>,
     allow_missing_metadata: bool,
     force_reupload: bool,
+    local_storage_path: Option<&Path>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false, None)
-        .await
-        .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
+    let ledger_storage = ledger_storage(false, local_storage_path).await?;
 
     solana_ledger::bigtable_upload::upload_confirmed_blocks(
         Arc::new(blockstore),
-        bigtable,
+        ledger_storage,
         starting_slot,
         ending_slot,
         allow_missing_metadata,
@@ -41,8 +61,10 @@ async fn upload(
     .await
 }
 
-async fn first_available_block() -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(true, None).await?;
+async fn first_available_block(
+    local_storage_path: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = ledger_storage(true, local_storage_path).await?;
     match bigtable.get_first_available_block().await? {
         Some(block) => println!("{}", block),
         None => println!("No blocks available"),
@@ -51,10 +73,12 @@ async fn first_available_block() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn block(slot: Slot, output_format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false, None)
-        .await
-        .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
+async fn block(
+    slot: Slot,
+    output_format: OutputFormat,
+    local_storage_path: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = ledger_storage(true, local_storage_path).await?;
 
     let block = bigtable.get_confirmed_block(slot).await?;
 
@@ -66,10 +90,12 @@ async fn block(slot: Slot, output_format: OutputFormat) -> Result<(), Box<dyn st
     Ok(())
 }
 
-async fn blocks(starting_slot: Slot, limit: usize) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false, None)
-        .await
-        .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
+async fn blocks(
+    starting_slot: Slot,
+    limit: usize,
+    local_storage_path: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = ledger_storage(true, local_storage_path).await?;
 
     let slots = bigtable.get_confirmed_blocks(starting_slot, limit).await?;
     println!("{:?}", slots);
@@ -82,10 +108,9 @@ async fn confirm(
     signature: &Signature,
     verbose: bool,
     output_format: OutputFormat,
+    local_storage_path: Option<&Path>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(false, None)
-        .await
-        .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
+    let bigtable = ledger_storage(true, local_storage_path).await?;
 
     let transaction_status = bigtable.get_signature_status(signature).await?;
 
@@ -131,8 +156,9 @@ pub async fn transaction_history(
     verbose: bool,
     show_transactions: bool,
     query_chunk_size: usize,
+    local_storage_path: Option<&Path>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let bigtable = solana_storage_bigtable::LedgerStorage::new(true, None).await?;
+    let bigtable = ledger_storage(true, local_storage_path).await?;
 
     let mut loaded_block: Option<(Slot, ConfirmedBlock)> = None;
     while limit > 0 {
@@ -141,9 +167,11 @@ pub async fn transaction_history(
                 address,
                 before.as_ref(),
                 until.as_ref(),
+                &AddressSignatureFilter::default(),
                 limit.min(query_chunk_size),
             )
-            .await?;
+            .await?
+            .signatures;
 
         if results.is_empty() {
             break;
@@ -218,6 +246,15 @@ pub trait BigTableSubCommand {
 
 impl BigTableSubCommand for App<'_, '_> {
     fn bigtable_subcommand(self) -> Self {
+        let local_storage_arg = Arg::with_name("local_storage")
+            .long("local-storage")
+            .value_name("DIR")
+            .takes_value(true)
+            .help(
+                "Use the local storage in this directory, \
+                as served by `solana-validator --local-ledger-storage`, \
+                instead of BigTable",
+            );
         self.subcommand(
             SubCommand::with_name("bigtable")
                 .about("Ledger data on a BigTable instance")
@@ -261,11 +298,13 @@ impl BigTableSubCommand for App<'_, '_> {
                                     Note: reupload will *not* delete any data from the tx-by-addr table;\
                                     Use with care.",
                                 ),
-                        ),
+                        )
+                        .arg(&local_storage_arg),
                 )
                 .subcommand(
                     SubCommand::with_name("first-available-block")
-                        .about("Get the first available block in the storage"),
+                        .about("Get the first available block in the storage")
+                        .arg(&local_storage_arg),
                 )
                 .subcommand(
                     SubCommand::with_name("blocks")
@@ -291,7 +330,8 @@ impl BigTableSubCommand for App<'_, '_> {
                                 .required(true)
                                 .default_value("1000")
                                 .help("Maximum number of slots to return"),
-                        ),
+                        )
+                        .arg(&local_storage_arg),
                 )
                 .subcommand(
                     SubCommand::with_name("block")
@@ -304,7 +344,8 @@ impl BigTableSubCommand for App<'_, '_> {
                                 .takes_value(true)
                                 .index(1)
                                 .required(true),
-                        ),
+                        )
+                        .arg(&local_storage_arg),
                 )
                 .subcommand(
                     SubCommand::with_name("confirm")
@@ -317,7 +358,8 @@ impl BigTableSubCommand for App<'_, '_> {
                                 .required(true)
                                 .index(1)
                                 .help("The transaction signature to confirm"),
-                        ),
+                        )
+                        .arg(&local_storage_arg),
                 )
                 .subcommand(
                     SubCommand::with_name("transaction-history")
@@ -375,7 +417,8 @@ impl BigTableSubCommand for App<'_, '_> {
                                 .long("show-transactions")
                                 .takes_value(false)
                                 .help("Display the full transactions"),
-                        ),
+                        )
+                        .arg(&local_storage_arg),
                 ),
         )
     }
@@ -398,6 +441,11 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
             OutputFormat::Display
         });
 
+    let local_storage_path = matches
+        .subcommand()
+        .1
+        .and_then(|arg_matches| arg_matches.value_of("local_storage"))
+        .map(Path::new);
     let future = match matches.subcommand() {
         ("upload", Some(arg_matches)) => {
             let starting_slot = value_t!(arg_matches, "starting_slot", Slot).unwrap_or(0);
@@ -413,18 +461,21 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
                 ending_slot,
                 allow_missing_metadata,
                 force_reupload,
+                local_storage_path,
             ))
         }
-        ("first-available-block", Some(_arg_matches)) => runtime.block_on(first_available_block()),
+        ("first-available-block", Some(_arg_matches)) => {
+            runtime.block_on(first_available_block(local_storage_path))
+        }
         ("block", Some(arg_matches)) => {
             let slot = value_t_or_exit!(arg_matches, "slot", Slot);
-            runtime.block_on(block(slot, output_format))
+            runtime.block_on(block(slot, output_format, local_storage_path))
         }
         ("blocks", Some(arg_matches)) => {
             let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
             let limit = value_t_or_exit!(arg_matches, "limit", usize);
 
-            runtime.block_on(blocks(starting_slot, limit))
+            runtime.block_on(blocks(starting_slot, limit, local_storage_path))
         }
         ("confirm", Some(arg_matches)) => {
             let signature = arg_matches
@@ -433,7 +484,12 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
                 .parse()
                 .expect("Invalid signature");
 
-            runtime.block_on(confirm(&signature, verbose, output_format))
+            runtime.block_on(confirm(
+                &signature,
+                verbose,
+                output_format,
+                local_storage_path,
+            ))
         }
         ("transaction-history", Some(arg_matches)) => {
             let address = pubkey_of(arg_matches, "address").unwrap();
@@ -455,6 +511,7 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
                 verbose,
                 show_transactions,
                 query_chunk_size,
+                local_storage_path,
             ))
         }
         _ => unreachable!(),
@@ -14,20 +14,23 @@ use solana_clap_utils::{
         is_parsable, is_pubkey, is_pubkey_or_keypair, is_slot, is_valid_percentage,
     },
 };
-use solana_core::cost_model::CostModel;
-use solana_core::cost_tracker::CostTracker;
 use solana_entry::entry::Entry;
 use solana_ledger::{
     ancestor_iterator::AncestorIterator,
     bank_forks_utils,
-    blockstore::{create_new_ledger, Blockstore, PurgeType},
-    blockstore_db::{self, AccessType, BlockstoreRecoveryMode, Column, Database},
+    blockstore::{create_new_ledger, Blockstore, PurgeType, BLOCKSTORE_DIRECTORY_ROCKS_FIFO},
+    blockstore_db::{
+        self, AccessType, BlockstoreOptions, BlockstoreRecoveryMode, BlockstoreRocksFifoOptions,
+        Column, Database, ShredStorageType,
+    },
     blockstore_processor::ProcessOptions,
     shred::Shred,
 };
 use solana_runtime::{
     bank::{Bank, RewardCalculationEvent},
     bank_forks::BankForks,
+    cost_model::CostModel,
+    cost_tracker::CostTracker,
     hardened_unpack::{open_genesis_config, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     snapshot_config::SnapshotConfig,
     snapshot_utils::{
@@ -69,6 +72,8 @@ use std::{
 
 mod bigtable;
 use bigtable::*;
+mod simulate_block_production;
+use simulate_block_production::*;
 
 #[derive(PartialEq)]
 enum LedgerOutputMethod {
@@ -641,12 +646,34 @@ fn analyze_storage(database: &Database) {
     analyze_column::<Rewards>(database, "Rewards", Rewards::key_size());
 }
 
+// The tool never knows the validator's --limit-ledger-size, so FIFO column families are
+// opened unbounded to make sure nothing is dropped while the ledger is being inspected
+fn unbounded_shred_storage_type(fifo: bool) -> ShredStorageType {
+    if fifo {
+        ShredStorageType::RocksFifo(BlockstoreRocksFifoOptions::new(u64::MAX))
+    } else {
+        ShredStorageType::RocksLevel
+    }
+}
+
+fn shred_storage_type(ledger_path: &Path) -> ShredStorageType {
+    unbounded_shred_storage_type(ledger_path.join(BLOCKSTORE_DIRECTORY_ROCKS_FIFO).exists())
+}
+
 fn open_blockstore(
     ledger_path: &Path,
     access_type: AccessType,
     wal_recovery_mode: Option<BlockstoreRecoveryMode>,
 ) -> Blockstore {
-    match Blockstore::open_with_access_type(ledger_path, access_type, wal_recovery_mode, true) {
+    match Blockstore::open_with_options(
+        ledger_path,
+        BlockstoreOptions {
+            access_type,
+            recovery_mode: wal_recovery_mode,
+            shred_storage_type: shred_storage_type(ledger_path),
+            ..BlockstoreOptions::default()
+        },
+    ) {
         Ok(blockstore) => blockstore,
         Err(err) => {
             eprintln!("Failed to open ledger at {:?}: {:?}", ledger_path, err);
@@ -656,7 +683,15 @@ fn open_blockstore(
 }
 
 fn open_database(ledger_path: &Path, access_type: AccessType) -> Database {
-    match Database::open(&ledger_path.join("rocksdb"), access_type, None) {
+    let shred_storage_type = shred_storage_type(ledger_path);
+    match Database::open_with_options(
+        &ledger_path.join(shred_storage_type.blockstore_directory()),
+        BlockstoreOptions {
+            access_type,
+            shred_storage_type,
+            ..BlockstoreOptions::default()
+        },
+    ) {
         Ok(database) => database,
         Err(err) => {
             eprintln!("Unable to read the Ledger rocksdb: {:?}", err);
@@ -1112,6 +1147,12 @@ fn main() {
             .arg(&starting_slot_arg)
             .arg(&ending_slot_arg)
         )
+        .subcommand(
+            SubCommand::with_name("erasure-redundancy")
+            .about("Prints the erasure sets of each slot and their effective redundancy")
+            .arg(&starting_slot_arg)
+            .arg(&ending_slot_arg)
+        )
         .subcommand(
             SubCommand::with_name("bank-hash")
             .about("Prints the hash of the working bank after reading the ledger")
@@ -1487,6 +1528,20 @@ fn main() {
                 .about("Output statistics in JSON format about \
                         all column families in the ledger rocksdb")
         )
+        .subcommand(
+            SubCommand::with_name("migrate-shred-storage")
+                .about("Rewrite the ledger rocksdb for the given shred compaction style, \
+                        as selected by the validator's --rocksdb-shred-compaction")
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("STYLE")
+                        .takes_value(true)
+                        .possible_values(&["level", "fifo"])
+                        .required(true)
+                        .help("Shred compaction style to migrate the ledger to")
+                )
+        )
         .subcommand(
             SubCommand::with_name("compute-slot-cost")
             .about("runs cost_model over the block at the given slots, \
@@ -1501,6 +1556,40 @@ fn main() {
                     .help("Slots that their blocks are computed for cost, default to all slots in ledger"),
             )
         )
+        .subcommand(
+            SubCommand::with_name("simulate-block-production")
+            .about("Produce the rooted blocks of a slot range again, from their recorded \
+                   transactions, and compare them with the recorded blocks")
+            .arg(&no_snapshot_arg)
+            .arg(&account_paths_arg)
+            .arg(&hard_forks_arg)
+            .arg(&no_accounts_db_caching_arg)
+            .arg(&no_bpf_jit_arg)
+            .arg(&max_genesis_archive_unpacked_size_arg)
+            .arg(
+                Arg::with_name("first_simulated_slot")
+                    .long("first-simulated-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .required(true)
+                    .help("The first slot to produce; the ledger is replayed up to its parent"),
+            )
+            .arg(
+                Arg::with_name("last_simulated_slot")
+                    .long("last-simulated-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .help("The last slot to produce [default: the first simulated slot]"),
+            )
+            .arg(
+                Arg::with_name("central_scheduler")
+                    .long("central-scheduler")
+                    .takes_value(false)
+                    .help("Feed the banking threads from the central transaction scheduler"),
+            )
+        )
         .get_matches();
 
     info!("{} {}", crate_name!(), solana_version::version!());
@@ -1687,6 +1776,59 @@ fn main() {
                 }
             }
         }
+        ("erasure-redundancy", Some(arg_matches)) => {
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let ending_slot = value_t!(arg_matches, "ending_slot", Slot).unwrap_or(Slot::MAX);
+            let blockstore = open_blockstore(
+                &ledger_path,
+                AccessType::TryPrimaryThenSecondary,
+                wal_recovery_mode,
+            );
+            for (slot, _meta) in blockstore
+                .slot_meta_iterator(starting_slot)
+                .unwrap()
+                .take_while(|(slot, _)| *slot <= ending_slot)
+            {
+                let index = match blockstore.get_index(slot) {
+                    Ok(Some(index)) => index,
+                    _ => continue,
+                };
+                let erasure_metas: Vec<_> =
+                    blockstore.erasure_meta_iterator(slot).unwrap().collect();
+                let num_data: usize = erasure_metas
+                    .iter()
+                    .map(|(_, erasure_meta)| erasure_meta.config.num_data())
+                    .sum();
+                let num_coding: usize = erasure_metas
+                    .iter()
+                    .map(|(_, erasure_meta)| erasure_meta.config.num_coding())
+                    .sum();
+                println!(
+                    "Slot {}: {} erasure sets, {} data shreds, {} coding shreds, redundancy: {:.2}",
+                    slot,
+                    erasure_metas.len(),
+                    num_data,
+                    num_coding,
+                    num_coding as f64 / num_data.max(1) as f64,
+                );
+                for (set_index, erasure_meta) in erasure_metas {
+                    println!(
+                        "  set {}: data {}/{}, coding {}/{} from index {}, status: {:?}",
+                        set_index,
+                        index
+                            .data()
+                            .present_in_bounds(erasure_meta.data_shreds_indices()),
+                        erasure_meta.config.num_data(),
+                        index
+                            .coding()
+                            .present_in_bounds(erasure_meta.coding_shreds_indices()),
+                        erasure_meta.config.num_coding(),
+                        erasure_meta.first_coding_index(),
+                        erasure_meta.status(&index),
+                    );
+                }
+            }
+        }
         ("bank-hash", Some(arg_matches)) => {
             let process_options = ProcessOptions {
                 dev_halt_at_slot: Some(0),
@@ -3096,6 +3238,59 @@ fn main() {
             ));
             println!("Ok.");
         }
+        ("migrate-shred-storage", Some(arg_matches)) => {
+            let source_type = shred_storage_type(&ledger_path);
+            let target_type =
+                unbounded_shred_storage_type(arg_matches.value_of("to") == Some("fifo"));
+            if source_type.blockstore_directory() == target_type.blockstore_directory() {
+                println!("Ledger already uses the requested shred compaction style");
+                exit(0);
+            }
+
+            let source_path = ledger_path.join(source_type.blockstore_directory());
+            let target_path = ledger_path.join(target_type.blockstore_directory());
+            let open = |path: &Path, access_type, shred_storage_type| {
+                Database::open_with_options(
+                    path,
+                    BlockstoreOptions {
+                        access_type,
+                        shred_storage_type,
+                        ..BlockstoreOptions::default()
+                    },
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("Unable to open {:?}: {:?}", path, err);
+                    exit(1);
+                })
+            };
+            let source = open(&source_path, AccessType::PrimaryOnly, source_type);
+            let target = open(
+                &target_path,
+                AccessType::PrimaryOnlyForMaintenance,
+                target_type,
+            );
+            let num_entries = source.copy_to(&target).unwrap_or_else(|err| {
+                eprintln!("Failed to migrate the ledger: {:?}", err);
+                exit(1);
+            });
+            drop(source);
+            drop(target);
+
+            if let Err(err) = Database::destroy(&source_path) {
+                eprintln!("Unable to destroy {:?}: {:?}", source_path, err);
+                exit(1);
+            }
+            if source_path.exists() {
+                if let Err(err) = fs::remove_dir_all(&source_path) {
+                    eprintln!("Unable to remove {:?}: {}", source_path, err);
+                    exit(1);
+                }
+            }
+            println!(
+                "Migrated {} entries from {:?} to {:?}",
+                num_entries, source_path, target_path
+            );
+        }
         ("compute-slot-cost", Some(arg_matches)) => {
             let blockstore = open_blockstore(
                 &ledger_path,
@@ -3118,6 +3313,60 @@ fn main() {
                 }
             }
         }
+        ("simulate-block-production", Some(arg_matches)) => {
+            let first_slot = value_t_or_exit!(arg_matches, "first_simulated_slot", Slot);
+            let last_slot =
+                value_t!(arg_matches, "last_simulated_slot", Slot).unwrap_or(first_slot);
+            let blockstore = open_blockstore(
+                &ledger_path,
+                AccessType::TryPrimaryThenSecondary,
+                wal_recovery_mode,
+            );
+            let parent_slot = match blockstore.meta(first_slot) {
+                Ok(Some(meta)) => meta.parent_slot,
+                Ok(None) => {
+                    eprintln!("Slot {} is not in the ledger", first_slot);
+                    exit(1);
+                }
+                Err(err) => {
+                    eprintln!("Failed to read slot {} meta: {:?}", first_slot, err);
+                    exit(1);
+                }
+            };
+            let process_options = ProcessOptions {
+                dev_halt_at_slot: Some(parent_slot),
+                new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
+                poh_verify: false,
+                bpf_jit: !arg_matches.is_present("no_bpf_jit"),
+                accounts_db_caching_enabled: !arg_matches.is_present("no_accounts_db_caching"),
+                ..ProcessOptions::default()
+            };
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            let (bank_forks, leader_schedule_cache, _) = load_bank_forks(
+                arg_matches,
+                &genesis_config,
+                &blockstore,
+                process_options,
+                snapshot_archive_path,
+            )
+            .unwrap_or_else(|err| {
+                eprintln!("Failed to load ledger: {:?}", err);
+                exit(1);
+            });
+
+            if let Err(err) = simulate_block_production(
+                &genesis_config,
+                Arc::new(blockstore),
+                bank_forks,
+                leader_schedule_cache,
+                first_slot,
+                last_slot,
+                arg_matches.is_present("central_scheduler"),
+            ) {
+                eprintln!("Block production simulation failed: {}", err);
+                exit(1);
+            }
+        }
         ("", _) => {
             eprintln!("{}", matches.usage());
             exit(1);
@@ -0,0 +1,495 @@
+//! The `simulate-block-production` subcommand feeds the recorded transactions of a ledger range
+//! back through `BankingStage` and `PohRecorder`, with the original leader schedule, and
+//! compares the blocks produced with the recorded ones
+use crossbeam_channel::unbounded;
+use log::*;
+use solana_core::{banking_stage::BankingStage, packet_forwarder::ForwardingConfig};
+use solana_entry::entry::Entry;
+use solana_gossip::{cluster_info::ClusterInfo, contact_info::ContactInfo};
+use solana_ledger::{
+    blockstore::Blockstore, blockstore_processor, leader_schedule_cache::LeaderScheduleCache,
+};
+use solana_perf::packet::to_packets_chunked;
+use solana_poh::{
+    poh_recorder::PohRecorder,
+    poh_service::{self, PohService},
+};
+use solana_runtime::{
+    bank::{Bank, ExecuteTimings},
+    bank_forks::BankForks,
+    cost_model::CostModel,
+    cost_tracker::CostTracker,
+};
+use solana_sdk::{
+    clock::Slot, genesis_config::GenesisConfig, signature::Signature, timing::timestamp,
+    transaction::Transaction,
+};
+use solana_vote_program::vote_transaction;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::RecvTimeoutError,
+        Arc, Mutex, RwLock,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+const PACKETS_PER_BATCH: usize = 128;
+
+// How long a simulated slot may run past its ideal duration before the simulation gives up
+const SLOT_TIMEOUT_FACTOR: u32 = 10;
+
+// What a block holds, as far as the comparison goes
+#[derive(Default)]
+struct BlockSummary {
+    // Transaction signatures, in block order
+    signatures: Vec<Signature>,
+    num_votes: usize,
+    // Ticks preceding the last transaction entry
+    last_transaction_tick: Option<usize>,
+    num_ticks: usize,
+}
+
+impl BlockSummary {
+    fn new<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Self {
+        let mut summary = Self::default();
+        for entry in entries {
+            if entry.is_tick() {
+                summary.num_ticks += 1;
+                continue;
+            }
+            summary.last_transaction_tick = Some(summary.num_ticks);
+            for transaction in &entry.transactions {
+                summary.add_transaction(transaction);
+            }
+        }
+        summary
+    }
+
+    fn add_transaction(&mut self, transaction: &Transaction) {
+        self.signatures.push(transaction.signatures[0]);
+        if vote_transaction::parse_vote_transaction(transaction).is_some() {
+            self.num_votes += 1;
+        }
+    }
+
+    fn format_last_transaction_tick(&self) -> String {
+        match self.last_transaction_tick {
+            Some(tick) => format!("{}/{}", tick, self.num_ticks),
+            None => format!("-/{}", self.num_ticks),
+        }
+    }
+}
+
+// Transactions of both blocks that would have to move for `simulated` to list them in the
+// order of `recorded`: the ones outside of a longest increasing run of recorded positions
+fn num_reordered(recorded: &[Signature], simulated: &[Signature]) -> usize {
+    let recorded_positions: HashMap<_, _> = recorded
+        .iter()
+        .enumerate()
+        .map(|(position, signature)| (signature, position))
+        .collect();
+    let positions: Vec<_> = simulated
+        .iter()
+        .filter_map(|signature| recorded_positions.get(signature).copied())
+        .collect();
+    let mut run_tails: Vec<usize> = vec![];
+    for position in &positions {
+        if let Err(i) = run_tails.binary_search(position) {
+            if i == run_tails.len() {
+                run_tails.push(*position);
+            } else {
+                run_tails[i] = *position;
+            }
+        }
+    }
+    positions.len() - run_tails.len()
+}
+
+// Compute units consumed by the programs whose executions `timings` accumulated
+fn units_consumed(timings: &ExecuteTimings) -> u64 {
+    timings
+        .details
+        .per_program_timings
+        .values()
+        .fold(0, |units: u64, timing| {
+            units.saturating_add(timing.accumulated_units)
+        })
+}
+
+// Time from the first shred of `slot` to the first shred of the next slot, as received by the
+// node which recorded the ledger: the closest the ledger comes to the duration of the recorded
+// slot. None if the next slot was skipped
+fn recorded_slot_time(blockstore: &Blockstore, slot: Slot) -> Option<u64> {
+    let meta = blockstore.meta(slot).ok().flatten()?;
+    let next_meta = blockstore.meta(slot + 1).ok().flatten()?;
+    if next_meta.parent_slot != slot || next_meta.first_shred_timestamp == 0 {
+        return None;
+    }
+    next_meta
+        .first_shred_timestamp
+        .checked_sub(meta.first_shred_timestamp)
+}
+
+#[derive(Default)]
+struct SimulationTotals {
+    num_slots: usize,
+    num_recorded: usize,
+    num_simulated: usize,
+    num_missing: usize,
+    num_extra: usize,
+    num_reordered: usize,
+    recorded_units: u64,
+    simulated_units: u64,
+}
+
+/// Simulates the production of the rooted slots in \[first_slot,last_slot\]. Each slot is
+/// produced by a bank of the recorded parent, as the leader of the recorded schedule, from
+/// packets of the recorded transactions that are all sent when the slot starts. The recorded
+/// block is then replayed to produce the parent of the next slot, so that slots are compared
+/// independently of one another
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_block_production(
+    genesis_config: &GenesisConfig,
+    blockstore: Arc<Blockstore>,
+    bank_forks: BankForks,
+    leader_schedule_cache: LeaderScheduleCache,
+    first_slot: Slot,
+    last_slot: Slot,
+    central_scheduler: bool,
+) -> Result<(), String> {
+    let first_parent_slot = blockstore
+        .meta(first_slot)
+        .map_err(|err| format!("Failed to read slot {} meta: {:?}", first_slot, err))?
+        .map(|meta| meta.parent_slot)
+        .ok_or_else(|| format!("Slot {} is not in the ledger", first_slot))?;
+    let mut parent = bank_forks.get(first_parent_slot).cloned().ok_or_else(|| {
+        format!(
+            "Parent slot {} of slot {} was not loaded; is the snapshot older than it?",
+            first_parent_slot, first_slot
+        )
+    })?;
+    let slots: Vec<_> = blockstore
+        .rooted_slot_iterator(first_slot)
+        .map_err(|err| format!("Failed to read the ledger roots: {:?}", err))?
+        .take_while(|slot| *slot <= last_slot)
+        .collect();
+    if slots.first() != Some(&first_slot) {
+        return Err(format!("Slot {} is not rooted", first_slot));
+    }
+
+    let leader_schedule_cache = Arc::new(leader_schedule_cache);
+    let poh_config = Arc::new(genesis_config.poh_config.clone());
+    let exit = Arc::new(AtomicBool::new(false));
+    let (poh_recorder, entry_receiver, record_receiver) = PohRecorder::new(
+        parent.tick_height(),
+        parent.last_blockhash(),
+        parent.slot(),
+        None,
+        parent.ticks_per_slot(),
+        &solana_sdk::pubkey::new_rand(),
+        &blockstore,
+        &leader_schedule_cache,
+        &poh_config,
+        exit.clone(),
+    );
+    let poh_recorder = Arc::new(Mutex::new(poh_recorder));
+    let poh_service = PohService::new(
+        poh_recorder.clone(),
+        &poh_config,
+        &exit,
+        parent.ticks_per_slot(),
+        poh_service::DEFAULT_PINNED_CPU_CORE,
+        poh_service::DEFAULT_HASHES_PER_BATCH,
+        record_receiver,
+    );
+
+    // This node leads no slot, so that the packets left over at the end of a slot are
+    // forwarded, and dropped, instead of being held for the next slot
+    let cluster_info = Arc::new(ClusterInfo::new_with_invalid_keypair(
+        ContactInfo::new_localhost(&solana_sdk::pubkey::new_rand(), timestamp()),
+    ));
+    let mut cost_model = CostModel::default();
+    cost_model.initialize_cost_table(&blockstore.read_program_costs().unwrap_or_default());
+    let cost_model = Arc::new(RwLock::new(cost_model));
+    let cost_tracker = Arc::new(RwLock::new(CostTracker::new(cost_model)));
+    let forwarding_bank_forks = Arc::new(RwLock::new(BankForks::new_from_banks(
+        &[parent.clone()],
+        parent.slot(),
+    )));
+    let (verified_sender, verified_receiver) = unbounded();
+    let (vote_sender, vote_receiver) = unbounded();
+    let (replay_vote_sender, _replay_vote_receiver) = unbounded();
+    let banking_stage = if central_scheduler {
+        BankingStage::new_with_scheduler(
+            &cluster_info,
+            &poh_recorder,
+            verified_receiver,
+            vote_receiver,
+            BankingStage::num_threads(),
+            None,
+            replay_vote_sender,
+            cost_tracker,
+            &forwarding_bank_forks,
+            ForwardingConfig::default(),
+        )
+    } else {
+        BankingStage::new(
+            &cluster_info,
+            &poh_recorder,
+            verified_receiver,
+            vote_receiver,
+            None,
+            replay_vote_sender,
+            cost_tracker,
+            &forwarding_bank_forks,
+            ForwardingConfig::default(),
+        )
+    };
+
+    let ticks_per_slot = parent.ticks_per_slot();
+    let slot_timeout =
+        poh_config.target_tick_duration * ticks_per_slot as u32 * SLOT_TIMEOUT_FACTOR;
+    let mut totals = SimulationTotals::default();
+    let mut result = Ok(());
+    for slot in slots {
+        let recorded_entries = match blockstore.get_slot_entries(slot, 0) {
+            Ok(entries) => entries,
+            Err(err) => {
+                result = Err(format!("Failed to load slot {} entries: {:?}", slot, err));
+                break;
+            }
+        };
+        let parent_slot = blockstore
+            .meta(slot)
+            .ok()
+            .flatten()
+            .map(|meta| meta.parent_slot);
+        if parent_slot != Some(parent.slot()) {
+            result = Err(format!(
+                "Slot {} does not descend from the simulated slot {}",
+                slot,
+                parent.slot()
+            ));
+            break;
+        }
+        let leader = match leader_schedule_cache.slot_leader_at(slot, Some(&parent)) {
+            Some(leader) => leader,
+            None => {
+                result = Err(format!("No leader is scheduled for slot {}", slot));
+                break;
+            }
+        };
+
+        // Tick through the slots skipped since the parent before starting the leader slot,
+        // as a leader does
+        poh_recorder
+            .lock()
+            .unwrap()
+            .reset(parent.last_blockhash(), parent.slot(), None);
+        while poh_recorder.lock().unwrap().tick_height() < slot * ticks_per_slot {
+            sleep(Duration::from_millis(1));
+        }
+        let bank = Arc::new(Bank::new_from_parent(&parent, &leader, slot));
+        poh_recorder.lock().unwrap().set_bank(&bank);
+        let slot_start = Instant::now();
+
+        let (votes, transactions): (Vec<_>, Vec<_>) = recorded_entries
+            .iter()
+            .flat_map(|entry| entry.transactions.iter().cloned())
+            .partition(|transaction| {
+                vote_transaction::parse_vote_transaction(transaction).is_some()
+            });
+        let _ = verified_sender.send(to_packets_chunked(&transactions, PACKETS_PER_BATCH));
+        let _ = vote_sender.send(to_packets_chunked(&votes, PACKETS_PER_BATCH));
+
+        let mut simulated_entries = vec![];
+        let mut last_transaction_elapsed = None;
+        loop {
+            match entry_receiver.recv_timeout(Duration::from_millis(100)) {
+                Ok((entry_bank, (entry, tick_height))) => {
+                    if entry_bank.slot() != slot {
+                        continue;
+                    }
+                    if !entry.is_tick() {
+                        last_transaction_elapsed = Some(slot_start.elapsed());
+                    }
+                    simulated_entries.push(entry);
+                    if tick_height == bank.max_tick_height() {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if slot_start.elapsed() > slot_timeout {
+                        result = Err(format!("Timed out producing slot {}", slot));
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    result = Err("PoH recorder disconnected".to_string());
+                    break;
+                }
+            }
+        }
+        let slot_elapsed = slot_start.elapsed();
+        if result.is_err() {
+            break;
+        }
+
+        // Wait for the banking threads to let go of the simulated bank, then discard its
+        // state, so that the blocks can be replayed in the same slot
+        while Arc::strong_count(&bank) > 1 && slot_start.elapsed() < slot_timeout {
+            sleep(Duration::from_millis(1));
+        }
+        parent.remove_unrooted_slots(&[(slot, bank.bank_id())]);
+        parent.clear_slot_signatures(slot);
+        drop(bank);
+
+        let recorded = BlockSummary::new(&recorded_entries);
+        let simulated = BlockSummary::new(&simulated_entries);
+
+        // Replay the simulated block on a bank of its own, which executes its transactions as
+        // the banking stage did, to count the compute units they consume
+        let simulated_bank = Arc::new(Bank::new_from_parent(&parent, &leader, slot));
+        let mut simulated_timings = ExecuteTimings::default();
+        if let Err(err) = blockstore_processor::process_entries_with_timings(
+            &simulated_bank,
+            &mut simulated_entries,
+            false,
+            None,
+            None,
+            &mut simulated_timings,
+        ) {
+            warn!("Failed to replay the simulated slot {}: {:?}", slot, err);
+        }
+        parent.remove_unrooted_slots(&[(slot, simulated_bank.bank_id())]);
+        parent.clear_slot_signatures(slot);
+        drop(simulated_bank);
+
+        // Replay the recorded block to produce the parent of the next slot
+        let recorded_bank = Arc::new(Bank::new_from_parent(&parent, &leader, slot));
+        let mut recorded_timings = ExecuteTimings::default();
+        let mut entries = recorded_entries;
+        if let Err(err) = blockstore_processor::process_entries_with_timings(
+            &recorded_bank,
+            &mut entries,
+            false,
+            None,
+            None,
+            &mut recorded_timings,
+        ) {
+            result = Err(format!("Failed to replay slot {}: {:?}", slot, err));
+            break;
+        }
+        recorded_bank.freeze();
+        if let Some(recorded_hash) = blockstore.get_bank_hash(slot) {
+            if recorded_hash != recorded_bank.hash() {
+                warn!(
+                    "Slot {} replayed to bank hash {}, instead of the recorded {}",
+                    slot,
+                    recorded_bank.hash(),
+                    recorded_hash
+                );
+            }
+        }
+        recorded_bank.squash();
+        parent = recorded_bank;
+
+        let recorded_units = units_consumed(&recorded_timings);
+        let simulated_units = units_consumed(&simulated_timings);
+        let recorded_signatures: HashSet<_> = recorded.signatures.iter().collect();
+        let num_included = simulated
+            .signatures
+            .iter()
+            .filter(|signature| recorded_signatures.contains(signature))
+            .count();
+        let num_missing = recorded.signatures.len() - num_included;
+        let num_extra = simulated.signatures.len() - num_included;
+        let num_reordered = num_reordered(&recorded.signatures, &simulated.signatures);
+        println!(
+            "Slot: {}, Leader: {}, Transactions: {} recorded ({} votes), {} simulated ({} votes), \
+             Missing: {}, Extra: {}, Reordered: {}, Compute units: {} recorded, {} simulated, \
+             Last transaction tick: {} recorded, {} simulated, \
+             Time: {} recorded, {}ms simulated, last simulated transaction at {}",
+            slot,
+            leader,
+            recorded.signatures.len(),
+            recorded.num_votes,
+            simulated.signatures.len(),
+            simulated.num_votes,
+            num_missing,
+            num_extra,
+            num_reordered,
+            recorded_units,
+            simulated_units,
+            recorded.format_last_transaction_tick(),
+            simulated.format_last_transaction_tick(),
+            recorded_slot_time(&blockstore, slot)
+                .map(|time| format!("{}ms", time))
+                .unwrap_or_else(|| "-".to_string()),
+            slot_elapsed.as_millis(),
+            last_transaction_elapsed
+                .map(|elapsed| format!("{}ms", elapsed.as_millis()))
+                .unwrap_or_else(|| "-".to_string()),
+        );
+        totals.num_slots += 1;
+        totals.num_recorded += recorded.signatures.len();
+        totals.num_simulated += simulated.signatures.len();
+        totals.num_missing += num_missing;
+        totals.num_extra += num_extra;
+        totals.num_reordered += num_reordered;
+        totals.recorded_units += recorded_units;
+        totals.simulated_units += simulated_units;
+    }
+
+    println!(
+        "Slots: {}, Transactions: {} recorded, {} simulated, Missing: {}, Extra: {}, \
+         Reordered: {}, Compute units: {} recorded, {} simulated",
+        totals.num_slots,
+        totals.num_recorded,
+        totals.num_simulated,
+        totals.num_missing,
+        totals.num_extra,
+        totals.num_reordered,
+        totals.recorded_units,
+        totals.simulated_units,
+    );
+
+    drop(verified_sender);
+    drop(vote_sender);
+    exit.store(true, Ordering::Relaxed);
+    let _ = banking_stage.join();
+    let _ = poh_service.join();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_num_reordered() {
+        let signatures: Vec<_> = (0..6u8).map(|i| Signature::new(&[i; 64])).collect();
+        let pick =
+            |indexes: &[usize]| -> Vec<_> { indexes.iter().map(|i| signatures[*i]).collect() };
+        let recorded = pick(&[0, 1, 2, 3, 4]);
+        assert_eq!(num_reordered(&recorded, &recorded), 0);
+        assert_eq!(num_reordered(&recorded, &[]), 0);
+        assert_eq!(num_reordered(&[], &recorded), 0);
+        // Missing and extra transactions are not reordered
+        assert_eq!(num_reordered(&recorded, &pick(&[0, 2, 4])), 0);
+        assert_eq!(num_reordered(&recorded, &pick(&[0, 5, 1, 2])), 0);
+        // Moving one transaction
+        assert_eq!(num_reordered(&recorded, &pick(&[4, 0, 1, 2, 3])), 1);
+        assert_eq!(num_reordered(&recorded, &pick(&[1, 2, 3, 4, 0])), 1);
+        assert_eq!(num_reordered(&recorded, &pick(&[0, 3, 1, 2, 4])), 1);
+        // Swapping two transactions
+        assert_eq!(num_reordered(&recorded, &pick(&[1, 0, 2, 3, 4])), 1);
+        assert_eq!(num_reordered(&recorded, &pick(&[0, 1, 3, 2, 5])), 1);
+        // Reversed order keeps a single transaction in place
+        assert_eq!(num_reordered(&recorded, &pick(&[4, 3, 2, 1, 0])), 4);
+        assert_eq!(num_reordered(&recorded, &pick(&[2, 3, 0, 1, 4])), 2);
+    }
+}
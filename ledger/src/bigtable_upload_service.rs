@@ -1,6 +1,7 @@
 use {
     crate::{bigtable_upload, blockstore::Blockstore},
     solana_runtime::commitment::BlockCommitmentCache,
+    solana_storage_bigtable::LedgerStorageAdapter,
     std::{
         sync::atomic::{AtomicBool, Ordering},
         sync::{Arc, RwLock},
@@ -24,7 +25,7 @@ pub struct BigTableUploadService {
 impl BigTableUploadService {
     pub fn new(
         runtime: Arc<Runtime>,
-        bigtable_ledger_storage: solana_storage_bigtable::LedgerStorage,
+        ledger_storage: Arc<dyn LedgerStorageAdapter>,
         blockstore: Arc<Blockstore>,
         block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
         exit: Arc<AtomicBool>,
@@ -35,7 +36,7 @@ impl BigTableUploadService {
             .spawn(move || {
                 Self::run(
                     runtime,
-                    bigtable_ledger_storage,
+                    ledger_storage,
                     blockstore,
                     block_commitment_cache,
                     exit,
@@ -48,7 +49,7 @@ impl BigTableUploadService {
 
     fn run(
         runtime: Arc<Runtime>,
-        bigtable_ledger_storage: solana_storage_bigtable::LedgerStorage,
+        ledger_storage: Arc<dyn LedgerStorageAdapter>,
         blockstore: Arc<Blockstore>,
         block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
         exit: Arc<AtomicBool>,
@@ -72,7 +73,7 @@ impl BigTableUploadService {
 
             let result = runtime.block_on(bigtable_upload::upload_confirmed_blocks(
                 blockstore.clone(),
-                bigtable_ledger_storage.clone(),
+                ledger_storage.clone(),
                 start_slot,
                 Some(end_slot),
                 true,
@@ -4,8 +4,9 @@
 use crate::{
     ancestor_iterator::AncestorIterator,
     blockstore_db::{
-        columns as cf, AccessType, BlockstoreRecoveryMode, Column, Database, IteratorDirection,
-        IteratorMode, LedgerColumn, Result, WriteBatch,
+        columns as cf, AccessType, BlockstoreOptions, BlockstoreRecoveryMode, Column, ColumnName,
+        Database, IteratorDirection, IteratorMode, LedgerBackendType, LedgerColumn, Result,
+        ShredStorageType, WriteBatch,
     },
     blockstore_meta::*,
     erasure::ErasureConfig,
@@ -20,7 +21,6 @@ use rayon::{
     iter::{IntoParallelRefIterator, ParallelIterator},
     ThreadPool,
 };
-use rocksdb::DBRawIterator;
 use solana_entry::entry::{create_ticks, Entry};
 use solana_measure::measure::Measure;
 use solana_metrics::{datapoint_debug, datapoint_error};
@@ -30,6 +30,7 @@ use solana_sdk::{
     clock::{Slot, UnixTimestamp, DEFAULT_TICKS_PER_SECOND, MS_PER_TICK},
     genesis_config::{GenesisConfig, DEFAULT_GENESIS_ARCHIVE, DEFAULT_GENESIS_FILE},
     hash::Hash,
+    message::Message,
     pubkey::Pubkey,
     sanitize::Sanitize,
     signature::{Keypair, Signature, Signer},
@@ -38,8 +39,9 @@ use solana_sdk::{
 };
 use solana_storage_proto::{StoredExtendedRewards, StoredTransactionStatusMeta};
 use solana_transaction_status::{
-    ConfirmedBlock, ConfirmedTransaction, ConfirmedTransactionStatusWithSignature, Rewards,
-    TransactionStatusMeta, TransactionWithStatusMeta,
+    invoked_program_ids, AddressSignatureFilter, AddressSignatures, ConfirmedBlock,
+    ConfirmedTransaction, ConfirmedTransactionStatusWithSignature, Rewards, TransactionStatusMeta,
+    TransactionWithStatusMeta, MAX_ADDRESS_SIGNATURES_SCANNED,
 };
 use std::{
     borrow::Cow,
@@ -63,9 +65,11 @@ use tempfile::TempDir;
 use thiserror::Error;
 use trees::{Tree, TreeWalk};
 
+pub mod blockstore_archive;
 pub mod blockstore_purge;
 
 pub const BLOCKSTORE_DIRECTORY: &str = "rocksdb";
+pub const BLOCKSTORE_DIRECTORY_ROCKS_FIFO: &str = "rocksdb_fifo";
 
 thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::ThreadPoolBuilder::new()
                     .num_threads(get_thread_count())
@@ -146,6 +150,9 @@ pub struct Blockstore {
     perf_samples_cf: LedgerColumn<cf::PerfSamples>,
     block_height_cf: LedgerColumn<cf::BlockHeight>,
     program_costs_cf: LedgerColumn<cf::ProgramCosts>,
+    archived_transaction_cf: LedgerColumn<cf::ArchivedTransaction>,
+    archived_address_signatures_cf: LedgerColumn<cf::ArchivedAddressSignatures>,
+    archived_blocktime_cf: LedgerColumn<cf::ArchivedBlocktime>,
     bank_hash_cf: LedgerColumn<cf::BankHash>,
     last_root: Arc<RwLock<Slot>>,
     insert_shreds_lock: Arc<Mutex<()>>,
@@ -154,6 +161,7 @@ pub struct Blockstore {
     pub lowest_cleanup_slot: Arc<RwLock<Slot>>,
     no_compaction: bool,
     slots_stats: Arc<Mutex<SlotsStats>>,
+    shred_storage_type: ShredStorageType,
 }
 
 struct SlotsStats {
@@ -279,6 +287,33 @@ impl BlockstoreInsertionMetrics {
     }
 }
 
+// A filtered walk over the AddressSignatures entries of an address, which are listed from the
+// newest slot to the oldest one. It ends after MAX_ADDRESS_SIGNATURES_SCANNED entries, or at the
+// first slot older than the filter's min_block_time, as no older one can match
+struct AddressSignatureScan<'a> {
+    filter: &'a AddressSignatureFilter,
+    num_scanned: usize,
+    done: bool,
+    resume_before: Option<Signature>,
+    // Block time of the slot last scanned
+    block_time: Option<(Slot, Option<UnixTimestamp>)>,
+    // Transaction messages of the slot last scanned, by signature
+    slot_messages: Option<(Slot, HashMap<Signature, Message>)>,
+}
+
+impl<'a> AddressSignatureScan<'a> {
+    fn new(filter: &'a AddressSignatureFilter) -> Self {
+        Self {
+            filter,
+            num_scanned: 0,
+            done: false,
+            resume_before: None,
+            block_time: None,
+            slot_messages: None,
+        }
+    }
+}
+
 impl Blockstore {
     pub fn db(self) -> Arc<Database> {
         self.db
@@ -290,38 +325,72 @@ impl Blockstore {
 
     /// Opens a Ledger in directory, provides "infinite" window of shreds
     pub fn open(ledger_path: &Path) -> Result<Blockstore> {
-        Self::do_open(ledger_path, AccessType::PrimaryOnly, None, true)
+        Self::do_open(ledger_path, BlockstoreOptions::default())
     }
 
-    pub fn open_with_access_type(
+    /// Opens a Ledger on the given storage backend. Nothing written to an
+    /// `InMemory` backend outlives the returned blockstore.
+    pub fn open_with_backend_type(
         ledger_path: &Path,
-        access_type: AccessType,
-        recovery_mode: Option<BlockstoreRecoveryMode>,
-        enforce_ulimit_nofile: bool,
+        backend_type: LedgerBackendType,
     ) -> Result<Blockstore> {
         Self::do_open(
             ledger_path,
-            access_type,
-            recovery_mode,
-            enforce_ulimit_nofile,
+            BlockstoreOptions {
+                backend_type,
+                ..BlockstoreOptions::default()
+            },
         )
     }
 
-    fn do_open(
+    pub fn open_with_access_type(
         ledger_path: &Path,
         access_type: AccessType,
         recovery_mode: Option<BlockstoreRecoveryMode>,
         enforce_ulimit_nofile: bool,
     ) -> Result<Blockstore> {
-        fs::create_dir_all(&ledger_path)?;
-        let blockstore_path = ledger_path.join(BLOCKSTORE_DIRECTORY);
+        Self::do_open(
+            ledger_path,
+            BlockstoreOptions {
+                access_type,
+                recovery_mode,
+                enforce_ulimit_nofile,
+                ..BlockstoreOptions::default()
+            },
+        )
+    }
+
+    pub fn open_with_options(ledger_path: &Path, options: BlockstoreOptions) -> Result<Blockstore> {
+        Self::do_open(ledger_path, options)
+    }
 
-        adjust_ulimit_nofile(enforce_ulimit_nofile)?;
+    fn do_open(ledger_path: &Path, options: BlockstoreOptions) -> Result<Blockstore> {
+        fs::create_dir_all(ledger_path)?;
+        let shred_storage_type = options.shred_storage_type;
+        let blockstore_path = ledger_path.join(shred_storage_type.blockstore_directory());
+
+        if options.backend_type == LedgerBackendType::RocksDb {
+            adjust_ulimit_nofile(options.enforce_ulimit_nofile)?;
+            let other_blockstore_path = ledger_path.join(match shred_storage_type {
+                ShredStorageType::RocksLevel => BLOCKSTORE_DIRECTORY_ROCKS_FIFO,
+                ShredStorageType::RocksFifo(_) => BLOCKSTORE_DIRECTORY,
+            });
+            if other_blockstore_path.exists() {
+                warn!(
+                    "Ignoring {:?}, which uses a different shred storage type; \
+                     migrate it with `solana-ledger-tool migrate-shred-storage`",
+                    other_blockstore_path
+                );
+            }
+        }
 
         // Open the database
         let mut measure = Measure::start("open");
-        info!("Opening database at {:?}", blockstore_path);
-        let db = Database::open(&blockstore_path, access_type, recovery_mode)?;
+        info!(
+            "Opening {:?} database at {:?}",
+            options.backend_type, blockstore_path
+        );
+        let db = Database::open_with_options(&blockstore_path, options)?;
 
         // Create the metadata column family
         let meta_cf = db.column();
@@ -347,6 +416,9 @@ impl Blockstore {
         let perf_samples_cf = db.column();
         let block_height_cf = db.column();
         let program_costs_cf = db.column();
+        let archived_transaction_cf = db.column();
+        let archived_address_signatures_cf = db.column();
+        let archived_blocktime_cf = db.column();
         let bank_hash_cf = db.column();
 
         let db = Arc::new(db);
@@ -397,6 +469,9 @@ impl Blockstore {
             perf_samples_cf,
             block_height_cf,
             program_costs_cf,
+            archived_transaction_cf,
+            archived_address_signatures_cf,
+            archived_blocktime_cf,
             bank_hash_cf,
             new_shreds_signals: vec![],
             completed_slots_senders: vec![],
@@ -405,6 +480,7 @@ impl Blockstore {
             lowest_cleanup_slot: Arc::new(RwLock::new(0)),
             no_compaction: false,
             slots_stats: Arc::new(Mutex::new(SlotsStats::default())),
+            shred_storage_type,
         };
         if initialize_transaction_status_index {
             blockstore.initialize_transaction_status_index()?;
@@ -414,15 +490,9 @@ impl Blockstore {
 
     pub fn open_with_signal(
         ledger_path: &Path,
-        recovery_mode: Option<BlockstoreRecoveryMode>,
-        enforce_ulimit_nofile: bool,
+        options: BlockstoreOptions,
     ) -> Result<BlockstoreSignals> {
-        let mut blockstore = Self::open_with_access_type(
-            ledger_path,
-            AccessType::PrimaryOnly,
-            recovery_mode,
-            enforce_ulimit_nofile,
-        )?;
+        let mut blockstore = Self::open_with_options(ledger_path, options)?;
         let (ledger_signal_sender, ledger_signal_receiver) = sync_channel(1);
         let (completed_slots_sender, completed_slots_receiver) =
             sync_channel(MAX_COMPLETED_SLOTS_IN_CHANNEL);
@@ -490,8 +560,14 @@ impl Blockstore {
     pub fn destroy(ledger_path: &Path) -> Result<()> {
         // Database::destroy() fails if the path doesn't exist
         fs::create_dir_all(ledger_path)?;
-        let blockstore_path = ledger_path.join(BLOCKSTORE_DIRECTORY);
-        Database::destroy(&blockstore_path)
+        for directory in &[BLOCKSTORE_DIRECTORY, BLOCKSTORE_DIRECTORY_ROCKS_FIFO] {
+            Database::destroy(&ledger_path.join(directory))?;
+        }
+        Ok(())
+    }
+
+    pub fn shred_storage_type(&self) -> ShredStorageType {
+        self.shred_storage_type
     }
 
     pub fn meta(&self, slot: Slot) -> Result<Option<SlotMeta>> {
@@ -540,6 +616,30 @@ impl Blockstore {
         }))
     }
 
+    /// Iterates over the erasure metas of the given slot, keyed by the fec
+    /// set index of each erasure set.
+    pub fn erasure_meta_iterator(
+        &self,
+        slot: Slot,
+    ) -> Result<impl Iterator<Item = (u64, ErasureMeta)> + '_> {
+        let erasure_meta_iter = self
+            .db
+            .iter::<cf::ErasureMeta>(IteratorMode::From((slot, 0), IteratorDirection::Forward))?;
+        Ok(erasure_meta_iter
+            .take_while(move |((erasure_slot, _), _)| *erasure_slot == slot)
+            .map(|((slot, set_index), erasure_meta_bytes)| {
+                (
+                    set_index,
+                    deserialize(&erasure_meta_bytes).unwrap_or_else(|e| {
+                        panic!(
+                            "Could not deserialize ErasureMeta for slot {}, set index {}: {:?}",
+                            slot, set_index, e
+                        )
+                    }),
+                )
+            }))
+    }
+
     #[allow(dead_code)]
     pub fn live_slots_iterator(&self, root: Slot) -> impl Iterator<Item = (Slot, SlotMeta)> + '_ {
         let root_forks = NextSlotsIterator::new(root, self);
@@ -615,37 +715,36 @@ impl Blockstore {
         prev_inserted_codes: &mut HashMap<(u64, u64), Shred>,
         code_cf: &LedgerColumn<cf::ShredCode>,
     ) {
-        (erasure_meta.set_index..erasure_meta.set_index + erasure_meta.config.num_coding() as u64)
-            .for_each(|i| {
-                if let Some(shred) = prev_inserted_codes
-                    .remove(&(slot, i))
-                    .map(|s| {
-                        // Remove from the index so it doesn't get committed. We know
-                        // this is safe to do because everything in
-                        // `prev_inserted_codes` does not yet exist in blockstore
-                        // (guaranteed by `check_cache_coding_shred`)
-                        index.coding_mut().set_present(i, false);
-                        s
-                    })
-                    .or_else(|| {
-                        if index.coding().is_present(i) {
-                            let some_code = code_cf
-                                .get_bytes((slot, i))
-                                .expect("Database failure, could not fetch code shred");
-                            if let Some(code) = some_code {
-                                Shred::new_from_serialized_shred(code).ok()
-                            } else {
-                                warn!("Code shred deleted while reading for recovery");
-                                None
-                            }
+        erasure_meta.coding_shreds_indices().for_each(|i| {
+            if let Some(shred) = prev_inserted_codes
+                .remove(&(slot, i))
+                .map(|s| {
+                    // Remove from the index so it doesn't get committed. We know
+                    // this is safe to do because everything in
+                    // `prev_inserted_codes` does not yet exist in blockstore
+                    // (guaranteed by `check_cache_coding_shred`)
+                    index.coding_mut().set_present(i, false);
+                    s
+                })
+                .or_else(|| {
+                    if index.coding().is_present(i) {
+                        let some_code = code_cf
+                            .get_bytes((slot, i))
+                            .expect("Database failure, could not fetch code shred");
+                        if let Some(code) = some_code {
+                            Shred::new_from_serialized_shred(code).ok()
                         } else {
+                            warn!("Code shred deleted while reading for recovery");
                             None
                         }
-                    })
-                {
-                    available_shreds.push(shred);
-                }
-            });
+                    } else {
+                        None
+                    }
+                })
+            {
+                available_shreds.push(shred);
+            }
+        });
     }
 
     fn recover_shreds(
@@ -686,6 +785,7 @@ impl Blockstore {
             erasure_meta.config.num_data(),
             erasure_meta.config.num_coding(),
             set_index as usize,
+            erasure_meta.first_coding_index() as usize,
             slot,
         ) {
             Self::submit_metrics(
@@ -757,18 +857,16 @@ impl Blockstore {
                     );
                 }
                 ErasureMetaStatus::DataFull => {
-                    (set_index..set_index + erasure_meta.config.num_coding() as u64).for_each(
-                        |i| {
-                            // Remove saved coding shreds. We don't need these for future recovery.
-                            if prev_inserted_codes.remove(&(slot, i)).is_some() {
-                                // Remove from the index so it doesn't get committed. We know
-                                // this is safe to do because everything in
-                                // `prev_inserted_codes` does not yet exist in blockstore
-                                // (guaranteed by `check_cache_coding_shred`)
-                                index.coding_mut().set_present(i, false);
-                            }
-                        },
-                    );
+                    erasure_meta.coding_shreds_indices().for_each(|i| {
+                        // Remove saved coding shreds. We don't need these for future recovery.
+                        if prev_inserted_codes.remove(&(slot, i)).is_some() {
+                            // Remove from the index so it doesn't get committed. We know
+                            // this is safe to do because everything in
+                            // `prev_inserted_codes` does not yet exist in blockstore
+                            // (guaranteed by `check_cache_coding_shred`)
+                            index.coding_mut().set_present(i, false);
+                        }
+                    });
                     Self::submit_metrics(
                         slot,
                         set_index,
@@ -1079,6 +1177,7 @@ impl Blockstore {
     fn erasure_mismatch(shred1: &Shred, shred2: &Shred) -> bool {
         shred1.coding_header.num_coding_shreds != shred2.coding_header.num_coding_shreds
             || shred1.coding_header.num_data_shreds != shred2.coding_header.num_data_shreds
+            || shred1.first_coding_index() != shred2.first_coding_index()
     }
 
     fn check_cache_coding_shred<F>(
@@ -1122,15 +1221,19 @@ impl Blockstore {
             shred.coding_header.num_data_shreds as usize,
             shred.coding_header.num_coding_shreds as usize,
         );
+        // Checked in should_insert_coding_shred for untrusted shreds.
+        let first_coding_index = u64::from(shred.first_coding_index().unwrap_or_default());
 
         let erasure_meta = erasure_metas.entry((slot, set_index)).or_insert_with(|| {
             self.erasure_meta_cf
                 .get((slot, set_index))
                 .expect("Expect database get to succeed")
-                .unwrap_or_else(|| ErasureMeta::new(set_index, erasure_config))
+                .unwrap_or_else(|| ErasureMeta::new(set_index, first_coding_index, erasure_config))
         });
 
-        if erasure_config != erasure_meta.config {
+        if erasure_config != erasure_meta.config
+            || first_coding_index != erasure_meta.first_coding_index()
+        {
             let conflicting_shred = self.find_conflicting_coding_shred(
                 &shred,
                 slot,
@@ -1151,8 +1254,8 @@ impl Blockstore {
             // ToDo: This is a potential slashing condition
             warn!("Received multiple erasure configs for the same erasure set!!!");
             warn!(
-                "Slot: {}, shred index: {}, set_index: {}, is_duplicate: {}, stored config: {:#?}, new config: {:#?}",
-                slot, shred.index(), set_index, self.has_duplicate_shreds_in_slot(slot), erasure_meta.config, erasure_config
+                "Slot: {}, shred index: {}, set_index: {}, is_duplicate: {}, stored config: {:#?}, new config: {:#?}, stored first coding index: {}, new first coding index: {}",
+                slot, shred.index(), set_index, self.has_duplicate_shreds_in_slot(slot), erasure_meta.config, erasure_config, erasure_meta.first_coding_index(), first_coding_index
             );
 
             return false;
@@ -1187,10 +1290,8 @@ impl Blockstore {
     ) -> Option<Vec<u8>> {
         // Search for the shred which set the initial erasure config, either inserted,
         // or in the current batch in just_received_coding_shreds.
-        let coding_indices = erasure_meta.set_index
-            ..erasure_meta.set_index + erasure_meta.config.num_coding() as u64;
         let mut conflicting_shred = None;
-        for coding_index in coding_indices {
+        for coding_index in erasure_meta.coding_shreds_indices() {
             let maybe_shred = self.get_coding_shred(slot, coding_index);
             if let Ok(Some(shred_data)) = maybe_shred {
                 let potential_shred = Shred::new_from_serialized_shred(shred_data).unwrap();
@@ -1298,15 +1399,14 @@ impl Blockstore {
     }
 
     fn should_insert_coding_shred(shred: &Shred, last_root: &RwLock<u64>) -> bool {
-        let shred_index = shred.index();
-        let fec_set_index = shred.common_header.fec_set_index;
+        let position = u32::from(shred.coding_header.position);
         let num_coding_shreds = shred.coding_header.num_coding_shreds as u32;
         shred.is_code()
-            && shred_index >= fec_set_index
-            && shred_index - fec_set_index < num_coding_shreds
+            && shred.first_coding_index().is_some()
+            && position < num_coding_shreds
             && num_coding_shreds != 0
             && num_coding_shreds <= 8 * MAX_DATA_SHREDS_PER_FEC_BLOCK
-            && num_coding_shreds - 1 <= u32::MAX - fec_set_index
+            && num_coding_shreds - 1 - position <= u32::MAX - shred.index()
             && shred.slot() > *last_root.read().unwrap()
     }
 
@@ -1321,7 +1421,7 @@ impl Blockstore {
 
         // Assert guaranteed by integrity checks on the shred that happen before
         // `insert_coding_shred` is called
-        assert!(shred.is_code() && shred_index >= shred.common_header.fec_set_index as u64);
+        assert!(shred.is_code() && shred.first_coding_index().is_some());
 
         // Commit step: commit all changes to the mutable structures at once, or none at all.
         // We don't want only a subset of these changes going through.
@@ -1525,12 +1625,15 @@ impl Blockstore {
 
         // Commit step: commit all changes to the mutable structures at once, or none at all.
         // We don't want only a subset of these changes going through.
-        write_batch.put_bytes::<cf::ShredData>(
-            (slot, index),
-            // Payload will be padded out to SHRED_PAYLOAD_SIZE
-            // But only need to store the bytes within data_header.size
-            &shred.payload[..shred.data_header.size as usize],
-        )?;
+        // Payload will be padded out to SHRED_PAYLOAD_SIZE
+        // But only need to store the bytes within data_header.size,
+        // except for Merkle shreds which also carry their proof
+        let payload = if shred.is_merkle() {
+            &shred.payload[..]
+        } else {
+            &shred.payload[..shred.data_header.size as usize]
+        };
+        write_batch.put_bytes::<cf::ShredData>((slot, index), payload)?;
         data_index.set_present(index, true);
         let newly_completed_data_sets = update_slot_meta(
             last_in_slot,
@@ -1699,8 +1802,13 @@ impl Blockstore {
                         0
                     }
                 };
-                let (mut data_shreds, mut coding_shreds, _) =
-                    shredder.entries_to_shreds(keypair, &current_entries, true, start_index);
+                let (mut data_shreds, mut coding_shreds, _) = shredder.entries_to_shreds(
+                    keypair,
+                    &current_entries,
+                    true,
+                    start_index,
+                    Some(start_index),
+                );
                 all_shreds.append(&mut data_shreds);
                 all_shreds.append(&mut coding_shreds);
                 shredder = Shredder::new(
@@ -1720,7 +1828,7 @@ impl Blockstore {
 
         if !slot_entries.is_empty() {
             let (mut data_shreds, mut coding_shreds, _) =
-                shredder.entries_to_shreds(keypair, &slot_entries, is_full_slot, 0);
+                shredder.entries_to_shreds(keypair, &slot_entries, is_full_slot, 0, Some(0));
             all_shreds.append(&mut data_shreds);
             all_shreds.append(&mut coding_shreds);
         }
@@ -1744,7 +1852,7 @@ impl Blockstore {
     // indexes in the ledger in the range [start_index, end_index)
     // for the slot with the specified slot
     fn find_missing_indexes<C>(
-        db_iterator: &mut DBRawIterator,
+        db: &Database,
         slot: Slot,
         first_timestamp: u64,
         start_index: u64,
@@ -1752,32 +1860,39 @@ impl Blockstore {
         max_missing: usize,
     ) -> Vec<u64>
     where
-        C: Column<Index = (u64, u64)>,
+        C: Column<Index = (u64, u64)> + ColumnName,
     {
         if start_index >= end_index || max_missing == 0 {
             return vec![];
         }
+        // Seek to the first shred with index >= start_index
+        let mut db_iterator = match db.iter::<C>(IteratorMode::From(
+            (slot, start_index),
+            IteratorDirection::Forward,
+        )) {
+            Ok(db_iterator) => db_iterator,
+            Err(_) => return vec![],
+        };
 
         let mut missing_indexes = vec![];
         let ticks_since_first_insert =
             DEFAULT_TICKS_PER_SECOND * (timestamp() - first_timestamp) / 1000;
 
-        // Seek to the first shred with index >= start_index
-        db_iterator.seek(&C::key((slot, start_index)));
-
         // The index of the first missing shred in the slot
         let mut prev_index = start_index;
         'outer: loop {
-            if !db_iterator.valid() {
-                for i in prev_index..end_index {
-                    missing_indexes.push(i);
-                    if missing_indexes.len() == max_missing {
-                        break;
+            let ((current_slot, index), value) = match db_iterator.next() {
+                Some(entry) => entry,
+                None => {
+                    for i in prev_index..end_index {
+                        missing_indexes.push(i);
+                        if missing_indexes.len() == max_missing {
+                            break;
+                        }
                     }
+                    break;
                 }
-                break;
-            }
-            let (current_slot, index) = C::index(db_iterator.key().expect("Expect a valid key"));
+            };
 
             let current_index = {
                 if current_slot > slot {
@@ -1789,9 +1904,7 @@ impl Blockstore {
 
             let upper_index = cmp::min(current_index, end_index);
             // the tick that will be used to figure out the timeout for this hole
-            let reference_tick = u64::from(Shred::reference_tick_from_data(
-                db_iterator.value().expect("couldn't read value"),
-            ));
+            let reference_tick = u64::from(Shred::reference_tick_from_data(&value));
 
             if ticks_since_first_insert < reference_tick + MAX_TURBINE_DELAY_IN_TICKS {
                 // The higher index holes have not timed out yet
@@ -1813,7 +1926,6 @@ impl Blockstore {
             }
 
             prev_index = current_index + 1;
-            db_iterator.next();
         }
 
         missing_indexes
@@ -1827,21 +1939,14 @@ impl Blockstore {
         end_index: u64,
         max_missing: usize,
     ) -> Vec<u64> {
-        if let Ok(mut db_iterator) = self
-            .db
-            .raw_iterator_cf(self.db.cf_handle::<cf::ShredData>())
-        {
-            Self::find_missing_indexes::<cf::ShredData>(
-                &mut db_iterator,
-                slot,
-                first_timestamp,
-                start_index,
-                end_index,
-                max_missing,
-            )
-        } else {
-            vec![]
-        }
+        Self::find_missing_indexes::<cf::ShredData>(
+            &self.db,
+            slot,
+            first_timestamp,
+            start_index,
+            end_index,
+            max_missing,
+        )
     }
 
     pub fn get_block_time(&self, slot: Slot) -> Result<Option<UnixTimestamp>> {
@@ -2099,8 +2204,13 @@ impl Blockstore {
         signature: Signature,
         writable_keys: Vec<&Pubkey>,
         readonly_keys: Vec<&Pubkey>,
+        program_ids: Vec<&Pubkey>,
         status: TransactionStatusMeta,
     ) -> Result<()> {
+        let attributes = AddressSignatureAttributes {
+            failed: status.status.is_err(),
+            program_ids: program_ids.into_iter().cloned().collect(),
+        };
         let status = status.into();
         // This write lock prevents interleaving issues with the transaction_status_index_cf by gating
         // writes to that column
@@ -2113,13 +2223,19 @@ impl Blockstore {
         for address in writable_keys {
             self.address_signatures_cf.put(
                 (primary_index, *address, slot, signature),
-                &AddressSignatureMeta { writeable: true },
+                &AddressSignatureMeta {
+                    writeable: true,
+                    attributes: Some(attributes.clone()),
+                },
             )?;
         }
         for address in readonly_keys {
             self.address_signatures_cf.put(
                 (primary_index, *address, slot, signature),
-                &AddressSignatureMeta { writeable: false },
+                &AddressSignatureMeta {
+                    writeable: false,
+                    attributes: Some(attributes.clone()),
+                },
             )?;
         }
         Ok(())
@@ -2299,6 +2415,125 @@ impl Blockstore {
             .find(|transaction| transaction.signatures[0] == signature))
     }
 
+    // Checks the next AddressSignatures entry of `scan` against its filter. `meta` is the raw
+    // column value when the caller already has it; otherwise it is looked up
+    fn scan_address_signature(
+        &self,
+        scan: &mut AddressSignatureScan,
+        address: Pubkey,
+        slot: Slot,
+        signature: Signature,
+        meta: Option<&[u8]>,
+    ) -> Result<bool> {
+        let filter = scan.filter;
+        if filter.is_empty() {
+            return Ok(true);
+        }
+        if scan.done {
+            return Ok(false);
+        }
+        scan.num_scanned += 1;
+        if scan.num_scanned >= MAX_ADDRESS_SIGNATURES_SCANNED {
+            scan.done = true;
+            scan.resume_before = Some(signature);
+        }
+
+        if filter.filters_block_time() {
+            let block_time = match scan.block_time {
+                Some((block_time_slot, block_time)) if block_time_slot == slot => block_time,
+                _ => {
+                    let block_time = self.blocktime_cf.get(slot)?;
+                    scan.block_time = Some((slot, block_time));
+                    block_time
+                }
+            };
+            if filter.precedes_min_block_time(block_time) {
+                scan.done = true;
+                scan.resume_before = None;
+                return Ok(false);
+            }
+            if !filter.matches_block_time(block_time) {
+                return Ok(false);
+            }
+        }
+
+        let attributes = match meta {
+            Some(meta) => deserialize::<AddressSignatureMeta>(meta)?.attributes,
+            None => {
+                let mut attributes = None;
+                for primary_index in 0..=1 {
+                    if let Some(meta) =
+                        self.address_signatures_cf
+                            .get((primary_index, address, slot, signature))?
+                    {
+                        attributes = meta.attributes;
+                        break;
+                    }
+                }
+                attributes
+            }
+        };
+        let attributes = match attributes {
+            Some(attributes) => attributes,
+            None => self.legacy_address_signature_attributes(scan, slot, signature)?,
+        };
+        Ok(filter.matches_status(attributes.failed)
+            && filter.matches_program_ids(&attributes.program_ids))
+    }
+
+    // Derives the filter attributes of an AddressSignatures entry written before they were
+    // recorded, from its transaction status and, for program id filters, from the transactions
+    // of its slot, which are deserialized once per slot
+    fn legacy_address_signature_attributes(
+        &self,
+        scan: &mut AddressSignatureScan,
+        slot: Slot,
+        signature: Signature,
+    ) -> Result<AddressSignatureAttributes> {
+        let status = self.read_transaction_status((signature, slot))?;
+        let failed = status
+            .as_ref()
+            .map(|status| status.status.is_err())
+            .unwrap_or(false);
+        if !scan.filter.filters_program_ids() {
+            return Ok(AddressSignatureAttributes {
+                failed,
+                program_ids: vec![],
+            });
+        }
+
+        if !matches!(&scan.slot_messages, Some((messages_slot, _)) if *messages_slot == slot) {
+            let messages = self
+                .get_slot_entries(slot, 0)?
+                .into_iter()
+                .flat_map(|entry| entry.transactions)
+                .filter_map(|transaction| {
+                    let signature = *transaction.signatures.first()?;
+                    Some((signature, transaction.message))
+                })
+                .collect();
+            scan.slot_messages = Some((slot, messages));
+        }
+        let program_ids = scan
+            .slot_messages
+            .as_ref()
+            .and_then(|(_, messages)| messages.get(&signature))
+            .map(|message| {
+                let inner_instructions = status
+                    .as_ref()
+                    .and_then(|status| status.inner_instructions.as_deref());
+                invoked_program_ids(message, inner_instructions)
+                    .into_iter()
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(AddressSignatureAttributes {
+            failed,
+            program_ids,
+        })
+    }
+
     // Returns all rooted signatures for an address, ordered by slot that the transaction was
     // processed in. Within each slot the transactions will be ordered by signature, and NOT by
     // the order in which the transactions exist in the block
@@ -2400,6 +2635,29 @@ impl Blockstore {
         until: Option<Signature>,
         limit: usize,
     ) -> Result<Vec<ConfirmedTransactionStatusWithSignature>> {
+        self.get_filtered_signatures_for_address(
+            address,
+            highest_slot,
+            before,
+            until,
+            &AddressSignatureFilter::default(),
+            limit,
+        )
+        .map(|address_signatures| address_signatures.signatures)
+    }
+
+    // Like `get_confirmed_signatures_for_address2()`, but only returns the signatures of
+    // transactions that pass `filter`. A filtered query examines at most
+    // MAX_ADDRESS_SIGNATURES_SCANNED entries, and returns where to resume from if it stops there
+    pub fn get_filtered_signatures_for_address(
+        &self,
+        address: Pubkey,
+        highest_slot: Slot, // highest_confirmed_root or highest_confirmed_slot
+        before: Option<Signature>,
+        until: Option<Signature>,
+        filter: &AddressSignatureFilter,
+        limit: usize,
+    ) -> Result<AddressSignatures<ConfirmedTransactionStatusWithSignature>> {
         datapoint_info!(
             "blockstore-rpc-api",
             (
@@ -2423,7 +2681,7 @@ impl Blockstore {
                 let transaction_status =
                     self.get_transaction_status(before, &confirmed_unrooted_slots)?;
                 match transaction_status {
-                    None => return Ok(vec![]),
+                    None => return Ok(AddressSignatures::default()),
                     Some((slot, _)) => {
                         let block = self.get_complete_block(slot, false).map_err(|err| {
                             BlockstoreError::Io(IoError::new(
@@ -2516,19 +2774,22 @@ impl Blockstore {
         // Fetch the list of signatures that affect the given address
         let first_available_block = self.get_first_available_block()?;
         let mut address_signatures = vec![];
+        let mut scan = AddressSignatureScan::new(filter);
 
         // Get signatures in `slot`
         let mut get_initial_slot_timer = Measure::start("get_initial_slot_timer");
         let mut signatures = self.find_address_signatures_for_slot(address, slot)?;
         signatures.reverse();
         if let Some(excluded_signatures) = before_excluded_signatures.take() {
-            address_signatures.extend(
-                signatures
-                    .into_iter()
-                    .filter(|(_, signature)| !excluded_signatures.contains(signature)),
-            )
-        } else {
-            address_signatures.append(&mut signatures);
+            signatures.retain(|(_, signature)| !excluded_signatures.contains(signature));
+        }
+        for (slot, signature) in signatures {
+            if scan.done {
+                break;
+            }
+            if self.scan_address_signature(&mut scan, address, slot, signature, None)? {
+                address_signatures.push((slot, signature));
+            }
         }
         get_initial_slot_timer.stop();
 
@@ -2550,8 +2811,8 @@ impl Blockstore {
             ))?;
 
             // Iterate through starting_iterator until limit is reached
-            while address_signatures.len() < limit {
-                if let Some(((i, key_address, slot, signature), _)) = starting_iterator.next() {
+            while address_signatures.len() < limit && !scan.done {
+                if let Some(((i, key_address, slot, signature), meta)) = starting_iterator.next() {
                     if slot == next_max_slot || slot < lowest_slot {
                         break;
                     }
@@ -2559,7 +2820,15 @@ impl Blockstore {
                         && key_address == address
                         && slot >= first_available_block
                     {
-                        if self.is_root(slot) || confirmed_unrooted_slots.contains(&slot) {
+                        if (self.is_root(slot) || confirmed_unrooted_slots.contains(&slot))
+                            && self.scan_address_signature(
+                                &mut scan,
+                                address,
+                                slot,
+                                signature,
+                                Some(&meta[..]),
+                            )?
+                        {
                             address_signatures.push((slot, signature));
                         }
                         continue;
@@ -2569,11 +2838,18 @@ impl Blockstore {
             }
 
             // Handle slots that cross primary indexes
-            if next_max_slot >= lowest_slot {
+            if next_max_slot >= lowest_slot && !scan.done {
                 let mut signatures =
                     self.find_address_signatures_for_slot(address, next_max_slot)?;
                 signatures.reverse();
-                address_signatures.append(&mut signatures);
+                for (slot, signature) in signatures {
+                    if scan.done {
+                        break;
+                    }
+                    if self.scan_address_signature(&mut scan, address, slot, signature, None)? {
+                        address_signatures.push((slot, signature));
+                    }
+                }
             }
         }
         starting_primary_index_iter_timer.stop();
@@ -2584,8 +2860,8 @@ impl Blockstore {
             (next_primary_index, address, slot, Signature::default()),
             IteratorDirection::Reverse,
         ))?;
-        while address_signatures.len() < limit {
-            if let Some(((i, key_address, slot, signature), _)) = next_iterator.next() {
+        while address_signatures.len() < limit && !scan.done {
+            if let Some(((i, key_address, slot, signature), meta)) = next_iterator.next() {
                 // Skip next_max_slot, which is already included
                 if slot == next_max_slot {
                     continue;
@@ -2597,7 +2873,15 @@ impl Blockstore {
                     && key_address == address
                     && slot >= first_available_block
                 {
-                    if self.is_root(slot) || confirmed_unrooted_slots.contains(&slot) {
+                    if (self.is_root(slot) || confirmed_unrooted_slots.contains(&slot))
+                        && self.scan_address_signature(
+                            &mut scan,
+                            address,
+                            slot,
+                            signature,
+                            Some(&meta[..]),
+                        )?
+                    {
                         address_signatures.push((slot, signature));
                     }
                     continue;
@@ -2611,6 +2895,11 @@ impl Blockstore {
             .filter(|(_, signature)| !until_excluded_signatures.contains(signature))
             .collect();
         address_signatures.truncate(limit);
+        let resume_before = if address_signatures.len() < limit {
+            scan.resume_before
+        } else {
+            None
+        };
 
         // Fill in the status information for each found transaction
         let mut get_status_info_timer = Measure::start("get_status_info_timer");
@@ -2664,7 +2953,10 @@ impl Blockstore {
             )
         );
 
-        Ok(infos)
+        Ok(AddressSignatures {
+            signatures: infos,
+            resume_before,
+        })
     }
 
     pub fn read_rewards(&self, index: Slot) -> Result<Option<Rewards>> {
@@ -3690,7 +3982,7 @@ pub fn create_new_ledger(
 
     let shredder = Shredder::new(0, 0, 0, version).unwrap();
     let shreds = shredder
-        .entries_to_shreds(&Keypair::new(), &entries, true, 0)
+        .entries_to_shreds(&Keypair::new(), &entries, true, 0, Some(0))
         .0;
     assert!(shreds.last().unwrap().last_in_slot());
 
@@ -3887,7 +4179,7 @@ pub fn entries_to_test_shreds(
 ) -> Vec<Shred> {
     Shredder::new(slot, parent_slot, 0, version)
         .unwrap()
-        .entries_to_shreds(&Keypair::new(), &entries, is_full_slot, 0)
+        .entries_to_shreds(&Keypair::new(), &entries, is_full_slot, 0, Some(0))
         .0
 }
 
@@ -4016,7 +4308,9 @@ pub mod tests {
         transaction::TransactionError,
     };
     use solana_storage_proto::convert::generated;
-    use solana_transaction_status::{InnerInstructions, Reward, Rewards, TransactionTokenBalance};
+    use solana_transaction_status::{
+        InnerInstructions, Reward, Rewards, TransactionStatusFilter, TransactionTokenBalance,
+    };
     use std::{sync::mpsc::channel, thread::Builder, time::Duration};
 
     // used for tests only
@@ -4054,8 +4348,7 @@ pub mod tests {
         Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
     }
 
-    #[test]
-    fn test_insert_get_bytes() {
+    fn run_test_insert_get_bytes(backend_type: LedgerBackendType) {
         // Create enough entries to ensure there are at least two shreds created
         let num_entries = max_ticks_per_n_shreds(1, None) + 1;
         assert!(num_entries > 1);
@@ -4063,7 +4356,7 @@ pub mod tests {
         let (mut shreds, _) = make_slot_entries(0, 0, num_entries);
 
         let ledger_path = get_tmp_ledger_path!();
-        let ledger = Blockstore::open(&ledger_path).unwrap();
+        let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
 
         // Insert last shred, test we can retrieve it
         let last_shred = shreds.pop().unwrap();
@@ -4086,13 +4379,18 @@ pub mod tests {
     }
 
     #[test]
-    fn test_write_entries() {
+    fn test_insert_get_bytes() {
+        run_test_insert_get_bytes(LedgerBackendType::RocksDb);
+        run_test_insert_get_bytes(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_write_entries(backend_type: LedgerBackendType) {
         solana_logger::setup();
         let ledger_path = get_tmp_ledger_path!();
         {
             let ticks_per_slot = 10;
             let num_slots = 10;
-            let ledger = Blockstore::open(&ledger_path).unwrap();
+            let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
             let mut ticks = vec![];
             //let mut shreds_per_slot = 0 as u64;
             let mut shreds_per_slot = vec![];
@@ -4185,9 +4483,14 @@ pub mod tests {
     }
 
     #[test]
-    fn test_put_get_simple() {
+    fn test_write_entries() {
+        run_test_write_entries(LedgerBackendType::RocksDb);
+        run_test_write_entries(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_put_get_simple(backend_type: LedgerBackendType) {
         let ledger_path = get_tmp_ledger_path!();
-        let ledger = Blockstore::open(&ledger_path).unwrap();
+        let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
 
         // Test meta column family
         let meta = SlotMeta::new(0, 1);
@@ -4235,14 +4538,19 @@ pub mod tests {
     }
 
     #[test]
-    fn test_read_shred_bytes() {
+    fn test_put_get_simple() {
+        run_test_put_get_simple(LedgerBackendType::RocksDb);
+        run_test_put_get_simple(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_read_shred_bytes(backend_type: LedgerBackendType) {
         let slot = 0;
         let (shreds, _) = make_slot_entries(slot, 0, 100);
         let num_shreds = shreds.len() as u64;
         let shred_bufs: Vec<_> = shreds.iter().map(|shred| shred.payload.clone()).collect();
 
         let ledger_path = get_tmp_ledger_path!();
-        let ledger = Blockstore::open(&ledger_path).unwrap();
+        let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
         ledger.insert_shreds(shreds, None, false).unwrap();
 
         let mut buf = [0; 4096];
@@ -4295,12 +4603,17 @@ pub mod tests {
     }
 
     #[test]
-    fn test_shred_cleanup_check() {
+    fn test_read_shred_bytes() {
+        run_test_read_shred_bytes(LedgerBackendType::RocksDb);
+        run_test_read_shred_bytes(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_shred_cleanup_check(backend_type: LedgerBackendType) {
         let slot = 1;
         let (shreds, _) = make_slot_entries(slot, 0, 100);
 
         let ledger_path = get_tmp_ledger_path!();
-        let ledger = Blockstore::open(&ledger_path).unwrap();
+        let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
         ledger.insert_shreds(shreds, None, false).unwrap();
 
         let mut buf = [0; 4096];
@@ -4317,7 +4630,12 @@ pub mod tests {
     }
 
     #[test]
-    fn test_insert_data_shreds_basic() {
+    fn test_shred_cleanup_check() {
+        run_test_shred_cleanup_check(LedgerBackendType::RocksDb);
+        run_test_shred_cleanup_check(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_insert_data_shreds_basic(backend_type: LedgerBackendType) {
         // Create enough entries to ensure there are at least two shreds created
         let num_entries = max_ticks_per_n_shreds(1, None) + 1;
         assert!(num_entries > 1);
@@ -4326,7 +4644,7 @@ pub mod tests {
         let num_shreds = shreds.len() as u64;
 
         let ledger_path = get_tmp_ledger_path!();
-        let ledger = Blockstore::open(&ledger_path).unwrap();
+        let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
 
         // Insert last shred, we're missing the other shreds, so no consecutive
         // shreds starting from slot 0, index 0 should exist.
@@ -4364,14 +4682,19 @@ pub mod tests {
     }
 
     #[test]
-    fn test_insert_data_shreds_reverse() {
+    fn test_insert_data_shreds_basic() {
+        run_test_insert_data_shreds_basic(LedgerBackendType::RocksDb);
+        run_test_insert_data_shreds_basic(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_insert_data_shreds_reverse(backend_type: LedgerBackendType) {
         let num_shreds = 10;
         let num_entries = max_ticks_per_n_shreds(num_shreds, None);
         let (mut shreds, entries) = make_slot_entries(0, 0, num_entries);
         let num_shreds = shreds.len() as u64;
 
         let ledger_path = get_tmp_ledger_path!();
-        let ledger = Blockstore::open(&ledger_path).unwrap();
+        let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
 
         // Insert shreds in reverse, check for consecutive returned shreds
         for i in (0..num_shreds).rev() {
@@ -4399,10 +4722,22 @@ pub mod tests {
         Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_insert_data_shreds_reverse() {
+        run_test_insert_data_shreds_reverse(LedgerBackendType::RocksDb);
+        run_test_insert_data_shreds_reverse(LedgerBackendType::InMemory);
+    }
+
     #[test]
     fn test_insert_slots() {
-        test_insert_data_shreds_slots("test_insert_data_shreds_slots_single", false);
-        test_insert_data_shreds_slots("test_insert_data_shreds_slots_bulk", true);
+        for backend_type in [LedgerBackendType::RocksDb, LedgerBackendType::InMemory] {
+            test_insert_data_shreds_slots(
+                "test_insert_data_shreds_slots_single",
+                false,
+                backend_type,
+            );
+            test_insert_data_shreds_slots("test_insert_data_shreds_slots_bulk", true, backend_type);
+        }
     }
 
     /*
@@ -4446,11 +4781,11 @@ pub mod tests {
         }
     */
 
-    #[test]
-    pub fn test_get_slot_entries1() {
+    fn run_test_get_slot_entries1(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let entries = create_ticks(8, 0, Hash::default());
             let shreds = entries_to_test_shreds(entries[0..4].to_vec(), 1, 0, false, 0);
             blockstore
@@ -4473,14 +4808,20 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    pub fn test_get_slot_entries1() {
+        run_test_get_slot_entries1(LedgerBackendType::RocksDb);
+        run_test_get_slot_entries1(LedgerBackendType::InMemory);
+    }
+
     // This test seems to be unnecessary with introduction of data shreds. There are no
     // guarantees that a particular shred index contains a complete entry
-    #[test]
     #[ignore]
-    pub fn test_get_slot_entries2() {
+    fn run_test_get_slot_entries2(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             // Write entries
             let num_slots = 5_u64;
@@ -4510,11 +4851,17 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_get_slot_entries3() {
+    pub fn test_get_slot_entries2() {
+        run_test_get_slot_entries2(LedgerBackendType::RocksDb);
+        run_test_get_slot_entries2(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_get_slot_entries3(backend_type: LedgerBackendType) {
         // Test inserting/fetching shreds which contain multiple entries per shred
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let num_slots = 5_u64;
             let shreds_per_slot = 5_u64;
             let entry_serialized_size =
@@ -4538,10 +4885,16 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_insert_data_shreds_consecutive() {
+    pub fn test_get_slot_entries3() {
+        run_test_get_slot_entries3(LedgerBackendType::RocksDb);
+        run_test_get_slot_entries3(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_insert_data_shreds_consecutive(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             // Create enough entries to ensure there are at least two shreds created
             let min_entries = max_ticks_per_n_shreds(1, None) + 1;
             for i in 0..4 {
@@ -4601,10 +4954,21 @@ pub mod tests {
     }
 
     #[test]
-    fn test_data_set_completed_on_insert() {
+    pub fn test_insert_data_shreds_consecutive() {
+        run_test_insert_data_shreds_consecutive(LedgerBackendType::RocksDb);
+        run_test_insert_data_shreds_consecutive(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_data_set_completed_on_insert(backend_type: LedgerBackendType) {
         let ledger_path = get_tmp_ledger_path!();
-        let BlockstoreSignals { blockstore, .. } =
-            Blockstore::open_with_signal(&ledger_path, None, true).unwrap();
+        let BlockstoreSignals { blockstore, .. } = Blockstore::open_with_signal(
+            &ledger_path,
+            BlockstoreOptions {
+                backend_type,
+                ..BlockstoreOptions::default()
+            },
+        )
+        .unwrap();
 
         // Create enough entries to fill 2 shreds, only the later one is data complete
         let slot = 0;
@@ -4638,14 +5002,26 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_new_shreds_signal() {
+    fn test_data_set_completed_on_insert() {
+        run_test_data_set_completed_on_insert(LedgerBackendType::RocksDb);
+        run_test_data_set_completed_on_insert(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_new_shreds_signal(backend_type: LedgerBackendType) {
         // Initialize ledger
         let ledger_path = get_tmp_ledger_path!();
         let BlockstoreSignals {
             blockstore: ledger,
             ledger_signal_receiver: recvr,
             ..
-        } = Blockstore::open_with_signal(&ledger_path, None, true).unwrap();
+        } = Blockstore::open_with_signal(
+            &ledger_path,
+            BlockstoreOptions {
+                backend_type,
+                ..BlockstoreOptions::default()
+            },
+        )
+        .unwrap();
         let ledger = Arc::new(ledger);
 
         let entries_per_slot = 50;
@@ -4722,14 +5098,26 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_completed_shreds_signal() {
+    pub fn test_new_shreds_signal() {
+        run_test_new_shreds_signal(LedgerBackendType::RocksDb);
+        run_test_new_shreds_signal(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_completed_shreds_signal(backend_type: LedgerBackendType) {
         // Initialize ledger
         let ledger_path = get_tmp_ledger_path!();
         let BlockstoreSignals {
             blockstore: ledger,
             completed_slots_receiver: recvr,
             ..
-        } = Blockstore::open_with_signal(&ledger_path, None, true).unwrap();
+        } = Blockstore::open_with_signal(
+            &ledger_path,
+            BlockstoreOptions {
+                backend_type,
+                ..BlockstoreOptions::default()
+            },
+        )
+        .unwrap();
         let ledger = Arc::new(ledger);
 
         let entries_per_slot = 10;
@@ -4748,14 +5136,26 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_completed_shreds_signal_orphans() {
+    pub fn test_completed_shreds_signal() {
+        run_test_completed_shreds_signal(LedgerBackendType::RocksDb);
+        run_test_completed_shreds_signal(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_completed_shreds_signal_orphans(backend_type: LedgerBackendType) {
         // Initialize ledger
         let ledger_path = get_tmp_ledger_path!();
         let BlockstoreSignals {
             blockstore: ledger,
             completed_slots_receiver: recvr,
             ..
-        } = Blockstore::open_with_signal(&ledger_path, None, true).unwrap();
+        } = Blockstore::open_with_signal(
+            &ledger_path,
+            BlockstoreOptions {
+                backend_type,
+                ..BlockstoreOptions::default()
+            },
+        )
+        .unwrap();
         let ledger = Arc::new(ledger);
 
         let entries_per_slot = 10;
@@ -4792,14 +5192,26 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_completed_shreds_signal_many() {
+    pub fn test_completed_shreds_signal_orphans() {
+        run_test_completed_shreds_signal_orphans(LedgerBackendType::RocksDb);
+        run_test_completed_shreds_signal_orphans(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_completed_shreds_signal_many(backend_type: LedgerBackendType) {
         // Initialize ledger
         let ledger_path = get_tmp_ledger_path!();
         let BlockstoreSignals {
             blockstore: ledger,
             completed_slots_receiver: recvr,
             ..
-        } = Blockstore::open_with_signal(&ledger_path, None, true).unwrap();
+        } = Blockstore::open_with_signal(
+            &ledger_path,
+            BlockstoreOptions {
+                backend_type,
+                ..BlockstoreOptions::default()
+            },
+        )
+        .unwrap();
         let ledger = Arc::new(ledger);
 
         let entries_per_slot = 10;
@@ -4827,12 +5239,18 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_handle_chaining_basic() {
-        let blockstore_path = get_tmp_ledger_path!();
-        {
+    pub fn test_completed_shreds_signal_many() {
+        run_test_completed_shreds_signal_many(LedgerBackendType::RocksDb);
+        run_test_completed_shreds_signal_many(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_handle_chaining_basic(backend_type: LedgerBackendType) {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
             let entries_per_slot = 5;
             let num_slots = 3;
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             // Construct the shreds
             let (mut shreds, _) = make_many_slot_entries(0, num_slots, entries_per_slot);
@@ -4892,10 +5310,16 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_handle_chaining_missing_slots() {
+    pub fn test_handle_chaining_basic() {
+        run_test_handle_chaining_basic(LedgerBackendType::RocksDb);
+        run_test_handle_chaining_basic(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_handle_chaining_missing_slots(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let num_slots = 30;
             let entries_per_slot = 5;
 
@@ -4976,11 +5400,17 @@ pub mod tests {
     }
 
     #[test]
+    pub fn test_handle_chaining_missing_slots() {
+        run_test_handle_chaining_missing_slots(LedgerBackendType::RocksDb);
+        run_test_handle_chaining_missing_slots(LedgerBackendType::InMemory);
+    }
+
     #[allow(clippy::cognitive_complexity)]
-    pub fn test_forward_chaining_is_connected() {
+    fn run_test_forward_chaining_is_connected(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let num_slots = 15;
             // Create enough entries to ensure there are at least two shreds created
             let entries_per_slot = max_ticks_per_n_shreds(1, None) + 1;
@@ -5062,6 +5492,12 @@ pub mod tests {
         }
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
+
+    #[test]
+    pub fn test_forward_chaining_is_connected() {
+        run_test_forward_chaining_is_connected(LedgerBackendType::RocksDb);
+        run_test_forward_chaining_is_connected(LedgerBackendType::InMemory);
+    }
     /*
         #[test]
         pub fn test_chaining_tree() {
@@ -5164,12 +5600,12 @@ pub mod tests {
             Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
         }
     */
-    #[test]
-    pub fn test_get_slots_since() {
+    fn run_test_get_slots_since(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
 
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             // Slot doesn't exist
             assert!(blockstore.get_slots_since(&[0]).unwrap().is_empty());
@@ -5201,10 +5637,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_orphans() {
+    pub fn test_get_slots_since() {
+        run_test_get_slots_since(LedgerBackendType::RocksDb);
+        run_test_get_slots_since(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_orphans(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             // Create shreds and entries
             let entries_per_slot = 1;
@@ -5274,10 +5716,21 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
-    fn test_insert_data_shreds_slots(name: &str, should_bulk_write: bool) {
+    #[test]
+    fn test_orphans() {
+        run_test_orphans(LedgerBackendType::RocksDb);
+        run_test_orphans(LedgerBackendType::InMemory);
+    }
+
+    fn test_insert_data_shreds_slots(
+        name: &str,
+        should_bulk_write: bool,
+        backend_type: LedgerBackendType,
+    ) {
         let blockstore_path = get_ledger_path_from_name(name);
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             // Create shreds and entries
             let num_entries = 20_u64;
@@ -5335,11 +5788,11 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
-    #[test]
-    fn test_find_missing_data_indexes() {
+    fn run_test_find_missing_data_indexes(backend_type: LedgerBackendType) {
         let slot = 0;
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
         // Write entries
         let gap: u64 = 10;
@@ -5426,10 +5879,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_find_missing_data_indexes_timeout() {
+    fn test_find_missing_data_indexes() {
+        run_test_find_missing_data_indexes(LedgerBackendType::RocksDb);
+        run_test_find_missing_data_indexes(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_find_missing_data_indexes_timeout(backend_type: LedgerBackendType) {
         let slot = 0;
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
         // Write entries
         let gap: u64 = 10;
@@ -5466,11 +5925,17 @@ pub mod tests {
     }
 
     #[test]
-    fn test_find_missing_data_indexes_sanity() {
+    fn test_find_missing_data_indexes_timeout() {
+        run_test_find_missing_data_indexes_timeout(LedgerBackendType::RocksDb);
+        run_test_find_missing_data_indexes_timeout(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_find_missing_data_indexes_sanity(backend_type: LedgerBackendType) {
         let slot = 0;
 
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
         // Early exit conditions
         let empty: Vec<u64> = vec![];
@@ -5526,10 +5991,16 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_no_missing_shred_indexes() {
+    fn test_find_missing_data_indexes_sanity() {
+        run_test_find_missing_data_indexes_sanity(LedgerBackendType::RocksDb);
+        run_test_find_missing_data_indexes_sanity(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_no_missing_shred_indexes(backend_type: LedgerBackendType) {
         let slot = 0;
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
         // Write entries
         let num_entries = 10;
@@ -5554,12 +6025,18 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_should_insert_data_shred() {
+    pub fn test_no_missing_shred_indexes() {
+        run_test_no_missing_shred_indexes(LedgerBackendType::RocksDb);
+        run_test_no_missing_shred_indexes(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_should_insert_data_shred(backend_type: LedgerBackendType) {
         solana_logger::setup();
         let (mut shreds, _) = make_slot_entries(0, 0, 200);
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let last_root = RwLock::new(0);
 
             // Insert the first 5 shreds, we don't have a "is_last" shred yet
@@ -5662,11 +6139,17 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_is_data_shred_present() {
+    pub fn test_should_insert_data_shred() {
+        run_test_should_insert_data_shred(LedgerBackendType::RocksDb);
+        run_test_should_insert_data_shred(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_is_data_shred_present(backend_type: LedgerBackendType) {
         let (shreds, _) = make_slot_entries(0, 0, 200);
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let index_cf = blockstore.db.column::<cf::Index>();
 
             blockstore
@@ -5699,13 +6182,19 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_check_cache_coding_shred() {
+    pub fn test_is_data_shred_present() {
+        run_test_is_data_shred_present(LedgerBackendType::RocksDb);
+        run_test_is_data_shred_present(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_check_cache_coding_shred(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             let slot = 1;
-            let (shred, coding) = Shredder::new_coding_shred_header(slot, 11, 11, 11, 11, 0);
+            let (shred, coding) = Shredder::new_coding_shred_header(slot, 11, 11, 11, 11, 10, 0);
             let coding_shred =
                 Shred::new_empty_from_header(shred, DataShredHeader::default(), coding);
 
@@ -5746,14 +6235,21 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_should_insert_coding_shred() {
+    pub fn test_check_cache_coding_shred() {
+        run_test_check_cache_coding_shred(LedgerBackendType::RocksDb);
+        run_test_check_cache_coding_shred(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_should_insert_coding_shred(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let last_root = RwLock::new(0);
 
             let slot = 1;
-            let (mut shred, coding) = Shredder::new_coding_shred_header(slot, 11, 11, 11, 11, 0);
+            let (mut shred, coding) =
+                Shredder::new_coding_shred_header(slot, 11, 11, 11, 11, 10, 0);
             let coding_shred = Shred::new_empty_from_header(
                 shred.clone(),
                 DataShredHeader::default(),
@@ -5802,7 +6298,7 @@ pub mod tests {
                     DataShredHeader::default(),
                     coding.clone(),
                 );
-                let index = coding_shred.index() - coding_shred.common_header.fec_set_index - 1;
+                let index = coding_shred.coding_header.position - 1;
                 coding_shred.set_index(index as u32);
 
                 assert!(!Blockstore::should_insert_coding_shred(
@@ -5832,9 +6328,8 @@ pub mod tests {
                     DataShredHeader::default(),
                     coding.clone(),
                 );
-                let num_coding_shreds =
-                    coding_shred.common_header.index - coding_shred.common_header.fec_set_index;
-                coding_shred.coding_header.num_coding_shreds = num_coding_shreds as u16;
+                let num_coding_shreds = coding_shred.coding_header.position;
+                coding_shred.coding_header.num_coding_shreds = num_coding_shreds;
                 assert!(!Blockstore::should_insert_coding_shred(
                     &coding_shred,
                     &last_root
@@ -5851,6 +6346,7 @@ pub mod tests {
                 );
                 coding_shred.common_header.fec_set_index = std::u32::MAX - 1;
                 coding_shred.coding_header.num_coding_shreds = 3;
+                coding_shred.coding_header.position = 0;
                 coding_shred.common_header.index = std::u32::MAX - 1;
                 assert!(!Blockstore::should_insert_coding_shred(
                     &coding_shred,
@@ -5892,12 +6388,18 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_insert_multiple_is_last() {
+    pub fn test_should_insert_coding_shred() {
+        run_test_should_insert_coding_shred(LedgerBackendType::RocksDb);
+        run_test_should_insert_coding_shred(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_insert_multiple_is_last(backend_type: LedgerBackendType) {
         solana_logger::setup();
         let (shreds, _) = make_slot_entries(0, 0, 20);
         let num_shreds = shreds.len() as u64;
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
         blockstore.insert_shreds(shreds, None, false).unwrap();
         let slot_meta = blockstore.meta(0).unwrap().unwrap();
@@ -5923,10 +6425,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_slot_data_iterator() {
+    pub fn test_insert_multiple_is_last() {
+        run_test_insert_multiple_is_last(LedgerBackendType::RocksDb);
+        run_test_insert_multiple_is_last(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_slot_data_iterator(backend_type: LedgerBackendType) {
         // Construct the shreds
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
         let shreds_per_slot = 10;
         let slots = vec![2, 4, 8, 12];
         let all_shreds = make_chaining_slot_entries(&slots, shreds_per_slot);
@@ -5953,9 +6461,15 @@ pub mod tests {
     }
 
     #[test]
-    fn test_set_roots() {
+    fn test_slot_data_iterator() {
+        run_test_slot_data_iterator(LedgerBackendType::RocksDb);
+        run_test_slot_data_iterator(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_set_roots(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
         let chained_slots = vec![0, 2, 4, 7, 12, 15];
         assert_eq!(blockstore.last_root(), 0);
 
@@ -5972,9 +6486,15 @@ pub mod tests {
     }
 
     #[test]
-    fn test_is_skipped() {
+    fn test_set_roots() {
+        run_test_set_roots(LedgerBackendType::RocksDb);
+        run_test_set_roots(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_is_skipped(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
         let roots = vec![2, 4, 7, 12, 15];
         blockstore.set_roots(roots.iter()).unwrap();
 
@@ -5991,9 +6511,15 @@ pub mod tests {
     }
 
     #[test]
-    fn test_iter_bounds() {
+    fn test_is_skipped() {
+        run_test_is_skipped(LedgerBackendType::RocksDb);
+        run_test_is_skipped(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_iter_bounds(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
         // slot 5 does not exist, iter should be ok and should be a noop
         blockstore
@@ -6005,6 +6531,12 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_iter_bounds() {
+        run_test_iter_bounds(LedgerBackendType::RocksDb);
+        run_test_iter_bounds(LedgerBackendType::InMemory);
+    }
+
     #[test]
     fn test_get_completed_data_ranges() {
         let completed_data_end_indexes = vec![2, 4, 9, 11];
@@ -6065,11 +6597,11 @@ pub mod tests {
         }
     }
 
-    #[test]
-    fn test_get_slot_entries_with_shred_count_corruption() {
+    fn run_test_get_slot_entries_with_shred_count_corruption(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let num_ticks = 8;
             let entries = create_ticks(num_ticks, 0, Hash::default());
             let slot = 1;
@@ -6107,13 +6639,19 @@ pub mod tests {
     }
 
     #[test]
-    fn test_no_insert_but_modify_slot_meta() {
+    fn test_get_slot_entries_with_shred_count_corruption() {
+        run_test_get_slot_entries_with_shred_count_corruption(LedgerBackendType::RocksDb);
+        run_test_get_slot_entries_with_shred_count_corruption(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_no_insert_but_modify_slot_meta(backend_type: LedgerBackendType) {
         // This tests correctness of the SlotMeta in various cases in which a shred
         // that gets filtered out by checks
         let (shreds0, _) = make_slot_entries(0, 0, 200);
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             // Insert the first 5 shreds, we don't have a "is_last" shred yet
             blockstore
@@ -6138,13 +6676,19 @@ pub mod tests {
     }
 
     #[test]
-    fn test_trusted_insert_shreds() {
+    fn test_no_insert_but_modify_slot_meta() {
+        run_test_no_insert_but_modify_slot_meta(LedgerBackendType::RocksDb);
+        run_test_no_insert_but_modify_slot_meta(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_trusted_insert_shreds(backend_type: LedgerBackendType) {
         // Make shred for slot 1
         let (shreds1, _) = make_slot_entries(1, 0, 1);
         let blockstore_path = get_tmp_ledger_path!();
         let last_root = 100;
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             blockstore.set_roots(std::iter::once(&last_root)).unwrap();
 
             // Insert will fail, slot < root
@@ -6162,7 +6706,12 @@ pub mod tests {
     }
 
     #[test]
-    fn test_get_rooted_block() {
+    fn test_trusted_insert_shreds() {
+        run_test_trusted_insert_shreds(LedgerBackendType::RocksDb);
+        run_test_trusted_insert_shreds(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_get_rooted_block(backend_type: LedgerBackendType) {
         let slot = 10;
         let entries = make_slot_entries_with_transactions(100);
         let blockhash = get_last_hash(entries.iter()).unwrap();
@@ -6170,7 +6719,7 @@ pub mod tests {
         let more_shreds = entries_to_test_shreds(entries.clone(), slot + 1, slot, true, 0);
         let unrooted_shreds = entries_to_test_shreds(entries.clone(), slot + 2, slot + 1, true, 0);
         let ledger_path = get_tmp_ledger_path!();
-        let ledger = Blockstore::open(&ledger_path).unwrap();
+        let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
         ledger.insert_shreds(shreds, None, false).unwrap();
         ledger.insert_shreds(more_shreds, None, false).unwrap();
         ledger.insert_shreds(unrooted_shreds, None, false).unwrap();
@@ -6347,10 +6896,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_persist_transaction_status() {
+    fn test_get_rooted_block() {
+        run_test_get_rooted_block(LedgerBackendType::RocksDb);
+        run_test_get_rooted_block(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_persist_transaction_status(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let transaction_status_cf = blockstore.db.column::<cf::TransactionStatus>();
 
             let pre_balances_vec = vec![1, 2, 3];
@@ -6478,11 +7033,17 @@ pub mod tests {
     }
 
     #[test]
+    fn test_persist_transaction_status() {
+        run_test_persist_transaction_status(LedgerBackendType::RocksDb);
+        run_test_persist_transaction_status(LedgerBackendType::InMemory);
+    }
+
     #[allow(clippy::cognitive_complexity)]
-    fn test_transaction_status_index() {
+    fn run_test_transaction_status_index(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let transaction_status_index_cf = blockstore.db.column::<cf::TransactionStatusIndex>();
             let slot0 = 10;
 
@@ -6498,6 +7059,7 @@ pub mod tests {
                         Signature::new(&random_bytes),
                         vec![&Pubkey::new(&random_bytes[0..32])],
                         vec![&Pubkey::new(&random_bytes[32..])],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -6564,6 +7126,7 @@ pub mod tests {
                         Signature::new(&random_bytes),
                         vec![&Pubkey::new(&random_bytes[0..32])],
                         vec![&Pubkey::new(&random_bytes[32..])],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -6685,10 +7248,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_get_transaction_status() {
+    fn test_transaction_status_index() {
+        run_test_transaction_status_index(LedgerBackendType::RocksDb);
+        run_test_transaction_status_index(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_get_transaction_status(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             // TransactionStatus column opens initialized with one entry at index 2
             let transaction_status_cf = blockstore.db.column::<cf::TransactionStatus>();
 
@@ -6876,6 +7445,12 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_get_transaction_status() {
+        run_test_get_transaction_status(LedgerBackendType::RocksDb);
+        run_test_get_transaction_status(LedgerBackendType::InMemory);
+    }
+
     fn do_test_lowest_cleanup_slot_and_special_cfs(
         simulate_compaction: bool,
         simulate_ledger_cleanup_service: bool,
@@ -6937,6 +7512,7 @@ pub mod tests {
                     signature1,
                     vec![&address0],
                     vec![],
+                    vec![],
                     TransactionStatusMeta::default(),
                 )
                 .unwrap();
@@ -6946,6 +7522,7 @@ pub mod tests {
                     signature2,
                     vec![&address1],
                     vec![],
+                    vec![],
                     TransactionStatusMeta::default(),
                 )
                 .unwrap();
@@ -7046,13 +7623,12 @@ pub mod tests {
         do_test_lowest_cleanup_slot_and_special_cfs(false, false);
     }
 
-    #[test]
-    fn test_get_rooted_transaction() {
+    fn run_test_get_rooted_transaction(backend_type: LedgerBackendType) {
         let slot = 2;
         let entries = make_slot_entries_with_transactions(5);
         let shreds = entries_to_test_shreds(entries.clone(), slot, slot - 1, true, 0);
         let ledger_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&ledger_path).unwrap();
+        let blockstore = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
         blockstore.insert_shreds(shreds, None, false).unwrap();
         blockstore.set_roots(vec![slot - 1, slot].iter()).unwrap();
 
@@ -7147,12 +7723,17 @@ pub mod tests {
     }
 
     #[test]
-    fn test_get_complete_transaction() {
+    fn test_get_rooted_transaction() {
+        run_test_get_rooted_transaction(LedgerBackendType::RocksDb);
+        run_test_get_rooted_transaction(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_get_complete_transaction(backend_type: LedgerBackendType) {
         let slot = 2;
         let entries = make_slot_entries_with_transactions(5);
         let shreds = entries_to_test_shreds(entries.clone(), slot, slot - 1, true, 0);
         let ledger_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&ledger_path).unwrap();
+        let blockstore = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
         blockstore.insert_shreds(shreds, None, false).unwrap();
 
         let expected_transactions: Vec<TransactionWithStatusMeta> = entries
@@ -7239,9 +7820,15 @@ pub mod tests {
     }
 
     #[test]
-    fn test_empty_transaction_status() {
+    fn test_get_complete_transaction() {
+        run_test_get_complete_transaction(LedgerBackendType::RocksDb);
+        run_test_get_complete_transaction(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_empty_transaction_status(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
         blockstore.set_roots(std::iter::once(&0)).unwrap();
         assert_eq!(
             blockstore
@@ -7252,10 +7839,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_get_confirmed_signatures_for_address() {
+    fn test_empty_transaction_status() {
+        run_test_empty_transaction_status(LedgerBackendType::RocksDb);
+        run_test_empty_transaction_status(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_get_confirmed_signatures_for_address(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             let address0 = solana_sdk::pubkey::new_rand();
             let address1 = solana_sdk::pubkey::new_rand();
@@ -7269,6 +7862,7 @@ pub mod tests {
                         signature,
                         vec![&address0],
                         vec![&address1],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -7284,6 +7878,7 @@ pub mod tests {
                         signature,
                         vec![&address0],
                         vec![&address1],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -7377,6 +7972,7 @@ pub mod tests {
                         signature,
                         vec![&address0],
                         vec![&address1],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -7392,10 +7988,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_find_address_signatures_for_slot() {
+    fn test_get_confirmed_signatures_for_address() {
+        run_test_get_confirmed_signatures_for_address(LedgerBackendType::RocksDb);
+        run_test_get_confirmed_signatures_for_address(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_find_address_signatures_for_slot(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             let address0 = solana_sdk::pubkey::new_rand();
             let address1 = solana_sdk::pubkey::new_rand();
@@ -7409,6 +8011,7 @@ pub mod tests {
                         signature,
                         vec![&address0],
                         vec![&address1],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -7422,6 +8025,7 @@ pub mod tests {
                         signature,
                         vec![&address0],
                         vec![&address1],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -7436,6 +8040,7 @@ pub mod tests {
                         signature,
                         vec![&address0],
                         vec![&address1],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -7449,6 +8054,7 @@ pub mod tests {
                         signature,
                         vec![&address0],
                         vec![&address1],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -7482,10 +8088,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_get_confirmed_signatures_for_address2() {
+    fn test_find_address_signatures_for_slot() {
+        run_test_find_address_signatures_for_slot(LedgerBackendType::RocksDb);
+        run_test_find_address_signatures_for_slot(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_get_confirmed_signatures_for_address2(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             fn make_slot_entries_with_transaction_addresses(addresses: &[Pubkey]) -> Vec<Entry> {
                 let mut entries: Vec<Entry> = Vec::new();
@@ -7527,6 +8139,7 @@ pub mod tests {
                                 transaction.signatures[0],
                                 transaction.message.account_keys.iter().collect(),
                                 vec![],
+                                vec![],
                                 TransactionStatusMeta::default(),
                             )
                             .unwrap();
@@ -7551,6 +8164,7 @@ pub mod tests {
                                 transaction.signatures[0],
                                 transaction.message.account_keys.iter().collect(),
                                 vec![],
+                                vec![],
                                 TransactionStatusMeta::default(),
                             )
                             .unwrap();
@@ -7888,6 +8502,237 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_get_confirmed_signatures_for_address2() {
+        run_test_get_confirmed_signatures_for_address2(LedgerBackendType::RocksDb);
+        run_test_get_confirmed_signatures_for_address2(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_get_confirmed_signatures_for_address2_filter(backend_type: LedgerBackendType) {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
+            let address = solana_sdk::pubkey::new_rand();
+            let program0 = solana_sdk::pubkey::new_rand();
+            let program1 = solana_sdk::pubkey::new_rand();
+
+            let slot = 1;
+            let programs_and_failures = [(program0, false), (program1, true), (program0, true)];
+            let transactions: Vec<_> = programs_and_failures
+                .iter()
+                .map(|(program_id, _)| {
+                    Transaction::new_with_compiled_instructions(
+                        &[&Keypair::new()],
+                        &[address],
+                        Hash::default(),
+                        vec![*program_id],
+                        vec![CompiledInstruction::new(2, &(), vec![1])],
+                    )
+                })
+                .collect();
+            let entries = vec![next_entry_mut(
+                &mut Hash::default(),
+                0,
+                transactions.clone(),
+            )];
+            let shreds = entries_to_test_shreds(entries, slot, 0, true, 0);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+            blockstore.set_roots(vec![slot].iter()).unwrap();
+            blockstore.cache_block_time(slot, 100).unwrap();
+
+            for (transaction, (_, failed)) in transactions.iter().zip(&programs_and_failures) {
+                let status = if *failed {
+                    Err(TransactionError::AccountInUse)
+                } else {
+                    Ok(())
+                };
+                blockstore
+                    .write_transaction_status(
+                        slot,
+                        transaction.signatures[0],
+                        vec![&address],
+                        vec![],
+                        transaction.message.program_ids(),
+                        TransactionStatusMeta {
+                            status,
+                            ..TransactionStatusMeta::default()
+                        },
+                    )
+                    .unwrap();
+            }
+            // Entries written before filter attributes were recorded are filtered by their
+            // transaction status and the transactions of their slot
+            blockstore
+                .address_signatures_cf
+                .put(
+                    (0, address, slot, transactions[2].signatures[0]),
+                    &AddressSignatureMeta {
+                        writeable: true,
+                        attributes: None,
+                    },
+                )
+                .unwrap();
+
+            let get_signatures = |filter: AddressSignatureFilter| {
+                let mut signatures: Vec<_> = blockstore
+                    .get_filtered_signatures_for_address(
+                        address,
+                        slot,
+                        None,
+                        None,
+                        &filter,
+                        usize::MAX,
+                    )
+                    .unwrap()
+                    .signatures
+                    .into_iter()
+                    .map(|info| info.signature)
+                    .collect();
+                signatures.sort();
+                signatures
+            };
+            let expected_signatures = |indexes: &[usize]| {
+                let mut signatures: Vec<_> = indexes
+                    .iter()
+                    .map(|i| transactions[*i].signatures[0])
+                    .collect();
+                signatures.sort();
+                signatures
+            };
+
+            assert_eq!(
+                get_signatures(AddressSignatureFilter::default()),
+                expected_signatures(&[0, 1, 2])
+            );
+            assert_eq!(
+                get_signatures(AddressSignatureFilter {
+                    status: Some(TransactionStatusFilter::Failed),
+                    ..AddressSignatureFilter::default()
+                }),
+                expected_signatures(&[1, 2])
+            );
+            assert_eq!(
+                get_signatures(AddressSignatureFilter {
+                    program_ids: vec![program0],
+                    ..AddressSignatureFilter::default()
+                }),
+                expected_signatures(&[0, 2])
+            );
+            assert_eq!(
+                get_signatures(AddressSignatureFilter {
+                    status: Some(TransactionStatusFilter::Failed),
+                    program_ids: vec![program0],
+                    ..AddressSignatureFilter::default()
+                }),
+                expected_signatures(&[2])
+            );
+            assert_eq!(
+                get_signatures(AddressSignatureFilter {
+                    max_block_time: Some(100),
+                    ..AddressSignatureFilter::default()
+                }),
+                expected_signatures(&[0, 1, 2])
+            );
+            assert!(get_signatures(AddressSignatureFilter {
+                min_block_time: Some(101),
+                ..AddressSignatureFilter::default()
+            })
+            .is_empty());
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
+    #[test]
+    fn test_get_confirmed_signatures_for_address2_filter() {
+        run_test_get_confirmed_signatures_for_address2_filter(LedgerBackendType::RocksDb);
+        run_test_get_confirmed_signatures_for_address2_filter(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_get_filtered_signatures_for_address_scan_limit(backend_type: LedgerBackendType) {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
+            let address = solana_sdk::pubkey::new_rand();
+            // Shreds of slot 1 make it the first available block
+            let (shreds, _) = make_slot_entries(1, 0, 1);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+            blockstore.set_roots(vec![1, 2].iter()).unwrap();
+            blockstore.cache_block_time(1, 100).unwrap();
+            blockstore.cache_block_time(2, 200).unwrap();
+
+            let put_entry = |slot: Slot, failed: bool| {
+                let random_bytes: Vec<u8> = (0..64).map(|_| rand::random::<u8>()).collect();
+                let signature = Signature::new(&random_bytes);
+                blockstore
+                    .address_signatures_cf
+                    .put(
+                        (0, address, slot, signature),
+                        &AddressSignatureMeta {
+                            writeable: true,
+                            attributes: Some(AddressSignatureAttributes {
+                                failed,
+                                program_ids: vec![],
+                            }),
+                        },
+                    )
+                    .unwrap();
+                signature
+            };
+            let failed_signature = put_entry(1, true);
+            let mut signatures: Vec<_> = (0..MAX_ADDRESS_SIGNATURES_SCANNED)
+                .map(|_| put_entry(2, false))
+                .collect();
+            signatures.sort();
+
+            // The failed transaction lies past the entries a query may examine
+            let filter = AddressSignatureFilter {
+                status: Some(TransactionStatusFilter::Failed),
+                ..AddressSignatureFilter::default()
+            };
+            let address_signatures = blockstore
+                .get_filtered_signatures_for_address(address, 2, None, None, &filter, 10)
+                .unwrap();
+            assert!(address_signatures.signatures.is_empty());
+            assert_eq!(address_signatures.resume_before, Some(signatures[0]));
+
+            // Slot 2 is examined entirely, as it is not older than the lower bound of the block
+            // time, but slot 1 is not examined
+            let filter = AddressSignatureFilter {
+                status: Some(TransactionStatusFilter::Failed),
+                min_block_time: Some(150),
+                ..AddressSignatureFilter::default()
+            };
+            let address_signatures = blockstore
+                .get_filtered_signatures_for_address(address, 2, None, None, &filter, 10)
+                .unwrap();
+            assert!(address_signatures.signatures.is_empty());
+            assert_eq!(address_signatures.resume_before, Some(signatures[0]));
+
+            // The walk ends at the first slot older than the lower bound of the block time
+            blockstore.cache_block_time(2, 50).unwrap();
+            let address_signatures = blockstore
+                .get_filtered_signatures_for_address(address, 2, None, None, &filter, 10)
+                .unwrap();
+            assert_eq!(address_signatures, AddressSignatures::default());
+
+            // Unfiltered queries aren't bounded by the number of entries examined
+            let all_signatures = blockstore
+                .get_confirmed_signatures_for_address2(address, 2, None, None, usize::MAX)
+                .unwrap();
+            assert_eq!(all_signatures.len(), MAX_ADDRESS_SIGNATURES_SCANNED + 1);
+            assert_eq!(all_signatures.last().unwrap().signature, failed_signature);
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
+    #[test]
+    fn test_get_filtered_signatures_for_address_scan_limit() {
+        run_test_get_filtered_signatures_for_address_scan_limit(LedgerBackendType::RocksDb);
+        run_test_get_filtered_signatures_for_address_scan_limit(LedgerBackendType::InMemory);
+    }
+
     #[test]
     #[allow(clippy::same_item_push)]
     fn test_get_last_hash() {
@@ -7905,11 +8750,11 @@ pub mod tests {
         assert_eq!(get_last_hash(entries_iterator).unwrap(), entries[9].hash);
     }
 
-    #[test]
-    fn test_map_transactions_to_statuses() {
+    fn run_test_map_transactions_to_statuses(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let transaction_status_cf = blockstore.db.column::<cf::TransactionStatus>();
 
             let slot = 0;
@@ -7961,10 +8806,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_write_get_perf_samples() {
+    fn test_map_transactions_to_statuses() {
+        run_test_map_transactions_to_statuses(LedgerBackendType::RocksDb);
+        run_test_map_transactions_to_statuses(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_write_get_perf_samples(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let num_entries: usize = 10;
             let mut perf_samples: Vec<(Slot, PerfSample)> = vec![];
             for x in 1..num_entries + 1 {
@@ -7993,10 +8844,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_lowest_slot() {
+    fn test_write_get_perf_samples() {
+        run_test_write_get_perf_samples(LedgerBackendType::RocksDb);
+        run_test_write_get_perf_samples(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_lowest_slot(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             for i in 0..10 {
                 let slot = i;
                 let (shreds, _) = make_slot_entries(slot, 0, 1);
@@ -8010,13 +8867,19 @@ pub mod tests {
     }
 
     #[test]
-    fn test_recovery() {
+    fn test_lowest_slot() {
+        run_test_lowest_slot(LedgerBackendType::RocksDb);
+        run_test_lowest_slot(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_recovery(backend_type: LedgerBackendType) {
         let slot = 1;
         let (data_shreds, coding_shreds, leader_schedule_cache) =
             setup_erasure_shreds(slot, 0, 100);
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             blockstore
                 .insert_shreds(coding_shreds, Some(&leader_schedule_cache), false)
                 .unwrap();
@@ -8042,7 +8905,117 @@ pub mod tests {
     }
 
     #[test]
-    fn test_index_integrity() {
+    fn test_recovery() {
+        run_test_recovery(LedgerBackendType::RocksDb);
+        run_test_recovery(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_merkle_recovery(backend_type: LedgerBackendType) {
+        let slot = 1;
+        let (data_shreds, coding_shreds, leader_schedule_cache) =
+            setup_erasure_shreds_with_variant(slot, 0, 100, true);
+        assert!(data_shreds.len() > MAX_DATA_SHREDS_PER_FEC_BLOCK as usize);
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
+            // Every other data shred, plus the coding shreds.
+            let shreds: Vec<_> = data_shreds
+                .iter()
+                .step_by(2)
+                .cloned()
+                .chain(coding_shreds)
+                .collect();
+            blockstore
+                .insert_shreds(shreds, Some(&leader_schedule_cache), false)
+                .unwrap();
+
+            // Check all the data shreds were recovered along with their proofs
+            for shred in &data_shreds {
+                assert_eq!(
+                    blockstore
+                        .get_data_shred(shred.slot(), shred.index() as u64)
+                        .unwrap()
+                        .unwrap(),
+                    shred.payload
+                );
+            }
+            assert!(blockstore.is_full(slot));
+            verify_index_integrity(&blockstore, slot);
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
+    #[test]
+    fn test_merkle_recovery() {
+        run_test_merkle_recovery(LedgerBackendType::RocksDb);
+        run_test_merkle_recovery(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_recovery_from_partial_coding_set(backend_type: LedgerBackendType) {
+        let slot = 1;
+        let (data_shreds, coding_shreds, leader_schedule_cache) =
+            setup_erasure_shreds(slot, 0, 100);
+        assert!(data_shreds.len() > MAX_DATA_SHREDS_PER_FEC_BLOCK as usize);
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
+            // Only the trailing coding shreds of each erasure set; just enough
+            // to recover all of its data shreds.
+            let shreds: Vec<_> = coding_shreds
+                .iter()
+                .filter(|shred| {
+                    let num_data = shred.coding_header.num_data_shreds;
+                    let num_coding = shred.coding_header.num_coding_shreds;
+                    shred.coding_header.position >= num_coding - num_data
+                })
+                .cloned()
+                .collect();
+            blockstore
+                .insert_shreds(shreds, Some(&leader_schedule_cache), false)
+                .unwrap();
+
+            for shred in &data_shreds {
+                assert_eq!(
+                    blockstore
+                        .get_data_shred(slot, shred.index() as u64)
+                        .unwrap()
+                        .unwrap(),
+                    shred.payload
+                );
+            }
+            assert!(blockstore.is_full(slot));
+
+            // Each erasure set records where its coding shreds start.
+            let mut expected: Vec<_> = coding_shreds
+                .iter()
+                .map(|shred| {
+                    (
+                        u64::from(shred.common_header.fec_set_index),
+                        u64::from(shred.first_coding_index().unwrap()),
+                    )
+                })
+                .collect();
+            expected.dedup();
+            let erasure_metas: Vec<_> = blockstore
+                .erasure_meta_iterator(slot)
+                .unwrap()
+                .map(|(set_index, erasure_meta)| (set_index, erasure_meta.first_coding_index()))
+                .collect();
+            assert_eq!(erasure_metas, expected);
+            verify_index_integrity(&blockstore, slot);
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
+    #[test]
+    fn test_recovery_from_partial_coding_set() {
+        run_test_recovery_from_partial_coding_set(LedgerBackendType::RocksDb);
+        run_test_recovery_from_partial_coding_set(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_index_integrity(backend_type: LedgerBackendType) {
         let slot = 1;
         let num_entries = 100;
         let (data_shreds, coding_shreds, leader_schedule_cache) =
@@ -8051,7 +9024,8 @@ pub mod tests {
         assert!(coding_shreds.len() > 3);
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             // Test inserting all the shreds
             let all_shreds: Vec<_> = data_shreds
                 .iter()
@@ -8179,16 +9153,34 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_index_integrity() {
+        run_test_index_integrity(LedgerBackendType::RocksDb);
+        run_test_index_integrity(LedgerBackendType::InMemory);
+    }
+
     fn setup_erasure_shreds(
         slot: u64,
         parent_slot: u64,
         num_entries: u64,
+    ) -> (Vec<Shred>, Vec<Shred>, Arc<LeaderScheduleCache>) {
+        setup_erasure_shreds_with_variant(slot, parent_slot, num_entries, false)
+    }
+
+    fn setup_erasure_shreds_with_variant(
+        slot: u64,
+        parent_slot: u64,
+        num_entries: u64,
+        merkle_variant: bool,
     ) -> (Vec<Shred>, Vec<Shred>, Arc<LeaderScheduleCache>) {
         let entries = make_slot_entries_with_transactions(num_entries);
         let leader_keypair = Arc::new(Keypair::new());
         let shredder = Shredder::new(slot, parent_slot, 0, 0).unwrap();
-        let (data_shreds, coding_shreds, _) =
-            shredder.entries_to_shreds(&leader_keypair, &entries, true, 0);
+        let (data_shreds, coding_shreds, _) = if merkle_variant {
+            shredder.entries_to_merkle_shreds(&leader_keypair, &entries, true, 0, Some(0))
+        } else {
+            shredder.entries_to_shreds(&leader_keypair, &entries, true, 0, Some(0))
+        };
 
         let genesis_config = create_genesis_config(2).genesis_config;
         let bank = Arc::new(Bank::new(&genesis_config));
@@ -8236,23 +9228,24 @@ pub mod tests {
         assert_eq!(num_coding_in_index, num_coding);
     }
 
-    #[test]
-    fn test_duplicate_slot() {
+    fn run_test_duplicate_slot(backend_type: LedgerBackendType) {
         let slot = 0;
         let entries1 = make_slot_entries_with_transactions(1);
         let entries2 = make_slot_entries_with_transactions(1);
         let leader_keypair = Arc::new(Keypair::new());
         let shredder = Shredder::new(slot, 0, 0, 0).unwrap();
-        let (shreds, _, _) = shredder.entries_to_shreds(&leader_keypair, &entries1, true, 0);
+        let (shreds, _, _) =
+            shredder.entries_to_shreds(&leader_keypair, &entries1, true, 0, Some(0));
         let (duplicate_shreds, _, _) =
-            shredder.entries_to_shreds(&leader_keypair, &entries2, true, 0);
+            shredder.entries_to_shreds(&leader_keypair, &entries2, true, 0, Some(0));
         let shred = shreds[0].clone();
         let duplicate_shred = duplicate_shreds[0].clone();
         let non_duplicate_shred = shred.clone();
 
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             blockstore
                 .insert_shreds(vec![shred.clone()], None, false)
                 .unwrap();
@@ -8297,10 +9290,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_clear_unconfirmed_slot() {
+    fn test_duplicate_slot() {
+        run_test_duplicate_slot(LedgerBackendType::RocksDb);
+        run_test_duplicate_slot(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_clear_unconfirmed_slot(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let unconfirmed_slot = 9;
             let unconfirmed_child_slot = 10;
             let slots = vec![2, unconfirmed_slot, unconfirmed_child_slot];
@@ -8341,6 +9340,12 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_clear_unconfirmed_slot() {
+        run_test_clear_unconfirmed_slot(LedgerBackendType::RocksDb);
+        run_test_clear_unconfirmed_slot(LedgerBackendType::InMemory);
+    }
+
     #[test]
     fn test_update_completed_data_indexes() {
         let mut completed_data_indexes: Vec<u32> = vec![];
@@ -8401,11 +9406,11 @@ pub mod tests {
         assert_eq!(completed_data_indexes, vec![0, 1, 3]);
     }
 
-    #[test]
-    fn test_rewards_protobuf_backward_compatability() {
+    fn run_test_rewards_protobuf_backward_compatability(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let rewards: Rewards = (0..100)
                 .map(|i| Reward {
                     pubkey: solana_sdk::pubkey::new_rand().to_string(),
@@ -8443,10 +9448,18 @@ pub mod tests {
     }
 
     #[test]
-    fn test_transaction_status_protobuf_backward_compatability() {
+    fn test_rewards_protobuf_backward_compatability() {
+        run_test_rewards_protobuf_backward_compatability(LedgerBackendType::RocksDb);
+        run_test_rewards_protobuf_backward_compatability(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_transaction_status_protobuf_backward_compatability(
+        backend_type: LedgerBackendType,
+    ) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let status = TransactionStatusMeta {
                 status: Ok(()),
                 fee: 42,
@@ -8517,11 +9530,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_remove_shred_data_complete_flag() {
+    fn test_transaction_status_protobuf_backward_compatability() {
+        run_test_transaction_status_protobuf_backward_compatability(LedgerBackendType::RocksDb);
+        run_test_transaction_status_protobuf_backward_compatability(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_remove_shred_data_complete_flag(backend_type: LedgerBackendType) {
         let (mut shreds, entries) = make_slot_entries(0, 0, 1);
 
         let ledger_path = get_tmp_ledger_path!();
-        let ledger = Blockstore::open(&ledger_path).unwrap();
+        let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
 
         // Remove the data complete flag from the last shred
         shreds[0].unset_data_complete();
@@ -8536,6 +9554,12 @@ pub mod tests {
         assert_eq!(entries, ledger.get_any_valid_slot_entries(0, 0));
     }
 
+    #[test]
+    fn test_remove_shred_data_complete_flag() {
+        run_test_remove_shred_data_complete_flag(LedgerBackendType::RocksDb);
+        run_test_remove_shred_data_complete_flag(LedgerBackendType::InMemory);
+    }
+
     fn make_large_tx_entry(num_txs: usize) -> Entry {
         let txs: Vec<_> = (0..num_txs)
             .into_iter()
@@ -8549,8 +9573,7 @@ pub mod tests {
         Entry::new(&Hash::default(), 1, txs)
     }
 
-    #[test]
-    fn erasure_multiple_config() {
+    fn run_test_erasure_multiple_config(backend_type: LedgerBackendType) {
         solana_logger::setup();
         let slot = 1;
         let parent = 0;
@@ -8560,10 +9583,10 @@ pub mod tests {
         assert!(shreds.len() > 1);
 
         let ledger_path = get_tmp_ledger_path!();
-        let ledger = Blockstore::open(&ledger_path).unwrap();
+        let ledger = Blockstore::open_with_backend_type(&ledger_path, backend_type).unwrap();
 
-        let coding1 = Shredder::generate_coding_shreds(&shreds, false);
-        let coding2 = Shredder::generate_coding_shreds(&shreds, true);
+        let coding1 = Shredder::generate_coding_shreds(&shreds, false, Some(0));
+        let coding2 = Shredder::generate_coding_shreds(&shreds, true, Some(0));
         for shred in &shreds {
             info!("shred {:?}", shred);
         }
@@ -8583,14 +9606,20 @@ pub mod tests {
     }
 
     #[test]
-    fn test_large_num_coding() {
+    fn erasure_multiple_config() {
+        run_test_erasure_multiple_config(LedgerBackendType::RocksDb);
+        run_test_erasure_multiple_config(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_large_num_coding(backend_type: LedgerBackendType) {
         solana_logger::setup();
         let slot = 1;
         let (_data_shreds, mut coding_shreds, leader_schedule_cache) =
             setup_erasure_shreds(slot, 0, 100);
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             coding_shreds[1].coding_header.num_coding_shreds = u16::MAX;
             blockstore
                 .insert_shreds(
@@ -8608,11 +9637,17 @@ pub mod tests {
     }
 
     #[test]
-    pub fn test_insert_data_shreds_same_slot_last_index() {
-        // Create RocksDb ledger
+    fn test_large_num_coding() {
+        run_test_large_num_coding(LedgerBackendType::RocksDb);
+        run_test_large_num_coding(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_insert_data_shreds_same_slot_last_index(backend_type: LedgerBackendType) {
+        // Create ledger
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
 
             // Create enough entries to ensure there are at least two shreds created
             let num_unique_entries = max_ticks_per_n_shreds(1, None) + 1;
@@ -8660,7 +9695,12 @@ pub mod tests {
     }
 
     #[test]
-    fn test_duplicate_last_index() {
+    pub fn test_insert_data_shreds_same_slot_last_index() {
+        run_test_insert_data_shreds_same_slot_last_index(LedgerBackendType::RocksDb);
+        run_test_insert_data_shreds_same_slot_last_index(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_duplicate_last_index(backend_type: LedgerBackendType) {
         let num_shreds = 2;
         let num_entries = max_ticks_per_n_shreds(num_shreds, None);
         let slot = 1;
@@ -8671,7 +9711,8 @@ pub mod tests {
         shreds[1].set_last_in_slot();
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             blockstore.insert_shreds(shreds, None, false).unwrap();
 
             assert!(blockstore.get_duplicate_slot(slot).is_some());
@@ -8680,7 +9721,12 @@ pub mod tests {
     }
 
     #[test]
-    fn test_duplicate_last_index_mark_dead() {
+    fn test_duplicate_last_index() {
+        run_test_duplicate_last_index(LedgerBackendType::RocksDb);
+        run_test_duplicate_last_index(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_duplicate_last_index_mark_dead(backend_type: LedgerBackendType) {
         let num_shreds = 10;
         let smaller_last_shred_index = 5;
         let larger_last_shred_index = 8;
@@ -8717,7 +9763,8 @@ pub mod tests {
 
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let mut slot = 0;
             let shreds = setup_test_shreds(slot);
 
@@ -8854,7 +9901,12 @@ pub mod tests {
     }
 
     #[test]
-    fn test_get_slot_entries_dead_slot_race() {
+    fn test_duplicate_last_index_mark_dead() {
+        run_test_duplicate_last_index_mark_dead(LedgerBackendType::RocksDb);
+        run_test_duplicate_last_index_mark_dead(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_get_slot_entries_dead_slot_race(backend_type: LedgerBackendType) {
         let setup_test_shreds = move |slot: Slot| -> Vec<Shred> {
             let num_shreds = 10;
             let middle_shred_index = 5;
@@ -8874,7 +9926,9 @@ pub mod tests {
 
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Arc::new(Blockstore::open(&blockstore_path).unwrap());
+            let blockstore = Arc::new(
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap(),
+            );
             let (slot_sender, slot_receiver) = channel();
             let (shred_sender, shred_receiver) = channel::<Vec<Shred>>();
             let (signal_sender, signal_receiver) = channel();
@@ -8949,10 +10003,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_read_write_cost_table() {
+    fn test_get_slot_entries_dead_slot_race() {
+        run_test_get_slot_entries_dead_slot_race(LedgerBackendType::RocksDb);
+        run_test_get_slot_entries_dead_slot_race(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_read_write_cost_table(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let num_entries: usize = 10;
             let mut cost_table: HashMap<Pubkey, u64> = HashMap::new();
             for x in 1..num_entries + 1 {
@@ -9021,10 +10081,16 @@ pub mod tests {
     }
 
     #[test]
-    fn test_delete_old_records_from_cost_table() {
+    fn test_read_write_cost_table() {
+        run_test_read_write_cost_table(LedgerBackendType::RocksDb);
+        run_test_read_write_cost_table(LedgerBackendType::InMemory);
+    }
+
+    fn run_test_delete_old_records_from_cost_table(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
         {
-            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let blockstore =
+                Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
             let num_entries: usize = 10;
             let mut cost_table: HashMap<Pubkey, u64> = HashMap::new();
             for x in 1..num_entries + 1 {
@@ -9069,4 +10135,10 @@ pub mod tests {
         }
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
+
+    #[test]
+    fn test_delete_old_records_from_cost_table() {
+        run_test_delete_old_records_from_cost_table(LedgerBackendType::RocksDb);
+        run_test_delete_old_records_from_cost_table(LedgerBackendType::InMemory);
+    }
 }
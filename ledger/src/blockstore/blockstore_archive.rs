@@ -0,0 +1,515 @@
+use super::*;
+use prost::Message as _;
+use solana_storage_proto::convert::generated;
+use std::convert::TryFrom;
+
+/// Selects the rooted transactions that LedgerCleanupService copies into the filtered archive
+/// columns before purging their slots
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionArchiveFilter {
+    /// Transactions invoking any of these programs, directly or through inner instructions
+    pub program_ids: HashSet<Pubkey>,
+    /// Transactions referencing any of these accounts
+    pub accounts: HashSet<Pubkey>,
+}
+
+impl TransactionArchiveFilter {
+    pub fn is_empty(&self) -> bool {
+        self.program_ids.is_empty() && self.accounts.is_empty()
+    }
+
+    pub fn matches(&self, transaction: &TransactionWithStatusMeta) -> bool {
+        let message = &transaction.transaction.message;
+        let inner_instructions = transaction
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.inner_instructions.as_deref());
+        message
+            .account_keys
+            .iter()
+            .any(|account| self.accounts.contains(account))
+            || invoked_program_ids(message, inner_instructions)
+                .into_iter()
+                .any(|program_id| self.program_ids.contains(program_id))
+    }
+}
+
+enum ArchiveQueryBound {
+    // Slot and signature of an archived transaction
+    Archived(Slot, Signature),
+    // A transaction still in the blockstore
+    Live,
+}
+
+impl Blockstore {
+    /// Copies the transactions of the rooted slots in \[from_slot,to_slot\] that pass `filter`,
+    /// along with their statuses and block times, into the filtered archive columns, which
+    /// LedgerCleanupService never purges. Transactions without a status are skipped, as are
+    /// slots without a complete block. Fails if any other slot cannot be read, so that the caller
+    /// keeps the slots and retries later. Returns the number of transactions archived
+    pub fn archive_filtered_transactions(
+        &self,
+        from_slot: Slot,
+        to_slot: Slot,
+        filter: &TransactionArchiveFilter,
+    ) -> Result<usize> {
+        let mut num_archived = 0;
+        if filter.is_empty() {
+            return Ok(num_archived);
+        }
+        let rooted_slots: Vec<_> = self
+            .rooted_slot_iterator(from_slot)?
+            .take_while(|slot| *slot <= to_slot)
+            .collect();
+        for slot in rooted_slots {
+            let block = match self.get_complete_block(slot, false) {
+                Ok(block) => block,
+                Err(BlockstoreError::SlotUnavailable) => {
+                    debug!("Nothing to archive in slot {}", slot);
+                    continue;
+                }
+                Err(err) => {
+                    warn!("Unable to archive slot {}: {:?}", slot, err);
+                    return Err(err);
+                }
+            };
+            let mut write_batch = self.db.batch()?;
+            let mut slot_archived = false;
+            for transaction in block.transactions {
+                if transaction.meta.is_none() || !filter.matches(&transaction) {
+                    continue;
+                }
+                let signature = transaction.transaction.signatures[0];
+                let (writable_keys, readonly_keys) = transaction
+                    .transaction
+                    .message
+                    .get_account_keys_by_lock_type();
+                let attributes = AddressSignatureAttributes {
+                    failed: transaction
+                        .meta
+                        .as_ref()
+                        .map(|meta| meta.status.is_err())
+                        .unwrap_or_default(),
+                    program_ids: invoked_program_ids(
+                        &transaction.transaction.message,
+                        transaction
+                            .meta
+                            .as_ref()
+                            .and_then(|meta| meta.inner_instructions.as_deref()),
+                    )
+                    .into_iter()
+                    .cloned()
+                    .collect(),
+                };
+                let writable_keys = writable_keys.into_iter().map(|key| (*key, true));
+                let readonly_keys = readonly_keys.into_iter().map(|key| (*key, false));
+                let address_signatures: Vec<_> = writable_keys.chain(readonly_keys).collect();
+                for (address, writeable) in address_signatures {
+                    write_batch.put::<cf::ArchivedAddressSignatures>(
+                        (address, slot, signature),
+                        &AddressSignatureMeta {
+                            writeable,
+                            attributes: Some(attributes.clone()),
+                        },
+                    )?;
+                }
+
+                let archived_transaction: generated::ConfirmedTransaction = transaction.into();
+                let mut buf = Vec::with_capacity(archived_transaction.encoded_len());
+                archived_transaction.encode(&mut buf)?;
+                write_batch.put_bytes::<cf::ArchivedTransaction>((signature, slot), &buf)?;
+                slot_archived = true;
+                num_archived += 1;
+            }
+            if slot_archived {
+                if let Some(block_time) = block.block_time {
+                    write_batch.put::<cf::ArchivedBlocktime>(slot, &block_time)?;
+                }
+                self.db.write(write_batch)?;
+            }
+        }
+        Ok(num_archived)
+    }
+
+    fn get_archived_transaction_slot(&self, signature: Signature) -> Result<Option<Slot>> {
+        let mut iterator = self.archived_transaction_cf.iter(IteratorMode::From(
+            (signature, 0),
+            IteratorDirection::Forward,
+        ))?;
+        Ok(iterator
+            .next()
+            .and_then(|((sig, slot), _data)| (sig == signature).then(|| slot)))
+    }
+
+    /// Returns a transaction from the filtered archive
+    pub fn get_archived_transaction(
+        &self,
+        signature: Signature,
+    ) -> Result<Option<ConfirmedTransaction>> {
+        datapoint_info!(
+            "blockstore-rpc-api",
+            ("method", "get_archived_transaction".to_string(), String)
+        );
+        let slot = match self.get_archived_transaction_slot(signature)? {
+            Some(slot) => slot,
+            None => return Ok(None),
+        };
+        let transaction = match self
+            .archived_transaction_cf
+            .get_protobuf((signature, slot))?
+        {
+            Some(transaction) => TransactionWithStatusMeta::try_from(transaction)?,
+            None => return Ok(None),
+        };
+        Ok(Some(ConfirmedTransaction {
+            slot,
+            transaction,
+            block_time: self.archived_blocktime_cf.get(slot)?,
+        }))
+    }
+
+    // Locates a `before` or `until` signature of an archive query: in the archive, or as a
+    // transaction still in the blockstore, and so newer than everything archived
+    fn find_archive_query_bound(
+        &self,
+        signature: Signature,
+        confirmed_unrooted_slots: &[Slot],
+    ) -> Result<Option<ArchiveQueryBound>> {
+        if let Some(slot) = self.get_archived_transaction_slot(signature)? {
+            return Ok(Some(ArchiveQueryBound::Archived(slot, signature)));
+        }
+        Ok(self
+            .get_transaction_status(signature, confirmed_unrooted_slots)?
+            .map(|_| ArchiveQueryBound::Live))
+    }
+
+    /// Like `get_filtered_signatures_for_address()`, but lists the signatures of the filtered
+    /// archive, for the slots already purged from the blockstore. `before` and `until` may
+    /// refer to transactions still in the blockstore, so that the results of both can be chained
+    pub fn get_archived_signatures_for_address(
+        &self,
+        address: Pubkey,
+        highest_slot: Slot, // highest_confirmed_root or highest_confirmed_slot
+        before: Option<Signature>,
+        until: Option<Signature>,
+        filter: &AddressSignatureFilter,
+        limit: usize,
+    ) -> Result<AddressSignatures<ConfirmedTransactionStatusWithSignature>> {
+        datapoint_info!(
+            "blockstore-rpc-api",
+            (
+                "method",
+                "get_archived_signatures_for_address".to_string(),
+                String
+            )
+        );
+        let last_root = self.last_root();
+        let confirmed_unrooted_slots: Vec<_> = AncestorIterator::new_inclusive(highest_slot, self)
+            .take_while(|&slot| slot > last_root)
+            .collect();
+
+        // Without an archived `before`, the listing starts below the slots still in the
+        // blockstore, which is only known once the lowest_cleanup_slot lock is held
+        let start = match before {
+            None => None,
+            Some(before) => {
+                match self.find_archive_query_bound(before, &confirmed_unrooted_slots)? {
+                    None => return Ok(AddressSignatures::default()),
+                    Some(ArchiveQueryBound::Live) => None,
+                    Some(ArchiveQueryBound::Archived(slot, before)) => {
+                        Some((address, slot, before))
+                    }
+                }
+            }
+        };
+        let until = match until {
+            None => None,
+            Some(until) => match self.find_archive_query_bound(until, &confirmed_unrooted_slots)? {
+                None => None,
+                Some(ArchiveQueryBound::Live) => return Ok(AddressSignatures::default()),
+                Some(ArchiveQueryBound::Archived(slot, until)) => Some((slot, until)),
+            },
+        };
+
+        let (_lock, lowest_available_slot) = self.ensure_lowest_cleanup_slot();
+        let start = start.unwrap_or((address, lowest_available_slot, Signature::default()));
+
+        let mut address_signatures = vec![];
+        let mut resume_before = None;
+        let mut num_scanned = 0;
+        let mut block_time: Option<(Slot, Option<UnixTimestamp>)> = None;
+        let iterator = self
+            .archived_address_signatures_cf
+            .iter(IteratorMode::From(start, IteratorDirection::Reverse))?;
+        for ((key_address, slot, signature), meta) in iterator {
+            if key_address != address || address_signatures.len() >= limit {
+                break;
+            }
+            if slot >= lowest_available_slot || Some(signature) == before {
+                continue;
+            }
+            if matches!(until, Some(until) if (slot, signature) <= until) {
+                break;
+            }
+            let slot_block_time = match block_time {
+                Some((block_time_slot, block_time)) if block_time_slot == slot => block_time,
+                _ => {
+                    let slot_block_time = self.archived_blocktime_cf.get(slot)?;
+                    block_time = Some((slot, slot_block_time));
+                    slot_block_time
+                }
+            };
+            if filter.is_empty() {
+                address_signatures.push((slot, signature, slot_block_time));
+                continue;
+            }
+            if filter.precedes_min_block_time(slot_block_time) {
+                break;
+            }
+            num_scanned += 1;
+            let attributes = deserialize::<AddressSignatureMeta>(&meta)?
+                .attributes
+                .unwrap_or_default();
+            if filter.matches_block_time(slot_block_time)
+                && filter.matches_status(attributes.failed)
+                && filter.matches_program_ids(&attributes.program_ids)
+            {
+                address_signatures.push((slot, signature, slot_block_time));
+            }
+            if num_scanned >= MAX_ADDRESS_SIGNATURES_SCANNED {
+                if address_signatures.len() < limit {
+                    resume_before = Some(signature);
+                }
+                break;
+            }
+        }
+
+        let mut signatures = vec![];
+        for (slot, signature, block_time) in address_signatures {
+            let err = self
+                .archived_transaction_cf
+                .get_protobuf((signature, slot))?
+                .and_then(|transaction| TransactionWithStatusMeta::try_from(transaction).ok())
+                .and_then(|transaction| transaction.meta)
+                .and_then(|meta| meta.status.err());
+            signatures.push(ConfirmedTransactionStatusWithSignature {
+                signature,
+                slot,
+                err,
+                memo: None,
+                block_time,
+            });
+        }
+        Ok(AddressSignatures {
+            signatures,
+            resume_before,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::get_tmp_ledger_path_auto_delete;
+    use solana_sdk::{instruction::CompiledInstruction, transaction::TransactionError};
+    use solana_transaction_status::TransactionStatusFilter;
+
+    // Writes a rooted slot with a transaction for each of `program_ids`, all referencing
+    // `account`, along with their statuses; the transactions of `failed_program_id` fail
+    fn write_rooted_slot(
+        blockstore: &Blockstore,
+        slot: Slot,
+        account: Pubkey,
+        program_ids: &[Pubkey],
+        failed_program_id: Option<Pubkey>,
+    ) -> Vec<Signature> {
+        let transactions: Vec<_> = program_ids
+            .iter()
+            .map(|program_id| {
+                Transaction::new_with_compiled_instructions(
+                    &[&Keypair::new()],
+                    &[account],
+                    Hash::default(),
+                    vec![*program_id],
+                    vec![CompiledInstruction::new(2, &(), vec![1])],
+                )
+            })
+            .collect();
+        let mut entries = create_ticks(1, 0, Hash::default());
+        entries.push(solana_entry::entry::next_entry(
+            &Hash::default(),
+            1,
+            transactions.clone(),
+        ));
+        let shreds = entries_to_test_shreds(entries, slot, slot - 1, true, 0);
+        blockstore.insert_shreds(shreds, None, false).unwrap();
+        blockstore.set_roots(std::iter::once(&slot)).unwrap();
+        blockstore
+            .cache_block_time(slot, slot as UnixTimestamp * 100)
+            .unwrap();
+        for transaction in &transactions {
+            let (writable_keys, readonly_keys) =
+                transaction.message.get_account_keys_by_lock_type();
+            let program_ids = transaction.message.program_ids();
+            let status = if Some(*program_ids[0]) == failed_program_id {
+                Err(TransactionError::AccountInUse)
+            } else {
+                Ok(())
+            };
+            blockstore
+                .write_transaction_status(
+                    slot,
+                    transaction.signatures[0],
+                    writable_keys,
+                    readonly_keys,
+                    program_ids,
+                    TransactionStatusMeta {
+                        status,
+                        ..TransactionStatusMeta::default()
+                    },
+                )
+                .unwrap();
+        }
+        transactions
+            .iter()
+            .map(|transaction| transaction.signatures[0])
+            .collect()
+    }
+
+    #[test]
+    fn test_archive_filtered_transactions() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+        let account = solana_sdk::pubkey::new_rand();
+        let archived_program_id = solana_sdk::pubkey::new_rand();
+        let other_program_id = solana_sdk::pubkey::new_rand();
+        let mut archived_signatures = vec![];
+        let mut other_signatures = vec![];
+        for slot in 1..=4 {
+            let signatures = write_rooted_slot(
+                &blockstore,
+                slot,
+                account,
+                &[archived_program_id, other_program_id],
+                (slot == 2).then(|| archived_program_id),
+            );
+            archived_signatures.push((slot, signatures[0]));
+            other_signatures.push(signatures[1]);
+        }
+
+        let filter = TransactionArchiveFilter {
+            program_ids: vec![archived_program_id].into_iter().collect(),
+            ..TransactionArchiveFilter::default()
+        };
+        assert_eq!(
+            blockstore
+                .archive_filtered_transactions(1, 3, &filter)
+                .unwrap(),
+            3
+        );
+        *blockstore.lowest_cleanup_slot.write().unwrap() = 3;
+        blockstore.purge_slots(1, 3, PurgeType::Exact);
+
+        // Only the matching transactions of the purged slots are served from the archive
+        for (slot, signature) in &archived_signatures[..3] {
+            let archived_transaction = blockstore
+                .get_archived_transaction(*signature)
+                .unwrap()
+                .unwrap();
+            assert_eq!(archived_transaction.slot, *slot);
+            assert_eq!(
+                archived_transaction.block_time,
+                Some(*slot as UnixTimestamp * 100)
+            );
+            assert_eq!(
+                archived_transaction.transaction.transaction.signatures[0],
+                *signature
+            );
+            assert!(blockstore
+                .get_rooted_transaction(*signature)
+                .unwrap()
+                .is_none());
+        }
+        assert!(blockstore
+            .get_archived_transaction(archived_signatures[3].1)
+            .unwrap()
+            .is_none());
+        // A root without a block has nothing to archive
+        blockstore.set_roots(std::iter::once(&5)).unwrap();
+        assert_eq!(
+            blockstore
+                .archive_filtered_transactions(5, 5, &filter)
+                .unwrap(),
+            0
+        );
+        for signature in &other_signatures {
+            assert!(blockstore
+                .get_archived_transaction(*signature)
+                .unwrap()
+                .is_none());
+        }
+
+        // Archived signatures chain on from those still in the blockstore
+        let get_signatures = |before, until, filter: AddressSignatureFilter| {
+            blockstore
+                .get_archived_signatures_for_address(account, 4, before, until, &filter, 10)
+                .unwrap()
+                .signatures
+                .into_iter()
+                .map(|signature| (signature.slot, signature.signature))
+                .collect::<Vec<_>>()
+        };
+        let all_archived: Vec<_> = archived_signatures[..3].iter().rev().cloned().collect();
+        assert_eq!(
+            get_signatures(None, None, AddressSignatureFilter::default()),
+            all_archived
+        );
+        assert_eq!(
+            get_signatures(
+                Some(other_signatures[3]),
+                None,
+                AddressSignatureFilter::default()
+            ),
+            all_archived
+        );
+        assert_eq!(
+            get_signatures(
+                Some(archived_signatures[2].1),
+                Some(archived_signatures[0].1),
+                AddressSignatureFilter::default()
+            ),
+            vec![archived_signatures[1]]
+        );
+        assert_eq!(
+            get_signatures(
+                None,
+                Some(archived_signatures[3].1),
+                AddressSignatureFilter::default()
+            ),
+            vec![]
+        );
+        assert_eq!(
+            get_signatures(
+                None,
+                None,
+                AddressSignatureFilter {
+                    status: Some(TransactionStatusFilter::Failed),
+                    ..AddressSignatureFilter::default()
+                }
+            ),
+            vec![archived_signatures[1]]
+        );
+        assert_eq!(
+            get_signatures(
+                None,
+                None,
+                AddressSignatureFilter {
+                    min_block_time: Some(200),
+                    ..AddressSignatureFilter::default()
+                }
+            ),
+            vec![archived_signatures[2], archived_signatures[1]]
+        );
+    }
+}
@@ -129,6 +129,12 @@ impl Blockstore {
             .expect("Database Error: Failed to get write batch");
         // delete range cf is not inclusive
         let to_slot = to_slot.saturating_add(1);
+        // FIFO compaction reclaims old shreds on its own, so routine cleanup only has to
+        // maintain the metadata columns. Explicit purges still delete the shreds.
+        let purge_shreds = !matches!(
+            (self.shred_storage_type, purge_type),
+            (ShredStorageType::RocksFifo(_), PurgeType::CompactionFilter)
+        );
 
         let mut delete_range_timer = Measure::start("delete_range");
         let mut columns_purged = self
@@ -143,14 +149,16 @@ impl Blockstore {
                 .db
                 .delete_range_cf::<cf::Root>(&mut write_batch, from_slot, to_slot)
                 .is_ok()
-            & self
-                .db
-                .delete_range_cf::<cf::ShredData>(&mut write_batch, from_slot, to_slot)
-                .is_ok()
-            & self
-                .db
-                .delete_range_cf::<cf::ShredCode>(&mut write_batch, from_slot, to_slot)
-                .is_ok()
+            & (!purge_shreds
+                || self
+                    .db
+                    .delete_range_cf::<cf::ShredData>(&mut write_batch, from_slot, to_slot)
+                    .is_ok())
+            & (!purge_shreds
+                || self
+                    .db
+                    .delete_range_cf::<cf::ShredCode>(&mut write_batch, from_slot, to_slot)
+                    .is_ok())
             & self
                 .db
                 .delete_range_cf::<cf::DeadSlots>(&mut write_batch, from_slot, to_slot)
@@ -187,6 +195,7 @@ impl Blockstore {
                 .db
                 .delete_range_cf::<cf::BlockHeight>(&mut write_batch, from_slot, to_slot)
                 .is_ok();
+        // The filtered archive columns are left alone: they exist to outlive purged slots
         let mut w_active_transaction_status_index =
             self.active_transaction_status_index.write().unwrap();
         match purge_type {
@@ -234,6 +243,8 @@ impl Blockstore {
             return Ok(false);
         }
         info!("compact_storage: from {} to {}", from_slot, to_slot);
+        // Nothing to compact in FIFO shred columns; files there are only ever dropped whole.
+        let compact_shreds = self.shred_storage_type == ShredStorageType::RocksLevel;
         let mut compact_timer = Measure::start("compact_range");
         let result = self
             .meta_cf
@@ -244,14 +255,16 @@ impl Blockstore {
                 .column::<cf::Root>()
                 .compact_range(from_slot, to_slot)
                 .unwrap_or(false)
-            && self
-                .data_shred_cf
-                .compact_range(from_slot, to_slot)
-                .unwrap_or(false)
-            && self
-                .code_shred_cf
-                .compact_range(from_slot, to_slot)
-                .unwrap_or(false)
+            && (!compact_shreds
+                || self
+                    .data_shred_cf
+                    .compact_range(from_slot, to_slot)
+                    .unwrap_or(false))
+            && (!compact_shreds
+                || self
+                    .code_shred_cf
+                    .compact_range(from_slot, to_slot)
+                    .unwrap_or(false))
             && self
                 .dead_slots_cf
                 .compact_range(from_slot, to_slot)
@@ -392,7 +405,10 @@ impl Blockstore {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::{blockstore::tests::make_slot_entries_with_transactions, get_tmp_ledger_path};
+    use crate::{
+        blockstore::tests::make_slot_entries_with_transactions,
+        blockstore_db::BlockstoreRocksFifoOptions, get_tmp_ledger_path,
+    };
     use bincode::serialize;
     use solana_entry::entry::next_entry_mut;
     use solana_sdk::{
@@ -493,10 +509,10 @@ pub mod tests {
         assert!(condition_met);
     }
 
-    #[test]
-    fn test_purge_slots() {
+    fn run_test_purge_slots(backend_type: LedgerBackendType) {
         let blockstore_path = get_tmp_ledger_path!();
-        let blockstore = Blockstore::open(&blockstore_path).unwrap();
+        let blockstore =
+            Blockstore::open_with_backend_type(&blockstore_path, backend_type).unwrap();
         let (shreds, _) = make_many_slot_entries(0, 50, 5);
         blockstore.insert_shreds(shreds, None, false).unwrap();
 
@@ -521,6 +537,49 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_purge_slots() {
+        run_test_purge_slots(LedgerBackendType::RocksDb);
+        run_test_purge_slots(LedgerBackendType::InMemory);
+    }
+
+    #[test]
+    fn test_purge_slots_fifo_shred_storage() {
+        let blockstore_path = get_tmp_ledger_path!();
+        let blockstore = Blockstore::open_with_options(
+            &blockstore_path,
+            BlockstoreOptions {
+                shred_storage_type: ShredStorageType::RocksFifo(BlockstoreRocksFifoOptions::new(
+                    1_000_000,
+                )),
+                ..BlockstoreOptions::default()
+            },
+        )
+        .unwrap();
+        let (shreds, _) = make_many_slot_entries(0, 10, 5);
+        blockstore.insert_shreds(shreds, None, false).unwrap();
+        let has_shreds = |slot| {
+            blockstore
+                .get_data_shreds_for_slot(slot, 0)
+                .map(|shreds| !shreds.is_empty())
+                .unwrap()
+        };
+
+        // Routine cleanup leaves the shreds to FIFO compaction.
+        blockstore.purge_slots(0, 4, PurgeType::CompactionFilter);
+        assert!(blockstore.meta(4).unwrap().is_none());
+        assert!(has_shreds(4));
+        assert!(blockstore.meta(5).unwrap().is_some());
+
+        // Explicit purges still delete them.
+        blockstore.purge_slots(0, 4, PurgeType::Exact);
+        assert!(!has_shreds(4));
+        assert!(has_shreds(5));
+
+        drop(blockstore);
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_purge_huge() {
         let blockstore_path = get_tmp_ledger_path!();
@@ -550,6 +609,7 @@ pub mod tests {
                         Signature::new(&random_bytes),
                         vec![&Pubkey::new(&random_bytes[0..32])],
                         vec![&Pubkey::new(&random_bytes[32..])],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -565,6 +625,7 @@ pub mod tests {
                         Signature::new(&random_bytes),
                         vec![&Pubkey::new(&random_bytes[0..32])],
                         vec![&Pubkey::new(&random_bytes[32..])],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -602,6 +663,7 @@ pub mod tests {
                         Signature::new(&random_bytes),
                         vec![&Pubkey::new(&random_bytes[0..32])],
                         vec![&Pubkey::new(&random_bytes[32..])],
+                        vec![],
                         TransactionStatusMeta::default(),
                     )
                     .unwrap();
@@ -791,6 +853,7 @@ pub mod tests {
                     signature,
                     vec![&Pubkey::new(&random_bytes[0..32])],
                     vec![&Pubkey::new(&random_bytes[32..])],
+                    vec![],
                     TransactionStatusMeta::default(),
                 )
                 .unwrap();
@@ -827,6 +890,7 @@ pub mod tests {
                     signature,
                     vec![&Pubkey::new(&random_bytes[0..32])],
                     vec![&Pubkey::new(&random_bytes[32..])],
+                    vec![],
                     TransactionStatusMeta::default(),
                 )
                 .unwrap();
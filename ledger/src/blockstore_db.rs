@@ -1,4 +1,8 @@
-use crate::blockstore_meta;
+use crate::{
+    blockstore::{BLOCKSTORE_DIRECTORY, BLOCKSTORE_DIRECTORY_ROCKS_FIFO},
+    blockstore_meta,
+    shred::SHRED_PAYLOAD_SIZE,
+};
 use bincode::{deserialize, serialize};
 use byteorder::{BigEndian, ByteOrder};
 use log::*;
@@ -8,8 +12,8 @@ use rocksdb::{
     self,
     compaction_filter::CompactionFilter,
     compaction_filter_factory::{CompactionFilterContext, CompactionFilterFactory},
-    ColumnFamily, ColumnFamilyDescriptor, CompactionDecision, DBIterator, DBRawIterator,
-    DBRecoveryMode, IteratorMode as RocksIteratorMode, Options, WriteBatch as RWriteBatch, DB,
+    ColumnFamily, ColumnFamilyDescriptor, CompactionDecision, DBCompactionStyle, DBRecoveryMode,
+    FifoCompactOptions, IteratorMode as RocksIteratorMode, Options, WriteBatch as RWriteBatch, DB,
 };
 
 use serde::de::DeserializeOwned;
@@ -22,14 +26,15 @@ use solana_sdk::{
 };
 use solana_storage_proto::convert::generated;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     ffi::{CStr, CString},
     fs,
     marker::PhantomData,
-    path::Path,
+    ops::Bound,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, RwLock,
     },
 };
 use thiserror::Error;
@@ -75,6 +80,12 @@ const PERF_SAMPLES_CF: &str = "perf_samples";
 const BLOCK_HEIGHT_CF: &str = "block_height";
 /// Column family for ProgramCosts
 const PROGRAM_COSTS_CF: &str = "program_costs";
+/// Column family for the transactions kept by the filtered archive
+const ARCHIVED_TRANSACTION_CF: &str = "archived_transaction";
+/// Column family for the Address Signatures of the filtered archive
+const ARCHIVED_ADDRESS_SIGNATURES_CF: &str = "archived_address_signatures";
+/// Column family for the Blocktime of slots with transactions in the filtered archive
+const ARCHIVED_BLOCKTIME_CF: &str = "archived_blocktime";
 
 // 1 day is chosen for the same reasoning of DEFAULT_COMPACTION_SLOT_INTERVAL
 const PERIODIC_COMPACTION_SECONDS: u64 = 60 * 60 * 24;
@@ -114,6 +125,13 @@ pub enum IteratorMode<Index> {
     From(Index, IteratorDirection),
 }
 
+/// Iterator position over the serialized keys of a column, as handed to a `LedgerBackend`
+pub enum BackendIteratorMode<'a> {
+    Start,
+    End,
+    From(&'a [u8], IteratorDirection),
+}
+
 pub mod columns {
     #[derive(Debug)]
     /// The slot metadata column
@@ -186,6 +204,18 @@ pub mod columns {
     #[derive(Debug)]
     // The program costs column
     pub struct ProgramCosts;
+
+    #[derive(Debug)]
+    /// The filtered archive transaction column
+    pub struct ArchivedTransaction;
+
+    #[derive(Debug)]
+    /// The filtered archive address signatures column
+    pub struct ArchivedAddressSignatures;
+
+    #[derive(Debug)]
+    /// The filtered archive blocktime column
+    pub struct ArchivedBlocktime;
 }
 
 pub enum AccessType {
@@ -259,14 +289,140 @@ impl OldestSlot {
     }
 }
 
+/// Storage engines which can back a `Database`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerBackendType {
+    /// RocksDB instance under the blockstore directory
+    RocksDb,
+    /// Volatile in-memory store; nothing is persisted once the `Database` is dropped
+    InMemory,
+}
+
+/// How the `ShredData` and `ShredCode` column families are compacted, and so cleaned up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShredStorageType {
+    /// Level compaction, with shreds purged by slot range like the rest of the ledger
+    RocksLevel,
+    /// FIFO compaction, where RocksDB drops the oldest shreds once a column family outgrows
+    /// its size and slot based cleanup only has to maintain the metadata columns
+    RocksFifo(BlockstoreRocksFifoOptions),
+}
+
+impl ShredStorageType {
+    /// The two storage types keep their RocksDB instances in different directories, as files
+    /// written with level compaction can not be opened with FIFO compaction.
+    pub fn blockstore_directory(&self) -> &'static str {
+        match self {
+            ShredStorageType::RocksLevel => BLOCKSTORE_DIRECTORY,
+            ShredStorageType::RocksFifo(_) => BLOCKSTORE_DIRECTORY_ROCKS_FIFO,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockstoreRocksFifoOptions {
+    /// Maximum size in bytes of the `ShredData` column family
+    pub shred_data_cf_size: u64,
+    /// Maximum size in bytes of the `ShredCode` column family
+    pub shred_code_cf_size: u64,
+}
+
+impl BlockstoreRocksFifoOptions {
+    /// Sizes each shred column family to hold `max_ledger_shreds` full payloads
+    pub fn new(max_ledger_shreds: u64) -> Self {
+        let cf_size = max_ledger_shreds.saturating_mul(SHRED_PAYLOAD_SIZE as u64);
+        Self {
+            shred_data_cf_size: cf_size,
+            shred_code_cf_size: cf_size,
+        }
+    }
+}
+
+pub struct BlockstoreOptions {
+    pub access_type: AccessType,
+    pub recovery_mode: Option<BlockstoreRecoveryMode>,
+    pub enforce_ulimit_nofile: bool,
+    pub backend_type: LedgerBackendType,
+    pub shred_storage_type: ShredStorageType,
+}
+
+impl Default for BlockstoreOptions {
+    fn default() -> Self {
+        Self {
+            access_type: AccessType::PrimaryOnly,
+            recovery_mode: None,
+            enforce_ulimit_nofile: true,
+            backend_type: LedgerBackendType::RocksDb,
+            shred_storage_type: ShredStorageType::RocksLevel,
+        }
+    }
+}
+
+/// A single mutation of a `WriteBatch`, applied atomically with the rest of the batch
+#[derive(Debug)]
+pub enum WriteOp {
+    Put {
+        cf: &'static str,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Delete {
+        cf: &'static str,
+        key: Vec<u8>,
+    },
+    /// Deletes the keys in `[from, to)`
+    DeleteRange {
+        cf: &'static str,
+        from: Vec<u8>,
+        to: Vec<u8>,
+    },
+}
+
+/// A serialized key and its value
+pub type BackendEntry = (Box<[u8]>, Box<[u8]>);
+
+/// Key-value storage underneath `Database` and `LedgerColumn`. Column families are
+/// addressed by their `ColumnName::NAME`, and keys are ordered bytewise.
+pub trait LedgerBackend: std::fmt::Debug + Send + Sync {
+    fn get_cf(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    fn put_cf(&self, cf: &'static str, key: &[u8], value: &[u8]) -> Result<()>;
+
+    fn delete_cf(&self, cf: &'static str, key: &[u8]) -> Result<()>;
+
+    fn iterator_cf<'a>(
+        &'a self,
+        cf: &'static str,
+        iterator_mode: BackendIteratorMode,
+    ) -> Box<dyn Iterator<Item = BackendEntry> + 'a>;
+
+    fn write(&self, batch: Vec<WriteOp>) -> Result<()>;
+
+    /// Reclaims the space of keys already deleted in `[from, to]`
+    fn compact_range_cf(&self, cf: &'static str, from: &[u8], to: &[u8]);
+
+    fn storage_size(&self) -> Result<u64>;
+
+    fn is_primary_access(&self) -> bool;
+
+    /// Slots older than this have been purged, so any of their leftovers may be dropped
+    fn set_oldest_slot(&self, oldest_slot: Slot);
+}
+
 #[derive(Debug)]
-struct Rocks(rocksdb::DB, ActualAccessType, OldestSlot);
+struct Rocks {
+    db: rocksdb::DB,
+    access_type: ActualAccessType,
+    oldest_slot: OldestSlot,
+    path: PathBuf,
+}
 
 impl Rocks {
     fn open(
         path: &Path,
         access_type: AccessType,
         recovery_mode: Option<BlockstoreRecoveryMode>,
+        shred_storage_type: ShredStorageType,
     ) -> Result<Rocks> {
         use columns::*;
 
@@ -316,14 +472,20 @@ impl Rocks {
             Index::NAME,
             get_cf_options::<Index>(&access_type, &oldest_slot),
         );
-        let shred_data_cf_descriptor = ColumnFamilyDescriptor::new(
-            ShredData::NAME,
-            get_cf_options::<ShredData>(&access_type, &oldest_slot),
-        );
-        let shred_code_cf_descriptor = ColumnFamilyDescriptor::new(
-            ShredCode::NAME,
-            get_cf_options::<ShredCode>(&access_type, &oldest_slot),
-        );
+        let (shred_data_cf_options, shred_code_cf_options) = match shred_storage_type {
+            ShredStorageType::RocksLevel => (
+                get_cf_options::<ShredData>(&access_type, &oldest_slot),
+                get_cf_options::<ShredCode>(&access_type, &oldest_slot),
+            ),
+            ShredStorageType::RocksFifo(fifo_options) => (
+                get_fifo_cf_options(&access_type, fifo_options.shred_data_cf_size),
+                get_fifo_cf_options(&access_type, fifo_options.shred_code_cf_size),
+            ),
+        };
+        let shred_data_cf_descriptor =
+            ColumnFamilyDescriptor::new(ShredData::NAME, shred_data_cf_options);
+        let shred_code_cf_descriptor =
+            ColumnFamilyDescriptor::new(ShredCode::NAME, shred_code_cf_options);
         let transaction_status_cf_descriptor = ColumnFamilyDescriptor::new(
             TransactionStatus::NAME,
             get_cf_options::<TransactionStatus>(&access_type, &oldest_slot),
@@ -356,6 +518,18 @@ impl Rocks {
             ProgramCosts::NAME,
             get_cf_options::<ProgramCosts>(&access_type, &oldest_slot),
         );
+        let archived_transaction_cf_descriptor = ColumnFamilyDescriptor::new(
+            ArchivedTransaction::NAME,
+            get_cf_options::<ArchivedTransaction>(&access_type, &oldest_slot),
+        );
+        let archived_address_signatures_cf_descriptor = ColumnFamilyDescriptor::new(
+            ArchivedAddressSignatures::NAME,
+            get_cf_options::<ArchivedAddressSignatures>(&access_type, &oldest_slot),
+        );
+        let archived_blocktime_cf_descriptor = ColumnFamilyDescriptor::new(
+            ArchivedBlocktime::NAME,
+            get_cf_options::<ArchivedBlocktime>(&access_type, &oldest_slot),
+        );
         // Don't forget to add to both run_purge_with_stats() and
         // compact_storage() in ledger/src/blockstore/blockstore_purge.rs!!
 
@@ -381,19 +555,34 @@ impl Rocks {
             (PerfSamples::NAME, perf_samples_cf_descriptor),
             (BlockHeight::NAME, block_height_cf_descriptor),
             (ProgramCosts::NAME, program_costs_cf_descriptor),
+            (
+                ArchivedTransaction::NAME,
+                archived_transaction_cf_descriptor,
+            ),
+            (
+                ArchivedAddressSignatures::NAME,
+                archived_address_signatures_cf_descriptor,
+            ),
+            (ArchivedBlocktime::NAME, archived_blocktime_cf_descriptor),
         ];
         let cf_names: Vec<_> = cfs.iter().map(|c| c.0).collect();
 
         // Open the database
         let db = match access_type {
-            AccessType::PrimaryOnly | AccessType::PrimaryOnlyForMaintenance => Rocks(
-                DB::open_cf_descriptors(&db_options, path, cfs.into_iter().map(|c| c.1))?,
-                ActualAccessType::Primary,
+            AccessType::PrimaryOnly | AccessType::PrimaryOnlyForMaintenance => Rocks {
+                db: DB::open_cf_descriptors(&db_options, path, cfs.into_iter().map(|c| c.1))?,
+                access_type: ActualAccessType::Primary,
                 oldest_slot,
-            ),
+                path: path.to_path_buf(),
+            },
             AccessType::TryPrimaryThenSecondary => {
                 match DB::open_cf_descriptors(&db_options, path, cfs.into_iter().map(|c| c.1)) {
-                    Ok(db) => Rocks(db, ActualAccessType::Primary, oldest_slot),
+                    Ok(db) => Rocks {
+                        db,
+                        access_type: ActualAccessType::Primary,
+                        oldest_slot,
+                        path: path.to_path_buf(),
+                    },
                     Err(err) => {
                         let secondary_path = path.join("solana-secondary");
 
@@ -404,16 +593,17 @@ impl Rocks {
                         // This is needed according to https://github.com/facebook/rocksdb/wiki/Secondary-instance
                         db_options.set_max_open_files(-1);
 
-                        Rocks(
-                            DB::open_cf_as_secondary(
+                        Rocks {
+                            db: DB::open_cf_as_secondary(
                                 &db_options,
                                 path,
                                 &secondary_path,
                                 cf_names.clone(),
                             )?,
-                            ActualAccessType::Secondary,
+                            access_type: ActualAccessType::Secondary,
                             oldest_slot,
-                        )
+                            path: path.to_path_buf(),
+                        }
                     }
                 }
             }
@@ -426,6 +616,13 @@ impl Rocks {
                 if excludes_from_compaction(cf_name) {
                     continue;
                 }
+                // FIFO compaction would take periodic_compaction_seconds as a TTL, dropping
+                // shreds regardless of the size limit.
+                if matches!(shred_storage_type, ShredStorageType::RocksFifo(_))
+                    && (cf_name == ShredData::NAME || cf_name == ShredCode::NAME)
+                {
+                    continue;
+                }
 
                 // This is the crux of our write-stall-free storage cleaning strategy with consistent
                 // state view for higher-layers
@@ -464,45 +661,21 @@ impl Rocks {
                 // (= all) of invalidated SST files, when combined with newer writes happening at the opposite
                 // edge of the key space. This causes a long and heavy disk IOs and possible write
                 // stall and ultimately, the deadly Replay/Banking stage stall at higher layers.
-                db.0.set_options_cf(
-                    db.cf_handle(cf_name),
-                    &[(
-                        "periodic_compaction_seconds",
-                        &format!("{}", PERIODIC_COMPACTION_SECONDS),
-                    )],
-                )
-                .unwrap();
+                db.db
+                    .set_options_cf(
+                        db.cf_handle(cf_name),
+                        &[(
+                            "periodic_compaction_seconds",
+                            &format!("{}", PERIODIC_COMPACTION_SECONDS),
+                        )],
+                    )
+                    .unwrap();
             }
         }
 
         Ok(db)
     }
 
-    fn columns(&self) -> Vec<&'static str> {
-        use columns::*;
-
-        vec![
-            ErasureMeta::NAME,
-            DeadSlots::NAME,
-            DuplicateSlots::NAME,
-            Index::NAME,
-            Orphans::NAME,
-            BankHash::NAME,
-            Root::NAME,
-            SlotMeta::NAME,
-            ShredData::NAME,
-            ShredCode::NAME,
-            TransactionStatus::NAME,
-            AddressSignatures::NAME,
-            TransactionStatusIndex::NAME,
-            Rewards::NAME,
-            Blocktime::NAME,
-            PerfSamples::NAME,
-            BlockHeight::NAME,
-            ProgramCosts::NAME,
-        ]
-    }
-
     fn destroy(path: &Path) -> Result<()> {
         DB::destroy(&Options::default(), path)?;
 
@@ -510,58 +683,264 @@ impl Rocks {
     }
 
     fn cf_handle(&self, cf: &str) -> &ColumnFamily {
-        self.0
+        self.db
             .cf_handle(cf)
             .expect("should never get an unknown column")
     }
+}
 
-    fn get_cf(&self, cf: &ColumnFamily, key: &[u8]) -> Result<Option<Vec<u8>>> {
-        let opt = self.0.get_cf(cf, key)?;
+impl LedgerBackend for Rocks {
+    fn get_cf(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let opt = self.db.get_cf(self.cf_handle(cf), key)?;
         Ok(opt)
     }
 
-    fn put_cf(&self, cf: &ColumnFamily, key: &[u8], value: &[u8]) -> Result<()> {
-        self.0.put_cf(cf, key, value)?;
+    fn put_cf(&self, cf: &'static str, key: &[u8], value: &[u8]) -> Result<()> {
+        self.db.put_cf(self.cf_handle(cf), key, value)?;
         Ok(())
     }
 
-    fn delete_cf(&self, cf: &ColumnFamily, key: &[u8]) -> Result<()> {
-        self.0.delete_cf(cf, key)?;
+    fn delete_cf(&self, cf: &'static str, key: &[u8]) -> Result<()> {
+        self.db.delete_cf(self.cf_handle(cf), key)?;
         Ok(())
     }
 
-    fn iterator_cf<C>(&self, cf: &ColumnFamily, iterator_mode: IteratorMode<C::Index>) -> DBIterator
-    where
-        C: Column,
-    {
-        let start_key;
+    fn iterator_cf<'a>(
+        &'a self,
+        cf: &'static str,
+        iterator_mode: BackendIteratorMode,
+    ) -> Box<dyn Iterator<Item = BackendEntry> + 'a> {
         let iterator_mode = match iterator_mode {
-            IteratorMode::From(start_from, direction) => {
-                start_key = C::key(start_from);
-                RocksIteratorMode::From(&start_key, direction)
+            BackendIteratorMode::From(start_key, direction) => {
+                RocksIteratorMode::From(start_key, direction)
             }
-            IteratorMode::Start => RocksIteratorMode::Start,
-            IteratorMode::End => RocksIteratorMode::End,
+            BackendIteratorMode::Start => RocksIteratorMode::Start,
+            BackendIteratorMode::End => RocksIteratorMode::End,
         };
-        self.0.iterator_cf(cf, iterator_mode)
+        Box::new(self.db.iterator_cf(self.cf_handle(cf), iterator_mode))
     }
 
-    fn raw_iterator_cf(&self, cf: &ColumnFamily) -> DBRawIterator {
-        self.0.raw_iterator_cf(cf)
+    fn write(&self, batch: Vec<WriteOp>) -> Result<()> {
+        let mut write_batch = RWriteBatch::default();
+        for op in batch {
+            match op {
+                WriteOp::Put { cf, key, value } => {
+                    write_batch.put_cf(self.cf_handle(cf), key, value)
+                }
+                WriteOp::Delete { cf, key } => write_batch.delete_cf(self.cf_handle(cf), key),
+                WriteOp::DeleteRange { cf, from, to } => {
+                    write_batch.delete_range_cf(self.cf_handle(cf), from, to)
+                }
+            }
+        }
+        self.db.write(write_batch)?;
+        Ok(())
     }
 
-    fn batch(&self) -> RWriteBatch {
-        RWriteBatch::default()
+    fn compact_range_cf(&self, cf: &'static str, from: &[u8], to: &[u8]) {
+        self.db
+            .compact_range_cf(self.cf_handle(cf), Some(from), Some(to));
     }
 
-    fn write(&self, batch: RWriteBatch) -> Result<()> {
-        self.0.write(batch)?;
+    fn storage_size(&self) -> Result<u64> {
+        Ok(fs_extra::dir::get_size(&self.path)?)
+    }
+
+    fn is_primary_access(&self) -> bool {
+        self.access_type == ActualAccessType::Primary
+    }
+
+    fn set_oldest_slot(&self, oldest_slot: Slot) {
+        self.oldest_slot.set(oldest_slot);
+    }
+}
+
+type InMemoryColumn = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// Number of entries an `InMemoryIterator` copies out under each read lock
+const IN_MEMORY_ITERATOR_CHUNK_SIZE: usize = 256;
+
+/// Non-persistent backend, mostly useful for tests. Unlike RocksDB, range deletes take
+/// effect immediately, so there is nothing to compact or filter by the oldest slot.
+#[derive(Debug)]
+struct InMemory {
+    columns: RwLock<HashMap<&'static str, InMemoryColumn>>,
+}
+
+impl InMemory {
+    fn new() -> Self {
+        let columns = column_names()
+            .into_iter()
+            .map(|cf| (cf, InMemoryColumn::default()))
+            .collect();
+        Self {
+            columns: RwLock::new(columns),
+        }
+    }
+}
+
+fn in_memory_column<'a>(
+    columns: &'a mut HashMap<&'static str, InMemoryColumn>,
+    cf: &str,
+) -> &'a mut InMemoryColumn {
+    columns
+        .get_mut(cf)
+        .expect("should never get an unknown column")
+}
+
+impl LedgerBackend for InMemory {
+    fn get_cf(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let columns = self.columns.read().unwrap();
+        Ok(columns[cf].get(key).cloned())
+    }
+
+    fn put_cf(&self, cf: &'static str, key: &[u8], value: &[u8]) -> Result<()> {
+        let mut columns = self.columns.write().unwrap();
+        in_memory_column(&mut columns, cf).insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete_cf(&self, cf: &'static str, key: &[u8]) -> Result<()> {
+        let mut columns = self.columns.write().unwrap();
+        in_memory_column(&mut columns, cf).remove(key);
+        Ok(())
+    }
+
+    fn iterator_cf<'a>(
+        &'a self,
+        cf: &'static str,
+        iterator_mode: BackendIteratorMode,
+    ) -> Box<dyn Iterator<Item = BackendEntry> + 'a> {
+        let (bound, direction) = match iterator_mode {
+            BackendIteratorMode::Start => (Bound::Unbounded, IteratorDirection::Forward),
+            BackendIteratorMode::End => (Bound::Unbounded, IteratorDirection::Reverse),
+            BackendIteratorMode::From(start_key, direction) => {
+                (Bound::Included(start_key.to_vec()), direction)
+            }
+        };
+        Box::new(InMemoryIterator {
+            backend: self,
+            cf,
+            direction,
+            bound: Some(bound),
+            buffer: Vec::default().into_iter(),
+        })
+    }
+
+    fn write(&self, batch: Vec<WriteOp>) -> Result<()> {
+        let mut columns = self.columns.write().unwrap();
+        for op in batch {
+            match op {
+                WriteOp::Put { cf, key, value } => {
+                    in_memory_column(&mut columns, cf).insert(key, value);
+                }
+                WriteOp::Delete { cf, key } => {
+                    in_memory_column(&mut columns, cf).remove(&key);
+                }
+                WriteOp::DeleteRange { cf, from, to } => {
+                    let column = in_memory_column(&mut columns, cf);
+                    if from < to {
+                        let keys: Vec<_> = column.range(from..to).map(|(k, _)| k.clone()).collect();
+                        for key in keys {
+                            column.remove(&key);
+                        }
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
+    fn compact_range_cf(&self, _cf: &'static str, _from: &[u8], _to: &[u8]) {}
+
+    fn storage_size(&self) -> Result<u64> {
+        let columns = self.columns.read().unwrap();
+        let size = columns
+            .values()
+            .flat_map(|column| column.iter())
+            .map(|(key, value)| (key.len() + value.len()) as u64)
+            .sum();
+        Ok(size)
+    }
+
     fn is_primary_access(&self) -> bool {
-        self.1 == ActualAccessType::Primary
+        true
     }
+
+    fn set_oldest_slot(&self, _oldest_slot: Slot) {}
+}
+
+/// Walks an `InMemory` column in chunks, so that the lock is not held across calls to
+/// `next` and writers may interleave, similar to a RocksDB iterator without a snapshot.
+struct InMemoryIterator<'a> {
+    backend: &'a InMemory,
+    cf: &'static str,
+    direction: IteratorDirection,
+    // Where the next chunk starts; None once the column is exhausted
+    bound: Option<Bound<Vec<u8>>>,
+    buffer: std::vec::IntoIter<BackendEntry>,
+}
+
+impl<'a> Iterator for InMemoryIterator<'a> {
+    type Item = BackendEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(entry) = self.buffer.next() {
+            return Some(entry);
+        }
+        let bound = self.bound.take()?;
+        let columns = self.backend.columns.read().unwrap();
+        let column = &columns[self.cf];
+        let entries = match self.direction {
+            IteratorDirection::Forward => column
+                .range((bound, Bound::Unbounded))
+                .take(IN_MEMORY_ITERATOR_CHUNK_SIZE)
+                .collect::<Vec<_>>(),
+            IteratorDirection::Reverse => column
+                .range((Bound::Unbounded, bound))
+                .rev()
+                .take(IN_MEMORY_ITERATOR_CHUNK_SIZE)
+                .collect::<Vec<_>>(),
+        };
+        if entries.len() == IN_MEMORY_ITERATOR_CHUNK_SIZE {
+            let (last_key, _) = entries[entries.len() - 1];
+            self.bound = Some(Bound::Excluded(last_key.clone()));
+        }
+        self.buffer = entries
+            .into_iter()
+            .map(|(key, value)| (key.as_slice().into(), value.as_slice().into()))
+            .collect::<Vec<_>>()
+            .into_iter();
+        self.buffer.next()
+    }
+}
+
+fn column_names() -> Vec<&'static str> {
+    use columns::*;
+
+    vec![
+        ErasureMeta::NAME,
+        DeadSlots::NAME,
+        DuplicateSlots::NAME,
+        Index::NAME,
+        Orphans::NAME,
+        BankHash::NAME,
+        Root::NAME,
+        SlotMeta::NAME,
+        ShredData::NAME,
+        ShredCode::NAME,
+        TransactionStatus::NAME,
+        AddressSignatures::NAME,
+        TransactionStatusIndex::NAME,
+        Rewards::NAME,
+        Blocktime::NAME,
+        PerfSamples::NAME,
+        BlockHeight::NAME,
+        ProgramCosts::NAME,
+        ArchivedTransaction::NAME,
+        ArchivedAddressSignatures::NAME,
+        ArchivedBlocktime::NAME,
+    ]
 }
 
 pub trait Column {
@@ -804,6 +1183,90 @@ impl Column for columns::ProgramCosts {
     }
 }
 
+impl Column for columns::ArchivedTransaction {
+    type Index = (Signature, Slot);
+
+    fn key((signature, slot): (Signature, Slot)) -> Vec<u8> {
+        let mut key = vec![0; 64 + 8]; // size_of Signature + size_of Slot
+        key[0..64].clone_from_slice(&signature.as_ref()[0..64]);
+        BigEndian::write_u64(&mut key[64..72], slot);
+        key
+    }
+
+    fn index(key: &[u8]) -> (Signature, Slot) {
+        let signature = Signature::new(&key[0..64]);
+        let slot = BigEndian::read_u64(&key[64..72]);
+        (signature, slot)
+    }
+
+    fn primary_index(_index: Self::Index) -> u64 {
+        unimplemented!()
+    }
+
+    fn slot(index: Self::Index) -> Slot {
+        index.1
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn as_index(_index: u64) -> Self::Index {
+        (Signature::default(), 0)
+    }
+}
+
+impl ColumnName for columns::ArchivedTransaction {
+    const NAME: &'static str = ARCHIVED_TRANSACTION_CF;
+}
+impl ProtobufColumn for columns::ArchivedTransaction {
+    type Type = generated::ConfirmedTransaction;
+}
+
+impl Column for columns::ArchivedAddressSignatures {
+    type Index = (Pubkey, Slot, Signature);
+
+    fn key((pubkey, slot, signature): (Pubkey, Slot, Signature)) -> Vec<u8> {
+        let mut key = vec![0; 32 + 8 + 64]; // size_of Pubkey + size_of Slot + size_of Signature
+        key[0..32].clone_from_slice(&pubkey.as_ref()[0..32]);
+        BigEndian::write_u64(&mut key[32..40], slot);
+        key[40..104].clone_from_slice(&signature.as_ref()[0..64]);
+        key
+    }
+
+    fn index(key: &[u8]) -> (Pubkey, Slot, Signature) {
+        let pubkey = Pubkey::new(&key[0..32]);
+        let slot = BigEndian::read_u64(&key[32..40]);
+        let signature = Signature::new(&key[40..104]);
+        (pubkey, slot, signature)
+    }
+
+    fn primary_index(_index: Self::Index) -> u64 {
+        unimplemented!()
+    }
+
+    fn slot(index: Self::Index) -> Slot {
+        index.1
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn as_index(_index: u64) -> Self::Index {
+        (Pubkey::default(), 0, Signature::default())
+    }
+}
+
+impl ColumnName for columns::ArchivedAddressSignatures {
+    const NAME: &'static str = ARCHIVED_ADDRESS_SIGNATURES_CF;
+}
+impl TypedColumn for columns::ArchivedAddressSignatures {
+    type Type = blockstore_meta::AddressSignatureMeta;
+}
+
+impl SlotColumn for columns::ArchivedBlocktime {}
+impl ColumnName for columns::ArchivedBlocktime {
+    const NAME: &'static str = ARCHIVED_BLOCKTIME_CF;
+}
+impl TypedColumn for columns::ArchivedBlocktime {
+    type Type = UnixTimestamp;
+}
+
 impl Column for columns::ShredCode {
     type Index = (u64, u64);
 
@@ -950,8 +1413,7 @@ impl TypedColumn for columns::ErasureMeta {
 
 #[derive(Debug, Clone)]
 pub struct Database {
-    backend: Arc<Rocks>,
-    path: Arc<Path>,
+    backend: Arc<dyn LedgerBackend>,
 }
 
 #[derive(Debug, Clone)]
@@ -959,13 +1421,13 @@ pub struct LedgerColumn<C>
 where
     C: Column,
 {
-    backend: Arc<Rocks>,
+    backend: Arc<dyn LedgerBackend>,
     column: PhantomData<C>,
 }
 
-pub struct WriteBatch<'a> {
-    write_batch: RWriteBatch,
-    map: HashMap<&'static str, &'a ColumnFamily>,
+#[derive(Debug, Default)]
+pub struct WriteBatch {
+    ops: Vec<WriteOp>,
 }
 
 impl Database {
@@ -974,12 +1436,33 @@ impl Database {
         access_type: AccessType,
         recovery_mode: Option<BlockstoreRecoveryMode>,
     ) -> Result<Self> {
-        let backend = Arc::new(Rocks::open(path, access_type, recovery_mode)?);
+        Self::open_with_options(
+            path,
+            BlockstoreOptions {
+                access_type,
+                recovery_mode,
+                ..BlockstoreOptions::default()
+            },
+        )
+    }
+
+    pub fn open_with_options(path: &Path, options: BlockstoreOptions) -> Result<Self> {
+        let backend: Arc<dyn LedgerBackend> = match options.backend_type {
+            LedgerBackendType::RocksDb => Arc::new(Rocks::open(
+                path,
+                options.access_type,
+                options.recovery_mode,
+                options.shred_storage_type,
+            )?),
+            LedgerBackendType::InMemory => Arc::new(InMemory::new()),
+        };
 
-        Ok(Database {
-            backend,
-            path: Arc::from(path),
-        })
+        Ok(Self::new(backend))
+    }
+
+    /// Wraps an arbitrary storage engine
+    pub fn new(backend: Arc<dyn LedgerBackend>) -> Self {
+        Database { backend }
     }
 
     pub fn destroy(path: &Path) -> Result<()> {
@@ -988,11 +1471,37 @@ impl Database {
         Ok(())
     }
 
+    /// Copies every column into `target`, returning the number of entries copied
+    pub fn copy_to(&self, target: &Database) -> Result<u64> {
+        let mut num_entries = 0;
+        for cf in column_names() {
+            let mut batch = Vec::new();
+            let mut batch_size = 0;
+            for (key, value) in self.backend.iterator_cf(cf, BackendIteratorMode::Start) {
+                batch_size += key.len() + value.len();
+                batch.push(WriteOp::Put {
+                    cf,
+                    key: key.into_vec(),
+                    value: value.into_vec(),
+                });
+                num_entries += 1;
+                if batch_size >= MAX_WRITE_BUFFER_SIZE as usize / 4 {
+                    target.backend.write(std::mem::take(&mut batch))?;
+                    batch_size = 0;
+                }
+            }
+            if !batch.is_empty() {
+                target.backend.write(batch)?;
+            }
+        }
+        Ok(num_entries)
+    }
+
     pub fn get<C>(&self, key: C::Index) -> Result<Option<C::Type>>
     where
         C: TypedColumn + ColumnName,
     {
-        if let Some(serialized_value) = self.backend.get_cf(self.cf_handle::<C>(), &C::key(key))? {
+        if let Some(serialized_value) = self.backend.get_cf(C::NAME, &C::key(key))? {
             let value = deserialize(&serialized_value)?;
 
             Ok(Some(value))
@@ -1008,17 +1517,7 @@ impl Database {
     where
         C: Column + ColumnName,
     {
-        let cf = self.cf_handle::<C>();
-        let iter = self.backend.iterator_cf::<C>(cf, iterator_mode);
-        Ok(iter.map(|(key, value)| (C::index(&key), value)))
-    }
-
-    #[inline]
-    pub fn cf_handle<C: ColumnName>(&self) -> &ColumnFamily
-    where
-        C: Column + ColumnName,
-    {
-        self.backend.cf_handle(C::NAME)
+        Ok(iterator_cf::<C>(self.backend.as_ref(), iterator_mode))
     }
 
     pub fn column<C>(&self) -> LedgerColumn<C>
@@ -1031,29 +1530,16 @@ impl Database {
         }
     }
 
-    #[inline]
-    pub fn raw_iterator_cf(&self, cf: &ColumnFamily) -> Result<DBRawIterator> {
-        Ok(self.backend.raw_iterator_cf(cf))
-    }
-
     pub fn batch(&self) -> Result<WriteBatch> {
-        let write_batch = self.backend.batch();
-        let map = self
-            .backend
-            .columns()
-            .into_iter()
-            .map(|desc| (desc, self.backend.cf_handle(desc)))
-            .collect();
-
-        Ok(WriteBatch { write_batch, map })
+        Ok(WriteBatch::default())
     }
 
     pub fn write(&self, batch: WriteBatch) -> Result<()> {
-        self.backend.write(batch.write_batch)
+        self.backend.write(batch.ops)
     }
 
     pub fn storage_size(&self) -> Result<u64> {
-        Ok(fs_extra::dir::get_size(&self.path)?)
+        self.backend.storage_size()
     }
 
     // Adds a range to delete to the given write batch
@@ -1061,10 +1547,9 @@ impl Database {
     where
         C: Column + ColumnName,
     {
-        let cf = self.cf_handle::<C>();
         let from_index = C::as_index(from);
         let to_index = C::as_index(to);
-        batch.delete_range_cf::<C>(cf, from_index, to_index)
+        batch.delete_range_cf::<C>(from_index, to_index)
     }
 
     pub fn is_primary_access(&self) -> bool {
@@ -1072,25 +1557,44 @@ impl Database {
     }
 
     pub fn set_oldest_slot(&self, oldest_slot: Slot) {
-        self.backend.2.set(oldest_slot);
+        self.backend.set_oldest_slot(oldest_slot);
     }
 }
 
+fn iterator_cf<C>(
+    backend: &dyn LedgerBackend,
+    iterator_mode: IteratorMode<C::Index>,
+) -> impl Iterator<Item = (C::Index, Box<[u8]>)> + '_
+where
+    C: Column + ColumnName,
+{
+    let start_key;
+    let iterator_mode = match iterator_mode {
+        IteratorMode::From(start_from, direction) => {
+            start_key = C::key(start_from);
+            BackendIteratorMode::From(&start_key, direction)
+        }
+        IteratorMode::Start => BackendIteratorMode::Start,
+        IteratorMode::End => BackendIteratorMode::End,
+    };
+    backend
+        .iterator_cf(C::NAME, iterator_mode)
+        .map(|(key, value)| (C::index(&key), value))
+}
+
 impl<C> LedgerColumn<C>
 where
     C: Column + ColumnName,
 {
     pub fn get_bytes(&self, key: C::Index) -> Result<Option<Vec<u8>>> {
-        self.backend.get_cf(self.handle(), &C::key(key))
+        self.backend.get_cf(C::NAME, &C::key(key))
     }
 
     pub fn iter(
         &self,
         iterator_mode: IteratorMode<C::Index>,
     ) -> Result<impl Iterator<Item = (C::Index, Box<[u8]>)> + '_> {
-        let cf = self.handle();
-        let iter = self.backend.iterator_cf::<C>(cf, iterator_mode);
-        Ok(iter.map(|(key, value)| (C::index(&key), value)))
+        Ok(iterator_cf::<C>(self.backend.as_ref(), iterator_mode))
     }
 
     pub fn delete_slot(
@@ -1131,27 +1635,22 @@ where
     where
         C::Index: PartialOrd + Copy,
     {
-        let cf = self.handle();
-        let from = Some(C::key(C::as_index(from)));
-        let to = Some(C::key(C::as_index(to)));
-        self.backend.0.compact_range_cf(cf, from, to);
+        let from = C::key(C::as_index(from));
+        let to = C::key(C::as_index(to));
+        self.backend.compact_range_cf(C::NAME, &from, &to);
         Ok(true)
     }
 
-    #[inline]
-    pub fn handle(&self) -> &ColumnFamily {
-        self.backend.cf_handle(C::NAME)
-    }
-
     #[cfg(test)]
     pub fn is_empty(&self) -> Result<bool> {
-        let mut iter = self.backend.raw_iterator_cf(self.handle());
-        iter.seek_to_first();
-        Ok(!iter.valid())
+        let mut iter = self
+            .backend
+            .iterator_cf(C::NAME, BackendIteratorMode::Start);
+        Ok(iter.next().is_none())
     }
 
     pub fn put_bytes(&self, key: C::Index, value: &[u8]) -> Result<()> {
-        self.backend.put_cf(self.handle(), &C::key(key), value)
+        self.backend.put_cf(C::NAME, &C::key(key), value)
     }
 }
 
@@ -1160,7 +1659,7 @@ where
     C: TypedColumn + ColumnName,
 {
     pub fn get(&self, key: C::Index) -> Result<Option<C::Type>> {
-        if let Some(serialized_value) = self.backend.get_cf(self.handle(), &C::key(key))? {
+        if let Some(serialized_value) = self.backend.get_cf(C::NAME, &C::key(key))? {
             let value = deserialize(&serialized_value)?;
 
             Ok(Some(value))
@@ -1173,11 +1672,11 @@ where
         let serialized_value = serialize(value)?;
 
         self.backend
-            .put_cf(self.handle(), &C::key(key), &serialized_value)
+            .put_cf(C::NAME, &C::key(key), &serialized_value)
     }
 
     pub fn delete(&self, key: C::Index) -> Result<()> {
-        self.backend.delete_cf(self.handle(), &C::key(key))
+        self.backend.delete_cf(C::NAME, &C::key(key))
     }
 }
 
@@ -1189,7 +1688,7 @@ where
         &self,
         key: C::Index,
     ) -> Result<Option<C::Type>> {
-        if let Some(serialized_value) = self.backend.get_cf(self.handle(), &C::key(key))? {
+        if let Some(serialized_value) = self.backend.get_cf(C::NAME, &C::key(key))? {
             let value = match C::Type::decode(&serialized_value[..]) {
                 Ok(value) => value,
                 Err(_) => deserialize::<T>(&serialized_value)?.into(),
@@ -1201,7 +1700,7 @@ where
     }
 
     pub fn get_protobuf(&self, key: C::Index) -> Result<Option<C::Type>> {
-        if let Some(serialized_value) = self.backend.get_cf(self.handle(), &C::key(key))? {
+        if let Some(serialized_value) = self.backend.get_cf(C::NAME, &C::key(key))? {
             Ok(Some(C::Type::decode(&serialized_value[..])?))
         } else {
             Ok(None)
@@ -1211,19 +1710,25 @@ where
     pub fn put_protobuf(&self, key: C::Index, value: &C::Type) -> Result<()> {
         let mut buf = Vec::with_capacity(value.encoded_len());
         value.encode(&mut buf)?;
-        self.backend.put_cf(self.handle(), &C::key(key), &buf)
+        self.backend.put_cf(C::NAME, &C::key(key), &buf)
     }
 }
 
-impl<'a> WriteBatch<'a> {
+impl WriteBatch {
     pub fn put_bytes<C: Column + ColumnName>(&mut self, key: C::Index, bytes: &[u8]) -> Result<()> {
-        self.write_batch
-            .put_cf(self.get_cf::<C>(), &C::key(key), bytes);
+        self.ops.push(WriteOp::Put {
+            cf: C::NAME,
+            key: C::key(key),
+            value: bytes.to_vec(),
+        });
         Ok(())
     }
 
     pub fn delete<C: Column + ColumnName>(&mut self, key: C::Index) -> Result<()> {
-        self.write_batch.delete_cf(self.get_cf::<C>(), &C::key(key));
+        self.ops.push(WriteOp::Delete {
+            cf: C::NAME,
+            key: C::key(key),
+        });
         Ok(())
     }
 
@@ -1233,24 +1738,24 @@ impl<'a> WriteBatch<'a> {
         value: &C::Type,
     ) -> Result<()> {
         let serialized_value = serialize(&value)?;
-        self.write_batch
-            .put_cf(self.get_cf::<C>(), &C::key(key), &serialized_value);
+        self.ops.push(WriteOp::Put {
+            cf: C::NAME,
+            key: C::key(key),
+            value: serialized_value,
+        });
         Ok(())
     }
 
-    #[inline]
-    fn get_cf<C: Column + ColumnName>(&self) -> &'a ColumnFamily {
-        self.map[C::NAME]
-    }
-
-    pub fn delete_range_cf<C: Column>(
+    pub fn delete_range_cf<C: Column + ColumnName>(
         &mut self,
-        cf: &ColumnFamily,
         from: C::Index,
         to: C::Index,
     ) -> Result<()> {
-        self.write_batch
-            .delete_range_cf(cf, C::key(from), C::key(to));
+        self.ops.push(WriteOp::DeleteRange {
+            cf: C::NAME,
+            from: C::key(from),
+            to: C::key(to),
+        });
         Ok(())
     }
 }
@@ -1326,8 +1831,8 @@ fn get_cf_options<C: 'static + Column + ColumnName>(
     options.set_max_bytes_for_level_base(total_size_base);
     options.set_target_file_size_base(file_size_base);
 
-    // TransactionStatusIndex and ProgramCosts must be excluded from LedgerCleanupService's rocksdb
-    // compactions....
+    // TransactionStatusIndex, ProgramCosts and the filtered archive columns must be excluded from
+    // LedgerCleanupService's rocksdb compactions....
     if matches!(access_type, AccessType::PrimaryOnly) && !excludes_from_compaction(C::NAME) {
         options.set_compaction_filter_factory(PurgedSlotFilterFactory::<C> {
             oldest_slot: oldest_slot.clone(),
@@ -1343,6 +1848,25 @@ fn get_cf_options<C: 'static + Column + ColumnName>(
     options
 }
 
+fn get_fifo_cf_options(access_type: &AccessType, max_cf_size: u64) -> Options {
+    let mut options = Options::default();
+    options.set_max_write_buffer_number(8);
+    options.set_write_buffer_size(MAX_WRITE_BUFFER_SIZE as usize);
+    // All files stay in level 0 and are never rewritten; once the column family outgrows
+    // max_cf_size, the oldest files are simply deleted. Shreds are written roughly in slot
+    // order, so those hold the oldest slots, and no compaction filter is needed.
+    options.set_compaction_style(DBCompactionStyle::Fifo);
+    let mut fifo_compact_options = FifoCompactOptions::default();
+    fifo_compact_options.set_max_table_files_size(max_cf_size);
+    options.set_fifo_compaction_options(&fifo_compact_options);
+
+    if matches!(access_type, AccessType::PrimaryOnlyForMaintenance) {
+        options.set_disable_auto_compactions(true);
+    }
+
+    options
+}
+
 fn get_db_options(access_type: &AccessType) -> Options {
     let mut options = Options::default();
     options.create_if_missing(true);
@@ -1373,6 +1897,9 @@ fn excludes_from_compaction(cf_name: &str) -> bool {
     let no_compaction_cfs: HashSet<&'static str> = vec![
         columns::TransactionStatusIndex::NAME,
         columns::ProgramCosts::NAME,
+        columns::ArchivedTransaction::NAME,
+        columns::ArchivedAddressSignatures::NAME,
+        columns::ArchivedBlocktime::NAME,
     ]
     .into_iter()
     .collect();
@@ -1433,13 +1960,152 @@ pub mod tests {
         );
     }
 
+    fn run_test_ledger_backend(backend_type: LedgerBackendType) {
+        use columns::Root;
+
+        let ledger_path = crate::get_tmp_ledger_path_auto_delete!();
+        let db = Database::open_with_options(
+            ledger_path.path(),
+            BlockstoreOptions {
+                backend_type,
+                ..BlockstoreOptions::default()
+            },
+        )
+        .unwrap();
+        let roots = db.column::<Root>();
+        let get_roots = |iterator_mode| -> Vec<Slot> {
+            db.iter::<Root>(iterator_mode)
+                .unwrap()
+                .map(|(slot, _)| slot)
+                .collect()
+        };
+        assert!(roots.is_empty().unwrap());
+        for slot in 1..=10 {
+            roots.put(slot, &true).unwrap();
+        }
+        assert_eq!(roots.get(5).unwrap(), Some(true));
+        assert_eq!(roots.get(11).unwrap(), None);
+        roots.delete(10).unwrap();
+        assert_eq!(roots.get(10).unwrap(), None);
+
+        assert_eq!(get_roots(IteratorMode::Start), (1..10).collect::<Vec<_>>());
+        assert_eq!(
+            get_roots(IteratorMode::End),
+            (1..10).rev().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            get_roots(IteratorMode::From(5, IteratorDirection::Forward)),
+            (5..10).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            get_roots(IteratorMode::From(5, IteratorDirection::Reverse)),
+            (1..=5).rev().collect::<Vec<_>>()
+        );
+
+        // Range deletes exclude the upper bound.
+        let mut batch = db.batch().unwrap();
+        batch.put::<Root>(12, &true).unwrap();
+        batch.delete::<Root>(1).unwrap();
+        db.delete_range_cf::<Root>(&mut batch, 3, 6).unwrap();
+        db.write(batch).unwrap();
+        assert_eq!(get_roots(IteratorMode::Start), vec![2, 6, 7, 8, 9, 12]);
+
+        // Iterators longer than a chunk of the in-memory backend.
+        let shreds = db.column::<ShredData>();
+        for index in 0..1000 {
+            shreds.put_bytes((7, index), &index.to_le_bytes()).unwrap();
+        }
+        let entries: Vec<_> = shreds
+            .iter(IteratorMode::From((7, 100), IteratorDirection::Forward))
+            .unwrap()
+            .collect();
+        assert_eq!(entries.len(), 900);
+        for ((key, value), index) in entries.into_iter().zip(100u64..) {
+            assert_eq!(key, (7, index));
+            assert_eq!(&value[..], &index.to_le_bytes()[..]);
+        }
+        assert!(db.storage_size().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_ledger_backend() {
+        run_test_ledger_backend(LedgerBackendType::RocksDb);
+        run_test_ledger_backend(LedgerBackendType::InMemory);
+    }
+
+    #[test]
+    fn test_copy_to_fifo_shred_storage() {
+        use columns::Root;
+
+        let ledger_path = crate::get_tmp_ledger_path_auto_delete!();
+        let source = Database::open(
+            &ledger_path.path().join(BLOCKSTORE_DIRECTORY),
+            AccessType::PrimaryOnly,
+            None,
+        )
+        .unwrap();
+        for slot in 0..10 {
+            source.column::<Root>().put(slot, &true).unwrap();
+            for index in 0..100 {
+                source
+                    .column::<ShredData>()
+                    .put_bytes((slot, index), &[slot as u8; 64])
+                    .unwrap();
+            }
+        }
+
+        let shred_storage_type =
+            ShredStorageType::RocksFifo(BlockstoreRocksFifoOptions::new(1_000_000));
+        let target = Database::open_with_options(
+            &ledger_path
+                .path()
+                .join(shred_storage_type.blockstore_directory()),
+            BlockstoreOptions {
+                access_type: AccessType::PrimaryOnlyForMaintenance,
+                shred_storage_type,
+                ..BlockstoreOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(source.copy_to(&target).unwrap(), 10 + 10 * 100);
+        assert_eq!(
+            target
+                .iter::<Root>(IteratorMode::Start)
+                .unwrap()
+                .map(|(slot, _)| slot)
+                .collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            target
+                .column::<ShredData>()
+                .get_bytes((7, 42))
+                .unwrap()
+                .unwrap(),
+            vec![7; 64]
+        );
+        assert_eq!(
+            target
+                .column::<ShredData>()
+                .iter(IteratorMode::Start)
+                .unwrap()
+                .count(),
+            10 * 100
+        );
+    }
+
     #[test]
     fn test_excludes_from_compaction() {
-        // currently there are two CFs are excluded from compaction:
+        // currently there are five CFs are excluded from compaction:
         assert!(excludes_from_compaction(
             columns::TransactionStatusIndex::NAME
         ));
         assert!(excludes_from_compaction(columns::ProgramCosts::NAME));
+        assert!(excludes_from_compaction(columns::ArchivedTransaction::NAME));
+        assert!(excludes_from_compaction(
+            columns::ArchivedAddressSignatures::NAME
+        ));
+        assert!(excludes_from_compaction(columns::ArchivedBlocktime::NAME));
         assert!(!excludes_from_compaction("something else"));
     }
 }
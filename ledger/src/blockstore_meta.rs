@@ -1,7 +1,10 @@
 use crate::erasure::ErasureConfig;
 use serde::{Deserialize, Serialize};
-use solana_sdk::{clock::Slot, hash::Hash};
-use std::{collections::BTreeSet, ops::RangeBounds};
+use solana_sdk::{clock::Slot, deserialize_utils::default_on_eof, hash::Hash, pubkey::Pubkey};
+use std::{
+    collections::BTreeSet,
+    ops::{Range, RangeBounds},
+};
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
 // The Meta column family
@@ -51,9 +54,9 @@ pub struct ShredIndex {
 pub struct ErasureMeta {
     /// Which erasure set in the slot this is
     pub set_index: u64,
-    /// Deprecated field.
-    #[serde(rename = "first_coding_index")]
-    __unused: u64,
+    /// First coding index in the FEC set; zero in metas written before the
+    /// field replaced an unused one. See ErasureMeta::first_coding_index.
+    first_coding_index: u64,
     /// Size of shards in this erasure set
     pub size: usize,
     /// Erasure configuration for this erasure set
@@ -216,22 +219,44 @@ impl SlotMeta {
 }
 
 impl ErasureMeta {
-    pub fn new(set_index: u64, config: ErasureConfig) -> ErasureMeta {
+    pub fn new(set_index: u64, first_coding_index: u64, config: ErasureConfig) -> ErasureMeta {
         ErasureMeta {
             set_index,
+            first_coding_index,
             config,
             ..Self::default()
         }
     }
 
+    pub fn data_shreds_indices(&self) -> Range<u64> {
+        let num_data = self.config.num_data() as u64;
+        self.set_index..self.set_index + num_data
+    }
+
+    /// Index of the first coding shred in the erasure set. Metas stored by
+    /// older versions left it at zero, when the coding shreds of a set were
+    /// always indexed from its set_index.
+    pub fn first_coding_index(&self) -> u64 {
+        if self.first_coding_index == 0 && self.set_index != 0 {
+            self.set_index
+        } else {
+            self.first_coding_index
+        }
+    }
+
+    pub fn coding_shreds_indices(&self) -> Range<u64> {
+        let num_coding = self.config.num_coding() as u64;
+        let first_coding_index = self.first_coding_index();
+        first_coding_index..first_coding_index + num_coding
+    }
+
     pub fn status(&self, index: &Index) -> ErasureMetaStatus {
         use ErasureMetaStatus::*;
 
-        let coding_indices = self.set_index..self.set_index + self.config.num_coding() as u64;
-        let num_coding = index.coding().present_in_bounds(coding_indices);
-        let num_data = index
-            .data()
-            .present_in_bounds(self.set_index..self.set_index + self.config.num_data() as u64);
+        let num_coding = index
+            .coding()
+            .present_in_bounds(self.coding_shreds_indices());
+        let num_data = index.data().present_in_bounds(self.data_shreds_indices());
 
         let (data_missing, num_needed) = (
             self.config.num_data().saturating_sub(num_data),
@@ -271,6 +296,16 @@ pub struct TransactionStatusIndexMeta {
 #[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
 pub struct AddressSignatureMeta {
     pub writeable: bool,
+    // What address signature queries can be filtered by; None for entries written before these
+    // were recorded
+    #[serde(deserialize_with = "default_on_eof")]
+    pub attributes: Option<AddressSignatureAttributes>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct AddressSignatureAttributes {
+    pub failed: bool,
+    pub program_ids: Vec<Pubkey>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
@@ -298,7 +333,7 @@ mod test {
         let set_index = 0;
         let erasure_config = ErasureConfig::default();
 
-        let mut e_meta = ErasureMeta::new(set_index, erasure_config);
+        let mut e_meta = ErasureMeta::new(set_index, set_index, erasure_config);
         let mut rng = thread_rng();
         let mut index = Index::new(0);
         e_meta.size = 1;
@@ -342,6 +377,25 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_erasure_meta_first_coding_index() {
+        let erasure_config = ErasureConfig::new(5, 20);
+        // Metas stored before the first coding index was recorded have zero
+        // in its place, and their coding shreds start at the set index.
+        let legacy_meta: ErasureMeta = bincode::deserialize(
+            &bincode::serialize(&ErasureMeta::new(32, 0, erasure_config)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(legacy_meta.first_coding_index(), 32);
+        assert_eq!(legacy_meta.coding_shreds_indices(), 32..52);
+
+        let erasure_meta = ErasureMeta::new(32, 40, erasure_config);
+        assert_eq!(erasure_meta.first_coding_index(), 40);
+        assert_eq!(erasure_meta.coding_shreds_indices(), 40..60);
+        let erasure_meta = ErasureMeta::new(0, 0, erasure_config);
+        assert_eq!(erasure_meta.coding_shreds_indices(), 0..20);
+    }
+
     #[test]
     fn test_clear_unconfirmed_slot() {
         let mut slot_meta = SlotMeta::new_orphan(5);
@@ -24,6 +24,8 @@ use solana_runtime::{
     bank_forks::BankForks,
     bank_utils,
     commitment::VOTE_THRESHOLD_SIZE,
+    cost_model::CostModelError,
+    cost_tracker::CostTracker,
     snapshot_utils::BankFromArchiveTimings,
     transaction_batch::TransactionBatch,
     vote_account::ArcVoteAccount,
@@ -221,22 +223,41 @@ pub fn process_entries(
     replay_vote_sender: Option<&ReplayVoteSender>,
 ) -> Result<()> {
     let mut timings = ExecuteTimings::default();
+    let result = process_entries_with_timings(
+        bank,
+        entries,
+        randomize,
+        transaction_status_sender,
+        replay_vote_sender,
+        &mut timings,
+    );
+
+    debug!("process_entries: {:?}", timings);
+    result
+}
+
+/// Same as `process_entries`, accumulating the execution timings into `timings`
+pub fn process_entries_with_timings(
+    bank: &Arc<Bank>,
+    entries: &mut [Entry],
+    randomize: bool,
+    transaction_status_sender: Option<&TransactionStatusSender>,
+    replay_vote_sender: Option<&ReplayVoteSender>,
+    timings: &mut ExecuteTimings,
+) -> Result<()> {
     let mut entry_types: Vec<_> = entries
         .iter()
         .map(EntryType::try_from)
         .collect::<Result<_>>()?;
-    let result = process_entries_with_callback(
+    process_entries_with_callback(
         bank,
         &mut entry_types,
         randomize,
         None,
         transaction_status_sender,
         replay_vote_sender,
-        &mut timings,
-    );
-
-    debug!("process_entries: {:?}", timings);
-    result
+        timings,
+    )
 }
 
 // Note: If randomize is true this will shuffle entries' transactions in-place.
@@ -363,6 +384,9 @@ pub enum BlockstoreProcessorError {
 
     #[error("root bank with mismatched capitalization at {0}")]
     RootBankWithMismatchedCapitalization(Slot),
+
+    #[error("block exceeds cost limits: {0:?}")]
+    BlockCostLimitExceeded(CostModelError),
 }
 
 /// Callback for accessing bank state while processing the blockstore
@@ -725,6 +749,10 @@ pub struct ConfirmationProgress {
     pub num_shreds: u64,
     pub num_entries: usize,
     pub num_txs: usize,
+    // Cost of the slot's transactions replayed so far; `CostModel` charges
+    // fixed per-program compute units, so every validator computes the same
+    // costs
+    pub cost_tracker: CostTracker,
 }
 
 impl ConfirmationProgress {
@@ -815,6 +843,13 @@ pub fn confirm_slot(
     )?;
     let transaction_duration_us = timing::duration_as_us(&check_start.elapsed());
 
+    if bank.gate_large_block_enabled() {
+        check_block_cost_limits(&mut progress.cost_tracker, &entries).map_err(|err| {
+            warn!("Block cost limits exceeded at slot: {}, {:?}", slot, err);
+            err
+        })?;
+    }
+
     let mut replay_elapsed = Measure::start("replay_elapsed");
     let mut execute_timings = ExecuteTimings::default();
     // Note: This will shuffle entries' transactions in-place.
@@ -855,6 +890,24 @@ pub fn confirm_slot(
     Ok(())
 }
 
+// Accumulate the cost of the entries' transactions, failing once the block
+// would exceed any of its cost limits
+fn check_block_cost_limits(
+    cost_tracker: &mut CostTracker,
+    entries: &[EntryType],
+) -> result::Result<(), BlockstoreProcessorError> {
+    for entry in entries {
+        if let EntryType::Transactions(transactions) = entry {
+            for transaction in transactions {
+                cost_tracker
+                    .try_add_transaction(transaction)
+                    .map_err(BlockstoreProcessorError::BlockCostLimitExceeded)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 // Special handling required for processing the entries in slot 0
 fn process_bank_0(
     bank0: &Arc<Bank>,
@@ -1326,12 +1379,14 @@ pub mod tests {
     use matches::assert_matches;
     use rand::{thread_rng, Rng};
     use solana_entry::entry::{create_ticks, next_entry, next_entry_mut};
-    use solana_runtime::genesis_utils::{
-        self, create_genesis_config_with_vote_accounts, ValidatorVoteKeypairs,
+    use solana_runtime::{
+        cost_model::CostModel,
+        genesis_utils::{self, create_genesis_config_with_vote_accounts, ValidatorVoteKeypairs},
     };
     use solana_sdk::{
         account::{AccountSharedData, WritableAccount},
         epoch_schedule::EpochSchedule,
+        feature_set,
         hash::Hash,
         pubkey::Pubkey,
         signature::{Keypair, Signer},
@@ -3552,4 +3607,67 @@ pub mod tests {
             8
         );
     }
+
+    #[test]
+    fn test_confirm_slot_block_cost_limits() {
+        solana_logger::setup();
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let (ledger_path, blockhash) = create_new_tmp_ledger!(&genesis_config);
+        let blockstore = Blockstore::open(&ledger_path).unwrap();
+
+        let tx = system_transaction::transfer(&mint_keypair, &Pubkey::new_unique(), 1, blockhash);
+        let entries = vec![next_entry(&blockhash, 1, vec![tx])];
+        blockstore
+            .write_entries(
+                1,
+                0,
+                0,
+                genesis_config.ticks_per_slot,
+                Some(0),
+                false,
+                &Arc::new(Keypair::new()),
+                entries,
+                0,
+            )
+            .unwrap();
+
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let confirm_slot_with_gate = |gate_large_block: bool| {
+            let mut bank = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+            if gate_large_block {
+                bank.activate_feature(&feature_set::gate_large_block::id());
+            } else {
+                bank.deactivate_feature(&feature_set::gate_large_block::id());
+            }
+            // a block too small for even a single transfer
+            let mut progress = ConfirmationProgress {
+                cost_tracker: CostTracker::new(Arc::new(RwLock::new(CostModel::new(1, 1)))),
+                ..ConfirmationProgress::new(blockhash)
+            };
+            confirm_slot(
+                &blockstore,
+                &Arc::new(bank),
+                &mut ConfirmationTiming::default(),
+                &mut progress,
+                true,
+                None,
+                None,
+                None,
+                &VerifyRecyclers::default(),
+                false,
+            )
+        };
+
+        assert_matches!(confirm_slot_with_gate(false), Ok(()));
+        assert_matches!(
+            confirm_slot_with_gate(true),
+            Err(BlockstoreProcessorError::BlockCostLimitExceeded(
+                CostModelError::WouldExceedNonVoteMaxLimit
+            ))
+        );
+    }
 }
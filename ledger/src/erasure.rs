@@ -1,12 +1,22 @@
 //! # Erasure Coding and Recovery
 //!
-//! Shreds are logically grouped into erasure sets or blocks. Each set contains 16 sequential data
-//! shreds and 4 sequential coding shreds.
+//! Shreds are logically grouped into erasure sets or blocks. Each set contains up to
+//! `MAX_DATA_SHREDS_PER_FEC_BLOCK` sequential data shreds, and a number of sequential coding
+//! shreds which depends on the number of data shreds in the set (see
+//! `shred::get_erasure_batch_size`). Smaller sets get proportionally more coding shreds, so
+//! that every set can be recovered with about the same probability.
 //!
-//! Coding shreds in each set starting from `start_idx`:
+//! Coding shreds in each set starting from `first_coding_index`:
 //!   For each erasure set:
-//!     generate `NUM_CODING` coding_shreds.
-//!     index the coding shreds from `start_idx` to `start_idx + NUM_CODING - 1`.
+//!     generate `num_coding` coding_shreds.
+//!     index the coding shreds from `first_coding_index` to `first_coding_index + num_coding - 1`,
+//!     and record the position of each one within the set in its coding header.
+//!   The next erasure set starts its coding shreds at `first_coding_index + num_coding`.
+//!
+//! Until the `variable_erasure_batch_size` feature is active, leaders use the legacy layout
+//! instead: a set holds as many coding shreds as data shreds (but for the last set in the slot),
+//! indexed from the index of the first data shred in the set. Older leaders leave the position
+//! of the coding shreds at zero; see `Shred::first_coding_index`.
 //!
 //!  model of an erasure set, with top row being data shreds and second being coding
 //!  |<======================= NUM_DATA ==============================>|
@@ -122,6 +132,15 @@ impl Session {
 
         Ok(())
     }
+
+    /// Recover both the missing data blocks and the missing coding blocks.
+    /// Merkle shreds need every block of the erasure set in order to rebuild
+    /// the proofs of the recovered shreds.
+    pub fn reconstruct_blocks(&self, blocks: &mut [(&mut [u8], bool)]) -> Result<()> {
+        self.0.reconstruct(blocks)?;
+
+        Ok(())
+    }
 }
 
 impl Default for Session {
@@ -205,4 +224,32 @@ pub mod test {
         assert_eq!(v_orig, vs[0]);
         assert_eq!(erased, vs[erasure]);
     }
+
+    #[test]
+    fn test_reconstruct_blocks() {
+        const N_DATA: usize = 4;
+        const N_CODING: usize = 2;
+
+        let session = Session::new(N_DATA, N_CODING).unwrap();
+        let data: Vec<Vec<u8>> = (0..N_DATA as u8).map(|i| (i..(16 + i)).collect()).collect();
+        let mut coding: Vec<_> = (0..N_CODING).map(|_| vec![0u8; 16]).collect();
+        session.encode(&data, &mut coding[..]).unwrap();
+
+        // Erase one data block and one coding block.
+        let mut blocks: Vec<_> = data.iter().chain(coding.iter()).cloned().collect();
+        let mut present = vec![true; N_DATA + N_CODING];
+        for erasure in &[1, N_DATA + 1] {
+            blocks[*erasure].iter_mut().for_each(|b| *b = 0);
+            present[*erasure] = false;
+        }
+        let mut blocks_present: Vec<_> = blocks
+            .iter_mut()
+            .map(Vec::as_mut_slice)
+            .zip(present)
+            .collect();
+        session.reconstruct_blocks(&mut blocks_present).unwrap();
+
+        assert_eq!(blocks[..N_DATA], data[..]);
+        assert_eq!(blocks[N_DATA..], coding[..]);
+    }
 }
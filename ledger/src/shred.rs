@@ -48,6 +48,14 @@
 //!
 //! So, given a) - c), we must restrict data shred's payload length such that the entire coding
 //! payload can fit into one coding shred / packet.
+//!
+//! Merkle shreds:
+//! Legacy shreds above are each signed by the leader. Merkle shreds instead carry the same
+//! signature over the Merkle root of their erasure batch, where the leaves of the tree are the
+//! data and coding shreds of the batch, excluding the signature and the proof. Each shred stores
+//! its Merkle proof at OFFSET_OF_MERKLE_PROOF, and the number of entries in the proof in the low
+//! bits of its shred type. The erasure shards of Merkle shreds exclude the signature too, since it
+//! is only known once the whole batch has been coded.
 
 use crate::{blockstore::MAX_DATA_SHREDS_PER_SLOT, erasure::Session};
 use bincode::config::Options;
@@ -60,6 +68,7 @@ use rayon::{
 use serde::{Deserialize, Serialize};
 use solana_entry::entry::{create_ticks, Entry};
 use solana_measure::measure::Measure;
+use solana_merkle_tree::{merkle_tree::hash_leaf, MerkleTree};
 use solana_perf::packet::{limited_deserialize, Packet};
 use solana_rayon_threadlimit::get_thread_count;
 use solana_runtime::bank::Bank;
@@ -127,8 +136,21 @@ pub const SIZE_OF_DATA_SHRED_PAYLOAD: usize = PACKET_DATA_SIZE
 pub const OFFSET_OF_SHRED_TYPE: usize = SIZE_OF_SIGNATURE;
 pub const OFFSET_OF_SHRED_SLOT: usize = SIZE_OF_SIGNATURE + SIZE_OF_SHRED_TYPE;
 pub const OFFSET_OF_SHRED_INDEX: usize = OFFSET_OF_SHRED_SLOT + SIZE_OF_SHRED_SLOT;
+const OFFSET_OF_FEC_SET_INDEX: usize =
+    OFFSET_OF_SHRED_INDEX + SIZE_OF_SHRED_INDEX + size_of::<u16>();
 pub const SHRED_PAYLOAD_SIZE: usize = PACKET_DATA_SIZE - SIZE_OF_NONCE;
 
+pub const SIZE_OF_MERKLE_PROOF_ENTRY: usize = size_of::<Hash>();
+/// Enough proof entries for an erasure batch of 2 * MAX_DATA_SHREDS_PER_FEC_BLOCK shreds
+pub const MAX_MERKLE_PROOF_SIZE: usize = 6;
+pub const OFFSET_OF_MERKLE_PROOF: usize =
+    SHRED_PAYLOAD_SIZE - MAX_MERKLE_PROOF_SIZE * SIZE_OF_MERKLE_PROOF_ENTRY;
+const SIZE_OF_MERKLE_ERASURE_SHARD: usize = OFFSET_OF_MERKLE_PROOF - SIZE_OF_CODING_SHRED_HEADERS;
+pub const SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD: usize = SIZE_OF_SIGNATURE
+    + SIZE_OF_MERKLE_ERASURE_SHARD
+    - SIZE_OF_COMMON_SHRED_HEADER
+    - SIZE_OF_DATA_SHRED_HEADER;
+
 thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::ThreadPoolBuilder::new()
                     .num_threads(get_thread_count())
                     .thread_name(|ix| format!("shredder_{}", ix))
@@ -138,9 +160,23 @@ thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::
 /// The constants that define if a shred is data or coding
 pub const DATA_SHRED: u8 = 0b1010_0101;
 pub const CODING_SHRED: u8 = 0b0101_1010;
+/// Merkle shreds store the number of entries in their proof in the low bits
+pub const MERKLE_DATA_SHRED: u8 = 0b1000_0000;
+pub const MERKLE_CODING_SHRED: u8 = 0b0100_0000;
+const MERKLE_PROOF_SIZE_MASK: u8 = 0b0000_1111;
 
 pub const MAX_DATA_SHREDS_PER_FEC_BLOCK: u32 = 32;
 
+// Maps the number of data shreds in an erasure batch to the total size of the
+// batch, such that losing shreds at the same rate the batch can be recovered
+// with about the same probability as a 32:32 batch.
+const ERASURE_BATCH_SIZE: [usize; 33] = [
+    0, 18, 20, 22, 23, 25, 27, 28, 30, // 8
+    32, 33, 35, 36, 38, 39, 41, 42, // 16
+    43, 45, 46, 48, 49, 51, 52, 53, // 24
+    55, 56, 58, 59, 60, 62, 63, 64, // 32
+];
+
 pub const SHRED_TICK_REFERENCE_MASK: u8 = 0b0011_1111;
 const LAST_SHRED_IN_SLOT: u8 = 0b1000_0000;
 pub const DATA_COMPLETE_SHRED: u8 = 0b0100_0000;
@@ -163,6 +199,9 @@ pub enum ShredError {
         "invalid parent offset; parent_offset {parent_offset} must be larger than slot {slot}"
     )]
     InvalidParentOffset { slot: Slot, parent_offset: u16 },
+
+    #[error("invalid data size; size {0} overlaps the merkle proof")]
+    InvalidDataSize(u16),
 }
 
 pub type Result<T> = std::result::Result<T, ShredError>;
@@ -175,6 +214,37 @@ impl Default for ShredType {
     }
 }
 
+impl ShredType {
+    fn merkle_data(proof_size: usize) -> Self {
+        ShredType(MERKLE_DATA_SHRED | proof_size as u8)
+    }
+
+    fn merkle_coding(proof_size: usize) -> Self {
+        ShredType(MERKLE_CODING_SHRED | proof_size as u8)
+    }
+
+    pub fn is_data(self) -> bool {
+        self.0 == DATA_SHRED || self.0 & !MERKLE_PROOF_SIZE_MASK == MERKLE_DATA_SHRED
+    }
+
+    pub fn is_code(self) -> bool {
+        self.0 == CODING_SHRED || self.0 & !MERKLE_PROOF_SIZE_MASK == MERKLE_CODING_SHRED
+    }
+
+    pub fn is_merkle(self) -> bool {
+        self.0 != DATA_SHRED && self.0 != CODING_SHRED && (self.is_data() || self.is_code())
+    }
+
+    /// Number of entries in the Merkle proof; zero for legacy shreds
+    pub fn merkle_proof_size(self) -> usize {
+        if self.is_merkle() {
+            usize::from(self.0 & MERKLE_PROOF_SIZE_MASK)
+        } else {
+            0
+        }
+    }
+}
+
 /// A common header that is present in data and code shred headers
 #[derive(Serialize, Clone, Deserialize, Default, PartialEq, Debug)]
 pub struct ShredCommonHeader {
@@ -199,8 +269,9 @@ pub struct DataShredHeader {
 pub struct CodingShredHeader {
     pub num_data_shreds: u16,
     pub num_coding_shreds: u16,
-    #[serde(rename = "position")]
-    __unused: u16,
+    /// Position of the coding shred among the coding shreds of its erasure
+    /// batch; its index less the position is the index of the first one.
+    pub position: u16,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -323,7 +394,10 @@ impl Shred {
         // so that erasure generation/recovery works correctly
         // But only the data_header.size is stored in blockstore.
         payload.resize(SHRED_PAYLOAD_SIZE, 0);
-        let shred = if common_header.shred_type == ShredType(CODING_SHRED) {
+        if common_header.shred_type.merkle_proof_size() > MAX_MERKLE_PROOF_SIZE {
+            return Err(ShredError::InvalidShredType);
+        }
+        let shred = if common_header.shred_type.is_code() {
             let coding_header: CodingShredHeader =
                 Self::deserialize_obj(&mut start, SIZE_OF_CODING_SHRED_HEADER, &payload)?;
             Self {
@@ -332,7 +406,7 @@ impl Shred {
                 coding_header,
                 payload,
             }
-        } else if common_header.shred_type == ShredType(DATA_SHRED) {
+        } else if common_header.shred_type.is_data() {
             let data_header: DataShredHeader =
                 Self::deserialize_obj(&mut start, SIZE_OF_DATA_SHRED_HEADER, &payload)?;
             if u64::from(data_header.parent_offset) > common_header.slot {
@@ -341,6 +415,11 @@ impl Shred {
                     parent_offset: data_header.parent_offset,
                 });
             }
+            if common_header.shred_type.is_merkle()
+                && usize::from(data_header.size) > SIZE_OF_SIGNATURE + SIZE_OF_MERKLE_ERASURE_SHARD
+            {
+                return Err(ShredError::InvalidDataSize(data_header.size));
+            }
             Self {
                 common_header,
                 data_header,
@@ -360,6 +439,7 @@ impl Shred {
         fec_set_index: u32,
         num_data: usize,
         num_code: usize,
+        position: usize,
         version: u16,
     ) -> Self {
         let (header, coding_header) = Shredder::new_coding_shred_header(
@@ -368,6 +448,7 @@ impl Shred {
             fec_set_index,
             num_data,
             num_code,
+            position,
             version,
         );
         Shred::new_empty_from_header(header, DataShredHeader::default(), coding_header)
@@ -387,7 +468,7 @@ impl Shred {
             &common_header,
         )
         .expect("Failed to write header into shred buffer");
-        if common_header.shred_type == ShredType(DATA_SHRED) {
+        if common_header.shred_type.is_data() {
             Self::serialize_obj_into(
                 &mut start,
                 SIZE_OF_DATA_SHRED_HEADER,
@@ -395,7 +476,7 @@ impl Shred {
                 &data_header,
             )
             .expect("Failed to write data header into shred buffer");
-        } else if common_header.shred_type == ShredType(CODING_SHRED) {
+        } else if common_header.shred_type.is_code() {
             Self::serialize_obj_into(
                 &mut start,
                 SIZE_OF_CODING_SHRED_HEADER,
@@ -440,6 +521,27 @@ impl Shred {
         self.common_header.version
     }
 
+    /// Index of the first coding shred in the erasure batch of a coding shred.
+    pub fn first_coding_index(&self) -> Option<u32> {
+        if !self.is_code() {
+            return None;
+        }
+        let index = self.index();
+        let position = u32::from(self.coding_header.position);
+        // Coding shreds of the legacy layout are indexed from the fec_set_index
+        // of their batch, and older leaders leave their position at zero. The
+        // sequential layout never puts the first coding shred of a batch in that
+        // range, unless at the fec_set_index itself (see erasure_batch_layout).
+        match index.checked_sub(self.common_header.fec_set_index) {
+            Some(offset)
+                if position == 0 && offset < u32::from(self.coding_header.num_coding_shreds) =>
+            {
+                Some(self.common_header.fec_set_index)
+            }
+            _ => index.checked_sub(position),
+        }
+    }
+
     pub fn set_index(&mut self, index: u32) {
         self.common_header.index = index;
         Self::serialize_obj_into(
@@ -478,18 +580,62 @@ impl Shred {
             }
         }
 
+        let sig = self.common_header.signature.as_ref();
+        if self.is_merkle() {
+            // All shreds of an erasure batch carry the same signature.
+            let h = hashv(&[
+                sig,
+                &[self.common_header.shred_type.0],
+                &self.index().to_le_bytes(),
+            ]);
+            return h.to_bytes();
+        }
         let mut seed = [0; 32];
         let seed_len = seed.len();
-        let sig = self.common_header.signature.as_ref();
         seed[0..seed_len].copy_from_slice(&sig[(sig.len() - seed_len)..]);
         seed
     }
 
     pub fn is_data(&self) -> bool {
-        self.common_header.shred_type == ShredType(DATA_SHRED)
+        self.common_header.shred_type.is_data()
     }
     pub fn is_code(&self) -> bool {
-        self.common_header.shred_type == ShredType(CODING_SHRED)
+        self.common_header.shred_type.is_code()
+    }
+    pub fn is_merkle(&self) -> bool {
+        self.common_header.shred_type.is_merkle()
+    }
+
+    fn set_shred_type(&mut self, shred_type: ShredType) {
+        self.common_header.shred_type = shred_type;
+        self.payload[OFFSET_OF_SHRED_TYPE] = shred_type.0;
+    }
+
+    // The part of a Merkle shred's payload which is erasure coded
+    fn merkle_erasure_shard(&self) -> &[u8] {
+        if self.is_data() {
+            &self.payload[SIZE_OF_SIGNATURE..SIZE_OF_SIGNATURE + SIZE_OF_MERKLE_ERASURE_SHARD]
+        } else {
+            &self.payload[SIZE_OF_CODING_SHRED_HEADERS..OFFSET_OF_MERKLE_PROOF]
+        }
+    }
+
+    fn set_merkle_proof_and_signature<'a, I>(&mut self, proof: I, signature: Signature)
+    where
+        I: IntoIterator<Item = &'a Hash>,
+    {
+        self.payload[OFFSET_OF_MERKLE_PROOF..]
+            .chunks_mut(SIZE_OF_MERKLE_PROOF_ENTRY)
+            .zip(proof)
+            .for_each(|(entry, hash)| entry.copy_from_slice(hash.as_ref()));
+        self.payload[..SIZE_OF_SIGNATURE].copy_from_slice(signature.as_ref());
+        self.common_header.signature = signature;
+    }
+
+    /// Merkle root of the erasure batch this shred belongs to, recomputed from
+    /// its proof; None for legacy shreds
+    pub fn merkle_root(&self) -> Option<Hash> {
+        merkle_root_from_payload(&self.payload)
     }
 
     pub fn last_in_slot(&self) -> bool {
@@ -562,11 +708,84 @@ impl Shred {
     }
 
     pub fn verify(&self, pubkey: &Pubkey) -> bool {
+        if self.is_merkle() {
+            return match self.merkle_root() {
+                Some(root) => self.signature().verify(pubkey.as_ref(), root.as_ref()),
+                None => false,
+            };
+        }
         self.signature()
             .verify(pubkey.as_ref(), &self.payload[SIZE_OF_SIGNATURE..])
     }
 }
 
+/// Returns the total number of data and coding shreds in an erasure batch
+/// with the given number of data shreds. The last batch of a slot has at least
+/// 2 * MAX_DATA_SHREDS_PER_FEC_BLOCK shreds.
+pub fn get_erasure_batch_size(num_data_shreds: usize, is_last_in_slot: bool) -> usize {
+    let erasure_batch_size = ERASURE_BATCH_SIZE
+        .get(num_data_shreds)
+        .copied()
+        .unwrap_or(2 * num_data_shreds);
+    if is_last_in_slot {
+        erasure_batch_size.max(2 * MAX_DATA_SHREDS_PER_FEC_BLOCK as usize)
+    } else {
+        erasure_batch_size
+    }
+}
+
+// Number of entries in the Merkle proofs of an erasure batch with
+// num_shreds data and coding shreds.
+fn merkle_proof_size(num_shreds: usize) -> usize {
+    let mut proof_size = 0;
+    while (1 << proof_size) < num_shreds {
+        proof_size += 1;
+    }
+    proof_size
+}
+
+/// Recomputes the Merkle root of the erasure batch which a Merkle shred belongs
+/// to, from the shred's leaf and the proof stored in its payload. Returns None
+/// for legacy shreds or malformed payloads.
+pub fn merkle_root_from_payload(payload: &[u8]) -> Option<Hash> {
+    let shred_type = ShredType(*payload.get(OFFSET_OF_SHRED_TYPE)?);
+    let proof_size = shred_type.merkle_proof_size();
+    if !shred_type.is_merkle() || proof_size > MAX_MERKLE_PROOF_SIZE {
+        return None;
+    }
+    let proof_end = OFFSET_OF_MERKLE_PROOF + proof_size * SIZE_OF_MERKLE_PROOF_ENTRY;
+    if payload.len() < proof_end {
+        return None;
+    }
+    let index: u32 = limited_deserialize(
+        &payload[OFFSET_OF_SHRED_INDEX..OFFSET_OF_SHRED_INDEX + SIZE_OF_SHRED_INDEX],
+    )
+    .ok()?;
+    let fec_set_index: u32 = limited_deserialize(
+        &payload[OFFSET_OF_FEC_SET_INDEX..OFFSET_OF_FEC_SET_INDEX + SIZE_OF_SHRED_INDEX],
+    )
+    .ok()?;
+    // Data shreds come first in the tree, followed by the coding shreds.
+    let position = if shred_type.is_code() {
+        let coding_header: CodingShredHeader = limited_deserialize(
+            &payload[SIZE_OF_COMMON_SHRED_HEADER..SIZE_OF_CODING_SHRED_HEADERS],
+        )
+        .ok()?;
+        usize::from(coding_header.num_data_shreds) + usize::from(coding_header.position)
+    } else {
+        index.checked_sub(fec_set_index)? as usize
+    };
+    if position >= 1 << proof_size {
+        return None;
+    }
+    let leaf = hash_leaf(&payload[SIZE_OF_SIGNATURE..OFFSET_OF_MERKLE_PROOF]);
+    let proof: Vec<Hash> = payload[OFFSET_OF_MERKLE_PROOF..proof_end]
+        .chunks(SIZE_OF_MERKLE_PROOF_ENTRY)
+        .map(Hash::new)
+        .collect();
+    Some(MerkleTree::root_from_path(leaf, position, &proof))
+}
+
 fn enable_deterministic_seed(shred_slot: Slot, bank: &Bank) -> bool {
     let feature_slot = bank
         .feature_set
@@ -582,6 +801,23 @@ fn enable_deterministic_seed(shred_slot: Slot, bank: &Bank) -> bool {
     }
 }
 
+// Merkle shreds are used from the epoch after the merkle_shreds feature is
+// activated, by when every node has rooted the activation and accepts them.
+pub fn enable_merkle_shreds(shred_slot: Slot, bank: &Bank) -> bool {
+    let feature_slot = bank
+        .feature_set
+        .activated_slot(&feature_set::merkle_shreds::id());
+    match feature_slot {
+        None => false,
+        Some(feature_slot) => {
+            let epoch_schedule = bank.epoch_schedule();
+            let feature_epoch = epoch_schedule.get_epoch(feature_slot);
+            let shred_epoch = epoch_schedule.get_epoch(shred_slot);
+            feature_epoch < shred_epoch
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Shredder {
     pub slot: Slot,
@@ -612,6 +848,7 @@ impl Shredder {
         entries: &[Entry],
         is_last_in_slot: bool,
         next_shred_index: u32,
+        next_code_index: Option<u32>,
     ) -> (Vec<Shred>, Vec<Shred>, u32) {
         let mut stats = ProcessShredsStats::default();
         let (data_shreds, last_shred_index) = self.entries_to_data_shreds(
@@ -622,9 +859,41 @@ impl Shredder {
             next_shred_index, // fec_set_offset
             &mut stats,
         );
-        let coding_shreds =
-            Self::data_shreds_to_coding_shreds(keypair, &data_shreds, is_last_in_slot, &mut stats)
-                .unwrap();
+        let coding_shreds = Self::data_shreds_to_coding_shreds(
+            keypair,
+            &data_shreds,
+            is_last_in_slot,
+            next_code_index,
+            &mut stats,
+        )
+        .unwrap();
+        (data_shreds, coding_shreds, last_shred_index)
+    }
+
+    /// Same as `entries_to_shreds`, but generates Merkle shreds
+    pub fn entries_to_merkle_shreds(
+        &self,
+        keypair: &Keypair,
+        entries: &[Entry],
+        is_last_in_slot: bool,
+        next_shred_index: u32,
+        next_code_index: Option<u32>,
+    ) -> (Vec<Shred>, Vec<Shred>, u32) {
+        let mut stats = ProcessShredsStats::default();
+        let (data_shreds, last_shred_index) = self.entries_to_merkle_data_shreds(
+            entries,
+            is_last_in_slot,
+            next_shred_index,
+            next_shred_index, // fec_set_offset
+            &mut stats,
+        );
+        let (data_shreds, coding_shreds) = Self::make_merkle_shreds(
+            keypair,
+            &data_shreds,
+            is_last_in_slot,
+            next_code_index,
+            &mut stats,
+        );
         (data_shreds, coding_shreds, last_shred_index)
     }
 
@@ -647,6 +916,51 @@ impl Shredder {
         // Shred index offset at which FEC sets are generated.
         fec_set_offset: u32,
         process_stats: &mut ProcessShredsStats,
+    ) -> (Vec<Shred>, u32) {
+        self.make_data_shreds(
+            Some(keypair),
+            SIZE_OF_DATA_SHRED_PAYLOAD,
+            entries,
+            is_last_in_slot,
+            next_shred_index,
+            fec_set_offset,
+            process_stats,
+        )
+    }
+
+    /// Generates unsigned data shreds sized to fit a Merkle proof. They
+    /// are signed along with the rest of their erasure batch by
+    /// `make_merkle_shreds`.
+    pub fn entries_to_merkle_data_shreds(
+        &self,
+        entries: &[Entry],
+        is_last_in_slot: bool,
+        next_shred_index: u32,
+        // Shred index offset at which FEC sets are generated.
+        fec_set_offset: u32,
+        process_stats: &mut ProcessShredsStats,
+    ) -> (Vec<Shred>, u32) {
+        self.make_data_shreds(
+            None, // keypair
+            SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD,
+            entries,
+            is_last_in_slot,
+            next_shred_index,
+            fec_set_offset,
+            process_stats,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn make_data_shreds(
+        &self,
+        keypair: Option<&Keypair>,
+        payload_capacity: usize,
+        entries: &[Entry],
+        is_last_in_slot: bool,
+        next_shred_index: u32,
+        fec_set_offset: u32,
+        process_stats: &mut ProcessShredsStats,
     ) -> (Vec<Shred>, u32) {
         let mut serialize_time = Measure::start("shred_serialize");
         let serialized_shreds =
@@ -654,7 +968,6 @@ impl Shredder {
         serialize_time.stop();
 
         let mut gen_data_time = Measure::start("shred_gen_data_time");
-        let payload_capacity = SIZE_OF_DATA_SHRED_PAYLOAD;
         // Integer division to ensure we have enough shreds to fit all the data
         let num_shreds = (serialized_shreds.len() + payload_capacity - 1) / payload_capacity;
         let last_shred_index = next_shred_index + num_shreds as u32 - 1;
@@ -675,7 +988,9 @@ impl Shredder {
                 self.version,
                 fec_set_index.unwrap(),
             );
-            Shredder::sign_shred(keypair, &mut shred);
+            if let Some(keypair) = keypair {
+                Shredder::sign_shred(keypair, &mut shred);
+            }
             shred
         };
         let data_shreds: Vec<Shred> = PAR_THREAD_POOL.with(|thread_pool| {
@@ -698,10 +1013,15 @@ impl Shredder {
         (data_shreds, last_shred_index + 1)
     }
 
+    /// Generates and signs the coding shreds of the data shreds. With a
+    /// next_code_index, erasure batches are sized by their number of data
+    /// shreds and coding shreds are indexed sequentially from it; without one,
+    /// they follow the legacy layout.
     pub fn data_shreds_to_coding_shreds(
         keypair: &Keypair,
         data_shreds: &[Shred],
         is_last_in_slot: bool,
+        next_code_index: Option<u32>,
         process_stats: &mut ProcessShredsStats,
     ) -> Result<Vec<Shred>> {
         if data_shreds.is_empty() {
@@ -709,12 +1029,17 @@ impl Shredder {
         }
         let mut gen_coding_time = Measure::start("gen_coding_shreds");
         // 1) Generate coding shreds
+        let batches = Self::make_erasure_batches(data_shreds, is_last_in_slot, next_code_index);
         let mut coding_shreds: Vec<_> = PAR_THREAD_POOL.with(|thread_pool| {
             thread_pool.borrow().install(|| {
-                data_shreds
-                    .par_chunks(MAX_DATA_SHREDS_PER_FEC_BLOCK as usize)
-                    .flat_map(|shred_data_batch| {
-                        Shredder::generate_coding_shreds(shred_data_batch, is_last_in_slot)
+                batches
+                    .into_par_iter()
+                    .flat_map(|(shred_data_batch, first_code_index)| {
+                        Shredder::generate_coding_shreds(
+                            shred_data_batch,
+                            is_last_in_slot,
+                            first_code_index,
+                        )
                     })
                     .collect()
             })
@@ -737,6 +1062,177 @@ impl Shredder {
         Ok(coding_shreds)
     }
 
+    /// Generates the coding shreds for Merkle data shreds made by
+    /// `entries_to_merkle_data_shreds`, then signs the Merkle root of each
+    /// erasure batch and attaches to every shred its proof and the batch
+    /// signature. Returns the data shreds and the coding shreds.
+    pub fn make_merkle_shreds(
+        keypair: &Keypair,
+        data_shreds: &[Shred],
+        is_last_in_slot: bool,
+        next_code_index: Option<u32>,
+        process_stats: &mut ProcessShredsStats,
+    ) -> (Vec<Shred>, Vec<Shred>) {
+        if data_shreds.is_empty() {
+            return (Vec::default(), Vec::default());
+        }
+        let mut gen_coding_time = Measure::start("gen_merkle_shreds");
+        let batches = Self::make_erasure_batches(data_shreds, is_last_in_slot, next_code_index);
+        let batches: Vec<_> = PAR_THREAD_POOL.with(|thread_pool| {
+            thread_pool.borrow().install(|| {
+                batches
+                    .into_par_iter()
+                    .map(|(batch, first_code_index)| {
+                        Shredder::make_merkle_erasure_batch(
+                            keypair,
+                            batch,
+                            is_last_in_slot,
+                            first_code_index,
+                        )
+                    })
+                    .collect()
+            })
+        });
+        gen_coding_time.stop();
+        process_stats.gen_coding_elapsed += gen_coding_time.as_us();
+        batches.into_iter().fold(
+            (Vec::default(), Vec::default()),
+            |(mut data_shreds, mut coding_shreds), (data, coding)| {
+                data_shreds.extend(data);
+                coding_shreds.extend(coding);
+                (data_shreds, coding_shreds)
+            },
+        )
+    }
+
+    // Splits the data shreds into erasure batches of at most
+    // MAX_DATA_SHREDS_PER_FEC_BLOCK shreds, each paired with the index of its
+    // first coding shred. In the sequential layout coding shreds are indexed
+    // across the batches, since a batch may have more coding shreds than data
+    // shreds.
+    fn make_erasure_batches(
+        data_shreds: &[Shred],
+        is_last_in_slot: bool,
+        mut next_code_index: Option<u32>,
+    ) -> Vec<(&[Shred], Option<u32>)> {
+        data_shreds
+            .chunks(MAX_DATA_SHREDS_PER_FEC_BLOCK as usize)
+            .map(|batch| {
+                let fec_set_index = batch[0].common_header.fec_set_index;
+                let (first_code_index, num_coding) = Self::erasure_batch_layout(
+                    fec_set_index,
+                    batch.len(),
+                    is_last_in_slot,
+                    next_code_index,
+                );
+                let first_code_index = next_code_index.map(|_| first_code_index);
+                next_code_index = first_code_index.map(|index| index + num_coding as u32);
+                (batch, first_code_index)
+            })
+            .collect()
+    }
+
+    // Returns the index of the first coding shred of an erasure batch and the
+    // number of coding shreds in it. In the legacy layout, coding shreds are
+    // indexed from the fec_set_index and the batch holds as many coding shreds
+    // as data shreds, but for the last batch in the slot. In the sequential
+    // layout, the batch is sized by get_erasure_batch_size and its coding
+    // shreds are indexed from next_code_index, skipping past the indices which
+    // first_coding_index would read as those of a legacy batch.
+    fn erasure_batch_layout(
+        fec_set_index: u32,
+        num_data: usize,
+        is_last_in_slot: bool,
+        next_code_index: Option<u32>,
+    ) -> (u32, usize) {
+        match next_code_index {
+            None => {
+                let num_coding = if is_last_in_slot {
+                    (2 * MAX_DATA_SHREDS_PER_FEC_BLOCK as usize)
+                        .saturating_sub(num_data)
+                        .max(num_data)
+                } else {
+                    num_data
+                };
+                (fec_set_index, num_coding)
+            }
+            Some(next_code_index) => {
+                let num_coding = Self::num_coding_shreds(num_data, is_last_in_slot);
+                let legacy_indices = fec_set_index + 1..fec_set_index + num_coding as u32;
+                if legacy_indices.contains(&next_code_index) {
+                    (legacy_indices.end, num_coding)
+                } else {
+                    (next_code_index, num_coding)
+                }
+            }
+        }
+    }
+
+    fn make_merkle_erasure_batch(
+        keypair: &Keypair,
+        data: &[Shred],
+        is_last_in_slot: bool,
+        next_code_index: Option<u32>,
+    ) -> (Vec<Shred>, Vec<Shred>) {
+        let ShredCommonHeader {
+            slot,
+            index,
+            version,
+            fec_set_index,
+            ..
+        } = data.first().unwrap().common_header;
+        assert_eq!(fec_set_index, index);
+        assert!(data.iter().all(|shred| shred.common_header.slot == slot
+            && shred.common_header.version == version
+            && shred.common_header.fec_set_index == fec_set_index));
+        let num_data = data.len();
+        let (first_code_index, num_coding) =
+            Self::erasure_batch_layout(fec_set_index, num_data, is_last_in_slot, next_code_index);
+        let proof_size = merkle_proof_size(num_data + num_coding);
+        let mut data = data.to_vec();
+        for shred in data.iter_mut() {
+            shred.set_shred_type(ShredType::merkle_data(proof_size));
+        }
+        let shards: Vec<_> = data.iter().map(Shred::merkle_erasure_shard).collect();
+        let mut parity = vec![vec![0u8; SIZE_OF_MERKLE_ERASURE_SHARD]; num_coding];
+        Session::new(num_data, num_coding)
+            .unwrap()
+            .encode(&shards, &mut parity[..])
+            .unwrap();
+        let mut coding: Vec<_> = parity
+            .iter()
+            .enumerate()
+            .map(|(i, parity)| {
+                let mut shred = Shred::new_empty_coding(
+                    slot,
+                    first_code_index + i as u32, // shred index
+                    fec_set_index,
+                    num_data,
+                    num_coding,
+                    i, // position
+                    version,
+                );
+                shred.set_shred_type(ShredType::merkle_coding(proof_size));
+                shred.payload[SIZE_OF_CODING_SHRED_HEADERS..OFFSET_OF_MERKLE_PROOF]
+                    .copy_from_slice(parity);
+                shred
+            })
+            .collect();
+        let leaves: Vec<_> = data
+            .iter()
+            .chain(&coding)
+            .map(|shred| &shred.payload[SIZE_OF_SIGNATURE..OFFSET_OF_MERKLE_PROOF])
+            .collect();
+        let tree = MerkleTree::new(&leaves);
+        let signature = keypair.sign_message(tree.get_root().unwrap().as_ref());
+        for (position, shred) in data.iter_mut().chain(coding.iter_mut()).enumerate() {
+            let proof = tree.find_path(position).unwrap();
+            let proof = proof.entries().iter().map(|entry| entry.sibling());
+            shred.set_merkle_proof_and_signature(proof, signature);
+        }
+        (data, coding)
+    }
+
     pub fn sign_shred(signer: &Keypair, shred: &mut Shred) {
         let signature = signer.sign_message(&shred.payload[SIZE_OF_SIGNATURE..]);
         bincode::serialize_into(&mut shred.payload[..SIZE_OF_SIGNATURE], &signature)
@@ -750,6 +1246,7 @@ impl Shredder {
         fec_set_index: u32,
         num_data: usize,
         num_code: usize,
+        position: usize,
         version: u16,
     ) -> (ShredCommonHeader, CodingShredHeader) {
         let header = ShredCommonHeader {
@@ -765,13 +1262,17 @@ impl Shredder {
             CodingShredHeader {
                 num_data_shreds: num_data as u16,
                 num_coding_shreds: num_code as u16,
-                ..CodingShredHeader::default()
+                position: position as u16,
             },
         )
     }
 
     /// Generates coding shreds for the data shreds in the current FEC set
-    pub fn generate_coding_shreds(data: &[Shred], is_last_in_slot: bool) -> Vec<Shred> {
+    pub fn generate_coding_shreds(
+        data: &[Shred],
+        is_last_in_slot: bool,
+        next_code_index: Option<u32>,
+    ) -> Vec<Shred> {
         const PAYLOAD_ENCODE_SIZE: usize = SHRED_PAYLOAD_SIZE - SIZE_OF_CODING_SHRED_HEADERS;
         let ShredCommonHeader {
             slot,
@@ -785,13 +1286,8 @@ impl Shredder {
             && shred.common_header.version == version
             && shred.common_header.fec_set_index == fec_set_index));
         let num_data = data.len();
-        let num_coding = if is_last_in_slot {
-            (2 * MAX_DATA_SHREDS_PER_FEC_BLOCK as usize)
-                .saturating_sub(num_data)
-                .max(num_data)
-        } else {
-            num_data
-        };
+        let (first_code_index, num_coding) =
+            Self::erasure_batch_layout(fec_set_index, num_data, is_last_in_slot, next_code_index);
         let data: Vec<_> = data
             .iter()
             .map(|shred| &shred.payload[..PAYLOAD_ENCODE_SIZE])
@@ -807,10 +1303,11 @@ impl Shredder {
             .map(|(i, parity)| {
                 let mut shred = Shred::new_empty_coding(
                     slot,
-                    fec_set_index + i as u32, // shred index
+                    first_code_index + i as u32, // shred index
                     fec_set_index,
                     num_data,
                     num_coding,
+                    i, // position
                     version,
                 );
                 shred.payload[SIZE_OF_CODING_SHRED_HEADERS..].copy_from_slice(parity);
@@ -819,129 +1316,220 @@ impl Shredder {
             .collect()
     }
 
-    fn fill_in_missing_shreds(
+    fn num_coding_shreds(num_data: usize, is_last_in_slot: bool) -> usize {
+        get_erasure_batch_size(num_data, is_last_in_slot) - num_data
+    }
+
+    pub fn try_recovery(
+        shreds: Vec<Shred>,
         num_data: usize,
         num_coding: usize,
-        first_index_in_fec_set: usize,
-        expected_index: usize,
-        index_found: usize,
-        present: &mut [bool],
-    ) -> Vec<Vec<u8>> {
-        let end_index = index_found.saturating_sub(1);
-        // The index of current shred must be within the range of shreds that are being
-        // recovered
-        if !(first_index_in_fec_set..first_index_in_fec_set + num_data + num_coding)
-            .contains(&end_index)
-        {
-            return vec![];
+        first_index: usize,
+        first_code_index: usize,
+        slot: Slot,
+    ) -> std::result::Result<Vec<Shred>, reed_solomon_erasure::Error> {
+        Self::verify_consistent_shred_payload_sizes("try_recovery()", &shreds)?;
+        if shreds[0].is_merkle() {
+            return Self::try_merkle_recovery(
+                shreds,
+                num_data,
+                num_coding,
+                first_index,
+                first_code_index,
+                slot,
+            );
         }
+        let fec_set_size = num_data + num_coding;
+        if num_coding == 0 || shreds.len() >= fec_set_size {
+            return Ok(Vec::default());
+        }
+        // Place each shred at its position in the erasure batch, so that any
+        // subset of the data and coding shreds can be used for recovery.
+        let mut batch: Vec<Option<Vec<u8>>> = vec![None; fec_set_size];
+        for shred in shreds {
+            let index = shred.index() as usize;
+            let position = if shred.is_data() {
+                match index.checked_sub(first_index) {
+                    Some(offset) if offset < num_data => offset,
+                    _ => continue,
+                }
+            } else {
+                match index.checked_sub(first_code_index) {
+                    Some(offset) if offset < num_coding => num_data + offset,
+                    _ => continue,
+                }
+            };
+            batch[position] = Some(shred.payload);
+        }
+        let present: Vec<_> = batch.iter().map(Option::is_some).collect();
+        let mut shred_bufs: Vec<Vec<u8>> = batch
+            .into_iter()
+            .enumerate()
+            .map(|(position, payload)| {
+                payload.unwrap_or_else(|| {
+                    if position < num_data {
+                        Shred::new_empty_data_shred().payload
+                    } else {
+                        vec![0; SHRED_PAYLOAD_SIZE]
+                    }
+                })
+            })
+            .collect();
 
-        let missing_blocks: Vec<Vec<u8>> = (expected_index..index_found)
-            .map(|missing| {
-                present[missing.saturating_sub(first_index_in_fec_set)] = false;
-                if missing < first_index_in_fec_set + num_data {
-                    Shred::new_empty_data_shred().payload
+        let session = Session::new(num_data, num_coding)?;
+
+        // All information (excluding the restricted section) from a data shred is encoded
+        let valid_data_len = SHRED_PAYLOAD_SIZE - SIZE_OF_CODING_SHRED_HEADERS;
+        let coding_block_offset = SIZE_OF_CODING_SHRED_HEADERS;
+        let mut blocks: Vec<(&mut [u8], bool)> = shred_bufs
+            .iter_mut()
+            .enumerate()
+            .map(|(position, x)| {
+                if position < num_data {
+                    x[..valid_data_len].as_mut()
                 } else {
-                    vec![0; SHRED_PAYLOAD_SIZE]
+                    x[coding_block_offset..].as_mut()
                 }
             })
+            .zip(present.iter().copied())
             .collect();
-        missing_blocks
+        session.decode_blocks(&mut blocks)?;
+
+        let recovered_data = shred_bufs
+            .into_iter()
+            .zip(present)
+            .take(num_data)
+            .enumerate()
+            .filter(|(_, (_, was_present))| !was_present)
+            .filter_map(|(position, (shred_buf, _))| {
+                let shred = Shred::new_from_serialized_shred(shred_buf).ok()?;
+                // A valid data shred must be in the same slot as the original
+                // shreds, and at its position in the erasure batch
+                if shred.slot() == slot
+                    && shred.is_data()
+                    && shred.index() as usize == first_index + position
+                {
+                    Some(shred)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        Ok(recovered_data)
     }
 
-    pub fn try_recovery(
+    // Recovers the missing shreds of a Merkle erasure batch, including the
+    // coding ones, so that the Merkle tree of the batch can be rebuilt and the
+    // recovered data shreds get back their proofs and the batch signature.
+    fn try_merkle_recovery(
         shreds: Vec<Shred>,
         num_data: usize,
         num_coding: usize,
         first_index: usize,
+        first_code_index: usize,
         slot: Slot,
     ) -> std::result::Result<Vec<Shred>, reed_solomon_erasure::Error> {
-        Self::verify_consistent_shred_payload_sizes("try_recovery()", &shreds)?;
-        let mut recovered_data = vec![];
         let fec_set_size = num_data + num_coding;
-
-        if num_coding > 0 && shreds.len() < fec_set_size {
-            // Let's try recovering missing shreds using erasure
-            let mut present = &mut vec![true; fec_set_size];
-            let mut next_expected_index = first_index;
-            let mut shred_bufs: Vec<Vec<u8>> = shreds
-                .into_iter()
-                .flat_map(|shred| {
-                    let offset = if shred.is_data() { 0 } else { num_data };
-                    let index = offset + shred.index() as usize;
-                    let mut blocks = Self::fill_in_missing_shreds(
+        if num_coding == 0 || shreds.len() >= fec_set_size {
+            return Ok(Vec::default());
+        }
+        let proof_size = merkle_proof_size(fec_set_size);
+        let mut batch: Vec<Option<Shred>> = vec![None; fec_set_size];
+        for shred in shreds {
+            let index = shred.index() as usize;
+            let position = if shred.is_data() {
+                match index.checked_sub(first_index) {
+                    Some(offset) if offset < num_data => offset,
+                    _ => continue,
+                }
+            } else {
+                match index.checked_sub(first_code_index) {
+                    Some(offset) if offset < num_coding => num_data + offset,
+                    _ => continue,
+                }
+            };
+            if shred.slot() == slot
+                && shred.common_header.shred_type.merkle_proof_size() == proof_size
+                && shred.common_header.fec_set_index as usize == first_index
+            {
+                batch[position] = Some(shred);
+            }
+        }
+        let (version, signature) = match batch.iter().flatten().next() {
+            None => return Err(reed_solomon_erasure::Error::TooFewShardsPresent),
+            Some(shred) => (shred.version(), shred.signature()),
+        };
+        let mut shards: Vec<Vec<u8>> = batch
+            .iter()
+            .map(|shred| match shred {
+                Some(shred) => shred.merkle_erasure_shard().to_vec(),
+                None => vec![0u8; SIZE_OF_MERKLE_ERASURE_SHARD],
+            })
+            .collect();
+        let mut blocks: Vec<(&mut [u8], bool)> = shards
+            .iter_mut()
+            .map(Vec::as_mut_slice)
+            .zip(batch.iter().map(Option::is_some))
+            .collect();
+        Session::new(num_data, num_coding)?.reconstruct_blocks(&mut blocks)?;
+        // Rebuild the payloads of the missing shreds, except for the
+        // signature and the proof.
+        let payloads: Vec<_> = batch
+            .into_iter()
+            .zip(shards)
+            .enumerate()
+            .map(|(position, (shred, shard))| match shred {
+                Some(shred) => (false, shred.payload),
+                None if position < num_data => {
+                    let mut payload = vec![0u8; SHRED_PAYLOAD_SIZE];
+                    payload[SIZE_OF_SIGNATURE..SIZE_OF_SIGNATURE + SIZE_OF_MERKLE_ERASURE_SHARD]
+                        .copy_from_slice(&shard);
+                    (true, payload)
+                }
+                None => {
+                    let mut shred = Shred::new_empty_coding(
+                        slot,
+                        (first_code_index + position - num_data) as u32,
+                        first_index as u32,
                         num_data,
                         num_coding,
-                        first_index,
-                        next_expected_index,
-                        index,
-                        &mut present,
+                        position - num_data,
+                        version,
                     );
-                    blocks.push(shred.payload);
-                    next_expected_index = index + 1;
-                    blocks
-                })
-                .collect();
-
-            // Insert any other missing shreds after the last shred we have received in the
-            // current FEC block
-            let mut pending_shreds = Self::fill_in_missing_shreds(
-                num_data,
-                num_coding,
-                first_index,
-                next_expected_index,
-                first_index + fec_set_size,
-                &mut present,
-            );
-
-            shred_bufs.append(&mut pending_shreds);
-
-            if shred_bufs.len() != fec_set_size {
-                return Err(reed_solomon_erasure::Error::TooFewShardsPresent);
-            }
-
-            let session = Session::new(num_data, num_coding)?;
-
-            // All information (excluding the restricted section) from a data shred is encoded
-            let valid_data_len = SHRED_PAYLOAD_SIZE - SIZE_OF_CODING_SHRED_HEADERS;
-            let coding_block_offset = SIZE_OF_CODING_SHRED_HEADERS;
-            let mut blocks: Vec<(&mut [u8], bool)> = shred_bufs
-                .iter_mut()
-                .enumerate()
-                .map(|(position, x)| {
-                    if position < num_data {
-                        x[..valid_data_len].as_mut()
-                    } else {
-                        x[coding_block_offset..].as_mut()
-                    }
-                })
-                .zip(present.clone())
-                .collect();
-            session.decode_blocks(&mut blocks)?;
-
-            let mut num_drained = 0;
-            present
-                .iter()
-                .enumerate()
-                .for_each(|(position, was_present)| {
-                    if !*was_present && position < num_data {
-                        let drain_this = position - num_drained;
-                        let shred_buf = shred_bufs.remove(drain_this);
-                        num_drained += 1;
-                        if let Ok(shred) = Shred::new_from_serialized_shred(shred_buf) {
-                            let shred_index = shred.index() as usize;
-                            // Valid shred must be in the same slot as the original shreds
-                            if shred.slot() == slot {
-                                // A valid data shred must be indexed between first_index and first+num_data index
-                                if (first_index..first_index + num_data).contains(&shred_index) {
-                                    recovered_data.push(shred)
-                                }
-                            }
-                        }
-                    }
-                });
-        }
-
+                    shred.set_shred_type(ShredType::merkle_coding(proof_size));
+                    shred.payload[SIZE_OF_CODING_SHRED_HEADERS..OFFSET_OF_MERKLE_PROOF]
+                        .copy_from_slice(&shard);
+                    (true, shred.payload)
+                }
+            })
+            .collect();
+        let leaves: Vec<_> = payloads
+            .iter()
+            .map(|(_, payload)| &payload[SIZE_OF_SIGNATURE..OFFSET_OF_MERKLE_PROOF])
+            .collect();
+        let tree = MerkleTree::new(&leaves);
+        let recovered_data = payloads
+            .iter()
+            .take(num_data)
+            .enumerate()
+            .filter(|(_, (recovered, _))| *recovered)
+            .filter_map(|(position, (_, payload))| {
+                let mut shred = Shred::new_from_serialized_shred(payload.clone()).ok()?;
+                let proof = tree.find_path(position)?;
+                let proof = proof.entries().iter().map(|entry| entry.sibling());
+                shred.set_merkle_proof_and_signature(proof, signature);
+                // The recovered shred must be at its position in the batch;
+                // its signature is checked by the caller.
+                if shred.slot() == slot
+                    && shred.is_data()
+                    && shred.index() as usize == first_index + position
+                {
+                    Some(shred)
+                } else {
+                    None
+                }
+            })
+            .collect();
         Ok(recovered_data)
     }
 
@@ -1014,6 +1602,7 @@ pub struct ShredFetchStats {
     pub duplicate_shred: usize,
     pub slot_out_of_range: usize,
     pub bad_shred_type: usize,
+    pub merkle_shred_not_enabled: usize,
 }
 
 // Get slot, index, and type from a packet with partial deserialize
@@ -1059,9 +1648,9 @@ pub fn get_shred_slot_index_type(
         }
     }
 
-    let shred_type = p.data[OFFSET_OF_SHRED_TYPE];
-    if shred_type == DATA_SHRED || shred_type == CODING_SHRED {
-        return Some((slot, index, shred_type == DATA_SHRED));
+    let shred_type = ShredType(p.data[OFFSET_OF_SHRED_TYPE]);
+    if shred_type.is_data() || shred_type.is_code() {
+        return Some((slot, index, shred_type.is_data()));
     } else {
         stats.bad_shred_type += 1;
     }
@@ -1215,7 +1804,7 @@ pub mod tests {
             .max(num_expected_data_shreds as usize);
         let start_index = 0;
         let (data_shreds, coding_shreds, next_index) =
-            shredder.entries_to_shreds(&keypair, &entries, true, start_index);
+            shredder.entries_to_shreds(&keypair, &entries, true, start_index, Some(start_index));
         assert_eq!(next_index as u64, num_expected_data_shreds);
 
         let mut data_shred_indexes = HashSet::new();
@@ -1284,7 +1873,9 @@ pub mod tests {
             })
             .collect();
 
-        let data_shreds = shredder.entries_to_shreds(&keypair, &entries, true, 0).0;
+        let data_shreds = shredder
+            .entries_to_shreds(&keypair, &entries, true, 0, Some(0))
+            .0;
 
         let deserialized_shred =
             Shred::new_from_serialized_shred(data_shreds.last().unwrap().payload.clone()).unwrap();
@@ -1307,7 +1898,9 @@ pub mod tests {
             })
             .collect();
 
-        let data_shreds = shredder.entries_to_shreds(&keypair, &entries, true, 0).0;
+        let data_shreds = shredder
+            .entries_to_shreds(&keypair, &entries, true, 0, Some(0))
+            .0;
         data_shreds.iter().for_each(|s| {
             assert_eq!(s.reference_tick(), 5);
             assert_eq!(Shred::reference_tick_from_data(&s.payload), 5);
@@ -1334,7 +1927,9 @@ pub mod tests {
             })
             .collect();
 
-        let data_shreds = shredder.entries_to_shreds(&keypair, &entries, true, 0).0;
+        let data_shreds = shredder
+            .entries_to_shreds(&keypair, &entries, true, 0, Some(0))
+            .0;
         data_shreds.iter().for_each(|s| {
             assert_eq!(s.reference_tick(), SHRED_TICK_REFERENCE_MASK);
             assert_eq!(
@@ -1368,7 +1963,7 @@ pub mod tests {
             .collect();
 
         let (data_shreds, coding_shreds, _) =
-            shredder.entries_to_shreds(&keypair, &entries, true, 0);
+            shredder.entries_to_shreds(&keypair, &entries, true, 0, Some(0));
 
         for (i, s) in data_shreds.iter().enumerate() {
             verify_test_data_shred(
@@ -1420,20 +2015,25 @@ pub mod tests {
             &keypair,
             &entries,
             is_last_in_slot,
-            0, // next_shred_index
+            0,       // next_shred_index
+            Some(0), // next_code_index
         );
         let num_coding_shreds = coding_shreds.len();
 
         // We should have 5 data shreds now
         assert_eq!(data_shreds.len(), num_data_shreds);
+        assert_eq!(
+            num_data_shreds + num_coding_shreds,
+            get_erasure_batch_size(num_data_shreds, is_last_in_slot)
+        );
         if is_last_in_slot {
             assert_eq!(
                 num_coding_shreds,
                 2 * MAX_DATA_SHREDS_PER_FEC_BLOCK as usize - num_data_shreds
             );
         } else {
-            // and an equal number of coding shreds
-            assert_eq!(num_data_shreds, num_coding_shreds);
+            // Small batches get more coding shreds than data shreds
+            assert!(num_coding_shreds > num_data_shreds);
         }
 
         let all_shreds = data_shreds
@@ -1448,7 +2048,8 @@ pub mod tests {
                 data_shreds[..data_shreds.len() - 1].to_vec(),
                 num_data_shreds,
                 num_coding_shreds,
-                0,
+                0, // first_index
+                0, // first_code_index
                 slot
             ),
             Err(reed_solomon_erasure::Error::TooFewShardsPresent)
@@ -1459,7 +2060,8 @@ pub mod tests {
             data_shreds[..].to_vec(),
             num_data_shreds,
             num_coding_shreds,
-            0,
+            0, // first_index
+            0, // first_code_index
             slot,
         )
         .unwrap();
@@ -1476,7 +2078,8 @@ pub mod tests {
             shred_info.clone(),
             num_data_shreds,
             num_coding_shreds,
-            0,
+            0, // first_index
+            0, // first_code_index
             slot,
         )
         .unwrap();
@@ -1523,7 +2126,8 @@ pub mod tests {
             shred_info.clone(),
             num_data_shreds,
             num_coding_shreds,
-            0,
+            0, // first_index
+            0, // first_code_index
             slot,
         )
         .unwrap();
@@ -1575,7 +2179,7 @@ pub mod tests {
         // and 2 missing coding shreds. Hint: should work
         let serialized_entries = bincode::serialize(&entries).unwrap();
         let (data_shreds, coding_shreds, _) =
-            shredder.entries_to_shreds(&keypair, &entries, true, 25);
+            shredder.entries_to_shreds(&keypair, &entries, true, 25, Some(25));
         let num_coding_shreds = coding_shreds.len();
         // We should have 10 shreds now
         assert_eq!(data_shreds.len(), num_data_shreds);
@@ -1596,7 +2200,8 @@ pub mod tests {
             shred_info.clone(),
             num_data_shreds,
             num_coding_shreds,
-            25,
+            25, // first_index
+            25, // first_code_index
             slot,
         )
         .unwrap();
@@ -1627,29 +2232,36 @@ pub mod tests {
             shred_info.clone(),
             num_data_shreds,
             num_coding_shreds,
-            25,
+            25, // first_index
+            25, // first_code_index
             slot + 1,
         )
         .unwrap();
         assert!(recovered_data.is_empty());
 
         // Test7: Try recovery/reassembly with incorrect index. Hint: does not recover any shreds
-        assert_matches!(
-            Shredder::try_recovery(
-                shred_info.clone(),
-                num_data_shreds,
-                num_coding_shreds,
-                15,
-                slot,
-            ),
-            Err(reed_solomon_erasure::Error::TooFewShardsPresent)
-        );
+        let recovered_data = Shredder::try_recovery(
+            shred_info.clone(),
+            num_data_shreds,
+            num_coding_shreds,
+            15, // first_index
+            25, // first_code_index
+            slot,
+        )
+        .unwrap_or_default();
+        assert!(recovered_data.is_empty());
 
         // Test8: Try recovery/reassembly with incorrect index. Hint: does not recover any shreds
-        assert_matches!(
-            Shredder::try_recovery(shred_info, num_data_shreds, num_coding_shreds, 35, slot),
-            Err(reed_solomon_erasure::Error::TooFewShardsPresent)
-        );
+        let recovered_data = Shredder::try_recovery(
+            shred_info,
+            num_data_shreds,
+            num_coding_shreds,
+            35, // first_index
+            35, // first_code_index
+            slot,
+        )
+        .unwrap_or_default();
+        assert!(recovered_data.is_empty());
     }
 
     #[test]
@@ -1685,21 +2297,25 @@ pub mod tests {
         )
         .unwrap();
         let next_shred_index = rng.gen_range(1, 1024);
-        let (data_shreds, coding_shreds, _) =
-            shredder.entries_to_shreds(&keypair, &[entry], is_last_in_slot, next_shred_index);
+        let next_code_index = rng.gen_range(1, 1024);
+        let (data_shreds, coding_shreds, _) = shredder.entries_to_shreds(
+            &keypair,
+            &[entry],
+            is_last_in_slot,
+            next_shred_index,
+            Some(next_code_index),
+        );
         let num_data_shreds = data_shreds.len();
         let num_coding_shreds = coding_shreds.len();
+        let first_code_index = coding_shreds[0].first_coding_index().unwrap();
+        assert!(coding_shreds
+            .iter()
+            .all(|shred| shred.first_coding_index() == Some(first_code_index)));
+        // Recovery works with any subset of the shreds, in any order.
         let mut shreds = coding_shreds;
         shreds.extend(data_shreds.iter().cloned());
         shreds.shuffle(&mut rng);
         shreds.truncate(num_data_shreds);
-        shreds.sort_by_key(|shred| {
-            if shred.is_data() {
-                shred.index()
-            } else {
-                shred.index() + num_data_shreds as u32
-            }
-        });
         let exclude: HashSet<_> = shreds
             .iter()
             .filter(|shred| shred.is_data())
@@ -1710,6 +2326,7 @@ pub mod tests {
             num_data_shreds,
             num_coding_shreds,
             next_shred_index as usize, // first index
+            first_code_index as usize, // first code index
             slot,
         )
         .unwrap();
@@ -1748,7 +2365,7 @@ pub mod tests {
             .collect();
 
         let (data_shreds, coding_shreds, _next_index) =
-            shredder.entries_to_shreds(&keypair, &entries, true, 0);
+            shredder.entries_to_shreds(&keypair, &entries, true, 0, Some(0));
         assert!(!data_shreds
             .iter()
             .chain(coding_shreds.iter())
@@ -1797,7 +2414,7 @@ pub mod tests {
 
         let start_index = 0x12;
         let (data_shreds, coding_shreds, _next_index) =
-            shredder.entries_to_shreds(&keypair, &entries, true, start_index);
+            shredder.entries_to_shreds(&keypair, &entries, true, start_index, Some(start_index));
 
         let max_per_block = MAX_DATA_SHREDS_PER_FEC_BLOCK as usize;
         data_shreds.iter().enumerate().for_each(|(i, s)| {
@@ -1811,9 +2428,88 @@ pub mod tests {
                 expected_fec_set_index -= max_per_block as u32;
             }
             assert_eq!(s.common_header.fec_set_index, expected_fec_set_index);
+            // Coding shreds are indexed sequentially across erasure batches.
+            assert_eq!(s.index(), start_index + i as u32);
         });
     }
 
+    #[test]
+    fn test_erasure_batch_layouts() {
+        let keypair = Arc::new(Keypair::new());
+        let shredder = Shredder::new(0, 0, 0, 0).unwrap();
+        let entries: Vec<_> = (0..500)
+            .map(|_| {
+                let keypair0 = Keypair::new();
+                let keypair1 = Keypair::new();
+                let tx0 =
+                    system_transaction::transfer(&keypair0, &keypair1.pubkey(), 1, Hash::default());
+                Entry::new(&Hash::default(), 1, vec![tx0])
+            })
+            .collect();
+        let mut stats = ProcessShredsStats::default();
+        let start_index = 0x12;
+        let (data_shreds, _next_index) = shredder.entries_to_data_shreds(
+            &keypair,
+            &entries,
+            false, // is_last_in_slot
+            start_index,
+            start_index, // fec_set_offset
+            &mut stats,
+        );
+        let max_per_block = MAX_DATA_SHREDS_PER_FEC_BLOCK as usize;
+        let data_shreds = &data_shreds[..max_per_block + 5];
+
+        // The legacy layout indexes coding shreds from the fec_set_index of
+        // their batch, with as many coding shreds as data shreds.
+        let coding_shreds = Shredder::data_shreds_to_coding_shreds(
+            &keypair,
+            data_shreds,
+            false, // is_last_in_slot
+            None,  // next_code_index
+            &mut stats,
+        )
+        .unwrap();
+        assert_eq!(coding_shreds.len(), data_shreds.len());
+        for (i, mut shred) in coding_shreds.into_iter().enumerate() {
+            let fec_set_index = start_index + (i - i % max_per_block) as u32;
+            assert_eq!(shred.index(), start_index + i as u32);
+            assert_eq!(shred.first_coding_index(), Some(fec_set_index));
+            // Older leaders leave the position at zero.
+            shred.coding_header.position = 0;
+            assert_eq!(shred.first_coding_index(), Some(fec_set_index));
+        }
+
+        // The sequential layout skips past the indices of a legacy batch, so
+        // that its first coding shred does not read as a legacy one.
+        let coding_shreds = Shredder::data_shreds_to_coding_shreds(
+            &keypair,
+            &data_shreds[max_per_block..],
+            false,                  // is_last_in_slot
+            Some(start_index + 40), // next_code_index
+            &mut stats,
+        )
+        .unwrap();
+        let fec_set_index = start_index + max_per_block as u32;
+        let num_coding = ERASURE_BATCH_SIZE[5] - 5;
+        assert_eq!(coding_shreds.len(), num_coding);
+        let first_code_index = fec_set_index + num_coding as u32;
+        for (i, shred) in coding_shreds.iter().enumerate() {
+            assert_eq!(shred.index(), first_code_index + i as u32);
+            assert_eq!(shred.first_coding_index(), Some(first_code_index));
+        }
+        let coding_shreds = Shredder::data_shreds_to_coding_shreds(
+            &keypair,
+            &data_shreds[max_per_block..],
+            false,                  // is_last_in_slot
+            Some(start_index + 90), // next_code_index
+            &mut stats,
+        )
+        .unwrap();
+        assert!(coding_shreds
+            .iter()
+            .all(|shred| shred.first_coding_index() == Some(start_index + 90)));
+    }
+
     #[test]
     fn test_max_coding_shreds() {
         let keypair = Arc::new(Keypair::new());
@@ -1849,14 +2545,17 @@ pub mod tests {
                 &keypair,
                 &data_shreds[..count],
                 false, // is_last_in_slot
+                Some(start_index),
                 &mut stats,
             )
             .unwrap();
-            assert_eq!(coding_shreds.len(), count);
+            assert_eq!(coding_shreds.len(), ERASURE_BATCH_SIZE[count] - count);
+            assert!(coding_shreds.len() >= count);
             let coding_shreds = Shredder::data_shreds_to_coding_shreds(
                 &keypair,
                 &data_shreds[..count],
                 true, // is_last_in_slot
+                Some(start_index),
                 &mut stats,
             )
             .unwrap();
@@ -1870,17 +2569,19 @@ pub mod tests {
             &keypair,
             &data_shreds[..MAX_DATA_SHREDS_PER_FEC_BLOCK as usize + 1],
             false, // is_last_in_slot
+            Some(start_index),
             &mut stats,
         )
         .unwrap();
         assert_eq!(
             coding_shreds.len(),
-            MAX_DATA_SHREDS_PER_FEC_BLOCK as usize + 1
+            MAX_DATA_SHREDS_PER_FEC_BLOCK as usize + ERASURE_BATCH_SIZE[1] - 1
         );
         let coding_shreds = Shredder::data_shreds_to_coding_shreds(
             &keypair,
             &data_shreds[..MAX_DATA_SHREDS_PER_FEC_BLOCK as usize + 1],
             true, // is_last_in_slot
+            Some(start_index),
             &mut stats,
         )
         .unwrap();
@@ -1939,7 +2640,7 @@ pub mod tests {
         );
         assert_eq!(stats.index_overrun, 4);
 
-        let shred = Shred::new_empty_coding(8, 2, 10, 30, 4, 200);
+        let shred = Shred::new_empty_coding(8, 2, 10, 30, 4, 1, 200);
         shred.copy_to_packet(&mut packet);
         assert_eq!(
             Some((8, 2, false)),
@@ -1951,7 +2652,8 @@ pub mod tests {
         assert_eq!(None, get_shred_slot_index_type(&packet, &mut stats));
         assert_eq!(1, stats.index_out_of_bounds);
 
-        let (mut header, coding_header) = Shredder::new_coding_shred_header(8, 2, 10, 30, 4, 200);
+        let (mut header, coding_header) =
+            Shredder::new_coding_shred_header(8, 2, 10, 30, 4, 1, 200);
         header.shred_type = ShredType(u8::MAX);
         let shred = Shred::new_empty_from_header(header, DataShredHeader::default(), coding_header);
         shred.copy_to_packet(&mut packet);
@@ -1959,4 +2661,147 @@ pub mod tests {
         assert_eq!(None, get_shred_slot_index_type(&packet, &mut stats));
         assert_eq!(1, stats.bad_shred_type);
     }
+
+    #[test]
+    fn test_merkle_shred_type() {
+        assert_eq!(
+            merkle_proof_size(2 * MAX_DATA_SHREDS_PER_FEC_BLOCK as usize),
+            MAX_MERKLE_PROOF_SIZE
+        );
+        assert_eq!(merkle_proof_size(1), 0);
+        assert_eq!(merkle_proof_size(3), 2);
+        assert!(
+            MAX_MERKLE_PROOF_SIZE <= MERKLE_PROOF_SIZE_MASK as usize
+                && SIZE_OF_MERKLE_DATA_SHRED_PAYLOAD < SIZE_OF_DATA_SHRED_PAYLOAD
+        );
+        for proof_size in 0..=MAX_MERKLE_PROOF_SIZE {
+            let shred_type = ShredType::merkle_data(proof_size);
+            assert!(shred_type.is_data() && !shred_type.is_code() && shred_type.is_merkle());
+            assert_eq!(shred_type.merkle_proof_size(), proof_size);
+            let shred_type = ShredType::merkle_coding(proof_size);
+            assert!(!shred_type.is_data() && shred_type.is_code() && shred_type.is_merkle());
+            assert_eq!(shred_type.merkle_proof_size(), proof_size);
+        }
+        for shred_type in [ShredType(DATA_SHRED), ShredType(CODING_SHRED)].iter() {
+            assert!(!shred_type.is_merkle());
+            assert_eq!(shred_type.merkle_proof_size(), 0);
+        }
+    }
+
+    fn make_test_entries(num_entries: usize) -> Vec<Entry> {
+        repeat_with(|| {
+            let keypair0 = Keypair::new();
+            let keypair1 = Keypair::new();
+            let tx0 =
+                system_transaction::transfer(&keypair0, &keypair1.pubkey(), 1, Hash::default());
+            Entry::new(&Hash::default(), 1, vec![tx0])
+        })
+        .take(num_entries)
+        .collect()
+    }
+
+    fn run_test_merkle_shreds(is_last_in_slot: bool) {
+        let keypair = Keypair::new();
+        let slot = 0x1234_5678_9abc_def0;
+        let shredder = Shredder::new(slot, slot - 5, 0, 0).unwrap();
+        let entries = make_test_entries(200);
+        let (data_shreds, coding_shreds, next_shred_index) =
+            shredder.entries_to_merkle_shreds(&keypair, &entries, is_last_in_slot, 3, Some(3));
+        assert_eq!(next_shred_index, 3 + data_shreds.len() as u32);
+        assert!(data_shreds.len() > MAX_DATA_SHREDS_PER_FEC_BLOCK as usize);
+        assert!(coding_shreds.len() >= data_shreds.len());
+        for (i, shred) in coding_shreds.iter().enumerate() {
+            assert_eq!(shred.index(), 3 + i as u32);
+        }
+
+        let other_keypair = Keypair::new();
+        for shred in data_shreds.iter().chain(&coding_shreds) {
+            assert!(shred.is_merkle());
+            assert!(shred.verify(&keypair.pubkey()));
+            assert!(!shred.verify(&other_keypair.pubkey()));
+            let deserialized = Shred::new_from_serialized_shred(shred.payload.clone()).unwrap();
+            assert_eq!(&deserialized, shred);
+            // Any change to the leaf breaks the proof.
+            let mut tampered = shred.clone();
+            tampered.payload[OFFSET_OF_MERKLE_PROOF - 1] ^= 1;
+            assert!(!tampered.verify(&keypair.pubkey()));
+        }
+        // Shreds of the same erasure batch share the root and signature.
+        for shreds in [&data_shreds, &coding_shreds].iter() {
+            for shred in shreds.iter() {
+                let first = shreds
+                    .iter()
+                    .find(|other| {
+                        other.common_header.fec_set_index == shred.common_header.fec_set_index
+                    })
+                    .unwrap();
+                assert_eq!(shred.signature(), first.signature());
+                assert_eq!(shred.merkle_root(), first.merkle_root());
+            }
+        }
+        let data_shreds_roots: HashSet<_> = data_shreds.iter().map(Shred::merkle_root).collect();
+        let coding_shreds_roots: HashSet<_> =
+            coding_shreds.iter().map(Shred::merkle_root).collect();
+        assert_eq!(data_shreds_roots, coding_shreds_roots);
+        assert_eq!(
+            data_shreds_roots.len(),
+            (data_shreds.len() + MAX_DATA_SHREDS_PER_FEC_BLOCK as usize - 1)
+                / MAX_DATA_SHREDS_PER_FEC_BLOCK as usize
+        );
+
+        let deshred_payload = Shredder::deshred(&data_shreds).unwrap();
+        let deshred_entries: Vec<Entry> = bincode::deserialize(&deshred_payload).unwrap();
+        assert_eq!(entries, deshred_entries);
+    }
+
+    #[test]
+    fn test_merkle_shreds() {
+        run_test_merkle_shreds(false);
+        run_test_merkle_shreds(true);
+    }
+
+    #[test]
+    fn test_merkle_shreds_recovery() {
+        let keypair = Keypair::new();
+        let slot = 11;
+        let shredder = Shredder::new(slot, slot - 1, 0, 0).unwrap();
+        let entries = make_test_entries(50);
+        let (data_shreds, coding_shreds, _) =
+            shredder.entries_to_merkle_shreds(&keypair, &entries, false, 0, Some(0));
+        let num_data = data_shreds.len();
+        let num_coding = coding_shreds.len();
+        assert!(num_data > 2 && num_data <= MAX_DATA_SHREDS_PER_FEC_BLOCK as usize);
+
+        // Drop every other data shred, and a coding shred.
+        let (dropped, kept): (Vec<_>, Vec<_>) = data_shreds
+            .iter()
+            .cloned()
+            .enumerate()
+            .partition(|(i, _)| i % 2 == 0);
+        let available: Vec<_> = kept
+            .into_iter()
+            .map(|(_, shred)| shred)
+            .chain(coding_shreds.iter().skip(1).cloned())
+            .collect();
+        let recovered =
+            Shredder::try_recovery(available.clone(), num_data, num_coding, 0, 0, slot).unwrap();
+        let dropped: Vec<_> = dropped.into_iter().map(|(_, shred)| shred).collect();
+        assert_eq!(recovered, dropped);
+        assert!(recovered
+            .iter()
+            .all(|shred| shred.verify(&keypair.pubkey())));
+
+        // Shreds from a different slot are not used for recovery.
+        let available: Vec<_> = available
+            .into_iter()
+            .map(|mut shred| {
+                shred.set_slot(slot + 1);
+                shred
+            })
+            .collect();
+        assert_matches!(
+            Shredder::try_recovery(available, num_data, num_coding, 0, 0, slot),
+            Err(reed_solomon_erasure::Error::TooFewShardsPresent)
+        );
+    }
 }
@@ -1,5 +1,5 @@
 #![allow(clippy::implicit_hasher)]
-use crate::shred::{ShredType, SIZE_OF_NONCE};
+use crate::shred::{merkle_root_from_payload, ShredType, OFFSET_OF_SHRED_TYPE, SIZE_OF_NONCE};
 use rayon::{
     iter::{
         IndexedParallelIterator, IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator,
@@ -18,6 +18,7 @@ use solana_perf::{
 use solana_rayon_threadlimit::get_thread_count;
 use solana_sdk::{
     clock::Slot,
+    hash::Hash,
     pubkey::Pubkey,
     signature::Signature,
     signature::{Keypair, Signer},
@@ -69,23 +70,118 @@ pub fn verify_shred_cpu(packet: &Packet, slot_leaders: &HashMap<u64, [u8; 32]>)
     }
     let signature = Signature::new(&packet.data[sig_start..sig_end]);
     trace!("signature {}", signature);
+    // Merkle shreds are signed over the root of their erasure batch.
+    if is_merkle_shred(packet) {
+        let verified = match merkle_root_from_payload(&packet.data[..msg_end]) {
+            Some(root) => signature.verify(pubkey, root.as_ref()),
+            None => false,
+        };
+        return Some(verified as u8);
+    }
     if !signature.verify(pubkey, &packet.data[msg_start..msg_end]) {
         return Some(0);
     }
     Some(1)
 }
 
+fn is_merkle_shred(packet: &Packet) -> bool {
+    packet.meta.size > OFFSET_OF_SHRED_TYPE
+        && ShredType(packet.data[OFFSET_OF_SHRED_TYPE]).is_merkle()
+}
+
+// Slot, signature and Merkle root recomputed from the proof of a Merkle shred.
+fn merkle_shred_signed_root(packet: &Packet) -> Option<(Slot, Signature, Hash)> {
+    let slot_start = size_of::<Signature>() + size_of::<ShredType>();
+    let slot_end = slot_start + size_of::<u64>();
+    if packet.meta.discard || packet.meta.size < slot_end || !is_merkle_shred(packet) {
+        return None;
+    }
+    let msg_end = if packet.meta.repair {
+        packet.meta.size.saturating_sub(SIZE_OF_NONCE)
+    } else {
+        packet.meta.size
+    };
+    let slot = limited_deserialize(&packet.data[slot_start..slot_end]).ok()?;
+    let signature = Signature::new(&packet.data[..size_of::<Signature>()]);
+    let root = merkle_root_from_payload(&packet.data[..msg_end])?;
+    Some((slot, signature, root))
+}
+
+// Verifies the Merkle shreds in the batches. Each erasure batch shares one
+// signature over its Merkle root, so after the hash paths are checked the
+// signature is verified once per distinct root. Legacy shreds map to None.
+fn verify_merkle_shreds_cpu(
+    batches: &[Packets],
+    slot_leaders: &HashMap<u64, [u8; 32]>,
+) -> Vec<Vec<Option<u8>>> {
+    use rayon::prelude::*;
+    let roots: Vec<Vec<Option<Option<(Slot, Signature, Hash)>>>> =
+        SIGVERIFY_THREAD_POOL.install(|| {
+            batches
+                .into_par_iter()
+                .map(|p| {
+                    p.packets
+                        .par_iter()
+                        .map(|packet| {
+                            if is_merkle_shred(packet) {
+                                Some(merkle_shred_signed_root(packet))
+                            } else {
+                                None
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        });
+    let mut signed_roots: Vec<_> = roots.iter().flatten().flatten().flatten().collect();
+    signed_roots.sort_unstable();
+    signed_roots.dedup();
+    let verified: HashMap<&(Slot, Signature, Hash), bool> = SIGVERIFY_THREAD_POOL.install(|| {
+        signed_roots
+            .into_par_iter()
+            .map(|signed_root| {
+                let (slot, signature, root) = signed_root;
+                let verified = match slot_leaders.get(slot) {
+                    Some(pubkey) => signature.verify(pubkey, root.as_ref()),
+                    None => false,
+                };
+                (signed_root, verified)
+            })
+            .collect()
+    });
+    inc_new_counter_debug!("ed25519_merkle_shred_verify_cpu", verified.len());
+    roots
+        .iter()
+        .map(|packets| {
+            packets
+                .iter()
+                .map(|packet| {
+                    packet.as_ref().map(|signed_root| match signed_root {
+                        Some(signed_root) => verified[signed_root] as u8,
+                        None => 0,
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
 fn verify_shreds_cpu(batches: &[Packets], slot_leaders: &HashMap<u64, [u8; 32]>) -> Vec<Vec<u8>> {
     use rayon::prelude::*;
     let count = batch_size(batches);
     debug!("CPU SHRED ECDSA for {}", count);
+    let merkle_rvs = verify_merkle_shreds_cpu(batches, slot_leaders);
     let rv = SIGVERIFY_THREAD_POOL.install(|| {
         batches
             .into_par_iter()
-            .map(|p| {
+            .zip(merkle_rvs)
+            .map(|(p, merkle_rvs)| {
                 p.packets
                     .par_iter()
-                    .map(|p| verify_shred_cpu(p, slot_leaders).unwrap_or(0))
+                    .zip(merkle_rvs)
+                    .map(|(p, merkle_rv)| {
+                        merkle_rv.unwrap_or_else(|| verify_shred_cpu(p, slot_leaders).unwrap_or(0))
+                    })
                     .collect()
             })
             .collect()
@@ -289,6 +385,17 @@ pub fn verify_shreds_gpu(
 
     sigverify::copy_return_values(&v_sig_lens, &out, &mut rvs);
 
+    // Merkle shreds are not signed over the packet data, so their
+    // signatures are verified on the CPU.
+    let merkle_rvs = verify_merkle_shreds_cpu(batches, slot_leaders);
+    for (rvs, merkle_rvs) in rvs.iter_mut().zip(merkle_rvs) {
+        for (rv, merkle_rv) in rvs.iter_mut().zip(merkle_rvs) {
+            if let Some(merkle_rv) = merkle_rv {
+                *rv = merkle_rv;
+            }
+        }
+    }
+
     inc_new_counter_debug!("ed25519_shred_verify_gpu", count);
     rvs
 }
@@ -452,7 +559,8 @@ pub fn sign_shreds_gpu(
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::shred::{Shred, Shredder, SIZE_OF_DATA_SHRED_PAYLOAD};
+    use crate::shred::{Shred, Shredder, OFFSET_OF_MERKLE_PROOF, SIZE_OF_DATA_SHRED_PAYLOAD};
+    use solana_entry::entry::Entry;
     use solana_sdk::signature::{Keypair, Signer};
 
     fn run_test_sigverify_shred_cpu(slot: Slot) {
@@ -554,6 +662,63 @@ pub mod tests {
         run_test_sigverify_shreds_cpu(0xdead_c0de);
     }
 
+    #[test]
+    fn test_sigverify_merkle_shreds_cpu() {
+        solana_logger::setup();
+        let slot = 0xdead_c0de;
+        let keypair = Keypair::new();
+        let entries: Vec<_> = (0..400)
+            .map(|i| Entry::new(&Hash::new_unique(), i, vec![]))
+            .collect();
+        let (data_shreds, coding_shreds, _) = Shredder::new(slot, slot - 1, 0, 0)
+            .unwrap()
+            .entries_to_merkle_shreds(&keypair, &entries, true, 0, Some(0));
+        let mut batch = Packets::default();
+        for shred in data_shreds.iter().chain(&coding_shreds) {
+            let mut packet = Packet::default();
+            shred.copy_to_packet(&mut packet);
+            batch.packets.push(packet);
+        }
+        // A legacy shred in the same batch.
+        let mut shred = Shred::new_from_data(slot, 0xc0de, 1, None, true, true, 0, 0, 0xc0de);
+        Shredder::sign_shred(&keypair, &mut shred);
+        let mut packet = Packet::default();
+        shred.copy_to_packet(&mut packet);
+        batch.packets.push(packet);
+        let num_packets = batch.packets.len();
+        let mut batches = [batch];
+
+        let leader_slots = [(slot, keypair.pubkey().to_bytes())]
+            .iter()
+            .cloned()
+            .collect();
+        let rv = verify_shreds_cpu(&batches, &leader_slots);
+        assert_eq!(rv, vec![vec![1; num_packets]]);
+        assert_eq!(
+            verify_shred_cpu(&batches[0].packets[0], &leader_slots),
+            Some(1)
+        );
+
+        let wrong_keypair = Keypair::new();
+        let wrong_leader_slots = [(slot, wrong_keypair.pubkey().to_bytes())]
+            .iter()
+            .cloned()
+            .collect();
+        let rv = verify_shreds_cpu(&batches, &wrong_leader_slots);
+        assert_eq!(rv, vec![vec![0; num_packets]]);
+
+        // Breaking the proof of one shred does not fail the rest of its batch.
+        batches[0].packets[1].data[OFFSET_OF_MERKLE_PROOF] ^= 1;
+        let rv = verify_shreds_cpu(&batches, &leader_slots);
+        let mut expected = vec![1; num_packets];
+        expected[1] = 0;
+        assert_eq!(rv, vec![expected]);
+        assert_eq!(
+            verify_shred_cpu(&batches[0].packets[1], &leader_slots),
+            Some(0)
+        );
+    }
+
     fn run_test_sigverify_shreds_gpu(slot: Slot) {
         solana_logger::setup();
         let recycler_cache = RecyclerCache::default();
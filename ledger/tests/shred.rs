@@ -47,7 +47,7 @@ fn test_multi_fec_block_coding() {
 
     let serialized_entries = bincode::serialize(&entries).unwrap();
     let (data_shreds, coding_shreds, next_index) =
-        shredder.entries_to_shreds(&keypair, &entries, true, 0);
+        shredder.entries_to_shreds(&keypair, &entries, true, 0, Some(0));
     assert_eq!(next_index as usize, num_data_shreds);
     assert_eq!(data_shreds.len(), num_data_shreds);
     assert_eq!(coding_shreds.len(), num_data_shreds);
@@ -77,6 +77,7 @@ fn test_multi_fec_block_coding() {
             MAX_DATA_SHREDS_PER_FEC_BLOCK as usize,
             MAX_DATA_SHREDS_PER_FEC_BLOCK as usize,
             shred_start_index,
+            shred_start_index,
             slot,
         )
         .unwrap();
@@ -121,7 +122,9 @@ fn test_multi_fec_block_different_size_coding() {
     for (fec_data_shreds, fec_coding_shreds) in fec_data.values().zip(fec_coding.values()) {
         let first_data_index = fec_data_shreds.first().unwrap().index() as usize;
         let first_code_index = fec_coding_shreds.first().unwrap().index() as usize;
-        assert_eq!(first_data_index, first_code_index);
+        assert!(fec_coding_shreds
+            .iter()
+            .all(|shred| shred.first_coding_index() == Some(first_code_index as u32)));
         let num_data = fec_data_shreds.len();
         let num_coding = fec_coding_shreds.len();
         let all_shreds: Vec<Shred> = fec_data_shreds
@@ -130,9 +133,15 @@ fn test_multi_fec_block_different_size_coding() {
             .chain(fec_coding_shreds.iter().step_by(2))
             .cloned()
             .collect();
-        let recovered_data =
-            Shredder::try_recovery(all_shreds, num_data, num_coding, first_data_index, slot)
-                .unwrap();
+        let recovered_data = Shredder::try_recovery(
+            all_shreds,
+            num_data,
+            num_coding,
+            first_data_index,
+            first_code_index,
+            slot,
+        )
+        .unwrap();
         // Necessary in order to ensure the last shred in the slot
         // is part of the recovered set, and that the below `index`
         // calcuation in the loop is correct
@@ -219,6 +228,7 @@ fn setup_different_sized_fec_blocks(
 
     // Run the shredder twice, generate data and coding shreds
     let mut next_index = 0;
+    let mut next_code_index = 0;
     let mut fec_data = BTreeMap::new();
     let mut fec_coding = BTreeMap::new();
     let mut data_slot_and_index = HashSet::new();
@@ -227,8 +237,13 @@ fn setup_different_sized_fec_blocks(
     let total_num_data_shreds: usize = 2 * num_shreds_per_iter;
     for i in 0..2 {
         let is_last = i == 1;
-        let (data_shreds, coding_shreds, new_next_index) =
-            shredder.entries_to_shreds(&keypair, &entries, is_last, next_index);
+        let (data_shreds, coding_shreds, new_next_index) = shredder.entries_to_shreds(
+            &keypair,
+            &entries,
+            is_last,
+            next_index,
+            Some(next_code_index),
+        );
         for shred in &data_shreds {
             if (shred.index() as usize) == total_num_data_shreds - 1 {
                 assert!(shred.data_complete());
@@ -242,6 +257,7 @@ fn setup_different_sized_fec_blocks(
         }
         assert_eq!(data_shreds.len(), num_shreds_per_iter as usize);
         next_index = new_next_index;
+        next_code_index = coding_shreds.last().unwrap().index() + 1;
         sort_data_coding_into_fec_sets(
             data_shreds,
             coding_shreds,
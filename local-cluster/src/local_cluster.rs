@@ -45,6 +45,7 @@ use std::{
     collections::HashMap,
     io::{Error, ErrorKind, Result},
     iter,
+    net::{IpAddr, Ipv4Addr},
     sync::{Arc, RwLock},
 };
 
@@ -160,7 +161,7 @@ impl LocalCluster {
         let leader_keypair = &keys_in_genesis[0].node_keypair;
         let leader_vote_keypair = &keys_in_genesis[0].vote_keypair;
         let leader_pubkey = leader_keypair.pubkey();
-        let leader_node = Node::new_localhost_with_pubkey(&leader_pubkey);
+        let leader_node = new_localhost_node(&leader_pubkey, &config.validator_configs[0]);
         let GenesisConfigInfo {
             mut genesis_config,
             mint_keypair,
@@ -317,7 +318,7 @@ impl LocalCluster {
             voting_keypair = Some(Arc::new(Keypair::new()));
         }
         let validator_pubkey = validator_keypair.pubkey();
-        let validator_node = Node::new_localhost_with_pubkey(&validator_keypair.pubkey());
+        let validator_node = new_localhost_node(&validator_keypair.pubkey(), validator_config);
         let contact_info = validator_node.info.clone();
         let (ledger_path, _blockhash) = create_new_tmp_ledger!(&self.genesis_config);
 
@@ -630,7 +631,7 @@ impl Cluster for LocalCluster {
         cluster_validator_info: &mut ClusterValidatorInfo,
     ) -> (Node, Option<ContactInfo>) {
         // Update the stored ContactInfo for this node
-        let node = Node::new_localhost_with_pubkey(pubkey);
+        let node = new_localhost_node(pubkey, &cluster_validator_info.config);
         cluster_validator_info.info.contact_info = node.info.clone();
         cluster_validator_info.config.rpc_addrs = Some((node.info.rpc, node.info.rpc_pubsub));
 
@@ -693,6 +694,15 @@ impl Cluster for LocalCluster {
     }
 }
 
+fn new_localhost_node(pubkey: &Pubkey, config: &ValidatorConfig) -> Node {
+    let mut node = Node::new_localhost_with_pubkey(pubkey);
+    if config.tpu_use_quic {
+        node.bind_tpu_quic(IpAddr::V4(Ipv4Addr::LOCALHOST), (1024, 65535))
+            .expect("tpu_quic bind");
+    }
+    node
+}
+
 impl Drop for LocalCluster {
     fn drop(&mut self) {
         self.close();
@@ -16,6 +16,7 @@ pub fn safe_clone_config(config: &ValidatorConfig) -> ValidatorConfig {
         pubsub_config: config.pubsub_config.clone(),
         snapshot_config: config.snapshot_config.clone(),
         max_ledger_shreds: config.max_ledger_shreds,
+        transaction_archive_filter: config.transaction_archive_filter.clone(),
         broadcast_stage_type: config.broadcast_stage_type.clone(),
         enable_partition: config.enable_partition.clone(),
         enforce_ulimit_nofile: config.enforce_ulimit_nofile,
@@ -35,6 +36,7 @@ pub fn safe_clone_config(config: &ValidatorConfig) -> ValidatorConfig {
         accounts_hash_interval_slots: config.accounts_hash_interval_slots,
         max_genesis_archive_unpacked_size: config.max_genesis_archive_unpacked_size,
         wal_recovery_mode: config.wal_recovery_mode.clone(),
+        shred_storage_type: config.shred_storage_type,
         poh_verify: config.poh_verify,
         cuda: config.cuda,
         require_tower: config.require_tower,
@@ -43,8 +45,7 @@ pub fn safe_clone_config(config: &ValidatorConfig) -> ValidatorConfig {
         contact_debug_interval: config.contact_debug_interval,
         contact_save_interval: config.contact_save_interval,
         bpf_jit: config.bpf_jit,
-        send_transaction_retry_ms: config.send_transaction_retry_ms,
-        send_transaction_leader_forward_count: config.send_transaction_leader_forward_count,
+        send_transaction_service_config: config.send_transaction_service_config.clone(),
         no_poh_speed_test: config.no_poh_speed_test,
         poh_pinned_cpu_core: config.poh_pinned_cpu_core,
         account_indexes: config.account_indexes.clone(),
@@ -53,8 +54,16 @@ pub fn safe_clone_config(config: &ValidatorConfig) -> ValidatorConfig {
         accounts_db_test_hash_calculation: config.accounts_db_test_hash_calculation,
         accounts_db_use_index_hash_calculation: config.accounts_db_use_index_hash_calculation,
         tpu_coalesce_ms: config.tpu_coalesce_ms,
+        tpu_use_quic: config.tpu_use_quic,
+        forwarding_config: config.forwarding_config.clone(),
+        banking_stage_scheduler: config.banking_stage_scheduler,
+        gossip_receiver_config: config.gossip_receiver_config.clone(),
+        tvu_receiver_config: config.tvu_receiver_config.clone(),
+        repair_receiver_config: config.repair_receiver_config.clone(),
+        tpu_receiver_config: config.tpu_receiver_config.clone(),
         validator_exit: Arc::new(RwLock::new(Exit::default())),
         poh_hashes_per_batch: config.poh_hashes_per_batch,
+        poh_entry_batch_config: config.poh_entry_batch_config.clone(),
         no_wait_for_vote_to_start_leader: config.no_wait_for_vote_to_start_leader,
         accounts_shrink_ratio: config.accounts_shrink_ratio,
     }
@@ -6,13 +6,19 @@ use log::*;
 use serial_test::serial;
 use solana_client::{
     pubsub_client::PubsubClient,
+    quic_client::QuicTpuConnection,
     rpc_client::RpcClient,
     rpc_config::{RpcProgramAccountsConfig, RpcSignatureSubscribeConfig},
     rpc_response::RpcSignatureResult,
     thin_client::{create_client, ThinClient},
 };
 use solana_core::{
-    broadcast_stage::{broadcast_duplicates_run::BroadcastDuplicatesConfig, BroadcastStageType},
+    broadcast_stage::{
+        broadcast_duplicates_run::{
+            BroadcastDuplicatesConfig, DUPLICATE_RATE, MINIMUM_DUPLICATE_SLOT,
+        },
+        BroadcastStageType,
+    },
     consensus::{Tower, SWITCH_FORK_THRESHOLD, VOTE_THRESHOLD_DEPTH},
     optimistic_confirmation_verifier::OptimisticConfirmationVerifier,
     replay_stage::DUPLICATE_THRESHOLD,
@@ -49,8 +55,10 @@ use solana_sdk::{
     epoch_schedule::MINIMUM_SLOTS_PER_EPOCH,
     genesis_config::ClusterType,
     hash::Hash,
+    packet::PACKET_DATA_SIZE,
     poh_config::PohConfig,
     pubkey::Pubkey,
+    quic::QUIC_PORT_OFFSET,
     signature::{Keypair, Signer},
     system_program, system_transaction,
 };
@@ -60,6 +68,7 @@ use std::{
     fs,
     io::Read,
     iter,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
     path::{Path, PathBuf},
     sync::atomic::{AtomicBool, Ordering},
     sync::Arc,
@@ -127,6 +136,140 @@ fn test_spend_and_verify_all_nodes_1() {
     );
 }
 
+// Sends transfers from the funding keypair with `send_wire_transaction` until all of them are
+// processed, while the caller floods the TPU
+fn spend_during_flood(cluster: &LocalCluster, send_wire_transaction: impl Fn(&[u8])) {
+    let client = RpcClient::new_socket(cluster.entry_point_info.rpc);
+    let recipients: Vec<_> = (0..10).map(|_| Pubkey::new_unique()).collect();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        let pending: Vec<_> = recipients
+            .iter()
+            .filter(|recipient| {
+                client
+                    .get_balance_with_commitment(recipient, CommitmentConfig::processed())
+                    .unwrap()
+                    .value
+                    == 0
+            })
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "{} staked transfers were not processed",
+            pending.len()
+        );
+        let (blockhash, _) = client.get_recent_blockhash().unwrap();
+        for recipient in pending {
+            let transaction =
+                system_transaction::transfer(&cluster.funding_keypair, recipient, 1, blockhash);
+            send_wire_transaction(&bincode::serialize(&transaction).unwrap());
+        }
+        sleep(Duration::from_millis(500));
+    }
+}
+
+#[test]
+#[serial]
+fn test_stake_weighted_qos_udp_flood() {
+    solana_logger::setup_with_default(RUST_LOG_FILTER);
+    let cluster = LocalCluster::new_with_equal_stakes(1, 10_000, 100);
+    let tpu = cluster.entry_point_info.tpu;
+
+    // The node's TPU is bound to 127.0.0.1, other loopback addresses are unstaked senders
+    let exit = Arc::new(AtomicBool::new(false));
+    let flood_threads: Vec<_> = (2..4)
+        .map(|i| {
+            let socket = UdpSocket::bind((Ipv4Addr::new(127, 0, 0, i), 0)).unwrap();
+            let exit = exit.clone();
+            Builder::new()
+                .name("udp-flood".to_string())
+                .spawn(move || {
+                    let garbage = [0xffu8; PACKET_DATA_SIZE];
+                    while !exit.load(Ordering::Relaxed) {
+                        let _ = socket.send_to(&garbage, tpu);
+                    }
+                })
+                .unwrap()
+        })
+        .collect();
+
+    let staked_socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    spend_during_flood(&cluster, |wire_transaction| {
+        staked_socket.send_to(wire_transaction, tpu).unwrap();
+    });
+
+    exit.store(true, Ordering::Relaxed);
+    for flood_thread in flood_threads {
+        flood_thread.join().unwrap();
+    }
+}
+
+#[test]
+#[serial]
+fn test_stake_weighted_qos_quic_flood() {
+    solana_logger::setup_with_default(RUST_LOG_FILTER);
+    let validator_config = ValidatorConfig {
+        tpu_use_quic: true,
+        ..ValidatorConfig::default()
+    };
+    let mut config = ClusterConfig {
+        cluster_lamports: 10_000,
+        node_stakes: vec![100],
+        validator_configs: vec![validator_config],
+        ..ClusterConfig::default()
+    };
+    let cluster = LocalCluster::new(&mut config);
+    let tpu = cluster.entry_point_info.tpu;
+    let tpu_quic = SocketAddr::new(tpu.ip(), tpu.port() + QUIC_PORT_OFFSET);
+
+    // Unstaked clients, authenticated with identities that hold no stake
+    let exit = Arc::new(AtomicBool::new(false));
+    let flood_threads: Vec<_> = (0..4)
+        .map(|_| {
+            let connection = QuicTpuConnection::new(
+                UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap(),
+                tpu_quic,
+                &Keypair::new(),
+            )
+            .unwrap();
+            let exit = exit.clone();
+            Builder::new()
+                .name("quic-flood".to_string())
+                .spawn(move || {
+                    let garbage = vec![vec![0xffu8; PACKET_DATA_SIZE]; 64];
+                    while !exit.load(Ordering::Relaxed) {
+                        let _ = connection.send_wire_transaction_batch(&garbage);
+                    }
+                })
+                .unwrap()
+        })
+        .collect();
+
+    let identity = cluster.validators[&cluster.entry_point_info.id]
+        .info
+        .keypair
+        .clone();
+    let staked_connection = QuicTpuConnection::new(
+        UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap(),
+        tpu_quic,
+        &identity,
+    )
+    .unwrap();
+    spend_during_flood(&cluster, |wire_transaction| {
+        staked_connection
+            .send_wire_transaction(wire_transaction)
+            .unwrap();
+    });
+
+    exit.store(true, Ordering::Relaxed);
+    for flood_thread in flood_threads {
+        flood_thread.join().unwrap();
+    }
+}
+
 #[test]
 #[serial]
 fn test_spend_and_verify_all_nodes_2() {
@@ -1959,9 +2102,9 @@ fn test_duplicate_shreds_broadcast_leader() {
     //       node will repair that correct version
     //    b) A descendant `D` of some version of `S` gets > DUPLICATE_THRESHOLD votes in gossip,
     //       but no version of `S` does. Then the node will not know to repair the right version
-    //       by just looking at gossip, but will instead have to use EpochSlots repair after
-    //       detecting that a descendant does not chain to its version of `S`, and marks that descendant
-    //       dead.
+    //       by just looking at gossip, but will instead have to sample the cluster for the ancestor
+    //       hashes of that descendant after detecting that it does not chain to its version of `S`,
+    //       and marks that descendant dead.
     //   Scenarios a) or b) are triggered by our node in 2) who's voting behavior we control.
 
     // Critical that bad_leader_stake + good_node_stake < DUPLICATE_THRESHOLD and that
@@ -2009,6 +2152,7 @@ fn test_duplicate_shreds_broadcast_leader() {
 
     // This is why it's important our node was last in `node_stakes`
     let our_id = validator_keys.last().unwrap().pubkey();
+    let partition_node_id = validator_keys[1].pubkey();
 
     // 2) Kill our node and start up a thread to simulate votes to control our voting behavior
     let our_info = cluster.exit_node(&our_id);
@@ -2129,12 +2273,94 @@ fn test_duplicate_shreds_broadcast_leader() {
     // 4) Check that the cluster is making progress
     cluster.check_for_new_roots(16, "test_duplicate_shreds_broadcast_leader");
 
+    // 5) The partition node only ever received the duplicate versions of slots, so in
+    // order to root past them it must have dumped its versions and repaired the versions
+    // the rest of the cluster rooted
+    check_partition_node_repaired_duplicates(&cluster, &partition_node_id, &bad_leader_ledger_path);
+
     // Clean up threads
     exit.store(true, Ordering::Relaxed);
     t_voter.join().unwrap();
     gossip_service.join().unwrap();
 }
 
+#[test]
+#[serial]
+fn test_duplicate_shreds_broadcast_leader_ancestor_hashes_repair() {
+    // 1) Bad leader with enough stake to duplicate confirm its own versions of slots
+    // 2) Good validator which receives the leader's versions
+    // 3) Partition validator which only receives the duplicate versions, and which has to
+    //    sample the cluster for the ancestor hashes of the confirmed descendants to find
+    //    out which of its slots to dump and repair
+    let bad_leader_stake = 10_000_000_000;
+    let good_node_stake = 500_000;
+    let partition_node_stake = 1;
+    let node_stakes = vec![bad_leader_stake, partition_node_stake, good_node_stake];
+    let total_stake: u64 = node_stakes.iter().sum();
+    assert!(bad_leader_stake as f64 / total_stake as f64 > DUPLICATE_THRESHOLD);
+
+    let (cluster, validator_keys) = test_faulty_node(
+        BroadcastStageType::BroadcastDuplicates(BroadcastDuplicatesConfig {
+            stake_partition: partition_node_stake,
+        }),
+        node_stakes,
+    );
+    let partition_node_id = validator_keys[1].pubkey();
+    let bad_leader_id = cluster.entry_point_info.id;
+    let bad_leader_ledger_path = cluster.validators[&bad_leader_id].info.ledger_path.clone();
+
+    cluster.check_for_new_roots(
+        16,
+        "test_duplicate_shreds_broadcast_leader_ancestor_hashes_repair",
+    );
+    check_partition_node_repaired_duplicates(&cluster, &partition_node_id, &bad_leader_ledger_path);
+}
+
+// Waits for the node which only received the duplicate versions of slots to root past the
+// first few of them, and checks that it rooted the versions of the bad leader
+fn check_partition_node_repaired_duplicates(
+    cluster: &LocalCluster,
+    partition_node_id: &Pubkey,
+    bad_leader_ledger_path: &Path,
+) {
+    let partition_node_ledger_path = cluster.validators[partition_node_id]
+        .info
+        .ledger_path
+        .clone();
+    let min_checked_root = MINIMUM_DUPLICATE_SLOT + DUPLICATE_RATE as Slot;
+    let start = Instant::now();
+    loop {
+        let partition_node_root =
+            root_in_tower(&partition_node_ledger_path, partition_node_id).unwrap_or(0);
+        if partition_node_root > min_checked_root {
+            break;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(120),
+            "partition node failed to root past slot {}, root: {}",
+            min_checked_root,
+            partition_node_root
+        );
+        sleep(Duration::from_millis(DEFAULT_MS_PER_SLOT));
+    }
+    let partition_node_blockstore = open_blockstore(&partition_node_ledger_path);
+    let bad_leader_blockstore = open_blockstore(bad_leader_ledger_path);
+    for slot in partition_node_blockstore
+        .rooted_slot_iterator(MINIMUM_DUPLICATE_SLOT)
+        .unwrap()
+        .take_while(|slot| *slot <= min_checked_root)
+    {
+        if let Some(partition_node_hash) = partition_node_blockstore.get_bank_hash(slot) {
+            assert_eq!(
+                Some(partition_node_hash),
+                bad_leader_blockstore.get_bank_hash(slot),
+                "partition node rooted the wrong version of slot {}",
+                slot
+            );
+        }
+    }
+}
+
 fn test_faulty_node(
     faulty_node_type: BroadcastStageType,
     node_stakes: Vec<u64>,
@@ -36,6 +36,11 @@ impl<'a> ProofEntry<'a> {
         assert!((None == left_sibling) ^ (None == right_sibling));
         Self(target, left_sibling, right_sibling)
     }
+
+    /// Returns the node hashed together with the path node at this level
+    pub fn sibling(&self) -> &'a Hash {
+        self.1.or(self.2).unwrap()
+    }
 }
 
 #[derive(Debug, Default, PartialEq)]
@@ -46,6 +51,10 @@ impl<'a> Proof<'a> {
         self.0.push(entry)
     }
 
+    pub fn entries(&self) -> &[ProofEntry<'a>] {
+        &self.0
+    }
+
     pub fn verify(&self, candidate: Hash) -> bool {
         let result = self.0.iter().try_fold(candidate, |candidate, pe| {
             let lsib = pe.1.unwrap_or(&candidate);
@@ -62,6 +71,11 @@ impl<'a> Proof<'a> {
     }
 }
 
+/// Returns the hash of a leaf node holding `data`
+pub fn hash_leaf(data: &[u8]) -> Hash {
+    hash_leaf!(data)
+}
+
 impl MerkleTree {
     #[inline]
     fn next_level_len(level_len: usize) -> usize {
@@ -175,6 +189,25 @@ impl MerkleTree {
         }
         Some(path)
     }
+
+    /// Recomputes the root of the tree from the hash of the leaf at `index`
+    /// and the siblings along its path, as returned by `find_path`
+    pub fn root_from_path<'a, I>(leaf: Hash, index: usize, siblings: I) -> Hash
+    where
+        I: IntoIterator<Item = &'a Hash>,
+    {
+        let (root, _) = siblings
+            .into_iter()
+            .fold((leaf, index), |(node, index), sibling| {
+                let hash = if index % 2 == 0 {
+                    hash_intermediate!(node, sibling)
+                } else {
+                    hash_intermediate!(sibling, node)
+                };
+                (hash, index / 2)
+            });
+        root
+    }
 }
 
 #[cfg(test)]
@@ -247,6 +280,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_root_from_path() {
+        for num_leaves in 1..=TEST.len() {
+            let mt = MerkleTree::new(&TEST[..num_leaves]);
+            let root = mt.get_root().unwrap();
+            for (i, s) in TEST[..num_leaves].iter().enumerate() {
+                let path = mt.find_path(i).unwrap();
+                let siblings = path.entries().iter().map(ProofEntry::sibling);
+                assert_eq!(
+                    MerkleTree::root_from_path(hash_leaf(s), i, siblings.clone()),
+                    *root
+                );
+                assert_ne!(
+                    MerkleTree::root_from_path(hash_leaf(b"bad"), i, siblings),
+                    *root
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_proof_entry_instantiation_lsib_set() {
         ProofEntry::new(&Hash::default(), Some(&Hash::default()), None);
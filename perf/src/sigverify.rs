@@ -260,7 +260,12 @@ pub fn generate_offsets(batches: &[Packets], recycler: &Recycler<TxOffset>) -> T
     batches.iter().for_each(|p| {
         let mut sig_lens = Vec::new();
         p.packets.iter().for_each(|packet| {
-            let packet_offsets = get_packet_offsets(packet, current_offset);
+            // Packets discarded before verification have no signatures to verify
+            let packet_offsets = if packet.meta.discard {
+                PacketOffsets::new(0, 0, 0, 0)
+            } else {
+                get_packet_offsets(packet, current_offset)
+            };
 
             sig_lens.push(packet_offsets.sig_len);
 
@@ -306,15 +311,11 @@ pub fn ed25519_verify_cpu(batches: &mut [Packets]) {
     inc_new_counter_debug!("ed25519_verify_cpu", count);
 }
 
+// Every packet passes, while packets discarded before verification, e.g. as
+// duplicates, stay discarded.
 pub fn ed25519_verify_disabled(batches: &mut [Packets]) {
-    use rayon::prelude::*;
     let count = batch_size(batches);
     debug!("disabled ECDSA for {}", batch_size(batches));
-    batches.into_par_iter().for_each(|p| {
-        p.packets
-            .par_iter_mut()
-            .for_each(|p| p.meta.discard = false)
-    });
     inc_new_counter_debug!("ed25519_verify_disabled", count);
 }
 
@@ -372,7 +373,11 @@ pub fn get_checked_scalar(scalar: &[u8; 32]) -> Result<[u8; 32], PacketError> {
 pub fn mark_disabled(batches: &mut [Packets], r: &[Vec<u8>]) {
     batches.iter_mut().zip(r).for_each(|(b, v)| {
         b.packets.iter_mut().zip(v).for_each(|(p, f)| {
-            p.meta.discard = *f == 0;
+            // Packets discarded before verification, e.g. as duplicates,
+            // stay discarded.
+            if !p.meta.discard {
+                p.meta.discard = *f == 0;
+            }
         })
     });
 }
@@ -389,7 +394,11 @@ pub fn ed25519_verify(
     let api = api.unwrap();
 
     use crate::packet::PACKET_DATA_SIZE;
-    let count = batch_size(batches);
+    let count = batches
+        .iter()
+        .flat_map(|p| &p.packets)
+        .filter(|p| !p.meta.discard)
+        .count();
 
     // micro-benchmarks show GPU time for smallest batch around 15-20ms
     // and CPU speed for 64-128 sigverifies around 10-20ms. 64 is a nice
@@ -497,6 +506,9 @@ mod tests {
         mark_disabled(&mut batches, &[vec![0]]);
         assert!(batches[0].packets[0].meta.discard);
         mark_disabled(&mut batches, &[vec![1]]);
+        assert!(batches[0].packets[0].meta.discard);
+        batches[0].packets[0].meta.discard = false;
+        mark_disabled(&mut batches, &[vec![1]]);
         assert!(!batches[0].packets[0].meta.discard);
     }
 
@@ -761,6 +773,34 @@ mod tests {
             .all(|p| p.meta.discard == should_discard));
     }
 
+    #[test]
+    fn test_verify_discarded() {
+        let packet = sigverify::make_packet_from_transaction(test_tx());
+        let mut batches = generate_packet_vec(&packet, 128, 2);
+        batches[0].packets[3].meta.discard = true;
+
+        let recycler = Recycler::default();
+        let (signature_offsets, _, _, _, sig_lens) = generate_offsets(&batches, &recycler);
+        assert_eq!(signature_offsets.len(), 255);
+        assert_eq!(sig_lens[0][3], 0);
+
+        let recycler_out = Recycler::default();
+        sigverify::ed25519_verify(&mut batches, &recycler, &recycler_out);
+        assert_eq!(
+            batches
+                .iter()
+                .flat_map(|p| &p.packets)
+                .filter(|p| p.meta.discard)
+                .count(),
+            1
+        );
+        assert!(batches[0].packets[3].meta.discard);
+
+        sigverify::ed25519_verify_disabled(&mut batches);
+        assert!(batches[0].packets[3].meta.discard);
+        assert!(!batches[1].packets[3].meta.discard);
+    }
+
     #[test]
     fn test_verify_tampered_sig_len() {
         let mut tx = test_tx();
@@ -1,12 +1,92 @@
 #![allow(clippy::integer_arithmetic)]
 use clap::{crate_description, crate_name, value_t, App, Arg};
+use rayon::prelude::*;
+use solana_entry::entry::hash_transactions;
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 use solana_entry::entry::{self, create_ticks, init_poh, EntrySlice, VerifyRecyclers};
 #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
 use solana_entry::entry::{create_ticks, init_poh, EntrySlice, VerifyRecyclers};
+use solana_ledger::{
+    blockstore::Blockstore,
+    genesis_utils::{create_genesis_config, GenesisConfigInfo},
+    get_tmp_ledger_path,
+};
 use solana_measure::measure::Measure;
-use solana_perf::perf_libs;
+use solana_perf::{perf_libs, test_tx::test_tx};
+use solana_poh::poh_recorder::{create_test_recorder, EntryBatchConfig};
+use solana_runtime::bank::Bank;
 use solana_sdk::hash::hash;
+use std::{
+    collections::HashSet,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+// Records `num_batches` batches from `num_threads` threads at once and reports how the PoH
+// recorder packed them into entries.
+fn bench_record_batches(
+    num_batches: usize,
+    transactions_per_batch: usize,
+    num_threads: usize,
+    entry_batch_config: EntryBatchConfig,
+) {
+    let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(2);
+    let bank = Arc::new(Bank::new(&genesis_config));
+    let ledger_path = get_tmp_ledger_path!();
+    {
+        let blockstore = Arc::new(Blockstore::open(&ledger_path).unwrap());
+        let (exit, poh_recorder, poh_service, entry_receiver) =
+            create_test_recorder(&bank, &blockstore, None);
+        poh_recorder
+            .lock()
+            .unwrap()
+            .set_entry_batch_config(entry_batch_config);
+        let recorder = poh_recorder.lock().unwrap().recorder();
+        let transactions: Vec<_> = (0..transactions_per_batch).map(|_| test_tx()).collect();
+        let mixin = hash_transactions(&transactions);
+
+        let mut time = Measure::start("record");
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+        let num_recorded: usize = thread_pool.install(|| {
+            (0..num_batches)
+                .into_par_iter()
+                .filter(|_| {
+                    recorder
+                        .record(bank.slot(), mixin, transactions.clone())
+                        .is_ok()
+                })
+                .count()
+        });
+        time.stop();
+        exit.store(true, Ordering::Relaxed);
+        poh_service.join().unwrap();
+
+        let mut slots = HashSet::new();
+        let mut num_entries = 0;
+        let mut num_transactions = 0;
+        while let Ok((bank, (entry, _tick_height))) =
+            entry_receiver.recv_timeout(Duration::from_millis(100))
+        {
+            if !entry.is_tick() {
+                slots.insert(bank.slot());
+                num_entries += 1;
+                num_transactions += entry.transactions.len();
+            }
+        }
+        println!(
+            "record batches: {} recorded: {} us: {} entries_per_slot: {:.2} transactions_per_entry: {:.2}",
+            num_batches,
+            num_recorded,
+            time.as_us(),
+            num_entries as f64 / slots.len().max(1) as f64,
+            num_transactions as f64 / num_entries.max(1) as f64,
+        );
+    }
+    Blockstore::destroy(&ledger_path).unwrap();
+}
 
 fn main() {
     solana_logger::setup();
@@ -40,7 +120,7 @@ fn main() {
                 .long("num-transactions-per-entry")
                 .takes_value(true)
                 .value_name("NUM")
-                .help("Skip transaction sanity execution"),
+                .help("Transactions per recorded batch"),
         )
         .arg(
             Arg::with_name("iterations")
@@ -54,6 +134,21 @@ fn main() {
                 .takes_value(true)
                 .help("Number of threads"),
         )
+        .arg(
+            Arg::with_name("entry_target_transactions")
+                .long("entry-target-transactions")
+                .takes_value(true)
+                .value_name("NUM")
+                .help("Also bench recording batches into PoH entries of this many transactions"),
+        )
+        .arg(
+            Arg::with_name("entry_max_delay_us")
+                .long("entry-max-delay-us")
+                .takes_value(true)
+                .value_name("MICROSECONDS")
+                .requires("entry_target_transactions")
+                .help("Longest a batch waits for its PoH entry to fill up"),
+        )
         .arg(
             Arg::with_name("cuda")
                 .long("cuda")
@@ -142,4 +237,22 @@ fn main() {
         println!();
         num_entries *= 2;
     }
+
+    if let Ok(target_transactions) = value_t!(matches, "entry_target_transactions", usize) {
+        let max_delay = value_t!(matches, "entry_max_delay_us", u64)
+            .map(Duration::from_micros)
+            .unwrap_or_else(|_| EntryBatchConfig::default().max_delay);
+        let num_threads = value_t!(matches, "num_threads", usize).unwrap_or(4);
+        let transactions_per_batch =
+            value_t!(matches, "num_transactions_per_entry", usize).unwrap_or(1);
+        bench_record_batches(
+            max_num_entries as usize,
+            transactions_per_batch,
+            num_threads,
+            EntryBatchConfig {
+                target_transactions,
+                max_delay,
+            },
+        );
+    }
 }
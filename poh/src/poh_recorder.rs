@@ -17,7 +17,10 @@ use {
         unbounded, Receiver as CrossbeamReceiver, RecvTimeoutError, Sender as CrossbeamSender,
     },
     log::*,
-    solana_entry::{entry::Entry, poh::Poh},
+    solana_entry::{
+        entry::{hash_transactions, Entry},
+        poh::Poh,
+    },
     solana_ledger::{blockstore::Blockstore, leader_schedule_cache::LeaderScheduleCache},
     solana_runtime::bank::Bank,
     solana_sdk::{
@@ -81,6 +84,36 @@ impl Record {
     }
 }
 
+/// Controls how executed transaction batches are accumulated into entries. A pending entry is
+/// recorded once it holds at least `target_transactions` transactions, once `max_delay` has
+/// passed since its first batch arrived, or before the next tick, whichever comes first. The
+/// default records every batch as its own entry.
+#[derive(Clone, Debug)]
+pub struct EntryBatchConfig {
+    pub target_transactions: usize,
+    pub max_delay: Duration,
+}
+
+impl Default for EntryBatchConfig {
+    fn default() -> Self {
+        Self {
+            target_transactions: 1,
+            max_delay: Duration::from_millis(0),
+        }
+    }
+}
+
+// Batches queued to be recorded as one entry. Their senders are answered only once the entry is
+// recorded, so banking threads keep the accounts locked until then and the batches sharing an
+// entry never conflict with each other.
+struct PendingEntry {
+    slot: Slot,
+    mixin: Hash,
+    transactions: Vec<Transaction>,
+    senders: Vec<CrossbeamSender<Result<()>>>,
+    deadline: Instant,
+}
+
 pub struct TransactionRecorder {
     // shared by all users of PohRecorder
     pub record_sender: CrossbeamSender<Record>,
@@ -182,6 +215,10 @@ pub struct PohRecorder {
     last_metric: Instant,
     record_sender: CrossbeamSender<Record>,
     pub is_exited: Arc<AtomicBool>,
+    entry_batch_config: EntryBatchConfig,
+    pending_entry: Option<PendingEntry>,
+    slot_entries: u64,
+    slot_transactions: u64,
 }
 
 impl PohRecorder {
@@ -207,6 +244,17 @@ impl PohRecorder {
                 ("slot", bank.slot(), i64),
                 ("elapsed", working_bank.start.elapsed().as_millis(), i64),
             );
+            datapoint_info!(
+                "poh_recorder-slot_entries",
+                ("slot", bank.slot(), i64),
+                ("entries", self.slot_entries, i64),
+                ("transactions", self.slot_transactions, i64),
+                (
+                    "transactions_per_entry",
+                    self.slot_transactions / self.slot_entries.max(1),
+                    i64
+                ),
+            );
         }
 
         if let Some(ref signal) = self.clear_bank_signal {
@@ -399,6 +447,8 @@ impl PohRecorder {
         trace!("new working bank");
         assert_eq!(working_bank.bank.ticks_per_slot(), self.ticks_per_slot());
         self.working_bank = Some(working_bank);
+        self.slot_entries = 0;
+        self.slot_transactions = 0;
         // TODO: adjust the working_bank.start time based on number of ticks
         // that have already elapsed based on current tick height.
         let _ = self.flush_cache(false);
@@ -481,6 +531,8 @@ impl PohRecorder {
     }
 
     pub fn tick(&mut self) {
+        // Batches waiting for an entry must not straddle a tick
+        self.record_pending_entry();
         let now = Instant::now();
         let (poh_entry, target_time) = {
             let mut poh_l = self.poh.lock().unwrap();
@@ -604,8 +656,11 @@ impl PohRecorder {
                     let bank_clone = working_bank.bank.clone();
                     self.prepare_send_us += timing::duration_as_us(&now.elapsed());
                     let now = Instant::now();
+                    let num_transactions = entry.transactions.len() as u64;
                     self.sender.send((bank_clone, (entry, self.tick_height)))?;
                     self.send_us += timing::duration_as_us(&now.elapsed());
+                    self.slot_entries += 1;
+                    self.slot_transactions += num_transactions;
                     return Ok(());
                 }
             }
@@ -616,6 +671,68 @@ impl PohRecorder {
         }
     }
 
+    pub fn set_entry_batch_config(&mut self, entry_batch_config: EntryBatchConfig) {
+        self.record_pending_entry();
+        self.entry_batch_config = entry_batch_config;
+    }
+
+    /// Queues `record` to be recorded along with other batches as a single entry, and records
+    /// that entry once it reached the target size. The record's sender gets the result of
+    /// recording the entry.
+    pub fn queue_record(&mut self, record: Record) {
+        if self
+            .pending_entry
+            .as_ref()
+            .map(|pending| pending.slot != record.slot)
+            .unwrap_or(false)
+        {
+            self.record_pending_entry();
+        }
+        match &mut self.pending_entry {
+            Some(pending) => {
+                pending.transactions.extend(record.transactions);
+                pending.senders.push(record.sender);
+            }
+            None => {
+                self.pending_entry = Some(PendingEntry {
+                    slot: record.slot,
+                    mixin: record.mixin,
+                    transactions: record.transactions,
+                    senders: vec![record.sender],
+                    deadline: Instant::now() + self.entry_batch_config.max_delay,
+                });
+            }
+        }
+        let num_transactions = self
+            .pending_entry
+            .as_ref()
+            .map(|pending| pending.transactions.len())
+            .unwrap_or(0);
+        if num_transactions >= self.entry_batch_config.target_transactions {
+            self.record_pending_entry();
+        }
+    }
+
+    /// When the pending entry, if any, has to be recorded regardless of its size.
+    pub fn pending_entry_deadline(&self) -> Option<Instant> {
+        self.pending_entry.as_ref().map(|pending| pending.deadline)
+    }
+
+    /// Records the pending entry, if any, and reports the result to all of its batches.
+    pub fn record_pending_entry(&mut self) {
+        if let Some(pending) = self.pending_entry.take() {
+            let mixin = if pending.senders.len() == 1 {
+                pending.mixin
+            } else {
+                hash_transactions(&pending.transactions)
+            };
+            let res = self.record(pending.slot, mixin, pending.transactions);
+            for sender in pending.senders {
+                let _ = sender.send(res.clone());
+            }
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn new_with_clear_signal(
         tick_height: u64,
@@ -678,6 +795,10 @@ impl PohRecorder {
                 last_metric: Instant::now(),
                 record_sender,
                 is_exited,
+                entry_batch_config: EntryBatchConfig::default(),
+                pending_entry: None,
+                slot_entries: 0,
+                slot_transactions: 0,
             },
             receiver,
             record_receiver,
@@ -1140,6 +1261,76 @@ mod tests {
         Blockstore::destroy(&ledger_path).unwrap();
     }
 
+    #[test]
+    fn test_poh_recorder_queue_record_batches_entries() {
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&ledger_path)
+                .expect("Expected to be able to open database ledger");
+            let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(2);
+            let bank = Arc::new(Bank::new(&genesis_config));
+            let prev_hash = bank.last_blockhash();
+            let (mut poh_recorder, entry_receiver, _record_receiver) = PohRecorder::new(
+                0,
+                prev_hash,
+                0,
+                Some((4, 4)),
+                bank.ticks_per_slot(),
+                &Pubkey::default(),
+                &Arc::new(blockstore),
+                &Arc::new(LeaderScheduleCache::new_from_bank(&bank)),
+                &Arc::new(PohConfig::default()),
+                Arc::new(AtomicBool::default()),
+            );
+            poh_recorder.set_entry_batch_config(EntryBatchConfig {
+                target_transactions: 3,
+                max_delay: Duration::from_secs(60),
+            });
+
+            let start = Arc::new(Instant::now());
+            let working_bank = WorkingBank {
+                bank: bank.clone(),
+                start,
+                min_tick_height: 1,
+                max_tick_height: 3,
+            };
+            poh_recorder.set_working_bank(working_bank);
+            poh_recorder.tick();
+
+            let queue = |poh_recorder: &mut PohRecorder, transactions: Vec<Transaction>| {
+                let (sender, receiver) = unbounded();
+                let mixin = hash_transactions(&transactions);
+                poh_recorder.queue_record(Record::new(mixin, transactions, bank.slot(), sender));
+                receiver
+            };
+
+            // Batches accumulate until the entry reaches the target size
+            let first = queue(&mut poh_recorder, vec![test_tx()]);
+            assert!(poh_recorder.pending_entry_deadline().is_some());
+            let second = queue(&mut poh_recorder, vec![test_tx(), test_tx()]);
+            assert!(poh_recorder.pending_entry_deadline().is_none());
+            assert!(first.try_recv().unwrap().is_ok());
+            assert!(second.try_recv().unwrap().is_ok());
+            let (_bank, (tick, _tick_height)) = entry_receiver.recv().unwrap();
+            assert!(tick.is_tick());
+            let (_bank, (e, _tick_height)) = entry_receiver.recv().unwrap();
+            assert_eq!(e.transactions.len(), 3);
+            // The mixin covers all of the entry's transactions
+            assert!(e.verify(&tick.hash));
+
+            // A tick records whatever is pending first
+            let third = queue(&mut poh_recorder, vec![test_tx()]);
+            assert!(third.try_recv().is_err());
+            poh_recorder.tick();
+            assert!(third.try_recv().unwrap().is_ok());
+            let (_bank, (e, _tick_height)) = entry_receiver.recv().unwrap();
+            assert_eq!(e.transactions.len(), 1);
+            let (_bank, (e, _tick_height)) = entry_receiver.recv().unwrap();
+            assert!(e.is_tick());
+        }
+        Blockstore::destroy(&ledger_path).unwrap();
+    }
+
     #[test]
     fn test_poh_recorder_record_at_max_fails() {
         let ledger_path = get_tmp_ledger_path!();
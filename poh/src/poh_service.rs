@@ -177,19 +177,25 @@ impl PohService {
         timeout: Duration,
     ) {
         let record = record_receiver.recv_timeout(timeout);
+        let mut poh_recorder = poh_recorder.lock().unwrap();
         if let Ok(record) = record {
-            if record
-                .sender
-                .send(poh_recorder.lock().unwrap().record(
-                    record.slot,
-                    record.mixin,
-                    record.transactions,
-                ))
-                .is_err()
-            {
-                panic!("Error returning mixin hash");
+            poh_recorder.queue_record(record);
+            // take whatever else is queued up so it can share the entry
+            for record in record_receiver.try_iter() {
+                poh_recorder.queue_record(record);
             }
         }
+        Self::record_expired_entry(&mut poh_recorder);
+    }
+
+    fn record_expired_entry(poh_recorder: &mut PohRecorder) {
+        if poh_recorder
+            .pending_entry_deadline()
+            .map(|deadline| deadline <= Instant::now())
+            .unwrap_or(false)
+        {
+            poh_recorder.record_pending_entry();
+        }
     }
 
     fn short_lived_sleepy_tick_producer(
@@ -215,8 +221,10 @@ impl PohService {
     }
 
     // returns true if we need to tick
+    #[allow(clippy::too_many_arguments)]
     fn record_or_hash(
         next_record: &mut Option<Record>,
+        pending_entry_deadline: &mut Option<Instant>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
         timing: &mut PohTiming,
         record_receiver: &Receiver<Record>,
@@ -234,12 +242,8 @@ impl PohService {
                 timing.total_lock_time_ns += lock_time.as_ns();
                 let mut record_time = Measure::start("record");
                 loop {
-                    let res = poh_recorder_l.record(
-                        record.slot,
-                        record.mixin,
-                        std::mem::take(&mut record.transactions),
-                    );
-                    let _ = record.sender.send(res); // what do we do on failure here? Ignore for now.
+                    // the record's sender is answered once the entry holding it is recorded
+                    poh_recorder_l.queue_record(record);
                     timing.num_hashes += 1; // note: may have also ticked inside record
 
                     let new_record_result = record_receiver.try_recv();
@@ -253,6 +257,8 @@ impl PohService {
                         }
                     }
                 }
+                Self::record_expired_entry(&mut poh_recorder_l);
+                *pending_entry_deadline = poh_recorder_l.pending_entry_deadline();
                 record_time.stop();
                 timing.total_record_time_us += record_time.as_us();
                 // PohRecorder.record would have ticked if it needed to, so should_tick will be false
@@ -274,6 +280,13 @@ impl PohService {
                         // nothing else can be done. tick required.
                         return true;
                     }
+                    if pending_entry_deadline
+                        .map(|deadline| deadline <= Instant::now())
+                        .unwrap_or(false)
+                    {
+                        // the pending entry has to be recorded before anything else
+                        break;
+                    }
                     // check to see if a record request has been sent
                     if let Ok(record) = record_receiver.try_recv() {
                         // remember the record we just received as the next record to occur
@@ -316,9 +329,11 @@ impl PohService {
         let poh = poh_recorder.lock().unwrap().poh.clone();
         let mut timing = PohTiming::new();
         let mut next_record = None;
+        let mut pending_entry_deadline = None;
         loop {
             let should_tick = Self::record_or_hash(
                 &mut next_record,
+                &mut pending_entry_deadline,
                 &poh_recorder,
                 &mut timing,
                 &record_receiver,
@@ -326,6 +341,14 @@ impl PohService {
                 &poh,
                 target_ns_per_tick,
             );
+            if next_record.is_none()
+                && pending_entry_deadline
+                    .map(|deadline| deadline <= Instant::now())
+                    .unwrap_or(false)
+            {
+                poh_recorder.lock().unwrap().record_pending_entry();
+                pending_entry_deadline = None;
+            }
             if should_tick {
                 // Lock PohRecorder only for the final hash. record_or_hash will lock PohRecorder for record calls but not for hashing.
                 {
@@ -334,8 +357,10 @@ impl PohService {
                     lock_time.stop();
                     timing.total_lock_time_ns += lock_time.as_ns();
                     let mut tick_time = Measure::start("tick");
+                    // also records the pending entry, if any
                     poh_recorder_l.tick();
                     tick_time.stop();
+                    pending_entry_deadline = None;
                     timing.total_tick_time_ns += tick_time.as_ns();
                 }
                 timing.num_ticks += 1;
@@ -2,18 +2,15 @@
 
 use {
     crate::{
-        max_slots::MaxSlots,
-        optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
-        parsed_token_accounts::*,
-        rpc_health::*,
-        send_transaction_service::{SendTransactionService, TransactionInfo},
+        max_slots::MaxSlots, optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
+        parsed_token_accounts::*, rpc_health::*,
     },
     bincode::{config::Options, serialize},
     jsonrpc_core::{types::error, Error, Metadata, Result},
     jsonrpc_derive::rpc,
     serde::{Deserialize, Serialize},
     solana_account_decoder::{
-        parse_token::{spl_token_id_v2_0, token_amount_to_ui_amount, UiTokenAmount},
+        parse_token::{token_amount_to_ui_amount, UiTokenAmount},
         UiAccount, UiAccountEncoding, UiDataSliceConfig,
     },
     solana_client::{
@@ -25,8 +22,10 @@ use {
         rpc_request::{
             TokenAccountsFilter, DELINQUENT_VALIDATOR_SLOT_DISTANCE,
             MAX_GET_CONFIRMED_BLOCKS_RANGE, MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT,
-            MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS_SLOT_RANGE, MAX_GET_PROGRAM_ACCOUNT_FILTERS,
-            MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS, MAX_GET_SLOT_LEADERS, MAX_MULTIPLE_ACCOUNTS,
+            MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS_SLOT_RANGE,
+            MAX_GET_DUPLICATE_SLOT_PROOFS_LIMIT, MAX_GET_PROGRAM_ACCOUNT_FILTERS,
+            MAX_GET_SIGNATURES_FOR_ADDRESS_PROGRAM_IDS, MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS,
+            MAX_GET_SLOT_LEADERS, MAX_MULTIPLE_ACCOUNTS, MAX_SIMULATE_BUNDLE_TRANSACTIONS,
             NUM_LARGEST_ACCOUNTS,
         },
         rpc_response::Response as RpcResponse,
@@ -41,9 +40,10 @@ use {
     solana_metrics::inc_new_counter_info,
     solana_perf::packet::PACKET_DATA_SIZE,
     solana_runtime::{
+        account_overrides::AccountOverrides,
         accounts::AccountAddressFilter,
         accounts_index::{AccountIndex, AccountSecondaryIndexes, IndexKey},
-        bank::{Bank, TransactionSimulationResult},
+        bank::{Bank, BundleTransactionSimulationResult, TransactionSimulationResult},
         bank_forks::BankForks,
         commitment::{BlockCommitmentArray, BlockCommitmentCache, CommitmentSlots},
         inline_spl_token_v2_0::{SPL_TOKEN_ACCOUNT_MINT_OFFSET, SPL_TOKEN_ACCOUNT_OWNER_OFFSET},
@@ -63,15 +63,21 @@ use {
         pubkey::Pubkey,
         sanitize::Sanitize,
         signature::{Keypair, Signature, Signer},
+        spl_token_ids::is_known_spl_token_id,
         stake::state::StakeState,
         stake_history::StakeHistory,
         system_instruction,
         sysvar::stake_history,
         transaction::{self, Transaction, TransactionError},
     },
+    solana_send_transaction_service::{
+        send_transaction_service::{SendTransactionService, TransactionInfo},
+        tpu_info::NullTpuInfo,
+    },
+    solana_storage_bigtable::LedgerStorageAdapter,
     solana_transaction_status::{
-        EncodedConfirmedTransaction, Reward, RewardType, TransactionConfirmationStatus,
-        TransactionStatus, UiConfirmedBlock, UiTransactionEncoding,
+        AddressSignatureFilter, AddressSignatures, EncodedConfirmedTransaction, Reward, RewardType,
+        TransactionConfirmationStatus, TransactionStatus, UiConfirmedBlock, UiTransactionEncoding,
     },
     solana_vote_program::vote_state::{VoteState, MAX_LOCKOUT_HISTORY},
     spl_token_v2_0::{
@@ -82,6 +88,7 @@ use {
         cmp::{max, min},
         collections::{HashMap, HashSet},
         net::SocketAddr,
+        path::PathBuf,
         str::FromStr,
         sync::{
             atomic::{AtomicBool, AtomicU64, Ordering},
@@ -134,7 +141,8 @@ pub struct JsonRpcConfig {
     pub faucet_addr: Option<SocketAddr>,
     pub health_check_slot_distance: u64,
     pub enable_bigtable_ledger_storage: bool,
-    pub enable_bigtable_ledger_upload: bool,
+    pub enable_ledger_storage_upload: bool,
+    pub local_ledger_storage_path: Option<PathBuf>,
     pub max_multiple_accounts: Option<usize>,
     pub account_indexes: AccountSecondaryIndexes,
     pub rpc_threads: usize,
@@ -157,7 +165,7 @@ pub struct JsonRpcRequestProcessor {
     genesis_hash: Hash,
     transaction_sender: Arc<Mutex<Sender<TransactionInfo>>>,
     runtime: Arc<Runtime>,
-    bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
+    ledger_storage: Option<Arc<dyn LedgerStorageAdapter>>,
     optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
     largest_accounts_cache: Arc<RwLock<LargestAccountsCache>>,
     max_slots: Arc<MaxSlots>,
@@ -243,7 +251,7 @@ impl JsonRpcRequestProcessor {
         cluster_info: Arc<ClusterInfo>,
         genesis_hash: Hash,
         runtime: Arc<Runtime>,
-        bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
+        ledger_storage: Option<Arc<dyn LedgerStorageAdapter>>,
         optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
         largest_accounts_cache: Arc<RwLock<LargestAccountsCache>>,
         max_slots: Arc<MaxSlots>,
@@ -264,7 +272,7 @@ impl JsonRpcRequestProcessor {
                 genesis_hash,
                 transaction_sender: Arc::new(Mutex::new(sender)),
                 runtime,
-                bigtable_ledger_storage,
+                ledger_storage,
                 optimistically_confirmed_bank,
                 largest_accounts_cache,
                 max_slots,
@@ -275,6 +283,10 @@ impl JsonRpcRequestProcessor {
         )
     }
 
+    pub(crate) fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
     // Useful for unit testing
     pub fn new_from_bank(bank: &Arc<Bank>) -> Self {
         let genesis_hash = bank.hash();
@@ -287,7 +299,14 @@ impl JsonRpcRequestProcessor {
         let cluster_info = Arc::new(ClusterInfo::default());
         let tpu_address = cluster_info.my_contact_info().tpu;
         let (sender, receiver) = channel();
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
+        SendTransactionService::new::<NullTpuInfo>(
+            tpu_address,
+            &bank_forks,
+            None,
+            receiver,
+            1000,
+            1,
+        );
 
         Self {
             config: JsonRpcConfig::default(),
@@ -305,7 +324,7 @@ impl JsonRpcRequestProcessor {
             genesis_hash,
             transaction_sender: Arc::new(Mutex::new(sender)),
             runtime: Arc::new(Runtime::new().expect("Runtime")),
-            bigtable_ledger_storage: None,
+            ledger_storage: None,
             optimistically_confirmed_bank: Arc::new(RwLock::new(OptimisticallyConfirmedBank {
                 bank: bank.clone(),
             })),
@@ -373,15 +392,15 @@ impl JsonRpcRequestProcessor {
         check_slice_and_encoding(&encoding, data_slice_config.is_some())?;
         let keyed_accounts = {
             if let Some(owner) = get_spl_token_owner_filter(program_id, &filters) {
-                self.get_filtered_spl_token_accounts_by_owner(&bank, &owner, filters)?
+                self.get_filtered_spl_token_accounts_by_owner(&bank, program_id, &owner, filters)?
             } else if let Some(mint) = get_spl_token_mint_filter(program_id, &filters) {
-                self.get_filtered_spl_token_accounts_by_mint(&bank, &mint, filters)?
+                self.get_filtered_spl_token_accounts_by_mint(&bank, program_id, &mint, filters)?
             } else {
                 self.get_filtered_program_accounts(&bank, program_id, filters)?
             }
         };
         let result =
-            if program_id == &spl_token_id_v2_0() && encoding == UiAccountEncoding::JsonParsed {
+            if is_known_spl_token_id(program_id) && encoding == UiAccountEncoding::JsonParsed {
                 get_parsed_token_accounts(bank.clone(), keyed_accounts.into_iter()).collect()
             } else {
                 let mut encoded_accounts = vec![];
@@ -416,7 +435,7 @@ impl JsonRpcRequestProcessor {
         // Rewards for this epoch are found in the first confirmed block of the next epoch
         let first_slot_in_epoch = epoch_schedule.get_first_slot_in_epoch(epoch.saturating_add(1));
         if first_slot_in_epoch < first_available_block {
-            if self.bigtable_ledger_storage.is_some() {
+            if self.ledger_storage.is_some() {
                 return Err(RpcCustomError::LongTermStorageSlotSkipped {
                     slot: first_slot_in_epoch,
                 }
@@ -506,6 +525,10 @@ impl JsonRpcRequestProcessor {
         }
     }
 
+    pub fn get_epoch_info(&self, commitment: Option<CommitmentConfig>) -> EpochInfo {
+        self.bank(commitment).get_epoch_info()
+    }
+
     pub fn get_epoch_schedule(&self) -> EpochSchedule {
         // Since epoch schedule data comes from the genesis config, any commitment level should be
         // fine
@@ -950,10 +973,10 @@ impl JsonRpcRequestProcessor {
                 let result = self.blockstore.get_rooted_block(slot, true);
                 self.check_blockstore_root(&result, slot)?;
                 if result.is_err() {
-                    if let Some(bigtable_ledger_storage) = &self.bigtable_ledger_storage {
+                    if let Some(ledger_storage) = &self.ledger_storage {
                         let bigtable_result = self
                             .runtime
-                            .block_on(bigtable_ledger_storage.get_confirmed_block(slot));
+                            .block_on(ledger_storage.get_confirmed_block(slot));
                         self.check_bigtable_result(&bigtable_result)?;
                         return Ok(bigtable_result.ok().map(|confirmed_block| {
                             confirmed_block.configure(encoding, transaction_details, show_rewards)
@@ -1037,11 +1060,11 @@ impl JsonRpcRequestProcessor {
             // If the starting slot is lower than what's available in blockstore assume the entire
             // [start_slot..end_slot] can be fetched from BigTable. This range should not ever run
             // into unfinalized confirmed blocks due to MAX_GET_CONFIRMED_BLOCKS_RANGE
-            if let Some(bigtable_ledger_storage) = &self.bigtable_ledger_storage {
+            if let Some(ledger_storage) = &self.ledger_storage {
                 return self
                     .runtime
                     .block_on(
-                        bigtable_ledger_storage
+                        ledger_storage
                             .get_confirmed_blocks(start_slot, (end_slot - start_slot) as usize + 1), // increment limit by 1 to ensure returned range is inclusive of both start_slot and end_slot
                     )
                     .map(|mut bigtable_blocks| {
@@ -1102,10 +1125,10 @@ impl JsonRpcRequestProcessor {
             // If the starting slot is lower than what's available in blockstore assume the entire
             // range can be fetched from BigTable. This range should not ever run into unfinalized
             // confirmed blocks due to MAX_GET_CONFIRMED_BLOCKS_RANGE
-            if let Some(bigtable_ledger_storage) = &self.bigtable_ledger_storage {
+            if let Some(ledger_storage) = &self.ledger_storage {
                 return Ok(self
                     .runtime
-                    .block_on(bigtable_ledger_storage.get_confirmed_blocks(start_slot, limit))
+                    .block_on(ledger_storage.get_confirmed_blocks(start_slot, limit))
                     .unwrap_or_default());
             }
         }
@@ -1155,10 +1178,10 @@ impl JsonRpcRequestProcessor {
             let result = self.blockstore.get_block_time(slot);
             self.check_blockstore_root(&result, slot)?;
             if result.is_err() || matches!(result, Ok(None)) {
-                if let Some(bigtable_ledger_storage) = &self.bigtable_ledger_storage {
+                if let Some(ledger_storage) = &self.ledger_storage {
                     let bigtable_result = self
                         .runtime
-                        .block_on(bigtable_ledger_storage.get_confirmed_block(slot));
+                        .block_on(ledger_storage.get_confirmed_block(slot));
                     self.check_bigtable_result(&bigtable_result)?;
                     return Ok(bigtable_result
                         .ok()
@@ -1244,9 +1267,9 @@ impl JsonRpcRequestProcessor {
                         }
                     })
                     .or_else(|| {
-                        if let Some(bigtable_ledger_storage) = &self.bigtable_ledger_storage {
+                        if let Some(ledger_storage) = &self.ledger_storage {
                             self.runtime
-                                .block_on(bigtable_ledger_storage.get_signature_status(&signature))
+                                .block_on(ledger_storage.get_signature_status(&signature))
                                 .map(Some)
                                 .unwrap_or(None)
                         } else {
@@ -1345,10 +1368,17 @@ impl JsonRpcRequestProcessor {
                     }
                 }
                 None => {
-                    if let Some(bigtable_ledger_storage) = &self.bigtable_ledger_storage {
+                    if let Some(archived_transaction) = self
+                        .blockstore
+                        .get_archived_transaction(signature)
+                        .unwrap_or(None)
+                    {
+                        return Ok(Some(archived_transaction.encode(encoding)));
+                    }
+                    if let Some(ledger_storage) = &self.ledger_storage {
                         return Ok(self
                             .runtime
-                            .block_on(bigtable_ledger_storage.get_confirmed_transaction(&signature))
+                            .block_on(ledger_storage.get_confirmed_transaction(&signature))
                             .unwrap_or(None)
                             .map(|confirmed| confirmed.encode(encoding)));
                     }
@@ -1367,7 +1397,7 @@ impl JsonRpcRequestProcessor {
         end_slot: Slot,
     ) -> Vec<Signature> {
         if self.config.enable_rpc_transaction_history {
-            // TODO: Add bigtable_ledger_storage support as a part of
+            // TODO: Add ledger_storage support as a part of
             // https://github.com/solana-labs/solana/pull/10928
             let end_slot = min(
                 end_slot,
@@ -1389,9 +1419,10 @@ impl JsonRpcRequestProcessor {
         address: Pubkey,
         mut before: Option<Signature>,
         until: Option<Signature>,
+        filter: AddressSignatureFilter,
         mut limit: usize,
         commitment: Option<CommitmentConfig>,
-    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    ) -> Result<AddressSignatures<RpcConfirmedTransactionStatusWithSignature>> {
         let commitment = commitment.unwrap_or_default();
         check_is_at_least_confirmed(commitment)?;
 
@@ -1408,38 +1439,66 @@ impl JsonRpcRequestProcessor {
                 highest_confirmed_root
             };
 
-            let mut results = self
+            let AddressSignatures {
+                signatures: mut results,
+                mut resume_before,
+            } = self
                 .blockstore
-                .get_confirmed_signatures_for_address2(address, highest_slot, before, until, limit)
+                .get_filtered_signatures_for_address(
+                    address,
+                    highest_slot,
+                    before,
+                    until,
+                    &filter,
+                    limit,
+                )
                 .map_err(|err| Error::invalid_params(format!("{}", err)))?;
 
-            if results.len() < limit {
-                if let Some(bigtable_ledger_storage) = &self.bigtable_ledger_storage {
+            if results.len() < limit && resume_before.is_none() {
+                if let Some(ledger_storage) = &self.ledger_storage {
                     if !results.is_empty() {
                         limit -= results.len();
                         before = results.last().map(|x| x.signature);
                     }
 
-                    let bigtable_results = self.runtime.block_on(
-                        bigtable_ledger_storage.get_confirmed_signatures_for_address(
-                            &address,
-                            before.as_ref(),
-                            until.as_ref(),
-                            limit,
-                        ),
-                    );
+                    let bigtable_results =
+                        self.runtime
+                            .block_on(ledger_storage.get_confirmed_signatures_for_address(
+                                &address,
+                                before.as_ref(),
+                                until.as_ref(),
+                                &filter,
+                                limit,
+                            ));
                     match bigtable_results {
                         Ok(bigtable_results) => {
-                            results.extend(bigtable_results.into_iter().map(|x| x.0));
+                            results.extend(bigtable_results.signatures.into_iter().map(|x| x.0));
+                            resume_before = bigtable_results.resume_before;
                         }
                         Err(err) => {
                             warn!("{:?}", err);
                         }
                     }
+                } else {
+                    // The filtered archive only holds some of the purged transactions, so it is
+                    // not chained with ledger storage, which holds all of them
+                    let archived_results = self
+                        .blockstore
+                        .get_archived_signatures_for_address(
+                            address,
+                            highest_slot,
+                            results.last().map(|x| x.signature).or(before),
+                            until,
+                            &filter,
+                            limit - results.len(),
+                        )
+                        .map_err(|err| Error::invalid_params(format!("{}", err)))?;
+                    results.extend(archived_results.signatures);
+                    resume_before = archived_results.resume_before;
                 }
             }
 
-            Ok(results
+            let signatures = results
                 .into_iter()
                 .map(|x| {
                     let mut item: RpcConfirmedTransactionStatusWithSignature = x.into();
@@ -1456,7 +1515,11 @@ impl JsonRpcRequestProcessor {
                     }
                     item
                 })
-                .collect())
+                .collect();
+            Ok(AddressSignatures {
+                signatures,
+                resume_before,
+            })
         } else {
             Err(RpcCustomError::TransactionHistoryNotAvailable.into())
         }
@@ -1468,10 +1531,10 @@ impl JsonRpcRequestProcessor {
             .get_first_available_block()
             .unwrap_or_default();
 
-        if let Some(bigtable_ledger_storage) = &self.bigtable_ledger_storage {
+        if let Some(ledger_storage) = &self.ledger_storage {
             let bigtable_slot = self
                 .runtime
-                .block_on(bigtable_ledger_storage.get_first_available_block())
+                .block_on(ledger_storage.get_first_available_block())
                 .unwrap_or(None)
                 .unwrap_or(slot);
 
@@ -1573,14 +1636,13 @@ impl JsonRpcRequestProcessor {
             Error::invalid_params("Invalid param: could not find account".to_string())
         })?;
 
-        if account.owner() != &spl_token_id_v2_0() {
+        if !is_known_spl_token_id(account.owner()) {
             return Err(Error::invalid_params(
-                "Invalid param: not a v2.0 Token account".to_string(),
+                "Invalid param: not a Token account".to_string(),
             ));
         }
-        let token_account = TokenAccount::unpack(account.data()).map_err(|_| {
-            Error::invalid_params("Invalid param: not a v2.0 Token account".to_string())
-        })?;
+        let token_account = TokenAccount::unpack(account.data())
+            .map_err(|_| Error::invalid_params("Invalid param: not a Token account".to_string()))?;
         let mint = &Pubkey::from_str(&token_account.mint.to_string())
             .expect("Token account mint should be convertible to Pubkey");
         let (_, decimals) = get_mint_owner_and_decimals(&bank, mint)?;
@@ -1597,9 +1659,9 @@ impl JsonRpcRequestProcessor {
         let mint_account = bank.get_account(mint).ok_or_else(|| {
             Error::invalid_params("Invalid param: could not find account".to_string())
         })?;
-        if mint_account.owner() != &spl_token_id_v2_0() {
+        if !is_known_spl_token_id(mint_account.owner()) {
             return Err(Error::invalid_params(
-                "Invalid param: not a v2.0 Token mint".to_string(),
+                "Invalid param: not a Token mint".to_string(),
             ));
         }
         let mint = Mint::unpack(mint_account.data()).map_err(|_| {
@@ -1617,13 +1679,13 @@ impl JsonRpcRequestProcessor {
     ) -> Result<RpcResponse<Vec<RpcTokenAccountBalance>>> {
         let bank = self.bank(commitment);
         let (mint_owner, decimals) = get_mint_owner_and_decimals(&bank, mint)?;
-        if mint_owner != spl_token_id_v2_0() {
+        if !is_known_spl_token_id(&mint_owner) {
             return Err(Error::invalid_params(
-                "Invalid param: not a v2.0 Token mint".to_string(),
+                "Invalid param: not a Token mint".to_string(),
             ));
         }
         let mut token_balances: Vec<RpcTokenAccountBalance> = self
-            .get_filtered_spl_token_accounts_by_mint(&bank, mint, vec![])?
+            .get_filtered_spl_token_accounts_by_mint(&bank, &mint_owner, mint, vec![])?
             .into_iter()
             .map(|(address, account)| {
                 let amount = TokenAccount::unpack(account.data())
@@ -1659,7 +1721,7 @@ impl JsonRpcRequestProcessor {
         let encoding = config.encoding.unwrap_or(UiAccountEncoding::Binary);
         let data_slice_config = config.data_slice;
         check_slice_and_encoding(&encoding, data_slice_config.is_some())?;
-        let (_, mint) = get_token_program_id_and_mint(&bank, token_account_filter)?;
+        let (token_program_id, mint) = get_token_program_id_and_mint(&bank, token_account_filter)?;
 
         let mut filters = vec![];
         if let Some(mint) = mint {
@@ -1671,8 +1733,12 @@ impl JsonRpcRequestProcessor {
             }));
         }
 
-        let keyed_accounts =
-            self.get_filtered_spl_token_accounts_by_owner(&bank, owner, filters)?;
+        let keyed_accounts = self.get_filtered_spl_token_accounts_by_owner(
+            &bank,
+            &token_program_id,
+            owner,
+            filters,
+        )?;
         let accounts = if encoding == UiAccountEncoding::JsonParsed {
             get_parsed_token_accounts(bank.clone(), keyed_accounts.into_iter()).collect()
         } else {
@@ -1724,7 +1790,7 @@ impl JsonRpcRequestProcessor {
         ];
         // Optional filter on Mint address, uses mint account index for scan
         let keyed_accounts = if let Some(mint) = mint {
-            self.get_filtered_spl_token_accounts_by_mint(&bank, &mint, filters)?
+            self.get_filtered_spl_token_accounts_by_mint(&bank, &token_program_id, &mint, filters)?
         } else {
             // Filter on Token Account state
             filters.push(RpcFilterType::DataSize(
@@ -1800,6 +1866,7 @@ impl JsonRpcRequestProcessor {
     fn get_filtered_spl_token_accounts_by_owner(
         &self,
         bank: &Arc<Bank>,
+        program_id: &Pubkey,
         owner_key: &Pubkey,
         mut filters: Vec<RpcFilterType>,
     ) -> RpcCustomResult<Vec<(Pubkey, AccountSharedData)>> {
@@ -1831,7 +1898,7 @@ impl JsonRpcRequestProcessor {
             }
             Ok(bank
                 .get_filtered_indexed_accounts(&IndexKey::SplTokenOwner(*owner_key), |account| {
-                    account.owner() == &spl_token_id_v2_0()
+                    account.owner() == program_id
                         && filters.iter().all(|filter_type| match filter_type {
                             RpcFilterType::DataSize(size) => account.data().len() as u64 == *size,
                             RpcFilterType::Memcmp(compare) => compare.bytes_match(account.data()),
@@ -1841,7 +1908,7 @@ impl JsonRpcRequestProcessor {
                     message: e.to_string(),
                 })?)
         } else {
-            self.get_filtered_program_accounts(bank, &spl_token_id_v2_0(), filters)
+            self.get_filtered_program_accounts(bank, program_id, filters)
         }
     }
 
@@ -1849,6 +1916,7 @@ impl JsonRpcRequestProcessor {
     fn get_filtered_spl_token_accounts_by_mint(
         &self,
         bank: &Arc<Bank>,
+        program_id: &Pubkey,
         mint_key: &Pubkey,
         mut filters: Vec<RpcFilterType>,
     ) -> RpcCustomResult<Vec<(Pubkey, AccountSharedData)>> {
@@ -1879,7 +1947,7 @@ impl JsonRpcRequestProcessor {
             }
             Ok(bank
                 .get_filtered_indexed_accounts(&IndexKey::SplTokenMint(*mint_key), |account| {
-                    account.owner() == &spl_token_id_v2_0()
+                    account.owner() == program_id
                         && filters.iter().all(|filter_type| match filter_type {
                             RpcFilterType::DataSize(size) => account.data().len() as u64 == *size,
                             RpcFilterType::Memcmp(compare) => compare.bytes_match(account.data()),
@@ -1889,7 +1957,7 @@ impl JsonRpcRequestProcessor {
                     message: e.to_string(),
                 })?)
         } else {
-            self.get_filtered_program_accounts(bank, &spl_token_id_v2_0(), filters)
+            self.get_filtered_program_accounts(bank, program_id, filters)
         }
     }
 }
@@ -1992,7 +2060,7 @@ fn get_encoded_account(
 ) -> Result<Option<UiAccount>> {
     match bank.get_account(pubkey) {
         Some(account) => {
-            let response = if account.owner() == &spl_token_id_v2_0()
+            let response = if is_known_spl_token_id(account.owner())
                 && encoding == UiAccountEncoding::JsonParsed
             {
                 get_parsed_token_account(bank.clone(), pubkey, account)
@@ -2028,7 +2096,7 @@ fn encode_account<T: ReadableAccount>(
 }
 
 fn get_spl_token_owner_filter(program_id: &Pubkey, filters: &[RpcFilterType]) -> Option<Pubkey> {
-    if program_id != &spl_token_id_v2_0() {
+    if !is_known_spl_token_id(program_id) {
         return None;
     }
     let mut data_size_filter: Option<u64> = None;
@@ -2056,7 +2124,7 @@ fn get_spl_token_owner_filter(program_id: &Pubkey, filters: &[RpcFilterType]) ->
 }
 
 fn get_spl_token_mint_filter(program_id: &Pubkey, filters: &[RpcFilterType]) -> Option<Pubkey> {
-    if program_id != &spl_token_id_v2_0() {
+    if !is_known_spl_token_id(program_id) {
         return None;
     }
     let mut data_size_filter: Option<u64> = None;
@@ -2092,15 +2160,15 @@ fn get_token_program_id_and_mint(
     match token_account_filter {
         TokenAccountsFilter::Mint(mint) => {
             let (mint_owner, _) = get_mint_owner_and_decimals(bank, &mint)?;
-            if mint_owner != spl_token_id_v2_0() {
+            if !is_known_spl_token_id(&mint_owner) {
                 return Err(Error::invalid_params(
-                    "Invalid param: not a v2.0 Token mint".to_string(),
+                    "Invalid param: not a Token mint".to_string(),
                 ));
             }
             Ok((mint_owner, Some(mint)))
         }
         TokenAccountsFilter::ProgramId(program_id) => {
-            if program_id == spl_token_id_v2_0() {
+            if is_known_spl_token_id(&program_id) {
                 Ok((program_id, None))
             } else {
                 Err(Error::invalid_params(
@@ -2117,6 +2185,7 @@ fn _send_transaction(
     wire_transaction: Vec<u8>,
     last_valid_slot: Slot,
     durable_nonce_info: Option<(Pubkey, Hash)>,
+    max_retries: Option<usize>,
 ) -> Result<String> {
     if transaction.signatures.is_empty() {
         return Err(RpcCustomError::TransactionSignatureVerificationFailure.into());
@@ -2127,6 +2196,7 @@ fn _send_transaction(
         wire_transaction,
         last_valid_slot,
         durable_nonce_info,
+        max_retries,
     );
     meta.transaction_sender
         .lock()
@@ -2233,8 +2303,7 @@ pub mod rpc_minimal {
             commitment: Option<CommitmentConfig>,
         ) -> Result<EpochInfo> {
             debug!("get_epoch_info rpc request received");
-            let bank = meta.bank(commitment);
-            Ok(bank.get_epoch_info())
+            Ok(meta.get_epoch_info(commitment))
         }
 
         fn get_health(&self, meta: Self::Metadata) -> Result<String> {
@@ -2962,6 +3031,14 @@ pub mod rpc_full {
             limit: Option<usize>,
         ) -> Result<Vec<RpcPerfSample>>;
 
+        #[rpc(meta, name = "getDuplicateSlotProofs")]
+        fn get_duplicate_slot_proofs(
+            &self,
+            meta: Self::Metadata,
+            start_slot: Option<Slot>,
+            limit: Option<usize>,
+        ) -> Result<Vec<RpcDuplicateSlotProof>>;
+
         #[rpc(meta, name = "getGenesisHash")]
         fn get_genesis_hash(&self, meta: Self::Metadata) -> Result<String>;
 
@@ -3004,6 +3081,14 @@ pub mod rpc_full {
             config: Option<RpcSimulateTransactionConfig>,
         ) -> Result<RpcResponse<RpcSimulateTransactionResult>>;
 
+        #[rpc(meta, name = "simulateBundle")]
+        fn simulate_bundle(
+            &self,
+            meta: Self::Metadata,
+            data: Vec<String>,
+            config: Option<RpcSimulateBundleConfig>,
+        ) -> Result<RpcResponse<RpcSimulateBundleResult>>;
+
         #[rpc(meta, name = "minimumLedgerSlot")]
         fn minimum_ledger_slot(&self, meta: Self::Metadata) -> Result<Slot>;
 
@@ -3053,10 +3138,73 @@ pub mod rpc_full {
             config: Option<RpcSignaturesForAddressConfig>,
         ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>>;
 
+        #[rpc(meta, name = "getFilteredSignaturesForAddress")]
+        fn get_filtered_signatures_for_address(
+            &self,
+            meta: Self::Metadata,
+            address: String,
+            config: Option<RpcSignaturesForAddressConfig>,
+        ) -> Result<RpcFilteredSignaturesForAddress>;
+
         #[rpc(meta, name = "getFirstAvailableBlock")]
         fn get_first_available_block(&self, meta: Self::Metadata) -> Result<Slot>;
     }
 
+    fn signatures_for_address(
+        meta: &JsonRpcRequestProcessor,
+        address: String,
+        config: Option<RpcSignaturesForAddressConfig>,
+    ) -> Result<AddressSignatures<RpcConfirmedTransactionStatusWithSignature>> {
+        let address = verify_pubkey(&address)?;
+
+        let config = config.unwrap_or_default();
+        let before = config
+            .before
+            .map(|ref before| verify_signature(before))
+            .transpose()?;
+        let until = config
+            .until
+            .map(|ref until| verify_signature(until))
+            .transpose()?;
+        let limit = config
+            .limit
+            .unwrap_or(MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT);
+
+        if limit == 0 || limit > MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT {
+            return Err(Error::invalid_params(format!(
+                "Invalid limit; max {}",
+                MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT
+            )));
+        }
+
+        if let (Some(min_block_time), Some(max_block_time)) =
+            (config.min_block_time, config.max_block_time)
+        {
+            if min_block_time > max_block_time {
+                return Err(Error::invalid_params(
+                    "Invalid block time range; minBlockTime exceeds maxBlockTime",
+                ));
+            }
+        }
+        let program_ids = config.program_ids.unwrap_or_default();
+        if program_ids.len() > MAX_GET_SIGNATURES_FOR_ADDRESS_PROGRAM_IDS {
+            return Err(Error::invalid_params(format!(
+                "Too many program ids provided; max {}",
+                MAX_GET_SIGNATURES_FOR_ADDRESS_PROGRAM_IDS
+            )));
+        }
+        let filter = AddressSignatureFilter {
+            status: config.status,
+            min_block_time: config.min_block_time,
+            max_block_time: config.max_block_time,
+            program_ids: program_ids
+                .iter()
+                .map(|program_id| verify_pubkey(program_id))
+                .collect::<Result<Vec<_>>>()?,
+        };
+        meta.get_signatures_for_address(address, before, until, filter, limit, config.commitment)
+    }
+
     pub struct FullImpl;
     impl Full for FullImpl {
         type Metadata = JsonRpcRequestProcessor;
@@ -3094,6 +3242,42 @@ pub mod rpc_full {
                 .collect())
         }
 
+        fn get_duplicate_slot_proofs(
+            &self,
+            meta: Self::Metadata,
+            start_slot: Option<Slot>,
+            limit: Option<usize>,
+        ) -> Result<Vec<RpcDuplicateSlotProof>> {
+            debug!("get_duplicate_slot_proofs request received");
+
+            let limit = limit.unwrap_or(MAX_GET_DUPLICATE_SLOT_PROOFS_LIMIT);
+
+            if limit > MAX_GET_DUPLICATE_SLOT_PROOFS_LIMIT {
+                return Err(Error::invalid_params(format!(
+                    "Invalid limit; max {}",
+                    MAX_GET_DUPLICATE_SLOT_PROOFS_LIMIT
+                )));
+            }
+
+            Ok(meta
+                .blockstore
+                .duplicate_slots_iterator(start_slot.unwrap_or_default())
+                .map_err(|err| {
+                    warn!("get_duplicate_slot_proofs failed: {:?}", err);
+                    Error::invalid_request()
+                })?
+                .take(limit)
+                .filter_map(|slot| {
+                    let proof = meta.blockstore.get_duplicate_slot(slot)?;
+                    Some(RpcDuplicateSlotProof {
+                        slot,
+                        shred1: base64::encode(&proof.shred1),
+                        shred2: base64::encode(&proof.shred2),
+                    })
+                })
+                .collect())
+        }
+
         fn get_cluster_nodes(&self, meta: Self::Metadata) -> Result<Vec<RpcContactInfo>> {
             debug!("get_cluster_nodes rpc request received");
             let cluster_info = &meta.cluster_info;
@@ -3214,7 +3398,14 @@ pub mod rpc_full {
                 Error::internal_error()
             })?;
 
-            _send_transaction(meta, transaction, wire_transaction, last_valid_slot, None)
+            _send_transaction(
+                meta,
+                transaction,
+                wire_transaction,
+                last_valid_slot,
+                None,
+                None,
+            )
         }
 
         fn send_transaction(
@@ -3313,6 +3504,7 @@ pub mod rpc_full {
                 wire_transaction,
                 last_valid_slot,
                 durable_nonce_info,
+                config.max_retries,
             )
         }
 
@@ -3409,6 +3601,151 @@ pub mod rpc_full {
             ))
         }
 
+        fn simulate_bundle(
+            &self,
+            meta: Self::Metadata,
+            data: Vec<String>,
+            config: Option<RpcSimulateBundleConfig>,
+        ) -> Result<RpcResponse<RpcSimulateBundleResult>> {
+            debug!("simulate_bundle rpc request received");
+            let config = config.unwrap_or_default();
+            let encoding = config.encoding.unwrap_or(UiTransactionEncoding::Base58);
+            if data.is_empty() {
+                return Err(Error::invalid_params("No transactions provided"));
+            }
+            if data.len() > MAX_SIMULATE_BUNDLE_TRANSACTIONS {
+                return Err(Error::invalid_params(format!(
+                    "Too many transactions provided; max {}",
+                    MAX_SIMULATE_BUNDLE_TRANSACTIONS
+                )));
+            }
+            if config.sig_verify && config.replace_recent_blockhash {
+                return Err(Error::invalid_params(
+                    "sigVerify may not be used with replaceRecentBlockhash",
+                ));
+            }
+
+            let bank = meta.bank(config.commitment);
+            let mut transactions = Vec::with_capacity(data.len());
+            for data in data {
+                let (_, mut transaction) = deserialize_transaction(data, encoding)?;
+                if config.sig_verify {
+                    verify_transaction(&transaction, bank.libsecp256k1_0_5_upgrade_enabled())?;
+                }
+                if config.replace_recent_blockhash {
+                    transaction.message.recent_blockhash = bank.last_blockhash();
+                }
+                transactions.push(transaction);
+            }
+
+            let mut account_overrides = AccountOverrides::default();
+            if let Some(config_overrides) = config.account_overrides {
+                if config_overrides.len() > MAX_MULTIPLE_ACCOUNTS {
+                    return Err(Error::invalid_params(format!(
+                        "Too many account overrides provided; max {}",
+                        MAX_MULTIPLE_ACCOUNTS
+                    )));
+                }
+                for (address_str, ui_account) in config_overrides {
+                    let address = verify_pubkey(&address_str)?;
+                    // A missing account loads as an empty, zero-lamport account
+                    let account = match ui_account {
+                        Some(ui_account) => ui_account.decode().ok_or_else(|| {
+                            Error::invalid_params(format!(
+                                "Invalid account override for {}",
+                                address
+                            ))
+                        })?,
+                        None => AccountSharedData::default(),
+                    };
+                    account_overrides.set_account(&address, Some(account));
+                }
+            }
+
+            let (accounts_encoding, addresses) = if let Some(config_accounts) = config.accounts {
+                let accounts_encoding = config_accounts
+                    .encoding
+                    .unwrap_or(UiAccountEncoding::Base64);
+
+                if accounts_encoding == UiAccountEncoding::Binary
+                    || accounts_encoding == UiAccountEncoding::Base58
+                {
+                    return Err(Error::invalid_params("base58 encoding not supported"));
+                }
+
+                if config_accounts.addresses.len() > MAX_MULTIPLE_ACCOUNTS {
+                    return Err(Error::invalid_params(format!(
+                        "Too many accounts provided; max {}",
+                        MAX_MULTIPLE_ACCOUNTS
+                    )));
+                }
+
+                let addresses = config_accounts
+                    .addresses
+                    .iter()
+                    .map(|address_str| verify_pubkey(address_str))
+                    .collect::<Result<Vec<_>>>()?;
+                (accounts_encoding, Some(addresses))
+            } else {
+                (UiAccountEncoding::Base64, None)
+            };
+
+            let transaction_results = bank
+                .simulate_bundle(
+                    &transactions,
+                    account_overrides,
+                    addresses.as_deref().unwrap_or_default(),
+                )
+                .into_iter()
+                .map(
+                    |BundleTransactionSimulationResult {
+                         simulation:
+                             TransactionSimulationResult {
+                                 result,
+                                 logs,
+                                 units_consumed,
+                                 ..
+                             },
+                         post_transaction_accounts,
+                     }| {
+                        let accounts = addresses.as_ref().map(|addresses| {
+                            if result.is_err() {
+                                vec![None; addresses.len()]
+                            } else {
+                                post_transaction_accounts
+                                    .iter()
+                                    .map(|(address, account)| {
+                                        account.as_ref().map(|account| {
+                                            UiAccount::encode(
+                                                address,
+                                                account,
+                                                accounts_encoding,
+                                                None,
+                                                None,
+                                            )
+                                        })
+                                    })
+                                    .collect()
+                            }
+                        });
+                        RpcSimulateTransactionResult {
+                            err: result.err(),
+                            logs: Some(logs),
+                            accounts,
+                            units_consumed: Some(units_consumed),
+                        }
+                    },
+                )
+                .collect();
+
+            Ok(new_response(
+                &bank,
+                RpcSimulateBundleResult {
+                    transaction_results,
+                },
+            ))
+        }
+
         fn minimum_ledger_slot(&self, meta: Self::Metadata) -> Result<Slot> {
             debug!("minimum_ledger_slot rpc request received");
             meta.minimum_ledger_slot()
@@ -3479,29 +3816,31 @@ pub mod rpc_full {
             address: String,
             config: Option<RpcSignaturesForAddressConfig>,
         ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
-            let address = verify_pubkey(&address)?;
-
-            let config = config.unwrap_or_default();
-            let before = config
-                .before
-                .map(|ref before| verify_signature(before))
-                .transpose()?;
-            let until = config
-                .until
-                .map(|ref until| verify_signature(until))
-                .transpose()?;
-            let limit = config
-                .limit
-                .unwrap_or(MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT);
-
-            if limit == 0 || limit > MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT {
-                return Err(Error::invalid_params(format!(
-                    "Invalid limit; max {}",
-                    MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT
-                )));
-            }
+            debug!(
+                "get_signatures_for_address rpc request received: {:?}",
+                address
+            );
+            Ok(signatures_for_address(&meta, address, config)?.signatures)
+        }
 
-            meta.get_signatures_for_address(address, before, until, limit, config.commitment)
+        fn get_filtered_signatures_for_address(
+            &self,
+            meta: Self::Metadata,
+            address: String,
+            config: Option<RpcSignaturesForAddressConfig>,
+        ) -> Result<RpcFilteredSignaturesForAddress> {
+            debug!(
+                "get_filtered_signatures_for_address rpc request received: {:?}",
+                address
+            );
+            let AddressSignatures {
+                signatures,
+                resume_before,
+            } = signatures_for_address(&meta, address, config)?;
+            Ok(RpcFilteredSignaturesForAddress {
+                signatures,
+                resume_before: resume_before.map(|signature| signature.to_string()),
+            })
         }
 
         fn get_first_available_block(&self, meta: Self::Metadata) -> Result<Slot> {
@@ -3672,7 +4011,15 @@ pub mod rpc_deprecated_v1_7 {
                 )));
             }
 
-            meta.get_signatures_for_address(address, before, until, limit, config.commitment)
+            meta.get_signatures_for_address(
+                address,
+                before,
+                until,
+                AddressSignatureFilter::default(),
+                limit,
+                config.commitment,
+            )
+            .map(|address_signatures| address_signatures.signatures)
         }
     }
 }
@@ -3983,6 +4330,7 @@ pub mod tests {
         bincode::deserialize,
         jsonrpc_core::{futures, ErrorCode, MetaIoHandler, Output, Response, Value},
         jsonrpc_core_client::transports::local,
+        solana_account_decoder::parse_token::spl_token_id_v2_0,
         solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes},
         solana_gossip::{contact_info::ContactInfo, socketaddr},
         solana_ledger::{
@@ -4193,7 +4541,14 @@ pub mod tests {
             Arc::new(LeaderScheduleCache::new_from_bank(&bank)),
             max_complete_transaction_status_slot,
         );
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
+        SendTransactionService::new::<NullTpuInfo>(
+            tpu_address,
+            &bank_forks,
+            None,
+            receiver,
+            1000,
+            1,
+        );
 
         cluster_info.insert_info(ContactInfo::new_with_pubkey_socketaddr(
             &leader_pubkey,
@@ -4343,6 +4698,71 @@ pub mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_rpc_get_signatures_for_address_filtered() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+        let address = solana_sdk::pubkey::new_rand();
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getSignaturesForAddress","params":["{}"]}}"#,
+            address
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["result"], json!([]));
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getSignaturesForAddress","params":["{}", {{"status":"failed"}}]}}"#,
+            address
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["result"], json!([]));
+
+        // getFilteredSignaturesForAddress also tells where to resume a search that stopped early
+        for params in [
+            json!([address.to_string()]),
+            json!([address.to_string(), {"status": "failed"}]),
+        ] {
+            let req = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "getFilteredSignaturesForAddress",
+                "params": params,
+            })
+            .to_string();
+            let res = io.handle_request_sync(&req, meta.clone());
+            let result: Value = serde_json::from_str(&res.expect("actual response"))
+                .expect("actual response deserialization");
+            assert_eq!(
+                result["result"],
+                json!({"signatures": [], "resumeBefore": null})
+            );
+        }
+
+        let program_ids: Vec<_> = (0..=MAX_GET_SIGNATURES_FOR_ADDRESS_PROGRAM_IDS)
+            .map(|_| solana_sdk::pubkey::new_rand().to_string())
+            .collect();
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getSignaturesForAddress","params":["{}", {{"programIds":{}}}]}}"#,
+            address,
+            json!(program_ids)
+        );
+        let res = io.handle_request_sync(&req, meta);
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(
+            result["error"]["message"],
+            format!(
+                "Too many program ids provided; max {}",
+                MAX_GET_SIGNATURES_FOR_ADDRESS_PROGRAM_IDS
+            )
+        );
+    }
+
     #[test]
     fn test_rpc_get_recent_performance_samples_invalid_limit() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -4369,6 +4789,57 @@ pub mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_rpc_get_duplicate_slot_proofs() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        for slot in &[3, 5, 8] {
+            meta.blockstore
+                .store_duplicate_slot(*slot, vec![*slot as u8; 4], vec![*slot as u8 + 1; 4])
+                .unwrap();
+        }
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getDuplicateSlotProofs","params":[4, 1]}"#;
+        let res = io.handle_request_sync(req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let proofs: Vec<RpcDuplicateSlotProof> =
+            serde_json::from_value(result["result"].clone()).unwrap();
+        assert_eq!(
+            proofs,
+            vec![RpcDuplicateSlotProof {
+                slot: 5,
+                shred1: base64::encode(&[5u8; 4]),
+                shred2: base64::encode(&[6u8; 4]),
+            }]
+        );
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getDuplicateSlotProofs"}"#;
+        let res = io.handle_request_sync(req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let slots: Vec<Slot> = result["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|proof| proof["slot"].as_u64().unwrap())
+            .collect();
+        assert_eq!(slots, vec![3, 5, 8]);
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getDuplicateSlotProofs","params":[0, {}]}}"#,
+            MAX_GET_DUPLICATE_SLOT_PROOFS_LIMIT + 1
+        );
+        let res = io.handle_request_sync(&req, meta);
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(
+            result["error"]["message"],
+            format!("Invalid limit; max {}", MAX_GET_DUPLICATE_SLOT_PROOFS_LIMIT)
+        );
+    }
+
     #[test]
     fn test_rpc_get_slot_leader() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -5473,6 +5944,136 @@ pub mod tests {
         let _ = io.handle_request_sync(&req, meta);
     }
 
+    #[test]
+    fn test_rpc_simulate_bundle() {
+        let RpcHandler {
+            io,
+            meta,
+            blockhash,
+            alice,
+            bank,
+            ..
+        } = start_rpc_handler_with_tx(&solana_sdk::pubkey::new_rand());
+
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let carol = Keypair::new();
+        let dave = Keypair::new();
+        let fund_carol = system_transaction::transfer(&alice, &carol.pubkey(), 2000, blockhash);
+        let pay_bob = system_transaction::transfer(&carol, &bob_pubkey, 1234, blockhash);
+        let dave_pays_bob = system_transaction::transfer(&dave, &bob_pubkey, 10, blockhash);
+        let encode = |tx: &Transaction| bs58::encode(serialize(tx).unwrap()).into_string();
+
+        bank.freeze(); // Ensure the root bank is frozen, `start_rpc_handler_with_tx()` doesn't do this
+
+        let lamports = |result: &Value| -> Vec<Option<u64>> {
+            result["accounts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|account| account["lamports"].as_u64())
+                .collect()
+        };
+
+        // The second transaction spends lamports deposited by the first one
+        let req = format!(
+            r#"{{"jsonrpc":"2.0",
+                 "id":1,
+                 "method":"simulateBundle",
+                 "params":[
+                   ["{}", "{}"],
+                   {{
+                     "sigVerify": true,
+                     "accounts": {{
+                       "addresses": ["{}", "{}"]
+                     }}
+                   }}
+                 ]
+            }}"#,
+            encode(&fund_carol),
+            encode(&pay_bob),
+            carol.pubkey(),
+            bob_pubkey,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let json: Value = serde_json::from_str(&res.unwrap()).unwrap();
+        let results = json["result"]["value"]["transactionResults"]
+            .as_array()
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result["err"].is_null()));
+        assert_eq!(lamports(&results[0]), vec![Some(2000), None]);
+        assert_eq!(lamports(&results[1]), vec![Some(766), Some(1234)]);
+        assert_eq!(bank.get_balance(&carol.pubkey()), 0);
+
+        // Out of order, the bundle stops at the first failure
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateBundle","params":[["{}", "{}"]]}}"#,
+            encode(&pay_bob),
+            encode(&fund_carol),
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let json: Value = serde_json::from_str(&res.unwrap()).unwrap();
+        let results = json["result"]["value"]["transactionResults"]
+            .as_array()
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["err"], json!("AccountNotFound"));
+        assert!(results[0]["accounts"].is_null());
+
+        // Overridden accounts are visible to the bundle
+        let req = format!(
+            r#"{{"jsonrpc":"2.0",
+                 "id":1,
+                 "method":"simulateBundle",
+                 "params":[
+                   ["{}"],
+                   {{
+                     "accountOverrides": {{
+                       "{}": {{
+                         "data": ["", "base64"],
+                         "executable": false,
+                         "owner": "11111111111111111111111111111111",
+                         "lamports": 500,
+                         "rentEpoch": 0
+                       }}
+                     }},
+                     "accounts": {{
+                       "addresses": ["{}"]
+                     }}
+                   }}
+                 ]
+            }}"#,
+            encode(&dave_pays_bob),
+            dave.pubkey(),
+            dave.pubkey(),
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let json: Value = serde_json::from_str(&res.unwrap()).unwrap();
+        let results = json["result"]["value"]["transactionResults"]
+            .as_array()
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0]["err"].is_null());
+        assert_eq!(lamports(&results[0]), vec![Some(490)]);
+
+        // Empty bundle
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"simulateBundle","params":[[]]}"#;
+        let res = io.handle_request_sync(req, meta);
+        let expected = json!({
+            "jsonrpc":"2.0",
+            "error": {
+                "code": error::ErrorCode::InvalidParams.code(),
+                "message": "No transactions provided"
+            },
+            "id":1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn test_rpc_get_signature_statuses() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -5757,7 +6358,14 @@ pub mod tests {
             Arc::new(LeaderScheduleCache::default()),
             Arc::new(AtomicU64::default()),
         );
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
+        SendTransactionService::new::<NullTpuInfo>(
+            tpu_address,
+            &bank_forks,
+            None,
+            receiver,
+            1000,
+            1,
+        );
 
         let mut bad_transaction = system_transaction::transfer(
             &mint_keypair,
@@ -6036,7 +6644,14 @@ pub mod tests {
             Arc::new(LeaderScheduleCache::default()),
             Arc::new(AtomicU64::default()),
         );
-        SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
+        SendTransactionService::new::<NullTpuInfo>(
+            tpu_address,
+            &bank_forks,
+            None,
+            receiver,
+            1000,
+            1,
+        );
         assert_eq!(
             request_processor.get_block_commitment(0),
             RpcBlockCommitment {
@@ -2,6 +2,7 @@
 
 use {
     crate::{
+        cluster_tpu_info::ClusterTpuInfo,
         max_slots::MaxSlots,
         optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
         rpc::{
@@ -9,15 +10,21 @@ use {
             rpc_obsolete_v1_7::*, *,
         },
         rpc_health::*,
-        send_transaction_service::{LeaderInfo, SendTransactionService},
     },
-    jsonrpc_core::{futures::prelude::*, MetaIoHandler},
+    jsonrpc_core::{futures::prelude::*, Error, ErrorCode, MetaIoHandler},
     jsonrpc_http_server::{
         hyper, AccessControlAllowOrigin, CloseHandle, DomainsValidation, RequestMiddleware,
         RequestMiddlewareAction, ServerBuilder,
     },
     regex::Regex,
-    solana_client::rpc_cache::LargestAccountsCache,
+    serde::Serialize,
+    solana_account_decoder::UiAccountEncoding,
+    solana_client::{
+        rpc_cache::LargestAccountsCache,
+        rpc_config::{
+            RpcAccountInfoConfig, RpcBlockConfig, RpcEncodingConfigWrapper, RpcTransactionConfig,
+        },
+    },
     solana_gossip::cluster_info::ClusterInfo,
     solana_ledger::{
         bigtable_upload_service::BigTableUploadService, blockstore::Blockstore,
@@ -30,13 +37,22 @@ use {
         snapshot_utils,
     },
     solana_sdk::{
-        exit::Exit, genesis_config::DEFAULT_GENESIS_DOWNLOAD_PATH, hash::Hash,
-        native_token::lamports_to_sol, pubkey::Pubkey,
+        clock::Slot,
+        commitment_config::CommitmentConfig,
+        exit::Exit,
+        genesis_config::DEFAULT_GENESIS_DOWNLOAD_PATH,
+        hash::{hash, Hash},
+        native_token::lamports_to_sol,
+        pubkey::Pubkey,
+        signature::Signature,
     },
+    solana_send_transaction_service::send_transaction_service::{self, SendTransactionService},
+    solana_storage_bigtable::{LedgerStorage, LedgerStorageAdapter, LocalLedgerStorage},
     std::{
         collections::HashSet,
         net::SocketAddr,
         path::{Path, PathBuf},
+        str::FromStr,
         sync::atomic::{AtomicBool, AtomicU64, Ordering},
         sync::{mpsc::channel, Arc, Mutex, RwLock},
         thread::{self, Builder, JoinHandle},
@@ -47,6 +63,11 @@ use {
 
 const LARGEST_ACCOUNTS_CACHE_DURATION: u64 = 60 * 60 * 2;
 
+// Finalized blocks and transactions never change, so caches may keep them indefinitely
+const REST_IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+// Everything else may be cached, but must be revalidated against its ETag before reuse
+const REST_REVALIDATE_CACHE_CONTROL: &str = "no-cache";
+
 pub struct JsonRpcService {
     thread_hdl: JoinHandle<()>,
 
@@ -62,6 +83,8 @@ struct RpcRequestMiddleware {
     snapshot_config: Option<SnapshotConfig>,
     bank_forks: Arc<RwLock<BankForks>>,
     health: Arc<RpcHealth>,
+    // Serves the cacheable REST reads; `None` when only the minimal API is enabled
+    rest_request_processor: Option<JsonRpcRequestProcessor>,
 }
 
 impl RpcRequestMiddleware {
@@ -70,6 +93,7 @@ impl RpcRequestMiddleware {
         snapshot_config: Option<SnapshotConfig>,
         bank_forks: Arc<RwLock<BankForks>>,
         health: Arc<RpcHealth>,
+        rest_request_processor: Option<JsonRpcRequestProcessor>,
     ) -> Self {
         Self {
             ledger_path,
@@ -80,6 +104,7 @@ impl RpcRequestMiddleware {
             snapshot_config,
             bank_forks,
             health,
+            rest_request_processor,
         }
     }
 
@@ -187,6 +212,31 @@ impl RpcRequestMiddleware {
         }
     }
 
+    // The cacheable REST reads may wait on the ledger or on long-term storage, so they run on the
+    // blocking threads of the request processor's runtime rather than on the HTTP event loop
+    fn process_cacheable_rest_get(
+        request_processor: &JsonRpcRequestProcessor,
+        request: hyper::Request<hyper::Body>,
+    ) -> RequestMiddlewareAction {
+        let task = request_processor.runtime().spawn_blocking({
+            let request_processor = request_processor.clone();
+            move || process_cacheable_rest(&request_processor, &request)
+        });
+        RequestMiddlewareAction::Respond {
+            should_validate_hosts: true,
+            response: Box::pin(async {
+                Ok(match task.await {
+                    Ok(Some(response)) => response,
+                    Ok(None) => Self::not_found(),
+                    Err(err) => {
+                        warn!("REST request failed: {}", err);
+                        Self::internal_server_error()
+                    }
+                })
+            }),
+        }
+    }
+
     fn health_check(&self) -> &'static str {
         let response = match self.health.check() {
             RpcHealthStatus::Ok => "ok",
@@ -231,6 +281,12 @@ impl RequestMiddleware for RpcRequestMiddleware {
                 .body(hyper::Body::from(result))
                 .unwrap()
                 .into()
+        } else if let Some(request_processor) = self
+            .rest_request_processor
+            .as_ref()
+            .filter(|_| cacheable_rest_route(&request).is_some())
+        {
+            Self::process_cacheable_rest_get(request_processor, request)
         } else if self.is_file_get_path(request.uri().path()) {
             self.process_file_get(request.uri().path())
         } else if request.uri().path() == "/health" {
@@ -270,6 +326,196 @@ fn process_rest(bank_forks: &Arc<RwLock<BankForks>>, path: &str) -> Option<Strin
     }
 }
 
+// Routes the versioned, cacheable REST reads:
+//   /v0/block/{slot}
+//   /v0/transaction/{signature}
+//   /v0/account/{pubkey}
+//   /v0/epoch
+// Each accepts an optional `commitment` query parameter
+fn cacheable_rest_route(request: &hyper::Request<hyper::Body>) -> Option<(&str, Option<&str>)> {
+    if request.method() != hyper::Method::GET {
+        return None;
+    }
+    let mut segments = request.uri().path().strip_prefix("/v0/")?.split('/');
+    let resource = segments.next()?;
+    let param = segments.next();
+    if segments.next().is_some() {
+        return None;
+    }
+    match (resource, param) {
+        ("block", Some(_)) | ("transaction", Some(_)) | ("account", Some(_)) | ("epoch", None) => {
+            Some((resource, param))
+        }
+        _ => None,
+    }
+}
+
+// Serves the cacheable REST reads routed by `cacheable_rest_route()`
+fn process_cacheable_rest(
+    request_processor: &JsonRpcRequestProcessor,
+    request: &hyper::Request<hyper::Body>,
+) -> Option<hyper::Response<hyper::Body>> {
+    let result = match cacheable_rest_route(request)? {
+        ("block", Some(slot)) => {
+            inc_new_counter_info!("rpc-rest-get_block", 1);
+            rest_commitment(request).and_then(|commitment| {
+                let slot = Slot::from_str(slot)
+                    .map_err(|_| Error::invalid_params(format!("Invalid slot: {}", slot)))?;
+                let config = RpcBlockConfig {
+                    commitment,
+                    ..RpcBlockConfig::default()
+                };
+                request_processor
+                    .get_block(slot, Some(RpcEncodingConfigWrapper::Current(Some(config))))
+                    .map(|block| {
+                        block.map(|block| {
+                            rest_json_response(request, &block, is_rest_immutable(commitment))
+                        })
+                    })
+            })
+        }
+        ("transaction", Some(signature)) => {
+            inc_new_counter_info!("rpc-rest-get_transaction", 1);
+            rest_commitment(request).and_then(|commitment| {
+                let signature = Signature::from_str(signature).map_err(|_| {
+                    Error::invalid_params(format!("Invalid signature: {}", signature))
+                })?;
+                let config = RpcTransactionConfig {
+                    commitment,
+                    ..RpcTransactionConfig::default()
+                };
+                request_processor
+                    .get_transaction(
+                        signature,
+                        Some(RpcEncodingConfigWrapper::Current(Some(config))),
+                    )
+                    .map(|transaction| {
+                        transaction.map(|transaction| {
+                            rest_json_response(request, &transaction, is_rest_immutable(commitment))
+                        })
+                    })
+            })
+        }
+        ("account", Some(pubkey)) => {
+            inc_new_counter_info!("rpc-rest-get_account", 1);
+            rest_commitment(request).and_then(|commitment| {
+                let pubkey = Pubkey::from_str(pubkey)
+                    .map_err(|_| Error::invalid_params(format!("Invalid pubkey: {}", pubkey)))?;
+                let config = RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    data_slice: None,
+                    commitment,
+                };
+                request_processor
+                    .get_account_info(&pubkey, Some(config))
+                    .map(|account| Some(rest_json_response(request, &account, false)))
+            })
+        }
+        ("epoch", None) => {
+            inc_new_counter_info!("rpc-rest-get_epoch", 1);
+            rest_commitment(request).map(|commitment| {
+                let epoch_info = request_processor.get_epoch_info(commitment);
+                Some(rest_json_response(request, &epoch_info, false))
+            })
+        }
+        _ => return None,
+    };
+
+    Some(match result {
+        Ok(Some(response)) => response,
+        Ok(None) => rest_not_found_response(),
+        Err(err) => rest_error_response(err),
+    })
+}
+
+fn rest_commitment(
+    request: &hyper::Request<hyper::Body>,
+) -> jsonrpc_core::Result<Option<CommitmentConfig>> {
+    request
+        .uri()
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("commitment="))
+        .last()
+        .map(|commitment| {
+            CommitmentConfig::from_str(commitment)
+                .map_err(|_| Error::invalid_params(format!("Invalid commitment: {}", commitment)))
+        })
+        .transpose()
+}
+
+fn is_rest_immutable(commitment: Option<CommitmentConfig>) -> bool {
+    commitment.unwrap_or_default().is_finalized()
+}
+
+fn rest_json_response<T: Serialize>(
+    request: &hyper::Request<hyper::Body>,
+    value: &T,
+    immutable: bool,
+) -> hyper::Response<hyper::Body> {
+    let body = serde_json::to_string(value).expect("serialize REST response");
+    let etag = format!("\"{}\"", hash(body.as_bytes()));
+    let cache_control = if immutable {
+        REST_IMMUTABLE_CACHE_CONTROL
+    } else {
+        REST_REVALIDATE_CACHE_CONTROL
+    };
+
+    let not_modified = request
+        .headers()
+        .get(hyper::header::IF_NONE_MATCH)
+        .and_then(|if_none_match| if_none_match.to_str().ok())
+        .map(|if_none_match| {
+            if_none_match
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        })
+        .unwrap_or(false);
+
+    let response = hyper::Response::builder()
+        .header(hyper::header::ETAG, &etag)
+        .header(hyper::header::CACHE_CONTROL, cache_control);
+    if not_modified {
+        response
+            .status(hyper::StatusCode::NOT_MODIFIED)
+            .body(hyper::Body::empty())
+            .unwrap()
+    } else {
+        response
+            .status(hyper::StatusCode::OK)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(body))
+            .unwrap()
+    }
+}
+
+// Data missing from this node may show up later, so a miss must not be cached either
+fn rest_not_found_response() -> hyper::Response<hyper::Body> {
+    hyper::Response::builder()
+        .status(hyper::StatusCode::NOT_FOUND)
+        .header(hyper::header::CACHE_CONTROL, REST_REVALIDATE_CACHE_CONTROL)
+        .body(hyper::Body::empty())
+        .unwrap()
+}
+
+fn rest_error_response(err: Error) -> hyper::Response<hyper::Body> {
+    let status = match err.code {
+        ErrorCode::InvalidParams | ErrorCode::InvalidRequest => hyper::StatusCode::BAD_REQUEST,
+        // Custom RPC errors report data that isn't available from this node (yet)
+        ErrorCode::ServerError(_) => hyper::StatusCode::NOT_FOUND,
+        _ => hyper::StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    hyper::Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .header(hyper::header::CACHE_CONTROL, REST_REVALIDATE_CACHE_CONTROL)
+        .body(hyper::Body::from(
+            serde_json::json!({ "error": err.message }).to_string(),
+        ))
+        .unwrap()
+}
+
 impl JsonRpcService {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -287,8 +533,7 @@ impl JsonRpcService {
         trusted_validators: Option<HashSet<Pubkey>>,
         override_health_check: Arc<AtomicBool>,
         optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
-        send_transaction_retry_ms: u64,
-        send_transaction_leader_forward_count: u64,
+        send_transaction_service_config: send_transaction_service::Config,
         max_slots: Arc<MaxSlots>,
         leader_schedule_cache: Arc<LeaderScheduleCache>,
         current_transaction_status_slot: Arc<AtomicU64>,
@@ -317,43 +562,46 @@ impl JsonRpcService {
                 .expect("Runtime"),
         );
 
-        let exit_bigtable_ledger_upload_service = Arc::new(AtomicBool::new(false));
+        let exit_ledger_storage_upload_service = Arc::new(AtomicBool::new(false));
 
-        let (bigtable_ledger_storage, _bigtable_ledger_upload_service) =
-            if config.enable_bigtable_ledger_storage || config.enable_bigtable_ledger_upload {
-                runtime
-                    .block_on(solana_storage_bigtable::LedgerStorage::new(
-                        !config.enable_bigtable_ledger_upload,
-                        config.rpc_bigtable_timeout,
-                    ))
-                    .map(|bigtable_ledger_storage| {
-                        info!("BigTable ledger storage initialized");
-
-                        let bigtable_ledger_upload_service = if config.enable_bigtable_ledger_upload
-                        {
-                            Some(Arc::new(BigTableUploadService::new(
-                                runtime.clone(),
-                                bigtable_ledger_storage.clone(),
-                                blockstore.clone(),
-                                block_commitment_cache.clone(),
-                                exit_bigtable_ledger_upload_service.clone(),
-                            )))
-                        } else {
-                            None
-                        };
-
-                        (
-                            Some(bigtable_ledger_storage),
-                            bigtable_ledger_upload_service,
-                        )
-                    })
-                    .unwrap_or_else(|err| {
-                        error!("Failed to initialize BigTable ledger storage: {:?}", err);
-                        (None, None)
-                    })
-            } else {
-                (None, None)
+        let (ledger_storage, _ledger_storage_upload_service) = if config
+            .enable_bigtable_ledger_storage
+            || config.enable_ledger_storage_upload
+            || config.local_ledger_storage_path.is_some()
+        {
+            let read_only = !config.enable_ledger_storage_upload;
+            let ledger_storage = match &config.local_ledger_storage_path {
+                Some(path) => LocalLedgerStorage::open(path, read_only)
+                    .map(|storage| Arc::new(storage) as Arc<dyn LedgerStorageAdapter>),
+                None => runtime
+                    .block_on(LedgerStorage::new(read_only, config.rpc_bigtable_timeout))
+                    .map(|storage| Arc::new(storage) as Arc<dyn LedgerStorageAdapter>),
             };
+            ledger_storage
+                .map(|ledger_storage| {
+                    info!("Long-term ledger storage initialized");
+
+                    let ledger_storage_upload_service = if config.enable_ledger_storage_upload {
+                        Some(Arc::new(BigTableUploadService::new(
+                            runtime.clone(),
+                            ledger_storage.clone(),
+                            blockstore.clone(),
+                            block_commitment_cache.clone(),
+                            exit_ledger_storage_upload_service.clone(),
+                        )))
+                    } else {
+                        None
+                    };
+
+                    (Some(ledger_storage), ledger_storage_upload_service)
+                })
+                .unwrap_or_else(|err| {
+                    error!("Failed to initialize long-term ledger storage: {:?}", err);
+                    (None, None)
+                })
+        } else {
+            (None, None)
+        };
 
         let minimal_api = config.minimal_api;
         let obsolete_v1_7_api = config.obsolete_v1_7_api;
@@ -368,7 +616,7 @@ impl JsonRpcService {
             cluster_info.clone(),
             genesis_hash,
             runtime,
-            bigtable_ledger_storage,
+            ledger_storage,
             optimistically_confirmed_bank,
             largest_accounts_cache,
             max_slots,
@@ -377,14 +625,13 @@ impl JsonRpcService {
         );
 
         let leader_info =
-            poh_recorder.map(|recorder| LeaderInfo::new(cluster_info.clone(), recorder));
-        let _send_transaction_service = Arc::new(SendTransactionService::new(
+            poh_recorder.map(|recorder| ClusterTpuInfo::new(cluster_info.clone(), recorder));
+        let _send_transaction_service = Arc::new(SendTransactionService::new_with_config(
             tpu_address,
             &bank_forks,
             leader_info,
             receiver,
-            send_transaction_retry_ms,
-            send_transaction_leader_forward_count,
+            send_transaction_service_config,
         ));
 
         #[cfg(test)]
@@ -431,6 +678,7 @@ impl JsonRpcService {
                     snapshot_config,
                     bank_forks.clone(),
                     health.clone(),
+                    (!minimal_api).then(|| request_processor.clone()),
                 );
                 let server = ServerBuilder::with_meta_extractor(
                     io,
@@ -459,7 +707,7 @@ impl JsonRpcService {
                 let server = server.unwrap();
                 close_handle_sender.send(server.close_handle()).unwrap();
                 server.wait();
-                exit_bigtable_ledger_upload_service.store(true, Ordering::Relaxed);
+                exit_ledger_storage_upload_service.store(true, Ordering::Relaxed);
             })
             .unwrap();
 
@@ -551,8 +799,11 @@ mod tests {
             None,
             Arc::new(AtomicBool::new(false)),
             optimistically_confirmed_bank,
-            1000,
-            1,
+            send_transaction_service::Config {
+                retry_rate_ms: 1000,
+                leader_forward_count: 1,
+                ..send_transaction_service::Config::default()
+            },
             Arc::new(MaxSlots::default()),
             Arc::new(LeaderScheduleCache::default()),
             Arc::new(AtomicU64::default()),
@@ -591,6 +842,122 @@ mod tests {
         );
     }
 
+    fn rest_request(uri: &str) -> hyper::Request<hyper::Body> {
+        hyper::Request::get(uri).body(hyper::Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn test_process_cacheable_rest() {
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let bank = Arc::new(Bank::new(&genesis_config));
+        let request_processor = JsonRpcRequestProcessor::new_from_bank(&bank);
+        let mut runtime = tokio_02::runtime::Runtime::new().unwrap();
+
+        // Unknown routes fall through to the other handlers
+        assert!(process_cacheable_rest(&request_processor, &rest_request("/v0/nope")).is_none());
+        assert!(process_cacheable_rest(&request_processor, &rest_request("/v0/epoch/1")).is_none());
+        let post = hyper::Request::post("/v0/epoch")
+            .body(hyper::Body::empty())
+            .unwrap();
+        assert!(process_cacheable_rest(&request_processor, &post).is_none());
+
+        // Epoch info is mutable, so it must be revalidated
+        let response =
+            process_cacheable_rest(&request_processor, &rest_request("/v0/epoch")).unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::OK);
+        assert_eq!(
+            response.headers()[hyper::header::CACHE_CONTROL],
+            REST_REVALIDATE_CACHE_CONTROL
+        );
+        let etag = response.headers()[hyper::header::ETAG].clone();
+
+        // A matching ETag short-circuits the body
+        let request = hyper::Request::get("/v0/epoch")
+            .header(hyper::header::IF_NONE_MATCH, etag.clone())
+            .body(hyper::Body::empty())
+            .unwrap();
+        let response = process_cacheable_rest(&request_processor, &request).unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[hyper::header::ETAG], etag);
+
+        let uri = format!("/v0/account/{}?commitment=processed", mint_keypair.pubkey());
+        let response = process_cacheable_rest(&request_processor, &rest_request(&uri)).unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::OK);
+        let body = runtime
+            .block_on(hyper::body::to_bytes(response.into_body()))
+            .unwrap();
+        let account: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(account["value"]["lamports"], 10_000);
+        assert_eq!(account["value"]["data"][1], "base64");
+
+        let response =
+            process_cacheable_rest(&request_processor, &rest_request("/v0/account/bad")).unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::BAD_REQUEST);
+        let uri = format!("/v0/account/{}?commitment=bad", mint_keypair.pubkey());
+        let response = process_cacheable_rest(&request_processor, &rest_request(&uri)).unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::BAD_REQUEST);
+
+        // Transaction history is disabled on this node
+        let response =
+            process_cacheable_rest(&request_processor, &rest_request("/v0/block/0")).unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()[hyper::header::CACHE_CONTROL],
+            REST_REVALIDATE_CACHE_CONTROL
+        );
+
+        // Nor is a miss cached for good
+        let response = rest_not_found_response();
+        assert_eq!(response.status(), hyper::StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()[hyper::header::CACHE_CONTROL],
+            REST_REVALIDATE_CACHE_CONTROL
+        );
+
+        // The middleware responds to the REST reads asynchronously, and lets other routes through
+        let rrm = RpcRequestMiddleware::new(
+            PathBuf::from("/"),
+            None,
+            create_bank_forks(),
+            RpcHealth::stub(),
+            Some(request_processor),
+        );
+        if let RequestMiddlewareAction::Respond { response, .. } =
+            rrm.on_request(rest_request("/v0/epoch"))
+        {
+            let response = runtime.block_on(response).unwrap();
+            assert_eq!(response.status(), hyper::StatusCode::OK);
+        } else {
+            panic!("Unexpected RequestMiddlewareAction variant");
+        }
+        assert!(matches!(
+            rrm.on_request(rest_request("/v0/nope")),
+            RequestMiddlewareAction::Proceed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_rest_json_response_cache_control() {
+        let request = rest_request("/v0/block/0");
+        let response = rest_json_response(&request, &42, true);
+        assert_eq!(
+            response.headers()[hyper::header::CACHE_CONTROL],
+            REST_IMMUTABLE_CACHE_CONTROL
+        );
+        assert_eq!(
+            response.headers()[hyper::header::ETAG],
+            format!("\"{}\"", hash(b"42")).as_str()
+        );
+
+        assert!(is_rest_immutable(None));
+        assert!(is_rest_immutable(Some(CommitmentConfig::finalized())));
+        assert!(!is_rest_immutable(Some(CommitmentConfig::confirmed())));
+    }
+
     #[test]
     fn test_is_file_get_path() {
         let bank_forks = create_bank_forks();
@@ -599,6 +966,7 @@ mod tests {
             None,
             bank_forks.clone(),
             RpcHealth::stub(),
+            None,
         );
         let rrm_with_snapshot_config = RpcRequestMiddleware::new(
             PathBuf::from("/"),
@@ -612,6 +980,7 @@ mod tests {
             }),
             bank_forks,
             RpcHealth::stub(),
+            None,
         );
 
         assert!(rrm.is_file_get_path(DEFAULT_GENESIS_DOWNLOAD_PATH));
@@ -657,6 +1026,7 @@ mod tests {
             None,
             create_bank_forks(),
             RpcHealth::stub(),
+            None,
         );
 
         // File does not exist => request should fail.
@@ -712,6 +1082,7 @@ mod tests {
             None,
             create_bank_forks(),
             RpcHealth::stub(),
+            None,
         );
         assert_eq!(rm.health_check(), "ok");
     }
@@ -734,7 +1105,8 @@ mod tests {
             override_health_check.clone(),
         ));
 
-        let rm = RpcRequestMiddleware::new(PathBuf::from("/"), None, create_bank_forks(), health);
+        let rm =
+            RpcRequestMiddleware::new(PathBuf::from("/"), None, create_bank_forks(), health, None);
 
         // No account hashes for this node or any trusted validators
         assert_eq!(rm.health_check(), "unknown");
@@ -11,7 +11,7 @@ use {
         SubscriptionId,
     },
     serde::Serialize,
-    solana_account_decoder::{parse_token::spl_token_id_v2_0, UiAccount, UiAccountEncoding},
+    solana_account_decoder::{UiAccount, UiAccountEncoding},
     solana_client::{
         rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSignatureSubscribeConfig},
         rpc_filter::RpcFilterType,
@@ -34,6 +34,7 @@ use {
         commitment_config::CommitmentConfig,
         pubkey::Pubkey,
         signature::Signature,
+        spl_token_ids::is_known_spl_token_id,
         timing::timestamp,
         transaction,
     },
@@ -292,7 +293,7 @@ fn filter_account_result(
     // and should notify that the account state has been reverted.
     let results: Box<dyn Iterator<Item = UiAccount>> = if last_modified_slot != last_notified_slot {
         let encoding = encoding.unwrap_or(UiAccountEncoding::Binary);
-        if account.owner() == &spl_token_id_v2_0() && encoding == UiAccountEncoding::JsonParsed {
+        if is_known_spl_token_id(account.owner()) && encoding == UiAccountEncoding::JsonParsed {
             Box::new(iter::once(get_parsed_token_account(bank, pubkey, account)))
         } else {
             Box::new(iter::once(UiAccount::encode(
@@ -338,7 +339,7 @@ fn filter_program_results(
             RpcFilterType::Memcmp(compare) => compare.bytes_match(account.data()),
         })
     });
-    let accounts: Box<dyn Iterator<Item = RpcKeyedAccount>> = if program_id == &spl_token_id_v2_0()
+    let accounts: Box<dyn Iterator<Item = RpcKeyedAccount>> = if is_known_spl_token_id(program_id)
         && encoding == UiAccountEncoding::JsonParsed
         && !accounts_is_empty
     {
@@ -8,7 +8,9 @@ use {
     solana_runtime::bank::{
         Bank, InnerInstructionsList, NonceRollbackInfo, TransactionLogMessages,
     },
-    solana_transaction_status::{InnerInstructions, Reward, TransactionStatusMeta},
+    solana_transaction_status::{
+        invoked_program_ids, InnerInstructions, Reward, TransactionStatusMeta,
+    },
     std::{
         sync::{
             atomic::{AtomicBool, AtomicU64, Ordering},
@@ -122,7 +124,7 @@ impl TransactionStatusService {
                                     instructions,
                                 })
                                 .filter(|i| !i.instructions.is_empty())
-                                .collect()
+                                .collect::<Vec<_>>()
                         });
 
                         let pre_token_balances = Some(pre_token_balances);
@@ -147,6 +149,10 @@ impl TransactionStatusService {
                                 transaction.signatures[0],
                                 writable_keys,
                                 readonly_keys,
+                                invoked_program_ids(
+                                    &transaction.message,
+                                    inner_instructions.as_deref(),
+                                ),
                                 TransactionStatusMeta {
                                     status,
                                     fee,
@@ -0,0 +1,116 @@
+//! The SPL Token compatible program ids are set once per process, so these tests run in their own
+//! test binary
+use {
+    solana_account_decoder::{UiAccountData, UiAccountEncoding},
+    solana_client::{rpc_config::RpcAccountInfoConfig, rpc_request::TokenAccountsFilter},
+    solana_ledger::genesis_utils::create_genesis_config,
+    solana_rpc::rpc::JsonRpcRequestProcessor,
+    solana_runtime::bank::Bank,
+    solana_sdk::{
+        account::{Account, AccountSharedData},
+        pubkey::Pubkey,
+        spl_token_ids::{set_spl_token_ids, SplTokenIds},
+    },
+    spl_token_v2_0::{
+        solana_program::{program_pack::Pack, pubkey::Pubkey as SplTokenPubkey},
+        state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+    },
+    std::sync::Arc,
+};
+
+#[test]
+fn test_token_rpcs_additional_token_program() {
+    let token_program_id = Pubkey::new_unique();
+    let unknown_program_id = Pubkey::new_unique();
+    set_spl_token_ids(SplTokenIds::new(vec![token_program_id])).unwrap();
+
+    let genesis = create_genesis_config(100);
+    let bank = Arc::new(Bank::new(&genesis.genesis_config));
+
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut account_data = vec![0; TokenAccount::get_packed_len()];
+    TokenAccount::pack(
+        TokenAccount {
+            mint: SplTokenPubkey::new_from_array(mint.to_bytes()),
+            owner: SplTokenPubkey::new_from_array(owner.to_bytes()),
+            amount: 420,
+            state: TokenAccountState::Initialized,
+            ..TokenAccount::default()
+        },
+        &mut account_data,
+    )
+    .unwrap();
+    let mut mint_data = vec![0; Mint::get_packed_len()];
+    Mint::pack(
+        Mint {
+            supply: 500,
+            decimals: 2,
+            is_initialized: true,
+            ..Mint::default()
+        },
+        &mut mint_data,
+    )
+    .unwrap();
+    let store_account = |pubkey: &Pubkey, data: &[u8], owner: Pubkey| {
+        bank.store_account(
+            pubkey,
+            &AccountSharedData::from(Account {
+                lamports: 111,
+                data: data.to_vec(),
+                owner,
+                ..Account::default()
+            }),
+        );
+    };
+    let token_account_pubkey = Pubkey::new_unique();
+    store_account(&token_account_pubkey, &account_data, token_program_id);
+    store_account(&mint, &mint_data, token_program_id);
+    // Same layout, but owned by a program that isn't SPL Token compatible
+    let unknown_account_pubkey = Pubkey::new_unique();
+    store_account(&unknown_account_pubkey, &account_data, unknown_program_id);
+
+    let request_processor = JsonRpcRequestProcessor::new_from_bank(&bank);
+
+    let balance = request_processor
+        .get_token_account_balance(&token_account_pubkey, None)
+        .unwrap()
+        .value;
+    assert_eq!(balance.amount, "420");
+    assert_eq!(balance.decimals, 2);
+    let error = request_processor
+        .get_token_account_balance(&unknown_account_pubkey, None)
+        .unwrap_err();
+    assert_eq!(error.message, "Invalid param: not a Token account");
+
+    let supply = request_processor
+        .get_token_supply(&mint, None)
+        .unwrap()
+        .value;
+    assert_eq!(supply.amount, "500");
+
+    let accounts = request_processor
+        .get_token_accounts_by_owner(
+            &owner,
+            TokenAccountsFilter::ProgramId(token_program_id),
+            Some(RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::JsonParsed),
+                ..RpcAccountInfoConfig::default()
+            }),
+        )
+        .unwrap()
+        .value;
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].pubkey, token_account_pubkey.to_string());
+    match &accounts[0].account.data {
+        UiAccountData::Json(parsed_account) => assert_eq!(parsed_account.program, "spl-token"),
+        data => panic!("unexpected account data: {:?}", data),
+    }
+    assert!(request_processor
+        .get_token_accounts_by_owner(
+            &owner,
+            TokenAccountsFilter::ProgramId(unknown_program_id),
+            None,
+        )
+        .is_err());
+}
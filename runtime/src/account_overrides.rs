@@ -0,0 +1,54 @@
+use {
+    solana_sdk::{account::AccountSharedData, pubkey::Pubkey},
+    std::collections::HashMap,
+};
+
+/// Encapsulates overridden accounts, typically used for transaction simulations
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccountOverrides {
+    accounts: HashMap<Pubkey, AccountSharedData>,
+}
+
+impl AccountOverrides {
+    /// Sets the account state for the given pubkey, replacing any previous override
+    pub fn set_account(&mut self, pubkey: &Pubkey, account: Option<AccountSharedData>) {
+        match account {
+            Some(account) => self.accounts.insert(*pubkey, account),
+            None => self.accounts.remove(pubkey),
+        };
+    }
+
+    /// Gets the account if it's found in the list of overrides
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&AccountSharedData> {
+        self.accounts.get(pubkey)
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::account::ReadableAccount};
+
+    #[test]
+    fn test_set_account() {
+        let mut accounts = AccountOverrides::default();
+        let data = AccountSharedData::new(42, 0, &Pubkey::default());
+        let key = Pubkey::new_unique();
+        assert!(accounts.is_empty());
+
+        accounts.set_account(&key, Some(data));
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts.get(&key).unwrap().lamports(), 42);
+
+        accounts.set_account(&key, None);
+        assert!(accounts.get(&key).is_none());
+        assert!(accounts.is_empty());
+    }
+}
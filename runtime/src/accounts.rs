@@ -1,4 +1,5 @@
 use crate::{
+    account_overrides::AccountOverrides,
     accounts_db::{
         AccountShrinkThreshold, AccountsDb, BankHashInfo, ErrorCounters, LoadHint, LoadedAccount,
         ScanStorageResult,
@@ -183,6 +184,7 @@ impl Accounts {
         error_counters: &mut ErrorCounters,
         rent_collector: &RentCollector,
         feature_set: &FeatureSet,
+        account_overrides: Option<&AccountOverrides>,
     ) -> Result<LoadedTransaction> {
         // Copy all the accounts
         let message = tx.message();
@@ -212,22 +214,28 @@ impl Accounts {
                         }
                         Self::construct_instructions_account(message)
                     } else {
-                        let (account, rent) = self
-                            .accounts_db
-                            .load_with_fixed_root(ancestors, key)
-                            .map(|(mut account, _)| {
-                                if message.is_writable(i) {
-                                    let rent_due = rent_collector.collect_from_existing_account(
-                                        key,
-                                        &mut account,
-                                        rent_for_sysvars,
-                                    );
-                                    (account, rent_due)
-                                } else {
-                                    (account, 0)
-                                }
-                            })
-                            .unwrap_or_default();
+                        let (account, rent) = if let Some(account_override) =
+                            account_overrides.and_then(|overrides| overrides.get(key))
+                        {
+                            (account_override.clone(), 0)
+                        } else {
+                            self.accounts_db
+                                .load_with_fixed_root(ancestors, key)
+                                .map(|(mut account, _)| {
+                                    if message.is_writable(i) {
+                                        let rent_due = rent_collector
+                                            .collect_from_existing_account(
+                                                key,
+                                                &mut account,
+                                                rent_for_sysvars,
+                                            );
+                                        (account, rent_due)
+                                    } else {
+                                        (account, 0)
+                                    }
+                                })
+                                .unwrap_or_default()
+                        };
 
                         if account.executable() && bpf_loader_upgradeable::check_id(account.owner())
                         {
@@ -236,11 +244,11 @@ impl Accounts {
                                 programdata_address,
                             }) = account.state()
                             {
-                                if let Some(account) = self
-                                    .accounts_db
-                                    .load_with_fixed_root(ancestors, &programdata_address)
-                                    .map(|(account, _)| account)
-                                {
+                                if let Some(account) = self.load_with_overrides(
+                                    ancestors,
+                                    &programdata_address,
+                                    account_overrides,
+                                ) {
                                     account_deps.push((programdata_address, account));
                                 } else {
                                     error_counters.account_not_found += 1;
@@ -315,6 +323,7 @@ impl Accounts {
                                     ancestors,
                                     &program_id,
                                     error_counters,
+                                    account_overrides,
                                 )
                             })
                             .collect::<Result<TransactionLoaders>>()?;
@@ -333,11 +342,27 @@ impl Accounts {
         }
     }
 
+    fn load_with_overrides(
+        &self,
+        ancestors: &Ancestors,
+        pubkey: &Pubkey,
+        account_overrides: Option<&AccountOverrides>,
+    ) -> Option<AccountSharedData> {
+        match account_overrides.and_then(|overrides| overrides.get(pubkey)) {
+            Some(account_override) => Some(account_override.clone()),
+            None => self
+                .accounts_db
+                .load_with_fixed_root(ancestors, pubkey)
+                .map(|(account, _)| account),
+        }
+    }
+
     fn load_executable_accounts(
         &self,
         ancestors: &Ancestors,
         program_id: &Pubkey,
         error_counters: &mut ErrorCounters,
+        account_overrides: Option<&AccountOverrides>,
     ) -> Result<Vec<(Pubkey, AccountSharedData)>> {
         let mut accounts = Vec::new();
         let mut depth = 0;
@@ -354,10 +379,7 @@ impl Accounts {
             }
             depth += 1;
 
-            let program = match self
-                .accounts_db
-                .load_with_fixed_root(ancestors, &program_id)
-                .map(|(account, _)| account)
+            let program = match self.load_with_overrides(ancestors, &program_id, account_overrides)
             {
                 Some(program) => program,
                 None => {
@@ -379,10 +401,8 @@ impl Accounts {
                     programdata_address,
                 }) = program.state()
                 {
-                    if let Some(program) = self
-                        .accounts_db
-                        .load_with_fixed_root(ancestors, &programdata_address)
-                        .map(|(account, _)| account)
+                    if let Some(program) =
+                        self.load_with_overrides(ancestors, &programdata_address, account_overrides)
                     {
                         accounts.insert(0, (programdata_address, program));
                     } else {
@@ -410,6 +430,7 @@ impl Accounts {
         error_counters: &mut ErrorCounters,
         rent_collector: &RentCollector,
         feature_set: &FeatureSet,
+        account_overrides: Option<&AccountOverrides>,
     ) -> Vec<TransactionLoadResult> {
         txs.zip(lock_results)
             .map(|etx| match etx {
@@ -435,6 +456,7 @@ impl Accounts {
                         error_counters,
                         rent_collector,
                         feature_set,
+                        account_overrides,
                     ) {
                         Ok(loaded_transaction) => loaded_transaction,
                         Err(e) => return (Err(e), None),
@@ -1123,6 +1145,7 @@ mod tests {
             error_counters,
             rent_collector,
             &FeatureSet::all_enabled(),
+            None,
         )
     }
 
@@ -1684,7 +1707,8 @@ mod tests {
             accounts.load_executable_accounts(
                 &ancestors,
                 &solana_sdk::pubkey::new_rand(),
-                &mut error_counters
+                &mut error_counters,
+                None,
             ),
             Err(TransactionError::ProgramAccountNotFound)
         );
@@ -2055,6 +2079,7 @@ mod tests {
             &mut error_counters,
             &rent_collector,
             &FeatureSet::all_enabled(),
+            None,
         )
     }
 
@@ -11,6 +11,7 @@ use solana_measure::measure::Measure;
 use solana_sdk::{
     clock::{BankId, Slot},
     pubkey::{Pubkey, PUBKEY_BYTES},
+    spl_token_ids::{spl_token_ids, SplTokenIds},
 };
 use std::{
     collections::{
@@ -1324,6 +1325,23 @@ impl<
         account_owner: &Pubkey,
         account_data: &[u8],
         account_indexes: &AccountSecondaryIndexes,
+    ) {
+        self.update_secondary_indexes_with_spl_token_ids(
+            pubkey,
+            account_owner,
+            account_data,
+            account_indexes,
+            spl_token_ids(),
+        )
+    }
+
+    fn update_secondary_indexes_with_spl_token_ids(
+        &self,
+        pubkey: &Pubkey,
+        account_owner: &Pubkey,
+        account_data: &[u8],
+        account_indexes: &AccountSecondaryIndexes,
+        spl_token_ids: &SplTokenIds,
     ) {
         if account_indexes.is_empty() {
             return;
@@ -1347,8 +1365,8 @@ impl<
         // 2) When the fetch from storage occurs, it will return AccountSharedData::Default
         // (as persisted tombstone for snapshots). This will then ultimately be
         // filtered out by post-scan filters, like in `get_filtered_spl_token_accounts_by_owner()`.
-        if *account_owner == inline_spl_token_v2_0::id()
-            && account_data.len() == inline_spl_token_v2_0::state::Account::get_packed_len()
+        if account_data.len() == inline_spl_token_v2_0::state::Account::get_packed_len()
+            && spl_token_ids.contains(account_owner)
         {
             if account_indexes.contains(&AccountIndex::SplTokenOwner) {
                 let owner_key = Pubkey::new(
@@ -3584,6 +3602,26 @@ pub mod tests {
 
         secondary_indexes.keys = None;
 
+        // Additional SPL Token compatible program id
+        let token_program_id = Pubkey::new_unique();
+        index.update_secondary_indexes(
+            &account_key,
+            &token_program_id,
+            &account_data,
+            &secondary_indexes,
+        );
+        assert!(secondary_index.index.is_empty());
+        index.update_secondary_indexes_with_spl_token_ids(
+            &account_key,
+            &token_program_id,
+            &account_data,
+            &secondary_indexes,
+            &SplTokenIds::new(vec![token_program_id]),
+        );
+        check_secondary_index_mapping_correct(secondary_index, &[index_key], &account_key);
+        secondary_index.index.clear();
+        secondary_index.reverse_index.clear();
+
         // Just right. Inserting the same index multiple times should be ok
         for _ in 0..2 {
             index.update_secondary_indexes(
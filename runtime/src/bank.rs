@@ -34,6 +34,7 @@
 //! on behalf of the caller, and a low-level API for when they have
 //! already been signed and verified.
 use crate::{
+    account_overrides::AccountOverrides,
     accounts::{
         AccountAddressFilter, Accounts, TransactionAccounts, TransactionLoadResult,
         TransactionLoaders,
@@ -71,6 +72,7 @@ use solana_sdk::{
         AccountSharedData, InheritableAccountFields, ReadableAccount, WritableAccount,
     },
     account_utils::StateMut,
+    bpf_loader_upgradeable::UpgradeableLoaderState,
     clock::{
         BankId, Epoch, Slot, SlotCount, SlotIndex, UnixTimestamp, DEFAULT_TICKS_PER_SECOND,
         INITIAL_RENT_EPOCH, MAX_PROCESSING_AGE, MAX_RECENT_BLOCKHASHES,
@@ -530,6 +532,12 @@ pub struct TransactionSimulationResult {
     pub post_simulation_accounts: Vec<(Pubkey, AccountSharedData)>,
     pub units_consumed: u64,
 }
+pub struct BundleTransactionSimulationResult {
+    pub simulation: TransactionSimulationResult,
+    /// State of the requested accounts once this transaction has executed, or `None` for
+    /// accounts that don't exist. Empty if the transaction failed.
+    pub post_transaction_accounts: Vec<(Pubkey, Option<AccountSharedData>)>,
+}
 pub struct TransactionBalancesSet {
     pub pre_balances: TransactionBalances,
     pub post_balances: TransactionBalances,
@@ -2722,6 +2730,72 @@ impl Bank {
     pub fn simulate_transaction(&self, transaction: &Transaction) -> TransactionSimulationResult {
         assert!(self.is_frozen(), "simulation bank must be frozen");
 
+        self.simulate_transaction_with_overrides(transaction, None)
+    }
+
+    /// Run an ordered bundle of transactions against a child of a frozen bank without committing
+    /// the results. Each transaction observes the account states written by the transactions
+    /// before it, layered on top of `account_overrides`. Simulation stops at the first
+    /// transaction that fails, so the returned results may be shorter than `transactions`.
+    pub fn simulate_bundle(
+        self: &Arc<Self>,
+        transactions: &[Transaction],
+        mut account_overrides: AccountOverrides,
+        post_transaction_addresses: &[Pubkey],
+    ) -> Vec<BundleTransactionSimulationResult> {
+        assert!(self.is_frozen(), "simulation bank must be frozen");
+
+        // The bundle runs as the next block would: with the child's slot, sysvars, blockhash
+        // queue and status cache
+        let bank = Bank::new_from_parent(self, self.collector_id(), self.slot() + 1);
+        let mut results = Vec::with_capacity(transactions.len());
+        for transaction in transactions {
+            let simulation =
+                bank.simulate_transaction_with_overrides(transaction, Some(&account_overrides));
+            if simulation.result.is_err() {
+                results.push(BundleTransactionSimulationResult {
+                    simulation,
+                    post_transaction_accounts: vec![],
+                });
+                break;
+            }
+
+            let message = &transaction.message;
+            for (i, (pubkey, account)) in simulation
+                .post_simulation_accounts
+                .iter()
+                .take(message.account_keys.len())
+                .enumerate()
+            {
+                if message.is_non_loader_key(i) && message.is_writable(i) {
+                    account_overrides.set_account(pubkey, Some(account.clone()));
+                }
+            }
+
+            let post_transaction_accounts = post_transaction_addresses
+                .iter()
+                .map(|pubkey| {
+                    let account = match account_overrides.get(pubkey) {
+                        Some(account) => Some(account.clone()),
+                        None => bank.get_account_with_fixed_root(pubkey),
+                    }
+                    .filter(|account| account.lamports() > 0);
+                    (*pubkey, account)
+                })
+                .collect();
+            results.push(BundleTransactionSimulationResult {
+                simulation,
+                post_transaction_accounts,
+            });
+        }
+        results
+    }
+
+    fn simulate_transaction_with_overrides(
+        &self,
+        transaction: &Transaction,
+        account_overrides: Option<&AccountOverrides>,
+    ) -> TransactionSimulationResult {
         let batch = match SanitizedTransaction::try_from(transaction) {
             Ok(sanitized_tx) => self.prepare_simulation_batch(sanitized_tx),
             Err(err) => {
@@ -2753,6 +2827,7 @@ impl Bank {
             false,
             true,
             &mut timings,
+            account_overrides,
         );
 
         let result = executed[0].0.clone().map(|_| ());
@@ -3146,6 +3221,18 @@ impl Bank {
         }
     }
 
+    /// Whether `pubkey` is a program whose account, or whose program data account, is
+    /// overridden
+    fn is_program_overridden(&self, pubkey: &Pubkey, account_overrides: &AccountOverrides) -> bool {
+        account_overrides.get(pubkey).is_some()
+            || matches!(
+                self.get_account_with_fixed_root(pubkey)
+                    .map(|account| account.state()),
+                Some(Ok(UpgradeableLoaderState::Program { programdata_address }))
+                    if account_overrides.get(&programdata_address).is_some()
+            )
+    }
+
     /// Remove an executor from the bank's cache
     pub fn remove_executor(&self, pubkey: &Pubkey) {
         let mut cow_cache = self.cached_executors.write().unwrap();
@@ -3161,6 +3248,7 @@ impl Bank {
         enable_cpi_recording: bool,
         enable_log_recording: bool,
         timings: &mut ExecuteTimings,
+        account_overrides: Option<&AccountOverrides>,
     ) -> (
         Vec<TransactionLoadResult>,
         Vec<TransactionExecutionResult>,
@@ -3207,6 +3295,7 @@ impl Bank {
             &mut error_counters,
             &self.rent_collector,
             &self.feature_set,
+            account_overrides,
         );
         load_time.stop();
 
@@ -3241,6 +3330,15 @@ impl Bank {
                     if process_result.is_ok() {
                         let executors =
                             self.get_executors(&tx.message, &loaded_transaction.loaders);
+                        // Cached executors were built from the programs as stored, so those of
+                        // overridden programs are rebuilt from the overrides and never cached
+                        let account_overrides = account_overrides
+                            .filter(|account_overrides| !account_overrides.is_empty());
+                        if let Some(account_overrides) = account_overrides {
+                            executors.borrow_mut().executors.retain(|pubkey, _| {
+                                !self.is_program_overridden(pubkey, account_overrides)
+                            });
+                        }
 
                         let (account_refcells, loader_refcells) = Self::accounts_to_refcells(
                             &mut loaded_transaction.accounts,
@@ -3298,7 +3396,7 @@ impl Bank {
                             process_result = Err(e);
                         }
 
-                        if process_result.is_ok() {
+                        if process_result.is_ok() && account_overrides.is_none() {
                             self.update_executors(executors);
                         }
                     } else {
@@ -4168,6 +4266,7 @@ impl Bank {
             enable_cpi_recording,
             enable_log_recording,
             timings,
+            None,
         );
 
         let results = self.commit_transactions(
@@ -5212,6 +5311,11 @@ impl Bank {
             .is_active(&feature_set::libsecp256k1_0_5_upgrade_enabled::id())
     }
 
+    pub fn gate_large_block_enabled(&self) -> bool {
+        self.feature_set
+            .is_active(&feature_set::gate_large_block::id())
+    }
+
     // Check if the wallclock time from bank creation to now has exceeded the allotted
     // time for transaction processing
     pub fn should_bank_still_be_processing_txs(
@@ -7807,6 +7911,155 @@ pub(crate) mod tests {
         assert_eq!(bank.transaction_count(), 2);
     }
 
+    #[test]
+    fn test_simulate_bundle() {
+        let (genesis_config, mint_keypair) = create_genesis_config(10_000);
+        let key1 = Keypair::new();
+        let key2 = solana_sdk::pubkey::new_rand();
+        let key3 = Keypair::new();
+        let bank = Arc::new(Bank::new(&genesis_config));
+        bank.freeze();
+
+        // The second transfer spends lamports that only exist after the first one
+        let t1 = system_transaction::transfer(
+            &mint_keypair,
+            &key1.pubkey(),
+            1_000,
+            bank.last_blockhash(),
+        );
+        let t2 = system_transaction::transfer(&key1, &key2, 400, bank.last_blockhash());
+        let results = bank.simulate_bundle(
+            &[t1.clone(), t2.clone()],
+            AccountOverrides::default(),
+            &[key1.pubkey(), key2],
+        );
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.simulation.result.is_ok()));
+        let balances = |result: &BundleTransactionSimulationResult| {
+            result
+                .post_transaction_accounts
+                .iter()
+                .map(|(_, account)| account.as_ref().map(|account| account.lamports()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(balances(&results[0]), vec![Some(1_000), None]);
+        assert_eq!(balances(&results[1]), vec![Some(600), Some(400)]);
+
+        // Nothing was committed
+        assert_eq!(bank.get_balance(&key1.pubkey()), 0);
+        assert_eq!(bank.get_balance(&key2), 0);
+
+        // Without the first transfer the bundle stops at the failing transaction
+        let results = bank.simulate_bundle(&[t2.clone(), t1], AccountOverrides::default(), &[]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].simulation.result,
+            Err(TransactionError::AccountNotFound)
+        );
+        assert!(results[0].post_transaction_accounts.is_empty());
+
+        // Overridden accounts are visible to the bundle
+        let mut account_overrides = AccountOverrides::default();
+        account_overrides.set_account(
+            &key3.pubkey(),
+            Some(AccountSharedData::new(100, 0, &system_program::id())),
+        );
+        let t3 = system_transaction::transfer(&key3, &key2, 50, bank.last_blockhash());
+        let results = bank.simulate_bundle(&[t3], account_overrides, &[key3.pubkey(), key2]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(balances(&results[0]), vec![Some(50), Some(50)]);
+        assert_eq!(bank.get_balance(&key3.pubkey()), 0);
+    }
+
+    #[test]
+    fn test_simulate_bundle_fees() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(10_000);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(100, 0);
+        let key1 = solana_sdk::pubkey::new_rand();
+        let key2 = solana_sdk::pubkey::new_rand();
+        let bank = Arc::new(Bank::new(&genesis_config));
+        bank.freeze();
+
+        // Each transaction pays its fee from the balance the previous one left behind, which
+        // leaves too little for the last transfer
+        let t1 = system_transaction::transfer(&mint_keypair, &key1, 1_000, bank.last_blockhash());
+        let t2 = system_transaction::transfer(&mint_keypair, &key2, 2_000, bank.last_blockhash());
+        let t3 = system_transaction::transfer(&mint_keypair, &key2, 6_750, bank.last_blockhash());
+        let results = bank.simulate_bundle(
+            &[t1, t2, t3],
+            AccountOverrides::default(),
+            &[mint_keypair.pubkey()],
+        );
+        assert_eq!(results.len(), 3);
+        let mint_balances: Vec<_> = results[..2]
+            .iter()
+            .map(|result| {
+                assert_eq!(result.simulation.result, Ok(()));
+                result.post_transaction_accounts[0]
+                    .1
+                    .as_ref()
+                    .unwrap()
+                    .lamports()
+            })
+            .collect();
+        assert_eq!(mint_balances, vec![8_900, 6_800]);
+        assert_eq!(
+            results[2].simulation.result,
+            Err(TransactionError::InstructionError(
+                0,
+                SystemError::ResultWithNegativeLamports.into()
+            ))
+        );
+        assert_eq!(bank.get_balance(&mint_keypair.pubkey()), 10_000);
+    }
+
+    #[test]
+    fn test_simulate_bundle_program_override() {
+        let (genesis_config, mint_keypair) = create_genesis_config(10_000);
+        let key1 = solana_sdk::pubkey::new_rand();
+        let bank = Arc::new(Bank::new(&genesis_config));
+        bank.freeze();
+
+        let t1 = system_transaction::transfer(&mint_keypair, &key1, 1_000, bank.last_blockhash());
+        let results =
+            bank.simulate_bundle(std::slice::from_ref(&t1), AccountOverrides::default(), &[]);
+        assert_eq!(results[0].simulation.result, Ok(()));
+
+        // Overriding the program account replaces the program that is loaded
+        let mut account_overrides = AccountOverrides::default();
+        account_overrides.set_account(
+            &system_program::id(),
+            Some(AccountSharedData::new(1, 0, &native_loader::id())),
+        );
+        let results = bank.simulate_bundle(std::slice::from_ref(&t1), account_overrides, &[]);
+        assert_eq!(
+            results[0].simulation.result,
+            Err(TransactionError::InvalidProgramForExecution)
+        );
+
+        // This bank is unaffected
+        let results = bank.simulate_bundle(&[t1], AccountOverrides::default(), &[]);
+        assert_eq!(results[0].simulation.result, Ok(()));
+    }
+
+    #[test]
+    fn test_simulate_bundle_next_block_sysvars() {
+        let (genesis_config, mint_keypair) = create_genesis_config(10_000);
+        let key1 = solana_sdk::pubkey::new_rand();
+        let bank = Arc::new(Bank::new(&genesis_config));
+        bank.freeze();
+
+        // The bundle sees the sysvars of the block following this bank
+        let t1 = system_transaction::transfer(&mint_keypair, &key1, 1_000, bank.last_blockhash());
+        let results =
+            bank.simulate_bundle(&[t1], AccountOverrides::default(), &[sysvar::clock::id()]);
+        assert_eq!(results[0].simulation.result, Ok(()));
+        let clock: sysvar::clock::Clock =
+            from_account(results[0].post_transaction_accounts[0].1.as_ref().unwrap()).unwrap();
+        assert_eq!(clock.slot, bank.slot() + 1);
+        assert_eq!(bank.clock().slot, bank.slot());
+    }
+
     #[test]
     fn test_one_source_two_tx_one_batch() {
         let (genesis_config, mint_keypair) = create_genesis_config(1);
@@ -2,14 +2,32 @@
 //! It does so by analyzing accounts the transaction touches, and instructions
 //! it includes. Using historical data as guideline, it estimates cost of
 //! reading/writing account, the sum of that comes up to "account access cost";
-//! Instructions take time to execute, both historical and runtime data are
-//! used to determine each instruction's execution time, the sum of that
-//! is transaction's "execution cost"
+//! Instructions take time to execute, each instruction is charged a fixed
+//! number of compute units for its program, the sum of that is transaction's
+//! "execution cost". The charge doesn't depend on anything the node observed
+//! itself, so every validator replaying a block computes the same cost.
 //! The main function is `calculate_cost` which returns &TransactionCost.
 //!
+//! The execution times learned from replay are kept in a separate table, they
+//! are persisted to the blockstore and reported, but never charged.
+//!
+//! Besides the cost, `TransactionCost` records whether the transaction is a
+//! vote, and how many bytes of account data it asks the system program to
+//! allocate, so trackers can budget votes, non-votes and account data growth
+//! separately.
+//!
 use crate::execute_cost_table::ExecuteCostTable;
+use lazy_static::lazy_static;
 use log::*;
-use solana_sdk::{pubkey::Pubkey, sanitized_transaction::SanitizedTransaction};
+use solana_sdk::{
+    compute_budget::{self, ComputeBudget},
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    sanitized_transaction::SanitizedTransaction,
+    secp256k1_program, stake,
+    system_instruction::SystemInstruction,
+    system_program,
+};
 use std::collections::HashMap;
 
 // Guestimated from mainnet-beta data, sigver averages 1us, average read 7us and average write 25us
@@ -25,9 +43,29 @@ const SIGNED_WRITABLE_ACCOUNT_ACCESS_COST: u64 =
 // min=194, max=62164, avg=8214.49, med=2243
 pub const ACCOUNT_MAX_COST: u64 = 100_000_000;
 pub const BLOCK_MAX_COST: u64 = 2_500_000_000;
+// Votes and non-votes each get a share of the block, so neither can starve the other
+pub const VOTE_MAX_COST: u64 = 1_000_000_000;
+pub const NON_VOTE_MAX_COST: u64 = 2_000_000_000;
+// Maximum bytes of new account data a single block may allocate
+pub const ACCOUNT_DATA_GROWTH_MAX_BYTES: u64 = 100_000_000;
 
 const MAX_WRITABLE_ACCOUNTS: usize = 256;
 
+lazy_static! {
+    // Compute units charged for an instruction of a builtin program
+    static ref BUILT_IN_INSTRUCTION_COSTS: HashMap<Pubkey, u64> = [
+        (system_program::id(), 200),
+        (solana_vote_program::id(), 2_800),
+        (stake::program::id(), 1_000),
+        (solana_config_program::id(), 600),
+        (secp256k1_program::id(), 960),
+        (compute_budget::id(), 150),
+    ]
+    .iter()
+    .cloned()
+    .collect();
+}
+
 #[derive(Debug, Clone)]
 pub enum CostModelError {
     /// transaction that would fail sanitize, cost model is not able to process
@@ -39,19 +77,56 @@ pub enum CostModelError {
 
     /// would exceed account max limit
     WouldExceedAccountMaxLimit,
+
+    /// would exceed the block limit for vote transactions
+    WouldExceedVoteMaxLimit,
+
+    /// would exceed the block limit for non-vote transactions
+    WouldExceedNonVoteMaxLimit,
+
+    /// would exceed the block limit for account data growth
+    WouldExceedAccountDataMaxLimit,
+}
+
+/// Limits a block's transactions are packed against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockCostLimits {
+    /// max cost chained on a single writable account
+    pub account_cost_limit: u64,
+    /// max cost of the whole block
+    pub block_cost_limit: u64,
+    /// max cost of all vote transactions in the block
+    pub vote_cost_limit: u64,
+    /// max cost of all non-vote transactions in the block
+    pub non_vote_cost_limit: u64,
+    /// max bytes of account data allocated by the block
+    pub account_data_growth_limit: u64,
+}
+
+impl Default for BlockCostLimits {
+    fn default() -> Self {
+        Self {
+            account_cost_limit: ACCOUNT_MAX_COST,
+            block_cost_limit: BLOCK_MAX_COST,
+            vote_cost_limit: VOTE_MAX_COST,
+            non_vote_cost_limit: NON_VOTE_MAX_COST,
+            account_data_growth_limit: ACCOUNT_DATA_GROWTH_MAX_BYTES,
+        }
+    }
 }
 
 // cost of transaction is made of account_access_cost and instruction execution_cost
 // where
 // account_access_cost is the sum of read/write/sign all accounts included in the transaction
 //     read is cheaper than write.
-// execution_cost is the sum of all instructions execution cost, which is
-//     observed during runtime and feedback by Replay
+// execution_cost is the sum of all instructions execution cost, in compute units
 #[derive(Default, Debug)]
 pub struct TransactionCost {
     pub writable_accounts: Vec<Pubkey>,
     pub account_access_cost: u64,
     pub execution_cost: u64,
+    pub is_vote: bool,
+    pub account_data_growth: u64,
 }
 
 impl TransactionCost {
@@ -66,13 +141,18 @@ impl TransactionCost {
         self.writable_accounts.clear();
         self.account_access_cost = 0;
         self.execution_cost = 0;
+        self.is_vote = false;
+        self.account_data_growth = 0;
+    }
+
+    pub fn sum(&self) -> u64 {
+        self.account_access_cost + self.execution_cost
     }
 }
 
 #[derive(Debug)]
 pub struct CostModel {
-    account_cost_limit: u64,
-    block_cost_limit: u64,
+    limits: BlockCostLimits,
     instruction_execution_cost_table: ExecuteCostTable,
 
     // reusable variables
@@ -81,26 +161,40 @@ pub struct CostModel {
 
 impl Default for CostModel {
     fn default() -> Self {
-        CostModel::new(ACCOUNT_MAX_COST, BLOCK_MAX_COST)
+        CostModel::new_with_limits(BlockCostLimits::default())
     }
 }
 
 impl CostModel {
+    /// Vote and non-vote transactions may each use up the whole block.
     pub fn new(chain_max: u64, block_max: u64) -> Self {
-        Self {
+        Self::new_with_limits(BlockCostLimits {
             account_cost_limit: chain_max,
             block_cost_limit: block_max,
+            vote_cost_limit: block_max,
+            non_vote_cost_limit: block_max,
+            ..BlockCostLimits::default()
+        })
+    }
+
+    pub fn new_with_limits(limits: BlockCostLimits) -> Self {
+        Self {
+            limits,
             instruction_execution_cost_table: ExecuteCostTable::default(),
             transaction_cost: TransactionCost::new_with_capacity(MAX_WRITABLE_ACCOUNTS),
         }
     }
 
     pub fn get_account_cost_limit(&self) -> u64 {
-        self.account_cost_limit
+        self.limits.account_cost_limit
     }
 
     pub fn get_block_cost_limit(&self) -> u64 {
-        self.block_cost_limit
+        self.limits.block_cost_limit
+    }
+
+    pub fn get_block_cost_limits(&self) -> &BlockCostLimits {
+        &self.limits
     }
 
     pub fn initialize_cost_table(&mut self, cost_table: &[(Pubkey, u64)]) {
@@ -130,7 +224,10 @@ impl CostModel {
         self.transaction_cost.reset();
 
         // calculate transaction exeution cost
-        self.transaction_cost.execution_cost = self.find_transaction_cost(transaction);
+        self.transaction_cost.execution_cost = Self::find_transaction_cost(transaction);
+        self.transaction_cost.is_vote = Self::is_vote_transaction(transaction);
+        self.transaction_cost.account_data_growth =
+            Self::calculate_account_data_growth(transaction);
 
         // calculate account access cost
         let message = transaction.message();
@@ -177,27 +274,50 @@ impl CostModel {
         self.instruction_execution_cost_table.get_cost_table()
     }
 
-    fn find_instruction_cost(&self, program_key: &Pubkey) -> u64 {
-        match self.instruction_execution_cost_table.get_cost(program_key) {
+    // Builtin programs are charged their fixed cost, any other program the compute units an
+    // instruction may consume by default
+    fn find_instruction_cost(program_key: &Pubkey) -> u64 {
+        match BUILT_IN_INSTRUCTION_COSTS.get(program_key) {
             Some(cost) => *cost,
-            None => {
-                let default_value = self.instruction_execution_cost_table.get_mode();
-                debug!(
-                    "Program key {:?} does not have assigned cost, using mode {}",
-                    program_key, default_value
-                );
-                default_value
-            }
+            None => ComputeBudget::default().max_units,
         }
     }
 
-    fn find_transaction_cost(&self, transaction: &SanitizedTransaction) -> u64 {
+    fn is_vote_transaction(transaction: &SanitizedTransaction) -> bool {
+        let message = transaction.message();
+        message.instructions.iter().any(|instruction| {
+            message.account_keys[instruction.program_id_index as usize] == solana_vote_program::id()
+        })
+    }
+
+    // Sum of the account space requested through the system program
+    fn calculate_account_data_growth(transaction: &SanitizedTransaction) -> u64 {
+        let message = transaction.message();
+        message
+            .instructions
+            .iter()
+            .filter(|instruction| {
+                message.account_keys[instruction.program_id_index as usize] == system_program::id()
+            })
+            .filter_map(
+                |instruction| match limited_deserialize(&instruction.data).ok()? {
+                    SystemInstruction::CreateAccount { space, .. }
+                    | SystemInstruction::CreateAccountWithSeed { space, .. }
+                    | SystemInstruction::Allocate { space }
+                    | SystemInstruction::AllocateWithSeed { space, .. } => Some(space),
+                    _ => None,
+                },
+            )
+            .fold(0, u64::saturating_add)
+    }
+
+    fn find_transaction_cost(transaction: &SanitizedTransaction) -> u64 {
         let mut cost: u64 = 0;
 
         for instruction in &transaction.message().instructions {
             let program_id =
                 transaction.message().account_keys[instruction.program_id_index as usize];
-            let instruction_cost = self.find_instruction_cost(&program_id);
+            let instruction_cost = Self::find_instruction_cost(&program_id);
             trace!(
                 "instruction {:?} has cost of {}",
                 instruction,
@@ -212,7 +332,7 @@ impl CostModel {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use solana_runtime::{
+    use crate::{
         bank::Bank,
         genesis_utils::{create_genesis_config, GenesisConfigInfo},
     };
@@ -249,22 +369,26 @@ mod tests {
     fn test_cost_model_instruction_cost() {
         let mut testee = CostModel::default();
 
+        // builtin programs have a fixed cost
+        assert_eq!(200, CostModel::find_instruction_cost(&system_program::id()));
+        assert_eq!(
+            2_800,
+            CostModel::find_instruction_cost(&solana_vote_program::id())
+        );
+
+        // any other program is charged the default compute units, whatever was learned for it
         let known_key = Pubkey::from_str("known11111111111111111111111111111111111111").unwrap();
         testee.upsert_instruction_cost(&known_key, 100).unwrap();
-        // find cost for known programs
-        assert_eq!(100, testee.find_instruction_cost(&known_key));
-
+        assert_eq!(
+            ComputeBudget::default().max_units,
+            CostModel::find_instruction_cost(&known_key)
+        );
         testee
             .upsert_instruction_cost(&bpf_loader::id(), 1999)
             .unwrap();
-        assert_eq!(1999, testee.find_instruction_cost(&bpf_loader::id()));
-
-        // unknown program is assigned with default cost
         assert_eq!(
-            testee.instruction_execution_cost_table.get_mode(),
-            testee.find_instruction_cost(
-                &Pubkey::from_str("unknown111111111111111111111111111111111111").unwrap()
-            )
+            ComputeBudget::default().max_units,
+            CostModel::find_instruction_cost(&bpf_loader::id())
         );
     }
 
@@ -283,15 +407,11 @@ mod tests {
         );
 
         // expected cost for one system transfer instructions
-        let expected_cost = 8;
+        let expected_cost = 200;
 
-        let mut testee = CostModel::default();
-        testee
-            .upsert_instruction_cost(&system_program::id(), expected_cost)
-            .unwrap();
         assert_eq!(
             expected_cost,
-            testee.find_transaction_cost(&simple_transaction)
+            CostModel::find_transaction_cost(&simple_transaction)
         );
     }
 
@@ -310,14 +430,10 @@ mod tests {
         debug!("many transfer transaction {:?}", tx);
 
         // expected cost for two system transfer instructions
-        let program_cost = 8;
+        let program_cost = 200;
         let expected_cost = program_cost * 2;
 
-        let mut testee = CostModel::default();
-        testee
-            .upsert_instruction_cost(&system_program::id(), program_cost)
-            .unwrap();
-        assert_eq!(expected_cost, testee.find_transaction_cost(&tx));
+        assert_eq!(expected_cost, CostModel::find_transaction_cost(&tx));
     }
 
     #[test]
@@ -344,11 +460,10 @@ mod tests {
         .unwrap();
         debug!("many random transaction {:?}", tx);
 
-        let testee = CostModel::default();
-        let result = testee.find_transaction_cost(&tx);
+        let result = CostModel::find_transaction_cost(&tx);
 
         // expected cost for two random/unknown program is
-        let expected_cost = testee.instruction_execution_cost_table.get_mode() * 2;
+        let expected_cost = ComputeBudget::default().max_units * 2;
         assert_eq!(expected_cost, result);
     }
 
@@ -390,17 +505,16 @@ mod tests {
         let cost1 = 100;
 
         let mut cost_model = CostModel::default();
-        // Using default cost for unknown instruction
-        assert_eq!(
-            cost_model.instruction_execution_cost_table.get_mode(),
-            cost_model.find_instruction_cost(&key1)
-        );
+        assert_eq!(None, cost_model.get_instruction_cost_table().get(&key1));
 
         // insert instruction cost to table
         assert!(cost_model.upsert_instruction_cost(&key1, cost1).is_ok());
 
         // now it is known insturction with known cost
-        assert_eq!(cost1, cost_model.find_instruction_cost(&key1));
+        assert_eq!(
+            Some(&cost1),
+            cost_model.get_instruction_cost_table().get(&key1)
+        );
     }
 
     #[test]
@@ -414,11 +528,12 @@ mod tests {
         let expected_account_cost = SIGNED_WRITABLE_ACCOUNT_ACCESS_COST
             + NON_SIGNED_WRITABLE_ACCOUNT_ACCESS_COST
             + NON_SIGNED_READONLY_ACCOUNT_ACCESS_COST;
-        let expected_execution_cost = 8;
+        let expected_execution_cost = 200;
 
+        // the learned cost is not charged
         let mut cost_model = CostModel::default();
         cost_model
-            .upsert_instruction_cost(&system_program::id(), expected_execution_cost)
+            .upsert_instruction_cost(&system_program::id(), 8)
             .unwrap();
         let tx_cost = cost_model.calculate_cost(&tx);
         assert_eq!(expected_account_cost, tx_cost.account_access_cost);
@@ -437,11 +552,17 @@ mod tests {
 
         // insert instruction cost to table
         assert!(cost_model.upsert_instruction_cost(&key1, cost1).is_ok());
-        assert_eq!(cost1, cost_model.find_instruction_cost(&key1));
+        assert_eq!(
+            Some(&cost1),
+            cost_model.get_instruction_cost_table().get(&key1)
+        );
 
         // update instruction cost
         assert!(cost_model.upsert_instruction_cost(&key1, cost2).is_ok());
-        assert_eq!(updated_cost, cost_model.find_instruction_cost(&key1));
+        assert_eq!(
+            Some(&updated_cost),
+            cost_model.get_instruction_cost_table().get(&key1)
+        );
     }
 
     #[test]
@@ -473,7 +594,8 @@ mod tests {
             + NON_SIGNED_READONLY_ACCOUNT_ACCESS_COST * 2;
         let cost1 = 100;
         let cost2 = 200;
-        // execution cost can be either 2 * Default (before write) or cost1+cost2 (after write)
+        // execution cost is 2 * Default, before and after write
+        let expected_execution_cost = ComputeBudget::default().max_units * 2;
 
         let cost_model: Arc<RwLock<CostModel>> = Arc::new(RwLock::new(CostModel::default()));
 
@@ -494,6 +616,7 @@ mod tests {
                         let tx_cost = cost_model.calculate_cost(&tx);
                         assert_eq!(3, tx_cost.writable_accounts.len());
                         assert_eq!(expected_account_cost, tx_cost.account_access_cost);
+                        assert_eq!(expected_execution_cost, tx_cost.execution_cost);
                     })
                 }
             })
@@ -504,6 +627,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cost_model_vote_and_account_data_growth() {
+        let (mint_keypair, start_hash) = test_setup();
+        let new_account = Keypair::new();
+        let instructions = vec![
+            system_instruction::create_account(
+                &mint_keypair.pubkey(),
+                &new_account.pubkey(),
+                1,
+                100,
+                &system_program::id(),
+            ),
+            system_instruction::allocate(&new_account.pubkey(), 50),
+            system_instruction::transfer(&mint_keypair.pubkey(), &new_account.pubkey(), 1),
+        ];
+        let message = Message::new(&instructions, Some(&mint_keypair.pubkey()));
+        let tx: SanitizedTransaction =
+            Transaction::new(&[&mint_keypair, &new_account], message, start_hash)
+                .try_into()
+                .unwrap();
+
+        let mut cost_model = CostModel::default();
+        let tx_cost = cost_model.calculate_cost(&tx);
+        assert!(!tx_cost.is_vote);
+        assert_eq!(150, tx_cost.account_data_growth);
+
+        let vote_keypair = Keypair::new();
+        let vote_tx: SanitizedTransaction =
+            solana_vote_program::vote_transaction::new_vote_transaction(
+                vec![1],
+                Hash::default(),
+                start_hash,
+                &mint_keypair,
+                &vote_keypair,
+                &vote_keypair,
+                None,
+            )
+            .try_into()
+            .unwrap();
+        let tx_cost = cost_model.calculate_cost(&vote_tx);
+        assert!(tx_cost.is_vote);
+        assert_eq!(0, tx_cost.account_data_growth);
+    }
+
     #[test]
     fn test_cost_model_init_cost_table() {
         // build cost table
@@ -519,7 +686,7 @@ mod tests {
 
         // verify
         for (id, cost) in cost_table.iter() {
-            assert_eq!(*cost, cost_model.find_instruction_cost(id));
+            assert_eq!(Some(cost), cost_model.get_instruction_cost_table().get(id));
         }
     }
 }
@@ -3,6 +3,11 @@
 //! The main functions are:
 //! - would_transaction_fit(&tx), immutable function to test if `tx` would fit into current block
 //! - add_transaction_cost(&tx), mutable function to accumulate `tx` cost to tracker.
+//! - try_add_transaction(&tx), mutable function to reserve `tx` cost if it fits, and
+//!   remove_transaction_cost(slot, &tx) to release it when `tx` is not committed after all.
+//!
+//! Vote and non-vote transactions are budgeted separately, and so is the account
+//! data the block's transactions allocate.
 //!
 use crate::cost_model::{CostModel, CostModelError, TransactionCost};
 use solana_sdk::{clock::Slot, pubkey::Pubkey, sanitized_transaction::SanitizedTransaction};
@@ -18,28 +23,40 @@ pub struct CostTracker {
     cost_model: Arc<RwLock<CostModel>>,
     account_cost_limit: u64,
     block_cost_limit: u64,
+    vote_cost_limit: u64,
+    non_vote_cost_limit: u64,
+    account_data_growth_limit: u64,
     current_bank_slot: Slot,
     cost_by_writable_accounts: HashMap<Pubkey, u64>,
     block_cost: u64,
+    vote_cost: u64,
+    account_data_growth: u64,
+}
+
+impl Default for CostTracker {
+    fn default() -> Self {
+        CostTracker::new(Arc::new(RwLock::new(CostModel::default())))
+    }
 }
 
 impl CostTracker {
     pub fn new(cost_model: Arc<RwLock<CostModel>>) -> Self {
-        let (account_cost_limit, block_cost_limit) = {
-            let cost_model = cost_model.read().unwrap();
-            (
-                cost_model.get_account_cost_limit(),
-                cost_model.get_block_cost_limit(),
-            )
-        };
-        assert!(account_cost_limit <= block_cost_limit);
+        let limits = cost_model.read().unwrap().get_block_cost_limits().clone();
+        assert!(limits.account_cost_limit <= limits.block_cost_limit);
+        assert!(limits.vote_cost_limit <= limits.block_cost_limit);
+        assert!(limits.non_vote_cost_limit <= limits.block_cost_limit);
         Self {
             cost_model,
-            account_cost_limit,
-            block_cost_limit,
+            account_cost_limit: limits.account_cost_limit,
+            block_cost_limit: limits.block_cost_limit,
+            vote_cost_limit: limits.vote_cost_limit,
+            non_vote_cost_limit: limits.non_vote_cost_limit,
+            account_data_growth_limit: limits.account_data_growth_limit,
             current_bank_slot: 0,
             cost_by_writable_accounts: HashMap::with_capacity(WRITABLE_ACCOUNTS_PER_BLOCK),
             block_cost: 0,
+            vote_cost: 0,
+            account_data_growth: 0,
         }
     }
 
@@ -49,23 +66,45 @@ impl CostTracker {
     ) -> Result<(), CostModelError> {
         let mut cost_model = self.cost_model.write().unwrap();
         let tx_cost = cost_model.calculate_cost(transaction);
-        self.would_fit(
-            &tx_cost.writable_accounts,
-            &(tx_cost.account_access_cost + tx_cost.execution_cost),
-        )
+        self.would_cost_fit(tx_cost)
     }
 
-    pub fn add_transaction_cost(&mut self, transaction: &SanitizedTransaction) {
+    /// Compute cost of `transaction` according to the cost model.
+    pub fn transaction_cost(&self, transaction: &SanitizedTransaction) -> u64 {
         let mut cost_model = self.cost_model.write().unwrap();
         let tx_cost = cost_model.calculate_cost(transaction);
-        let cost = tx_cost.account_access_cost + tx_cost.execution_cost;
-        for account_key in tx_cost.writable_accounts.iter() {
-            *self
-                .cost_by_writable_accounts
-                .entry(*account_key)
-                .or_insert(0) += cost;
+        tx_cost.sum()
+    }
+
+    pub fn add_transaction_cost(&mut self, transaction: &SanitizedTransaction) {
+        let cost_model = self.cost_model.clone();
+        let mut cost_model = cost_model.write().unwrap();
+        let tx_cost = cost_model.calculate_cost(transaction);
+        self.add_cost(tx_cost);
+    }
+
+    /// Check `transaction` against every limit and, if it fits, account for it.
+    pub fn try_add_transaction(
+        &mut self,
+        transaction: &SanitizedTransaction,
+    ) -> Result<u64, CostModelError> {
+        let cost_model = self.cost_model.clone();
+        let mut cost_model = cost_model.write().unwrap();
+        let tx_cost = cost_model.calculate_cost(transaction);
+        self.try_add(tx_cost)
+    }
+
+    /// Release the cost of `transaction`, reserved with `try_add_transaction` while tracking
+    /// `slot`, because it was not committed. Nothing is released once the tracker has moved on
+    /// to another bank, the reservation was dropped with the old bank's costs.
+    pub fn remove_transaction_cost(&mut self, slot: Slot, transaction: &SanitizedTransaction) {
+        if slot != self.current_bank_slot {
+            return;
         }
-        self.block_cost += cost;
+        let cost_model = self.cost_model.clone();
+        let mut cost_model = cost_model.write().unwrap();
+        let tx_cost = cost_model.calculate_cost(transaction);
+        self.sub_cost(tx_cost);
     }
 
     pub fn reset_if_new_bank(&mut self, slot: Slot) {
@@ -73,17 +112,65 @@ impl CostTracker {
             self.current_bank_slot = slot;
             self.cost_by_writable_accounts.clear();
             self.block_cost = 0;
+            self.vote_cost = 0;
+            self.account_data_growth = 0;
         }
     }
 
     pub fn try_add(&mut self, transaction_cost: &TransactionCost) -> Result<u64, CostModelError> {
-        let cost = transaction_cost.account_access_cost + transaction_cost.execution_cost;
-        self.would_fit(&transaction_cost.writable_accounts, &cost)?;
+        self.would_cost_fit(transaction_cost)?;
 
-        self.add_transaction(&transaction_cost.writable_accounts, &cost);
+        self.add_cost(transaction_cost);
         Ok(self.block_cost)
     }
 
+    fn would_cost_fit(&self, transaction_cost: &TransactionCost) -> Result<(), CostModelError> {
+        let cost = transaction_cost.sum();
+        if transaction_cost.is_vote {
+            if self.vote_cost + cost > self.vote_cost_limit {
+                return Err(CostModelError::WouldExceedVoteMaxLimit);
+            }
+        } else if self.block_cost - self.vote_cost + cost > self.non_vote_cost_limit {
+            return Err(CostModelError::WouldExceedNonVoteMaxLimit);
+        }
+
+        if self.account_data_growth + transaction_cost.account_data_growth
+            > self.account_data_growth_limit
+        {
+            return Err(CostModelError::WouldExceedAccountDataMaxLimit);
+        }
+
+        self.would_fit(&transaction_cost.writable_accounts, &cost)
+    }
+
+    fn add_cost(&mut self, transaction_cost: &TransactionCost) {
+        let cost = transaction_cost.sum();
+        self.add_transaction(&transaction_cost.writable_accounts, &cost);
+        if transaction_cost.is_vote {
+            self.vote_cost += cost;
+        }
+        self.account_data_growth += transaction_cost.account_data_growth;
+    }
+
+    fn sub_cost(&mut self, transaction_cost: &TransactionCost) {
+        let cost = transaction_cost.sum();
+        for account_key in transaction_cost.writable_accounts.iter() {
+            if let Some(chained_cost) = self.cost_by_writable_accounts.get_mut(account_key) {
+                *chained_cost = chained_cost.saturating_sub(cost);
+                if *chained_cost == 0 {
+                    self.cost_by_writable_accounts.remove(account_key);
+                }
+            }
+        }
+        self.block_cost = self.block_cost.saturating_sub(cost);
+        if transaction_cost.is_vote {
+            self.vote_cost = self.vote_cost.saturating_sub(cost);
+        }
+        self.account_data_growth = self
+            .account_data_growth
+            .saturating_sub(transaction_cost.account_data_growth);
+    }
+
     fn would_fit(&self, keys: &[Pubkey], cost: &u64) -> Result<(), CostModelError> {
         // check against the total package cost
         if self.block_cost + cost > self.block_cost_limit {
@@ -128,6 +215,8 @@ impl CostTracker {
 pub struct CostStats {
     pub bank_slot: Slot,
     pub total_cost: u64,
+    pub vote_cost: u64,
+    pub account_data_growth: u64,
     pub number_of_accounts: usize,
     pub costliest_account: Pubkey,
     pub costliest_account_cost: u64,
@@ -138,6 +227,8 @@ impl CostTracker {
         let mut stats = CostStats {
             bank_slot: self.current_bank_slot,
             total_cost: self.block_cost,
+            vote_cost: self.vote_cost,
+            account_data_growth: self.account_data_growth,
             number_of_accounts: self.cost_by_writable_accounts.len(),
             costliest_account: Pubkey::default(),
             costliest_account_cost: 0,
@@ -157,8 +248,9 @@ impl CostTracker {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use solana_runtime::{
+    use crate::{
         bank::Bank,
+        cost_model::BlockCostLimits,
         genesis_utils::{create_genesis_config, GenesisConfigInfo},
     };
     use solana_sdk::{
@@ -167,7 +259,7 @@ mod tests {
         system_transaction,
         transaction::Transaction,
     };
-    use std::{cmp, sync::Arc};
+    use std::{cmp, convert::TryFrom, sync::Arc};
 
     fn test_setup() -> (Keypair, Hash) {
         solana_logger::setup();
@@ -197,6 +289,8 @@ mod tests {
         let testee = CostTracker::new(Arc::new(RwLock::new(CostModel::new(10, 11))));
         assert_eq!(10, testee.account_cost_limit);
         assert_eq!(11, testee.block_cost_limit);
+        assert_eq!(11, testee.vote_cost_limit);
+        assert_eq!(11, testee.non_vote_cost_limit);
         assert_eq!(0, testee.cost_by_writable_accounts.len());
         assert_eq!(0, testee.block_cost);
     }
@@ -368,6 +462,7 @@ mod tests {
                 writable_accounts: vec![acct1, acct2, acct3],
                 account_access_cost: 0,
                 execution_cost: cost,
+                ..TransactionCost::default()
             };
             assert!(testee.try_add(&tx_cost).is_ok());
             let stat = testee.get_stats();
@@ -386,6 +481,7 @@ mod tests {
                 writable_accounts: vec![acct2],
                 account_access_cost: 0,
                 execution_cost: cost,
+                ..TransactionCost::default()
             };
             assert!(testee.try_add(&tx_cost).is_ok());
             let stat = testee.get_stats();
@@ -406,6 +502,7 @@ mod tests {
                 writable_accounts: vec![acct1, acct2],
                 account_access_cost: 0,
                 execution_cost: cost,
+                ..TransactionCost::default()
             };
             assert!(testee.try_add(&tx_cost).is_err());
             let stat = testee.get_stats();
@@ -415,4 +512,105 @@ mod tests {
             assert_eq!(acct2, stat.costliest_account);
         }
     }
+
+    #[test]
+    fn test_cost_tracker_vote_and_non_vote_limits() {
+        let acct1 = Pubkey::new_unique();
+        let acct2 = Pubkey::new_unique();
+        let cost = 100;
+
+        let mut testee = CostTracker::new(Arc::new(RwLock::new(CostModel::new_with_limits(
+            BlockCostLimits {
+                account_cost_limit: cost * 4,
+                block_cost_limit: cost * 4,
+                vote_cost_limit: cost * 2,
+                non_vote_cost_limit: cost * 3,
+                account_data_growth_limit: 0,
+            },
+        ))));
+        let vote_cost = TransactionCost {
+            writable_accounts: vec![acct1],
+            execution_cost: cost,
+            is_vote: true,
+            ..TransactionCost::default()
+        };
+        let non_vote_cost = TransactionCost {
+            writable_accounts: vec![acct2],
+            execution_cost: cost,
+            ..TransactionCost::default()
+        };
+
+        // votes fill up their share of the block
+        assert!(testee.try_add(&vote_cost).is_ok());
+        assert!(testee.try_add(&vote_cost).is_ok());
+        assert!(matches!(
+            testee.try_add(&vote_cost),
+            Err(CostModelError::WouldExceedVoteMaxLimit)
+        ));
+
+        // non-votes still have room, but only up to the block limit
+        assert!(testee.try_add(&non_vote_cost).is_ok());
+        assert!(testee.try_add(&non_vote_cost).is_ok());
+        assert!(matches!(
+            testee.try_add(&non_vote_cost),
+            Err(CostModelError::WouldExceedBlockMaxLimit)
+        ));
+
+        let stats = testee.get_stats();
+        assert_eq!(cost * 4, stats.total_cost);
+        assert_eq!(cost * 2, stats.vote_cost);
+    }
+
+    #[test]
+    fn test_cost_tracker_account_data_growth_limit() {
+        let mut testee = CostTracker::new(Arc::new(RwLock::new(CostModel::new_with_limits(
+            BlockCostLimits {
+                account_data_growth_limit: 1_000,
+                ..BlockCostLimits::default()
+            },
+        ))));
+        let tx_cost = TransactionCost {
+            writable_accounts: vec![Pubkey::new_unique()],
+            execution_cost: 1,
+            account_data_growth: 600,
+            ..TransactionCost::default()
+        };
+        assert!(testee.try_add(&tx_cost).is_ok());
+        assert!(matches!(
+            testee.try_add(&tx_cost),
+            Err(CostModelError::WouldExceedAccountDataMaxLimit)
+        ));
+        assert_eq!(600, testee.get_stats().account_data_growth);
+
+        // the budget is per block
+        testee.reset_if_new_bank(1);
+        assert!(testee.try_add(&tx_cost).is_ok());
+    }
+
+    #[test]
+    fn test_cost_tracker_remove_transaction_cost() {
+        let (mint_keypair, start_hash) = test_setup();
+        let (tx, _keys, _cost) = build_simple_transaction(&mint_keypair, &start_hash);
+        let tx = SanitizedTransaction::try_from(tx).unwrap();
+        let mut testee = CostTracker::default();
+        let cost = testee.transaction_cost(&tx);
+
+        testee.reset_if_new_bank(1);
+        assert_eq!(Ok(cost), testee.try_add_transaction(&tx).map_err(|_| ()));
+        assert_eq!(
+            Ok(cost * 2),
+            testee.try_add_transaction(&tx).map_err(|_| ())
+        );
+
+        // releasing a reservation for another bank leaves the costs untouched
+        testee.remove_transaction_cost(2, &tx);
+        assert_eq!(cost * 2, testee.block_cost);
+
+        testee.remove_transaction_cost(1, &tx);
+        assert_eq!(cost, testee.block_cost);
+        assert_eq!(2, testee.cost_by_writable_accounts.len());
+        testee.remove_transaction_cost(1, &tx);
+        assert_eq!(0, testee.block_cost);
+        assert!(testee.cost_by_writable_accounts.is_empty());
+    }
 }
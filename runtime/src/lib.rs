@@ -1,5 +1,6 @@
 #![cfg_attr(RUSTC_WITH_SPECIALIZATION, feature(min_specialization))]
 #![allow(clippy::integer_arithmetic)]
+pub mod account_overrides;
 pub mod accounts;
 pub mod accounts_background_service;
 pub mod accounts_cache;
@@ -17,7 +18,10 @@ pub mod bloom;
 pub mod builtins;
 pub mod commitment;
 pub mod contains;
+pub mod cost_model;
+pub mod cost_tracker;
 pub mod epoch_stakes;
+pub mod execute_cost_table;
 pub mod genesis_utils;
 pub mod hardened_unpack;
 pub mod inline_spl_token_v2_0;
@@ -199,6 +199,30 @@ pub mod spl_token_v2_set_authority_fix {
     solana_sdk::declare_id!("FToKNBYyiF4ky9s8WsmLBXHCht17Ek7RXaLZGHzzQhJ1");
 }
 
+pub mod gate_large_block {
+    solana_sdk::declare_id!("2ry7ygxiYURULZCrypHhveanvP5tzZ4toRwVp89oCNSj");
+}
+
+pub mod merkle_shreds {
+    solana_sdk::declare_id!("8gQFv9BuSVEidXtTXFwoLPKkZYykd6d3mvKzjKKbQDaF");
+}
+
+pub mod stake_weighted_turbine_tree {
+    solana_sdk::declare_id!("7XPJoCHiadYLcVK3Xtior7vCooVbHx1HVAv6Xd1pPKPU");
+}
+
+pub mod stake_weighted_turbine_fanout_64 {
+    solana_sdk::declare_id!("DMotdWhVyffCAMLeoBRxdiuFVrimgh2Gge5Jspvhr7xE");
+}
+
+pub mod sign_repair_requests {
+    solana_sdk::declare_id!("UMssjSFoPie65oH8SMAuydbDk3DWGUT6ruNAtBYHbJ2");
+}
+
+pub mod variable_erasure_batch_size {
+    solana_sdk::declare_id!("9Hs2qEHsZ9AhFwrUT2hn1JR6VfHFnV3uSdAJGWFjVgbR");
+}
+
 lazy_static! {
     /// Map of feature identifiers to user-visible description
     pub static ref FEATURE_NAMES: HashMap<Pubkey, &'static str> = [
@@ -243,6 +267,12 @@ lazy_static! {
         (libsecp256k1_0_5_upgrade_enabled::id(), "upgrade libsecp256k1 to v0.5.0"),
         (tx_wide_compute_cap::id(), "Transaction wide compute cap"),
         (spl_token_v2_set_authority_fix::id(), "spl-token set_authority fix"),
+        (gate_large_block::id(), "validator checks block cost against max limit in realtime, reject if exceeds."),
+        (merkle_shreds::id(), "leader signs the merkle root of each erasure batch instead of every shred"),
+        (stake_weighted_turbine_tree::id(), "retransmit shreds over a stake-weighted tree without neighborhoods"),
+        (sign_repair_requests::id(), "sign repair requests with the requester's identity"),
+        (variable_erasure_batch_size::id(), "size erasure batches by their number of data shreds and index coding shreds sequentially"),
+        (stake_weighted_turbine_fanout_64::id(), "retransmit shreds over the stake-weighted tree with a fanout of 64"),
         /*************** ADD NEW FEATURES HERE ***************/
     ]
     .iter()
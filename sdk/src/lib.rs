@@ -38,6 +38,7 @@ pub mod poh_config;
 pub mod process_instruction;
 pub mod program_utils;
 pub mod pubkey;
+pub mod quic;
 pub mod recent_blockhashes_account;
 pub mod rpc_port;
 pub mod sanitized_transaction;
@@ -45,6 +46,7 @@ pub mod secp256k1_instruction;
 pub mod shred_version;
 pub mod signature;
 pub mod signer;
+pub mod spl_token_ids;
 pub mod system_transaction;
 pub mod timing;
 pub mod transaction;
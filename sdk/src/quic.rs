@@ -0,0 +1,24 @@
+//! Definitions shared by the QUIC TPU server and its clients
+
+/// The QUIC TPU listens on the UDP TPU port plus this offset
+pub const QUIC_PORT_OFFSET: u16 = 6;
+
+/// Maximum number of unidirectional streams, i.e. transactions in flight, a client may have open
+/// on a single connection
+pub const QUIC_MAX_CONCURRENT_STREAMS: usize = 2048;
+
+/// Streams a connection from an unstaked client may have open at once
+pub const QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS: usize = 128;
+
+/// Streams shared by all staked clients in proportion to their stake, before the per connection
+/// bounds are applied
+pub const QUIC_TOTAL_STAKED_CONCURRENT_STREAMS: usize = 100_000;
+
+/// Streams a connection from a staked client may have open at once, however small its stake
+pub const QUIC_MIN_STAKED_CONCURRENT_STREAMS: usize = 128;
+
+/// Connections idle for longer than this are closed
+pub const QUIC_MAX_TIMEOUT_MS: u32 = 2_000;
+
+/// Clients ping idle connections this often to keep them open
+pub const QUIC_KEEP_ALIVE_MS: u64 = 1_000;
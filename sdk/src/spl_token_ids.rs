@@ -0,0 +1,79 @@
+//! Registry of the program ids whose accounts and instructions share the SPL Token v2.0 layout,
+//! so that they are decoded, indexed and served like SPL Token ones. It is set once, at startup,
+//! and read by the account decoder, the accounts index and the RPC service alike.
+
+use crate::pubkey::Pubkey;
+use once_cell::sync::OnceCell;
+
+mod spl_token_v2_0 {
+    crate::declare_id!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+}
+
+/// SPL Token compatible program ids. The SPL Token v2.0 program id is always included
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SplTokenIds {
+    additional: Vec<Pubkey>,
+}
+
+impl SplTokenIds {
+    pub fn new(additional: Vec<Pubkey>) -> Self {
+        let spl_token_id = spl_token_v2_0::id();
+        let mut ids = Self { additional: vec![] };
+        for program_id in additional {
+            if program_id != spl_token_id && !ids.additional.contains(&program_id) {
+                ids.additional.push(program_id);
+            }
+        }
+        ids
+    }
+
+    pub fn contains(&self, program_id: &Pubkey) -> bool {
+        spl_token_v2_0::check_id(program_id) || self.additional.contains(program_id)
+    }
+
+    /// Every SPL Token compatible program id, the SPL Token v2.0 one first
+    pub fn iter(&self) -> impl Iterator<Item = Pubkey> + '_ {
+        std::iter::once(spl_token_v2_0::id()).chain(self.additional.iter().copied())
+    }
+}
+
+static SPL_TOKEN_IDS: OnceCell<SplTokenIds> = OnceCell::new();
+
+/// Sets the SPL Token compatible program ids for the rest of the process. Has to be called at
+/// startup: it fails once the ids have been set or looked up
+pub fn set_spl_token_ids(spl_token_ids: SplTokenIds) -> Result<(), SplTokenIds> {
+    SPL_TOKEN_IDS.set(spl_token_ids)
+}
+
+pub fn spl_token_ids() -> &'static SplTokenIds {
+    SPL_TOKEN_IDS.get_or_init(SplTokenIds::default)
+}
+
+pub fn is_known_spl_token_id(program_id: &Pubkey) -> bool {
+    spl_token_ids().contains(program_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spl_token_ids() {
+        let default_ids = SplTokenIds::default();
+        assert!(default_ids.contains(&spl_token_v2_0::id()));
+        assert!(!default_ids.contains(&Pubkey::new_unique()));
+        assert_eq!(
+            default_ids.iter().collect::<Vec<_>>(),
+            vec![spl_token_v2_0::id()]
+        );
+
+        let program_id = Pubkey::new_unique();
+        let ids = SplTokenIds::new(vec![program_id, spl_token_v2_0::id(), program_id]);
+        assert!(ids.contains(&spl_token_v2_0::id()));
+        assert!(ids.contains(&program_id));
+        assert_eq!(
+            ids.iter().collect::<Vec<_>>(),
+            vec![spl_token_v2_0::id(), program_id]
+        );
+    }
+}
@@ -1,23 +1,17 @@
-// TODO: Merge this implementation with the one at `banks-server/src/send_transaction_service.rs`
 use {
+    crate::tpu_info::TpuInfo,
     log::*,
-    solana_gossip::cluster_info::ClusterInfo,
+    solana_client::connection_cache,
     solana_metrics::{datapoint_warn, inc_new_counter_info},
-    solana_poh::poh_recorder::PohRecorder,
     solana_runtime::{bank::Bank, bank_forks::BankForks},
-    solana_sdk::{
-        clock::{Slot, NUM_CONSECUTIVE_LEADER_SLOTS},
-        hash::Hash,
-        nonce_account,
-        pubkey::Pubkey,
-        signature::Signature,
-    },
+    solana_sdk::{clock::Slot, hash::Hash, nonce_account, pubkey::Pubkey, signature::Signature},
+    solana_streamer::sendmmsg::{batch_send, SendPktsError},
     std::{
         collections::HashMap,
         net::{SocketAddr, UdpSocket},
         sync::{
             mpsc::{Receiver, RecvTimeoutError},
-            Arc, Mutex, RwLock,
+            Arc, RwLock,
         },
         thread::{self, Builder, JoinHandle},
         time::{Duration, Instant},
@@ -26,6 +20,36 @@ use {
 
 /// Maximum size of the transaction queue
 const MAX_TRANSACTION_QUEUE_SIZE: usize = 10_000; // This seems like a lot but maybe it needs to be bigger one day
+/// Maximum number of transactions sent to a leader with a single `sendmmsg`
+const MAX_TRANSACTION_BATCH_SIZE: usize = 128;
+/// How often the TPU addresses of the leaders are refreshed
+const LEADER_INFO_REFRESH_RATE_MS: u64 = 1000;
+
+pub const DEFAULT_RETRY_RATE_MS: u64 = 2_000;
+pub const DEFAULT_LEADER_FORWARD_COUNT: u64 = 2;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// How often queued transactions are checked and resent
+    pub retry_rate_ms: u64,
+    /// Number of leaders, starting with the current one, each transaction is sent to
+    pub leader_forward_count: u64,
+    /// Retries of transactions that don't set their own limit, unlimited if `None`
+    pub default_max_retries: Option<usize>,
+    /// Upper bound on the retries of any transaction
+    pub service_max_retries: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            retry_rate_ms: DEFAULT_RETRY_RATE_MS,
+            leader_forward_count: DEFAULT_LEADER_FORWARD_COUNT,
+            default_max_retries: None,
+            service_max_retries: usize::MAX,
+        }
+    }
+}
 
 pub struct SendTransactionService {
     thread: JoinHandle<()>,
@@ -36,6 +60,8 @@ pub struct TransactionInfo {
     pub wire_transaction: Vec<u8>,
     pub last_valid_slot: Slot,
     pub durable_nonce_info: Option<(Pubkey, Hash)>,
+    pub max_retries: Option<usize>,
+    retries: usize,
 }
 
 impl TransactionInfo {
@@ -44,98 +70,105 @@ impl TransactionInfo {
         wire_transaction: Vec<u8>,
         last_valid_slot: Slot,
         durable_nonce_info: Option<(Pubkey, Hash)>,
+        max_retries: Option<usize>,
     ) -> Self {
         Self {
             signature,
             wire_transaction,
             last_valid_slot,
             durable_nonce_info,
+            max_retries,
+            retries: 0,
         }
     }
 }
 
-pub struct LeaderInfo {
-    cluster_info: Arc<ClusterInfo>,
-    poh_recorder: Arc<Mutex<PohRecorder>>,
-    recent_peers: HashMap<Pubkey, SocketAddr>,
-}
-
-impl LeaderInfo {
-    pub fn new(cluster_info: Arc<ClusterInfo>, poh_recorder: Arc<Mutex<PohRecorder>>) -> Self {
-        Self {
-            cluster_info,
-            poh_recorder,
-            recent_peers: HashMap::new(),
-        }
-    }
-
-    pub fn refresh_recent_peers(&mut self) {
-        self.recent_peers = self
-            .cluster_info
-            .tpu_peers()
-            .into_iter()
-            .map(|ci| (ci.id, ci.tpu))
-            .collect();
-    }
-
-    pub fn get_leader_tpus(&self, max_count: u64) -> Vec<&SocketAddr> {
-        let recorder = self.poh_recorder.lock().unwrap();
-        let leaders: Vec<_> = (0..max_count)
-            .filter_map(|i| recorder.leader_after_n_slots(i * NUM_CONSECUTIVE_LEADER_SLOTS))
-            .collect();
-        drop(recorder);
-        let mut unique_leaders = vec![];
-        for leader in leaders.iter() {
-            if let Some(addr) = self.recent_peers.get(leader) {
-                if !unique_leaders.contains(&addr) {
-                    unique_leaders.push(addr);
-                }
-            }
-        }
-        unique_leaders
-    }
-}
-
 #[derive(Default, Debug, PartialEq)]
 struct ProcessTransactionsResult {
     rooted: u64,
     expired: u64,
     retried: u64,
+    max_retries_elapsed: u64,
     failed: u64,
     retained: u64,
 }
 
+#[derive(Default, Debug, PartialEq)]
+struct SendCounts {
+    sent: u64,
+    failed: u64,
+}
+
+/// Transactions sent to each TPU address since the last report
+#[derive(Default)]
+struct LeaderSendStats {
+    counts: HashMap<SocketAddr, SendCounts>,
+}
+
+impl LeaderSendStats {
+    fn record(&mut self, address: &SocketAddr, sent: usize, failed: usize) {
+        let counts = self.counts.entry(*address).or_default();
+        counts.sent += sent as u64;
+        counts.failed += failed as u64;
+    }
+
+    fn report(&mut self) {
+        for (address, counts) in self.counts.drain() {
+            datapoint_info!(
+                "send_transaction_service-leader-sends",
+                ("tpu", address.to_string(), String),
+                ("sent", counts.sent, i64),
+                ("failed", counts.failed, i64)
+            );
+        }
+    }
+}
+
 impl SendTransactionService {
-    pub fn new(
+    pub fn new<T: TpuInfo + Send + 'static>(
         tpu_address: SocketAddr,
         bank_forks: &Arc<RwLock<BankForks>>,
-        leader_info: Option<LeaderInfo>,
+        leader_info: Option<T>,
         receiver: Receiver<TransactionInfo>,
         retry_rate_ms: u64,
         leader_forward_count: u64,
+    ) -> Self {
+        let config = Config {
+            retry_rate_ms,
+            leader_forward_count,
+            ..Config::default()
+        };
+        Self::new_with_config(tpu_address, bank_forks, leader_info, receiver, config)
+    }
+
+    pub fn new_with_config<T: TpuInfo + Send + 'static>(
+        tpu_address: SocketAddr,
+        bank_forks: &Arc<RwLock<BankForks>>,
+        leader_info: Option<T>,
+        receiver: Receiver<TransactionInfo>,
+        config: Config,
     ) -> Self {
         let thread = Self::retry_thread(
             tpu_address,
             receiver,
             bank_forks.clone(),
             leader_info,
-            retry_rate_ms,
-            leader_forward_count,
+            config,
         );
         Self { thread }
     }
 
-    fn retry_thread(
+    fn retry_thread<T: TpuInfo + Send + 'static>(
         tpu_address: SocketAddr,
         receiver: Receiver<TransactionInfo>,
         bank_forks: Arc<RwLock<BankForks>>,
-        mut leader_info: Option<LeaderInfo>,
-        retry_rate_ms: u64,
-        leader_forward_count: u64,
+        mut leader_info: Option<T>,
+        config: Config,
     ) -> JoinHandle<()> {
         let mut last_status_check = Instant::now();
         let mut last_leader_refresh = Instant::now();
         let mut transactions = HashMap::new();
+        let mut leader_sends = LeaderSendStats::default();
         let send_socket = UdpSocket::bind("0.0.0.0:0").unwrap();
 
         if let Some(leader_info) = leader_info.as_mut() {
@@ -145,38 +178,37 @@ impl SendTransactionService {
         Builder::new()
             .name("send-tx-sv2".to_string())
             .spawn(move || loop {
-                match receiver.recv_timeout(Duration::from_millis(1000.min(retry_rate_ms))) {
+                match receiver.recv_timeout(Duration::from_millis(1000.min(config.retry_rate_ms))) {
                     Err(RecvTimeoutError::Disconnected) => break,
                     Err(RecvTimeoutError::Timeout) => {}
                     Ok(transaction_info) => {
-                        let addresses = leader_info
-                            .as_ref()
-                            .map(|leader_info| leader_info.get_leader_tpus(leader_forward_count));
-                        let addresses = addresses
-                            .map(|address_list| {
-                                if address_list.is_empty() {
-                                    vec![&tpu_address]
-                                } else {
-                                    address_list
-                                }
-                            })
-                            .unwrap_or_else(|| vec![&tpu_address]);
-                        for address in addresses {
-                            Self::send_transaction(
-                                &send_socket,
-                                address,
-                                &transaction_info.wire_transaction,
-                            );
-                        }
-                        if transactions.len() < MAX_TRANSACTION_QUEUE_SIZE {
-                            transactions.insert(transaction_info.signature, transaction_info);
-                        } else {
-                            datapoint_warn!("send_transaction_service-queue-overflow");
+                        // Send whatever queued up behind the first transaction along with it
+                        let mut new_transactions = vec![transaction_info];
+                        new_transactions
+                            .extend(receiver.try_iter().take(MAX_TRANSACTION_BATCH_SIZE - 1));
+                        let addresses =
+                            Self::get_tpu_addresses(&tpu_address, &leader_info, &config);
+                        let wire_transactions: Vec<_> = new_transactions
+                            .iter()
+                            .map(|transaction_info| &transaction_info.wire_transaction[..])
+                            .collect();
+                        Self::send_transactions(
+                            &send_socket,
+                            &addresses,
+                            &wire_transactions,
+                            &mut leader_sends,
+                        );
+                        for transaction_info in new_transactions {
+                            if transactions.len() < MAX_TRANSACTION_QUEUE_SIZE {
+                                transactions.insert(transaction_info.signature, transaction_info);
+                            } else {
+                                datapoint_warn!("send_transaction_service-queue-overflow");
+                            }
                         }
                     }
                 }
 
-                if last_status_check.elapsed().as_millis() as u64 >= retry_rate_ms {
+                if last_status_check.elapsed().as_millis() as u64 >= config.retry_rate_ms {
                     if !transactions.is_empty() {
                         datapoint_info!(
                             "send_transaction_service-queue-size",
@@ -197,11 +229,15 @@ impl SendTransactionService {
                             &tpu_address,
                             &mut transactions,
                             &leader_info,
-                            leader_forward_count,
+                            &config,
+                            &mut leader_sends,
                         );
                     }
+                    leader_sends.report();
                     last_status_check = Instant::now();
-                    if last_leader_refresh.elapsed().as_millis() > 1000 {
+                    if last_leader_refresh.elapsed().as_millis() as u64
+                        > LEADER_INFO_REFRESH_RATE_MS
+                    {
                         if let Some(leader_info) = leader_info.as_mut() {
                             leader_info.refresh_recent_peers();
                         }
@@ -212,16 +248,19 @@ impl SendTransactionService {
             .unwrap()
     }
 
-    fn process_transactions(
+    #[allow(clippy::too_many_arguments)]
+    fn process_transactions<T: TpuInfo>(
         working_bank: &Arc<Bank>,
         root_bank: &Arc<Bank>,
         send_socket: &UdpSocket,
         tpu_address: &SocketAddr,
         transactions: &mut HashMap<Signature, TransactionInfo>,
-        leader_info: &Option<LeaderInfo>,
-        leader_forward_count: u64,
+        leader_info: &Option<T>,
+        config: &Config,
+        leader_sends: &mut LeaderSendStats,
     ) -> ProcessTransactionsResult {
         let mut result = ProcessTransactionsResult::default();
+        let mut retry_signatures = vec![];
 
         transactions.retain(|signature, transaction_info| {
             if transaction_info.durable_nonce_info.is_some() {
@@ -253,30 +292,24 @@ impl SendTransactionService {
 
             match working_bank.get_signature_status_slot(signature) {
                 None => {
+                    let max_retries = transaction_info
+                        .max_retries
+                        .or(config.default_max_retries)
+                        .unwrap_or(config.service_max_retries)
+                        .min(config.service_max_retries);
+                    if transaction_info.retries >= max_retries {
+                        info!("Dropping transaction due to max retries: {}", signature);
+                        result.max_retries_elapsed += 1;
+                        inc_new_counter_info!("send_transaction_service-max_retries", 1);
+                        return false;
+                    }
                     // Transaction is unknown to the working bank, it might have been
                     // dropped or landed in another fork.  Re-send it
                     info!("Retrying transaction: {}", signature);
                     result.retried += 1;
+                    transaction_info.retries += 1;
                     inc_new_counter_info!("send_transaction_service-retry", 1);
-                    let addresses = leader_info
-                        .as_ref()
-                        .map(|leader_info| leader_info.get_leader_tpus(leader_forward_count));
-                    let addresses = addresses
-                        .map(|address_list| {
-                            if address_list.is_empty() {
-                                vec![tpu_address]
-                            } else {
-                                address_list
-                            }
-                        })
-                        .unwrap_or_else(|| vec![tpu_address]);
-                    for address in addresses {
-                        Self::send_transaction(
-                            send_socket,
-                            address,
-                            &transaction_info.wire_transaction,
-                        );
-                    }
+                    retry_signatures.push(*signature);
                     true
                 }
                 Some((_slot, status)) => {
@@ -293,16 +326,77 @@ impl SendTransactionService {
             }
         });
 
+        if !retry_signatures.is_empty() {
+            let addresses = Self::get_tpu_addresses(tpu_address, leader_info, config);
+            let wire_transactions: Vec<_> = retry_signatures
+                .iter()
+                .filter_map(|signature| transactions.get(signature))
+                .map(|transaction_info| &transaction_info.wire_transaction[..])
+                .collect();
+            for wire_transactions in wire_transactions.chunks(MAX_TRANSACTION_BATCH_SIZE) {
+                Self::send_transactions(send_socket, &addresses, wire_transactions, leader_sends);
+            }
+        }
+
         result
     }
 
-    fn send_transaction(
+    /// TPU addresses of the next `leader_forward_count` leaders, or the fixed `tpu_address`
+    /// if none of them are known
+    fn get_tpu_addresses<'a, T: TpuInfo>(
+        tpu_address: &'a SocketAddr,
+        leader_info: &'a Option<T>,
+        config: &Config,
+    ) -> Vec<&'a SocketAddr> {
+        let addresses = leader_info
+            .as_ref()
+            .map(|leader_info| leader_info.get_leader_tpus(config.leader_forward_count));
+        addresses
+            .map(|address_list| {
+                if address_list.is_empty() {
+                    vec![tpu_address]
+                } else {
+                    address_list
+                }
+            })
+            .unwrap_or_else(|| vec![tpu_address])
+    }
+
+    fn send_transactions(
         send_socket: &UdpSocket,
-        tpu_address: &SocketAddr,
-        wire_transaction: &[u8],
+        addresses: &[&SocketAddr],
+        wire_transactions: &[&[u8]],
+        leader_sends: &mut LeaderSendStats,
     ) {
-        if let Err(err) = send_socket.send_to(wire_transaction, tpu_address) {
-            warn!("Failed to send transaction to {}: {:?}", tpu_address, err);
+        for address in addresses {
+            let num_failed = if connection_cache::use_quic() {
+                // Connections are cached, so every retry reuses the one to this leader
+                match connection_cache::get_connection(address) {
+                    Ok(connection) => {
+                        for wire_transaction in wire_transactions {
+                            connection.send_wire_transaction_async(wire_transaction.to_vec());
+                        }
+                        0
+                    }
+                    Err(err) => {
+                        warn!("Failed to connect to {}: {:?}", address, err);
+                        wire_transactions.len()
+                    }
+                }
+            } else {
+                let packets: Vec<_> = wire_transactions
+                    .iter()
+                    .map(|wire_transaction| (*wire_transaction, *address))
+                    .collect();
+                match batch_send(send_socket, &packets) {
+                    Ok(()) => 0,
+                    Err(SendPktsError::IoError(err, num_failed)) => {
+                        warn!("Failed to send transactions to {}: {:?}", address, err);
+                        num_failed
+                    }
+                }
+            };
+            leader_sends.record(address, wire_transactions.len() - num_failed, num_failed);
         }
     }
 
@@ -315,25 +409,17 @@ impl SendTransactionService {
 mod test {
     use {
         super::*,
-        solana_gossip::contact_info::ContactInfo,
-        solana_ledger::{
-            blockstore::Blockstore, get_tmp_ledger_path, leader_schedule_cache::LeaderScheduleCache,
-        },
-        solana_runtime::genesis_utils::{
-            create_genesis_config_with_vote_accounts, GenesisConfigInfo, ValidatorVoteKeypairs,
-        },
+        crate::tpu_info::NullTpuInfo,
         solana_sdk::{
             account::AccountSharedData,
             fee_calculator::FeeCalculator,
             genesis_config::create_genesis_config,
             nonce,
-            poh_config::PohConfig,
             pubkey::Pubkey,
-            signature::{Keypair, Signer},
+            signature::Signer,
             system_program, system_transaction,
-            timing::timestamp,
         },
-        std::sync::{atomic::AtomicBool, mpsc::channel},
+        std::sync::mpsc::channel,
     };
 
     #[test]
@@ -343,8 +429,14 @@ mod test {
         let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
         let (sender, receiver) = channel();
 
-        let send_tranaction_service =
-            SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
+        let send_tranaction_service = SendTransactionService::new(
+            tpu_address,
+            &bank_forks,
+            None::<NullTpuInfo>,
+            receiver,
+            1000,
+            1,
+        );
 
         drop(sender);
         send_tranaction_service.join().unwrap();
@@ -359,7 +451,11 @@ mod test {
         let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
         let send_socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let tpu_address = "127.0.0.1:0".parse().unwrap();
-        let leader_forward_count = 1;
+        let config = Config {
+            leader_forward_count: 1,
+            ..Config::default()
+        };
+        let mut leader_sends = LeaderSendStats::default();
 
         let root_bank = Arc::new(Bank::new_from_parent(
             &bank_forks.read().unwrap().working_bank(),
@@ -390,7 +486,13 @@ mod test {
         info!("Expired transactions are dropped...");
         transactions.insert(
             Signature::default(),
-            TransactionInfo::new(Signature::default(), vec![], root_bank.slot() - 1, None),
+            TransactionInfo::new(
+                Signature::default(),
+                vec![],
+                root_bank.slot() - 1,
+                None,
+                None,
+            ),
         );
         let result = SendTransactionService::process_transactions(
             &working_bank,
@@ -398,8 +500,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert!(transactions.is_empty());
         assert_eq!(
@@ -413,7 +516,7 @@ mod test {
         info!("Rooted transactions are dropped...");
         transactions.insert(
             rooted_signature,
-            TransactionInfo::new(rooted_signature, vec![], working_bank.slot(), None),
+            TransactionInfo::new(rooted_signature, vec![], working_bank.slot(), None, None),
         );
         let result = SendTransactionService::process_transactions(
             &working_bank,
@@ -421,8 +524,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert!(transactions.is_empty());
         assert_eq!(
@@ -436,7 +540,7 @@ mod test {
         info!("Failed transactions are dropped...");
         transactions.insert(
             failed_signature,
-            TransactionInfo::new(failed_signature, vec![], working_bank.slot(), None),
+            TransactionInfo::new(failed_signature, vec![], working_bank.slot(), None, None),
         );
         let result = SendTransactionService::process_transactions(
             &working_bank,
@@ -444,8 +548,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert!(transactions.is_empty());
         assert_eq!(
@@ -459,7 +564,13 @@ mod test {
         info!("Non-rooted transactions are kept...");
         transactions.insert(
             non_rooted_signature,
-            TransactionInfo::new(non_rooted_signature, vec![], working_bank.slot(), None),
+            TransactionInfo::new(
+                non_rooted_signature,
+                vec![],
+                working_bank.slot(),
+                None,
+                None,
+            ),
         );
         let result = SendTransactionService::process_transactions(
             &working_bank,
@@ -467,8 +578,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert_eq!(transactions.len(), 1);
         assert_eq!(
@@ -483,7 +595,13 @@ mod test {
         info!("Unknown transactions are retried...");
         transactions.insert(
             Signature::default(),
-            TransactionInfo::new(Signature::default(), vec![], working_bank.slot(), None),
+            TransactionInfo::new(
+                Signature::default(),
+                vec![],
+                working_bank.slot(),
+                None,
+                None,
+            ),
         );
         let result = SendTransactionService::process_transactions(
             &working_bank,
@@ -491,8 +609,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert_eq!(transactions.len(), 1);
         assert_eq!(
@@ -513,7 +632,11 @@ mod test {
         let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
         let send_socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let tpu_address = "127.0.0.1:0".parse().unwrap();
-        let leader_forward_count = 1;
+        let config = Config {
+            leader_forward_count: 1,
+            ..Config::default()
+        };
+        let mut leader_sends = LeaderSendStats::default();
 
         let root_bank = Arc::new(Bank::new_from_parent(
             &bank_forks.read().unwrap().working_bank(),
@@ -562,6 +685,7 @@ mod test {
                 vec![],
                 last_valid_slot,
                 Some((nonce_address, durable_nonce)),
+                None,
             ),
         );
         let result = SendTransactionService::process_transactions(
@@ -570,8 +694,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert!(transactions.is_empty());
         assert_eq!(
@@ -589,6 +714,7 @@ mod test {
                 vec![],
                 last_valid_slot,
                 Some((nonce_address, Hash::new_unique())),
+                None,
             ),
         );
         let result = SendTransactionService::process_transactions(
@@ -597,8 +723,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert!(transactions.is_empty());
         assert_eq!(
@@ -618,6 +745,7 @@ mod test {
                 vec![],
                 last_valid_slot,
                 Some((nonce_address, Hash::new_unique())),
+                None,
             ),
         );
         let result = SendTransactionService::process_transactions(
@@ -626,8 +754,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert!(transactions.is_empty());
         assert_eq!(
@@ -645,6 +774,7 @@ mod test {
                 vec![],
                 root_bank.slot() - 1,
                 Some((nonce_address, durable_nonce)),
+                None,
             ),
         );
         let result = SendTransactionService::process_transactions(
@@ -653,8 +783,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert!(transactions.is_empty());
         assert_eq!(
@@ -673,6 +804,7 @@ mod test {
                 vec![],
                 last_valid_slot,
                 Some((nonce_address, Hash::new_unique())), // runtime should advance nonce on failed transactions
+                None,
             ),
         );
         let result = SendTransactionService::process_transactions(
@@ -681,8 +813,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert!(transactions.is_empty());
         assert_eq!(
@@ -701,6 +834,7 @@ mod test {
                 vec![],
                 last_valid_slot,
                 Some((nonce_address, Hash::new_unique())), // runtime advances nonce when transaction lands
+                None,
             ),
         );
         let result = SendTransactionService::process_transactions(
@@ -709,8 +843,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert_eq!(transactions.len(), 1);
         assert_eq!(
@@ -730,6 +865,7 @@ mod test {
                 vec![],
                 last_valid_slot,
                 Some((nonce_address, durable_nonce)),
+                None,
             ),
         );
         let result = SendTransactionService::process_transactions(
@@ -738,8 +874,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert_eq!(transactions.len(), 1);
         assert_eq!(
@@ -766,8 +903,9 @@ mod test {
             &send_socket,
             &tpu_address,
             &mut transactions,
-            &None,
-            leader_forward_count,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
         );
         assert_eq!(transactions.len(), 0);
         assert_eq!(
@@ -780,112 +918,132 @@ mod test {
     }
 
     #[test]
-    fn test_get_leader_tpus() {
-        let ledger_path = get_tmp_ledger_path!();
-        {
-            let blockstore = Blockstore::open(&ledger_path).unwrap();
-
-            let validator_vote_keypairs0 = ValidatorVoteKeypairs::new_rand();
-            let validator_vote_keypairs1 = ValidatorVoteKeypairs::new_rand();
-            let validator_vote_keypairs2 = ValidatorVoteKeypairs::new_rand();
-            let validator_keypairs = vec![
-                &validator_vote_keypairs0,
-                &validator_vote_keypairs1,
-                &validator_vote_keypairs2,
-            ];
-            let GenesisConfigInfo {
-                genesis_config,
-                mint_keypair: _,
-                voting_keypair: _,
-            } = create_genesis_config_with_vote_accounts(
-                1_000_000_000,
-                &validator_keypairs,
-                vec![10_000; 3],
+    fn test_max_retries() {
+        solana_logger::setup();
+
+        let (genesis_config, _mint_keypair) = create_genesis_config(4);
+        let bank = Arc::new(Bank::new(&genesis_config));
+        let send_socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let tpu_address = "127.0.0.1:0".parse().unwrap();
+        let config = Config {
+            default_max_retries: Some(2),
+            service_max_retries: 3,
+            ..Config::default()
+        };
+        let mut leader_sends = LeaderSendStats::default();
+
+        let mut transactions = HashMap::new();
+        let default_signature = Signature::new(&[1; 64]);
+        let capped_signature = Signature::new(&[2; 64]);
+        transactions.insert(
+            default_signature,
+            TransactionInfo::new(default_signature, vec![], bank.slot(), None, None),
+        );
+        transactions.insert(
+            capped_signature,
+            TransactionInfo::new(capped_signature, vec![], bank.slot(), None, Some(10)),
+        );
+
+        info!("Transactions are retried up to their limit...");
+        for _ in 0..2 {
+            let result = SendTransactionService::process_transactions(
+                &bank,
+                &bank,
+                &send_socket,
+                &tpu_address,
+                &mut transactions,
+                &None::<NullTpuInfo>,
+                &config,
+                &mut leader_sends,
             );
-            let bank = Arc::new(Bank::new(&genesis_config));
-
-            let (poh_recorder, _entry_receiver, _record_receiver) = PohRecorder::new(
-                0,
-                bank.last_blockhash(),
-                0,
-                Some((2, 2)),
-                bank.ticks_per_slot(),
-                &Pubkey::default(),
-                &Arc::new(blockstore),
-                &Arc::new(LeaderScheduleCache::new_from_bank(&bank)),
-                &Arc::new(PohConfig::default()),
-                Arc::new(AtomicBool::default()),
+            assert_eq!(
+                result,
+                ProcessTransactionsResult {
+                    retried: 2,
+                    ..ProcessTransactionsResult::default()
+                }
             );
+        }
+
+        info!("...which the service caps...");
+        let result = SendTransactionService::process_transactions(
+            &bank,
+            &bank,
+            &send_socket,
+            &tpu_address,
+            &mut transactions,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
+        );
+        assert_eq!(
+            result,
+            ProcessTransactionsResult {
+                retried: 1,
+                max_retries_elapsed: 1,
+                ..ProcessTransactionsResult::default()
+            }
+        );
+        assert!(transactions.contains_key(&capped_signature));
+        let result = SendTransactionService::process_transactions(
+            &bank,
+            &bank,
+            &send_socket,
+            &tpu_address,
+            &mut transactions,
+            &None::<NullTpuInfo>,
+            &config,
+            &mut leader_sends,
+        );
+        assert!(transactions.is_empty());
+        assert_eq!(
+            result,
+            ProcessTransactionsResult {
+                max_retries_elapsed: 1,
+                ..ProcessTransactionsResult::default()
+            }
+        );
+    }
 
-            let node_keypair = Arc::new(Keypair::new());
-            let cluster_info = Arc::new(ClusterInfo::new(
-                ContactInfo::new_localhost(&node_keypair.pubkey(), timestamp()),
-                node_keypair,
-            ));
-
-            let validator0_socket = SocketAddr::from(([127, 0, 0, 1], 1111));
-            let validator1_socket = SocketAddr::from(([127, 0, 0, 1], 2222));
-            let validator2_socket = SocketAddr::from(([127, 0, 0, 1], 3333));
-            let recent_peers: HashMap<_, _> = vec![
-                (
-                    validator_vote_keypairs0.node_keypair.pubkey(),
-                    validator0_socket,
-                ),
-                (
-                    validator_vote_keypairs1.node_keypair.pubkey(),
-                    validator1_socket,
-                ),
-                (
-                    validator_vote_keypairs2.node_keypair.pubkey(),
-                    validator2_socket,
-                ),
-            ]
+    #[test]
+    fn test_send_transactions_to_leaders() {
+        let send_socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let leader_sockets: Vec<_> = (0..2)
+            .map(|_| UdpSocket::bind("127.0.0.1:0").unwrap())
+            .collect();
+        for socket in &leader_sockets {
+            socket
+                .set_read_timeout(Some(Duration::from_secs(1)))
+                .unwrap();
+        }
+        let leader_addresses: Vec<_> = leader_sockets
             .iter()
-            .cloned()
+            .map(|socket| socket.local_addr().unwrap())
             .collect();
-            let leader_info = LeaderInfo {
-                cluster_info,
-                poh_recorder: Arc::new(Mutex::new(poh_recorder)),
-                recent_peers: recent_peers.clone(),
-            };
+        let addresses: Vec<_> = leader_addresses.iter().collect();
+        let wire_transactions: Vec<_> = (1..=3u8).map(|i| vec![i; 10]).collect();
+        let wire_transactions: Vec<_> = wire_transactions.iter().map(|tx| &tx[..]).collect();
+        let mut leader_sends = LeaderSendStats::default();
 
-            let slot = bank.slot();
-            let first_leader =
-                solana_ledger::leader_schedule_utils::slot_leader_at(slot, &bank).unwrap();
-            assert_eq!(
-                leader_info.get_leader_tpus(1),
-                vec![recent_peers.get(&first_leader).unwrap()]
-            );
+        SendTransactionService::send_transactions(
+            &send_socket,
+            &addresses,
+            &wire_transactions,
+            &mut leader_sends,
+        );
 
-            let second_leader = solana_ledger::leader_schedule_utils::slot_leader_at(
-                slot + NUM_CONSECUTIVE_LEADER_SLOTS,
-                &bank,
-            )
-            .unwrap();
-            let mut expected_leader_sockets = vec![
-                recent_peers.get(&first_leader).unwrap(),
-                recent_peers.get(&second_leader).unwrap(),
-            ];
-            expected_leader_sockets.dedup();
-            assert_eq!(leader_info.get_leader_tpus(2), expected_leader_sockets);
-
-            let third_leader = solana_ledger::leader_schedule_utils::slot_leader_at(
-                slot + (2 * NUM_CONSECUTIVE_LEADER_SLOTS),
-                &bank,
-            )
-            .unwrap();
-            let mut expected_leader_sockets = vec![
-                recent_peers.get(&first_leader).unwrap(),
-                recent_peers.get(&second_leader).unwrap(),
-                recent_peers.get(&third_leader).unwrap(),
-            ];
-            expected_leader_sockets.dedup();
-            assert_eq!(leader_info.get_leader_tpus(3), expected_leader_sockets);
-
-            for x in 4..8 {
-                assert!(leader_info.get_leader_tpus(x).len() <= recent_peers.len());
+        let mut buf = [0u8; 10];
+        for (socket, address) in leader_sockets.iter().zip(leader_addresses.iter()) {
+            for wire_transaction in &wire_transactions {
+                let (size, _) = socket.recv_from(&mut buf).unwrap();
+                assert_eq!(&buf[..size], *wire_transaction);
             }
+            assert_eq!(
+                leader_sends.counts[address],
+                SendCounts { sent: 3, failed: 0 }
+            );
         }
-        Blockstore::destroy(&ledger_path).unwrap();
+        leader_sends.report();
+        assert!(leader_sends.counts.is_empty());
     }
 }
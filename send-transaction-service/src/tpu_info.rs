@@ -0,0 +1,19 @@
+use std::net::SocketAddr;
+
+/// Source of the TPU addresses of the current and upcoming leaders
+pub trait TpuInfo {
+    fn refresh_recent_peers(&mut self);
+    fn get_leader_tpus(&self, max_count: u64) -> Vec<&SocketAddr>;
+}
+
+/// A `TpuInfo` that knows no leaders, so that transactions are only sent to the fixed TPU
+/// address the service was created with
+#[derive(Clone)]
+pub struct NullTpuInfo;
+
+impl TpuInfo for NullTpuInfo {
+    fn refresh_recent_peers(&mut self) {}
+    fn get_leader_tpus(&self, _max_count: u64) -> Vec<&SocketAddr> {
+        vec![]
+    }
+}
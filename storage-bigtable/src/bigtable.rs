@@ -567,7 +567,7 @@ impl<F: FnMut(Request<()>) -> InterceptedRequestResult> BigTable<F> {
         let mut bytes_written = 0;
         let mut new_row_data = vec![];
         for (row_key, data) in cells {
-            let data = compress_best(&bincode::serialize(&data).unwrap())?;
+            let data = serialize_bincode_cell_data(data)?;
             bytes_written += data.len();
             new_row_data.push((row_key, vec![("bin".to_string(), data)]));
         }
@@ -587,9 +587,7 @@ impl<F: FnMut(Request<()>) -> InterceptedRequestResult> BigTable<F> {
         let mut bytes_written = 0;
         let mut new_row_data = vec![];
         for (row_key, data) in cells {
-            let mut buf = Vec::with_capacity(data.encoded_len());
-            data.encode(&mut buf).unwrap();
-            let data = compress_best(&buf)?;
+            let data = serialize_protobuf_cell_data(data)?;
             bytes_written += data.len();
             new_row_data.push((row_key, vec![("proto".to_string(), data)]));
         }
@@ -599,6 +597,22 @@ impl<F: FnMut(Request<()>) -> InterceptedRequestResult> BigTable<F> {
     }
 }
 
+pub(crate) fn serialize_bincode_cell_data<T>(data: &T) -> Result<CellValue>
+where
+    T: serde::ser::Serialize,
+{
+    Ok(compress_best(&bincode::serialize(data).unwrap())?)
+}
+
+pub(crate) fn serialize_protobuf_cell_data<T>(data: &T) -> Result<CellValue>
+where
+    T: prost::Message,
+{
+    let mut buf = Vec::with_capacity(data.encoded_len());
+    data.encode(&mut buf).unwrap();
+    Ok(compress_best(&buf)?)
+}
+
 pub(crate) fn deserialize_protobuf_or_bincode_cell_data<B, P>(
     row_data: RowDataSlice,
     table: &str,
@@ -1,5 +1,7 @@
 #![allow(clippy::integer_arithmetic)]
 use {
+    crate::bigtable::{RowData, RowKey},
+    async_trait::async_trait,
     log::*,
     serde::{Deserialize, Serialize},
     solana_sdk::{
@@ -12,9 +14,10 @@ use {
     },
     solana_storage_proto::convert::{generated, tx_by_addr},
     solana_transaction_status::{
-        ConfirmedBlock, ConfirmedTransaction, ConfirmedTransactionStatusWithSignature, Reward,
+        invoked_program_ids, AddressSignatureFilter, AddressSignatures, ConfirmedBlock,
+        ConfirmedTransaction, ConfirmedTransactionStatusWithSignature, Reward,
         TransactionByAddrInfo, TransactionConfirmationStatus, TransactionStatus,
-        TransactionStatusMeta, TransactionWithStatusMeta,
+        TransactionStatusMeta, TransactionWithStatusMeta, MAX_ADDRESS_SIGNATURES_SCANNED,
     },
     std::{collections::HashMap, convert::TryInto},
     thiserror::Error,
@@ -26,13 +29,19 @@ extern crate serde_derive;
 mod access_token;
 mod bigtable;
 mod compression;
+mod local_storage;
 mod root_ca_certificate;
 
+pub use local_storage::LocalLedgerStorage;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("BigTable: {0}")]
     BigTableError(bigtable::Error),
 
+    #[error("Local storage: {0}")]
+    LocalStorageError(rocksdb::Error),
+
     #[error("I/O Error: {0}")]
     IoError(std::io::Error),
 
@@ -52,6 +61,12 @@ impl std::convert::From<bigtable::Error> for Error {
     }
 }
 
+impl std::convert::From<rocksdb::Error> for Error {
+    fn from(err: rocksdb::Error) -> Self {
+        Self::LocalStorageError(err)
+    }
+}
+
 impl std::convert::From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
         Self::IoError(err)
@@ -301,10 +316,336 @@ impl From<LegacyTransactionByAddrInfo> for TransactionByAddrInfo {
             index,
             memo,
             block_time: None,
+            program_ids: vec![],
+        }
+    }
+}
+
+/// Long-term storage of confirmed blocks, and the transaction and address indexes over them, for
+/// ledger history that is no longer available from the local blockstore
+#[async_trait]
+pub trait LedgerStorageAdapter: Send + Sync {
+    /// Return the available slot that contains a block
+    async fn get_first_available_block(&self) -> Result<Option<Slot>>;
+
+    /// Fetch the next slots after the provided slot that contains a block
+    ///
+    /// start_slot: slot to start the search from (inclusive)
+    /// limit: stop after this many slots have been found; if limit==0, all records in the table
+    /// after start_slot will be read
+    async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>>;
+
+    /// Fetch the confirmed block from the desired slot
+    async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock>;
+
+    async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus>;
+
+    /// Fetch a confirmed transaction
+    async fn get_confirmed_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<ConfirmedTransaction>>;
+
+    /// Get confirmed signatures for the provided address, in descending ledger order
+    ///
+    /// address: address to search for
+    /// before_signature: start with the first signature older than this one
+    /// until_signature: end with the last signature more recent than this one
+    /// filter: only return signatures of transactions that pass this filter; a filtered query
+    /// examines at most `MAX_ADDRESS_SIGNATURES_SCANNED` transactions, and stops at the first one
+    /// older than the filter's `min_block_time`
+    /// limit: stop after this many signatures; if limit==0, all records in the table will be read
+    async fn get_confirmed_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before_signature: Option<&Signature>,
+        until_signature: Option<&Signature>,
+        filter: &AddressSignatureFilter,
+        limit: usize,
+    ) -> Result<
+        AddressSignatures<(
+            ConfirmedTransactionStatusWithSignature,
+            u32, /*slot index*/
+        )>,
+    >;
+
+    // Upload a new confirmed block and associated meta data.
+    async fn upload_confirmed_block(
+        &self,
+        slot: Slot,
+        confirmed_block: ConfirmedBlock,
+    ) -> Result<()>;
+}
+
+fn confirmed_block_from_cell_data(
+    slot: Slot,
+    block_cell_data: bigtable::CellData<StoredConfirmedBlock, generated::ConfirmedBlock>,
+) -> Result<ConfirmedBlock> {
+    Ok(match block_cell_data {
+        bigtable::CellData::Bincode(block) => block.into(),
+        bigtable::CellData::Protobuf(block) => block.try_into().map_err(|_err| {
+            bigtable::Error::ObjectCorrupt(format!("blocks/{}", slot_to_key(slot)))
+        })?,
+    })
+}
+
+// Extract the transaction at `index` from its containing block, checking that it is really the
+// one identified by `signature`
+fn confirmed_transaction_from_block(
+    signature: &Signature,
+    slot: Slot,
+    index: u32,
+    block: ConfirmedBlock,
+) -> Option<ConfirmedTransaction> {
+    match block.transactions.into_iter().nth(index as usize) {
+        None => {
+            warn!("Transaction info for {} is corrupt", signature);
+            None
+        }
+        Some(bucket_block_transaction) => {
+            if bucket_block_transaction.transaction.signatures[0] != *signature {
+                warn!(
+                    "Transaction info or confirmed block for {} is corrupt",
+                    signature
+                );
+                None
+            } else {
+                Some(ConfirmedTransaction {
+                    slot,
+                    transaction: bucket_block_transaction,
+                    block_time: block.block_time,
+                })
+            }
         }
     }
 }
 
+fn tx_by_addr_cell_len(
+    cell_data: bigtable::CellData<Vec<LegacyTransactionByAddrInfo>, tx_by_addr::TransactionByAddr>,
+) -> usize {
+    match cell_data {
+        bigtable::CellData::Bincode(tx_by_addr) => tx_by_addr.len(),
+        bigtable::CellData::Protobuf(tx_by_addr) => tx_by_addr.tx_by_addrs.len(),
+    }
+}
+
+// Bounds of a `get_confirmed_signatures_for_address()` query, resolved from its before/until
+// signatures, and the position of the next page of `tx-by-addr` rows to list
+struct SignaturesForAddressRange {
+    address_prefix: String,
+    first_slot: Slot,
+    before_transaction_index: u32,
+    last_slot: Slot,
+    until_transaction_index: u32,
+    next_slot: Slot,
+    // Transactions examined by a filtered query
+    num_scanned: usize,
+    resume_before: Option<Signature>,
+}
+
+impl SignaturesForAddressRange {
+    fn new(
+        address: &Pubkey,
+        before: Option<TransactionInfo>,
+        until: Option<TransactionInfo>,
+    ) -> Self {
+        let (first_slot, before_transaction_index) = before
+            .map(|TransactionInfo { slot, index, .. }| (slot, index))
+            .unwrap_or((Slot::MAX, 0));
+        let (last_slot, until_transaction_index) = until
+            .map(|TransactionInfo { slot, index, .. }| (slot, index))
+            .unwrap_or((0, u32::MAX));
+        Self {
+            address_prefix: format!("{}/", address),
+            first_slot,
+            before_transaction_index,
+            last_slot,
+            until_transaction_index,
+            next_slot: first_slot,
+            num_scanned: 0,
+            resume_before: None,
+        }
+    }
+
+    // Row keys are ordered by the one's complement of the slot, so listing goes from the newest
+    // slot to the oldest one
+    fn start_key(&self) -> RowKey {
+        format!("{}{}", self.address_prefix, slot_to_key(!self.next_slot))
+    }
+
+    fn end_key(&self) -> RowKey {
+        format!("{}{}", self.address_prefix, slot_to_key(!self.last_slot))
+    }
+
+    // Append the infos that pass `filter` from a page of at most `rows_limit` rows listed from
+    // `start_key()`, and return whether the next page should be listed.
+    //
+    // Unfiltered queries are always satisfied by their first page, as it is sized for `limit`.
+    // Filtered queries keep paging until `limit` is reached, the rows run out, a transaction
+    // older than the filter's min_block_time is reached or MAX_ADDRESS_SIGNATURES_SCANNED
+    // transactions were examined, in which case `resume_before` is set
+    fn collect_page(
+        &mut self,
+        tx_by_addr_data: Vec<(RowKey, RowData)>,
+        rows_limit: usize,
+        filter: &AddressSignatureFilter,
+        limit: usize,
+        infos: &mut Vec<(ConfirmedTransactionStatusWithSignature, u32)>,
+    ) -> Result<bool> {
+        let num_rows = tx_by_addr_data.len();
+        for (row_key, data) in tx_by_addr_data {
+            let slot = !key_to_slot(&row_key[self.address_prefix.len()..]).ok_or_else(|| {
+                bigtable::Error::ObjectCorrupt(format!(
+                    "Failed to convert key to slot: tx-by-addr/{}",
+                    row_key
+                ))
+            })?;
+
+            let deserialized_cell_data = bigtable::deserialize_protobuf_or_bincode_cell_data::<
+                Vec<LegacyTransactionByAddrInfo>,
+                tx_by_addr::TransactionByAddr,
+            >(&data, "tx-by-addr", row_key.clone())?;
+
+            let mut cell_data: Vec<TransactionByAddrInfo> = match deserialized_cell_data {
+                bigtable::CellData::Bincode(tx_by_addr) => {
+                    tx_by_addr.into_iter().map(|legacy| legacy.into()).collect()
+                }
+                bigtable::CellData::Protobuf(tx_by_addr) => {
+                    tx_by_addr.try_into().map_err(|error| {
+                        bigtable::Error::ObjectCorrupt(format!(
+                            "Failed to deserialize: {}: tx-by-addr/{}",
+                            error,
+                            row_key.clone()
+                        ))
+                    })?
+                }
+            };
+
+            cell_data.reverse();
+            for tx_by_addr_info in cell_data.into_iter() {
+                // Filter out records before `before_transaction_index`
+                if slot == self.first_slot && tx_by_addr_info.index >= self.before_transaction_index
+                {
+                    continue;
+                }
+                // Filter out records after `until_transaction_index`
+                if slot == self.last_slot && tx_by_addr_info.index <= self.until_transaction_index {
+                    continue;
+                }
+                if filter.precedes_min_block_time(tx_by_addr_info.block_time) {
+                    return Ok(false);
+                }
+                let signature = tx_by_addr_info.signature;
+                // Records uploaded before program ids were, never match a program id filter
+                if filter.matches_status(tx_by_addr_info.err.is_some())
+                    && filter.matches_block_time(tx_by_addr_info.block_time)
+                    && filter.matches_program_ids(&tx_by_addr_info.program_ids)
+                {
+                    infos.push((
+                        ConfirmedTransactionStatusWithSignature {
+                            signature,
+                            slot,
+                            err: tx_by_addr_info.err,
+                            memo: tx_by_addr_info.memo,
+                            block_time: tx_by_addr_info.block_time,
+                        },
+                        tx_by_addr_info.index,
+                    ));
+                    // Respect limit
+                    if infos.len() >= limit {
+                        return Ok(false);
+                    }
+                }
+                if !filter.is_empty() {
+                    self.num_scanned += 1;
+                    if self.num_scanned >= MAX_ADDRESS_SIGNATURES_SCANNED {
+                        self.resume_before = Some(signature);
+                        return Ok(false);
+                    }
+                }
+            }
+
+            if slot == 0 {
+                return Ok(false);
+            }
+            self.next_slot = slot - 1;
+        }
+        Ok(!filter.is_empty() && num_rows >= rows_limit)
+    }
+}
+
+// The `tx` and `tx-by-addr` cells that index the transactions of a confirmed block
+type ConfirmedBlockIndexCells = (
+    Vec<(RowKey, TransactionInfo)>,
+    Vec<(RowKey, tx_by_addr::TransactionByAddr)>,
+);
+
+fn confirmed_block_index_cells(
+    slot: Slot,
+    confirmed_block: &ConfirmedBlock,
+) -> ConfirmedBlockIndexCells {
+    let mut by_addr: HashMap<&Pubkey, Vec<TransactionByAddrInfo>> = HashMap::new();
+
+    let mut tx_cells = vec![];
+    for (index, transaction_with_meta) in confirmed_block.transactions.iter().enumerate() {
+        let TransactionWithStatusMeta { meta, transaction } = transaction_with_meta;
+        let err = meta.as_ref().and_then(|meta| meta.status.clone().err());
+        let index = index as u32;
+        let signature = transaction.signatures[0];
+        let inner_instructions = meta
+            .as_ref()
+            .and_then(|meta| meta.inner_instructions.as_deref());
+        let program_ids: Vec<Pubkey> =
+            invoked_program_ids(&transaction.message, inner_instructions)
+                .into_iter()
+                .cloned()
+                .collect();
+
+        for address in &transaction.message.account_keys {
+            if !is_sysvar_id(address) {
+                by_addr
+                    .entry(address)
+                    .or_default()
+                    .push(TransactionByAddrInfo {
+                        signature,
+                        err: err.clone(),
+                        index,
+                        memo: None, // TODO
+                        block_time: confirmed_block.block_time,
+                        program_ids: program_ids.clone(),
+                    });
+            }
+        }
+
+        tx_cells.push((
+            signature.to_string(),
+            TransactionInfo {
+                slot,
+                index,
+                err,
+                memo: None, // TODO
+            },
+        ));
+    }
+
+    let tx_by_addr_cells: Vec<_> = by_addr
+        .into_iter()
+        .map(|(address, transaction_info_by_addr)| {
+            (
+                format!("{}/{}", address, slot_to_key(!slot)),
+                tx_by_addr::TransactionByAddr {
+                    tx_by_addrs: transaction_info_by_addr
+                        .into_iter()
+                        .map(|by_addr| by_addr.into())
+                        .collect(),
+                },
+            )
+        })
+        .collect();
+
+    (tx_cells, tx_by_addr_cells)
+}
+
 #[derive(Clone)]
 pub struct LedgerStorage {
     connection: bigtable::BigTableConnection,
@@ -316,9 +657,11 @@ impl LedgerStorage {
             bigtable::BigTableConnection::new("solana-ledger", read_only, timeout).await?;
         Ok(Self { connection })
     }
+}
 
-    /// Return the available slot that contains a block
-    pub async fn get_first_available_block(&self) -> Result<Option<Slot>> {
+#[async_trait]
+impl LedgerStorageAdapter for LedgerStorage {
+    async fn get_first_available_block(&self) -> Result<Option<Slot>> {
         let mut bigtable = self.connection.client();
         let blocks = bigtable.get_row_keys("blocks", None, None, 1).await?;
         if blocks.is_empty() {
@@ -327,12 +670,7 @@ impl LedgerStorage {
         Ok(key_to_slot(&blocks[0]))
     }
 
-    /// Fetch the next slots after the provided slot that contains a block
-    ///
-    /// start_slot: slot to start the search from (inclusive)
-    /// limit: stop after this many slots have been found; if limit==0, all records in the table
-    /// after start_slot will be read
-    pub async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+    async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
         let mut bigtable = self.connection.client();
         let blocks = bigtable
             .get_row_keys("blocks", Some(slot_to_key(start_slot)), None, limit as i64)
@@ -340,8 +678,7 @@ impl LedgerStorage {
         Ok(blocks.into_iter().filter_map(|s| key_to_slot(&s)).collect())
     }
 
-    /// Fetch the confirmed block from the desired slot
-    pub async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
+    async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
         let mut bigtable = self.connection.client();
         let block_cell_data = bigtable
             .get_protobuf_or_bincode_cell::<StoredConfirmedBlock, generated::ConfirmedBlock>(
@@ -353,15 +690,10 @@ impl LedgerStorage {
                 bigtable::Error::RowNotFound => Error::BlockNotFound(slot),
                 _ => err.into(),
             })?;
-        Ok(match block_cell_data {
-            bigtable::CellData::Bincode(block) => block.into(),
-            bigtable::CellData::Protobuf(block) => block.try_into().map_err(|_err| {
-                bigtable::Error::ObjectCorrupt(format!("blocks/{}", slot_to_key(slot)))
-            })?,
-        })
+        confirmed_block_from_cell_data(slot, block_cell_data)
     }
 
-    pub async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
+    async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
         let mut bigtable = self.connection.client();
         let transaction_info = bigtable
             .get_bincode_cell::<TransactionInfo>("tx", signature.to_string())
@@ -373,8 +705,7 @@ impl LedgerStorage {
         Ok(transaction_info.into())
     }
 
-    /// Fetch a confirmed transaction
-    pub async fn get_confirmed_transaction(
+    async fn get_confirmed_transaction(
         &self,
         signature: &Signature,
     ) -> Result<Option<ConfirmedTransaction>> {
@@ -391,215 +722,88 @@ impl LedgerStorage {
 
         // Load the block and return the transaction
         let block = self.get_confirmed_block(slot).await?;
-        match block.transactions.into_iter().nth(index as usize) {
-            None => {
-                warn!("Transaction info for {} is corrupt", signature);
-                Ok(None)
-            }
-            Some(bucket_block_transaction) => {
-                if bucket_block_transaction.transaction.signatures[0] != *signature {
-                    warn!(
-                        "Transaction info or confirmed block for {} is corrupt",
-                        signature
-                    );
-                    Ok(None)
-                } else {
-                    Ok(Some(ConfirmedTransaction {
-                        slot,
-                        transaction: bucket_block_transaction,
-                        block_time: block.block_time,
-                    }))
-                }
-            }
-        }
+        Ok(confirmed_transaction_from_block(
+            signature, slot, index, block,
+        ))
     }
 
-    /// Get confirmed signatures for the provided address, in descending ledger order
-    ///
-    /// address: address to search for
-    /// before_signature: start with the first signature older than this one
-    /// until_signature: end with the last signature more recent than this one
-    /// limit: stop after this many signatures; if limit==0, all records in the table will be read
-    pub async fn get_confirmed_signatures_for_address(
+    async fn get_confirmed_signatures_for_address(
         &self,
         address: &Pubkey,
         before_signature: Option<&Signature>,
         until_signature: Option<&Signature>,
+        filter: &AddressSignatureFilter,
         limit: usize,
     ) -> Result<
-        Vec<(
+        AddressSignatures<(
             ConfirmedTransactionStatusWithSignature,
             u32, /*slot index*/
         )>,
     > {
         let mut bigtable = self.connection.client();
-        let address_prefix = format!("{}/", address);
 
         // Figure out where to start listing from based on `before_signature`
-        let (first_slot, before_transaction_index) = match before_signature {
-            None => (Slot::MAX, 0),
-            Some(before_signature) => {
-                let TransactionInfo { slot, index, .. } = bigtable
+        let before = match before_signature {
+            None => None,
+            Some(before_signature) => Some(
+                bigtable
                     .get_bincode_cell("tx", before_signature.to_string())
-                    .await?;
-
-                (slot, index)
-            }
+                    .await?,
+            ),
         };
 
         // Figure out where to end listing from based on `until_signature`
-        let (last_slot, until_transaction_index) = match until_signature {
-            None => (0, u32::MAX),
-            Some(until_signature) => {
-                let TransactionInfo { slot, index, .. } = bigtable
+        let until = match until_signature {
+            None => None,
+            Some(until_signature) => Some(
+                bigtable
                     .get_bincode_cell("tx", until_signature.to_string())
-                    .await?;
-
-                (slot, index)
-            }
+                    .await?,
+            ),
         };
 
-        let mut infos = vec![];
+        let mut range = SignaturesForAddressRange::new(address, before, until);
 
         let starting_slot_tx_len = bigtable
             .get_protobuf_or_bincode_cell::<Vec<LegacyTransactionByAddrInfo>, tx_by_addr::TransactionByAddr>(
                 "tx-by-addr",
-                format!("{}{}", address_prefix, slot_to_key(!first_slot)),
+                range.start_key(),
             )
             .await
-            .map(|cell_data| {
-                match cell_data {
-                    bigtable::CellData::Bincode(tx_by_addr) => tx_by_addr.len(),
-                    bigtable::CellData::Protobuf(tx_by_addr) => tx_by_addr.tx_by_addrs.len(),
-                }
-            })
+            .map(tx_by_addr_cell_len)
             .unwrap_or(0);
 
-        // Return the next tx-by-addr data of amount `limit` plus extra to account for the largest
-        // number that might be flitered out
-        let tx_by_addr_data = bigtable
-            .get_row_data(
-                "tx-by-addr",
-                Some(format!("{}{}", address_prefix, slot_to_key(!first_slot))),
-                Some(format!("{}{}", address_prefix, slot_to_key(!last_slot))),
-                limit as i64 + starting_slot_tx_len as i64,
-            )
-            .await?;
-
-        'outer: for (row_key, data) in tx_by_addr_data {
-            let slot = !key_to_slot(&row_key[address_prefix.len()..]).ok_or_else(|| {
-                bigtable::Error::ObjectCorrupt(format!(
-                    "Failed to convert key to slot: tx-by-addr/{}",
-                    row_key
-                ))
-            })?;
-
-            let deserialized_cell_data = bigtable::deserialize_protobuf_or_bincode_cell_data::<
-                Vec<LegacyTransactionByAddrInfo>,
-                tx_by_addr::TransactionByAddr,
-            >(&data, "tx-by-addr", row_key.clone())?;
-
-            let mut cell_data: Vec<TransactionByAddrInfo> = match deserialized_cell_data {
-                bigtable::CellData::Bincode(tx_by_addr) => {
-                    tx_by_addr.into_iter().map(|legacy| legacy.into()).collect()
-                }
-                bigtable::CellData::Protobuf(tx_by_addr) => {
-                    tx_by_addr.try_into().map_err(|error| {
-                        bigtable::Error::ObjectCorrupt(format!(
-                            "Failed to deserialize: {}: tx-by-addr/{}",
-                            error,
-                            row_key.clone()
-                        ))
-                    })?
-                }
-            };
-
-            cell_data.reverse();
-            for tx_by_addr_info in cell_data.into_iter() {
-                // Filter out records before `before_transaction_index`
-                if slot == first_slot && tx_by_addr_info.index >= before_transaction_index {
-                    continue;
-                }
-                // Filter out records after `until_transaction_index`
-                if slot == last_slot && tx_by_addr_info.index <= until_transaction_index {
-                    continue;
-                }
-                infos.push((
-                    ConfirmedTransactionStatusWithSignature {
-                        signature: tx_by_addr_info.signature,
-                        slot,
-                        err: tx_by_addr_info.err,
-                        memo: tx_by_addr_info.memo,
-                        block_time: tx_by_addr_info.block_time,
-                    },
-                    tx_by_addr_info.index,
-                ));
-                // Respect limit
-                if infos.len() >= limit {
-                    break 'outer;
-                }
+        // Read tx-by-addr data in pages of `limit` plus extra to account for the largest number
+        // that might be flitered out
+        let rows_limit = limit + starting_slot_tx_len;
+        let mut infos = vec![];
+        loop {
+            let tx_by_addr_data = bigtable
+                .get_row_data(
+                    "tx-by-addr",
+                    Some(range.start_key()),
+                    Some(range.end_key()),
+                    rows_limit as i64,
+                )
+                .await?;
+            if !range.collect_page(tx_by_addr_data, rows_limit, filter, limit, &mut infos)? {
+                break;
             }
         }
-        Ok(infos)
+        Ok(AddressSignatures {
+            signatures: infos,
+            resume_before: range.resume_before,
+        })
     }
 
-    // Upload a new confirmed block and associated meta data.
-    pub async fn upload_confirmed_block(
+    async fn upload_confirmed_block(
         &self,
         slot: Slot,
         confirmed_block: ConfirmedBlock,
     ) -> Result<()> {
         let mut bytes_written = 0;
 
-        let mut by_addr: HashMap<&Pubkey, Vec<TransactionByAddrInfo>> = HashMap::new();
-
-        let mut tx_cells = vec![];
-        for (index, transaction_with_meta) in confirmed_block.transactions.iter().enumerate() {
-            let TransactionWithStatusMeta { meta, transaction } = transaction_with_meta;
-            let err = meta.as_ref().and_then(|meta| meta.status.clone().err());
-            let index = index as u32;
-            let signature = transaction.signatures[0];
-
-            for address in &transaction.message.account_keys {
-                if !is_sysvar_id(address) {
-                    by_addr
-                        .entry(address)
-                        .or_default()
-                        .push(TransactionByAddrInfo {
-                            signature,
-                            err: err.clone(),
-                            index,
-                            memo: None, // TODO
-                            block_time: confirmed_block.block_time,
-                        });
-                }
-            }
-
-            tx_cells.push((
-                signature.to_string(),
-                TransactionInfo {
-                    slot,
-                    index,
-                    err,
-                    memo: None, // TODO
-                },
-            ));
-        }
-
-        let tx_by_addr_cells: Vec<_> = by_addr
-            .into_iter()
-            .map(|(address, transaction_info_by_addr)| {
-                (
-                    format!("{}/{}", address, slot_to_key(!slot)),
-                    tx_by_addr::TransactionByAddr {
-                        tx_by_addrs: transaction_info_by_addr
-                            .into_iter()
-                            .map(|by_addr| by_addr.into())
-                            .collect(),
-                    },
-                )
-            })
-            .collect();
+        let (tx_cells, tx_by_addr_cells) = confirmed_block_index_cells(slot, &confirmed_block);
 
         if !tx_cells.is_empty() {
             bytes_written += self
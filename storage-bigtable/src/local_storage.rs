@@ -0,0 +1,618 @@
+// A `LedgerStorageAdapter` over an embedded RocksDB instance, for operators who would rather
+// self-host ledger history than depend on BigTable.
+//
+// The `blocks`, `tx` and `tx-by-addr` tables are column families keyed by the same row keys as
+// their BigTable counterparts, and each row holds the same (compressed) cells, so data may be
+// moved between the two backends without being re-encoded.
+
+use {
+    crate::{
+        bigtable::{self, CellData, RowData, RowKey},
+        confirmed_block_from_cell_data, confirmed_block_index_cells,
+        confirmed_transaction_from_block, key_to_slot, slot_to_key, tx_by_addr_cell_len, Error,
+        LedgerStorageAdapter, LegacyTransactionByAddrInfo, Result, SignaturesForAddressRange,
+        StoredConfirmedBlock, TransactionInfo,
+    },
+    async_trait::async_trait,
+    log::*,
+    rocksdb::{Direction, IteratorMode, Options, WriteBatch, DB},
+    solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature},
+    solana_storage_proto::convert::{generated, tx_by_addr},
+    solana_transaction_status::{
+        AddressSignatureFilter, AddressSignatures, ConfirmedBlock, ConfirmedTransaction,
+        ConfirmedTransactionStatusWithSignature, TransactionStatus,
+    },
+    std::{
+        path::Path,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+};
+
+const TABLES: &[&str] = &["blocks", "tx", "tx-by-addr"];
+
+// How often a read-only instance picks up the writes of the instance storing new blocks
+const CATCH_UP_WITH_PRIMARY_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone)]
+pub struct LocalLedgerStorage {
+    db: Arc<DB>,
+    // Time of the last catch-up of a read-only instance, which is opened as a RocksDB secondary
+    // instance and only sees the writes of the primary instance as of then
+    last_catch_up: Option<Arc<Mutex<Instant>>>,
+}
+
+impl LocalLedgerStorage {
+    /// Open the storage in directory `path`, which is created if missing unless `read_only` is
+    /// set.  A read-only instance may be opened while another process is writing to the same
+    /// directory, and keeps up with the blocks it stores.
+    pub fn open(path: &Path, read_only: bool) -> Result<Self> {
+        let mut options = Options::default();
+        let (db, last_catch_up) = if read_only {
+            // This is needed according to https://github.com/facebook/rocksdb/wiki/Secondary-instance
+            options.set_max_open_files(-1);
+            let secondary_path = path.join("solana-secondary");
+            let db = DB::open_cf_as_secondary(&options, path, &secondary_path, TABLES)?;
+            (db, Some(Arc::new(Mutex::new(Instant::now()))))
+        } else {
+            options.create_if_missing(true);
+            options.create_missing_column_families(true);
+            (DB::open_cf(&options, path, TABLES)?, None)
+        };
+        info!("Opened local ledger storage at {:?}", path);
+        Ok(Self {
+            db: Arc::new(db),
+            last_catch_up,
+        })
+    }
+
+    fn catch_up_with_primary(&self) {
+        if let Some(last_catch_up) = &self.last_catch_up {
+            let mut last_catch_up = last_catch_up.lock().unwrap();
+            if last_catch_up.elapsed() >= CATCH_UP_WITH_PRIMARY_INTERVAL {
+                if let Err(err) = self.db.try_catch_up_with_primary() {
+                    warn!("Failed to catch up with the primary local storage: {}", err);
+                }
+                *last_catch_up = Instant::now();
+            }
+        }
+    }
+
+    // RocksDB I/O blocks, so the adapter methods run it on the blocking thread pool rather than
+    // on the async runtime
+    async fn run_blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self) -> Result<T> + Send + 'static,
+    {
+        let storage = self.clone();
+        tokio::task::spawn_blocking(move || {
+            storage.catch_up_with_primary();
+            f(&storage)
+        })
+        .await
+        .map_err(|err| Error::IoError(std::io::Error::new(std::io::ErrorKind::Other, err)))?
+    }
+
+    // Same semantics as `BigTable::get_row_data()`, including a `rows_limit` of zero listing
+    // rows until `end_at` or the end of the table
+    fn get_row_data(
+        &self,
+        table: &str,
+        start_at: Option<RowKey>,
+        end_at: Option<RowKey>,
+        rows_limit: usize,
+    ) -> Result<Vec<(RowKey, RowData)>> {
+        let cf = self.db.cf_handle(table).unwrap();
+        let iterator_mode = match &start_at {
+            Some(start_at) => IteratorMode::From(start_at.as_bytes(), Direction::Forward),
+            None => IteratorMode::Start,
+        };
+
+        let mut rows = vec![];
+        for (key, value) in self.db.iterator_cf(cf, iterator_mode) {
+            if rows_limit > 0 && rows.len() >= rows_limit {
+                break;
+            }
+            if let Some(end_at) = &end_at {
+                if *key > *end_at.as_bytes() {
+                    break;
+                }
+            }
+            let row_key = String::from_utf8(key.into_vec()).map_err(|err| {
+                bigtable::Error::ObjectCorrupt(format!("{}: invalid row key: {}", table, err))
+            })?;
+            let row_data = deserialize_row_data(table, &row_key, &value)?;
+            rows.push((row_key, row_data));
+        }
+        Ok(rows)
+    }
+
+    fn get_row_keys(
+        &self,
+        table: &str,
+        start_at: Option<RowKey>,
+        rows_limit: usize,
+    ) -> Result<Vec<RowKey>> {
+        Ok(self
+            .get_row_data(table, start_at, None, rows_limit)?
+            .into_iter()
+            .map(|(row_key, _)| row_key)
+            .collect())
+    }
+
+    fn get_single_row_data(&self, table: &str, row_key: &str) -> Result<RowData> {
+        let cf = self.db.cf_handle(table).unwrap();
+        let value = self
+            .db
+            .get_cf(cf, row_key)?
+            .ok_or(bigtable::Error::RowNotFound)?;
+        deserialize_row_data(table, row_key, &value)
+    }
+
+    fn get_bincode_cell<T>(&self, table: &str, key: RowKey) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let row_data = self.get_single_row_data(table, &key)?;
+        Ok(bigtable::deserialize_bincode_cell_data(
+            &row_data, table, key,
+        )?)
+    }
+
+    fn get_protobuf_or_bincode_cell<B, P>(&self, table: &str, key: RowKey) -> Result<CellData<B, P>>
+    where
+        B: serde::de::DeserializeOwned,
+        P: prost::Message + Default,
+    {
+        let row_data = self.get_single_row_data(table, &key)?;
+        Ok(bigtable::deserialize_protobuf_or_bincode_cell_data(
+            &row_data, table, key,
+        )?)
+    }
+
+    fn put_row_data(&self, table: &str, row_data: &[(RowKey, RowData)]) -> Result<usize> {
+        let cf = self.db.cf_handle(table).unwrap();
+        let mut bytes_written = 0;
+        let mut batch = WriteBatch::default();
+        for (row_key, row_data) in row_data {
+            let value = bincode::serialize(row_data).unwrap();
+            bytes_written += value.len();
+            batch.put_cf(cf, row_key, value);
+        }
+        self.db.write(batch)?;
+        Ok(bytes_written)
+    }
+
+    fn put_bincode_cells<T>(&self, table: &str, cells: &[(RowKey, T)]) -> Result<usize>
+    where
+        T: serde::ser::Serialize,
+    {
+        let mut new_row_data = vec![];
+        for (row_key, data) in cells {
+            let data = bigtable::serialize_bincode_cell_data(data)?;
+            new_row_data.push((row_key.clone(), vec![("bin".to_string(), data)]));
+        }
+        self.put_row_data(table, &new_row_data)
+    }
+
+    fn put_protobuf_cells<T>(&self, table: &str, cells: &[(RowKey, T)]) -> Result<usize>
+    where
+        T: prost::Message,
+    {
+        let mut new_row_data = vec![];
+        for (row_key, data) in cells {
+            let data = bigtable::serialize_protobuf_cell_data(data)?;
+            new_row_data.push((row_key.clone(), vec![("proto".to_string(), data)]));
+        }
+        self.put_row_data(table, &new_row_data)
+    }
+}
+
+fn deserialize_row_data(table: &str, row_key: &str, value: &[u8]) -> Result<RowData> {
+    bincode::deserialize(value).map_err(|err| {
+        warn!("Failed to deserialize {}/{}: {}", table, row_key, err);
+        bigtable::Error::ObjectCorrupt(format!("{}/{}", table, row_key)).into()
+    })
+}
+
+// Blocking counterparts of the `LedgerStorageAdapter` methods
+impl LocalLedgerStorage {
+    fn first_available_block(&self) -> Result<Option<Slot>> {
+        let blocks = self.get_row_keys("blocks", None, 1)?;
+        Ok(blocks.first().and_then(|key| key_to_slot(key)))
+    }
+
+    fn confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+        let blocks = self.get_row_keys("blocks", Some(slot_to_key(start_slot)), limit)?;
+        Ok(blocks.into_iter().filter_map(|s| key_to_slot(&s)).collect())
+    }
+
+    fn confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
+        let block_cell_data = self
+            .get_protobuf_or_bincode_cell::<StoredConfirmedBlock, generated::ConfirmedBlock>(
+                "blocks",
+                slot_to_key(slot),
+            )
+            .map_err(|err| match err {
+                Error::BigTableError(bigtable::Error::RowNotFound) => Error::BlockNotFound(slot),
+                _ => err,
+            })?;
+        confirmed_block_from_cell_data(slot, block_cell_data)
+    }
+
+    fn signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
+        let transaction_info = self
+            .get_bincode_cell::<TransactionInfo>("tx", signature.to_string())
+            .map_err(|err| match err {
+                Error::BigTableError(bigtable::Error::RowNotFound) => Error::SignatureNotFound,
+                _ => err,
+            })?;
+        Ok(transaction_info.into())
+    }
+
+    fn confirmed_transaction(&self, signature: &Signature) -> Result<Option<ConfirmedTransaction>> {
+        // Figure out which block the transaction is located in
+        let TransactionInfo { slot, index, .. } = self
+            .get_bincode_cell("tx", signature.to_string())
+            .map_err(|err| match err {
+                Error::BigTableError(bigtable::Error::RowNotFound) => Error::SignatureNotFound,
+                _ => err,
+            })?;
+
+        // Load the block and return the transaction
+        let block = self.confirmed_block(slot)?;
+        Ok(confirmed_transaction_from_block(
+            signature, slot, index, block,
+        ))
+    }
+
+    fn confirmed_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before_signature: Option<&Signature>,
+        until_signature: Option<&Signature>,
+        filter: &AddressSignatureFilter,
+        limit: usize,
+    ) -> Result<
+        AddressSignatures<(
+            ConfirmedTransactionStatusWithSignature,
+            u32, /*slot index*/
+        )>,
+    > {
+        let before = before_signature
+            .map(|signature| self.get_bincode_cell("tx", signature.to_string()))
+            .transpose()?;
+        let until = until_signature
+            .map(|signature| self.get_bincode_cell("tx", signature.to_string()))
+            .transpose()?;
+
+        let mut range = SignaturesForAddressRange::new(address, before, until);
+
+        let starting_slot_tx_len = self
+            .get_protobuf_or_bincode_cell::<Vec<LegacyTransactionByAddrInfo>, tx_by_addr::TransactionByAddr>(
+                "tx-by-addr",
+                range.start_key(),
+            )
+            .map(tx_by_addr_cell_len)
+            .unwrap_or(0);
+
+        let rows_limit = limit + starting_slot_tx_len;
+        let mut infos = vec![];
+        loop {
+            let tx_by_addr_data = self.get_row_data(
+                "tx-by-addr",
+                Some(range.start_key()),
+                Some(range.end_key()),
+                rows_limit,
+            )?;
+            if !range.collect_page(tx_by_addr_data, rows_limit, filter, limit, &mut infos)? {
+                break;
+            }
+        }
+        Ok(AddressSignatures {
+            signatures: infos,
+            resume_before: range.resume_before,
+        })
+    }
+
+    fn store_confirmed_block(&self, slot: Slot, confirmed_block: ConfirmedBlock) -> Result<()> {
+        let mut bytes_written = 0;
+
+        let (tx_cells, tx_by_addr_cells) = confirmed_block_index_cells(slot, &confirmed_block);
+        bytes_written += self.put_bincode_cells("tx", &tx_cells)?;
+        bytes_written += self.put_protobuf_cells("tx-by-addr", &tx_by_addr_cells)?;
+
+        let num_transactions = confirmed_block.transactions.len();
+
+        // As with BigTable, store the block itself last so that partially stored blocks never
+        // become visible
+        let blocks_cells = [(
+            slot_to_key(slot),
+            generated::ConfirmedBlock::from(confirmed_block),
+        )];
+        bytes_written += self.put_protobuf_cells("blocks", &blocks_cells)?;
+        info!(
+            "stored block for slot {}: {} transactions, {} bytes",
+            slot, num_transactions, bytes_written
+        );
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LedgerStorageAdapter for LocalLedgerStorage {
+    async fn get_first_available_block(&self) -> Result<Option<Slot>> {
+        self.run_blocking(|storage| storage.first_available_block())
+            .await
+    }
+
+    async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+        self.run_blocking(move |storage| storage.confirmed_blocks(start_slot, limit))
+            .await
+    }
+
+    async fn get_confirmed_block(&self, slot: Slot) -> Result<ConfirmedBlock> {
+        self.run_blocking(move |storage| storage.confirmed_block(slot))
+            .await
+    }
+
+    async fn get_signature_status(&self, signature: &Signature) -> Result<TransactionStatus> {
+        let signature = *signature;
+        self.run_blocking(move |storage| storage.signature_status(&signature))
+            .await
+    }
+
+    async fn get_confirmed_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<ConfirmedTransaction>> {
+        let signature = *signature;
+        self.run_blocking(move |storage| storage.confirmed_transaction(&signature))
+            .await
+    }
+
+    async fn get_confirmed_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before_signature: Option<&Signature>,
+        until_signature: Option<&Signature>,
+        filter: &AddressSignatureFilter,
+        limit: usize,
+    ) -> Result<
+        AddressSignatures<(
+            ConfirmedTransactionStatusWithSignature,
+            u32, /*slot index*/
+        )>,
+    > {
+        let address = *address;
+        let before_signature = before_signature.cloned();
+        let until_signature = until_signature.cloned();
+        let filter = filter.clone();
+        self.run_blocking(move |storage| {
+            storage.confirmed_signatures_for_address(
+                &address,
+                before_signature.as_ref(),
+                until_signature.as_ref(),
+                &filter,
+                limit,
+            )
+        })
+        .await
+    }
+
+    async fn upload_confirmed_block(
+        &self,
+        slot: Slot,
+        confirmed_block: ConfirmedBlock,
+    ) -> Result<()> {
+        self.run_blocking(move |storage| storage.store_confirmed_block(slot, confirmed_block))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{hash::Hash, signature::Keypair, system_program, system_transaction},
+        solana_transaction_status::{
+            TransactionStatusFilter, TransactionStatusMeta, TransactionWithStatusMeta,
+        },
+    };
+
+    fn confirmed_block(
+        parent_slot: Slot,
+        transactions: Vec<TransactionWithStatusMeta>,
+    ) -> ConfirmedBlock {
+        ConfirmedBlock {
+            previous_blockhash: Hash::default().to_string(),
+            blockhash: Hash::new_unique().to_string(),
+            parent_slot,
+            transactions,
+            rewards: vec![],
+            block_time: Some(1_628_000_000),
+            block_height: Some(parent_slot + 1),
+        }
+    }
+
+    fn transfer(from: &Keypair, to: &Pubkey, lamports: u64) -> TransactionWithStatusMeta {
+        TransactionWithStatusMeta {
+            transaction: system_transaction::transfer(from, to, lamports, Hash::default()),
+            meta: Some(TransactionStatusMeta {
+                status: Ok(()),
+                fee: 5000,
+                pre_balances: vec![100, 0, 1],
+                post_balances: vec![53, 42, 1],
+                inner_instructions: Some(vec![]),
+                log_messages: Some(vec![]),
+                pre_token_balances: Some(vec![]),
+                post_token_balances: Some(vec![]),
+                rewards: Some(vec![]),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_local_ledger_storage() {
+        let ledger_path = tempfile::tempdir().unwrap();
+        let storage = LocalLedgerStorage::open(ledger_path.path(), false).unwrap();
+
+        let no_filter = AddressSignatureFilter::default();
+        let from = Keypair::new();
+        let to = solana_sdk::pubkey::new_rand();
+        let transactions: Vec<_> = (0..3).map(|i| transfer(&from, &to, 42 + i)).collect();
+        let signatures: Vec<_> = transactions
+            .iter()
+            .map(|tx| tx.transaction.signatures[0])
+            .collect();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            assert_eq!(storage.get_first_available_block().await.unwrap(), None);
+
+            let block = confirmed_block(4, vec![transactions[0].clone()]);
+            storage
+                .upload_confirmed_block(5, block.clone())
+                .await
+                .unwrap();
+            let mut later_block =
+                confirmed_block(5, vec![transactions[1].clone(), transactions[2].clone()]);
+            later_block.block_time = block.block_time.map(|block_time| block_time + 1);
+            storage
+                .upload_confirmed_block(300, later_block)
+                .await
+                .unwrap();
+
+            assert_eq!(storage.get_first_available_block().await.unwrap(), Some(5));
+            assert_eq!(
+                storage.get_confirmed_blocks(0, 0).await.unwrap(),
+                vec![5, 300]
+            );
+            assert_eq!(storage.get_confirmed_blocks(6, 1).await.unwrap(), vec![300]);
+            assert_eq!(storage.get_confirmed_block(5).await.unwrap(), block);
+            assert!(matches!(
+                storage.get_confirmed_block(6).await,
+                Err(Error::BlockNotFound(6))
+            ));
+
+            let status = storage.get_signature_status(&signatures[2]).await.unwrap();
+            assert_eq!(status.slot, 300);
+            let transaction = storage
+                .get_confirmed_transaction(&signatures[2])
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(transaction.slot, 300);
+            assert_eq!(transaction.transaction, transactions[2]);
+            assert!(matches!(
+                storage
+                    .get_confirmed_transaction(&Signature::default())
+                    .await,
+                Err(Error::SignatureNotFound)
+            ));
+
+            // Newest first, across blocks
+            let results = storage
+                .get_confirmed_signatures_for_address(&to, None, None, &no_filter, 10)
+                .await
+                .unwrap();
+            assert_eq!(results.resume_before, None);
+            let results: Vec<_> = results
+                .signatures
+                .into_iter()
+                .map(|(info, index)| (info.signature, info.slot, index))
+                .collect();
+            assert_eq!(
+                results,
+                vec![
+                    (signatures[2], 300, 1),
+                    (signatures[1], 300, 0),
+                    (signatures[0], 5, 0),
+                ]
+            );
+
+            let results = storage
+                .get_confirmed_signatures_for_address(
+                    &to,
+                    Some(&signatures[2]),
+                    None,
+                    &no_filter,
+                    1,
+                )
+                .await
+                .unwrap()
+                .signatures;
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].0.signature, signatures[1]);
+
+            let results = storage
+                .get_confirmed_signatures_for_address(
+                    &to,
+                    None,
+                    Some(&signatures[0]),
+                    &no_filter,
+                    10,
+                )
+                .await
+                .unwrap()
+                .signatures;
+            assert_eq!(results.len(), 2);
+
+            // Filtered queries page past the rows that are filtered out
+            let filter = AddressSignatureFilter {
+                max_block_time: Some(1_628_000_000),
+                program_ids: vec![system_program::id()],
+                ..AddressSignatureFilter::default()
+            };
+            let results = storage
+                .get_confirmed_signatures_for_address(&to, None, None, &filter, 1)
+                .await
+                .unwrap()
+                .signatures;
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].0.signature, signatures[0]);
+
+            let filter = AddressSignatureFilter {
+                status: Some(TransactionStatusFilter::Failed),
+                ..AddressSignatureFilter::default()
+            };
+            assert_eq!(
+                storage
+                    .get_confirmed_signatures_for_address(&to, None, None, &filter, 10)
+                    .await
+                    .unwrap(),
+                AddressSignatures::default()
+            );
+
+            // Listing ends at the first transaction older than the lower bound of the block time
+            let filter = AddressSignatureFilter {
+                min_block_time: Some(i64::MAX),
+                ..AddressSignatureFilter::default()
+            };
+            assert_eq!(
+                storage
+                    .get_confirmed_signatures_for_address(&to, None, None, &filter, 10)
+                    .await
+                    .unwrap(),
+                AddressSignatures::default()
+            );
+        });
+
+        // Written history is visible to a read-only instance, which catches up with later writes
+        let reader = LocalLedgerStorage::open(ledger_path.path(), true).unwrap();
+        assert_eq!(
+            runtime.block_on(reader.get_confirmed_blocks(0, 0)).unwrap(),
+            vec![5, 300]
+        );
+        runtime
+            .block_on(storage.upload_confirmed_block(301, confirmed_block(300, vec![])))
+            .unwrap();
+        std::thread::sleep(CATCH_UP_WITH_PRIMARY_INTERVAL);
+        assert_eq!(
+            runtime.block_on(reader.get_confirmed_blocks(0, 0)).unwrap(),
+            vec![5, 300, 301]
+        );
+    }
+}
@@ -783,6 +783,7 @@ impl From<TransactionByAddrInfo> for tx_by_addr::TransactionByAddrInfo {
             index,
             memo,
             block_time,
+            program_ids,
         } = by_addr;
 
         Self {
@@ -791,6 +792,10 @@ impl From<TransactionByAddrInfo> for tx_by_addr::TransactionByAddrInfo {
             index,
             memo: memo.map(|memo| tx_by_addr::Memo { memo }),
             block_time: block_time.map(|timestamp| tx_by_addr::UnixTimestamp { timestamp }),
+            program_ids: program_ids
+                .into_iter()
+                .map(|program_id| <Pubkey as AsRef<[u8]>>::as_ref(&program_id).into())
+                .collect(),
         }
     }
 }
@@ -816,6 +821,14 @@ impl TryFrom<tx_by_addr::TransactionByAddrInfo> for TransactionByAddrInfo {
             block_time: transaction_by_addr
                 .block_time
                 .map(|tx_by_addr::UnixTimestamp { timestamp }| timestamp),
+            program_ids: transaction_by_addr
+                .program_ids
+                .iter()
+                .map(|program_id| {
+                    <[u8; 32]>::try_from(program_id.as_slice()).map(Pubkey::new_from_array)
+                })
+                .collect::<Result<_, _>>()
+                .map_err(|_| "Invalid program id")?,
         })
     }
 }
@@ -872,7 +885,8 @@ mod test {
             err: None,
             index: 5,
             memo: Some("string".to_string()),
-            block_time: Some(1610674861)
+            block_time: Some(1610674861),
+            program_ids: vec![Pubkey::new_unique()],
         };
 
         let tx_by_addr_transaction_info: tx_by_addr::TransactionByAddrInfo = info.clone().into();
@@ -1,9 +1,11 @@
 #![allow(clippy::integer_arithmetic)]
 pub mod packet;
+pub mod quic;
 pub mod recvmmsg;
 pub mod sendmmsg;
 pub mod socket;
 pub mod streamer;
+pub mod tls_certificates;
 
 #[macro_use]
 extern crate log;
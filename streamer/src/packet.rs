@@ -1,6 +1,6 @@
 //! The `packet` module defines data structures and methods to pull data from the network.
 use crate::{
-    recvmmsg::{recv_mmsg, NUM_RCVMMSGS},
+    recvmmsg::{recv_mmsg, recv_mmsg_with_timestamps, MAX_RCVMMSGS},
     socket::is_global,
 };
 pub use solana_perf::packet::{
@@ -10,10 +10,57 @@ pub use solana_perf::packet::{
 
 use solana_metrics::inc_new_counter_debug;
 pub use solana_sdk::packet::{Meta, Packet, PACKET_DATA_SIZE};
-use std::{io::Result, net::UdpSocket, time::Instant};
+use std::{
+    io::Result,
+    net::UdpSocket,
+    time::{Instant, SystemTime},
+};
 
 pub fn recv_from(obj: &mut Packets, socket: &UdpSocket, max_wait_ms: u64) -> Result<usize> {
+    recv_from_with_config(obj, socket, max_wait_ms, PACKETS_PER_BATCH, None)
+}
+
+/// How long received packets waited in the socket receive buffer, going by their kernel
+/// receive timestamps
+#[derive(Default, Debug)]
+pub struct ReceiveLatency {
+    pub num_packets: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+}
+
+impl ReceiveLatency {
+    fn record(&mut self, timestamps: &[Option<SystemTime>]) {
+        let now = SystemTime::now();
+        for timestamp in timestamps.iter().flatten() {
+            let latency_us = now
+                .duration_since(*timestamp)
+                .map(|latency| latency.as_micros() as u64)
+                .unwrap_or_default();
+            self.num_packets += 1;
+            self.total_us += latency_us;
+            self.max_us = self.max_us.max(latency_us);
+        }
+    }
+
+    pub fn mean_us(&self) -> u64 {
+        self.total_us
+            .checked_div(self.num_packets)
+            .unwrap_or_default()
+    }
+}
+
+/// Like `recv_from`, but reads up to `batch_size` packets into `obj`, and records the receive
+/// latency of the packets in `latency` if given
+pub fn recv_from_with_config(
+    obj: &mut Packets,
+    socket: &UdpSocket,
+    max_wait_ms: u64,
+    batch_size: usize,
+    mut latency: Option<&mut ReceiveLatency>,
+) -> Result<usize> {
     let mut i = 0;
+    let mut timestamps = vec![];
     //DOCUMENTED SIDE-EFFECT
     //Performance out of the IO without poll
     //  * block on the socket until it's readable
@@ -25,10 +72,15 @@ pub fn recv_from(obj: &mut Packets, socket: &UdpSocket, max_wait_ms: u64) -> Res
     let start = Instant::now();
     loop {
         obj.packets.resize(
-            std::cmp::min(i + NUM_RCVMMSGS, PACKETS_PER_BATCH),
+            std::cmp::min(i + MAX_RCVMMSGS, batch_size),
             Packet::default(),
         );
-        match recv_mmsg(socket, &mut obj.packets[i..]) {
+        let result = if latency.is_some() {
+            recv_mmsg_with_timestamps(socket, &mut obj.packets[i..], &mut timestamps)
+        } else {
+            recv_mmsg(socket, &mut obj.packets[i..])
+        };
+        match result {
             Err(_) if i > 0 => {
                 if start.elapsed().as_millis() as u64 > max_wait_ms {
                     break;
@@ -43,10 +95,13 @@ pub fn recv_from(obj: &mut Packets, socket: &UdpSocket, max_wait_ms: u64) -> Res
                     socket.set_nonblocking(true)?;
                 }
                 trace!("got {} packets", npkts);
+                if let Some(latency) = latency.as_mut() {
+                    latency.record(&timestamps);
+                }
                 i += npkts;
                 // Try to batch into big enough buffers
                 // will cause less re-shuffling later on.
-                if start.elapsed().as_millis() as u64 > max_wait_ms || i >= PACKETS_PER_BATCH {
+                if start.elapsed().as_millis() as u64 > max_wait_ms || i >= batch_size {
                     break;
                 }
             }
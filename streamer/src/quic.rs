@@ -0,0 +1,766 @@
+//! The `quic` module implements a QUIC server that receives transactions, one per unidirectional
+//! stream, and feeds them in batches to the same channel the UDP TPU receivers use. Unlike UDP,
+//! every connection identifies its sender and is subject to QUIC flow control.
+use {
+    crate::{
+        streamer::{PacketSender, StakedNodes},
+        tls_certificates::{get_pubkey_from_tls_certificate, new_self_signed_tls_certificate},
+    },
+    futures_util::stream::StreamExt,
+    quinn::{
+        Connecting, Endpoint, EndpointConfig, IdleTimeout, Incoming, IncomingUniStreams,
+        NewConnection, ServerConfig, VarInt,
+    },
+    rcgen::RcgenError,
+    rustls::{server::ClientCertVerified, Certificate, DistinguishedNames},
+    solana_perf::{
+        packet::{Packets, PacketsRecycler, PACKETS_PER_BATCH},
+        recycler::Recycler,
+    },
+    solana_sdk::{
+        packet::{Packet, PACKET_DATA_SIZE},
+        pubkey::Pubkey,
+        quic::{
+            QUIC_MAX_CONCURRENT_STREAMS, QUIC_MAX_TIMEOUT_MS, QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS,
+            QUIC_MIN_STAKED_CONCURRENT_STREAMS, QUIC_TOTAL_STAKED_CONCURRENT_STREAMS,
+        },
+        signature::Keypair,
+    },
+    std::{
+        collections::HashMap,
+        net::{IpAddr, SocketAddr, UdpSocket},
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc, Mutex, RwLock,
+        },
+        thread,
+        time::{Duration, Instant, SystemTime},
+    },
+    thiserror::Error,
+    tokio::{
+        runtime::Runtime,
+        sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        time::{timeout, timeout_at},
+    },
+};
+
+/// Maximum number of connections a single IP address may hold open at once
+pub const MAX_CONNECTIONS_PER_IP: usize = 8;
+
+/// Staked and unstaked clients draw from separate connection budgets, so that a flood of
+/// unstaked connections can't lock staked nodes out
+pub const MAX_STAKED_CONNECTIONS: usize = 2_000;
+pub const MAX_UNSTAKED_CONNECTIONS: usize = 500;
+
+const WAIT_FOR_CONNECTION_TIMEOUT_MS: u64 = 1_000;
+/// A stream is dropped if the client hasn't finished sending its transaction by then
+const STREAM_READ_TIMEOUT_MS: u64 = 2_000;
+const STATS_REPORT_INTERVAL_MS: u64 = 5_000;
+
+#[derive(Error, Debug)]
+pub enum QuicServerError {
+    #[error("Server configuration failed")]
+    ConfigureFailed,
+
+    #[error("Certificate error: {0}")]
+    CertificateError(#[from] RcgenError),
+
+    #[error("TLS error: {0}")]
+    TlsError(#[from] rustls::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// The certificate only tells the server who the client is; any self-signed certificate is
+/// accepted, as long as the client proves it holds the certificate's key
+struct SkipClientVerification;
+
+impl rustls::server::ClientCertVerifier for SkipClientVerification {
+    fn client_auth_root_subjects(&self) -> Option<DistinguishedNames> {
+        Some(DistinguishedNames::new())
+    }
+
+    fn verify_client_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _now: SystemTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        Ok(ClientCertVerified::assertion())
+    }
+}
+
+pub(crate) fn configure_server(
+    identity_keypair: &Keypair,
+    gossip_host: IpAddr,
+) -> Result<ServerConfig, QuicServerError> {
+    let (cert, priv_key) = new_self_signed_tls_certificate(identity_keypair, gossip_host)?;
+
+    let server_tls_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(Arc::new(SkipClientVerification))
+        .with_single_cert(vec![cert], priv_key)?;
+
+    let mut server_config = ServerConfig::with_crypto(Arc::new(server_tls_config));
+    let config =
+        Arc::get_mut(&mut server_config.transport).ok_or(QuicServerError::ConfigureFailed)?;
+    // Raised for staked clients once they are identified
+    config.max_concurrent_uni_streams(VarInt::from(QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS as u32));
+    config.stream_receive_window(VarInt::from(PACKET_DATA_SIZE as u32));
+    config.receive_window(VarInt::from(
+        (PACKET_DATA_SIZE * QUIC_MAX_CONCURRENT_STREAMS) as u32,
+    ));
+    // Transactions only travel from the client to the server
+    config.max_concurrent_bidi_streams(VarInt::from(0u8));
+    config.max_idle_timeout(Some(IdleTimeout::from(VarInt::from(QUIC_MAX_TIMEOUT_MS))));
+    Ok(server_config)
+}
+
+#[derive(Default)]
+struct StreamStats {
+    active_connections: AtomicUsize,
+    active_staked_connections: AtomicUsize,
+    total_new_connections: AtomicUsize,
+    connection_add_failed: AtomicUsize,
+    connection_setup_error: AtomicUsize,
+    total_new_streams: AtomicUsize,
+    stream_read_errors: AtomicUsize,
+    stream_read_timeouts: AtomicUsize,
+    staked_packets_received: AtomicUsize,
+    unstaked_packets_received: AtomicUsize,
+    packet_batches_sent: AtomicUsize,
+    packet_send_errors: AtomicUsize,
+}
+
+impl StreamStats {
+    fn report(&self) {
+        datapoint_info!(
+            "quic-connections",
+            (
+                "active_connections",
+                self.active_connections.load(Ordering::Relaxed),
+                i64
+            ),
+            (
+                "active_staked_connections",
+                self.active_staked_connections.load(Ordering::Relaxed),
+                i64
+            ),
+            (
+                "new_connections",
+                self.total_new_connections.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "connection_add_failed",
+                self.connection_add_failed.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "connection_setup_error",
+                self.connection_setup_error.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "new_streams",
+                self.total_new_streams.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "stream_read_errors",
+                self.stream_read_errors.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "stream_read_timeouts",
+                self.stream_read_timeouts.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "staked_packets_received",
+                self.staked_packets_received.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "unstaked_packets_received",
+                self.unstaked_packets_received.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "packet_batches_sent",
+                self.packet_batches_sent.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "packet_send_errors",
+                self.packet_send_errors.swap(0, Ordering::Relaxed),
+                i64
+            ),
+        );
+    }
+}
+
+/// Number of open connections per remote IP address
+struct ConnectionTable {
+    connections: HashMap<IpAddr, usize>,
+    total_connections: usize,
+    max_connections: usize,
+}
+
+impl ConnectionTable {
+    fn new(max_connections: usize) -> Self {
+        Self {
+            connections: HashMap::new(),
+            total_connections: 0,
+            max_connections,
+        }
+    }
+
+    fn try_add(&mut self, ip: IpAddr) -> bool {
+        if self.total_connections >= self.max_connections {
+            return false;
+        }
+        let connections = self.connections.entry(ip).or_default();
+        if *connections >= MAX_CONNECTIONS_PER_IP {
+            return false;
+        }
+        *connections += 1;
+        self.total_connections += 1;
+        true
+    }
+
+    fn remove(&mut self, ip: &IpAddr) {
+        if let Some(connections) = self.connections.get_mut(ip) {
+            *connections = connections.saturating_sub(1);
+            self.total_connections = self.total_connections.saturating_sub(1);
+            if *connections == 0 {
+                self.connections.remove(ip);
+            }
+        }
+    }
+}
+
+struct ConnectionTables {
+    staked: ConnectionTable,
+    unstaked: ConnectionTable,
+}
+
+impl ConnectionTables {
+    fn new() -> Self {
+        Self {
+            staked: ConnectionTable::new(MAX_STAKED_CONNECTIONS),
+            unstaked: ConnectionTable::new(MAX_UNSTAKED_CONNECTIONS),
+        }
+    }
+
+    fn table(&mut self, staked: bool) -> &mut ConnectionTable {
+        if staked {
+            &mut self.staked
+        } else {
+            &mut self.unstaked
+        }
+    }
+}
+
+/// Number of streams a connection from a client with `stake` out of `total_stake` may have open
+pub fn compute_max_allowed_uni_streams(stake: u64, total_stake: u64) -> usize {
+    if stake == 0 || total_stake == 0 {
+        return QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS;
+    }
+    let share = (QUIC_TOTAL_STAKED_CONCURRENT_STREAMS as u128)
+        .saturating_mul(stake as u128)
+        .checked_div(total_stake as u128)
+        .unwrap_or_default() as usize;
+    share.clamp(
+        QUIC_MIN_STAKED_CONCURRENT_STREAMS,
+        QUIC_MAX_CONCURRENT_STREAMS,
+    )
+}
+
+/// Returns the identity the client presented in its certificate
+pub fn get_remote_pubkey(connection: &quinn::Connection) -> Option<Pubkey> {
+    connection
+        .peer_identity()?
+        .downcast::<Vec<Certificate>>()
+        .ok()?
+        .first()
+        .and_then(get_pubkey_from_tls_certificate)
+}
+
+fn new_packet(data: &[u8], remote_addr: &SocketAddr) -> Packet {
+    let mut packet = Packet::default();
+    packet.data[..data.len()].copy_from_slice(data);
+    packet.meta.size = data.len();
+    packet.meta.set_addr(remote_addr);
+    packet
+}
+
+fn rt() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+/// Spawns a thread serving QUIC connections on `sock`, and sending the received transactions to
+/// `packet_sender` in batches coalesced for up to `coalesce_ms`
+pub fn spawn_server(
+    sock: UdpSocket,
+    keypair: &Keypair,
+    gossip_host: IpAddr,
+    packet_sender: PacketSender,
+    exit: Arc<AtomicBool>,
+    staked_nodes: Arc<RwLock<StakedNodes>>,
+    coalesce_ms: u64,
+) -> Result<thread::JoinHandle<()>, QuicServerError> {
+    let config = configure_server(keypair, gossip_host)?;
+    let runtime = rt();
+    let (endpoint, incoming) = {
+        let _guard = runtime.enter();
+        Endpoint::new(EndpointConfig::default(), Some(config), sock)?
+    };
+
+    let handle = thread::Builder::new()
+        .name("solana-quic-server".to_string())
+        .spawn(move || {
+            runtime.block_on(run_server(
+                incoming,
+                packet_sender,
+                exit,
+                staked_nodes,
+                coalesce_ms,
+            ));
+            endpoint.close(0u32.into(), b"exit");
+        })
+        .unwrap();
+    Ok(handle)
+}
+
+async fn run_server(
+    mut incoming: Incoming,
+    packet_sender: PacketSender,
+    exit: Arc<AtomicBool>,
+    staked_nodes: Arc<RwLock<StakedNodes>>,
+    coalesce_ms: u64,
+) {
+    let stats = Arc::new(StreamStats::default());
+    let recycler: PacketsRecycler = Recycler::warmed(100, PACKETS_PER_BATCH);
+    let (batch_sender, batch_receiver) = unbounded_channel();
+    tokio::spawn(packet_batch_sender(
+        batch_receiver,
+        packet_sender,
+        recycler,
+        Duration::from_millis(coalesce_ms),
+        stats.clone(),
+    ));
+    let connection_tables = Arc::new(Mutex::new(ConnectionTables::new()));
+    let mut last_report = Instant::now();
+    while !exit.load(Ordering::Relaxed) {
+        let connecting = timeout(
+            Duration::from_millis(WAIT_FOR_CONNECTION_TIMEOUT_MS),
+            incoming.next(),
+        )
+        .await;
+
+        if last_report.elapsed() > Duration::from_millis(STATS_REPORT_INTERVAL_MS) {
+            stats.report();
+            last_report = Instant::now();
+        }
+
+        match connecting {
+            Ok(Some(connecting)) => {
+                tokio::spawn(setup_connection(
+                    connecting,
+                    connection_tables.clone(),
+                    staked_nodes.clone(),
+                    batch_sender.clone(),
+                    stats.clone(),
+                    exit.clone(),
+                ));
+            }
+            // The endpoint was closed
+            Ok(None) => break,
+            Err(_timeout) => {}
+        }
+    }
+}
+
+async fn setup_connection(
+    connecting: Connecting,
+    connection_tables: Arc<Mutex<ConnectionTables>>,
+    staked_nodes: Arc<RwLock<StakedNodes>>,
+    batch_sender: UnboundedSender<Packet>,
+    stats: Arc<StreamStats>,
+    exit: Arc<AtomicBool>,
+) {
+    let NewConnection {
+        connection,
+        uni_streams,
+        ..
+    } = match connecting.await {
+        Ok(new_connection) => new_connection,
+        Err(err) => {
+            debug!("quic connection setup failed: {:?}", err);
+            stats.connection_setup_error.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+    };
+    let remote_addr = connection.remote_address();
+    let remote_pubkey = get_remote_pubkey(&connection);
+    let (stake, total_stake) = {
+        let staked_nodes = staked_nodes.read().unwrap();
+        let stake = remote_pubkey
+            .and_then(|pubkey| staked_nodes.pubkey_stake_map.get(&pubkey).copied())
+            .unwrap_or_default();
+        (stake, staked_nodes.total_stake)
+    };
+    let staked = stake > 0;
+    if !connection_tables
+        .lock()
+        .unwrap()
+        .table(staked)
+        .try_add(remote_addr.ip())
+    {
+        stats.connection_add_failed.fetch_add(1, Ordering::Relaxed);
+        connection.close(0u32.into(), b"too many connections");
+        return;
+    }
+    let max_uni_streams = compute_max_allowed_uni_streams(stake, total_stake);
+    if staked {
+        connection.set_max_concurrent_uni_streams(VarInt::from(max_uni_streams as u32));
+        stats
+            .active_staked_connections
+            .fetch_add(1, Ordering::Relaxed);
+    }
+    trace!(
+        "new quic connection from {} ({:?}), stake: {}, max streams: {}",
+        remote_addr,
+        remote_pubkey,
+        stake,
+        max_uni_streams
+    );
+    stats.total_new_connections.fetch_add(1, Ordering::Relaxed);
+    stats.active_connections.fetch_add(1, Ordering::Relaxed);
+
+    handle_connection(
+        uni_streams,
+        remote_addr,
+        staked,
+        &batch_sender,
+        &stats,
+        &exit,
+    )
+    .await;
+
+    connection_tables
+        .lock()
+        .unwrap()
+        .table(staked)
+        .remove(&remote_addr.ip());
+    if staked {
+        stats
+            .active_staked_connections
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+    stats.active_connections.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Gathers the packets read from all connections into batches of up to `PACKETS_PER_BATCH`,
+/// waiting at most `coalesce` after the first packet of a batch
+async fn packet_batch_sender(
+    mut batch_receiver: UnboundedReceiver<Packet>,
+    packet_sender: PacketSender,
+    recycler: PacketsRecycler,
+    coalesce: Duration,
+    stats: Arc<StreamStats>,
+) {
+    while let Some(packet) = batch_receiver.recv().await {
+        let mut packets =
+            Packets::new_with_recycler(recycler.clone(), PACKETS_PER_BATCH, "quic_packet_batch");
+        packets.packets.push(packet);
+        let deadline = tokio::time::Instant::now() + coalesce;
+        while packets.packets.len() < PACKETS_PER_BATCH {
+            match timeout_at(deadline, batch_receiver.recv()).await {
+                Ok(Some(packet)) => packets.packets.push(packet),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        stats.packet_batches_sent.fetch_add(1, Ordering::Relaxed);
+        if packet_sender.send(packets).is_err() {
+            stats.packet_send_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+async fn handle_connection(
+    mut uni_streams: IncomingUniStreams,
+    remote_addr: SocketAddr,
+    staked: bool,
+    batch_sender: &UnboundedSender<Packet>,
+    stats: &Arc<StreamStats>,
+    exit: &Arc<AtomicBool>,
+) {
+    while !exit.load(Ordering::Relaxed) {
+        let stream = match uni_streams.next().await {
+            Some(Ok(stream)) => stream,
+            // The connection was closed or lost
+            Some(Err(_)) | None => break,
+        };
+        stats.total_new_streams.fetch_add(1, Ordering::Relaxed);
+        let batch_sender = batch_sender.clone();
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            match timeout(
+                Duration::from_millis(STREAM_READ_TIMEOUT_MS),
+                stream.read_to_end(PACKET_DATA_SIZE),
+            )
+            .await
+            {
+                Ok(Ok(data)) => {
+                    if staked {
+                        stats
+                            .staked_packets_received
+                            .fetch_add(1, Ordering::Relaxed);
+                    } else {
+                        stats
+                            .unstaked_packets_received
+                            .fetch_add(1, Ordering::Relaxed);
+                    }
+                    if batch_sender.send(new_packet(&data, &remote_addr)).is_err() {
+                        stats.packet_send_errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                Ok(Err(err)) => {
+                    trace!("quic stream read from {} failed: {:?}", remote_addr, err);
+                    stats.stream_read_errors.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(_timeout) => {
+                    stats.stream_read_timeouts.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        quinn::ClientConfig,
+        solana_sdk::{quic::QUIC_KEEP_ALIVE_MS, signature::Signer},
+        std::{net::Ipv4Addr, sync::mpsc::channel},
+    };
+
+    struct SkipServerVerification;
+
+    impl rustls::client::ServerCertVerifier for SkipServerVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &Certificate,
+            _intermediates: &[Certificate],
+            _server_name: &rustls::ServerName,
+            _scts: &mut dyn Iterator<Item = &[u8]>,
+            _ocsp_response: &[u8],
+            _now: SystemTime,
+        ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+            Ok(rustls::client::ServerCertVerified::assertion())
+        }
+    }
+
+    async fn connect(addr: &SocketAddr, client_keypair: &Keypair) -> NewConnection {
+        let (cert, priv_key) =
+            new_self_signed_tls_certificate(client_keypair, IpAddr::V4(Ipv4Addr::LOCALHOST))
+                .unwrap();
+        let crypto = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(SkipServerVerification))
+            .with_single_cert(vec![cert], priv_key)
+            .unwrap();
+        let mut config = ClientConfig::new(Arc::new(crypto));
+        Arc::get_mut(&mut config.transport)
+            .unwrap()
+            .keep_alive_interval(Some(Duration::from_millis(QUIC_KEEP_ALIVE_MS)));
+
+        let mut endpoint = Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+        endpoint.set_default_client_config(config);
+        endpoint.connect(*addr, "localhost").unwrap().await.unwrap()
+    }
+
+    fn make_client_endpoint(runtime: &Runtime, addr: &SocketAddr) -> NewConnection {
+        runtime.block_on(connect(addr, &Keypair::new()))
+    }
+
+    fn setup_quic_server() -> (
+        thread::JoinHandle<()>,
+        Arc<AtomicBool>,
+        std::sync::mpsc::Receiver<Packets>,
+        SocketAddr,
+    ) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let exit = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = channel();
+        let keypair = Keypair::new();
+        let ip = "127.0.0.1".parse().unwrap();
+        let server_address = socket.local_addr().unwrap();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let thread =
+            spawn_server(socket, &keypair, ip, sender, exit.clone(), staked_nodes, 1).unwrap();
+        (thread, exit, receiver, server_address)
+    }
+
+    #[test]
+    fn test_quic_server_exit() {
+        let (thread, exit, _receiver, _server_address) = setup_quic_server();
+        exit.store(true, Ordering::Relaxed);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_quic_server_streams() {
+        solana_logger::setup();
+        let (thread, exit, receiver, server_address) = setup_quic_server();
+
+        let runtime = rt();
+        let conn = make_client_endpoint(&runtime, &server_address);
+        let num_transactions = 10;
+        runtime.block_on(async {
+            for i in 0..num_transactions {
+                let mut stream = conn.connection.open_uni().await.unwrap();
+                stream
+                    .write_all(&[i as u8; PACKET_DATA_SIZE])
+                    .await
+                    .unwrap();
+                stream.finish().await.unwrap();
+            }
+        });
+
+        let mut received = vec![];
+        while received.len() < num_transactions {
+            let packets = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+            for packet in packets.packets.iter() {
+                assert_eq!(packet.meta.size, PACKET_DATA_SIZE);
+                assert_eq!(packet.meta.addr().ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+                received.push(packet.data[0]);
+            }
+        }
+        received.sort_unstable();
+        assert_eq!(received, (0..num_transactions as u8).collect::<Vec<_>>());
+
+        // Oversized transactions are dropped; the server may stop the stream before it is finished
+        runtime.block_on(async {
+            let mut stream = conn.connection.open_uni().await.unwrap();
+            let _ = stream.write_all(&[0u8; PACKET_DATA_SIZE + 1]).await;
+            let _ = stream.finish().await;
+        });
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+
+        exit.store(true, Ordering::Relaxed);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_quic_server_remote_pubkey() {
+        let server_keypair = Keypair::new();
+        let client_keypair = Keypair::new();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_address = socket.local_addr().unwrap();
+        let config = configure_server(&server_keypair, IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        let runtime = rt();
+        let (_endpoint, mut incoming) = {
+            let _guard = runtime.enter();
+            Endpoint::new(EndpointConfig::default(), Some(config), socket).unwrap()
+        };
+        let (server_conn, client_conn) = runtime.block_on(async {
+            let accept = async { incoming.next().await.unwrap().await.unwrap() };
+            tokio::join!(accept, connect(&server_address, &client_keypair))
+        });
+        // Each side sees the identity the other presented in its certificate
+        assert_eq!(
+            get_remote_pubkey(&server_conn.connection),
+            Some(client_keypair.pubkey())
+        );
+        assert_eq!(
+            get_remote_pubkey(&client_conn.connection),
+            Some(server_keypair.pubkey())
+        );
+    }
+
+    #[test]
+    fn test_packet_batch_sender() {
+        let runtime = rt();
+        let (batch_sender, batch_receiver) = unbounded_channel();
+        let (packet_sender, packet_receiver) = channel();
+        let num_packets = 2 * PACKETS_PER_BATCH + 1;
+        for _ in 0..num_packets {
+            batch_sender.send(Packet::default()).unwrap();
+        }
+        drop(batch_sender);
+        runtime.block_on(packet_batch_sender(
+            batch_receiver,
+            packet_sender,
+            PacketsRecycler::default(),
+            Duration::from_millis(1),
+            Arc::new(StreamStats::default()),
+        ));
+        let batch_sizes: Vec<_> = packet_receiver
+            .try_iter()
+            .map(|packets| packets.packets.len())
+            .collect();
+        assert_eq!(batch_sizes, vec![PACKETS_PER_BATCH, PACKETS_PER_BATCH, 1]);
+    }
+
+    #[test]
+    fn test_connection_table() {
+        let mut table = ConnectionTable::new(MAX_UNSTAKED_CONNECTIONS);
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        for _ in 0..MAX_CONNECTIONS_PER_IP {
+            assert!(table.try_add(ip));
+        }
+        assert!(!table.try_add(ip));
+        table.remove(&ip);
+        assert!(table.try_add(ip));
+        for _ in 0..MAX_CONNECTIONS_PER_IP {
+            table.remove(&ip);
+        }
+        assert!(table.connections.is_empty());
+        assert_eq!(table.total_connections, 0);
+
+        // The table is full once it holds `max_connections`, whichever IPs they come from
+        let mut table = ConnectionTable::new(2);
+        assert!(table.try_add(ip));
+        assert!(table.try_add(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))));
+        assert!(!table.try_add(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3))));
+        table.remove(&ip);
+        assert!(table.try_add(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3))));
+    }
+
+    #[test]
+    fn test_compute_max_allowed_uni_streams() {
+        assert_eq!(
+            compute_max_allowed_uni_streams(0, 0),
+            QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS
+        );
+        assert_eq!(
+            compute_max_allowed_uni_streams(0, 1_000),
+            QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS
+        );
+        // Small stakes still get the staked minimum
+        assert_eq!(
+            compute_max_allowed_uni_streams(1, u64::MAX),
+            QUIC_MIN_STAKED_CONCURRENT_STREAMS
+        );
+        assert_eq!(
+            compute_max_allowed_uni_streams(1, 100),
+            QUIC_TOTAL_STAKED_CONCURRENT_STREAMS / 100
+        );
+        assert_eq!(
+            compute_max_allowed_uni_streams(50, 100),
+            QUIC_MAX_CONCURRENT_STREAMS
+        );
+        assert_eq!(
+            compute_max_allowed_uni_streams(u64::MAX, u64::MAX),
+            QUIC_MAX_CONCURRENT_STREAMS
+        );
+    }
+}
@@ -5,11 +5,19 @@ pub use solana_perf::packet::NUM_RCVMMSGS;
 use std::cmp;
 use std::io;
 use std::net::UdpSocket;
+use std::time::SystemTime;
+
+/// Maximum number of packets read by a single `recv_mmsg` call
+pub const MAX_RCVMMSGS: usize = 1024;
+
+// u64 words of control message buffer per packet, enough for a receive timestamp
+#[cfg(target_os = "linux")]
+const CONTROL_WORDS: usize = 4;
 
 #[cfg(not(target_os = "linux"))]
 pub fn recv_mmsg(socket: &UdpSocket, packets: &mut [Packet]) -> io::Result<(usize, usize)> {
     let mut i = 0;
-    let count = cmp::min(NUM_RCVMMSGS, packets.len());
+    let count = cmp::min(MAX_RCVMMSGS, packets.len());
     let mut total_size = 0;
     for p in packets.iter_mut().take(count) {
         p.meta.size = 0;
@@ -34,23 +42,66 @@ pub fn recv_mmsg(socket: &UdpSocket, packets: &mut [Packet]) -> io::Result<(usiz
     Ok((total_size, i))
 }
 
+#[cfg(not(target_os = "linux"))]
+pub fn recv_mmsg_with_timestamps(
+    socket: &UdpSocket,
+    packets: &mut [Packet],
+    timestamps: &mut Vec<Option<SystemTime>>,
+) -> io::Result<(usize, usize)> {
+    let (total_size, npkts) = recv_mmsg(socket, packets)?;
+    timestamps.clear();
+    timestamps.resize(npkts, None);
+    Ok((total_size, npkts))
+}
+
 #[cfg(target_os = "linux")]
 pub fn recv_mmsg(sock: &UdpSocket, packets: &mut [Packet]) -> io::Result<(usize, usize)> {
+    recv_mmsg_linux(sock, packets, None)
+}
+
+/// Like `recv_mmsg`, but also stores the kernel receive time of each received packet in
+/// `timestamps`, or `None` if it has none. Only sockets with `SO_TIMESTAMPNS` enabled, see
+/// `socket::set_receive_timestamps`, get timestamps.
+#[cfg(target_os = "linux")]
+pub fn recv_mmsg_with_timestamps(
+    sock: &UdpSocket,
+    packets: &mut [Packet],
+    timestamps: &mut Vec<Option<SystemTime>>,
+) -> io::Result<(usize, usize)> {
+    recv_mmsg_linux(sock, packets, Some(timestamps))
+}
+
+#[cfg(target_os = "linux")]
+fn recv_mmsg_linux(
+    sock: &UdpSocket,
+    packets: &mut [Packet],
+    timestamps: Option<&mut Vec<Option<SystemTime>>>,
+) -> io::Result<(usize, usize)> {
     use libc::{
-        c_void, iovec, mmsghdr, recvmmsg, sockaddr_in, socklen_t, timespec, MSG_WAITFORONE,
+        c_void, iovec, mmsghdr, recvmmsg, sockaddr_in, socklen_t, timespec, CMSG_SPACE,
+        MSG_WAITFORONE,
     };
     use nix::sys::socket::InetAddr;
     use std::mem;
     use std::os::unix::io::AsRawFd;
 
-    let mut hdrs: [mmsghdr; NUM_RCVMMSGS] = unsafe { mem::zeroed() };
-    let mut iovs: [iovec; NUM_RCVMMSGS] = unsafe { mem::zeroed() };
-    let mut addr: [sockaddr_in; NUM_RCVMMSGS] = unsafe { mem::zeroed() };
-    let addrlen = mem::size_of_val(&addr) as socklen_t;
+    let mut hdrs: [mmsghdr; MAX_RCVMMSGS] = unsafe { mem::zeroed() };
+    let mut iovs: [iovec; MAX_RCVMMSGS] = unsafe { mem::zeroed() };
+    let mut addr: [sockaddr_in; MAX_RCVMMSGS] = unsafe { mem::zeroed() };
+    let addrlen = mem::size_of::<sockaddr_in>() as socklen_t;
+    // Room for one control message with the receive timestamp per packet. u64 words keep the
+    // control messages aligned.
+    let mut control = [0u64; CONTROL_WORDS * MAX_RCVMMSGS];
+    let control_len = if timestamps.is_some() {
+        unsafe { CMSG_SPACE(mem::size_of::<timespec>() as u32) as usize }
+    } else {
+        0
+    };
+    debug_assert!(control_len <= CONTROL_WORDS * mem::size_of::<u64>());
 
-    let sock_fd = sock.as_raw_fd();
+    let count = cmp::min(MAX_RCVMMSGS, packets.len());
 
-    let count = cmp::min(iovs.len(), packets.len());
+    let sock_fd = sock.as_raw_fd();
 
     for i in 0..count {
         iovs[i].iov_base = packets[i].data.as_mut_ptr() as *mut c_void;
@@ -60,6 +111,10 @@ pub fn recv_mmsg(sock: &UdpSocket, packets: &mut [Packet]) -> io::Result<(usize,
         hdrs[i].msg_hdr.msg_namelen = addrlen;
         hdrs[i].msg_hdr.msg_iov = &mut iovs[i];
         hdrs[i].msg_hdr.msg_iovlen = 1;
+        if control_len > 0 {
+            hdrs[i].msg_hdr.msg_control = control[i * CONTROL_WORDS..].as_mut_ptr() as *mut _;
+            hdrs[i].msg_hdr.msg_controllen = control_len as _;
+        }
     }
     let mut ts = timespec {
         tv_sec: 1,
@@ -67,24 +122,56 @@ pub fn recv_mmsg(sock: &UdpSocket, packets: &mut [Packet]) -> io::Result<(usize,
     };
 
     let mut total_size = 0;
-    let npkts =
-        match unsafe { recvmmsg(sock_fd, &mut hdrs[0], count as u32, MSG_WAITFORONE, &mut ts) } {
-            -1 => return Err(io::Error::last_os_error()),
-            n => {
-                for i in 0..n as usize {
-                    let mut p = &mut packets[i];
-                    p.meta.size = hdrs[i].msg_len as usize;
-                    total_size += p.meta.size;
-                    let inet_addr = InetAddr::V4(addr[i]);
-                    p.meta.set_addr(&inet_addr.to_std());
-                }
-                n as usize
+    let npkts = match unsafe {
+        recvmmsg(
+            sock_fd,
+            hdrs.as_mut_ptr(),
+            count as u32,
+            MSG_WAITFORONE,
+            &mut ts,
+        )
+    } {
+        -1 => return Err(io::Error::last_os_error()),
+        n => {
+            for i in 0..n as usize {
+                let p = &mut packets[i];
+                p.meta.size = hdrs[i].msg_len as usize;
+                total_size += p.meta.size;
+                let inet_addr = InetAddr::V4(addr[i]);
+                p.meta.set_addr(&inet_addr.to_std());
             }
-        };
+            n as usize
+        }
+    };
+    if let Some(timestamps) = timestamps {
+        timestamps.clear();
+        timestamps.extend(
+            hdrs[..npkts]
+                .iter()
+                .map(|hdr| unsafe { receive_timestamp(&hdr.msg_hdr) }),
+        );
+    }
 
     Ok((total_size, npkts))
 }
 
+/// Kernel receive time of a message, from its `SCM_TIMESTAMPNS` control message
+#[cfg(target_os = "linux")]
+unsafe fn receive_timestamp(msg_hdr: &libc::msghdr) -> Option<SystemTime> {
+    use libc::{timespec, CMSG_DATA, CMSG_FIRSTHDR, CMSG_NXTHDR, SCM_TIMESTAMPNS, SOL_SOCKET};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let mut cmsg = CMSG_FIRSTHDR(msg_hdr);
+    while !cmsg.is_null() {
+        if (*cmsg).cmsg_level == SOL_SOCKET && (*cmsg).cmsg_type == SCM_TIMESTAMPNS {
+            let ts = std::ptr::read_unaligned(CMSG_DATA(cmsg) as *const timespec);
+            return Some(UNIX_EPOCH + Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32));
+        }
+        cmsg = CMSG_NXTHDR(msg_hdr, cmsg);
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use crate::packet::PACKET_DATA_SIZE;
@@ -212,4 +299,32 @@ mod tests {
             assert_eq!(packet.meta.addr(), saddr2);
         }
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    pub fn test_recv_mmsg_with_timestamps() {
+        let reader = UdpSocket::bind("127.0.0.1:0").expect("bind");
+        let addr = reader.local_addr().unwrap();
+        crate::socket::set_receive_timestamps(&reader).unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").expect("bind");
+        let sent = TEST_NUM_MSGS;
+        let before = std::time::SystemTime::now();
+        for _ in 0..sent {
+            let data = [0; 64];
+            sender.send_to(&data[..], addr).unwrap();
+        }
+
+        let mut packets = vec![Packet::default(); TEST_NUM_MSGS];
+        let mut timestamps = vec![];
+        let recv = recv_mmsg_with_timestamps(&reader, &mut packets[..], &mut timestamps)
+            .unwrap()
+            .1;
+        assert_eq!(sent, recv);
+        assert_eq!(timestamps.len(), recv);
+        for timestamp in timestamps {
+            let timestamp = timestamp.unwrap();
+            assert!(timestamp >= before);
+            assert!(timestamp <= std::time::SystemTime::now());
+        }
+    }
 }
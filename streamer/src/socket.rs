@@ -1,4 +1,7 @@
-use std::net::SocketAddr;
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+};
 
 // TODO: remove these once IpAddr::is_global is stable.
 
@@ -26,3 +29,58 @@ pub fn is_global(addr: &SocketAddr) -> bool {
         }
     }
 }
+
+/// Sets `SO_BUSY_POLL` on `socket`, so that blocking receives busy-poll the device queue for up
+/// to `busy_poll_us` microseconds before sleeping. Raising it above `net.core.busy_read` needs
+/// `CAP_NET_ADMIN`.
+#[cfg(target_os = "linux")]
+pub fn set_busy_poll(socket: &UdpSocket, busy_poll_us: u32) -> io::Result<()> {
+    set_socket_option(socket, libc::SO_BUSY_POLL, busy_poll_us as libc::c_int)
+}
+
+/// Enables `SO_TIMESTAMPNS` on `socket`, so that the kernel records the time each packet was
+/// received, see `recvmmsg::recv_mmsg_with_timestamps`
+#[cfg(target_os = "linux")]
+pub fn set_receive_timestamps(socket: &UdpSocket) -> io::Result<()> {
+    set_socket_option(socket, libc::SO_TIMESTAMPNS, 1)
+}
+
+#[cfg(target_os = "linux")]
+fn set_socket_option(
+    socket: &UdpSocket,
+    option: libc::c_int,
+    value: libc::c_int,
+) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            option,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_busy_poll(_socket: &UdpSocket, _busy_poll_us: u32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "SO_BUSY_POLL is only supported on Linux",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_receive_timestamps(_socket: &UdpSocket) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "SO_TIMESTAMPNS is only supported on Linux",
+    ))
+}
@@ -1,10 +1,13 @@
 //! The `streamer` module defines a set of services for efficiently pulling data from UDP sockets.
 //!
 
-use crate::packet::{self, send_to, Packets, PacketsRecycler, PACKETS_PER_BATCH};
-use crate::recvmmsg::NUM_RCVMMSGS;
+use crate::packet::{self, send_to, Packets, PacketsRecycler, ReceiveLatency, PACKETS_PER_BATCH};
+use crate::socket::{set_busy_poll, set_receive_timestamps};
+use solana_sdk::pubkey::Pubkey;
 use solana_sdk::timing::{duration_as_ms, timestamp};
-use std::net::UdpSocket;
+use std::collections::HashMap;
+use std::net::{IpAddr, UdpSocket};
+use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError, SendError, Sender};
 use std::sync::Arc;
@@ -15,6 +18,15 @@ use thiserror::Error;
 pub type PacketReceiver = Receiver<Packets>;
 pub type PacketSender = Sender<Packets>;
 
+/// Stakes of the nodes in the current epoch, by identity and by the IP address of their TPU,
+/// used to give staked senders a share of the TPU in proportion to their stake
+#[derive(Default, Debug)]
+pub struct StakedNodes {
+    pub total_stake: u64,
+    pub pubkey_stake_map: HashMap<Pubkey, u64>,
+    pub ip_stake_map: HashMap<IpAddr, u64>,
+}
+
 #[derive(Error, Debug)]
 pub enum StreamerError {
     #[error("I/O error")]
@@ -29,7 +41,124 @@ pub enum StreamerError {
 
 pub type Result<T> = std::result::Result<T, StreamerError>;
 
-fn recv_loop(
+/// Tuning of the threads that receive packets from a stage's sockets
+#[derive(Clone, Debug, PartialEq)]
+pub struct PacketReceiverConfig {
+    /// Cores the receive threads are pinned to, unpinned if empty
+    pub cores: Vec<usize>,
+    /// `SO_BUSY_POLL` microseconds, see `socket::set_busy_poll`
+    pub busy_poll_us: Option<u32>,
+    /// Packets read into one batch, up to `MAX_RCVMMSGS` of them per `recvmmsg` call
+    pub batch_size: usize,
+    /// Record kernel receive timestamps to report how long packets wait in the socket buffer
+    pub receive_timestamps: bool,
+}
+
+impl Default for PacketReceiverConfig {
+    fn default() -> Self {
+        Self {
+            cores: vec![],
+            busy_poll_us: None,
+            batch_size: PACKETS_PER_BATCH,
+            receive_timestamps: false,
+        }
+    }
+}
+
+/// Parses comma separated options, e.g. `cores=2-3,busy_poll_us=50,batch_size=256,timestamps`
+impl FromStr for PacketReceiverConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        fn parse<T: FromStr>(key: &str, value: Option<&str>) -> std::result::Result<T, String> {
+            value
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| format!("invalid value for {}", key))
+        }
+
+        let mut config = Self::default();
+        for option in s
+            .split(',')
+            .map(str::trim)
+            .filter(|option| !option.is_empty())
+        {
+            let mut key_value = option.splitn(2, '=');
+            let key = key_value.next().unwrap();
+            let value = key_value.next();
+            match key {
+                "cores" => {
+                    let value = value.ok_or_else(|| "invalid value for cores".to_string())?;
+                    let mut range = value.splitn(2, '-');
+                    let first: usize = parse(key, range.next())?;
+                    let last: usize = match range.next() {
+                        Some(last) => parse(key, Some(last))?,
+                        None => first,
+                    };
+                    if last < first {
+                        return Err("invalid value for cores".to_string());
+                    }
+                    config.cores = (first..=last).collect();
+                }
+                "busy_poll_us" => config.busy_poll_us = Some(parse(key, value)?),
+                "batch_size" => {
+                    config.batch_size = parse(key, value)?;
+                    if config.batch_size == 0 {
+                        return Err("batch_size must be positive".to_string());
+                    }
+                }
+                "timestamps" => config.receive_timestamps = true,
+                _ => return Err(format!("unknown option {}", key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl PacketReceiverConfig {
+    /// Applies the socket options to `sock`. Failures are logged rather than returned, since the
+    /// receiver works without them.
+    fn configure_socket(&self, sock: &UdpSocket, name: &str) {
+        if let Some(busy_poll_us) = self.busy_poll_us {
+            if let Err(err) = set_busy_poll(sock, busy_poll_us) {
+                warn!("{}: failed to set SO_BUSY_POLL: {}", name, err);
+            }
+        }
+        if self.receive_timestamps {
+            if let Err(err) = set_receive_timestamps(sock) {
+                warn!("{}: failed to set SO_TIMESTAMPNS: {}", name, err);
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn pin_current_thread(&self, name: &str) {
+        use nix::{sched::CpuSet, unistd::Pid};
+
+        if self.cores.is_empty() {
+            return;
+        }
+        let mut cpu_set = CpuSet::new();
+        for core in &self.cores {
+            if let Err(err) = cpu_set.set(*core) {
+                warn!("{}: invalid core {}: {}", name, core, err);
+                return;
+            }
+        }
+        if let Err(err) = nix::sched::sched_setaffinity(Pid::from_raw(0), &cpu_set) {
+            warn!("{}: failed to pin to cores {:?}: {}", name, self.cores, err);
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn pin_current_thread(&self, name: &str) {
+        if !self.cores.is_empty() {
+            warn!("{}: pinning to cores is only supported on Linux", name);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn recv_loop<F: FnMut(&mut Packets)>(
     sock: &UdpSocket,
     exit: Arc<AtomicBool>,
     channel: &PacketSender,
@@ -37,16 +166,19 @@ fn recv_loop(
     name: &'static str,
     coalesce_ms: u64,
     use_pinned_memory: bool,
+    config: &PacketReceiverConfig,
+    filter: &mut F,
 ) -> Result<()> {
     let mut recv_count = 0;
     let mut call_count = 0;
     let mut now = Instant::now();
     let mut num_max_received = 0; // Number of times maximum packets were received
+    let mut latency = ReceiveLatency::default();
     loop {
         let mut msgs = if use_pinned_memory {
-            Packets::new_with_recycler(recycler.clone(), PACKETS_PER_BATCH, name)
+            Packets::new_with_recycler(recycler.clone(), config.batch_size, name)
         } else {
-            Packets::with_capacity(PACKETS_PER_BATCH)
+            Packets::with_capacity(config.batch_size)
         };
         loop {
             // Check for exit signal, even if socket is busy
@@ -54,13 +186,25 @@ fn recv_loop(
             if exit.load(Ordering::Relaxed) {
                 return Ok(());
             }
-            if let Ok(len) = packet::recv_from(&mut msgs, sock, coalesce_ms) {
-                if len == NUM_RCVMMSGS {
+            let latency = if config.receive_timestamps {
+                Some(&mut latency)
+            } else {
+                None
+            };
+            if let Ok(len) = packet::recv_from_with_config(
+                &mut msgs,
+                sock,
+                coalesce_ms,
+                config.batch_size,
+                latency,
+            ) {
+                if len == config.batch_size {
                     num_max_received += 1;
                 }
                 recv_count += len;
                 call_count += 1;
                 if len > 0 {
+                    filter(&mut msgs);
                     channel.send(msgs)?;
                 }
                 break;
@@ -73,10 +217,13 @@ fn recv_loop(
                 ("call_count", i64::from(call_count), i64),
                 ("elapsed", now.elapsed().as_millis() as i64, i64),
                 ("max_received", i64::from(num_max_received), i64),
+                ("receive_latency_mean_us", latency.mean_us() as i64, i64),
+                ("receive_latency_max_us", latency.max_us as i64, i64),
             );
             recv_count = 0;
             call_count = 0;
             num_max_received = 0;
+            latency = ReceiveLatency::default();
         }
         now = Instant::now();
     }
@@ -90,15 +237,48 @@ pub fn receiver(
     name: &'static str,
     coalesce_ms: u64,
     use_pinned_memory: bool,
+    config: PacketReceiverConfig,
 ) -> JoinHandle<()> {
+    receiver_with_filter(
+        sock,
+        exit,
+        packet_sender,
+        recycler,
+        name,
+        coalesce_ms,
+        use_pinned_memory,
+        config,
+        |_| (),
+    )
+}
+
+/// Like `receiver`, but runs `filter` on every batch on the receiver thread before it is sent,
+/// e.g. to mark packets as discarded
+#[allow(clippy::too_many_arguments)]
+pub fn receiver_with_filter<F>(
+    sock: Arc<UdpSocket>,
+    exit: &Arc<AtomicBool>,
+    packet_sender: PacketSender,
+    recycler: PacketsRecycler,
+    name: &'static str,
+    coalesce_ms: u64,
+    use_pinned_memory: bool,
+    config: PacketReceiverConfig,
+    mut filter: F,
+) -> JoinHandle<()>
+where
+    F: FnMut(&mut Packets) + Send + 'static,
+{
     let res = sock.set_read_timeout(Some(Duration::new(1, 0)));
     if res.is_err() {
         panic!("streamer::receiver set_read_timeout error");
     }
+    config.configure_socket(&sock, name);
     let exit = exit.clone();
     Builder::new()
         .name("solana-receiver".to_string())
         .spawn(move || {
+            config.pin_current_thread(name);
             let _ = recv_loop(
                 &sock,
                 exit,
@@ -107,6 +287,8 @@ pub fn receiver(
                 name,
                 coalesce_ms,
                 use_pinned_memory,
+                &config,
+                &mut filter,
             );
         })
         .unwrap()
@@ -219,6 +401,7 @@ mod test {
             "test",
             1,
             true,
+            PacketReceiverConfig::default(),
         );
         let t_responder = {
             let (s_responder, r_responder) = channel();
@@ -244,4 +427,70 @@ mod test {
         t_receiver.join().expect("join");
         t_responder.join().expect("join");
     }
+
+    #[test]
+    fn test_packet_receiver_config_from_str() {
+        assert_eq!(
+            "".parse::<PacketReceiverConfig>(),
+            Ok(PacketReceiverConfig::default())
+        );
+        assert_eq!(
+            "cores=2-4, busy_poll_us=50,batch_size=512,timestamps".parse(),
+            Ok(PacketReceiverConfig {
+                cores: vec![2, 3, 4],
+                busy_poll_us: Some(50),
+                batch_size: 512,
+                receive_timestamps: true,
+            })
+        );
+        assert_eq!(
+            "cores=7".parse::<PacketReceiverConfig>().unwrap().cores,
+            vec![7]
+        );
+        assert!("cores=4-2".parse::<PacketReceiverConfig>().is_err());
+        assert!("cores".parse::<PacketReceiverConfig>().is_err());
+        assert!("batch_size=0".parse::<PacketReceiverConfig>().is_err());
+        assert!("busy_poll_us=x".parse::<PacketReceiverConfig>().is_err());
+        assert!("unknown".parse::<PacketReceiverConfig>().is_err());
+    }
+
+    #[test]
+    fn streamer_receive_large_batches() {
+        let read = UdpSocket::bind("127.0.0.1:0").expect("bind");
+        let addr = read.local_addr().unwrap();
+        let send = UdpSocket::bind("127.0.0.1:0").expect("bind");
+        let exit = Arc::new(AtomicBool::new(false));
+        let (s_reader, r_reader) = channel();
+        let config = PacketReceiverConfig {
+            batch_size: PACKETS_PER_BATCH + 22,
+            receive_timestamps: true,
+            ..PacketReceiverConfig::default()
+        };
+        // Few enough not to overflow the default socket receive buffer
+        let num_packets = PACKETS_PER_BATCH + 32;
+        for _ in 0..num_packets {
+            send.send_to(&[0u8; 8], addr).unwrap();
+        }
+        let t_receiver = receiver(
+            Arc::new(read),
+            &exit,
+            s_reader,
+            Recycler::default(),
+            "test",
+            1_000,
+            false,
+            config,
+        );
+
+        let mut num = num_packets;
+        let mut max_batch_len = 0;
+        while num > 0 {
+            let msgs = r_reader.recv_timeout(Duration::new(5, 0)).unwrap();
+            max_batch_len = max_batch_len.max(msgs.packets.len());
+            num -= msgs.packets.len();
+        }
+        assert_eq!(max_batch_len, PACKETS_PER_BATCH + 22);
+        exit.store(true, Ordering::Relaxed);
+        t_receiver.join().expect("join");
+    }
 }
@@ -0,0 +1,79 @@
+//! The `tls_certificates` module creates the self-signed certificates QUIC peers present to each
+//! other, and recovers the peer identity from them.
+use {
+    rcgen::{CertificateParams, DistinguishedName, DnType, RcgenError, SanType},
+    solana_sdk::{pubkey::Pubkey, signature::Keypair},
+    std::{convert::TryFrom, net::IpAddr},
+    x509_parser::{prelude::*, public_key::PublicKey},
+};
+
+/// Creates a self-signed certificate whose key is the ed25519 `keypair`, so that the peer can
+/// tell who is on the other end of the connection
+pub fn new_self_signed_tls_certificate(
+    keypair: &Keypair,
+    san: IpAddr,
+) -> Result<(rustls::Certificate, rustls::PrivateKey), RcgenError> {
+    // Unfortunately, rcgen does not accept a "raw" Ed25519 key.
+    // We have to convert it to DER and pass it to the library.
+
+    // Convert private key into PKCS#8 v1 object.
+    // RFC 8410, Section 7: Private Key Format
+    // https://datatracker.ietf.org/doc/html/rfc8410#section-7
+    //
+    // The hardcoded prefix decodes to the following ASN.1 structure:
+    //
+    //   PrivateKeyInfo SEQUENCE (3 elem)
+    //     version Version INTEGER 0
+    //     privateKeyAlgorithm AlgorithmIdentifier SEQUENCE (1 elem)
+    //       algorithm OBJECT IDENTIFIER 1.3.101.112 curveEd25519 (EdDSA 25519 signature algorithm)
+    //     privateKey PrivateKey OCTET STRING (34 byte)
+    const PKCS8_PREFIX: [u8; 16] = [
+        0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04,
+        0x20,
+    ];
+    let mut key_pkcs8_der = Vec::<u8>::with_capacity(PKCS8_PREFIX.len() + 32);
+    key_pkcs8_der.extend_from_slice(&PKCS8_PREFIX);
+    key_pkcs8_der.extend_from_slice(keypair.secret().as_bytes());
+
+    let rcgen_keypair = rcgen::KeyPair::from_der(&key_pkcs8_der)?;
+
+    let mut cert_params = CertificateParams::default();
+    cert_params.subject_alt_names = vec![SanType::IpAddress(san)];
+    cert_params.alg = &rcgen::PKCS_ED25519;
+    cert_params.key_pair = Some(rcgen_keypair);
+    cert_params.distinguished_name = DistinguishedName::new();
+    cert_params
+        .distinguished_name
+        .push(DnType::CommonName, "Solana node");
+
+    let cert = rcgen::Certificate::from_params(cert_params)?;
+    let cert_der = cert.serialize_der()?;
+    let priv_key = cert.serialize_private_key_der();
+    Ok((rustls::Certificate(cert_der), rustls::PrivateKey(priv_key)))
+}
+
+/// Returns the ed25519 public key of a certificate created by
+/// `new_self_signed_tls_certificate()`
+pub fn get_pubkey_from_tls_certificate(der_cert: &rustls::Certificate) -> Option<Pubkey> {
+    let (_, cert) = X509Certificate::from_der(der_cert.as_ref()).ok()?;
+    match cert.public_key().parsed().ok()? {
+        PublicKey::Unknown(key) => <[u8; 32]>::try_from(key).ok().map(Pubkey::new_from_array),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::signer::Signer, std::net::Ipv4Addr};
+
+    #[test]
+    fn test_generate_tls_certificate() {
+        let keypair = Keypair::new();
+        let (cert, _) =
+            new_self_signed_tls_certificate(&keypair, IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        assert_eq!(
+            get_pubkey_from_tls_certificate(&cert),
+            Some(keypair.pubkey())
+        );
+    }
+}
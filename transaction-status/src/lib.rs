@@ -700,6 +700,124 @@ pub struct TransactionByAddrInfo {
     pub index: u32,                    // Where the transaction is located in the block
     pub memo: Option<String>,          // Transaction memo
     pub block_time: Option<UnixTimestamp>,
+    #[serde(default)]
+    pub program_ids: Vec<Pubkey>, // Programs invoked by the transaction, if recorded
+}
+
+/// Programs a transaction invokes, either directly or through cross-program invocations
+pub fn invoked_program_ids<'a>(
+    message: &'a Message,
+    inner_instructions: Option<&[InnerInstructions]>,
+) -> Vec<&'a Pubkey> {
+    let mut program_ids = message.program_ids();
+    for instruction in inner_instructions
+        .into_iter()
+        .flatten()
+        .flat_map(|inner_instructions| &inner_instructions.instructions)
+    {
+        if let Some(program_id) = message
+            .account_keys
+            .get(instruction.program_id_index as usize)
+        {
+            if !program_ids.contains(&program_id) {
+                program_ids.push(program_id);
+            }
+        }
+    }
+    program_ids
+}
+
+/// Most address signature entries a filtered query examines, so that a filter matching few
+/// transactions can't make a query walk the entire history of an address
+pub const MAX_ADDRESS_SIGNATURES_SCANNED: usize = 10_000;
+
+/// Signatures found for an address, newest first
+#[derive(Clone, Debug, PartialEq)]
+pub struct AddressSignatures<T> {
+    pub signatures: Vec<T>,
+    /// Set when a filtered query examined `MAX_ADDRESS_SIGNATURES_SCANNED` entries before
+    /// reaching its limit: the oldest signature examined, to pass as `before` to resume the search
+    pub resume_before: Option<Signature>,
+}
+
+impl<T> Default for AddressSignatures<T> {
+    fn default() -> Self {
+        Self {
+            signatures: vec![],
+            resume_before: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionStatusFilter {
+    Succeeded,
+    Failed,
+}
+
+/// Narrows down the transactions listed for an address, on top of the `before`/`until` bounds
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AddressSignatureFilter {
+    pub status: Option<TransactionStatusFilter>,
+    /// Inclusive lower bound of the block time
+    pub min_block_time: Option<UnixTimestamp>,
+    /// Inclusive upper bound of the block time
+    pub max_block_time: Option<UnixTimestamp>,
+    /// If not empty, only transactions that invoke at least one of these programs
+    pub program_ids: Vec<Pubkey>,
+}
+
+impl AddressSignatureFilter {
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none() && !self.filters_block_time() && !self.filters_program_ids()
+    }
+
+    pub fn filters_block_time(&self) -> bool {
+        self.min_block_time.is_some() || self.max_block_time.is_some()
+    }
+
+    pub fn filters_program_ids(&self) -> bool {
+        !self.program_ids.is_empty()
+    }
+
+    pub fn matches_status(&self, failed: bool) -> bool {
+        match self.status {
+            None => true,
+            Some(TransactionStatusFilter::Succeeded) => !failed,
+            Some(TransactionStatusFilter::Failed) => failed,
+        }
+    }
+
+    /// Transactions of unknown block time only match if no block time bound is set
+    pub fn matches_block_time(&self, block_time: Option<UnixTimestamp>) -> bool {
+        if !self.filters_block_time() {
+            return true;
+        }
+        block_time.map_or(false, |block_time| {
+            self.min_block_time.map_or(true, |min| block_time >= min)
+                && self.max_block_time.map_or(true, |max| block_time <= max)
+        })
+    }
+
+    /// Whether `block_time` is older than the lower bound. As transactions are listed from the
+    /// newest to the oldest, none of those listed after it can match either
+    pub fn precedes_min_block_time(&self, block_time: Option<UnixTimestamp>) -> bool {
+        matches!(
+            (self.min_block_time, block_time),
+            (Some(min_block_time), Some(block_time)) if block_time < min_block_time
+        )
+    }
+
+    pub fn matches_program_ids<'a>(
+        &self,
+        program_ids: impl IntoIterator<Item = &'a Pubkey>,
+    ) -> bool {
+        !self.filters_program_ids()
+            || program_ids
+                .into_iter()
+                .any(|program_id| self.program_ids.contains(program_id))
+    }
 }
 
 #[cfg(test)]
@@ -798,4 +916,71 @@ mod test {
         };
         assert!(status.satisfies_commitment(CommitmentConfig::confirmed()));
     }
+
+    #[test]
+    fn test_address_signature_filter() {
+        let program_id = Pubkey::new_unique();
+        let other_program_id = Pubkey::new_unique();
+
+        let filter = AddressSignatureFilter::default();
+        assert!(filter.is_empty());
+        assert!(filter.matches_status(true));
+        assert!(filter.matches_block_time(None));
+        assert!(filter.matches_program_ids(&[]));
+
+        let filter = AddressSignatureFilter {
+            status: Some(TransactionStatusFilter::Failed),
+            min_block_time: Some(10),
+            max_block_time: Some(20),
+            program_ids: vec![program_id],
+        };
+        assert!(!filter.is_empty());
+        assert!(filter.matches_status(true));
+        assert!(!filter.matches_status(false));
+        assert!(!filter.matches_block_time(None));
+        assert!(!filter.matches_block_time(Some(9)));
+        assert!(filter.matches_block_time(Some(10)));
+        assert!(filter.matches_block_time(Some(20)));
+        assert!(!filter.matches_block_time(Some(21)));
+        assert!(filter.matches_program_ids(&[other_program_id, program_id]));
+        assert!(!filter.matches_program_ids(&[other_program_id]));
+        assert!(filter.precedes_min_block_time(Some(9)));
+        assert!(!filter.precedes_min_block_time(Some(10)));
+        assert!(!filter.precedes_min_block_time(None));
+
+        let filter = AddressSignatureFilter {
+            status: Some(TransactionStatusFilter::Succeeded),
+            max_block_time: Some(20),
+            ..AddressSignatureFilter::default()
+        };
+        assert!(filter.matches_status(false));
+        assert!(filter.matches_block_time(Some(i64::MIN)));
+        assert!(!filter.precedes_min_block_time(Some(i64::MIN)));
+        assert!(filter.matches_program_ids(&[]));
+    }
+
+    #[test]
+    fn test_invoked_program_ids() {
+        let program_id = Pubkey::new_unique();
+        let invoked_program_id = Pubkey::new_unique();
+        let message = Message {
+            account_keys: vec![Pubkey::new_unique(), program_id, invoked_program_id],
+            instructions: vec![CompiledInstruction::new(1, &(), vec![0])],
+            ..Message::default()
+        };
+        assert_eq!(invoked_program_ids(&message, None), vec![&program_id]);
+
+        let inner_instructions = vec![InnerInstructions {
+            index: 0,
+            instructions: vec![
+                CompiledInstruction::new(2, &(), vec![0]),
+                CompiledInstruction::new(1, &(), vec![0]),
+                CompiledInstruction::new(2, &(), vec![0]),
+            ],
+        }];
+        assert_eq!(
+            invoked_program_ids(&message, Some(&inner_instructions)),
+            vec![&program_id, &invoked_program_id]
+        );
+    }
 }
@@ -9,7 +9,10 @@ use crate::{
 use inflector::Inflector;
 use serde_json::Value;
 use solana_account_decoder::parse_token::spl_token_id_v2_0;
-use solana_sdk::{instruction::CompiledInstruction, pubkey::Pubkey, stake, system_program};
+use solana_sdk::{
+    instruction::CompiledInstruction, pubkey::Pubkey, spl_token_ids::is_known_spl_token_id, stake,
+    system_program,
+};
 use std::{collections::HashMap, str::from_utf8};
 use thiserror::Error;
 
@@ -94,9 +97,11 @@ pub fn parse(
     instruction: &CompiledInstruction,
     account_keys: &[Pubkey],
 ) -> Result<ParsedInstruction, ParseInstructionError> {
-    let program_name = PARSABLE_PROGRAM_IDS
-        .get(program_id)
-        .ok_or(ParseInstructionError::ProgramNotParsable)?;
+    let program_name = match PARSABLE_PROGRAM_IDS.get(program_id) {
+        Some(program_name) => program_name,
+        None if is_known_spl_token_id(program_id) => &ParsableProgram::SplToken,
+        None => return Err(ParseInstructionError::ProgramNotParsable),
+    };
     let parsed_json = match program_name {
         ParsableProgram::SplAssociatedTokenAccount => {
             serde_json::to_value(parse_associated_token(instruction, account_keys)?)?
@@ -1,9 +1,9 @@
 use crate::TransactionTokenBalance;
 use solana_account_decoder::parse_token::{
-    spl_token_id_v2_0, spl_token_v2_0_native_mint, token_amount_to_ui_amount, UiTokenAmount,
+    spl_token_v2_0_native_mint, token_amount_to_ui_amount, UiTokenAmount,
 };
 use solana_runtime::{bank::Bank, transaction_batch::TransactionBatch};
-use solana_sdk::{account::ReadableAccount, pubkey::Pubkey};
+use solana_sdk::{account::ReadableAccount, pubkey::Pubkey, spl_token_ids::is_known_spl_token_id};
 use spl_token_v2_0::{
     solana_program::program_pack::Pack,
     state::{Account as TokenAccount, Mint},
@@ -30,10 +30,6 @@ impl TransactionTokenBalancesSet {
     }
 }
 
-fn is_token_program(program_id: &Pubkey) -> bool {
-    program_id == &spl_token_id_v2_0()
-}
-
 fn get_mint_decimals(bank: &Bank, mint: &Pubkey) -> Option<u8> {
     if mint == &spl_token_v2_0_native_mint() {
         Some(spl_token_v2_0::native_mint::DECIMALS)
@@ -57,12 +53,12 @@ pub fn collect_token_balances(
 
     for transaction in batch.transactions_iter() {
         let account_keys = &transaction.message.account_keys;
-        let has_token_program = account_keys.iter().any(|p| is_token_program(p));
+        let has_token_program = account_keys.iter().any(is_known_spl_token_id);
 
         let mut transaction_balances: Vec<TransactionTokenBalance> = vec![];
         if has_token_program {
             for (index, account_id) in account_keys.iter().enumerate() {
-                if is_token_program(account_id)
+                if is_known_spl_token_id(account_id)
                     || transaction.message.program_ids().contains(&account_id)
                 {
                     continue;
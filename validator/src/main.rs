@@ -16,11 +16,12 @@ use {
         keypair::SKIP_SEED_PHRASE_VALIDATION_ARG,
     },
     solana_client::{
-        rpc_client::RpcClient, rpc_config::RpcLeaderScheduleConfig,
+        connection_cache, rpc_client::RpcClient, rpc_config::RpcLeaderScheduleConfig,
         rpc_request::MAX_MULTIPLE_ACCOUNTS,
     },
     solana_core::{
         ledger_cleanup_service::{DEFAULT_MAX_LEDGER_SHREDS, DEFAULT_MIN_MAX_LEDGER_SHREDS},
+        packet_forwarder::ForwardingConfig,
         tpu::DEFAULT_TPU_COALESCE_MS,
         validator::{
             is_snapshot_config_invalid, Validator, ValidatorConfig, ValidatorStartProgress,
@@ -33,9 +34,12 @@ use {
         contact_info::ContactInfo,
         gossip_service::GossipService,
     },
-    solana_ledger::blockstore_db::BlockstoreRecoveryMode,
+    solana_ledger::{
+        blockstore::blockstore_archive::TransactionArchiveFilter,
+        blockstore_db::{BlockstoreRecoveryMode, BlockstoreRocksFifoOptions, ShredStorageType},
+    },
     solana_perf::recycler::enable_recycler_warming,
-    solana_poh::poh_service,
+    solana_poh::{poh_recorder::EntryBatchConfig, poh_service},
     solana_rpc::{rpc::JsonRpcConfig, rpc_pubsub_service::PubSubConfig},
     solana_runtime::{
         accounts_db::{
@@ -57,7 +61,10 @@ use {
         hash::Hash,
         pubkey::Pubkey,
         signature::{Keypair, Signer},
+        spl_token_ids::{set_spl_token_ids, SplTokenIds},
     },
+    solana_send_transaction_service::send_transaction_service,
+    solana_streamer::streamer::PacketReceiverConfig,
     solana_validator::{
         admin_rpc_service, dashboard::Dashboard, new_spinner_progress_bar, println_name_value,
         redirect_stderr_to_file,
@@ -91,6 +98,12 @@ const INCLUDE_KEY: &str = "account-index-include-key";
 const DEFAULT_MIN_SNAPSHOT_DOWNLOAD_SPEED: u64 = 10485760;
 // The maximum times of snapshot download abort and retry
 const MAX_SNAPSHOT_DOWNLOAD_ABORT: u32 = 5;
+// The options shared by the --*-receiver-options arguments
+const PACKET_RECEIVER_OPTIONS_HELP: &str = "as comma separated options: \
+    cores=FIRST[-LAST] to pin the threads to cores, \
+    busy_poll_us=MICROSECS to set SO_BUSY_POLL on the sockets, \
+    batch_size=NUM packets to read per batch, \
+    timestamps to report how long packets wait in the socket buffers";
 
 fn monitor_validator(ledger_path: &Path) {
     let dashboard = Dashboard::new(ledger_path, None, None).unwrap_or_else(|err| {
@@ -386,6 +399,7 @@ fn start_gossip_node(
         gossip_socket,
         gossip_validators,
         should_check_duplicate_instance,
+        PacketReceiverConfig::default(),
         &gossip_exit_flag,
     );
     (cluster_info, gossip_exit_flag, gossip_service)
@@ -672,7 +686,11 @@ fn verify_reachable_ports(
     cluster_entrypoint: &ContactInfo,
     validator_config: &ValidatorConfig,
 ) -> bool {
-    let mut udp_sockets = vec![&node.sockets.gossip, &node.sockets.repair];
+    let mut udp_sockets = vec![
+        &node.sockets.gossip,
+        &node.sockets.repair,
+        &node.sockets.ancestor_hashes_requests,
+    ];
 
     if ContactInfo::is_valid_address(&node.info.serve_repair) {
         udp_sockets.push(&node.sockets.serve_repair);
@@ -1038,12 +1056,21 @@ pub fn main() {
         PubSubConfig::default().max_out_buffer_capacity.to_string();
     let default_rpc_pubsub_max_active_subscriptions =
         PubSubConfig::default().max_active_subscriptions.to_string();
-    let default_rpc_send_transaction_retry_ms = ValidatorConfig::default()
-        .send_transaction_retry_ms
+    let default_send_transaction_service_config = send_transaction_service::Config::default();
+    let default_rpc_send_transaction_retry_ms = default_send_transaction_service_config
+        .retry_rate_ms
+        .to_string();
+    let default_rpc_send_transaction_leader_forward_count = default_send_transaction_service_config
+        .leader_forward_count
         .to_string();
-    let default_rpc_send_transaction_leader_forward_count = ValidatorConfig::default()
-        .send_transaction_leader_forward_count
+    let default_rpc_send_transaction_service_max_retries = default_send_transaction_service_config
+        .service_max_retries
         .to_string();
+    let default_forwarding_config = ForwardingConfig::default();
+    let default_forward_num_leaders = default_forwarding_config.num_leaders.to_string();
+    let default_forward_min_fee_lamports = default_forwarding_config.min_fee_lamports.to_string();
+    let default_forward_max_bytes_per_second =
+        default_forwarding_config.max_bytes_per_second.to_string();
     let default_rpc_threads = num_cpus::get().to_string();
     let default_max_snapshot_to_retain = &DEFAULT_MAX_FULL_SNAPSHOT_ARCHIVES_TO_RETAIN.to_string();
     let default_min_snapshot_download_speed = &DEFAULT_MIN_SNAPSHOT_DOWNLOAD_SPEED.to_string();
@@ -1051,6 +1078,22 @@ pub fn main() {
     let default_accounts_shrink_optimize_total_space =
         &DEFAULT_ACCOUNTS_SHRINK_OPTIMIZE_TOTAL_SPACE.to_string();
     let default_accounts_shrink_ratio = &DEFAULT_ACCOUNTS_SHRINK_RATIO.to_string();
+    let gossip_receiver_options_help = &format!(
+        "Tuning of the gossip receive threads, {}",
+        PACKET_RECEIVER_OPTIONS_HELP
+    );
+    let tvu_receiver_options_help = &format!(
+        "Tuning of the TVU shred receive threads, {}",
+        PACKET_RECEIVER_OPTIONS_HELP
+    );
+    let repair_receiver_options_help = &format!(
+        "Tuning of the repair receive threads, {}",
+        PACKET_RECEIVER_OPTIONS_HELP
+    );
+    let tpu_receiver_options_help = &format!(
+        "Tuning of the TPU transaction receive threads, {}",
+        PACKET_RECEIVER_OPTIONS_HELP
+    );
 
     let matches = App::new(crate_name!()).about(crate_description!())
         .version(solana_version::version!())
@@ -1217,11 +1260,23 @@ pub fn main() {
                        as a fallback to local ledger data"),
         )
         .arg(
-            Arg::with_name("enable_bigtable_ledger_upload")
-                .long("enable-bigtable-ledger-upload")
+            Arg::with_name("enable_ledger_storage_upload")
+                .long("enable-ledger-storage-upload")
+                .alias("enable-bigtable-ledger-upload")
                 .requires("enable_rpc_transaction_history")
                 .takes_value(false)
-                .help("Upload new confirmed blocks into a BigTable instance"),
+                .help("Upload new confirmed blocks into the long-term ledger storage: \
+                       a BigTable instance, or the --local-ledger-storage directory"),
+        )
+        .arg(
+            Arg::with_name("local_ledger_storage")
+                .long("local-ledger-storage")
+                .value_name("DIR")
+                .takes_value(true)
+                .requires("enable_rpc_transaction_history")
+                .help("Fetch historical transaction info from a local storage directory \
+                       instead of a BigTable instance. With --enable-ledger-storage-upload, \
+                       new confirmed blocks are uploaded into this directory instead"),
         )
         .arg(
             Arg::with_name("enable_cpi_and_log_storage")
@@ -1231,6 +1286,32 @@ pub fn main() {
                 .help("Include CPI inner instructions and logs in the \
                         historical transaction info stored"),
         )
+        .arg(
+            Arg::with_name("archive_program_ids")
+                .long("archive-program-id")
+                .validator(is_pubkey)
+                .value_name("PROGRAM ID")
+                .multiple(true)
+                .takes_value(true)
+                .requires_all(&["enable_rpc_transaction_history", "limit_ledger_size"])
+                .help("Keep the rooted transactions invoking this program in a compact archive \
+                       when --limit-ledger-size purges their slots, and keep serving them over \
+                       JSON RPC. Not supported with --rocksdb-shred-compaction=fifo. \
+                       May be specified multiple times"),
+        )
+        .arg(
+            Arg::with_name("archive_accounts")
+                .long("archive-account")
+                .validator(is_pubkey)
+                .value_name("PUBKEY")
+                .multiple(true)
+                .takes_value(true)
+                .requires_all(&["enable_rpc_transaction_history", "limit_ledger_size"])
+                .help("Keep the rooted transactions referencing this account in a compact archive \
+                       when --limit-ledger-size purges their slots, and keep serving them over \
+                       JSON RPC. Not supported with --rocksdb-shred-compaction=fifo. \
+                       May be specified multiple times"),
+        )
         .arg(
             Arg::with_name("rpc_max_multiple_accounts")
                 .long("rpc-max-multiple-accounts")
@@ -1550,6 +1631,22 @@ pub fn main() {
                 .takes_value(true)
                 .help("Number of slots between compacting ledger"),
         )
+        .arg(
+            Arg::with_name("rocksdb_shred_compaction")
+                .long("rocksdb-shred-compaction")
+                .value_name("ROCKSDB_COMPACTION_STYLE")
+                .takes_value(true)
+                .possible_values(&["level", "fifo"])
+                .default_value("level")
+                .help("Controls how RocksDB compacts shreds. \
+                       *WARNING*: You will lose your ledger data when you switch between options \
+                       unless you first run `solana-ledger-tool migrate-shred-storage`. \
+                       Possible values are: \
+                       'level': stores shreds using RocksDB's default (level) compaction. \
+                       'fifo': stores shreds under RocksDB's FIFO compaction, which drops the \
+                       oldest shreds once --limit-ledger-size is reached. This requires \
+                       --limit-ledger-size."),
+        )
         .arg(
             Arg::with_name("tpu_coalesce_ms")
                 .long("tpu-coalesce-ms")
@@ -1558,6 +1655,81 @@ pub fn main() {
                 .validator(is_parsable::<u64>)
                 .help("Milliseconds to wait in the TPU receiver for packet coalescing."),
         )
+        .arg(
+            Arg::with_name("gossip_receiver_options")
+                .long("gossip-receiver-options")
+                .value_name("OPTIONS")
+                .takes_value(true)
+                .validator(is_parsable::<PacketReceiverConfig>)
+                .help(gossip_receiver_options_help),
+        )
+        .arg(
+            Arg::with_name("tvu_receiver_options")
+                .long("tvu-receiver-options")
+                .value_name("OPTIONS")
+                .takes_value(true)
+                .validator(is_parsable::<PacketReceiverConfig>)
+                .help(tvu_receiver_options_help),
+        )
+        .arg(
+            Arg::with_name("repair_receiver_options")
+                .long("repair-receiver-options")
+                .value_name("OPTIONS")
+                .takes_value(true)
+                .validator(is_parsable::<PacketReceiverConfig>)
+                .help(repair_receiver_options_help),
+        )
+        .arg(
+            Arg::with_name("tpu_receiver_options")
+                .long("tpu-receiver-options")
+                .value_name("OPTIONS")
+                .takes_value(true)
+                .validator(is_parsable::<PacketReceiverConfig>)
+                .help(tpu_receiver_options_help),
+        )
+        .arg(
+            Arg::with_name("tpu_use_quic")
+                .long("tpu-use-quic")
+                .takes_value(false)
+                .help("Also receive transactions over QUIC, on the TPU port plus 6, \
+                       in addition to UDP"),
+        )
+        .arg(
+            Arg::with_name("banking_stage_scheduler")
+                .long("banking-stage-scheduler")
+                .takes_value(false)
+                .help("Schedule transactions to the banking threads from a central thread \
+                       that hands each thread only batches whose account locks do not \
+                       conflict with the other batches in flight"),
+        )
+        .arg(
+            Arg::with_name("forward_num_leaders")
+                .long("forward-num-leaders")
+                .value_name("NUMBER")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .default_value(&default_forward_num_leaders)
+                .help("Number of distinct upcoming leaders buffered transactions are \
+                       forwarded to"),
+        )
+        .arg(
+            Arg::with_name("forward_min_fee_lamports")
+                .long("forward-min-fee-lamports")
+                .value_name("LAMPORTS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value(&default_forward_min_fee_lamports)
+                .help("Do not forward transactions paying a smaller fee than this"),
+        )
+        .arg(
+            Arg::with_name("forward_max_bytes_per_second")
+                .long("forward-max-bytes-per-second")
+                .value_name("BYTES")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .default_value(&default_forward_max_bytes_per_second)
+                .help("Maximum number of bytes of transactions forwarded per second"),
+        )
         .arg(
             Arg::with_name("rocksdb_max_compaction_jitter")
                 .long("rocksdb-max-compaction-jitter-slots")
@@ -1673,6 +1845,30 @@ pub fn main() {
                 .default_value(&default_rpc_send_transaction_leader_forward_count)
                 .help("The number of upcoming leaders to which to forward transactions sent via rpc service."),
         )
+        .arg(
+            Arg::with_name("rpc_send_transaction_default_max_retries")
+                .long("rpc-send-default-max-retries")
+                .value_name("NUMBER")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .help("The maximum number of transaction broadcast retries when unspecified by the request, otherwise retried until expiration."),
+        )
+        .arg(
+            Arg::with_name("rpc_send_transaction_service_max_retries")
+                .long("rpc-send-service-max-retries")
+                .value_name("NUMBER")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .default_value(&default_rpc_send_transaction_service_max_retries)
+                .help("The maximum number of transaction broadcast retries, regardless of requested value."),
+        )
+        .arg(
+            Arg::with_name("rpc_send_transaction_use_quic")
+                .long("rpc-send-use-quic")
+                .takes_value(false)
+                .help("Forward transactions sent via rpc service to the leaders' QUIC TPU port, \
+                       authenticated with the validator identity"),
+        )
         .arg(
             Arg::with_name("rpc_scan_and_fix_roots")
                 .long("rpc-scan-and-fix-roots")
@@ -1770,6 +1966,24 @@ pub fn main() {
                 .value_name("NUM")
                 .help("Specify hashes per batch in PoH service"),
         )
+        .arg(
+            Arg::with_name("poh_entry_target_transactions")
+                .hidden(true)
+                .long("poh-entry-target-transactions")
+                .takes_value(true)
+                .value_name("NUM")
+                .help("Accumulate executed transaction batches into PoH entries of this many \
+                       transactions"),
+        )
+        .arg(
+            Arg::with_name("poh_entry_max_delay_us")
+                .hidden(true)
+                .long("poh-entry-max-delay-us")
+                .takes_value(true)
+                .value_name("MICROSECONDS")
+                .requires("poh_entry_target_transactions")
+                .help("Longest a transaction batch waits for its PoH entry to fill up"),
+        )
         .arg(
             Arg::with_name("account_indexes")
                 .long("account-index")
@@ -1798,6 +2012,16 @@ pub fn main() {
                 .value_name("KEY")
                 .help("When account indexes are enabled, only include specific keys in the index. This overrides --account-index-exclude-key."),
         )
+        .arg(
+            Arg::with_name("spl_token_program_id")
+                .long("spl-token-program-id")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .multiple(true)
+                .value_name("PROGRAM_ID")
+                .help("Treat this program as SPL Token compatible in token RPC methods, \
+                       account decoding, spl-token account indexes and token balances"),
+        )
         .arg(
             Arg::with_name("no_accounts_db_caching")
                 .long("no-accounts-db-caching")
@@ -2198,6 +2422,9 @@ pub fn main() {
     let contact_debug_interval = value_t_or_exit!(matches, "contact_debug_interval", u64);
 
     let account_indexes = process_account_indexes(&matches);
+    let spl_token_ids =
+        SplTokenIds::new(values_t!(matches, "spl_token_program_id", Pubkey).unwrap_or_default());
+    set_spl_token_ids(spl_token_ids).expect("SPL Token program ids are set once, at startup");
 
     let restricted_repair_only_mode = matches.is_present("restricted_repair_only_mode");
     let accounts_shrink_optimize_total_space =
@@ -2256,7 +2483,8 @@ pub fn main() {
             enable_cpi_and_log_storage: matches.is_present("enable_cpi_and_log_storage"),
             enable_bigtable_ledger_storage: matches
                 .is_present("enable_rpc_bigtable_ledger_storage"),
-            enable_bigtable_ledger_upload: matches.is_present("enable_bigtable_ledger_upload"),
+            enable_ledger_storage_upload: matches.is_present("enable_ledger_storage_upload"),
+            local_ledger_storage_path: matches.value_of("local_ledger_storage").map(PathBuf::from),
             faucet_addr: matches.value_of("rpc_faucet_addr").map(|address| {
                 solana_net_utils::parse_host_port(address).expect("failed to parse faucet address")
             }),
@@ -2322,22 +2550,51 @@ pub fn main() {
         debug_keys,
         contact_debug_interval,
         bpf_jit: !matches.is_present("no_bpf_jit"),
-        send_transaction_retry_ms: value_t_or_exit!(matches, "rpc_send_transaction_retry_ms", u64),
-        send_transaction_leader_forward_count: value_t_or_exit!(
-            matches,
-            "rpc_send_transaction_leader_forward_count",
-            u64
-        ),
+        send_transaction_service_config: send_transaction_service::Config {
+            retry_rate_ms: value_t_or_exit!(matches, "rpc_send_transaction_retry_ms", u64),
+            leader_forward_count: value_t_or_exit!(
+                matches,
+                "rpc_send_transaction_leader_forward_count",
+                u64
+            ),
+            default_max_retries: value_t!(
+                matches,
+                "rpc_send_transaction_default_max_retries",
+                usize
+            )
+            .ok(),
+            service_max_retries: value_t_or_exit!(
+                matches,
+                "rpc_send_transaction_service_max_retries",
+                usize
+            ),
+        },
         no_poh_speed_test: matches.is_present("no_poh_speed_test"),
         poh_pinned_cpu_core: value_of(&matches, "poh_pinned_cpu_core")
             .unwrap_or(poh_service::DEFAULT_PINNED_CPU_CORE),
         poh_hashes_per_batch: value_of(&matches, "poh_hashes_per_batch")
             .unwrap_or(poh_service::DEFAULT_HASHES_PER_BATCH),
+        poh_entry_batch_config: poh_entry_batch_config(&matches),
         account_indexes,
         accounts_db_caching_enabled: !matches.is_present("no_accounts_db_caching"),
         accounts_db_test_hash_calculation: matches.is_present("accounts_db_test_hash_calculation"),
         accounts_db_use_index_hash_calculation: matches.is_present("accounts_db_index_hashing"),
         tpu_coalesce_ms,
+        gossip_receiver_config: value_t!(matches, "gossip_receiver_options", PacketReceiverConfig)
+            .unwrap_or_default(),
+        tvu_receiver_config: value_t!(matches, "tvu_receiver_options", PacketReceiverConfig)
+            .unwrap_or_default(),
+        repair_receiver_config: value_t!(matches, "repair_receiver_options", PacketReceiverConfig)
+            .unwrap_or_default(),
+        tpu_receiver_config: value_t!(matches, "tpu_receiver_options", PacketReceiverConfig)
+            .unwrap_or_default(),
+        tpu_use_quic: matches.is_present("tpu_use_quic"),
+        banking_stage_scheduler: matches.is_present("banking_stage_scheduler"),
+        forwarding_config: ForwardingConfig {
+            num_leaders: value_t_or_exit!(matches, "forward_num_leaders", usize),
+            min_fee_lamports: value_t_or_exit!(matches, "forward_min_fee_lamports", u64),
+            max_bytes_per_second: value_t_or_exit!(matches, "forward_max_bytes_per_second", usize),
+        },
         no_wait_for_vote_to_start_leader: matches.is_present("no_wait_for_vote_to_start_leader"),
         accounts_shrink_ratio,
         ..ValidatorConfig::default()
@@ -2493,6 +2750,34 @@ pub fn main() {
             exit(1);
         }
         validator_config.max_ledger_shreds = Some(limit_ledger_size);
+        let transaction_archive_filter = TransactionArchiveFilter {
+            program_ids: values_t!(matches, "archive_program_ids", Pubkey)
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+            accounts: values_t!(matches, "archive_accounts", Pubkey)
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        };
+        if !transaction_archive_filter.is_empty() {
+            validator_config.transaction_archive_filter = Some(transaction_archive_filter);
+        }
+        if matches.value_of("rocksdb_shred_compaction") == Some("fifo") {
+            // FIFO compaction drops shreds on its own, before the archive could copy them
+            if validator_config.transaction_archive_filter.is_some() {
+                eprintln!(
+                    "--archive-program-id and --archive-account are not supported with \
+                     --rocksdb-shred-compaction=fifo"
+                );
+                exit(1);
+            }
+            validator_config.shred_storage_type =
+                ShredStorageType::RocksFifo(BlockstoreRocksFifoOptions::new(limit_ledger_size));
+        }
+    } else if matches.value_of("rocksdb_shred_compaction") == Some("fifo") {
+        eprintln!("--rocksdb-shred-compaction=fifo requires --limit-ledger-size");
+        exit(1);
     }
 
     if matches.is_present("halt_on_trusted_validators_accounts_hash_mismatch") {
@@ -2613,6 +2898,19 @@ pub fn main() {
         bind_address,
     );
 
+    if validator_config.tpu_use_quic {
+        node.bind_tpu_quic(bind_address, dynamic_port_range)
+            .unwrap_or_else(|err| {
+                eprintln!("Unable to bind the TPU QUIC port: {}", err);
+                exit(1);
+            });
+    }
+
+    if matches.is_present("rpc_send_transaction_use_quic") {
+        connection_cache::set_use_quic(true);
+        connection_cache::set_identity(&identity_keypair);
+    }
+
     if restricted_repair_only_mode {
         let any = SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::new(0, 0, 0, 0)), 0);
         // When in --restricted_repair_only_mode is enabled only the gossip and repair ports
@@ -2702,6 +3000,17 @@ pub fn main() {
     info!("Validator exiting..");
 }
 
+fn poh_entry_batch_config(matches: &ArgMatches) -> EntryBatchConfig {
+    let default = EntryBatchConfig::default();
+    EntryBatchConfig {
+        target_transactions: value_t!(matches, "poh_entry_target_transactions", usize)
+            .unwrap_or(default.target_transactions),
+        max_delay: value_t!(matches, "poh_entry_max_delay_us", u64)
+            .map(Duration::from_micros)
+            .unwrap_or(default.max_delay),
+    }
+}
+
 fn process_account_indexes(matches: &ArgMatches) -> AccountSecondaryIndexes {
     let account_indexes: HashSet<AccountIndex> = matches
         .values_of("account_indexes")